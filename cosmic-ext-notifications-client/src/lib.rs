@@ -0,0 +1,460 @@
+//! Typed D-Bus access to the COSMIC notification daemon
+//!
+//! Three interfaces are served:
+//!
+//! - [`NotificationsProxy`]: the freedesktop.org notification spec, plus
+//!   the `ActivationToken` and `NotificationReplied` extensions
+//! - [`ControlProxy`]: do-not-disturb, pause, snooze and history queries
+//! - [`AppletProxy`]: the panel applet's history and focus session API
+//!
+//! ```no_run
+//! # async fn example() -> zbus::Result<()> {
+//! use cosmic_ext_notifications_client::{ControlProxy, HistoryFilter};
+//!
+//! let conn = zbus::Connection::session().await?;
+//! let control = ControlProxy::new(&conn).await?;
+//! let filter = HistoryFilter::default().app("org.mozilla.firefox");
+//! for entry in control.list_history(filter.to_dict(), 5).await? {
+//!     println!("{:?}", cosmic_ext_notifications_client::HistoryEntry::try_from(entry));
+//! }
+//! # Ok(())
+//! # }
+//! ```
+
+use std::{
+    collections::HashMap,
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+use zbus::{
+    proxy,
+    zvariant::{OwnedValue, Value},
+};
+
+/// Well-known name of the control interface
+pub const CONTROL_BUS_NAME: &str = "com.system76.CosmicNotifications";
+pub const CONTROL_PATH: &str = "/com/system76/CosmicNotifications";
+pub const NOTIFICATIONS_PATH: &str = "/org/freedesktop/Notifications";
+pub const APPLET_PATH: &str = "/com/system76/NotificationsApplet";
+
+/// The freedesktop.org notification interface
+#[proxy(
+    interface = "org.freedesktop.Notifications",
+    default_service = "org.freedesktop.Notifications",
+    default_path = "/org/freedesktop/Notifications"
+)]
+pub trait Notifications {
+    #[allow(clippy::too_many_arguments)]
+    fn notify(
+        &self,
+        app_name: &str,
+        replaces_id: u32,
+        app_icon: &str,
+        summary: &str,
+        body: &str,
+        actions: &[&str],
+        hints: HashMap<&str, Value<'_>>,
+        expire_timeout: i32,
+    ) -> zbus::Result<u32>;
+
+    fn close_notification(&self, id: u32) -> zbus::Result<()>;
+
+    fn get_capabilities(&self) -> zbus::Result<Vec<String>>;
+
+    /// Name, vendor, version and spec version
+    fn get_server_information(&self) -> zbus::Result<(String, String, String, String)>;
+
+    #[zbus(signal)]
+    fn action_invoked(&self, id: u32, action_key: &str) -> zbus::Result<()>;
+
+    #[zbus(signal)]
+    fn activation_token(&self, id: u32, activation_token: &str) -> zbus::Result<()>;
+
+    #[zbus(signal)]
+    fn notification_replied(&self, id: u32, text: &str) -> zbus::Result<()>;
+
+    /// `reason` converts to a [`ClosedReason`]
+    #[zbus(signal)]
+    fn notification_closed(&self, id: u32, reason: u32) -> zbus::Result<()>;
+}
+
+/// Daemon state, history and popup control
+///
+/// The daemon also answers on `org.freedesktop.Notifications`, so build the
+/// proxy with that destination if another program owns the control name.
+#[proxy(
+    interface = "com.system76.CosmicNotifications",
+    default_service = "com.system76.CosmicNotifications",
+    default_path = "/com/system76/CosmicNotifications"
+)]
+pub trait Control {
+    #[zbus(property)]
+    fn do_not_disturb(&self) -> zbus::Result<bool>;
+
+    /// Whether critical notifications break through priority-only DND
+    #[zbus(property)]
+    fn critical_bypasses_dnd(&self) -> zbus::Result<bool>;
+
+    #[zbus(property)]
+    fn set_critical_bypasses_dnd(&self, enabled: bool) -> zbus::Result<()>;
+
+    /// Number of notification popups on screen
+    #[zbus(property)]
+    fn visible_count(&self) -> zbus::Result<u32>;
+
+    #[zbus(property)]
+    fn paused(&self) -> zbus::Result<bool>;
+
+    /// Notifications waiting for `resume`
+    #[zbus(property)]
+    fn held_count(&self) -> zbus::Result<u32>;
+
+    /// Past notifications, newest first; see [`HistoryFilter`] and [`HistoryEntry`]
+    fn list_history(
+        &self,
+        filter: HashMap<&str, Value<'_>>,
+        limit: u32,
+    ) -> zbus::Result<Vec<HashMap<String, OwnedValue>>>;
+
+    /// History entries matching every word of `query` (word prefixes,
+    /// ignoring case), newest first; parse them with [`HistoryEntry`]
+    fn search_history(
+        &self,
+        query: &str,
+        limit: u32,
+    ) -> zbus::Result<Vec<HashMap<String, OwnedValue>>>;
+
+    /// Counts per app ([`AppStats`]) and per local hour over the last
+    /// `days` days, 0 for everything kept
+    #[zbus(out_args("apps", "hours"))]
+    fn history_stats(
+        &self,
+        days: u32,
+    ) -> zbus::Result<(Vec<HashMap<String, OwnedValue>>, Vec<u32>)>;
+
+    fn get_notification(&self, id: u32) -> zbus::Result<HashMap<String, OwnedValue>>;
+
+    fn clear_history(&self) -> zbus::Result<()>;
+
+    /// Run an action of a popup; an empty key runs the default action
+    fn invoke_action(&self, id: u32, action_key: &str) -> zbus::Result<()>;
+
+    fn snooze(&self, id: u32, seconds: u32) -> zbus::Result<()>;
+
+    /// Open the notification center, or close it if it is open
+    fn toggle_center(&self) -> zbus::Result<()>;
+
+    /// Give the popups keyboard focus, starting on the newest
+    fn focus_popups(&self) -> zbus::Result<()>;
+
+    fn close_all(&self) -> zbus::Result<()>;
+
+    /// Dismiss the popups of an app, by app name or desktop entry
+    fn close_app(&self, app_name: &str) -> zbus::Result<()>;
+
+    fn pause(&self) -> zbus::Result<()>;
+
+    fn resume(&self) -> zbus::Result<()>;
+
+    fn toggle_dnd(&self) -> zbus::Result<()>;
+
+    /// A non-zero `duration_secs` turns do-not-disturb back off after that long
+    fn set_dnd(&self, enabled: bool, duration_secs: u32) -> zbus::Result<()>;
+
+    /// Like [`set_dnd`](Self::set_dnd), with `mode` "off", "priority" or "on"
+    fn set_dnd_mode(&self, mode: &str, duration_secs: u32) -> zbus::Result<()>;
+
+    /// Every accepted notification, in the [`HistoryEntry`] format
+    #[zbus(signal)]
+    fn notification_posted(
+        &self,
+        notification: HashMap<&str, Value<'_>>,
+        replaced: bool,
+    ) -> zbus::Result<()>;
+}
+
+/// The interface the panel applet uses
+#[proxy(
+    interface = "com.system76.NotificationsApplet",
+    default_service = "org.freedesktop.Notifications",
+    default_path = "/com/system76/NotificationsApplet"
+)]
+pub trait Applet {
+    fn invoke_action(&self, id: u32, action: &str) -> zbus::Result<()>;
+
+    /// Id, app name, app icon, summary, body and Unix time of past notifications
+    fn get_history(&self) -> zbus::Result<Vec<(u32, String, String, String, String, i64)>>;
+
+    /// Past notifications as JSON
+    fn get_history_full(&self) -> zbus::Result<Vec<String>>;
+
+    /// Past notifications as versioned `NotificationRecord` JSON
+    fn get_history_records(&self) -> zbus::Result<Vec<String>>;
+
+    fn start_focus_session(&self, duration_secs: u32, allowed_apps: &[&str]) -> zbus::Result<()>;
+
+    fn stop_focus_session(&self) -> zbus::Result<()>;
+
+    fn focus_session_remaining(&self) -> zbus::Result<u32>;
+
+    /// Daemon state as JSON, for bug reports
+    fn debug_dump(&self) -> zbus::Result<String>;
+
+    /// Key, label, collapsed and notification ids of each history date section
+    fn get_history_sections(&self) -> zbus::Result<Vec<(String, String, bool, Vec<u32>)>>;
+
+    fn set_history_section_collapsed(&self, key: &str, collapsed: bool) -> zbus::Result<()>;
+
+    /// Master volume in percent, 0-100
+    fn set_sound_volume(&self, volume: u8) -> zbus::Result<()>;
+
+    #[allow(clippy::too_many_arguments)]
+    #[zbus(signal)]
+    fn notify(
+        &self,
+        app_name: &str,
+        replaces_id: u32,
+        app_icon: &str,
+        summary: &str,
+        body: &str,
+        actions: Vec<&str>,
+        hints: HashMap<&str, Value<'_>>,
+        expire_timeout: i32,
+    ) -> zbus::Result<()>;
+
+    /// 0 when no session is active
+    #[zbus(signal)]
+    fn focus_session_changed(&self, remaining_secs: u32) -> zbus::Result<()>;
+}
+
+/// Why a notification closed, from `NotificationClosed`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ClosedReason {
+    Expired,
+    Dismissed,
+    CloseNotification,
+    Undefined,
+}
+
+impl From<u32> for ClosedReason {
+    fn from(reason: u32) -> Self {
+        match reason {
+            1 => ClosedReason::Expired,
+            2 => ClosedReason::Dismissed,
+            3 => ClosedReason::CloseNotification,
+            _ => ClosedReason::Undefined,
+        }
+    }
+}
+
+/// Which entries `list_history` returns; unset fields match everything
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct HistoryFilter {
+    app: Option<String>,
+    text: Option<String>,
+    since: Option<i64>,
+    urgency: Option<u8>,
+}
+
+impl HistoryFilter {
+    /// App name or desktop entry, ignoring case
+    pub fn app(mut self, app: impl Into<String>) -> Self {
+        self.app = Some(app.into());
+        self
+    }
+
+    /// Text in the summary or body, ignoring case
+    pub fn text(mut self, text: impl Into<String>) -> Self {
+        self.text = Some(text.into());
+        self
+    }
+
+    /// Received at or after this time
+    pub fn since(mut self, time: SystemTime) -> Self {
+        let secs = time.duration_since(UNIX_EPOCH).map_or(0, |d| d.as_secs());
+        self.since = Some(i64::try_from(secs).unwrap_or(i64::MAX));
+        self
+    }
+
+    /// At least this urgent (0 low, 1 normal, 2 critical)
+    pub fn min_urgency(mut self, urgency: u8) -> Self {
+        self.urgency = Some(urgency);
+        self
+    }
+
+    /// The `a{sv}` argument of `list_history`
+    pub fn to_dict(&self) -> HashMap<&'static str, Value<'_>> {
+        let mut dict = HashMap::new();
+        if let Some(app) = &self.app {
+            dict.insert("app", Value::from(app.as_str()));
+        }
+        if let Some(text) = &self.text {
+            dict.insert("text", Value::from(text.as_str()));
+        }
+        if let Some(since) = self.since {
+            dict.insert("since", Value::from(since));
+        }
+        if let Some(urgency) = self.urgency {
+            dict.insert("urgency", Value::from(urgency));
+        }
+        dict
+    }
+}
+
+/// A notification as returned by `list_history` and `get_notification`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct HistoryEntry {
+    pub id: u32,
+    pub app_name: String,
+    pub app_icon: String,
+    pub desktop_entry: Option<String>,
+    pub category: Option<String>,
+    pub summary: String,
+    /// Sanitized markup
+    pub body: String,
+    pub urgency: u8,
+    pub time: SystemTime,
+    /// Action key and label
+    pub actions: Vec<(String, String)>,
+}
+
+impl TryFrom<HashMap<String, OwnedValue>> for HistoryEntry {
+    type Error = zbus::zvariant::Error;
+
+    fn try_from(mut dict: HashMap<String, OwnedValue>) -> Result<Self, Self::Error> {
+        let mut take = |key: &str| {
+            dict.remove(key)
+                .ok_or_else(|| zbus::zvariant::Error::Message(format!("Missing '{key}'")))
+        };
+        let id = u32::try_from(take("id")?)?;
+        let app_name = String::try_from(take("app-name")?)?;
+        let app_icon = String::try_from(take("app-icon")?)?;
+        let summary = String::try_from(take("summary")?)?;
+        let body = String::try_from(take("body")?)?;
+        let urgency = u8::try_from(take("urgency")?)?;
+        let secs = i64::try_from(take("time")?)?;
+        let actions = Vec::<(String, String)>::try_from(take("actions")?)?;
+        let desktop_entry = take("desktop-entry").ok().map(String::try_from).transpose()?;
+        let category = take("category").ok().map(String::try_from).transpose()?;
+
+        Ok(Self {
+            id,
+            app_name,
+            app_icon,
+            desktop_entry,
+            category,
+            summary,
+            body,
+            urgency,
+            time: UNIX_EPOCH + Duration::from_secs(u64::try_from(secs).unwrap_or(0)),
+            actions,
+        })
+    }
+}
+
+/// One app's line of `history_stats`
+#[derive(Debug, Clone, PartialEq)]
+pub struct AppStats {
+    pub app: String,
+    pub received: u32,
+    pub dismissed: u32,
+    pub actioned: u32,
+    pub expired: u32,
+    /// Share of received notifications the user dismissed
+    pub dismiss_rate: f64,
+    /// Share of received notifications the user acted on
+    pub action_rate: f64,
+}
+
+impl TryFrom<HashMap<String, OwnedValue>> for AppStats {
+    type Error = zbus::zvariant::Error;
+
+    fn try_from(mut dict: HashMap<String, OwnedValue>) -> Result<Self, Self::Error> {
+        let mut take = |key: &str| {
+            dict.remove(key)
+                .ok_or_else(|| zbus::zvariant::Error::Message(format!("Missing '{key}'")))
+        };
+        Ok(Self {
+            app: String::try_from(take("app")?)?,
+            received: u32::try_from(take("received")?)?,
+            dismissed: u32::try_from(take("dismissed")?)?,
+            actioned: u32::try_from(take("actioned")?)?,
+            expired: u32::try_from(take("expired")?)?,
+            dismiss_rate: f64::try_from(take("dismiss-rate")?)?,
+            action_rate: f64::try_from(take("action-rate")?)?,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_history_filter_dict() {
+        assert!(HistoryFilter::default().to_dict().is_empty());
+
+        let filter = HistoryFilter::default()
+            .app("Mail")
+            .min_urgency(2)
+            .since(UNIX_EPOCH + Duration::from_secs(1_500));
+        let dict = filter.to_dict();
+        assert_eq!(dict.get("app"), Some(&Value::from("Mail")));
+        assert_eq!(dict.get("urgency"), Some(&Value::from(2u8)));
+        assert_eq!(dict.get("since"), Some(&Value::from(1_500i64)));
+        assert!(!dict.contains_key("text"));
+    }
+
+    #[test]
+    fn test_history_entry_from_dict() {
+        let value = |v: Value<'static>| OwnedValue::try_from(v).unwrap();
+        let mut dict = HashMap::from([
+            ("id".to_string(), value(Value::from(7u32))),
+            ("app-name".to_string(), value(Value::from("Mail"))),
+            ("app-icon".to_string(), value(Value::from(""))),
+            ("summary".to_string(), value(Value::from("Invoice due"))),
+            ("body".to_string(), value(Value::from("<b>Pay</b>"))),
+            ("urgency".to_string(), value(Value::from(1u8))),
+            ("time".to_string(), value(Value::from(1_000i64))),
+            (
+                "actions".to_string(),
+                value(Value::from(vec![("default".to_string(), "Open".to_string())])),
+            ),
+            ("category".to_string(), value(Value::from("email"))),
+        ]);
+
+        let entry = HistoryEntry::try_from(dict.clone()).unwrap();
+        assert_eq!(entry.id, 7);
+        assert_eq!(entry.category.as_deref(), Some("email"));
+        assert_eq!(entry.desktop_entry, None);
+        assert_eq!(entry.time, UNIX_EPOCH + Duration::from_secs(1_000));
+        assert_eq!(entry.actions, vec![("default".to_string(), "Open".to_string())]);
+
+        dict.remove("summary");
+        assert!(HistoryEntry::try_from(dict).is_err());
+    }
+
+    #[test]
+    fn test_app_stats_from_dict() {
+        let value = |v: Value<'static>| OwnedValue::try_from(v).unwrap();
+        let dict = HashMap::from([
+            ("app".to_string(), value(Value::from("Firefox"))),
+            ("received".to_string(), value(Value::from(214u32))),
+            ("dismissed".to_string(), value(Value::from(107u32))),
+            ("actioned".to_string(), value(Value::from(0u32))),
+            ("expired".to_string(), value(Value::from(107u32))),
+            ("dismiss-rate".to_string(), value(Value::from(0.5))),
+            ("action-rate".to_string(), value(Value::from(0.0))),
+        ]);
+
+        let stats = AppStats::try_from(dict).unwrap();
+        assert_eq!(stats.app, "Firefox");
+        assert_eq!(stats.received, 214);
+        assert_eq!(stats.dismiss_rate, 0.5);
+    }
+
+    #[test]
+    fn test_closed_reason() {
+        assert_eq!(ClosedReason::from(2), ClosedReason::Dismissed);
+        assert_eq!(ClosedReason::from(99), ClosedReason::Undefined);
+    }
+}
@@ -1,4 +1,6 @@
 use cosmic_config::{CosmicConfigEntry, cosmic_config_derive::CosmicConfigEntry};
+use std::collections::BTreeMap;
+use std::time::Duration;
 
 pub const ID: &str = "io.github.olafkfreund.CosmicExtNotifications";
 
@@ -24,6 +26,349 @@ pub enum GroupingMode {
     ByApp,
     /// Group notifications by category hint
     ByCategory,
+    /// Group chat and email notifications by conversation (app and
+    /// sender), everything else by app
+    ByConversation,
+}
+
+/// How much room each popup takes
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash, serde::Serialize, serde::Deserialize)]
+pub enum Density {
+    /// Full cards with images and the whole body
+    #[default]
+    Comfortable,
+    /// Tight cards without images, the body on a single line
+    Compact,
+}
+
+/// How non-square notification images fill the square image slot
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash, serde::Serialize, serde::Deserialize)]
+pub enum ImageFit {
+    /// Keep the whole image at its own aspect ratio
+    #[default]
+    Contain,
+    /// Crop the center square out of the image
+    Crop,
+    /// Pad the image to a square with transparent bars
+    Letterbox,
+}
+
+/// Effective do-not-disturb mode
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash, serde::Serialize, serde::Deserialize)]
+pub enum DndMode {
+    /// All notifications show popups
+    #[default]
+    Open,
+    /// Only notifications on the priority list, and critical ones unless
+    /// `critical_bypass_dnd` is off, show popups
+    PriorityOnly,
+    /// No popups are shown
+    Full,
+}
+
+/// What happens to a popup's timeout when its notification is replaced
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash, serde::Serialize, serde::Deserialize)]
+pub enum ReplaceTimeout {
+    /// Start the timeout over, so an app reporting progress stays on screen
+    #[default]
+    Reset,
+    /// Keep counting down from when the popup first appeared
+    Preserve,
+}
+
+/// What a popup's timeout does when the pointer leaves it
+///
+/// The timeout is paused while the pointer is over the popup.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash, serde::Serialize, serde::Deserialize)]
+pub enum HoverTimeout {
+    /// Carry on with the time that was left
+    #[default]
+    Resume,
+    /// Start the whole timeout over
+    Restart,
+}
+
+/// How much of a notification screen readers announce
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash, serde::Serialize, serde::Deserialize)]
+pub enum AnnounceVerbosity {
+    /// Announce nothing; the popups can still be read on demand
+    Off,
+    /// App name and summary, leaving out the body
+    Summary,
+    /// App name, summary and body
+    #[default]
+    Full,
+}
+
+/// Which monitor popups appear on
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash, serde::Serialize, serde::Deserialize)]
+pub enum PopupPlacement {
+    /// Wherever the compositor puts new surfaces, usually the monitor in
+    /// use when the first popup appears
+    #[default]
+    Active,
+    /// The monitor of the focused window; popups move along when focus
+    /// goes to another monitor
+    FollowFocus,
+    /// Always the monitor named in `popup_output`
+    Fixed,
+}
+
+/// How much of a notification shows while the screen is locked
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash, serde::Serialize, serde::Deserialize)]
+pub enum LockScreenVisibility {
+    /// Show notifications in full
+    #[default]
+    ShowAll,
+    /// Show only which app a notification is from
+    HideContent,
+    /// Show no popups; notifications go to history
+    HideAll,
+}
+
+/// How popups appear and disappear
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash, serde::Serialize, serde::Deserialize)]
+pub enum AnimationStyle {
+    /// Slide in from the screen edge the popups are anchored to, and back out
+    #[default]
+    SlideFromEdge,
+    /// Fade in and out
+    Fade,
+    /// Grow from and shrink to the middle
+    Scale,
+    /// Appear and disappear at once
+    None,
+}
+
+/// What happens to popups while the focused window is fullscreen
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash, serde::Serialize, serde::Deserialize)]
+pub enum FullscreenPopups {
+    /// Send non-critical notifications straight to history
+    Suppress,
+    /// Hold non-critical notifications back until fullscreen ends
+    #[default]
+    Queue,
+    /// Show popups as usual
+    ShowAnyway,
+}
+
+/// What is wiped from history when the session locks or ends
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash, serde::Serialize, serde::Deserialize)]
+pub enum HistoryWipe {
+    /// Keep history
+    #[default]
+    Never,
+    /// Remove notifications from apps whose rule marks them `sensitive`
+    Sensitive,
+    /// Remove everything
+    All,
+}
+
+/// A conversation muted until a point in time
+#[derive(Debug, Clone, PartialEq, Eq, Hash, serde::Serialize, serde::Deserialize)]
+pub struct MutedConversation {
+    /// Conversation identity, see `Notification::conversation_key`
+    pub key: String,
+    /// Unix time in seconds when the mute expires
+    pub until: u64,
+}
+
+/// A find-and-replace applied to notification summaries and bodies
+#[derive(Debug, Clone, PartialEq, Eq, Hash, serde::Serialize, serde::Deserialize)]
+pub struct RewriteRule {
+    /// Regular expression to look for
+    pub pattern: String,
+    /// What each match becomes; `$1` or `${name}` insert capture groups
+    #[serde(default)]
+    pub replacement: String,
+    /// Only rewrite notifications from this app name or desktop entry
+    #[serde(default)]
+    pub app_name: Option<String>,
+}
+
+/// Notification event a hook runs on
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, serde::Serialize, serde::Deserialize)]
+pub enum HookEvent {
+    /// A notification arrived
+    Received,
+    /// The user dismissed a notification
+    Dismissed,
+    /// The user clicked a notification or one of its actions
+    ActionInvoked,
+}
+
+/// A command run when something happens to a notification, like dunst's
+/// `script`
+///
+/// The command runs without a shell; the notification is described in
+/// environment variables, never in arguments.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, serde::Serialize, serde::Deserialize)]
+pub struct NotificationHook {
+    pub event: HookEvent,
+    /// Program and its arguments
+    pub command: Vec<String>,
+    /// Only run for notifications from this app name or desktop entry
+    #[serde(default)]
+    pub app_name: Option<String>,
+}
+
+/// A WebAssembly plugin that may rewrite, reroute or drop notifications
+/// before they are shown
+///
+/// Only used when the daemon is built with the `plugins` feature.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, serde::Serialize, serde::Deserialize)]
+pub struct PluginConfig {
+    /// Path of the `.wasm` or `.wat` file
+    pub path: String,
+    #[serde(default = "default_true")]
+    pub enabled: bool,
+    /// Only run for notifications from this app name or desktop entry
+    #[serde(default)]
+    pub app_name: Option<String>,
+    /// Handed to the plugin with every notification
+    #[serde(default)]
+    pub settings: BTreeMap<String, String>,
+}
+
+/// Daemon that talks to the paired phone
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, serde::Serialize, serde::Deserialize)]
+pub enum PhoneBridge {
+    /// KDE Connect (`kdeconnectd`)
+    KdeConnect,
+    /// Valent, the GNOME implementation of the KDE Connect protocol
+    Valent,
+}
+
+/// Where notifications of apps with `forward_to_phone` are sent
+#[derive(Debug, Clone, PartialEq, Eq, Hash, serde::Serialize, serde::Deserialize)]
+pub struct PhoneForwarding {
+    pub bridge: PhoneBridge,
+    /// Id of the paired phone, as `kdeconnect-cli --list-devices` or
+    /// `valent --list-devices` show it
+    pub device_id: String,
+    /// Also take a forwarded notification off the phone when it's dismissed
+    /// on the desktop; only Valent supports this
+    #[serde(default)]
+    pub mirror_dismissals: bool,
+}
+
+/// An HTTPS endpoint that matching notifications are POSTed to as JSON,
+/// for home automation or paging
+#[derive(Debug, Clone, PartialEq, Eq, Hash, serde::Serialize, serde::Deserialize)]
+pub struct WebhookConfig {
+    /// `https://` URL of the endpoint
+    pub url: String,
+    /// Extra request headers, such as `Authorization`
+    #[serde(default)]
+    pub headers: BTreeMap<String, String>,
+    /// Only send notifications from these app names or desktop entries;
+    /// all apps when empty
+    #[serde(default)]
+    pub app_names: Vec<String>,
+    /// Lowest urgency sent, 0 (low) to 2 (critical)
+    #[serde(default)]
+    pub min_urgency: u8,
+}
+
+/// How the email digest is sent
+#[derive(Debug, Clone, PartialEq, Eq, Hash, serde::Serialize, serde::Deserialize)]
+pub enum EmailTransport {
+    /// Hand the message to the local `sendmail` command
+    Sendmail {
+        /// Command to run instead of `sendmail` from `PATH`
+        #[serde(default)]
+        command: Option<String>,
+    },
+    /// Send through an SMTP server, with STARTTLS, or TLS from the start
+    /// on port 465
+    Smtp {
+        host: String,
+        #[serde(default = "default_smtp_port")]
+        port: u16,
+        #[serde(default)]
+        username: Option<String>,
+        /// File holding the password, so it stays out of the config
+        #[serde(default)]
+        password_file: Option<String>,
+    },
+}
+
+/// Emails listing what arrived while the user was away or in do-not-disturb
+#[derive(Debug, Clone, PartialEq, Eq, Hash, serde::Serialize, serde::Deserialize)]
+pub struct EmailDigestConfig {
+    /// Address the digest goes to
+    pub to: String,
+    /// Sender address; `to` when unset
+    #[serde(default)]
+    pub from: Option<String>,
+    pub transport: EmailTransport,
+    /// Minutes away before the first email, and between emails after that
+    #[serde(default = "default_email_digest_minutes")]
+    pub after_minutes: u32,
+    /// Include notification bodies, not only app names and summaries
+    #[serde(default)]
+    pub include_body: bool,
+}
+
+/// How long a popup's snooze button puts it away
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, serde::Serialize, serde::Deserialize)]
+pub enum SnoozePreset {
+    /// For a number of minutes
+    Minutes(u32),
+    /// Until tomorrow morning
+    Tomorrow,
+}
+
+/// User-curated list of notifications that break through priority-only DND
+#[derive(Debug, Clone, Default, PartialEq, Eq, Hash, serde::Serialize, serde::Deserialize)]
+pub struct PriorityList {
+    /// App names or desktop entries (case-insensitive)
+    #[serde(default)]
+    pub apps: Vec<String>,
+    /// Categories, either exact ("im.received") or a class prefix ("im")
+    #[serde(default)]
+    pub categories: Vec<String>,
+    /// Keywords matched case-insensitively in the summary or body
+    #[serde(default)]
+    pub keywords: Vec<String>,
+}
+
+impl PriorityList {
+    /// Check if a notification is on the priority list
+    pub fn matches(
+        &self,
+        app_name: &str,
+        desktop_entry: Option<&str>,
+        category: Option<&str>,
+        summary: &str,
+        body: &str,
+    ) -> bool {
+        let app_match = self.apps.iter().any(|app| {
+            app.eq_ignore_ascii_case(app_name)
+                || desktop_entry.is_some_and(|entry| app.eq_ignore_ascii_case(entry))
+        });
+
+        let category_match = category.is_some_and(|category| {
+            self.categories.iter().any(|c| {
+                category == c
+                    || category
+                        .strip_prefix(c.as_str())
+                        .is_some_and(|rest| rest.starts_with('.'))
+            })
+        });
+
+        let keyword_match = || {
+            let summary = summary.to_lowercase();
+            let body = body.to_lowercase();
+            self.keywords
+                .iter()
+                .filter(|k| !k.trim().is_empty())
+                .map(|k| k.to_lowercase())
+                .any(|k| summary.contains(&k) || body.contains(&k))
+        };
+
+        app_match || category_match || (!self.keywords.is_empty() && keyword_match())
+    }
 }
 
 #[derive(Debug, Clone, PartialEq, Eq, Hash, serde::Serialize, serde::Deserialize)]
@@ -42,6 +387,107 @@ pub struct AppRule {
     pub sound_enabled: bool,
     /// Override timeout in milliseconds
     pub timeout_override: Option<u32>,
+    /// Show the notification image as a sender avatar with a small app
+    /// badge (None = decide from the notification category)
+    #[serde(default)]
+    pub show_avatar: Option<bool>,
+    /// Read the summary aloud through speech-dispatcher
+    #[serde(default)]
+    pub speak: bool,
+    /// Override the window in milliseconds in which repeated identical
+    /// sounds play only once (0 = never debounce)
+    #[serde(default)]
+    pub sound_debounce_ms: Option<u32>,
+    /// Sound volume in percent (0-100) for this app, replacing the
+    /// per-urgency level; still scaled by the master volume
+    #[serde(default)]
+    pub volume_override: Option<u8>,
+    /// Keep this app's notifications in history once their popup is gone;
+    /// off for password managers, banking and the like
+    #[serde(default = "default_true")]
+    pub store_in_history: bool,
+    /// Treat this app's notifications as private, so they are wiped from
+    /// history on lock when `wipe_history` is `Sensitive`
+    #[serde(default)]
+    pub sensitive: bool,
+    /// Override how many notifications a minute this app may keep sending
+    /// (0 = unlimited)
+    #[serde(default)]
+    pub rate_limit_per_minute: Option<u32>,
+    /// Override how many notifications this app may send in a quick burst
+    #[serde(default)]
+    pub rate_limit_burst: Option<u32>,
+    /// How far this app's notifications move up (positive) or down
+    /// (negative) with `smart_ordering`, from -100 to 100
+    #[serde(default)]
+    pub priority: Option<i32>,
+    /// Override whether one-time codes and reset links from this app are
+    /// masked until revealed
+    #[serde(default)]
+    pub mask_secrets: Option<bool>,
+    /// Hold this app's notifications and deliver them as one digest card
+    /// every so many minutes, for feeds such as RSS or CI status
+    #[serde(default)]
+    pub digest_minutes: Option<u32>,
+    /// Run `hooks` for this app's notifications; off for password managers
+    /// and other apps whose text shouldn't reach scripts
+    #[serde(default = "default_true")]
+    pub run_hooks: bool,
+    /// Forward this app's notifications to the phone set up in
+    /// `phone_forwarding`
+    #[serde(default)]
+    pub forward_to_phone: bool,
+}
+
+/// How much each signal counts when `smart_ordering` ranks notifications
+///
+/// Weights are relative to each other; 0 leaves a signal out.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, serde::Serialize, serde::Deserialize)]
+pub struct PriorityWeights {
+    /// Urgency the app gave the notification
+    #[serde(default)]
+    pub urgency: u32,
+    /// `priority` of the app's rule
+    #[serde(default)]
+    pub app: u32,
+    /// How new the notification is
+    #[serde(default)]
+    pub recency: u32,
+    /// How often the user acts on the app's notifications
+    #[serde(default)]
+    pub interaction: u32,
+}
+
+impl Default for PriorityWeights {
+    fn default() -> Self {
+        Self {
+            urgency: 100,
+            app: 50,
+            recency: 30,
+            interaction: 20,
+        }
+    }
+}
+
+/// How fast one app may send notifications
+///
+/// Each app has a bucket of `burst` notifications, refilled at
+/// `per_minute`; notifications arriving to an empty bucket are dropped.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RateLimit {
+    /// Sustained rate (0 = unlimited)
+    pub per_minute: u32,
+    /// Notifications allowed at once before the rate applies
+    pub burst: u32,
+}
+
+impl Default for RateLimit {
+    fn default() -> Self {
+        Self {
+            per_minute: default_rate_limit_per_minute(),
+            burst: default_rate_limit_burst(),
+        }
+    }
 }
 
 impl Default for AppRule {
@@ -53,6 +499,19 @@ impl Default for AppRule {
             urgency_override: None,
             sound_enabled: true,
             timeout_override: None,
+            show_avatar: None,
+            speak: false,
+            sound_debounce_ms: None,
+            volume_override: None,
+            store_in_history: true,
+            sensitive: false,
+            rate_limit_per_minute: None,
+            rate_limit_burst: None,
+            priority: None,
+            mask_secrets: None,
+            digest_minutes: None,
+            run_hooks: true,
+            forward_to_phone: false,
         }
     }
 }
@@ -72,6 +531,49 @@ pub struct NotificationsConfig {
     pub max_timeout_normal: Option<u32>,
     /// Max time in milliseconds a low priority notification can be displayed before being removed.
     pub max_timeout_low: Option<u32>,
+    /// Timeout in milliseconds for critical notifications that leave it to
+    /// the server (expire_timeout = -1), 0 = never expire (default: 0)
+    #[serde(default)]
+    pub default_timeout_urgent: u32,
+    /// Timeout in milliseconds for normal notifications that leave it to
+    /// the server (default: 5000)
+    #[serde(default = "default_timeout_normal")]
+    pub default_timeout_normal: u32,
+    /// Timeout in milliseconds for low urgency notifications that leave it
+    /// to the server (default: 3000)
+    #[serde(default = "default_timeout_low")]
+    pub default_timeout_low: u32,
+    /// Whether replacing a visible notification restarts its timeout
+    /// (default: Reset)
+    #[serde(default)]
+    pub replace_timeout: ReplaceTimeout,
+    /// Whether a popup's timeout, paused while hovered, resumes or starts
+    /// over when the pointer leaves (default: Resume)
+    #[serde(default)]
+    pub hover_timeout: HoverTimeout,
+    /// How much screen readers announce of new and updated popups
+    /// (default: Full)
+    #[serde(default)]
+    pub announce_verbosity: AnnounceVerbosity,
+    /// How much of a notification popups and screen readers give away
+    /// while the session is locked (default: ShowAll)
+    #[serde(default)]
+    pub lock_screen_visibility: LockScreenVisibility,
+    /// What happens to non-critical popups over fullscreen windows such as
+    /// games and videos (default: Queue)
+    #[serde(default)]
+    pub fullscreen_popups: FullscreenPopups,
+    /// Dismiss an app's popups once one of its windows gets focus, since
+    /// the user is looking at the app anyway (default: false)
+    #[serde(default)]
+    pub dismiss_on_focus: bool,
+    /// Which monitor popups appear on (default: Active)
+    #[serde(default)]
+    pub popup_placement: PopupPlacement,
+    /// Output name such as "DP-1" used with `PopupPlacement::Fixed`; popups
+    /// fall back to the active monitor while it's unplugged (default: None)
+    #[serde(default)]
+    pub popup_output: Option<String>,
 
     // Rich notification configuration options
     /// Whether to display images in notifications (default: true)
@@ -89,6 +591,16 @@ pub struct NotificationsConfig {
     /// Whether animated images (GIFs) play and card animations are enabled (default: true)
     #[serde(default = "default_true")]
     pub enable_animations: bool,
+    /// How much room each popup takes; Compact fits more on small screens
+    /// (default: Comfortable)
+    #[serde(default)]
+    pub density: Density,
+    /// How popups enter and leave, when `enable_animations` is on (default: SlideFromEdge)
+    #[serde(default)]
+    pub animation_style: AnimationStyle,
+    /// How long popups take to enter or leave in milliseconds (default: 200)
+    #[serde(default = "default_animation_duration_ms")]
+    pub animation_duration_ms: u32,
 
     /// Per-application notification rules
     #[serde(default)]
@@ -105,6 +617,188 @@ pub struct NotificationsConfig {
     /// Whether to show group count badge (e.g., "Firefox (3)")
     #[serde(default = "default_true")]
     pub show_group_count: bool,
+    /// A new chat message takes the place of the popup from the same
+    /// conversation, which moves to history (default: false)
+    #[serde(default)]
+    pub thread_conversations: bool,
+
+    /// Order popups and the notification center by a priority score
+    /// instead of urgency and time (default: false)
+    #[serde(default)]
+    pub smart_ordering: bool,
+    /// How the priority score weighs urgency, app rules, recency and the
+    /// user's interaction with each app
+    #[serde(default)]
+    pub priority_weights: PriorityWeights,
+
+    /// Whether to detect phone numbers and email addresses in bodies and
+    /// offer call/copy/compose quick actions (default: false)
+    #[serde(default)]
+    pub detect_contacts: bool,
+
+    /// Master notification sound volume in percent (0-100), applied on top
+    /// of the per-urgency levels (default: 100)
+    #[serde(default = "default_sound_volume")]
+    pub sound_volume: u8,
+    /// Sound volume in percent (0-100) for low urgency notifications
+    #[serde(default = "default_sound_volume")]
+    pub sound_volume_low: u8,
+    /// Sound volume in percent (0-100) for normal urgency notifications
+    #[serde(default = "default_sound_volume")]
+    pub sound_volume_normal: u8,
+    /// Sound volume in percent (0-100) for critical notifications
+    #[serde(default = "default_sound_volume")]
+    pub sound_volume_critical: u8,
+    /// Remove bidi overrides, zero-width and control characters from
+    /// notification text to prevent spoofing (default: true)
+    #[serde(default = "default_true")]
+    pub strip_invisible_chars: bool,
+    /// Rewrite rules applied in order before notifications are shown or
+    /// kept in history (default: none)
+    #[serde(default)]
+    pub rewrite_rules: Vec<RewriteRule>,
+    /// Commands run when notifications arrive, are dismissed or have an
+    /// action invoked (default: none)
+    #[serde(default)]
+    pub hooks: Vec<NotificationHook>,
+    /// WebAssembly plugins run in order on each new notification, after
+    /// the rewrite rules (default: none)
+    #[serde(default)]
+    pub plugins: Vec<PluginConfig>,
+    /// Record each notification and what became of it in the systemd
+    /// journal, to audit alert delivery (default: false)
+    #[serde(default)]
+    pub journal_audit: bool,
+    /// Paired phone that notifications of apps with `forward_to_phone` are
+    /// sent to (default: none)
+    #[serde(default)]
+    pub phone_forwarding: Option<PhoneForwarding>,
+    /// HTTPS endpoint that matching notifications are POSTed to as JSON
+    /// (default: none)
+    #[serde(default)]
+    pub webhook: Option<WebhookConfig>,
+    /// Email a digest of notifications that arrive while the session is
+    /// locked or do-not-disturb is on (default: none)
+    #[serde(default)]
+    pub email_digest: Option<EmailDigestConfig>,
+    /// Mask one-time codes and password-reset links on screen until the
+    /// user reveals them (default: true)
+    #[serde(default = "default_true")]
+    pub mask_secrets: bool,
+    /// Extra directories trusted for `sound-file` hints, for sounds shipped
+    /// outside the standard sound directories (default: none)
+    #[serde(default)]
+    pub extra_sound_dirs: Vec<String>,
+    /// freedesktop.org sound theme to look sound names up in first, such
+    /// as "ocean" or "Yaru" (default: "freedesktop")
+    #[serde(default = "default_sound_theme")]
+    pub sound_theme: String,
+    /// Fade-in at the start of every sound in milliseconds, avoids clicks
+    /// (default: 10)
+    #[serde(default = "default_sound_fade_in_ms")]
+    pub sound_fade_in_ms: u32,
+    /// Fade-out in milliseconds when a sound is stopped before it ends,
+    /// e.g. because its notification was dismissed (default: 50)
+    #[serde(default = "default_sound_fade_out_ms")]
+    pub sound_fade_out_ms: u32,
+    /// Identical sounds from the same app within this many milliseconds
+    /// play only once (default: 1000)
+    #[serde(default = "default_sound_debounce_ms")]
+    pub sound_debounce_ms: u32,
+    /// Lower media player volume while a critical notification sound
+    /// plays (default: false)
+    #[serde(default)]
+    pub duck_media: bool,
+    /// Media volume while ducked, in percent of its normal level (default: 30)
+    #[serde(default = "default_duck_level")]
+    pub duck_level: u8,
+
+    /// Do-not-disturb sub-mode: let notifications on the priority list
+    /// through instead of silencing everything (default: false)
+    #[serde(default)]
+    pub priority_only: bool,
+    /// Apps, categories and keywords allowed through priority-only DND
+    #[serde(default)]
+    pub priority_list: PriorityList,
+    /// Whether critical notifications break through priority-only DND even
+    /// when not on the priority list (default: true)
+    #[serde(default = "default_true")]
+    pub critical_bypass_dnd: bool,
+    /// Show critical notifications as a large banner in the middle of the
+    /// screen that stays until dismissed, for alarms and security alerts
+    /// (default: false)
+    #[serde(default)]
+    pub critical_banner: bool,
+    /// Fold a notification identical to the app's latest popup into that
+    /// popup, counting the repeats, instead of stacking a copy
+    /// (default: true)
+    #[serde(default = "default_true")]
+    pub coalesce_duplicates: bool,
+    /// Send the notifications of an app that floods the screen, in a burst
+    /// like clockwork or with random-looking bodies, to history for a while
+    /// and sum them up in one card when that ends (default: false)
+    #[serde(default)]
+    pub storm_detection: bool,
+    /// How long an app stays quieted after a storm, in minutes (default: 10)
+    #[serde(default = "default_storm_cooldown_minutes")]
+    pub storm_cooldown_minutes: u32,
+
+    /// Show a "While you were away" summary after unlocking, or at the next
+    /// login, when notifications arrived while the session was locked or
+    /// ended (default: true)
+    #[serde(default = "default_true")]
+    pub away_summary: bool,
+
+    /// Show a summary of what arrived during do-not-disturb once it ends
+    /// (default: true)
+    #[serde(default = "default_true")]
+    pub dnd_summary: bool,
+
+    /// Turn do-not-disturb on while the screen is shared or cast, and hide
+    /// the content of popups already on screen (default: true)
+    #[serde(default = "default_true")]
+    pub screen_share_dnd: bool,
+
+    /// What to wipe from history when the session locks or ends, for
+    /// shared machines (default: Never)
+    #[serde(default)]
+    pub wipe_history: HistoryWipe,
+
+    /// Conversations muted from the popup, removed once they expire
+    #[serde(default)]
+    pub muted_conversations: Vec<MutedConversation>,
+    /// How long "Mute conversation" silences a conversation, in hours
+    /// (default: 8)
+    #[serde(default = "default_mute_conversation_hours")]
+    pub mute_conversation_hours: u32,
+
+    /// How many notifications a minute each app may keep sending; more are
+    /// dropped and summarized (default: 60, 0 = unlimited)
+    #[serde(default = "default_rate_limit_per_minute")]
+    pub rate_limit_per_minute: u32,
+    /// How many notifications each app may send in a quick burst
+    /// (default: 20)
+    #[serde(default = "default_rate_limit_burst")]
+    pub rate_limit_burst: u32,
+
+    /// Whether popups show a snooze button (default: false)
+    #[serde(default)]
+    pub snooze_button: bool,
+    /// Durations offered when snoozing a popup (default: 5 minutes, 1 hour
+    /// and tomorrow morning)
+    #[serde(default = "default_snooze_presets")]
+    pub snooze_presets: Vec<SnoozePreset>,
+
+    /// Corner radius in pixels applied to notification images (default: 0)
+    #[serde(default)]
+    pub image_corner_radius: u32,
+    /// How non-square images are fitted (default: Contain)
+    #[serde(default)]
+    pub image_fit: ImageFit,
+    /// Whether square images from chat and email apps are masked to a
+    /// circle like avatars (default: true)
+    #[serde(default = "default_true")]
+    pub circle_avatars: bool,
 }
 
 impl Default for NotificationsConfig {
@@ -117,15 +811,73 @@ impl Default for NotificationsConfig {
             max_timeout_urgent: None,
             max_timeout_normal: Some(5000),
             max_timeout_low: Some(3000),
+            default_timeout_urgent: 0,
+            default_timeout_normal: default_timeout_normal(),
+            default_timeout_low: default_timeout_low(),
+            replace_timeout: ReplaceTimeout::default(),
+            hover_timeout: HoverTimeout::default(),
+            announce_verbosity: AnnounceVerbosity::default(),
+            lock_screen_visibility: LockScreenVisibility::default(),
+            fullscreen_popups: FullscreenPopups::default(),
+            dismiss_on_focus: false,
+            popup_placement: PopupPlacement::default(),
+            popup_output: None,
             show_images: default_true(),
             show_actions: default_true(),
             max_image_size: default_max_image_size(),
             enable_links: default_true(),
             enable_animations: default_true(),
+            density: Density::default(),
+            animation_style: AnimationStyle::default(),
+            animation_duration_ms: default_animation_duration_ms(),
             app_rules: Vec::new(),
             grouping_mode: GroupingMode::default(),
             max_per_group: default_max_per_group(),
             show_group_count: default_true(),
+            thread_conversations: false,
+            smart_ordering: false,
+            priority_weights: PriorityWeights::default(),
+            detect_contacts: false,
+            sound_volume: default_sound_volume(),
+            sound_volume_low: default_sound_volume(),
+            sound_volume_normal: default_sound_volume(),
+            sound_volume_critical: default_sound_volume(),
+            strip_invisible_chars: true,
+            rewrite_rules: Vec::new(),
+            hooks: Vec::new(),
+            plugins: Vec::new(),
+            journal_audit: false,
+            phone_forwarding: None,
+            webhook: None,
+            email_digest: None,
+            mask_secrets: true,
+            extra_sound_dirs: Vec::new(),
+            sound_theme: default_sound_theme(),
+            sound_fade_in_ms: default_sound_fade_in_ms(),
+            sound_fade_out_ms: default_sound_fade_out_ms(),
+            sound_debounce_ms: default_sound_debounce_ms(),
+            duck_media: false,
+            duck_level: default_duck_level(),
+            priority_only: false,
+            priority_list: PriorityList::default(),
+            critical_bypass_dnd: true,
+            critical_banner: false,
+            coalesce_duplicates: default_true(),
+            storm_detection: false,
+            storm_cooldown_minutes: default_storm_cooldown_minutes(),
+            away_summary: true,
+            dnd_summary: true,
+            screen_share_dnd: true,
+            wipe_history: HistoryWipe::default(),
+            muted_conversations: Vec::new(),
+            mute_conversation_hours: default_mute_conversation_hours(),
+            rate_limit_per_minute: default_rate_limit_per_minute(),
+            rate_limit_burst: default_rate_limit_burst(),
+            snooze_button: false,
+            snooze_presets: default_snooze_presets(),
+            image_corner_radius: 0,
+            image_fit: ImageFit::default(),
+            circle_avatars: default_true(),
         }
     }
 }
@@ -143,6 +895,33 @@ impl NotificationsConfig {
         self.app_rules.iter().find(|r| r.app_name == app_name && r.desktop_entry.is_none())
     }
 
+    /// The rule for an app, added with defaults if there is none yet
+    ///
+    /// A new rule matches on the desktop entry when there is one, since
+    /// [`Self::find_app_rule`] prefers those.
+    pub fn app_rule_mut(&mut self, app_name: &str, desktop_entry: Option<&str>) -> &mut AppRule {
+        let found = desktop_entry
+            .and_then(|entry| {
+                self.app_rules
+                    .iter()
+                    .position(|r| r.desktop_entry.as_deref() == Some(entry))
+            })
+            .or_else(|| {
+                self.app_rules
+                    .iter()
+                    .position(|r| r.app_name == app_name && r.desktop_entry.is_none())
+            });
+        let index = found.unwrap_or_else(|| {
+            self.app_rules.push(AppRule {
+                app_name: app_name.to_string(),
+                desktop_entry: desktop_entry.map(str::to_string),
+                ..Default::default()
+            });
+            self.app_rules.len() - 1
+        });
+        &mut self.app_rules[index]
+    }
+
     /// Check if notifications are enabled for an app
     pub fn is_app_enabled(&self, app_name: &str, desktop_entry: Option<&str>) -> bool {
         self.find_app_rule(app_name, desktop_entry)
@@ -156,6 +935,224 @@ impl NotificationsConfig {
             .map(|r| r.sound_enabled)
             .unwrap_or(true)
     }
+
+    /// Current do-not-disturb mode
+    pub fn dnd_mode(&self) -> DndMode {
+        match (self.do_not_disturb, self.priority_only) {
+            (false, _) => DndMode::Open,
+            (true, true) => DndMode::PriorityOnly,
+            (true, false) => DndMode::Full,
+        }
+    }
+
+    /// Switch to a do-not-disturb mode
+    pub fn set_dnd_mode(&mut self, mode: DndMode) {
+        self.do_not_disturb = mode != DndMode::Open;
+        self.priority_only = mode == DndMode::PriorityOnly;
+    }
+
+    /// Per-app override for the avatar-with-badge layout, if any
+    pub fn show_avatar_for_app(&self, app_name: &str, desktop_entry: Option<&str>) -> Option<bool> {
+        self.find_app_rule(app_name, desktop_entry)
+            .and_then(|r| r.show_avatar)
+    }
+
+    /// Check if a conversation is muted at `now` (Unix seconds)
+    pub fn is_conversation_muted(&self, key: &str, now: u64) -> bool {
+        self.muted_conversations
+            .iter()
+            .any(|m| m.key == key && m.until > now)
+    }
+
+    /// Muted conversations with expired mutes removed
+    pub fn active_conversation_mutes(&self, now: u64) -> Vec<MutedConversation> {
+        self.muted_conversations
+            .iter()
+            .filter(|m| m.until > now)
+            .cloned()
+            .collect()
+    }
+
+    /// Timeout in milliseconds to apply to a notification, 0 = never expire
+    ///
+    /// `requested` is the sender's expire_timeout. For -1 (server decides)
+    /// the app rule's `timeout_override` or the per-urgency default is used.
+    /// The result is then clamped to the per-urgency maximum, except that
+    /// notifications that never expire stay that way.
+    pub fn resolve_timeout(
+        &self,
+        urgency: u8,
+        requested: i32,
+        app_name: &str,
+        desktop_entry: Option<&str>,
+    ) -> u32 {
+        let (default, max) = match urgency {
+            0 => (self.default_timeout_low, self.max_timeout_low),
+            1 => (self.default_timeout_normal, self.max_timeout_normal),
+            _ => (self.default_timeout_urgent, self.max_timeout_urgent),
+        };
+        let timeout = match u32::try_from(requested) {
+            Ok(requested) => requested,
+            Err(_) => self
+                .find_app_rule(app_name, desktop_entry)
+                .and_then(|r| r.timeout_override)
+                .unwrap_or(default),
+        };
+        match max {
+            Some(max) if timeout > 0 => timeout.min(max),
+            _ => timeout,
+        }
+    }
+
+    /// Window in which repeated identical sounds from an app play only once
+    pub fn sound_debounce_for_app(&self, app_name: &str, desktop_entry: Option<&str>) -> Duration {
+        let ms = self
+            .find_app_rule(app_name, desktop_entry)
+            .and_then(|r| r.sound_debounce_ms)
+            .unwrap_or(self.sound_debounce_ms);
+        Duration::from_millis(u64::from(ms))
+    }
+
+    /// Check if notifications from an app should be read aloud
+    pub fn speak_for_app(&self, app_name: &str, desktop_entry: Option<&str>) -> bool {
+        self.find_app_rule(app_name, desktop_entry)
+            .is_some_and(|r| r.speak)
+    }
+
+    /// Check if an app's notifications may be kept in history
+    pub fn store_in_history_for_app(&self, app_name: &str, desktop_entry: Option<&str>) -> bool {
+        self.find_app_rule(app_name, desktop_entry)
+            .map(|r| r.store_in_history)
+            .unwrap_or(true)
+    }
+
+    /// How fast an app may send notifications, its rule overriding the
+    /// global limit
+    pub fn rate_limit_for_app(&self, app_name: &str, desktop_entry: Option<&str>) -> RateLimit {
+        let rule = self.find_app_rule(app_name, desktop_entry);
+        RateLimit {
+            per_minute: rule
+                .and_then(|r| r.rate_limit_per_minute)
+                .unwrap_or(self.rate_limit_per_minute),
+            burst: rule
+                .and_then(|r| r.rate_limit_burst)
+                .unwrap_or(self.rate_limit_burst),
+        }
+    }
+
+    /// Priority of an app's notifications from its rule, -100 to 100
+    pub fn priority_for_app(&self, app_name: &str, desktop_entry: Option<&str>) -> i32 {
+        self.find_app_rule(app_name, desktop_entry)
+            .and_then(|r| r.priority)
+            .unwrap_or(0)
+            .clamp(-100, 100)
+    }
+
+    /// Check if one-time codes and reset links from an app are masked
+    pub fn mask_secrets_for_app(&self, app_name: &str, desktop_entry: Option<&str>) -> bool {
+        self.find_app_rule(app_name, desktop_entry)
+            .and_then(|r| r.mask_secrets)
+            .unwrap_or(self.mask_secrets)
+    }
+
+    /// How often an app's notifications are delivered as a digest, or
+    /// `None` if they pop up one by one
+    pub fn digest_interval_for_app(&self, app_name: &str, desktop_entry: Option<&str>) -> Option<Duration> {
+        self.find_app_rule(app_name, desktop_entry)
+            .and_then(|r| r.digest_minutes)
+            .filter(|minutes| *minutes > 0)
+            .map(|minutes| Duration::from_secs(u64::from(minutes) * 60))
+    }
+
+    /// Hooks to run for `event` on a notification from an app
+    pub fn hooks_for(
+        &self,
+        event: HookEvent,
+        app_name: &str,
+        desktop_entry: Option<&str>,
+    ) -> Vec<&NotificationHook> {
+        if self.find_app_rule(app_name, desktop_entry).is_some_and(|r| !r.run_hooks) {
+            return Vec::new();
+        }
+        self.hooks
+            .iter()
+            .filter(|hook| hook.event == event && !hook.command.is_empty())
+            .filter(|hook| {
+                hook.app_name
+                    .as_deref()
+                    .is_none_or(|app| app == app_name || Some(app) == desktop_entry)
+            })
+            .collect()
+    }
+
+    /// Where to forward a notification from an app, if anywhere
+    pub fn phone_forwarding_for(&self, app_name: &str, desktop_entry: Option<&str>) -> Option<&PhoneForwarding> {
+        self.phone_forwarding.as_ref().filter(|forwarding| {
+            !forwarding.device_id.is_empty()
+                && self
+                    .find_app_rule(app_name, desktop_entry)
+                    .is_some_and(|r| r.forward_to_phone)
+        })
+    }
+
+    /// Check if a notification goes to the `webhook`
+    ///
+    /// Without `app_names`, apps marked sensitive or kept out of history
+    /// are left out; they only go when listed by name.
+    pub fn webhook_matches(&self, app_name: &str, desktop_entry: Option<&str>, urgency: u8) -> bool {
+        self.webhook.as_ref().is_some_and(|webhook| {
+            let listed = if webhook.app_names.is_empty() {
+                !self.is_sensitive_app(app_name, desktop_entry)
+                    && self.store_in_history_for_app(app_name, desktop_entry)
+            } else {
+                webhook
+                    .app_names
+                    .iter()
+                    .any(|app| app == app_name || Some(app.as_str()) == desktop_entry)
+            };
+            urgency >= webhook.min_urgency && listed
+        })
+    }
+
+    /// Check if an app's notifications are marked as private
+    pub fn is_sensitive_app(&self, app_name: &str, desktop_entry: Option<&str>) -> bool {
+        self.find_app_rule(app_name, desktop_entry)
+            .is_some_and(|r| r.sensitive)
+    }
+
+    /// Sound volume in percent for a notification from an app
+    ///
+    /// The app's `volume_override` takes the place of the per-urgency level.
+    pub fn sound_volume_for_app(
+        &self,
+        urgency: u8,
+        app_name: &str,
+        desktop_entry: Option<&str>,
+    ) -> u8 {
+        match self
+            .find_app_rule(app_name, desktop_entry)
+            .and_then(|r| r.volume_override)
+        {
+            Some(level) => {
+                (u16::from(level.min(100)) * u16::from(self.sound_volume.min(100)) / 100) as u8
+            }
+            None => self.sound_volume_for_urgency(urgency),
+        }
+    }
+
+    /// Sound volume in percent for an urgency level (0=low, 1=normal, 2=critical)
+    ///
+    /// The per-urgency level is scaled by the master `sound_volume`.
+    pub fn sound_volume_for_urgency(&self, urgency: u8) -> u8 {
+        let level = match urgency {
+            0 => self.sound_volume_low,
+            1 => self.sound_volume_normal,
+            _ => self.sound_volume_critical,
+        }
+        .min(100);
+        // Both factors are at most 100, so the result fits in a u8
+        (u16::from(level) * u16::from(self.sound_volume.min(100)) / 100) as u8
+    }
 }
 
 // Default value helpers for serde
@@ -171,6 +1168,74 @@ const fn default_max_per_group() -> u32 {
     3
 }
 
+const fn default_sound_volume() -> u8 {
+    100
+}
+
+const fn default_timeout_normal() -> u32 {
+    5000
+}
+
+const fn default_timeout_low() -> u32 {
+    3000
+}
+
+fn default_sound_theme() -> String {
+    "freedesktop".to_string()
+}
+
+const fn default_animation_duration_ms() -> u32 {
+    200
+}
+
+const fn default_sound_fade_in_ms() -> u32 {
+    10
+}
+
+const fn default_sound_fade_out_ms() -> u32 {
+    50
+}
+
+const fn default_sound_debounce_ms() -> u32 {
+    1000
+}
+
+const fn default_duck_level() -> u8 {
+    30
+}
+
+const fn default_mute_conversation_hours() -> u32 {
+    8
+}
+
+const fn default_storm_cooldown_minutes() -> u32 {
+    10
+}
+
+const fn default_rate_limit_per_minute() -> u32 {
+    60
+}
+
+const fn default_rate_limit_burst() -> u32 {
+    20
+}
+
+const fn default_smtp_port() -> u16 {
+    587
+}
+
+const fn default_email_digest_minutes() -> u32 {
+    60
+}
+
+fn default_snooze_presets() -> Vec<SnoozePreset> {
+    vec![
+        SnoozePreset::Minutes(5),
+        SnoozePreset::Minutes(60),
+        SnoozePreset::Tomorrow,
+    ]
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -186,6 +1251,24 @@ mod tests {
         assert_eq!(config.max_timeout_normal, Some(5000));
         assert_eq!(config.max_timeout_low, Some(3000));
         assert_eq!(config.max_timeout_urgent, None);
+        assert_eq!(config.default_timeout_urgent, 0);
+        assert_eq!(config.default_timeout_normal, 5000);
+        assert_eq!(config.default_timeout_low, 3000);
+        assert_eq!(config.replace_timeout, ReplaceTimeout::Reset);
+        assert_eq!(config.hover_timeout, HoverTimeout::Resume);
+        assert_eq!(config.announce_verbosity, AnnounceVerbosity::Full);
+        assert_eq!(config.lock_screen_visibility, LockScreenVisibility::ShowAll);
+        assert_eq!(config.fullscreen_popups, FullscreenPopups::Queue);
+        assert!(!config.dismiss_on_focus);
+        assert_eq!(config.popup_placement, PopupPlacement::Active);
+        assert_eq!(config.popup_output, None);
+        assert!(!config.critical_banner);
+        assert!(config.coalesce_duplicates);
+        assert!(!config.storm_detection);
+        assert_eq!(config.storm_cooldown_minutes, 10);
+        assert!(!config.thread_conversations);
+        assert!(!config.smart_ordering);
+        assert_eq!(config.priority_weights, PriorityWeights::default());
 
         // Test new rich notification fields
         assert!(config.show_images);
@@ -193,6 +1276,50 @@ mod tests {
         assert_eq!(config.max_image_size, 128);
         assert!(config.enable_links);
         assert!(config.enable_animations);
+        assert_eq!(config.animation_style, AnimationStyle::SlideFromEdge);
+        assert_eq!(config.animation_duration_ms, 200);
+        assert_eq!(config.density, Density::Comfortable);
+
+        // Content analyzers are opt-in
+        assert!(!config.detect_contacts);
+        assert_eq!(config.sound_volume, 100);
+        assert_eq!(config.sound_volume_low, 100);
+        assert_eq!(config.sound_volume_normal, 100);
+        assert_eq!(config.sound_volume_critical, 100);
+        assert!(config.strip_invisible_chars);
+        assert!(config.rewrite_rules.is_empty());
+        assert!(config.hooks.is_empty());
+        assert!(config.plugins.is_empty());
+        assert!(!config.journal_audit);
+        assert!(config.phone_forwarding.is_none());
+        assert!(config.webhook.is_none());
+        assert!(config.email_digest.is_none());
+        assert!(config.mask_secrets);
+        assert_eq!(config.sound_theme, "freedesktop");
+        assert_eq!(config.sound_fade_in_ms, 10);
+        assert_eq!(config.sound_fade_out_ms, 50);
+        assert!(!config.duck_media);
+        assert_eq!(config.duck_level, 30);
+        assert!(!config.priority_only);
+        assert_eq!(config.priority_list, PriorityList::default());
+        assert!(config.critical_bypass_dnd);
+        assert_eq!(config.dnd_mode(), DndMode::Open);
+        assert!(config.away_summary);
+        assert!(config.dnd_summary);
+        assert!(config.screen_share_dnd);
+        assert_eq!(config.wipe_history, HistoryWipe::Never);
+        assert!(config.muted_conversations.is_empty());
+        assert_eq!(config.mute_conversation_hours, 8);
+        assert_eq!(config.rate_limit_per_minute, 60);
+        assert_eq!(config.rate_limit_burst, 20);
+        assert!(!config.snooze_button);
+        assert_eq!(
+            config.snooze_presets,
+            vec![SnoozePreset::Minutes(5), SnoozePreset::Minutes(60), SnoozePreset::Tomorrow]
+        );
+        assert_eq!(config.image_corner_radius, 0);
+        assert_eq!(config.image_fit, ImageFit::Contain);
+        assert!(config.circle_avatars);
     }
 
     #[test]
@@ -398,6 +1525,179 @@ mod tests {
         assert_eq!(rule.urgency_override, None);
         assert!(rule.sound_enabled);
         assert_eq!(rule.timeout_override, None);
+        assert_eq!(rule.show_avatar, None);
+        assert!(rule.store_in_history);
+    }
+
+    #[test]
+    fn test_app_rule_mut() {
+        let mut config = NotificationsConfig::default();
+        config.app_rules.push(AppRule {
+            app_name: "Slack".to_string(),
+            ..Default::default()
+        });
+
+        config.app_rule_mut("Slack", None).enabled = false;
+        assert_eq!(config.app_rules.len(), 1);
+        assert!(!config.is_app_enabled("Slack", None));
+
+        // Apps with a desktop entry get a rule of their own
+        config.app_rule_mut("Firefox", Some("firefox")).sound_enabled = false;
+        assert_eq!(config.app_rules.len(), 2);
+        assert_eq!(config.app_rules[1].desktop_entry.as_deref(), Some("firefox"));
+        assert!(!config.is_sound_enabled_for_app("Firefox", Some("firefox")));
+    }
+
+    #[test]
+    fn test_rate_limit_for_app() {
+        let mut config = NotificationsConfig::default();
+        config.app_rules.push(AppRule {
+            app_name: "backup-script".to_string(),
+            rate_limit_per_minute: Some(6),
+            ..Default::default()
+        });
+
+        assert_eq!(config.rate_limit_for_app("Signal", None), RateLimit::default());
+        assert_eq!(
+            config.rate_limit_for_app("backup-script", None),
+            RateLimit { per_minute: 6, burst: 20 }
+        );
+    }
+
+    #[test]
+    fn test_priority_for_app() {
+        let mut config = NotificationsConfig::default();
+        config.app_rules.push(AppRule {
+            app_name: "Pager".to_string(),
+            priority: Some(250),
+            ..Default::default()
+        });
+
+        assert_eq!(config.priority_for_app("Pager", None), 100);
+        assert_eq!(config.priority_for_app("Signal", None), 0);
+    }
+
+    #[test]
+    fn test_mask_secrets_for_app() {
+        let mut config = NotificationsConfig::default();
+        config.app_rules.push(AppRule {
+            app_name: "Authenticator".to_string(),
+            mask_secrets: Some(false),
+            ..Default::default()
+        });
+
+        assert!(config.mask_secrets_for_app("Messages", None));
+        assert!(!config.mask_secrets_for_app("Authenticator", None));
+        config.mask_secrets = false;
+        assert!(!config.mask_secrets_for_app("Messages", None));
+    }
+
+    #[test]
+    fn test_digest_interval_for_app() {
+        let mut config = NotificationsConfig::default();
+        config.app_rules.push(AppRule {
+            app_name: "Feeds".to_string(),
+            digest_minutes: Some(15),
+            ..Default::default()
+        });
+        config.app_rules.push(AppRule {
+            app_name: "CI".to_string(),
+            digest_minutes: Some(0),
+            ..Default::default()
+        });
+
+        assert_eq!(config.digest_interval_for_app("Feeds", None), Some(Duration::from_secs(900)));
+        assert_eq!(config.digest_interval_for_app("CI", None), None);
+        assert_eq!(config.digest_interval_for_app("Signal", None), None);
+    }
+
+    #[test]
+    fn test_hooks_for() {
+        let hook = |event, app: Option<&str>| NotificationHook {
+            event,
+            command: vec!["notify-log".to_string()],
+            app_name: app.map(str::to_string),
+        };
+        let mut config = NotificationsConfig::default();
+        config.hooks = vec![
+            hook(HookEvent::Received, None),
+            hook(HookEvent::Received, Some("org.example.Mail")),
+            hook(HookEvent::Dismissed, None),
+        ];
+        config.app_rules.push(AppRule {
+            app_name: "Vault".to_string(),
+            run_hooks: false,
+            ..Default::default()
+        });
+
+        assert_eq!(config.hooks_for(HookEvent::Received, "Mail", Some("org.example.Mail")).len(), 2);
+        assert_eq!(config.hooks_for(HookEvent::Received, "Chat", None).len(), 1);
+        assert_eq!(config.hooks_for(HookEvent::ActionInvoked, "Chat", None).len(), 0);
+        assert!(config.hooks_for(HookEvent::Received, "Vault", None).is_empty());
+    }
+
+    #[test]
+    fn test_phone_forwarding_for() {
+        let mut config = NotificationsConfig::default();
+        config.app_rules.push(AppRule {
+            app_name: "Signal".to_string(),
+            forward_to_phone: true,
+            ..Default::default()
+        });
+        assert!(config.phone_forwarding_for("Signal", None).is_none());
+
+        config.phone_forwarding = Some(PhoneForwarding {
+            bridge: PhoneBridge::KdeConnect,
+            device_id: "a1b2c3".to_string(),
+            mirror_dismissals: false,
+        });
+        assert!(config.phone_forwarding_for("Signal", None).is_some());
+        assert!(config.phone_forwarding_for("Mail", None).is_none());
+    }
+
+    #[test]
+    fn test_webhook_matches() {
+        let mut config = NotificationsConfig::default();
+        assert!(!config.webhook_matches("Doorbell", None, 2));
+
+        config.webhook = Some(WebhookConfig {
+            url: "https://hooks.example.com/notify".to_string(),
+            headers: BTreeMap::new(),
+            app_names: vec!["org.example.Doorbell".to_string()],
+            min_urgency: 1,
+        });
+        assert!(config.webhook_matches("Doorbell", Some("org.example.Doorbell"), 1));
+        assert!(!config.webhook_matches("Doorbell", Some("org.example.Doorbell"), 0));
+        assert!(!config.webhook_matches("Mail", None, 2));
+
+        // Every app, except private ones
+        config.webhook.as_mut().unwrap().app_names.clear();
+        config.app_rules.push(AppRule {
+            app_name: "Bank".to_string(),
+            sensitive: true,
+            ..Default::default()
+        });
+        config.app_rules.push(AppRule {
+            app_name: "KeePassXC".to_string(),
+            store_in_history: false,
+            ..Default::default()
+        });
+        assert!(config.webhook_matches("Mail", None, 2));
+        assert!(!config.webhook_matches("Bank", None, 2));
+        assert!(!config.webhook_matches("KeePassXC", None, 2));
+    }
+
+    #[test]
+    fn test_show_avatar_for_app() {
+        let mut config = NotificationsConfig::default();
+        config.app_rules.push(AppRule {
+            app_name: "Slack".to_string(),
+            show_avatar: Some(false),
+            ..Default::default()
+        });
+
+        assert_eq!(config.show_avatar_for_app("Slack", None), Some(false));
+        assert_eq!(config.show_avatar_for_app("Signal", None), None);
     }
 
     #[test]
@@ -410,6 +1710,7 @@ mod tests {
             urgency_override: Some(1),
             sound_enabled: false,
             timeout_override: Some(10000),
+            ..Default::default()
         });
 
         // Should find rule by app_name
@@ -433,6 +1734,7 @@ mod tests {
             urgency_override: Some(2),
             sound_enabled: false,
             timeout_override: Some(15000),
+            ..Default::default()
         });
 
         // Should find rule by desktop_entry
@@ -461,6 +1763,7 @@ mod tests {
             urgency_override: Some(0),
             sound_enabled: true,
             timeout_override: Some(5000),
+            ..Default::default()
         });
 
         // Add specific desktop_entry rule
@@ -471,6 +1774,7 @@ mod tests {
             urgency_override: Some(2),
             sound_enabled: false,
             timeout_override: Some(10000),
+            ..Default::default()
         });
 
         // Desktop entry rule should take precedence
@@ -496,6 +1800,7 @@ mod tests {
             urgency_override: None,
             sound_enabled: true,
             timeout_override: None,
+            ..Default::default()
         });
 
         // Disabled app
@@ -505,6 +1810,19 @@ mod tests {
         assert!(config.is_app_enabled("some-other-app", None));
     }
 
+    #[test]
+    fn test_muting_from_popup_disables_app() {
+        let mut config = NotificationsConfig::default();
+        config
+            .app_rule_mut("Firefox", Some("org.mozilla.firefox"))
+            .enabled = false;
+
+        assert!(!config.is_app_enabled("Firefox", Some("org.mozilla.firefox")));
+        // A renamed app is still recognized by its desktop entry
+        assert!(!config.is_app_enabled("Firefox Nightly", Some("org.mozilla.firefox")));
+        assert!(config.is_app_enabled("Firefox", Some("org.mozilla.thunderbird")));
+    }
+
     #[test]
     fn test_is_sound_enabled_for_app() {
         let mut config = NotificationsConfig::default();
@@ -515,6 +1833,7 @@ mod tests {
             urgency_override: None,
             sound_enabled: false,
             timeout_override: None,
+            ..Default::default()
         });
 
         // Sound disabled for specific app
@@ -524,6 +1843,196 @@ mod tests {
         assert!(config.is_sound_enabled_for_app("normal-app", None));
     }
 
+    #[test]
+    fn test_resolve_timeout() {
+        let mut config = NotificationsConfig::default();
+        config.app_rules.push(AppRule {
+            app_name: "Slow".to_string(),
+            timeout_override: Some(4000),
+            ..Default::default()
+        });
+
+        // -1 uses the per-urgency default
+        assert_eq!(config.resolve_timeout(0, -1, "App", None), 3000);
+        assert_eq!(config.resolve_timeout(1, -1, "App", None), 5000);
+        assert_eq!(config.resolve_timeout(2, -1, "App", None), 0);
+        // ...or the app rule
+        assert_eq!(config.resolve_timeout(1, -1, "Slow", None), 4000);
+        // Requested timeouts are clamped to the maximum
+        assert_eq!(config.resolve_timeout(1, 60_000, "App", None), 5000);
+        assert_eq!(config.resolve_timeout(2, 60_000, "App", None), 60_000);
+        // 0 never expires, even with a maximum set
+        assert_eq!(config.resolve_timeout(1, 0, "App", None), 0);
+    }
+
+    #[test]
+    fn test_sound_debounce_for_app() {
+        let mut config = NotificationsConfig::default();
+        config.app_rules.push(AppRule {
+            app_name: "Chat".to_string(),
+            sound_debounce_ms: Some(5000),
+            ..Default::default()
+        });
+
+        assert_eq!(config.sound_debounce_for_app("Chat", None), Duration::from_secs(5));
+        assert_eq!(config.sound_debounce_for_app("Other", None), Duration::from_secs(1));
+    }
+
+    #[test]
+    fn test_speak_for_app() {
+        let mut config = NotificationsConfig::default();
+        config.app_rules.push(AppRule {
+            app_name: "Calendar".to_string(),
+            speak: true,
+            ..Default::default()
+        });
+
+        assert!(config.speak_for_app("Calendar", None));
+        // Opt-in only
+        assert!(!config.speak_for_app("Firefox", None));
+    }
+
+    #[test]
+    fn test_store_in_history_for_app() {
+        let mut config = NotificationsConfig::default();
+        config.app_rules.push(AppRule {
+            app_name: "KeePassXC".to_string(),
+            store_in_history: false,
+            ..Default::default()
+        });
+
+        assert!(!config.store_in_history_for_app("KeePassXC", None));
+        assert!(config.store_in_history_for_app("Firefox", None));
+    }
+
+    #[test]
+    fn test_is_sensitive_app() {
+        let mut config = NotificationsConfig::default();
+        config.app_rules.push(AppRule {
+            app_name: "Bank".to_string(),
+            sensitive: true,
+            ..Default::default()
+        });
+
+        assert!(config.is_sensitive_app("Bank", None));
+        // Opt-in only
+        assert!(!config.is_sensitive_app("Firefox", None));
+    }
+
+    #[test]
+    fn test_muted_conversations_expire() {
+        let mut config = NotificationsConfig::default();
+        config.muted_conversations = vec![
+            MutedConversation {
+                key: "chat\u{1f}Alice".to_string(),
+                until: 1_000,
+            },
+            MutedConversation {
+                key: "chat\u{1f}Bob".to_string(),
+                until: 5_000,
+            },
+        ];
+
+        assert!(config.is_conversation_muted("chat\u{1f}Alice", 999));
+        assert!(!config.is_conversation_muted("chat\u{1f}Alice", 1_000));
+        assert!(!config.is_conversation_muted("chat\u{1f}Carol", 0));
+
+        let active = config.active_conversation_mutes(2_000);
+        assert_eq!(active.len(), 1);
+        assert_eq!(active[0].key, "chat\u{1f}Bob");
+    }
+
+    #[test]
+    fn test_dnd_mode_round_trip() {
+        let mut config = NotificationsConfig::default();
+        for mode in [DndMode::PriorityOnly, DndMode::Full, DndMode::Open] {
+            config.set_dnd_mode(mode);
+            assert_eq!(config.dnd_mode(), mode);
+        }
+
+        // Legacy configs with only do_not_disturb set mean full DND
+        config.do_not_disturb = true;
+        config.priority_only = false;
+        assert_eq!(config.dnd_mode(), DndMode::Full);
+    }
+
+    #[test]
+    fn test_priority_list_matches() {
+        let list = PriorityList {
+            apps: vec!["Signal".to_string(), "org.gnome.Calendar".to_string()],
+            categories: vec!["call".to_string()],
+            keywords: vec!["urgent".to_string()],
+        };
+
+        assert!(list.matches("signal", None, None, "Hi", ""));
+        assert!(list.matches("Calendar", Some("org.gnome.Calendar"), None, "Meeting", ""));
+        assert!(list.matches("Phone", None, Some("call.incoming"), "Bob", ""));
+        assert!(list.matches("Mail", None, None, "URGENT: server down", ""));
+        assert!(list.matches("Mail", None, None, "Server", "this is Urgent"));
+
+        assert!(!list.matches("Firefox", None, None, "Download complete", ""));
+        // Category prefixes only match whole classes
+        assert!(!list.matches("Phone", None, Some("callback"), "Bob", ""));
+    }
+
+    #[test]
+    fn test_empty_priority_list_matches_nothing() {
+        let list = PriorityList {
+            keywords: vec!["  ".to_string()],
+            ..Default::default()
+        };
+        assert!(!list.matches("App", None, Some("im"), "Summary", "Body"));
+    }
+
+    #[test]
+    fn test_sound_volume_for_urgency() {
+        let config = NotificationsConfig {
+            sound_volume_low: 20,
+            sound_volume_normal: 40,
+            sound_volume_critical: 250,
+            ..Default::default()
+        };
+
+        assert_eq!(config.sound_volume_for_urgency(0), 20);
+        assert_eq!(config.sound_volume_for_urgency(1), 40);
+        // Critical is clamped to 100%
+        assert_eq!(config.sound_volume_for_urgency(2), 100);
+    }
+
+    #[test]
+    fn test_sound_volume_for_app() {
+        let mut config = NotificationsConfig {
+            sound_volume: 50,
+            ..Default::default()
+        };
+        config.app_rules.push(AppRule {
+            app_name: "Build Bot".to_string(),
+            volume_override: Some(20),
+            ..Default::default()
+        });
+
+        assert_eq!(config.sound_volume_for_app(2, "Build Bot", None), 10);
+        assert_eq!(config.sound_volume_for_app(2, "Other", None), 50);
+    }
+
+    #[test]
+    fn test_master_sound_volume_scales_urgency_levels() {
+        let config = NotificationsConfig {
+            sound_volume: 50,
+            sound_volume_low: 40,
+            ..Default::default()
+        };
+
+        assert_eq!(config.sound_volume_for_urgency(0), 20);
+        assert_eq!(config.sound_volume_for_urgency(2), 50);
+
+        let muted = NotificationsConfig {
+            sound_volume: 0,
+            ..Default::default()
+        };
+        assert_eq!(muted.sound_volume_for_urgency(2), 0);
+    }
+
     #[test]
     fn test_app_rule_serialization() {
         let rule = AppRule {
@@ -533,6 +2042,7 @@ mod tests {
             urgency_override: Some(1),
             sound_enabled: false,
             timeout_override: Some(8000),
+            ..Default::default()
         };
 
         let json = serde_json::to_string(&rule).unwrap();
@@ -557,6 +2067,7 @@ mod tests {
         assert!(rule.sound_enabled);
         assert_eq!(rule.urgency_override, None);
         assert_eq!(rule.timeout_override, None);
+        assert!(rule.store_in_history);
     }
 
     #[test]
@@ -569,6 +2080,7 @@ mod tests {
             urgency_override: Some(2),
             sound_enabled: false,
             timeout_override: Some(10000),
+            ..Default::default()
         });
 
         let json = serde_json::to_string(&config).unwrap();
@@ -669,6 +2181,7 @@ mod tests {
             urgency_override: Some(0),
             sound_enabled: true,
             timeout_override: None,
+            ..Default::default()
         });
 
         // Test normal urgency override
@@ -679,6 +2192,7 @@ mod tests {
             urgency_override: Some(1),
             sound_enabled: true,
             timeout_override: None,
+            ..Default::default()
         });
 
         // Test critical urgency override
@@ -689,6 +2203,7 @@ mod tests {
             urgency_override: Some(2),
             sound_enabled: true,
             timeout_override: None,
+            ..Default::default()
         });
 
         let low = config.find_app_rule("low-priority", None);
@@ -29,6 +29,21 @@ pub fn has_action_icons(hints: &[Hint]) -> bool {
     hints.iter().any(|h| matches!(h, Hint::ActionIcons(true)))
 }
 
+/// Icon name for an action when the `action-icons` hint is set
+///
+/// The spec reuses the action identifier as a freedesktop icon name. Only
+/// plain names are accepted, so a client can't point the icon loader at a
+/// file path; None means the action should fall back to its text label.
+pub fn action_icon_name(action_id: &str) -> Option<&str> {
+    let valid = !action_id.is_empty()
+        && action_id.len() <= 128
+        && action_id
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | '.'))
+        && !action_id.starts_with('.');
+    valid.then_some(action_id)
+}
+
 /// Get the default action if present (action with id "default")
 pub fn get_default_action(actions: &[NotificationAction]) -> Option<&NotificationAction> {
     actions.iter().find(|a| a.id == "default")
@@ -140,4 +155,14 @@ mod tests {
         assert!(!has_action_icons(&hints_without));
         assert!(!has_action_icons(&hints_empty));
     }
+
+    #[test]
+    fn test_action_icon_name() {
+        assert_eq!(action_icon_name("media-playback-pause"), Some("media-playback-pause"));
+        assert_eq!(action_icon_name("call-start-symbolic"), Some("call-start-symbolic"));
+        assert_eq!(action_icon_name("/etc/passwd"), None);
+        assert_eq!(action_icon_name("../icons/x"), None);
+        assert_eq!(action_icon_name("open link"), None);
+        assert_eq!(action_icon_name(""), None);
+    }
 }
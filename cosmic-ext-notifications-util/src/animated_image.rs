@@ -1,3 +1,5 @@
+use crate::notification_image::{NotificationImage, ProcessedImage, MAX_IMAGE_FILE_BYTES};
+use std::path::Path;
 use std::time::Duration;
 
 /// Maximum frames to store per animation (memory protection)
@@ -6,8 +8,15 @@ pub const MAX_FRAMES: usize = 100;
 /// Maximum animation duration
 pub const MAX_ANIMATION_DURATION: Duration = Duration::from_secs(30);
 
+/// Frames asking for this delay or less play at [`DEFAULT_FRAME_DELAY_MS`],
+/// as browsers do, so a zero delay can't spin the CPU
+pub const MIN_FRAME_DELAY_MS: u32 = 10;
+
+/// Delay used for frames that ask for less than [`MIN_FRAME_DELAY_MS`]
+pub const DEFAULT_FRAME_DELAY_MS: u32 = 100;
+
 /// Single frame of an animation
-#[derive(Clone)]
+#[derive(Debug, Clone)]
 pub struct AnimationFrame {
     pub data: Vec<u8>,      // RGBA pixels
     pub width: u32,
@@ -16,7 +25,7 @@ pub struct AnimationFrame {
 }
 
 /// Animated image with frame data
-#[derive(Clone)]
+#[derive(Debug, Clone)]
 pub struct AnimatedImage {
     frames: Vec<AnimationFrame>,
     total_duration_ms: u32,
@@ -50,39 +59,99 @@ impl AnimatedImage {
     }
 
     /// Try to decode animated image from data
+    ///
+    /// Handles GIF and APNG. Frames are scaled down to thumbnail size as
+    /// they are decoded, and decoding stops after [`MAX_FRAMES`] or
+    /// [`MAX_ANIMATION_DURATION`], whichever comes first.
     /// Returns None if not animated or decoding fails
     pub fn from_data(data: &[u8]) -> Option<Self> {
         use image::codecs::gif::GifDecoder;
-        use image::AnimationDecoder;
+        use image::codecs::png::PngDecoder;
+        use image::{AnimationDecoder, ImageDecoder};
         use std::io::Cursor;
 
-        // Try GIF first
-        if let Ok(decoder) = GifDecoder::new(Cursor::new(data)) {
-            let frames: Vec<_> = decoder
-                .into_frames()
-                .filter_map(|f| f.ok())
-                .take(MAX_FRAMES)
-                .map(|frame| {
-                    let (numer, denom) = frame.delay().numer_denom_ms();
-                    let delay_ms = ((numer as u64 * 1000) / denom as u64) as u32;
-                    let buffer = frame.into_buffer();
-                    let (width, height) = buffer.dimensions();
-
-                    AnimationFrame {
-                        data: buffer.into_raw(),
-                        width,
-                        height,
-                        delay_ms: delay_ms.max(10), // Minimum 10ms delay
-                    }
-                })
-                .collect();
-
-            if frames.len() > 1 {
-                return Some(Self::new(frames));
+        let frames = if let Ok(mut decoder) = GifDecoder::new(Cursor::new(data)) {
+            decoder.set_limits(NotificationImage::decode_limits()).ok()?;
+            Self::collect_frames(decoder.into_frames())
+        } else {
+            let decoder =
+                PngDecoder::with_limits(Cursor::new(data), NotificationImage::decode_limits()).ok()?;
+            if !decoder.is_apng().ok()? {
+                return None;
             }
+            Self::collect_frames(decoder.apng().ok()?.into_frames())
+        };
+
+        (frames.len() > 1).then(|| Self::new(frames))
+    }
+
+    /// Read and decode an animated image file
+    ///
+    /// Files over [`MAX_IMAGE_FILE_BYTES`] are refused unread.
+    /// Returns None if not animated or decoding fails
+    pub fn from_path(path: &Path) -> Option<Self> {
+        if std::fs::metadata(path).ok()?.len() > MAX_IMAGE_FILE_BYTES {
+            return None;
+        }
+        let data = std::fs::read(path).ok()?;
+        if !Self::might_be_animated(&data) {
+            return None;
         }
+        Self::from_data(&data)
+    }
 
-        None
+    fn collect_frames(frames: image::Frames<'_>) -> Vec<AnimationFrame> {
+        let mut collected = Vec::new();
+        let mut total_ms = 0u64;
+        // A broken frame ends the animation where it is
+        for frame in frames.map_while(Result::ok) {
+            if collected.len() >= MAX_FRAMES || total_ms >= MAX_ANIMATION_DURATION.as_millis() as u64 {
+                break;
+            }
+            let (numer, denom) = frame.delay().numer_denom_ms();
+            let delay_ms = match numer / denom.max(1) {
+                ms if ms <= MIN_FRAME_DELAY_MS => DEFAULT_FRAME_DELAY_MS,
+                ms => ms,
+            };
+            let buffer = frame.into_buffer();
+            let (width, height) = buffer.dimensions();
+            let Ok((width, height, data)) =
+                NotificationImage::resize_if_needed(width, height, buffer.into_raw())
+            else {
+                break;
+            };
+
+            total_ms += u64::from(delay_ms);
+            collected.push(AnimationFrame {
+                data,
+                width,
+                height,
+                delay_ms,
+            });
+        }
+        collected
+    }
+
+    /// Run every frame through `f`, e.g. to crop or round its corners
+    pub fn map_frames(self, mut f: impl FnMut(ProcessedImage) -> ProcessedImage) -> Self {
+        let frames = self
+            .frames
+            .into_iter()
+            .map(|frame| {
+                let image = f(ProcessedImage {
+                    data: frame.data,
+                    width: frame.width,
+                    height: frame.height,
+                });
+                AnimationFrame {
+                    data: image.data,
+                    width: image.width,
+                    height: image.height,
+                    delay_ms: frame.delay_ms,
+                }
+            })
+            .collect();
+        Self::new(frames)
     }
 
     /// Get number of frames
@@ -97,21 +166,34 @@ impl AnimatedImage {
 
     /// Get frame at specific time offset (loops)
     pub fn frame_at(&self, elapsed_ms: u32) -> Option<&AnimationFrame> {
-        if self.frames.is_empty() || self.total_duration_ms == 0 {
-            return self.frames.first();
+        self.frames.get(self.frame_index_at(elapsed_ms)?)
+    }
+
+    /// Index of the frame at a specific time offset (loops)
+    pub fn frame_index_at(&self, elapsed_ms: u32) -> Option<usize> {
+        if self.frames.is_empty() {
+            return None;
+        }
+        if self.total_duration_ms == 0 {
+            return Some(0);
         }
 
         let looped_time = elapsed_ms % self.total_duration_ms;
         let mut accumulated = 0u32;
 
-        for frame in &self.frames {
+        for (index, frame) in self.frames.iter().enumerate() {
             accumulated += frame.delay_ms;
             if accumulated > looped_time {
-                return Some(frame);
+                return Some(index);
             }
         }
 
-        self.frames.first()
+        Some(0)
+    }
+
+    /// Take the frames out, e.g. to hand their pixels to the renderer
+    pub fn into_frames(self) -> Vec<AnimationFrame> {
+        self.frames
     }
 
     /// Get first frame (for static fallback)
@@ -172,6 +254,54 @@ mod tests {
         assert!(anim.frame_at(350).is_some());
     }
 
+    #[test]
+    fn test_animation_frame_index_at() {
+        let frames = vec![
+            AnimationFrame { data: vec![], width: 10, height: 10, delay_ms: 100 },
+            AnimationFrame { data: vec![], width: 10, height: 10, delay_ms: 50 },
+        ];
+        let anim = AnimatedImage::new(frames);
+
+        assert_eq!(anim.frame_index_at(0), Some(0));
+        assert_eq!(anim.frame_index_at(120), Some(1));
+        assert_eq!(anim.frame_index_at(160), Some(0));
+        assert_eq!(AnimatedImage::new(Vec::new()).frame_index_at(0), None);
+    }
+
+    #[test]
+    fn test_decode_gif_frames() {
+        use image::codecs::gif::GifEncoder;
+        use image::{Delay, Frame, Rgba, RgbaImage};
+
+        let mut gif = Vec::new();
+        {
+            let mut encoder = GifEncoder::new(&mut gif);
+            let frames = [Rgba([255, 0, 0, 255]), Rgba([0, 0, 255, 255])].map(|color| {
+                Frame::from_parts(
+                    RgbaImage::from_pixel(300, 150, color),
+                    0,
+                    0,
+                    Delay::from_numer_denom_ms(50, 1),
+                )
+            });
+            encoder.encode_frames(frames).unwrap();
+        }
+
+        let anim = AnimatedImage::from_data(&gif).unwrap();
+        assert_eq!(anim.frame_count(), 2);
+        assert_eq!(anim.total_duration(), Duration::from_millis(100));
+        // Scaled down while decoding
+        let frame = anim.first_frame().unwrap();
+        assert_eq!((frame.width, frame.height), (128, 64));
+
+        // Still images aren't animations
+        let mut png = Vec::new();
+        RgbaImage::new(4, 4)
+            .write_to(&mut std::io::Cursor::new(&mut png), image::ImageFormat::Png)
+            .unwrap();
+        assert!(AnimatedImage::from_data(&png).is_none());
+    }
+
     #[test]
     fn test_is_animated() {
         let single = AnimatedImage::new(vec![
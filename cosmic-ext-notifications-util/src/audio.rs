@@ -9,22 +9,685 @@
 //! - `/usr/share/sounds/**`
 //! - `/usr/local/share/sounds/**`
 //! - `$XDG_DATA_HOME/sounds/**` (or `$HOME/.local/share/sounds/**`)
+//! - Extra directories configured with [`set_extra_sound_dirs`]
 
+use std::collections::HashMap;
 use std::fs::File;
-use std::io::BufReader;
+use std::io::{BufReader, Cursor};
 use std::path::{Path, PathBuf};
-use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::atomic::{AtomicBool, AtomicU32, AtomicU64, Ordering};
+use std::sync::mpsc::{self, Receiver, RecvTimeoutError, SyncSender, TrySendError};
+use std::sync::{Arc, LazyLock, Mutex, OnceLock};
 use std::thread;
+use std::time::{Duration, Instant};
 
-use rodio::{Decoder, OutputStream, Sink};
+use rodio::{Decoder, OutputStream, OutputStreamHandle, Sample, Sink, Source};
 use tracing::{debug, error, warn};
 
+#[cfg(feature = "pipewire")]
+mod pipewire;
+
+use crate::sound_theme::{sound_base_dirs, SoundLookup, SoundThemeResolver, FALLBACK_THEME};
+
 /// Maximum number of concurrent sounds that can be played simultaneously.
-/// This prevents DoS attacks from malicious apps spawning unlimited audio threads.
+/// This prevents DoS attacks from malicious apps flooding the audio device.
 const MAX_CONCURRENT_SOUNDS: usize = 4;
 
-/// Tracks the current number of active sound playback threads.
-static ACTIVE_SOUNDS: AtomicUsize = AtomicUsize::new(0);
+/// Maximum number of sound requests waiting for the audio engine.
+/// Requests beyond this are dropped instead of piling up behind a slow device.
+const SOUND_QUEUE_SIZE: usize = 16;
+
+/// How often the engine wakes up to release finished sinks
+const SINK_REAP_INTERVAL: Duration = Duration::from_millis(250);
+
+/// How long the output stream stays open with nothing playing
+const IDLE_STREAM_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// Extra time a stopped sound gets beyond its fade-out before it is cut
+const FADE_OUT_GRACE: Duration = Duration::from_millis(100);
+
+/// How long sounds use rodio after PipeWire failed, doubled with each
+/// failure in a row
+#[cfg(feature = "pipewire")]
+const PIPEWIRE_RETRY_DELAY: Duration = Duration::from_secs(5);
+
+/// Longest wait before PipeWire is tried again
+#[cfg(feature = "pipewire")]
+const PIPEWIRE_MAX_RETRY_DELAY: Duration = Duration::from_secs(300);
+
+/// How deep to search sound directories for a sample of each format
+const FORMAT_SAMPLE_DEPTH: usize = 4;
+
+/// Decoded samples that prove a codec actually works, not just its header
+const FORMAT_PROBE_SAMPLES: usize = 1024;
+
+/// Format support on this system, probed once per process
+static FORMAT_SUPPORT: OnceLock<Vec<(SoundFormat, FormatSupport)>> = OnceLock::new();
+
+/// Cached sound theme lookups, shared by all playback requests
+static SOUND_THEME: LazyLock<Mutex<SoundThemeResolver>> =
+    LazyLock::new(|| Mutex::new(SoundThemeResolver::from_env(FALLBACK_THEME)));
+
+/// Fade-in applied to every sound, in milliseconds (see [`set_fade_durations`])
+static FADE_IN_MS: AtomicU32 = AtomicU32::new(10);
+
+/// Fade-out applied when a sound is stopped early, in milliseconds
+static FADE_OUT_MS: AtomicU32 = AtomicU32::new(50);
+
+/// Extra trusted sound directories, already canonicalized
+static EXTRA_SOUND_DIRS: LazyLock<Mutex<Vec<PathBuf>>> = LazyLock::new(Mutex::default);
+
+/// Directories that may never be trusted as sound directories, along with
+/// everything below them
+const FORBIDDEN_SOUND_DIRS: &[&str] = &["/etc", "/proc", "/sys", "/dev", "/run", "/boot", "/root"];
+
+/// Sender for the shared audio engine, started on first use
+static ENGINE: LazyLock<Option<SyncSender<Command>>> = LazyLock::new(AudioEngine::spawn);
+
+/// Messages to the audio engine thread
+enum Command {
+    Play(PlayRequest),
+    /// A playback was cancelled, wake up to stop it right away
+    Stop,
+}
+
+/// A validated sound waiting to be played by the engine
+struct PlayRequest {
+    path: PathBuf,
+    volume: f32,
+    state: Arc<PlaybackState>,
+}
+
+/// Playback progress shared between a [`PlaybackHandle`] and the engine
+#[derive(Debug, Default)]
+struct PlaybackState {
+    cancelled: AtomicBool,
+    finished: AtomicBool,
+}
+
+impl PlaybackState {
+    fn finish(&self) {
+        self.finished.store(true, Ordering::Release);
+    }
+}
+
+/// Handle to a sound queued on the audio engine
+///
+/// Dropping the handle lets the sound play to the end; call
+/// [`PlaybackHandle::stop`] to cut it off, e.g. when its notification is
+/// dismissed. All methods are non-blocking and safe to call from async code.
+#[derive(Debug, Clone)]
+pub struct PlaybackHandle {
+    state: Arc<PlaybackState>,
+    engine: Option<SyncSender<Command>>,
+}
+
+impl PlaybackHandle {
+    /// Handle for a sound that was skipped and never plays
+    fn finished() -> Self {
+        let state = PlaybackState::default();
+        state.finish();
+        Self {
+            state: Arc::new(state),
+            engine: None,
+        }
+    }
+
+    /// Stop the sound, or keep it from starting if it is still queued
+    pub fn stop(&self) {
+        if self.is_finished() || self.state.cancelled.swap(true, Ordering::AcqRel) {
+            return;
+        }
+        // If the queue is full the engine still notices on its next reap
+        if let Some(engine) = &self.engine {
+            let _ = engine.try_send(Command::Stop);
+        }
+    }
+
+    /// Whether the sound has ended, was stopped, or never started
+    pub fn is_finished(&self) -> bool {
+        self.state.finished.load(Ordering::Acquire)
+    }
+}
+
+/// Handle to the shared audio engine
+///
+/// Cheap to clone. Sounds are validated on the calling thread and played on
+/// the engine thread, so `play` never blocks on the audio device.
+#[derive(Debug, Clone)]
+pub struct AudioHandle {
+    engine: SyncSender<Command>,
+}
+
+impl AudioHandle {
+    /// Get a handle to the engine, starting it on first use
+    pub fn shared() -> Result<Self, AudioError> {
+        ENGINE
+            .clone()
+            .map(|engine| Self { engine })
+            .ok_or_else(|| AudioError::PlaybackError("audio engine is not running".to_string()))
+    }
+
+    /// Play a sound file at the given volume (0.0 = silent, 1.0 = full)
+    ///
+    /// Same limits and path validation as [`play_sound_file`]. Sounds that
+    /// are muted or dropped by the limits return an already finished handle.
+    pub fn play(&self, path: &Path, volume: f32) -> Result<PlaybackHandle, AudioError> {
+        if volume <= 0.0 {
+            debug!("Volume is zero, skipping sound {:?}", path);
+            return Ok(PlaybackHandle::finished());
+        }
+
+        if !path.exists() {
+            return Err(report_failure(path, AudioError::FileNotFound(path.to_path_buf())));
+        }
+
+        // Security: Validate path is in an allowed sound directory
+        // This prevents path traversal attacks (CWE-22)
+        //
+        // Note: There is a small TOCTOU (Time-of-Check-Time-of-Use) window between
+        // validation and file open. For sound files this is acceptable risk because:
+        // 1. Sound directories are typically system-owned with limited write access
+        // 2. Attack requires local file system access
+        // 3. Worst case is playing wrong sound, not code execution
+        // 4. The audio decoder (rodio) is memory-safe Rust
+        if !is_allowed_sound_path(path) {
+            return Err(report_failure(path, AudioError::PathNotAllowed(path.to_path_buf())));
+        }
+
+        let state = Arc::new(PlaybackState::default());
+        let request = PlayRequest {
+            path: path.to_path_buf(),
+            volume,
+            state: state.clone(),
+        };
+        match self.engine.try_send(Command::Play(request)) {
+            Ok(()) => Ok(PlaybackHandle {
+                state,
+                engine: Some(self.engine.clone()),
+            }),
+            Err(TrySendError::Full(_)) => {
+                warn!(
+                    "Sound queue full ({} pending), dropping sound request for {:?}",
+                    SOUND_QUEUE_SIZE, path
+                );
+                report(PlaybackEvent::Dropped {
+                    path: path.to_path_buf(),
+                    reason: DropReason::QueueFull,
+                });
+                Ok(PlaybackHandle::finished())
+            }
+            Err(TrySendError::Disconnected(_)) => Err(report_failure(
+                path,
+                AudioError::PlaybackError("audio engine stopped".to_string()),
+            )),
+        }
+    }
+
+    /// Play a sound from the XDG sound theme at the given volume
+    pub fn play_name(&self, name: &str, volume: f32) -> Result<PlaybackHandle, AudioError> {
+        let path = find_sound_theme_file(name)?;
+        self.play(&path, volume)
+    }
+}
+
+/// Long-lived audio engine
+///
+/// Owns a single output stream shared by all notification sounds, opened
+/// lazily and closed again after [`IDLE_STREAM_TIMEOUT`] of silence. Each
+/// sound gets a sink on that stream; at most [`MAX_CONCURRENT_SOUNDS`] sinks
+/// play at once. `OutputStream` is not `Send`, so the engine lives on its own
+/// thread and is fed through a bounded queue.
+#[derive(Default)]
+struct AudioEngine {
+    output: Option<(OutputStream, OutputStreamHandle)>,
+    sinks: Vec<ActiveSound>,
+    last_active: Option<Instant>,
+    /// While PipeWire keeps failing, rodio is used until it is tried again
+    #[cfg(feature = "pipewire")]
+    pipewire_backoff: Backoff,
+}
+
+/// Retries of an output that failed, with growing delays
+#[cfg(feature = "pipewire")]
+#[derive(Debug, Default)]
+struct Backoff {
+    /// Failures in a row
+    failures: u32,
+    retry_at: Option<Instant>,
+}
+
+#[cfg(feature = "pipewire")]
+impl Backoff {
+    /// Whether the output may be tried at `now`
+    fn ready(&self, now: Instant) -> bool {
+        self.retry_at.is_none_or(|at| now >= at)
+    }
+
+    /// The output failed at `now`
+    fn failed(&mut self, now: Instant) {
+        let delay = PIPEWIRE_RETRY_DELAY
+            .saturating_mul(1 << self.failures.min(16))
+            .min(PIPEWIRE_MAX_RETRY_DELAY);
+        self.failures = self.failures.saturating_add(1);
+        self.retry_at = Some(now + delay);
+    }
+
+    fn succeeded(&mut self) {
+        *self = Self::default();
+    }
+}
+
+/// A sound playing on the engine
+struct ActiveSound {
+    /// None for sounds playing on their own PipeWire stream
+    sink: Option<Sink>,
+    state: Arc<PlaybackState>,
+    fade_out: Duration,
+    /// When the engine first saw the sound cancelled
+    stopping_since: Option<Instant>,
+}
+
+impl ActiveSound {
+    /// Whether the sink can be released
+    ///
+    /// A cancelled sound fades itself out through [`Fade`] and ends on its
+    /// own; the sink is only cut if that takes longer than expected.
+    fn is_done(&mut self, now: Instant) -> bool {
+        let Some(sink) = &self.sink else {
+            // The PipeWire thread fades out and finishes by itself
+            return self.state.finished.load(Ordering::Acquire);
+        };
+        if sink.empty() {
+            return true;
+        }
+        if !self.state.cancelled.load(Ordering::Acquire) {
+            return false;
+        }
+        let since = *self.stopping_since.get_or_insert(now);
+        if self.fade_out.is_zero() || now.duration_since(since) >= self.fade_out + FADE_OUT_GRACE {
+            sink.stop();
+            return true;
+        }
+        false
+    }
+}
+
+impl AudioEngine {
+    /// Start the engine thread, returning the sender for play requests
+    fn spawn() -> Option<SyncSender<Command>> {
+        let (tx, rx) = mpsc::sync_channel(SOUND_QUEUE_SIZE);
+        match thread::Builder::new()
+            .name("audio-engine".into())
+            .spawn(move || AudioEngine::default().run(rx))
+        {
+            Ok(_) => Some(tx),
+            Err(e) => {
+                error!("Failed to spawn audio engine thread: {}", e);
+                None
+            }
+        }
+    }
+
+    fn run(mut self, rx: Receiver<Command>) {
+        log_format_support();
+        loop {
+            match rx.recv_timeout(SINK_REAP_INTERVAL) {
+                Ok(Command::Play(request)) => {
+                    self.reap();
+                    if let Err(e) = self.play(&request) {
+                        request.state.finish();
+                        error!("Failed to play sound file {:?}: {}", request.path, e);
+                        report_failure(&request.path, e);
+                    }
+                }
+                Ok(Command::Stop) => self.reap(),
+                Err(RecvTimeoutError::Timeout) => self.reap(),
+                Err(RecvTimeoutError::Disconnected) => break,
+            }
+        }
+    }
+
+    /// Drop finished or cancelled sinks and close the stream once it has been idle
+    fn reap(&mut self) {
+        let now = Instant::now();
+        self.sinks.retain_mut(|sound| {
+            let done = sound.is_done(now);
+            if done {
+                sound.state.finish();
+            }
+            !done
+        });
+        if !self.sinks.is_empty() {
+            self.last_active = Some(Instant::now());
+        } else if self.output.is_some()
+            && self
+                .last_active
+                .is_none_or(|t| t.elapsed() >= IDLE_STREAM_TIMEOUT)
+        {
+            debug!("Closing idle audio output stream");
+            self.output = None;
+        }
+    }
+
+    fn play(&mut self, request: &PlayRequest) -> Result<(), AudioError> {
+        if request.state.cancelled.load(Ordering::Acquire) {
+            debug!("Sound {:?} was cancelled before it started", request.path);
+            request.state.finish();
+            report(PlaybackEvent::Dropped {
+                path: request.path.clone(),
+                reason: DropReason::Cancelled,
+            });
+            return Ok(());
+        }
+
+        if self.sinks.len() >= MAX_CONCURRENT_SOUNDS {
+            warn!(
+                "Maximum concurrent sounds ({}) reached, dropping sound request for {:?}",
+                MAX_CONCURRENT_SOUNDS, request.path
+            );
+            request.state.finish();
+            report(PlaybackEvent::Dropped {
+                path: request.path.clone(),
+                reason: DropReason::ConcurrencyLimit,
+            });
+            return Ok(());
+        }
+
+        let fade_in = Duration::from_millis(FADE_IN_MS.load(Ordering::Relaxed).into());
+        let fade_out = Duration::from_millis(FADE_OUT_MS.load(Ordering::Relaxed).into());
+
+        #[cfg(feature = "pipewire")]
+        if self.pipewire_backoff.ready(Instant::now()) {
+            let source = Fade::new(decode(&request.path)?, fade_in, fade_out, request.state.clone());
+            match pipewire::play(source, request.volume, fade_out, request.state.clone()) {
+                Ok(()) => {
+                    self.pipewire_backoff.succeeded();
+                    self.sinks.push(ActiveSound {
+                        sink: None,
+                        state: request.state.clone(),
+                        fade_out,
+                        stopping_since: None,
+                    });
+                    self.last_active = Some(Instant::now());
+                    report(PlaybackEvent::Started {
+                        path: request.path.clone(),
+                    });
+                    return Ok(());
+                }
+                Err(e) => {
+                    warn!("PipeWire output unavailable, falling back to ALSA for now: {}", e);
+                    self.pipewire_backoff.failed(Instant::now());
+                }
+            }
+        }
+
+        let source = decode(&request.path)?;
+        let sink = match Sink::try_new(self.handle()?) {
+            Ok(sink) => sink,
+            Err(e) => {
+                // The device may have gone away, reopen it on the next sound
+                self.output = None;
+                return Err(AudioError::PlaybackError(e.to_string()));
+            }
+        };
+        sink.set_volume(request.volume.clamp(0.0, 1.0));
+        sink.append(Fade::new(source, fade_in, fade_out, request.state.clone()));
+        self.sinks.push(ActiveSound {
+            sink: Some(sink),
+            state: request.state.clone(),
+            fade_out,
+            stopping_since: None,
+        });
+        self.last_active = Some(Instant::now());
+        report(PlaybackEvent::Started {
+            path: request.path.clone(),
+        });
+
+        Ok(())
+    }
+
+    /// Handle to the shared output stream, opening it if needed
+    fn handle(&mut self) -> Result<&OutputStreamHandle, AudioError> {
+        if self.output.is_none() {
+            self.output = Some(OutputStream::try_default().map_err(|_| AudioError::NoAudioDevice)?);
+        }
+        Ok(&self.output.as_ref().expect("output stream just opened").1)
+    }
+}
+
+/// Capacity of each [`playback_events`] channel; events beyond it are dropped
+const PLAYBACK_EVENT_QUEUE: usize = 64;
+
+/// Listeners registered through [`playback_events`]
+static PLAYBACK_LISTENERS: Mutex<Vec<SyncSender<PlaybackEvent>>> = Mutex::new(Vec::new());
+
+static SOUNDS_STARTED: AtomicU64 = AtomicU64::new(0);
+static SOUNDS_DROPPED: AtomicU64 = AtomicU64::new(0);
+static SOUNDS_FAILED: AtomicU64 = AtomicU64::new(0);
+
+/// Outcome of a playback request
+#[derive(Debug, Clone)]
+pub enum PlaybackEvent {
+    /// The sound started playing
+    Started { path: PathBuf },
+    /// The sound was skipped without an error
+    Dropped { path: PathBuf, reason: DropReason },
+    /// The sound could not be played
+    Failed { path: PathBuf, error: AudioError },
+}
+
+/// Why a sound was skipped
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DropReason {
+    /// [`MAX_CONCURRENT_SOUNDS`] were already playing
+    ConcurrencyLimit,
+    /// The engine had [`SOUND_QUEUE_SIZE`] requests pending
+    QueueFull,
+    /// Stopped before the engine got to it
+    Cancelled,
+}
+
+/// Playback outcomes counted since the process started
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct PlaybackStats {
+    pub started: u64,
+    pub dropped: u64,
+    pub failed: u64,
+}
+
+/// Snapshot of the playback counters
+pub fn playback_stats() -> PlaybackStats {
+    PlaybackStats {
+        started: SOUNDS_STARTED.load(Ordering::Relaxed),
+        dropped: SOUNDS_DROPPED.load(Ordering::Relaxed),
+        failed: SOUNDS_FAILED.load(Ordering::Relaxed),
+    }
+}
+
+/// Receive an event for every playback request from now on
+///
+/// The channel is bounded; if the receiver falls behind, events are
+/// dropped rather than blocking the audio engine. Counters are kept
+/// regardless. The listener is removed once the receiver is dropped.
+pub fn playback_events() -> Receiver<PlaybackEvent> {
+    let (tx, rx) = mpsc::sync_channel(PLAYBACK_EVENT_QUEUE);
+    if let Ok(mut listeners) = PLAYBACK_LISTENERS.lock() {
+        listeners.push(tx);
+    }
+    rx
+}
+
+/// Count an outcome and pass it on to listeners
+fn report(event: PlaybackEvent) {
+    let counter = match &event {
+        PlaybackEvent::Started { .. } => &SOUNDS_STARTED,
+        PlaybackEvent::Dropped { .. } => &SOUNDS_DROPPED,
+        PlaybackEvent::Failed { .. } => &SOUNDS_FAILED,
+    };
+    counter.fetch_add(1, Ordering::Relaxed);
+
+    if let Ok(mut listeners) = PLAYBACK_LISTENERS.lock() {
+        listeners.retain(|tx| !matches!(tx.try_send(event.clone()), Err(TrySendError::Disconnected(_))));
+    }
+}
+
+/// Report a failed playback, returning the error for the caller
+fn report_failure(path: &Path, error: AudioError) -> AudioError {
+    report(PlaybackEvent::Failed {
+        path: path.to_path_buf(),
+        error: error.clone(),
+    });
+    error
+}
+
+/// Open and decode a sound file
+fn decode(path: &Path) -> Result<Decoder<BufReader<File>>, AudioError> {
+    let file = File::open(path).map_err(|e| AudioError::IoError(e.to_string()))?;
+    Decoder::new(BufReader::new(file)).map_err(|e| AudioError::DecodeError(e.to_string()))
+}
+
+/// Set the fade-in applied to every sound and the fade-out applied when a
+/// sound is stopped before it ends
+///
+/// Ramping the volume instead of jumping avoids audible clicks. Zero
+/// disables the respective fade. Applies to sounds started afterwards.
+pub fn set_fade_durations(fade_in: Duration, fade_out: Duration) {
+    let ms = |d: Duration| u32::try_from(d.as_millis()).unwrap_or(u32::MAX);
+    FADE_IN_MS.store(ms(fade_in), Ordering::Relaxed);
+    FADE_OUT_MS.store(ms(fade_out), Ordering::Relaxed);
+}
+
+/// Source wrapper applying a linear fade-in, and a fade-out once its
+/// playback is cancelled
+struct Fade<S> {
+    inner: S,
+    /// Fade lengths in interleaved samples
+    fade_in: u64,
+    fade_out: u64,
+    position: u64,
+    fade_out_start: Option<u64>,
+    state: Arc<PlaybackState>,
+}
+
+impl<S: Source> Fade<S>
+where
+    S::Item: Sample,
+{
+    fn new(inner: S, fade_in: Duration, fade_out: Duration, state: Arc<PlaybackState>) -> Self {
+        let rate = u64::from(inner.sample_rate()) * u64::from(inner.channels());
+        let samples = |d: Duration| {
+            u64::try_from(d.as_millis())
+                .unwrap_or(u64::MAX)
+                .saturating_mul(rate)
+                / 1000
+        };
+        Self {
+            fade_in: samples(fade_in),
+            fade_out: samples(fade_out),
+            inner,
+            position: 0,
+            fade_out_start: None,
+            state,
+        }
+    }
+
+    /// Gain for the current position, None once the fade-out has finished
+    fn gain(&mut self) -> Option<f32> {
+        let mut gain = 1.0;
+        if self.position < self.fade_in {
+            gain = self.position as f32 / self.fade_in as f32;
+        }
+        if self.state.cancelled.load(Ordering::Relaxed) {
+            let start = *self.fade_out_start.get_or_insert(self.position);
+            let elapsed = self.position - start;
+            if elapsed >= self.fade_out {
+                return None;
+            }
+            gain *= 1.0 - elapsed as f32 / self.fade_out as f32;
+        }
+        Some(gain)
+    }
+}
+
+impl<S: Source> Iterator for Fade<S>
+where
+    S::Item: Sample,
+{
+    type Item = S::Item;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let gain = self.gain()?;
+        let sample = self.inner.next()?;
+        self.position += 1;
+        Some(sample.amplify(gain))
+    }
+}
+
+impl<S: Source> Source for Fade<S>
+where
+    S::Item: Sample,
+{
+    fn current_frame_len(&self) -> Option<usize> {
+        self.inner.current_frame_len()
+    }
+
+    fn channels(&self) -> u16 {
+        self.inner.channels()
+    }
+
+    fn sample_rate(&self) -> u32 {
+        self.inner.sample_rate()
+    }
+
+    fn total_duration(&self) -> Option<Duration> {
+        self.inner.total_duration()
+    }
+}
+
+/// Entries older than this are forgotten by [`SoundDebouncer`]
+const DEBOUNCE_RETENTION: Duration = Duration::from_secs(60);
+
+/// Drops repeats of the same sound that arrive in quick succession
+///
+/// A burst of ten identical chat notifications should ding once, not ten
+/// times. Sounds are identified by a caller-chosen key, typically the app
+/// and the sound it asked for.
+#[derive(Debug, Default)]
+pub struct SoundDebouncer {
+    last_played: HashMap<String, Instant>,
+}
+
+impl SoundDebouncer {
+    /// Whether a sound with `key` should play, recording it if so
+    ///
+    /// Returns false if the same key played less than `window` ago. A zero
+    /// window never debounces.
+    pub fn should_play(&mut self, key: &str, window: Duration) -> bool {
+        self.should_play_at(key, window, Instant::now())
+    }
+
+    fn should_play_at(&mut self, key: &str, window: Duration, now: Instant) -> bool {
+        self.last_played
+            .retain(|_, at| now.duration_since(*at) < DEBOUNCE_RETENTION.max(window));
+
+        if let Some(at) = self.last_played.get(key) {
+            if now.duration_since(*at) < window {
+                debug!("Debounced repeated sound {:?}", key);
+                return false;
+            }
+        }
+        self.last_played.insert(key.to_string(), now);
+        true
+    }
+}
+
+/// Convert a volume percentage (0-100) to a rodio volume factor
+///
+/// Values above 100 are clamped so a misconfigured level can't amplify.
+pub fn volume_from_percent(percent: u8) -> f32 {
+    f32::from(percent.min(100)) / 100.0
+}
 
 /// Check if a sound file path is in an allowed directory.
 ///
@@ -38,6 +701,7 @@ static ACTIVE_SOUNDS: AtomicUsize = AtomicUsize::new(0);
 /// - `/usr/local/share/sounds/**`
 /// - `$XDG_DATA_HOME/sounds/**`
 /// - `$HOME/.local/share/sounds/**`
+/// - Directories set with [`set_extra_sound_dirs`]
 ///
 /// # Security notes
 ///
@@ -85,6 +749,12 @@ fn is_allowed_sound_path(path: &Path) -> bool {
         }
     }
 
+    if let Ok(extra) = EXTRA_SOUND_DIRS.lock() {
+        if extra.iter().any(|dir| canonical.starts_with(dir)) {
+            return true;
+        }
+    }
+
     warn!(
         "Sound file path {:?} (canonical: {:?}) is not in an allowed directory",
         path, canonical
@@ -92,166 +762,317 @@ fn is_allowed_sound_path(path: &Path) -> bool {
     false
 }
 
+/// Check that a directory is safe to trust for sound files
+///
+/// Returns the canonical path. The directory must be absolute, exist, and
+/// be specific enough: `/`, top-level directories like `/opt`, the home
+/// directory itself and system directories such as `/etc` are rejected.
+pub fn validate_sound_dir(dir: &Path) -> Result<PathBuf, String> {
+    if !dir.is_absolute() {
+        return Err("path is not absolute".to_string());
+    }
+    let canonical = dir.canonicalize().map_err(|e| e.to_string())?;
+    if !canonical.is_dir() {
+        return Err("not a directory".to_string());
+    }
+    if canonical.components().count() < 3 {
+        return Err("too broad, use a more specific directory".to_string());
+    }
+    if FORBIDDEN_SOUND_DIRS
+        .iter()
+        .any(|forbidden| canonical.starts_with(forbidden))
+    {
+        return Err("system directory".to_string());
+    }
+    let home = std::env::var_os("HOME").and_then(|h| PathBuf::from(h).canonicalize().ok());
+    if home.is_some_and(|home| canonical == home) {
+        return Err("too broad, use a more specific directory".to_string());
+    }
+    Ok(canonical)
+}
+
+/// Trust sound files below these directories in addition to the standard
+/// sound directories
+///
+/// Each directory is checked with [`validate_sound_dir`]; rejected ones are
+/// logged and skipped. Replaces any previously configured directories.
+pub fn set_extra_sound_dirs<P: AsRef<Path>>(dirs: &[P]) {
+    let valid = dirs
+        .iter()
+        .filter_map(|dir| {
+            let dir = dir.as_ref();
+            validate_sound_dir(dir)
+                .inspect_err(|e| warn!("Ignoring extra sound directory {:?}: {}", dir, e))
+                .ok()
+        })
+        .collect();
+    if let Ok(mut extra) = EXTRA_SOUND_DIRS.lock() {
+        *extra = valid;
+    }
+}
+
 /// Play a sound file
 ///
 /// Supports common audio formats: WAV, OGG, MP3, FLAC
-/// Sound is played in a background thread to avoid blocking.
+/// Sound is queued to the shared audio engine to avoid blocking.
 ///
-/// To prevent resource exhaustion from malicious apps, this function limits
-/// the number of concurrent sound playbacks to [`MAX_CONCURRENT_SOUNDS`].
-/// If the limit is reached, the sound request is silently dropped.
+/// To prevent resource exhaustion from malicious apps, the engine plays at
+/// most [`MAX_CONCURRENT_SOUNDS`] sounds at once and queues at most
+/// [`SOUND_QUEUE_SIZE`] requests. Excess requests are silently dropped.
 pub fn play_sound_file(path: &Path) -> Result<(), AudioError> {
-    if !path.exists() {
-        return Err(AudioError::FileNotFound(path.to_path_buf()));
-    }
-
-    // Security: Validate path is in an allowed sound directory
-    // This prevents path traversal attacks (CWE-22)
-    //
-    // Note: There is a small TOCTOU (Time-of-Check-Time-of-Use) window between
-    // validation and file open. For sound files this is acceptable risk because:
-    // 1. Sound directories are typically system-owned with limited write access
-    // 2. Attack requires local file system access
-    // 3. Worst case is playing wrong sound, not code execution
-    // 4. The audio decoder (rodio) is memory-safe Rust
-    if !is_allowed_sound_path(path) {
-        return Err(AudioError::PathNotAllowed(path.to_path_buf()));
-    }
-
-    // Atomically check and increment the active sound counter
-    // Using compare_exchange prevents race condition where multiple threads
-    // could pass the limit check simultaneously
-    loop {
-        let current = ACTIVE_SOUNDS.load(Ordering::SeqCst);
-        if current >= MAX_CONCURRENT_SOUNDS {
-            warn!(
-                "Maximum concurrent sounds ({}) reached, dropping sound request for {:?}",
-                MAX_CONCURRENT_SOUNDS, path
-            );
-            return Ok(());
-        }
+    play_sound_file_with_volume(path, 1.0)
+}
 
-        // Try to atomically increment if counter hasn't changed
-        match ACTIVE_SOUNDS.compare_exchange(
-            current,
-            current + 1,
-            Ordering::SeqCst,
-            Ordering::SeqCst,
-        ) {
-            Ok(_) => break, // Successfully incremented, proceed to spawn thread
-            Err(_) => continue, // Counter changed, retry the check
-        }
-    }
+/// Play a sound file at the given volume (0.0 = silent, 1.0 = full)
+///
+/// Same limits and path validation as [`play_sound_file`]. Use
+/// [`AudioHandle::play`] to be able to stop the sound later.
+pub fn play_sound_file_with_volume(path: &Path, volume: f32) -> Result<(), AudioError> {
+    AudioHandle::shared()?.play(path, volume).map(drop)
+}
 
-    let path = path.to_path_buf();
+/// Play a sound from the XDG sound theme
+///
+/// Looks up the sound name in the configured freedesktop.org sound theme
+/// (see [`set_sound_theme`]), following its `Inherits` chain.
+/// Common sound names: "message-new-instant", "bell", "dialog-warning"
+pub fn play_sound_name(name: &str) -> Result<(), AudioError> {
+    play_sound_name_with_volume(name, 1.0)
+}
 
-    // Spawn a thread to play the sound so we don't block
-    let spawn_result = thread::Builder::new()
-        .name("audio-playback".into())
-        .spawn(move || {
-            let result = play_sound_file_blocking(&path);
+/// Play a sound from the XDG sound theme at the given volume
+pub fn play_sound_name_with_volume(name: &str, volume: f32) -> Result<(), AudioError> {
+    // Look up the sound file in XDG sound theme directories
+    let sound_path = find_sound_theme_file(name)?;
+    play_sound_file_with_volume(&sound_path, volume)
+}
 
-            // Always decrement the counter when done, even on error
-            ACTIVE_SOUNDS.fetch_sub(1, Ordering::SeqCst);
+/// Resolve a theme sound and decode its header without playing it
+///
+/// Checks the same path rules as playback, so a success means
+/// [`play_sound_name`] would find, accept and decode the file. Used as an
+/// audio-device-free backend by the daemon's self-test.
+pub fn check_sound_name(name: &str) -> Result<PathBuf, AudioError> {
+    let path = find_sound_theme_file(name)?;
+    if !is_allowed_sound_path(&path) {
+        return Err(AudioError::PathNotAllowed(path));
+    }
+    let file = File::open(&path).map_err(|e| AudioError::IoError(e.to_string()))?;
+    Decoder::new(BufReader::new(file)).map_err(|e| AudioError::DecodeError(e.to_string()))?;
+    Ok(path)
+}
 
-            if let Err(e) = result {
-                error!("Failed to play sound file {:?}: {}", path, e);
-            }
-        });
+/// Set the sound theme used to resolve sound names
+///
+/// Falls back to the `freedesktop` theme for sounds the theme lacks. An
+/// empty name selects `freedesktop` directly.
+pub fn set_sound_theme(theme: &str) {
+    let theme = match theme.trim() {
+        "" => FALLBACK_THEME,
+        theme => theme,
+    };
+    if let Ok(mut resolver) = SOUND_THEME.lock() {
+        resolver.set_theme(theme);
+    }
+}
+
+/// Find a sound file from the XDG sound theme
+fn find_sound_theme_file(name: &str) -> Result<PathBuf, AudioError> {
+    let lookup = SOUND_THEME
+        .lock()
+        .map_err(|_| AudioError::PlaybackError("sound theme resolver poisoned".to_string()))?
+        .lookup(name);
 
-    // Handle spawn failure - must decrement counter if thread creation failed
-    if let Err(e) = spawn_result {
-        ACTIVE_SOUNDS.fetch_sub(1, Ordering::SeqCst);
-        warn!("Failed to spawn audio thread: {}", e);
+    match lookup {
+        Some(SoundLookup::Found(path)) => {
+            debug!("Found sound theme file: {:?}", path);
+            Ok(path)
+        }
+        Some(SoundLookup::Disabled) => Err(AudioError::SoundDisabled(name.to_string())),
+        None => Err(AudioError::SoundNotFound(name.to_string())),
     }
+}
 
-    Ok(())
+/// Get the `sounds/` directories searched for sound themes
+fn get_sound_theme_dirs() -> Vec<PathBuf> {
+    sound_base_dirs()
 }
 
-/// Play a sound file (blocking)
-fn play_sound_file_blocking(path: &Path) -> Result<(), AudioError> {
-    // Create a new output stream for this playback
-    let (_stream, handle) = OutputStream::try_default()
-        .map_err(|_| AudioError::NoAudioDevice)?;
+/// Audio file formats notification sounds commonly come in
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum SoundFormat {
+    Wav,
+    Ogg,
+    Oga,
+    Flac,
+    Mp3,
+    Opus,
+}
 
-    let file = File::open(path).map_err(|e| AudioError::IoError(e.to_string()))?;
-    let reader = BufReader::new(file);
+impl SoundFormat {
+    pub const ALL: [SoundFormat; 6] = [
+        SoundFormat::Wav,
+        SoundFormat::Ogg,
+        SoundFormat::Oga,
+        SoundFormat::Flac,
+        SoundFormat::Mp3,
+        SoundFormat::Opus,
+    ];
 
-    let source = Decoder::new(reader).map_err(|e| AudioError::DecodeError(e.to_string()))?;
+    /// File extension without the dot
+    pub fn extension(self) -> &'static str {
+        match self {
+            SoundFormat::Wav => "wav",
+            SoundFormat::Ogg => "ogg",
+            SoundFormat::Oga => "oga",
+            SoundFormat::Flac => "flac",
+            SoundFormat::Mp3 => "mp3",
+            SoundFormat::Opus => "opus",
+        }
+    }
 
-    let sink = Sink::try_new(&handle).map_err(|e| AudioError::PlaybackError(e.to_string()))?;
-    sink.append(source);
-    sink.sleep_until_end();
+    /// Format of a file, judged by its extension
+    pub fn from_path(path: &Path) -> Option<Self> {
+        let ext = path.extension()?.to_str()?.to_ascii_lowercase();
+        Self::ALL.into_iter().find(|f| f.extension() == ext)
+    }
+}
 
-    Ok(())
+/// Whether a sound format decodes on this system
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum FormatSupport {
+    /// A sample file decoded successfully
+    Supported(PathBuf),
+    /// A sample file exists but could not be decoded
+    Unsupported { path: PathBuf, error: String },
+    /// No installed sound uses this format, so it could not be tested
+    Untested,
 }
 
-/// Play a sound from the XDG sound theme
+/// Decode the first samples of a sound file without playing it
 ///
-/// Looks up the sound name in the freedesktop.org sound theme.
-/// Common sound names: "message-new-instant", "bell", "dialog-warning"
-pub fn play_sound_name(name: &str) -> Result<(), AudioError> {
-    // Look up the sound file in XDG sound theme directories
-    let sound_path = find_sound_theme_file(name)?;
-    play_sound_file(&sound_path)
+/// Catches files whose header parses but whose codec is missing, which
+/// [`check_sound_name`] alone would let through.
+pub fn probe_sound_file(path: &Path) -> Result<(), AudioError> {
+    let file = File::open(path).map_err(|e| AudioError::IoError(e.to_string()))?;
+    probe_source(BufReader::new(file))
 }
 
-/// Find a sound file from the XDG sound theme
-fn find_sound_theme_file(name: &str) -> Result<PathBuf, AudioError> {
-    // XDG sound theme directories
-    let search_dirs = get_sound_theme_dirs();
+fn probe_source<R>(reader: R) -> Result<(), AudioError>
+where
+    R: std::io::Read + std::io::Seek + Send + Sync + 'static,
+{
+    let decoder = Decoder::new(reader).map_err(|e| AudioError::DecodeError(e.to_string()))?;
+    if decoder.take(FORMAT_PROBE_SAMPLES).count() == 0 {
+        return Err(AudioError::DecodeError("no audio samples decoded".to_string()));
+    }
+    Ok(())
+}
 
-    // Common extensions for sound files
-    let extensions = ["oga", "ogg", "wav", "mp3"];
+/// Which sound formats decode on this system
+///
+/// Each format is tested against the first installed sound that uses it.
+/// WAV is tested against a generated clip when no sample is installed, since
+/// it needs no codec. The result is computed once and cached.
+pub fn format_support() -> &'static [(SoundFormat, FormatSupport)] {
+    FORMAT_SUPPORT.get_or_init(|| probe_formats(&get_sound_theme_dirs()))
+}
 
-    for dir in &search_dirs {
-        for ext in &extensions {
-            let path = dir.join(format!("{}.{}", name, ext));
-            if path.exists() {
-                debug!("Found sound theme file: {:?}", path);
-                return Ok(path);
-            }
+fn probe_formats(dirs: &[PathBuf]) -> Vec<(SoundFormat, FormatSupport)> {
+    SoundFormat::ALL
+        .into_iter()
+        .map(|format| {
+            let support = match find_format_sample(dirs, format) {
+                Some(path) => match probe_sound_file(&path) {
+                    Ok(()) => FormatSupport::Supported(path),
+                    Err(e) => FormatSupport::Unsupported {
+                        path,
+                        error: e.to_string(),
+                    },
+                },
+                None if format == SoundFormat::Wav => {
+                    let path = PathBuf::from("<generated>");
+                    match probe_source(Cursor::new(silent_wav())) {
+                        Ok(()) => FormatSupport::Supported(path),
+                        Err(e) => FormatSupport::Unsupported {
+                            path,
+                            error: e.to_string(),
+                        },
+                    }
+                }
+                None => FormatSupport::Untested,
+            };
+            (format, support)
+        })
+        .collect()
+}
 
-            // Also check stereo subdirectory
-            let stereo_path = dir.join("stereo").join(format!("{}.{}", name, ext));
-            if stereo_path.exists() {
-                debug!("Found sound theme file: {:?}", stereo_path);
-                return Ok(stereo_path);
-            }
+/// First file with the format's extension under `dirs`, in a stable order
+fn find_format_sample(dirs: &[PathBuf], format: SoundFormat) -> Option<PathBuf> {
+    fn walk(dir: &Path, format: SoundFormat, depth: usize) -> Option<PathBuf> {
+        let mut entries: Vec<PathBuf> = std::fs::read_dir(dir)
+            .ok()?
+            .filter_map(|e| e.ok().map(|e| e.path()))
+            .collect();
+        entries.sort();
+        if let Some(file) = entries
+            .iter()
+            .find(|p| p.is_file() && SoundFormat::from_path(p) == Some(format))
+        {
+            return Some(file.clone());
+        }
+        if depth == 0 {
+            return None;
         }
+        entries
+            .iter()
+            .filter(|p| p.is_dir())
+            .find_map(|p| walk(p, format, depth - 1))
     }
 
-    Err(AudioError::SoundNotFound(name.to_string()))
+    dirs.iter().find_map(|dir| walk(dir, format, FORMAT_SAMPLE_DEPTH))
 }
 
-/// Get XDG sound theme directories
-fn get_sound_theme_dirs() -> Vec<PathBuf> {
-    let mut dirs = Vec::new();
+/// A short 16-bit mono PCM WAV of silence
+fn silent_wav() -> Vec<u8> {
+    const SAMPLE_RATE: u32 = 8000;
+    let data_len = (FORMAT_PROBE_SAMPLES * 2 * 2) as u32;
 
-    // User sound themes
-    if let Some(data_home) = std::env::var_os("XDG_DATA_HOME") {
-        dirs.push(PathBuf::from(&data_home).join("sounds/freedesktop/stereo"));
-        dirs.push(PathBuf::from(data_home).join("sounds"));
-    } else if let Some(home) = std::env::var_os("HOME") {
-        dirs.push(PathBuf::from(&home).join(".local/share/sounds/freedesktop/stereo"));
-        dirs.push(PathBuf::from(home).join(".local/share/sounds"));
-    }
-
-    // System sound themes
-    let system_dirs = [
-        "/usr/share/sounds/freedesktop/stereo",
-        "/usr/share/sounds/freedesktop",
-        "/usr/share/sounds",
-        "/usr/local/share/sounds/freedesktop/stereo",
-        "/usr/local/share/sounds/freedesktop",
-        "/usr/local/share/sounds",
-    ];
+    let mut wav = Vec::with_capacity(44 + data_len as usize);
+    wav.extend_from_slice(b"RIFF");
+    wav.extend_from_slice(&(36 + data_len).to_le_bytes());
+    wav.extend_from_slice(b"WAVEfmt ");
+    wav.extend_from_slice(&16u32.to_le_bytes()); // fmt chunk size
+    wav.extend_from_slice(&1u16.to_le_bytes()); // PCM
+    wav.extend_from_slice(&1u16.to_le_bytes()); // mono
+    wav.extend_from_slice(&SAMPLE_RATE.to_le_bytes());
+    wav.extend_from_slice(&(SAMPLE_RATE * 2).to_le_bytes()); // byte rate
+    wav.extend_from_slice(&2u16.to_le_bytes()); // block align
+    wav.extend_from_slice(&16u16.to_le_bytes()); // bits per sample
+    wav.extend_from_slice(b"data");
+    wav.extend_from_slice(&data_len.to_le_bytes());
+    wav.resize(44 + data_len as usize, 0);
+    wav
+}
 
-    for dir in &system_dirs {
-        dirs.push(PathBuf::from(dir));
+/// Warn once about formats that installed sounds use but can't be decoded
+fn log_format_support() {
+    for (format, support) in format_support() {
+        match support {
+            FormatSupport::Supported(path) => {
+                debug!("Sound format {} decodes ({:?})", format.extension(), path)
+            }
+            FormatSupport::Unsupported { path, error } => warn!(
+                "Sound format {} cannot be decoded, sounds like {:?} will not play: {}",
+                format.extension(),
+                path,
+                error
+            ),
+            FormatSupport::Untested => {
+                debug!("No installed sound uses format {}", format.extension())
+            }
+        }
     }
-
-    dirs
 }
 
 /// Audio playback errors
@@ -263,6 +1084,8 @@ pub enum AudioError {
     FileNotFound(PathBuf),
     /// Sound theme entry not found
     SoundNotFound(String),
+    /// Sound theme explicitly disables this sound
+    SoundDisabled(String),
     /// Sound file path is not in an allowed directory (security violation)
     PathNotAllowed(PathBuf),
     /// IO error reading file
@@ -281,6 +1104,9 @@ impl std::fmt::Display for AudioError {
             AudioError::SoundNotFound(name) => {
                 write!(f, "Sound '{}' not found in theme", name)
             }
+            AudioError::SoundDisabled(name) => {
+                write!(f, "Sound '{}' is disabled by the theme", name)
+            }
             AudioError::PathNotAllowed(path) => {
                 write!(f, "Sound file path not in allowed directory: {:?}", path)
             }
@@ -297,12 +1123,198 @@ impl std::error::Error for AudioError {}
 mod tests {
     use super::*;
 
+    #[cfg(feature = "pipewire")]
+    #[test]
+    fn test_pipewire_retried_with_backoff() {
+        let now = Instant::now();
+        let mut backoff = Backoff::default();
+        assert!(backoff.ready(now));
+
+        backoff.failed(now);
+        assert!(!backoff.ready(now));
+        assert!(backoff.ready(now + PIPEWIRE_RETRY_DELAY));
+
+        backoff.failed(now);
+        assert!(!backoff.ready(now + PIPEWIRE_RETRY_DELAY));
+        assert!(backoff.ready(now + PIPEWIRE_RETRY_DELAY * 2));
+
+        for _ in 0..40 {
+            backoff.failed(now);
+        }
+        assert!(backoff.ready(now + PIPEWIRE_MAX_RETRY_DELAY));
+
+        backoff.succeeded();
+        assert!(backoff.ready(now));
+    }
+
     #[test]
     fn test_get_sound_theme_dirs() {
         let dirs = get_sound_theme_dirs();
         assert!(!dirs.is_empty());
     }
 
+    #[test]
+    fn test_volume_from_percent() {
+        assert_eq!(volume_from_percent(0), 0.0);
+        assert_eq!(volume_from_percent(50), 0.5);
+        assert_eq!(volume_from_percent(100), 1.0);
+        assert_eq!(volume_from_percent(255), 1.0);
+    }
+
+    #[test]
+    fn test_zero_volume_skips_playback() {
+        // Muted playback succeeds without touching the file or audio device
+        let path = Path::new("/nonexistent/path/to/sound.wav");
+        assert!(play_sound_file_with_volume(path, 0.0).is_ok());
+    }
+
+    #[test]
+    fn test_engine_reports_unreadable_file() {
+        // File errors surface before the output device is touched
+        let mut engine = AudioEngine::default();
+        let request = PlayRequest {
+            path: PathBuf::from("/nonexistent/path/to/sound.wav"),
+            volume: 1.0,
+            state: Arc::default(),
+        };
+        assert!(matches!(engine.play(&request), Err(AudioError::IoError(_))));
+        assert!(engine.output.is_none());
+    }
+
+    #[test]
+    fn test_cancelled_request_never_starts() {
+        let mut engine = AudioEngine::default();
+        let state = Arc::new(PlaybackState::default());
+        let handle = PlaybackHandle {
+            state: state.clone(),
+            engine: None,
+        };
+        handle.stop();
+
+        let request = PlayRequest {
+            path: PathBuf::from("/nonexistent/path/to/sound.wav"),
+            volume: 1.0,
+            state,
+        };
+        assert!(engine.play(&request).is_ok());
+        assert!(handle.is_finished());
+        assert!(engine.sinks.is_empty());
+        assert!(engine.output.is_none());
+    }
+
+    #[test]
+    fn test_playback_events_report_failures() {
+        let events = playback_events();
+        let before = playback_stats();
+
+        let path = Path::new("/nonexistent/path/to/event.wav");
+        assert!(AudioHandle::shared().unwrap().play(path, 1.0).is_err());
+
+        // Other tests may report concurrently, look for ours
+        let failed = events.try_iter().any(|event| {
+            matches!(event, PlaybackEvent::Failed { path: p, error: AudioError::FileNotFound(_) } if p == path)
+        });
+        assert!(failed);
+        assert!(playback_stats().failed > before.failed);
+    }
+
+    #[test]
+    fn test_fade_envelope() {
+        // 1 kHz mono source of full-scale samples
+        let source = rodio::buffer::SamplesBuffer::new(1, 1000, vec![1.0f32; 1000]);
+        let state = Arc::new(PlaybackState::default());
+        let mut fade = Fade::new(
+            source,
+            Duration::from_millis(10),
+            Duration::from_millis(20),
+            state.clone(),
+        );
+
+        // Ramps up over the first 10 samples
+        let start: Vec<f32> = fade.by_ref().take(11).collect();
+        assert_eq!(start[0], 0.0);
+        assert!(start[5] > 0.4 && start[5] < 0.6);
+        assert_eq!(start[10], 1.0);
+
+        // Stopping ramps down over 20 samples instead of cutting off
+        state.cancelled.store(true, Ordering::Release);
+        let tail: Vec<f32> = fade.collect();
+        assert_eq!(tail.len(), 20);
+        assert_eq!(tail[0], 1.0);
+        assert!(tail.windows(2).all(|w| w[1] < w[0]));
+    }
+
+    #[test]
+    fn test_muted_playback_returns_finished_handle() {
+        let handle = AudioHandle::shared()
+            .unwrap()
+            .play(Path::new("/nonexistent/path/to/sound.wav"), 0.0)
+            .unwrap();
+        assert!(handle.is_finished());
+        // Stopping a finished sound is a no-op
+        handle.stop();
+    }
+
+    #[test]
+    fn test_sound_debouncer() {
+        let mut debouncer = SoundDebouncer::default();
+        let start = Instant::now();
+        let window = Duration::from_secs(1);
+        let at = |ms| start + Duration::from_millis(ms);
+
+        // A burst within the window plays once
+        assert!(debouncer.should_play_at("chat/message", window, at(0)));
+        for ms in (100..1000).step_by(100) {
+            assert!(!debouncer.should_play_at("chat/message", window, at(ms)));
+        }
+        // Other sounds are independent
+        assert!(debouncer.should_play_at("mail/message", window, at(500)));
+        // Repeats count from the last sound that played
+        assert!(debouncer.should_play_at("chat/message", window, at(1000)));
+        // Zero window disables debouncing
+        assert!(debouncer.should_play_at("chat/message", Duration::ZERO, at(1001)));
+    }
+
+    #[test]
+    fn test_sound_format_from_path() {
+        assert_eq!(SoundFormat::from_path(Path::new("a/bell.OGA")), Some(SoundFormat::Oga));
+        assert_eq!(SoundFormat::from_path(Path::new("ding.opus")), Some(SoundFormat::Opus));
+        assert_eq!(SoundFormat::from_path(Path::new("index.theme")), None);
+        assert_eq!(SoundFormat::from_path(Path::new("noext")), None);
+    }
+
+    #[test]
+    fn test_generated_wav_decodes() {
+        assert!(probe_source(Cursor::new(silent_wav())).is_ok());
+    }
+
+    #[test]
+    fn test_format_matrix() {
+        let dir = std::env::temp_dir().join(format!("audio-format-test-{}", std::process::id()));
+        std::fs::create_dir_all(dir.join("theme/stereo")).unwrap();
+        std::fs::write(dir.join("theme/stereo/bell.wav"), silent_wav()).unwrap();
+        std::fs::write(dir.join("theme/stereo/broken.ogg"), b"not really vorbis").unwrap();
+
+        let matrix = probe_formats(std::slice::from_ref(&dir));
+        let support = |format| {
+            matrix
+                .iter()
+                .find(|(f, _)| *f == format)
+                .map(|(_, s)| s.clone())
+                .unwrap()
+        };
+
+        assert_eq!(matrix.len(), SoundFormat::ALL.len());
+        assert_eq!(
+            support(SoundFormat::Wav),
+            FormatSupport::Supported(dir.join("theme/stereo/bell.wav"))
+        );
+        assert!(matches!(support(SoundFormat::Ogg), FormatSupport::Unsupported { .. }));
+        assert_eq!(support(SoundFormat::Flac), FormatSupport::Untested);
+
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
     #[test]
     fn test_audio_error_display() {
         let err = AudioError::NoAudioDevice;
@@ -458,6 +1470,37 @@ mod tests {
             }
         }
 
+        #[test]
+        fn test_validate_sound_dir() {
+            assert!(validate_sound_dir(Path::new("relative/sounds")).is_err());
+            assert!(validate_sound_dir(Path::new("/")).is_err());
+            assert!(validate_sound_dir(Path::new("/usr")).is_err());
+            assert!(validate_sound_dir(Path::new("/etc/ssl")).is_err());
+            assert!(validate_sound_dir(Path::new("/nonexistent/vendor/sounds")).is_err());
+
+            let dir = std::env::temp_dir().join(format!("extra-sounds-{}", std::process::id()));
+            std::fs::create_dir_all(&dir).unwrap();
+            assert_eq!(validate_sound_dir(&dir), Ok(dir.canonicalize().unwrap()));
+            let _ = std::fs::remove_dir_all(&dir);
+        }
+
+        #[test]
+        fn test_extra_sound_dir_allows_files() {
+            let dir = std::env::temp_dir().join(format!("extra-sounds-allow-{}", std::process::id()));
+            std::fs::create_dir_all(&dir).unwrap();
+            let sound = dir.join("ding.oga");
+            std::fs::write(&sound, b"").unwrap();
+
+            assert!(!is_allowed_sound_path(&sound));
+            set_extra_sound_dirs(std::slice::from_ref(&dir));
+            assert!(is_allowed_sound_path(&sound));
+            // Escaping the directory is still rejected
+            assert!(!is_allowed_sound_path(&dir.join("../../etc/passwd")));
+
+            set_extra_sound_dirs::<PathBuf>(&[]);
+            let _ = std::fs::remove_dir_all(&dir);
+        }
+
         #[test]
         fn test_play_sound_file_file_not_found_before_path_check() {
             // Non-existent file should return FileNotFound, not PathNotAllowed
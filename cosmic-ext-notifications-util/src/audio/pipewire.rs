@@ -0,0 +1,252 @@
+//! PipeWire output for notification sounds
+//!
+//! rodio plays through ALSA, which PipeWire sees as an anonymous client.
+//! Playing through a native stream instead lets us tag it with
+//! `media.role = Notification`, so volume mixers list notification sounds
+//! separately and session policies (ducking, routing) apply to them.
+//!
+//! Each sound gets its own thread running a PipeWire main loop; at most
+//! [`MAX_CONCURRENT_SOUNDS`](super::MAX_CONCURRENT_SOUNDS) exist at once.
+
+use std::cell::Cell;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc;
+use std::sync::Arc;
+use std::thread;
+use std::time::{Duration, Instant};
+
+use ::pipewire as pw;
+use pw::spa;
+use rodio::Source;
+use tracing::{debug, warn};
+
+use super::{AudioError, PlaybackState, FADE_OUT_GRACE};
+
+/// How long to wait for the PipeWire daemon before falling back to rodio
+const CONNECT_TIMEOUT: Duration = Duration::from_millis(500);
+
+/// Bytes per sample, the stream is always S16LE
+const SAMPLE_SIZE: usize = 2;
+
+/// Silent buffers queued after the sound ends so its tail isn't cut off
+const DRAIN_BUFFERS: u32 = 2;
+
+/// How often the main loop checks whether the sound was stopped, in case
+/// the stream isn't asking for buffers
+const CANCEL_POLL_INTERVAL: Duration = Duration::from_millis(50);
+
+/// When a stream's main loop should quit early
+struct Stop {
+    state: Arc<PlaybackState>,
+    /// Set once [`play`] gave up waiting; the sound then plays elsewhere
+    abandoned: Arc<AtomicBool>,
+    fade_out: Duration,
+}
+
+impl Stop {
+    fn abandoned(&self) -> bool {
+        self.abandoned.load(Ordering::Acquire)
+    }
+
+    fn cancelled(&self) -> bool {
+        self.state.cancelled.load(Ordering::Acquire)
+    }
+}
+
+/// Play a decoded sound on its own PipeWire stream
+///
+/// Returns once the stream is connected; the thread marks `state` finished
+/// when playback ends. Fails if PipeWire isn't running, in which case the
+/// caller should fall back to another output. A stream that connects only
+/// after [`CONNECT_TIMEOUT`] is torn down again without playing, so the
+/// sound isn't heard twice.
+pub(super) fn play<S>(
+    source: S,
+    volume: f32,
+    fade_out: Duration,
+    state: Arc<PlaybackState>,
+) -> Result<(), AudioError>
+where
+    S: Source<Item = i16> + Send + 'static,
+{
+    let (ready_tx, ready_rx) = mpsc::sync_channel(1);
+    let abandoned = Arc::new(AtomicBool::new(false));
+    let stop = Stop {
+        state: state.clone(),
+        abandoned: abandoned.clone(),
+        fade_out,
+    };
+    thread::Builder::new()
+        .name("pipewire-sound".into())
+        .spawn(move || {
+            let result = run_stream(source, volume, &stop, &ready_tx);
+            if let Err(e) = &result {
+                // Only reported here if the stream already started
+                debug!("PipeWire playback ended with error: {}", e);
+            }
+            let failed = result.is_err();
+            let _ = ready_tx.try_send(result);
+            // Otherwise the fallback output owns the sound now
+            if !failed && !stop.abandoned() {
+                stop.state.finish();
+            }
+        })
+        .map_err(|e| AudioError::PlaybackError(e.to_string()))?;
+
+    match ready_rx.recv_timeout(CONNECT_TIMEOUT) {
+        Ok(Ok(())) => Ok(()),
+        Ok(Err(e)) => Err(e),
+        Err(_) => {
+            abandoned.store(true, Ordering::Release);
+            warn!("PipeWire did not answer within {:?}", CONNECT_TIMEOUT);
+            Err(AudioError::NoAudioDevice)
+        }
+    }
+}
+
+fn pw_error(e: pw::Error) -> AudioError {
+    AudioError::PlaybackError(format!("PipeWire: {e}"))
+}
+
+/// Connect a playback stream and run its main loop until the sound ends
+///
+/// The loop also quits once the sound was stopped and had time to fade
+/// out, or once [`play`] stopped waiting for it.
+fn run_stream<S>(
+    source: S,
+    volume: f32,
+    stop: &Stop,
+    ready: &mpsc::SyncSender<Result<(), AudioError>>,
+) -> Result<(), AudioError>
+where
+    S: Source<Item = i16> + Send + 'static,
+{
+    pw::init();
+    let mainloop = pw::main_loop::MainLoop::new(None).map_err(pw_error)?;
+    let context = pw::context::Context::new(&mainloop).map_err(pw_error)?;
+    let core = context.connect(None).map_err(|_| AudioError::NoAudioDevice)?;
+
+    let channels = source.channels();
+    let rate = source.sample_rate();
+    let stream = pw::stream::Stream::new(
+        &core,
+        "notification-sound",
+        pw::properties::properties! {
+            *pw::keys::MEDIA_TYPE => "Audio",
+            *pw::keys::MEDIA_CATEGORY => "Playback",
+            *pw::keys::MEDIA_ROLE => "Notification",
+            *pw::keys::APP_NAME => "COSMIC Notifications",
+            *pw::keys::APP_ICON_NAME => "preferences-system-notifications",
+        },
+    )
+    .map_err(pw_error)?;
+
+    let quit = mainloop.clone();
+    let state = stop.state.clone();
+    let abandoned = stop.abandoned.clone();
+    let mut samples = source.amplify(volume.clamp(0.0, 1.0));
+    let mut drained = 0;
+    let _listener = stream
+        .add_local_listener_with_user_data(())
+        .process(move |stream, _| {
+            if abandoned.load(Ordering::Acquire) {
+                quit.quit();
+                return;
+            }
+            let Some(mut buffer) = stream.dequeue_buffer() else {
+                return;
+            };
+            let stride = SAMPLE_SIZE * usize::from(channels);
+            let data = &mut buffer.datas_mut()[0];
+            let mut written = 0;
+            if let Some(slice) = data.data() {
+                for chunk in slice.chunks_exact_mut(SAMPLE_SIZE) {
+                    match samples.next() {
+                        Some(sample) => {
+                            chunk.copy_from_slice(&sample.to_le_bytes());
+                            written += SAMPLE_SIZE;
+                        }
+                        None => break,
+                    }
+                }
+                if written == 0 {
+                    // Sound is over, play a little silence before quitting
+                    let len = slice.len() - slice.len() % stride;
+                    slice[..len].fill(0);
+                    written = len;
+                    drained += 1;
+                }
+            }
+            let frames = written / stride;
+            let chunk = data.chunk_mut();
+            *chunk.offset_mut() = 0;
+            *chunk.stride_mut() = stride as i32;
+            *chunk.size_mut() = (frames * stride) as u32;
+
+            // A stopped sound has faded out by the time its source ends
+            if drained > DRAIN_BUFFERS || (drained > 0 && state.cancelled.load(Ordering::Acquire)) {
+                quit.quit();
+            }
+        })
+        .register()
+        .map_err(pw_error)?;
+
+    let mut info = spa::param::audio::AudioInfoRaw::new();
+    info.set_format(spa::param::audio::AudioFormat::S16LE);
+    info.set_rate(rate);
+    info.set_channels(u32::from(channels));
+    let format = spa::pod::serialize::PodSerializer::serialize(
+        std::io::Cursor::new(Vec::new()),
+        &spa::pod::Value::Object(spa::pod::Object {
+            type_: spa::utils::SpaTypes::ObjectParamFormat.as_raw(),
+            id: spa::param::ParamType::EnumFormat.as_raw(),
+            properties: info.into(),
+        }),
+    )
+    .map_err(|e| AudioError::PlaybackError(format!("PipeWire format: {e:?}")))?
+    .0
+    .into_inner();
+    let mut params = [spa::pod::Pod::from_bytes(&format)
+        .ok_or_else(|| AudioError::PlaybackError("PipeWire format pod".to_string()))?];
+
+    stream
+        .connect(
+            spa::utils::Direction::Output,
+            None,
+            pw::stream::StreamFlags::AUTOCONNECT | pw::stream::StreamFlags::MAP_BUFFERS,
+            &mut params,
+        )
+        .map_err(pw_error)?;
+
+    // Without buffers being asked for, `process` never sees a stop
+    let quit = mainloop.clone();
+    let stopping_since = Cell::new(None);
+    let fade_out = stop.fade_out;
+    let state = stop.state.clone();
+    let abandoned = stop.abandoned.clone();
+    let timer = mainloop.loop_().add_timer(move |_| {
+        if abandoned.load(Ordering::Acquire) {
+            quit.quit();
+            return;
+        }
+        if !state.cancelled.load(Ordering::Acquire) {
+            return;
+        }
+        let since = stopping_since.get().unwrap_or_else(Instant::now);
+        stopping_since.set(Some(since));
+        if since.elapsed() >= fade_out + FADE_OUT_GRACE {
+            quit.quit();
+        }
+    });
+    timer
+        .update_timer(Some(CANCEL_POLL_INTERVAL), Some(CANCEL_POLL_INTERVAL))
+        .into_result()
+        .map_err(|e| AudioError::PlaybackError(format!("PipeWire timer: {e:?}")))?;
+
+    if stop.cancelled() || stop.abandoned() {
+        return Ok(());
+    }
+    let _ = ready.try_send(Ok(()));
+    mainloop.run();
+    Ok(())
+}
@@ -0,0 +1,175 @@
+//! Phone number and email address detection for quick actions
+//!
+//! Finds contact details in plain-text notification bodies so the UI can
+//! offer "Call", "Copy number" and "Compose email" buttons. Detection is
+//! heuristic and tuned to avoid false positives (dates, version numbers,
+//! digits inside URLs) rather than to find every possible number format.
+
+use linkify::{LinkFinder, LinkKind};
+use regex::Regex;
+use std::sync::LazyLock;
+
+/// Minimum digits for something to count as a phone number
+const MIN_PHONE_DIGITS: usize = 7;
+
+/// Maximum digits in a phone number (E.164 limit)
+const MAX_PHONE_DIGITS: usize = 15;
+
+static PHONE_PATTERN: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r"(?:\+\d{1,3}[\s.-]?)?(?:\(\d{1,4}\)[\s.-]?)?\d{2,4}(?:[\s.-]?\d{2,4}){1,4}").unwrap()
+});
+
+static DATE_PATTERN: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r"^\d{4}[-./]\d{1,2}[-./]\d{1,2}$|^\d{1,2}[-./]\d{1,2}[-./]\d{2,4}$").unwrap()
+});
+
+/// Kind of contact detail found in a notification body
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ContactKind {
+    Phone,
+    Email,
+}
+
+/// A phone number or email address found in text
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DetectedContact {
+    pub kind: ContactKind,
+    /// The contact as written in the text
+    pub value: String,
+    /// `tel:` or `mailto:` URI for opening the contact
+    pub uri: String,
+    /// Byte offset of the contact in the text
+    pub start: usize,
+}
+
+/// Detect phone numbers and email addresses in plain text
+///
+/// Results are sorted by position. Digits that are part of a URL or email
+/// address are never reported as phone numbers.
+pub fn detect_contacts(text: &str) -> Vec<DetectedContact> {
+    let mut finder = LinkFinder::new();
+    finder.kinds(&[LinkKind::Url, LinkKind::Email]);
+    let links: Vec<_> = finder.links(text).collect();
+
+    let mut contacts: Vec<DetectedContact> = links
+        .iter()
+        .filter(|link| *link.kind() == LinkKind::Email)
+        .map(|link| DetectedContact {
+            kind: ContactKind::Email,
+            value: link.as_str().to_string(),
+            uri: format!("mailto:{}", link.as_str()),
+            start: link.start(),
+        })
+        .collect();
+
+    for m in PHONE_PATTERN.find_iter(text) {
+        let overlaps_link = links
+            .iter()
+            .any(|link| m.start() < link.end() && link.start() < m.end());
+        if overlaps_link || !is_standalone(text, m.start(), m.end()) {
+            continue;
+        }
+        if let Some(uri) = phone_uri(m.as_str()) {
+            contacts.push(DetectedContact {
+                kind: ContactKind::Phone,
+                value: m.as_str().to_string(),
+                uri,
+                start: m.start(),
+            });
+        }
+    }
+
+    contacts.sort_by_key(|c| c.start);
+    contacts
+}
+
+/// Build a `tel:` URI for a phone number candidate, or None if it doesn't
+/// look like a phone number
+fn phone_uri(candidate: &str) -> Option<String> {
+    if DATE_PATTERN.is_match(candidate) {
+        return None;
+    }
+    // Dotted numbers like 1.2.3.4 are versions or addresses, not phones
+    if candidate.contains('.') && !candidate.contains(['-', ' ', '(']) {
+        return None;
+    }
+
+    let digits: String = candidate.chars().filter(char::is_ascii_digit).collect();
+    if !(MIN_PHONE_DIGITS..=MAX_PHONE_DIGITS).contains(&digits.len()) {
+        return None;
+    }
+
+    let prefix = if candidate.starts_with('+') { "+" } else { "" };
+    Some(format!("tel:{prefix}{digits}"))
+}
+
+/// Check that a match isn't glued to surrounding letters, digits or separators
+fn is_standalone(text: &str, start: usize, end: usize) -> bool {
+    let before = text[..start].chars().next_back();
+    let after = text[end..].chars().next();
+    let joined = |c: Option<char>| c.is_some_and(|c| c.is_alphanumeric() || matches!(c, '-' | '.' | '/' | '_'));
+    // A trailing period ends a sentence, it doesn't extend the number
+    let after_joined = joined(after) && !(after == Some('.') && text[end + 1..].chars().next().is_none_or(char::is_whitespace));
+    !joined(before) && !after_joined
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn phones(text: &str) -> Vec<String> {
+        detect_contacts(text)
+            .into_iter()
+            .filter(|c| c.kind == ContactKind::Phone)
+            .map(|c| c.uri)
+            .collect()
+    }
+
+    #[test]
+    fn test_detects_international_number() {
+        assert_eq!(phones("Call me at +47 912 34 567"), vec!["tel:+4791234567"]);
+    }
+
+    #[test]
+    fn test_detects_local_formats() {
+        assert_eq!(phones("Office: (555) 123-4567."), vec!["tel:5551234567"]);
+        assert_eq!(phones("Ring 020 7946 0958 today"), vec!["tel:02079460958"]);
+    }
+
+    #[test]
+    fn test_detects_email() {
+        let contacts = detect_contacts("Reply to jane@example.com please");
+        assert_eq!(contacts.len(), 1);
+        assert_eq!(contacts[0].kind, ContactKind::Email);
+        assert_eq!(contacts[0].value, "jane@example.com");
+        assert_eq!(contacts[0].uri, "mailto:jane@example.com");
+    }
+
+    #[test]
+    fn test_ignores_dates_and_versions() {
+        assert!(phones("Released on 2024-01-15").is_empty());
+        assert!(phones("Updated to 10.2.30.4000").is_empty());
+        assert!(phones("Due 15/01/2024").is_empty());
+    }
+
+    #[test]
+    fn test_ignores_short_numbers() {
+        assert!(phones("Your code is 1234").is_empty());
+        assert!(phones("3 new messages").is_empty());
+    }
+
+    #[test]
+    fn test_ignores_digits_in_urls_and_words() {
+        assert!(phones("See https://example.com/issues/12345678").is_empty());
+        assert!(phones("Order ABC12345678 shipped").is_empty());
+    }
+
+    #[test]
+    fn test_results_sorted_by_position() {
+        let contacts = detect_contacts("Mail bob@example.com or call +1 555 123 4567");
+        assert_eq!(contacts.len(), 2);
+        assert_eq!(contacts[0].kind, ContactKind::Email);
+        assert_eq!(contacts[1].kind, ContactKind::Phone);
+        assert_eq!(contacts[1].uri, "tel:+15551234567");
+    }
+}
@@ -0,0 +1,87 @@
+//! Sender heuristics for threading chat notifications
+//!
+//! Chat apps rarely say which conversation a notification belongs to, but
+//! nearly all of them put the sender or chat name in the summary, often
+//! dressed up as "New message from Alice" or "Alice (3 messages)". Taking
+//! that dressing off gives a name that stays the same from one message to
+//! the next.
+
+/// Lead-ins chat apps put before the sender, lowercase
+const SENDER_PREFIXES: &[&str] = &[
+    "new messages from ",
+    "new message from ",
+    "message from ",
+    "messages from ",
+    "reply from ",
+    "new reply from ",
+];
+
+/// Endings chat apps put after the sender, lowercase
+const SENDER_SUFFIXES: &[&str] = &[
+    " sent you a message",
+    " sent a message",
+    " sent you a photo",
+    " sent a photo",
+    " sent an attachment",
+    " messaged you",
+    " replied",
+    ":",
+];
+
+/// The sender or chat named in a chat notification's summary
+///
+/// `None` when nothing is left once the dressing is taken off.
+pub fn sender_name(summary: &str) -> Option<&str> {
+    let mut name = summary.lines().next().unwrap_or_default().trim();
+    for prefix in SENDER_PREFIXES {
+        if name.get(..prefix.len()).is_some_and(|head| head.eq_ignore_ascii_case(prefix)) {
+            name = &name[prefix.len()..];
+            break;
+        }
+    }
+    // Unread counts like "Alice (3)" or "Alice (3 messages)"
+    if let Some(open) = name.rfind(" (") {
+        let count = name[open + 2..].strip_suffix(')').unwrap_or_default();
+        if count.split_whitespace().next().is_some_and(|n| n.parse::<u32>().is_ok()) {
+            name = &name[..open];
+        }
+    }
+    for suffix in SENDER_SUFFIXES {
+        let Some(cut) = name.len().checked_sub(suffix.len()) else {
+            continue;
+        };
+        if name.get(cut..).is_some_and(|tail| tail.eq_ignore_ascii_case(suffix)) {
+            name = &name[..cut];
+            break;
+        }
+    }
+    let name = name.trim();
+    (!name.is_empty()).then_some(name)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_plain_sender() {
+        assert_eq!(sender_name("Alice"), Some("Alice"));
+        assert_eq!(sender_name("  Family chat  "), Some("Family chat"));
+    }
+
+    #[test]
+    fn test_strips_dressing() {
+        assert_eq!(sender_name("New message from Alice"), Some("Alice"));
+        assert_eq!(sender_name("Alice (3 messages)"), Some("Alice"));
+        assert_eq!(sender_name("Alice (2)"), Some("Alice"));
+        assert_eq!(sender_name("Alice sent you a photo"), Some("Alice"));
+        assert_eq!(sender_name("Alice:"), Some("Alice"));
+    }
+
+    #[test]
+    fn test_keeps_names_that_only_look_dressed() {
+        assert_eq!(sender_name("Alice (work)"), Some("Alice (work)"));
+        assert_eq!(sender_name("   "), None);
+        assert_eq!(sender_name(""), None);
+    }
+}
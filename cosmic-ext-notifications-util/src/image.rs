@@ -1,6 +1,10 @@
-use fast_image_resize as fr;
+use crate::notification_image::NotificationImage;
 use std::str::FromStr;
 use zbus::zvariant::{Signature, Structure};
+
+/// Raw pixels from the `image-data` (or legacy `icon_data`) hint, laid out
+/// as the spec's `(iiibiiay)` struct.
+#[derive(Debug, Clone, PartialEq, Eq)]
 pub struct ImageData {
     pub width: u32,
     pub height: u32,
@@ -12,57 +16,35 @@ pub struct ImageData {
 }
 
 impl ImageData {
-    pub fn into_rgba(self) -> Self {
-        let rgba = if self.has_alpha {
-            self
-        } else {
-            let mut data = self.data;
-            let mut new_data = Vec::with_capacity(data.len() / self.channels as usize * 4);
-
-            for chunk in data.chunks_exact_mut(self.channels as usize) {
-                new_data.extend_from_slice(chunk);
-                new_data.push(0xFF);
-            }
-
-            Self {
-                has_alpha: true,
-                data: new_data,
-                channels: 4,
-                rowstride: self.width as i32 * 4,
-                ..self
-            }
-        };
-
-        if rgba.width <= 16 && rgba.height <= 16 {
-            return rgba;
-        }
-        let mut src =
-            fr::images::Image::from_vec_u8(rgba.width, rgba.height, rgba.data, fr::PixelType::U8x4)
-                .unwrap();
-
-        let mut dst =
-            fr::images::Image::new(rgba.width.min(16), rgba.height.min(16), fr::PixelType::U8x4);
-
-        // Multiple RGB channels of source image by alpha channel
-        // (not required for the Nearest algorithm)
-        fr::MulDiv::default()
-            .multiply_alpha_inplace(&mut src)
-            .unwrap();
-
-        fr::Resizer::new()
-            .resize(&src, &mut dst, Some(&fr::ResizeOptions::default()))
-            .unwrap();
+    /// Convert to tightly packed RGBA, dropping any rowstride padding and
+    /// downscaling to fit within `MAX_IMAGE_WIDTH` x `MAX_IMAGE_HEIGHT`.
+    pub fn into_rgba(self) -> Result<Self, ::image::ImageError> {
+        let image = NotificationImage::from_raw_data(
+            &self.data,
+            self.width as i32,
+            self.height as i32,
+            self.rowstride,
+            self.has_alpha,
+        )?;
 
-        fr::MulDiv::default()
-            .divide_alpha_inplace(&mut dst)
-            .unwrap();
+        Ok(Self {
+            width: image.width,
+            height: image.height,
+            rowstride: image.width as i32 * 4,
+            has_alpha: true,
+            bits_per_sample: 8,
+            channels: 4,
+            data: image.data,
+        })
+    }
 
-        Self {
-            width: dst.width(),
-            height: dst.height(),
-            data: dst.into_vec(),
-            ..rgba
-        }
+    /// Bytes the pixel buffer must hold: every row but the last is
+    /// `rowstride` long, the last only needs `width * channels` bytes.
+    fn required_len(width: i32, height: i32, rowstride: i32, channels: i32) -> Option<usize> {
+        let row = (width as usize).checked_mul(channels as usize)?;
+        (rowstride as usize)
+            .checked_mul(height as usize - 1)?
+            .checked_add(row)
     }
 }
 
@@ -124,19 +106,30 @@ impl<'a> TryFrom<Structure<'a>> for ImageData {
             ));
         }
 
-        if (width * height * channels) as usize != data.len() {
+        if !has_alpha && channels != 3 {
             return Err(zbus::Error::Failure(
-                "Invalid ImageData: data length does not match width * height * channels"
-                    .to_string(),
+                "Invalid ImageData: has_alpha is false but channels is not 3".to_string(),
             ));
         }
 
-        if data.len() != (rowstride * height) as usize {
+        if rowstride < width.saturating_mul(channels) {
             return Err(zbus::Error::Failure(
-                "Invalid ImageData: data length does not match rowstride * height".to_string(),
+                "Invalid ImageData: rowstride is smaller than width * channels".to_string(),
             ));
         }
 
+        // Rows may be padded out to rowstride, and clients commonly leave
+        // the padding off the last row
+        match ImageData::required_len(width, height, rowstride, channels) {
+            Some(len) if data.len() >= len => {}
+            _ => {
+                return Err(zbus::Error::Failure(
+                    "Invalid ImageData: data is shorter than rowstride * (height - 1) + width * channels"
+                        .to_string(),
+                ));
+            }
+        }
+
         Ok(Self {
             width: width as u32,
             height: height as u32,
@@ -148,3 +141,93 @@ impl<'a> TryFrom<Structure<'a>> for ImageData {
         })
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn structure(
+        width: i32,
+        height: i32,
+        rowstride: i32,
+        has_alpha: bool,
+        channels: i32,
+        data: Vec<u8>,
+    ) -> Structure<'static> {
+        Structure::from((width, height, rowstride, has_alpha, 8i32, channels, data))
+    }
+
+    #[test]
+    fn test_rgba_packed() {
+        let data: Vec<u8> = (0..2 * 2 * 4).collect();
+        let image = ImageData::try_from(structure(2, 2, 8, true, 4, data.clone()))
+            .unwrap()
+            .into_rgba()
+            .unwrap();
+
+        assert_eq!((image.width, image.height), (2, 2));
+        assert_eq!(image.data, data);
+    }
+
+    #[test]
+    fn test_rgb_padded_rowstride() {
+        // 2x2 RGB with rows padded to 8 bytes
+        let data = vec![
+            1, 2, 3, 4, 5, 6, 0, 0, //
+            7, 8, 9, 10, 11, 12, 0, 0,
+        ];
+        let image = ImageData::try_from(structure(2, 2, 8, false, 3, data))
+            .unwrap()
+            .into_rgba()
+            .unwrap();
+
+        assert_eq!(image.channels, 4);
+        assert_eq!(image.rowstride, 8);
+        assert_eq!(
+            image.data,
+            vec![1, 2, 3, 255, 4, 5, 6, 255, 7, 8, 9, 255, 10, 11, 12, 255]
+        );
+    }
+
+    #[test]
+    fn test_rgba_unpadded_last_row() {
+        // Rows padded to 12 bytes, but the last row stops after its pixels
+        let mut data = vec![1u8; 12];
+        data.extend_from_slice(&[2u8; 8]);
+        let image = ImageData::try_from(structure(2, 2, 12, true, 4, data))
+            .unwrap()
+            .into_rgba()
+            .unwrap();
+
+        assert_eq!(image.data.len(), 16);
+        assert_eq!(&image.data[..8], &[1u8; 8]);
+        assert_eq!(&image.data[8..], &[2u8; 8]);
+    }
+
+    #[test]
+    fn test_invalid_layouts() {
+        // Rowstride can't hold a row
+        assert!(ImageData::try_from(structure(2, 2, 4, true, 4, vec![0; 16])).is_err());
+        // Data too short
+        assert!(ImageData::try_from(structure(2, 2, 8, true, 4, vec![0; 15])).is_err());
+        // Channels disagree with has_alpha
+        assert!(ImageData::try_from(structure(2, 2, 8, false, 4, vec![0; 16])).is_err());
+        assert!(ImageData::try_from(structure(2, 2, 6, true, 3, vec![0; 12])).is_err());
+        // Empty image
+        assert!(ImageData::try_from(structure(0, 2, 8, true, 4, vec![])).is_err());
+    }
+
+    #[test]
+    fn test_large_image_downscaled() {
+        let (width, height) = (512, 256);
+        let data = vec![128u8; (width * height * 4) as usize];
+        let image = ImageData::try_from(structure(width, height, width * 4, true, 4, data))
+            .unwrap()
+            .into_rgba()
+            .unwrap();
+
+        assert_eq!(image.width, crate::MAX_IMAGE_WIDTH);
+        assert_eq!(image.height, crate::MAX_IMAGE_WIDTH / 2);
+        assert_eq!(image.data.len(), (image.width * image.height * 4) as usize);
+    }
+}
@@ -6,7 +6,9 @@ pub use image::*;
 #[cfg(feature = "image")]
 pub mod notification_image;
 #[cfg(feature = "image")]
-pub use notification_image::{NotificationImage, ProcessedImage, MAX_IMAGE_HEIGHT, MAX_IMAGE_WIDTH};
+pub use notification_image::{
+    ImagePolicy, NotificationImage, ProcessedImage, MAX_IMAGE_HEIGHT, MAX_IMAGE_WIDTH,
+};
 
 #[cfg(feature = "image")]
 pub mod animated_image;
@@ -16,28 +18,61 @@ pub use animated_image::{AnimatedImage, AnimationFrame, MAX_FRAMES, MAX_ANIMATIO
 #[cfg(feature = "audio")]
 pub mod audio;
 #[cfg(feature = "audio")]
-pub use audio::{play_sound_file, play_sound_name, AudioError};
+pub use audio::{
+    play_sound_file, play_sound_file_with_volume, play_sound_name, play_sound_name_with_volume,
+    check_sound_name, format_support, playback_events, playback_stats, probe_sound_file,
+    set_extra_sound_dirs, set_fade_durations, set_sound_theme, validate_sound_dir,
+    volume_from_percent,
+    AudioError, AudioHandle, DropReason, FormatSupport, PlaybackEvent, PlaybackHandle,
+    PlaybackStats, SoundDebouncer, SoundFormat,
+};
+
+#[cfg(feature = "audio")]
+pub mod sound_theme;
+
+#[cfg(feature = "plugins")]
+pub mod plugin;
+#[cfg(feature = "plugins")]
+pub use plugin::{PluginAction, PluginHost};
 
 pub mod action;
 pub mod action_parser;
+pub mod contact_detector;
+pub mod conversation;
 pub mod link;
 pub mod link_detector;
 pub mod markup_parser;
+pub mod record;
+pub mod rewrite;
 pub mod rich_content;
 pub mod sanitizer;
+pub mod secret_detector;
+pub mod text_normalizer;
 pub mod urgency;
 pub mod urgency_style;
 
 pub use action::NotificationAction;
 pub use action_parser::{
-    get_button_actions, get_default_action, has_action_icons, limit_actions, parse_actions,
+    action_icon_name, get_button_actions, get_default_action, has_action_icons, limit_actions, parse_actions,
     parse_actions_from_strs,
 };
+pub use contact_detector::{detect_contacts, ContactKind, DetectedContact};
+pub use conversation::sender_name;
 pub use link::NotificationLink;
-pub use link_detector::{detect_links, is_safe_url, open_link};
-pub use markup_parser::{parse_markup, segments_to_plain_text, StyledSegment, TextStyle};
+pub use link_detector::{check_links, detect_links, is_link_text_mismatch, is_safe_url, open_link, CheckedLink};
+pub use markup_parser::{linkify_segments, parse_markup, segments_to_plain_text, StyledSegment, TextStyle};
+pub use record::{
+    ImageRef, LifecycleEvent, LifecycleKind, NotificationRecord, RecordAction, RecordCloseReason,
+    RecordError, RecordHints, SCHEMA_VERSION,
+};
+pub use rewrite::RewriteRules;
 pub use rich_content::RichContent;
-pub use sanitizer::{clean_bare_schemes, extract_hrefs, has_rich_content, sanitize_html, strip_html};
+pub use sanitizer::{
+    cap_input, clean_bare_schemes, extract_hrefs, has_rich_content, sanitize_and_parse, sanitize_html,
+    strip_html, MAX_MARKUP_BYTES,
+};
+pub use secret_detector::{detect_secrets, mask_secrets, DetectedSecret, SecretKind};
+pub use text_normalizer::{normalize_nfc, strip_spoofing_chars};
 pub use urgency::NotificationUrgency;
 pub use urgency_style::{
     categories, category_icon, is_message_category, is_system_category, urgency_color,
@@ -53,6 +88,22 @@ use std::{
 #[cfg(feature = "zbus_notifications")]
 use cosmic_ext_notifications_config::GroupingMode;
 
+/// Maximum bytes kept from a notification's app name or icon
+pub const MAX_APP_NAME_BYTES: usize = 256;
+
+/// Maximum bytes kept from a notification summary
+pub const MAX_SUMMARY_BYTES: usize = 1024;
+
+/// Maximum action pairs accepted from a single notification
+pub const MAX_ACTIONS: usize = 32;
+
+/// Maximum bytes kept from an action label
+pub const MAX_ACTION_LABEL_BYTES: usize = 256;
+
+/// Action a client adds to ask for a reply text field (KDE extension);
+/// its label names the send button
+pub const INLINE_REPLY_ACTION: &str = "inline-reply";
+
 /// A group of related notifications
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub struct NotificationGroup {
@@ -89,6 +140,9 @@ impl NotificationGroup {
     }
 
     /// Get the group label with count (e.g., "Firefox (3)")
+    ///
+    /// Untranslated, for logs and tests. The UI uses the localized
+    /// `rendering::group_label` with `display_name` and `count()`.
     pub fn label(&self) -> String {
         if self.notifications.len() > 1 {
             format!("{} ({})", self.display_name, self.notifications.len())
@@ -107,7 +161,12 @@ pub struct Notification {
     pub body: String,
     pub actions: Vec<(ActionId, String)>,
     pub hints: Vec<Hint>,
+    /// Timeout requested by the sender: -1 lets the server decide, 0 never expires
     pub expire_timeout: i32,
+    /// Timeout in milliseconds the daemon actually applied (0 = never),
+    /// kept in history to explain why a popup stayed or vanished
+    #[serde(default)]
+    pub applied_timeout: Option<u32>,
     pub time: SystemTime,
 }
 
@@ -124,84 +183,80 @@ impl Notification {
         hints: HashMap<&str, zbus::zvariant::Value<'_>>,
         expire_timeout: i32,
     ) -> Self {
+        // Hard caps on untrusted input, applied before anything else touches it
+        let app_name = cap_input(app_name, MAX_APP_NAME_BYTES);
+        let app_icon = cap_input(app_icon, MAX_APP_NAME_BYTES);
+        let summary = cap_input(summary, MAX_SUMMARY_BYTES);
+        let body = cap_input(body, MAX_MARKUP_BYTES);
+        // NFC so visually identical text matches rules and dedups the same
+        let app_name = normalize_nfc(app_name);
+        let summary = normalize_nfc(summary);
+        let body = normalize_nfc(body);
+
         let actions = actions
             .chunks_exact(2)
-            .map(|a| (a[0].parse().unwrap(), a[1].to_string()))
+            .take(MAX_ACTIONS)
+            .map(|a| (a[0].parse().unwrap(), cap_input(a[1], MAX_ACTION_LABEL_BYTES).to_string()))
             .collect();
 
-        let hints = hints
+        // Several image hints may be sent; only the highest-ranked one is kept
+        let mut images = Vec::new();
+        let mut hints: Vec<Hint> = hints
             .into_iter()
             .filter_map(|(k, v)| match k {
                 "action-icons" => bool::try_from(v).map(Hint::ActionIcons).ok(),
                 "category" => String::try_from(v).map(Hint::Category).ok(),
                 "desktop-entry" => String::try_from(v).map(Hint::DesktopEntry).ok(),
                 "resident" => bool::try_from(v).map(Hint::Resident).ok(),
-                "sound-file" => String::try_from(v)
-                    .map(|s| Hint::SoundFile(PathBuf::from(s)))
-                    .ok(),
+                "sound-file" => String::try_from(v).ok().map(|s| {
+                    // Clients send either a plain path or a file:// URL
+                    Hint::SoundFile(
+                        url::Url::parse(&s)
+                            .ok()
+                            .and_then(|u| u.to_file_path().ok())
+                            .unwrap_or_else(|| PathBuf::from(&s)),
+                    )
+                }),
                 "sound-name" => String::try_from(v).map(Hint::SoundName).ok(),
                 "suppress-sound" => bool::try_from(v).map(Hint::SuppressSound).ok(),
                 "transient" => bool::try_from(v).map(Hint::Transient).ok(),
                 "sender-pid" => u32::try_from(v).map(Hint::SenderPid).ok(),
                 "urgency" => u8::try_from(v).map(Hint::Urgency).ok(),
+                "x-kde-conversation" => String::try_from(v)
+                    .ok()
+                    .map(|s| Hint::Conversation(cap_input(&s, MAX_ACTION_LABEL_BYTES).to_string())),
+                "x-kde-reply-placeholder-text" => String::try_from(v)
+                    .ok()
+                    .map(|s| Hint::ReplyPlaceholder(cap_input(&s, MAX_ACTION_LABEL_BYTES).to_string())),
                 "value" => i32::try_from(v).map(Hint::Value).ok(),
                 "x" => i32::try_from(v).map(Hint::X).ok(),
                 "y" => i32::try_from(v).map(Hint::Y).ok(),
-                "image-path" | "image_path" => String::try_from(v).ok().map(|s| {
-                    Hint::Image(
-                        // First try parsing as file:// URL
-                        url::Url::parse(&s)
-                            .ok()
-                            .and_then(|u| u.to_file_path().ok())
-                            .map(Image::File)
-                            // Then check if it's an absolute file path
-                            .or_else(|| {
-                                if s.starts_with('/') {
-                                    Some(Image::File(PathBuf::from(&s)))
-                                } else {
-                                    None
-                                }
-                            })
-                            // Otherwise treat as icon name
-                            .unwrap_or_else(|| Image::Name(s)),
-                    )
-                }),
-                "image-data" | "image_data" | "icon_data" => match v {
-                    zbus::zvariant::Value::Structure(v) => match ImageData::try_from(v) {
-                        Ok(mut image) => Some({
-                            image = image.into_rgba();
-                            Hint::Image(Image::Data {
-                                width: image.width,
-                                height: image.height,
-                                data: Arc::new(image.data),
-                            })
-                        }),
-                        Err(err) => {
-                            tracing::warn!("Invalid image data: {}", err);
-                            None
-                        }
-                    },
-                    _ => {
-                        tracing::warn!("Invalid value for hint: {}", k);
-                        None
+                "image-data" | "image_data" | "image-path" | "image_path" | "icon_data" => {
+                    if let Some(image) = parse_image_hint(k, v) {
+                        images.push((image_hint_rank(k), image));
                     }
-                },
+                    None
+                }
                 _ => {
                     tracing::warn!("Unknown hint: {}", k);
                     None
                 }
             })
             .collect();
+        if let Some((_, image)) = images.into_iter().min_by_key(|(rank, _)| *rank) {
+            hints.push(Hint::Image(image));
+        }
 
         Notification {
             id,
-            app_name: app_name.to_string(),
+            app_name,
             app_icon: app_icon.to_string(),
-            summary: summary.to_string(),
-            body: body.to_string(),
+            summary,
+            body,
             actions,
             hints,
             expire_timeout,
+            applied_timeout: None,
             time: SystemTime::now(),
         }
     }
@@ -210,11 +265,92 @@ impl Notification {
         self.hints.iter().any(|h| *h == Hint::Transient(true))
     }
 
+    /// Label of the send button if the sender accepts inline replies
+    pub fn inline_reply_label(&self) -> Option<&str> {
+        self.actions.iter().find_map(|(id, label)| match id {
+            ActionId::Custom(id) if id == INLINE_REPLY_ACTION => Some(label.as_str()),
+            _ => None,
+        })
+    }
+
+    /// Placeholder text the sender wants in the reply field
+    pub fn reply_placeholder(&self) -> Option<&str> {
+        self.hints.iter().find_map(|h| match h {
+            Hint::ReplyPlaceholder(text) => Some(text.as_str()),
+            _ => None,
+        })
+    }
+
     /// Check if action buttons should display icons instead of text labels
     pub fn action_icons(&self) -> bool {
         self.hints.iter().any(|h| *h == Hint::ActionIcons(true))
     }
 
+    /// Remove bidi overrides, zero-width and control characters from the
+    /// app name, summary and body
+    pub fn strip_spoofing_chars(&mut self) {
+        self.app_name = strip_spoofing_chars(&self.app_name);
+        self.summary = strip_spoofing_chars(&self.summary);
+        self.body = strip_spoofing_chars(&self.body);
+    }
+
+    /// A copy with one-time codes and reset links in the summary and body
+    /// masked, or `None` if there are none
+    pub fn masked(&self) -> Option<Self> {
+        let summary = mask_secrets(&self.summary);
+        let body = mask_secrets(&self.body);
+        if summary.is_none() && body.is_none() {
+            return None;
+        }
+        Some(Self {
+            summary: summary.unwrap_or_else(|| self.summary.clone()),
+            body: body.unwrap_or_else(|| self.body.clone()),
+            ..self.clone()
+        })
+    }
+
+    /// Secrets in the summary and body, see [`detect_secrets`]
+    pub fn secrets(&self) -> Vec<DetectedSecret> {
+        let mut secrets = detect_secrets(&self.summary);
+        secrets.extend(detect_secrets(&strip_html(&self.body)));
+        secrets
+    }
+
+    /// Apply the user's rewrite rules to the summary and body
+    pub fn rewrite(&mut self, rules: &RewriteRules) {
+        if rules.is_empty() {
+            return;
+        }
+        let desktop_entry = self.desktop_entry().map(str::to_string);
+        self.summary = rules.apply(&self.summary, &self.app_name, desktop_entry.as_deref());
+        self.body = rules.apply(&self.body, &self.app_name, desktop_entry.as_deref());
+    }
+
+    /// A copy showing nothing but which app it's from, with `summary` in
+    /// place of the real one
+    ///
+    /// Body, actions, images and progress are left out; app name, icon,
+    /// urgency and category stay so the popup still looks like the app's.
+    pub fn redacted(&self, summary: String) -> Self {
+        Self {
+            summary,
+            body: String::new(),
+            actions: Vec::new(),
+            hints: self
+                .hints
+                .iter()
+                .filter(|h| {
+                    !matches!(
+                        h,
+                        Hint::Image(_) | Hint::IconData(_) | Hint::ReplyPlaceholder(_) | Hint::Value(_)
+                    )
+                })
+                .cloned()
+                .collect(),
+            ..self.clone()
+        }
+    }
+
     /// Check if sound should be suppressed for this notification
     pub fn suppress_sound(&self) -> bool {
         self.hints.iter().any(|h| *h == Hint::SuppressSound(true))
@@ -228,6 +364,22 @@ impl Notification {
         })
     }
 
+    /// Identifies the sound this notification would play, for debouncing
+    ///
+    /// None if the notification has no sound or suppresses it.
+    pub fn sound_key(&self) -> Option<String> {
+        if self.suppress_sound() {
+            return None;
+        }
+        let sound = match (self.sound_file(), self.sound_name()) {
+            (Some(path), _) => path.display().to_string(),
+            (None, Some(name)) => name.to_string(),
+            (None, None) => return None,
+        };
+        let app = self.desktop_entry().unwrap_or(&self.app_name);
+        Some(format!("{app}\u{1f}{sound}"))
+    }
+
     /// Get the sound name hint if present (XDG sound theme name)
     pub fn sound_name(&self) -> Option<&str> {
         self.hints.iter().find_map(|h| match h {
@@ -239,30 +391,74 @@ impl Notification {
     /// Play the notification sound if configured
     ///
     /// Respects suppress-sound hint, and plays sound-file or sound-name if specified.
+    /// `volume` ranges from 0.0 (silent) to 1.0 (full). Returns a handle that
+    /// can stop the sound early, or None if nothing was played.
     #[cfg(feature = "audio")]
-    pub fn play_sound(&self) {
+    pub fn play_sound(&self, volume: f32) -> Option<PlaybackHandle> {
         // Don't play if sound is suppressed
         if self.suppress_sound() {
             tracing::debug!("Sound suppressed for notification {}", self.id);
-            return;
+            return None;
         }
 
+        let audio = match AudioHandle::shared() {
+            Ok(audio) => audio,
+            Err(e) => {
+                tracing::warn!("Audio unavailable: {}", e);
+                return None;
+            }
+        };
+
         // Try sound-file first (takes precedence)
         if let Some(path) = self.sound_file() {
             tracing::debug!("Playing sound file: {:?}", path);
-            if let Err(e) = crate::audio::play_sound_file(path) {
-                tracing::warn!("Failed to play sound file {:?}: {}", path, e);
-            }
-            return;
+            return audio
+                .play(path, volume)
+                .inspect_err(|e| tracing::warn!("Failed to play sound file {:?}: {}", path, e))
+                .ok();
         }
 
         // Try sound-name (XDG sound theme)
-        if let Some(name) = self.sound_name() {
-            tracing::debug!("Playing sound name: {}", name);
-            if let Err(e) = crate::audio::play_sound_name(name) {
-                tracing::warn!("Failed to play sound '{}': {}", name, e);
-            }
+        let name = self.sound_name()?;
+        tracing::debug!("Playing sound name: {}", name);
+        audio
+            .play_name(name, volume)
+            .inspect_err(|e| tracing::warn!("Failed to play sound '{}': {}", name, e))
+            .ok()
+    }
+
+    /// Identity of the chat or email conversation this notification belongs to
+    ///
+    /// The `x-kde-conversation` hint names it outright. Otherwise messages,
+    /// told apart by their category or an inline reply action, are threaded
+    /// by the sender named in the summary, see [`sender_name`]. The key
+    /// combines that with the app. None for notifications that are not
+    /// messages.
+    pub fn conversation_key(&self) -> Option<String> {
+        let app = self.desktop_entry().unwrap_or(&self.app_name);
+        if let Some(conversation) = self.conversation() {
+            return Some(format!("{app}\u{1f}{conversation}"));
+        }
+        let is_message =
+            self.category().is_some_and(is_message_category) || self.inline_reply_label().is_some();
+        if !is_message {
+            return None;
         }
+        let sender = sender_name(&self.summary)?;
+        Some(format!("{app}\u{1f}{sender}"))
+    }
+
+    /// Conversation id from the `x-kde-conversation` hint
+    pub fn conversation(&self) -> Option<&str> {
+        self.hints.iter().find_map(|h| match h {
+            Hint::Conversation(s) => Some(s.as_str()),
+            _ => None,
+        })
+    }
+
+    /// Name to show for this notification's conversation
+    pub fn conversation_title(&self) -> String {
+        sender_name(&self.summary).unwrap_or(&self.summary).to_string()
     }
 
     pub fn category(&self) -> Option<&str> {
@@ -279,6 +475,34 @@ impl Notification {
         })
     }
 
+    /// Whether `app` names the sender, by app name or desktop entry, ignoring case
+    pub fn is_from_app(&self, app: &str) -> bool {
+        self.app_name.eq_ignore_ascii_case(app)
+            || self.desktop_entry().is_some_and(|e| e.eq_ignore_ascii_case(app))
+    }
+
+    /// Replace client-supplied identity hints with what the daemon verified
+    /// about the sending process.
+    ///
+    /// A sandboxed app ID always becomes the desktop entry, so per-app rules
+    /// follow the real sender whatever it claims. An ID taken from a plain
+    /// launch scope only fills in a missing desktop entry, since unsandboxed
+    /// children inherit their launcher's scope (a script run from a terminal).
+    pub fn set_sender_identity(&mut self, pid: u32, app_id: Option<&str>, sandboxed: bool) {
+        self.hints.retain(|h| !matches!(h, Hint::SenderPid(_)));
+        self.hints.push(Hint::SenderPid(pid));
+
+        let Some(app_id) = app_id else {
+            return;
+        };
+        if sandboxed {
+            self.hints.retain(|h| !matches!(h, Hint::DesktopEntry(_)));
+        } else if self.desktop_entry().is_some() {
+            return;
+        }
+        self.hints.push(Hint::DesktopEntry(app_id.to_string()));
+    }
+
     pub fn urgency(&self) -> u8 {
         self.hints
             .iter()
@@ -305,22 +529,13 @@ impl Notification {
                 height,
                 data,
             }) => Some(icon::from_raster_pixels(*width, *height, (**data).clone()).icon()),
-            None => {
-                if !self.app_icon.is_empty() {
-                    // Handle file:// URLs in app_icon
-                    if self.app_icon.starts_with("file://") {
-                        if let Ok(url) = url::Url::parse(&self.app_icon) {
-                            if let Ok(path) = url.to_file_path() {
-                                return Some(icon::from_path(path).icon());
-                            }
-                        }
-                    }
-                    // Otherwise treat as icon name
-                    Some(icon::from_name(self.app_icon.as_str()).icon())
-                } else {
-                    None
-                }
-            }
+            // app_icon ranks below every image hint
+            None if !self.app_icon.is_empty() => match image_from_path(&self.app_icon) {
+                Image::File(path) => Some(icon::from_path(path).icon()),
+                Image::Name(name) => Some(icon::from_name(name).icon()),
+                Image::Data { .. } => None,
+            },
+            None => None,
         }
     }
 
@@ -359,6 +574,59 @@ impl Notification {
     }
 }
 
+/// Precedence of the image hints per the spec: `image-data` wins over
+/// `image-path`, which wins over the deprecated `icon_data`.
+#[cfg(feature = "zbus_notifications")]
+fn image_hint_rank(key: &str) -> u8 {
+    match key {
+        "image-data" | "image_data" => 0,
+        "image-path" | "image_path" => 1,
+        _ => 2,
+    }
+}
+
+#[cfg(feature = "zbus_notifications")]
+fn parse_image_hint(key: &str, value: zbus::zvariant::Value<'_>) -> Option<Image> {
+    match value {
+        zbus::zvariant::Value::Str(s) if image_hint_rank(key) == 1 => Some(image_from_path(s.as_str())),
+        zbus::zvariant::Value::Structure(v) => {
+            match ImageData::try_from(v).and_then(|image| {
+                image
+                    .into_rgba()
+                    .map_err(|err| zbus::Error::Failure(err.to_string()))
+            }) {
+                Ok(image) => Some(Image::Data {
+                    width: image.width,
+                    height: image.height,
+                    data: Arc::new(image.data),
+                }),
+                Err(err) => {
+                    tracing::warn!("Invalid image data in {}: {}", key, err);
+                    None
+                }
+            }
+        }
+        _ => {
+            tracing::warn!("Invalid value for hint: {}", key);
+            None
+        }
+    }
+}
+
+/// Interpret an `image-path` or `app_icon` string: a file:// URL, an
+/// absolute path, or otherwise an icon name.
+fn image_from_path(s: &str) -> Image {
+    // First try parsing as file:// URL
+    url::Url::parse(s)
+        .ok()
+        .and_then(|u| u.to_file_path().ok())
+        .map(Image::File)
+        // Then check if it's an absolute file path
+        .or_else(|| s.starts_with('/').then(|| Image::File(PathBuf::from(s))))
+        // Otherwise treat as icon name
+        .unwrap_or_else(|| Image::Name(s.to_string()))
+}
+
 #[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
 pub enum ActionId {
     Default,
@@ -374,14 +642,20 @@ impl fmt::Display for ActionId {
     }
 }
 
+impl From<&str> for ActionId {
+    fn from(s: &str) -> Self {
+        match s {
+            "default" => ActionId::Default,
+            s => ActionId::Custom(s.to_string()),
+        }
+    }
+}
+
 impl FromStr for ActionId {
     type Err = Infallible;
 
     fn from_str(s: &str) -> Result<Self, Self::Err> {
-        Ok(match s {
-            "default" => ActionId::Default,
-            s => ActionId::Custom(s.to_string()),
-        })
+        Ok(ActionId::from(s))
     }
 }
 
@@ -389,9 +663,13 @@ impl FromStr for ActionId {
 pub enum Hint {
     ActionIcons(bool),
     Category(String),
+    /// Conversation the message belongs to (`x-kde-conversation`)
+    Conversation(String),
     DesktopEntry(String),
     Image(Image),
     IconData(Vec<u8>),
+    /// Placeholder for the inline reply field (`x-kde-reply-placeholder-text`)
+    ReplyPlaceholder(String),
     Resident(bool),
     SenderPid(u32),
     SoundFile(PathBuf),
@@ -410,6 +688,7 @@ impl Hint {
         match self {
             Hint::ActionIcons(_) => 8,
             Hint::Category(s) => s.len() + 8,
+            Hint::Conversation(s) => s.len() + 8,
             Hint::DesktopEntry(s) => s.len() + 8,
             Hint::Image(img) => match img {
                 Image::Name(s) => s.len() + 8,
@@ -417,6 +696,7 @@ impl Hint {
                 Image::Data { data, .. } => data.len() + 32, // Arc overhead is minimal
             },
             Hint::IconData(data) => data.len() + 8,
+            Hint::ReplyPlaceholder(s) => s.len() + 8,
             Hint::Resident(_) => 8,
             Hint::SenderPid(_) => 8,
             Hint::SoundFile(p) => p.as_os_str().len() + 8,
@@ -486,6 +766,19 @@ pub fn group_notifications(
             }
             groups.into_values().collect()
         }
+        GroupingMode::ByConversation => {
+            let mut groups: HashMap<String, NotificationGroup> = HashMap::new();
+            for notification in notifications {
+                let (key, display) = match notification.conversation_key() {
+                    Some(key) => (key, notification.conversation_title()),
+                    None => (notification.app_name.clone(), notification.app_name.clone()),
+                };
+                groups.entry(key.clone())
+                    .or_insert_with(|| NotificationGroup::new(key, display))
+                    .add(notification.clone());
+            }
+            groups.into_values().collect()
+        }
         GroupingMode::ByCategory => {
             let mut groups: HashMap<String, NotificationGroup> = HashMap::new();
             for notification in notifications {
@@ -635,6 +928,165 @@ mod integration_tests {
         assert!(limited.len() <= 3);
     }
 
+    #[cfg(feature = "zbus_notifications")]
+    #[test]
+    fn test_new_caps_oversized_input() {
+        let long_name = "a".repeat(MAX_APP_NAME_BYTES * 4);
+        let long_summary = "s".repeat(MAX_SUMMARY_BYTES * 4);
+        let long_body = "é".repeat(MAX_MARKUP_BYTES);
+        let action_strs: Vec<String> = (0..MAX_ACTIONS * 2)
+            .flat_map(|i| vec![format!("action{}", i), "L".repeat(MAX_ACTION_LABEL_BYTES * 2)])
+            .collect();
+
+        let notification = Notification::new(
+            &long_name,
+            1,
+            &long_name,
+            &long_summary,
+            &long_body,
+            action_strs.iter().map(|s| s.as_str()).collect(),
+            HashMap::new(),
+            -1,
+        );
+
+        assert_eq!(notification.app_name.len(), MAX_APP_NAME_BYTES);
+        assert_eq!(notification.app_icon.len(), MAX_APP_NAME_BYTES);
+        assert_eq!(notification.summary.len(), MAX_SUMMARY_BYTES);
+        assert!(notification.body.len() <= MAX_MARKUP_BYTES);
+        assert_eq!(notification.actions.len(), MAX_ACTIONS);
+        assert!(notification.actions.iter().all(|(_, label)| label.len() == MAX_ACTION_LABEL_BYTES));
+    }
+
+    #[test]
+    fn test_sound_hints() {
+        let hints = HashMap::from([
+            ("sound-file", zbus::zvariant::Value::from("file:///usr/share/sounds/a%20b.oga")),
+            ("sound-name", zbus::zvariant::Value::from("message-new-instant")),
+            ("suppress-sound", zbus::zvariant::Value::from(true)),
+        ]);
+        let notification = Notification::new("app", 1, "", "summary", "", vec![], hints, -1);
+
+        assert_eq!(
+            notification.sound_file(),
+            Some(std::path::Path::new("/usr/share/sounds/a b.oga"))
+        );
+        assert_eq!(notification.sound_name(), Some("message-new-instant"));
+        assert!(notification.suppress_sound());
+
+        let hints = HashMap::from([("sound-file", zbus::zvariant::Value::from("/tmp/ding.wav"))]);
+        let notification = Notification::new("app", 2, "", "summary", "", vec![], hints, -1);
+        assert_eq!(notification.sound_file(), Some(std::path::Path::new("/tmp/ding.wav")));
+        assert!(!notification.suppress_sound());
+    }
+
+    #[test]
+    fn test_inline_reply() {
+        let hints = HashMap::from([(
+            "x-kde-reply-placeholder-text",
+            zbus::zvariant::Value::from("Reply to Alice"),
+        )]);
+        let notification = Notification::new(
+            "chat",
+            1,
+            "",
+            "Alice",
+            "Lunch?",
+            vec!["default", "Open", INLINE_REPLY_ACTION, "Send"],
+            hints,
+            -1,
+        );
+        assert_eq!(notification.inline_reply_label(), Some("Send"));
+        assert_eq!(notification.reply_placeholder(), Some("Reply to Alice"));
+
+        let plain = Notification::new("app", 2, "", "summary", "", vec!["default", "Open"], HashMap::new(), -1);
+        assert_eq!(plain.inline_reply_label(), None);
+    }
+
+    #[test]
+    fn test_sender_identity() {
+        let hints = HashMap::from([
+            ("desktop-entry", zbus::zvariant::Value::from("org.gnome.Calendar")),
+            ("sender-pid", zbus::zvariant::Value::from(1u32)),
+        ]);
+        let mut notification = Notification::new("Calendar", 1, "", "summary", "", vec![], hints, -1);
+
+        // A launch scope doesn't override what the client said
+        notification.set_sender_identity(42, Some("com.system76.CosmicTerm"), false);
+        assert_eq!(notification.desktop_entry(), Some("org.gnome.Calendar"));
+        assert_eq!(
+            notification.hints.iter().filter(|h| matches!(h, Hint::SenderPid(_))).count(),
+            1
+        );
+        assert!(notification.hints.contains(&Hint::SenderPid(42)));
+
+        // A sandbox does
+        notification.set_sender_identity(42, Some("com.spammy.App"), true);
+        assert_eq!(notification.desktop_entry(), Some("com.spammy.App"));
+
+        // And a scope fills in a missing entry
+        let mut notification = Notification::new("Term", 2, "", "summary", "", vec![], HashMap::new(), -1);
+        notification.set_sender_identity(7, Some("com.system76.CosmicTerm"), false);
+        assert_eq!(notification.desktop_entry(), Some("com.system76.CosmicTerm"));
+    }
+
+    #[test]
+    fn test_is_from_app() {
+        let hints = HashMap::from([(
+            "desktop-entry",
+            zbus::zvariant::Value::from("org.gnome.Calendar"),
+        )]);
+        let notification = Notification::new("Calendar", 1, "", "summary", "", vec![], hints, -1);
+        assert!(notification.is_from_app("calendar"));
+        assert!(notification.is_from_app("org.gnome.calendar"));
+        assert!(!notification.is_from_app("org.gnome"));
+    }
+
+    #[test]
+    fn test_image_hint_precedence() {
+        let pixels = || {
+            zbus::zvariant::Value::from(zbus::zvariant::Structure::from((
+                1i32, 1i32, 4i32, true, 8i32, 4i32, vec![1u8, 2, 3, 4],
+            )))
+        };
+
+        // image-data beats image-path and icon_data
+        let hints = HashMap::from([
+            ("icon_data", pixels()),
+            ("image-path", zbus::zvariant::Value::from("/tmp/photo.png")),
+            ("image-data", pixels()),
+        ]);
+        let notification = Notification::new("app", 1, "app-icon", "summary", "", vec![], hints, -1);
+        assert!(matches!(notification.image(), Some(Image::Data { width: 1, height: 1, .. })));
+        assert_eq!(
+            notification.hints.iter().filter(|h| matches!(h, Hint::Image(_))).count(),
+            1
+        );
+
+        // image-path beats the deprecated icon_data
+        let hints = HashMap::from([
+            ("icon_data", pixels()),
+            ("image-path", zbus::zvariant::Value::from("file:///tmp/photo.png")),
+        ]);
+        let notification = Notification::new("app", 2, "", "summary", "", vec![], hints, -1);
+        assert!(matches!(notification.image(), Some(Image::File(p)) if p == std::path::Path::new("/tmp/photo.png")));
+
+        // icon_data is still used on its own
+        let hints = HashMap::from([("icon_data", pixels())]);
+        let notification = Notification::new("app", 3, "", "summary", "", vec![], hints, -1);
+        assert!(matches!(notification.image(), Some(Image::Data { .. })));
+
+        // Invalid image-data falls through to the next hint
+        let broken = zbus::zvariant::Value::from(zbus::zvariant::Structure::from((
+            2i32, 2i32, 8i32, true, 8i32, 4i32, vec![0u8; 3],
+        )));
+        let hints = HashMap::from([
+            ("image-data", broken),
+            ("image-path", zbus::zvariant::Value::from("avatar-default")),
+        ]);
+        let notification = Notification::new("app", 4, "", "summary", "", vec![], hints, -1);
+        assert!(matches!(notification.image(), Some(Image::Name(n)) if n == "avatar-default"));
+    }
+
     #[test]
     fn test_backward_compatibility_basic_notification() {
         // Test: basic Notification struct without rich content still works
@@ -647,6 +1099,7 @@ mod integration_tests {
             actions: vec![],
             hints: vec![],
             expire_timeout: 5000,
+            applied_timeout: None,
             time: SystemTime::now(),
         };
 
@@ -677,6 +1130,7 @@ mod grouping_tests {
             actions: vec![],
             hints,
             expire_timeout: 5000,
+            applied_timeout: None,
             time: SystemTime::now(),
         }
     }
@@ -800,6 +1254,55 @@ mod grouping_tests {
         assert_eq!(uncat_group.unwrap().count(), 1);
     }
 
+    #[test]
+    fn test_grouping_by_conversation() {
+        let mut alice_1 = create_test_notification(1, "Signal", Some("im.received"));
+        alice_1.summary = "Alice".to_string();
+        let mut alice_2 = create_test_notification(2, "Signal", Some("im.received"));
+        alice_2.summary = "Alice".to_string();
+        let mut bob = create_test_notification(3, "Signal", Some("im.received"));
+        bob.summary = "Bob".to_string();
+        let update = create_test_notification(4, "Updater", None);
+
+        assert_eq!(alice_1.conversation_key(), alice_2.conversation_key());
+        assert_ne!(alice_1.conversation_key(), bob.conversation_key());
+        assert!(update.conversation_key().is_none());
+
+        let groups = group_notifications(&[alice_1, alice_2, bob, update], GroupingMode::ByConversation);
+        assert_eq!(groups.len(), 3);
+
+        let alice = groups.iter().find(|g| g.display_name == "Alice").unwrap();
+        assert_eq!(alice.count(), 2);
+        assert!(groups.iter().any(|g| g.key == "Updater"));
+    }
+
+    #[test]
+    fn test_conversation_threading_hints() {
+        // Counts in the summary don't split a thread
+        let mut first = create_test_notification(1, "Chat", Some("im.received"));
+        first.summary = "Alice".to_string();
+        let mut second = create_test_notification(2, "Chat", Some("im.received"));
+        second.summary = "Alice (2 messages)".to_string();
+        assert_eq!(first.conversation_key(), second.conversation_key());
+        assert_eq!(second.conversation_title(), "Alice");
+
+        // The conversation hint wins over the summary, and needs no category
+        let mut hinted = create_test_notification(3, "Chat", None);
+        hinted.summary = "Bob in Family".to_string();
+        hinted.hints.push(Hint::Conversation("family".to_string()));
+        let mut other = create_test_notification(4, "Chat", None);
+        other.summary = "Carol in Family".to_string();
+        other.hints.push(Hint::Conversation("family".to_string()));
+        assert!(hinted.conversation_key().is_some());
+        assert_eq!(hinted.conversation_key(), other.conversation_key());
+
+        // Apps offering an inline reply are chats even without a category
+        let mut reply = create_test_notification(5, "Chat", None);
+        reply.summary = "Dave".to_string();
+        reply.actions.push((INLINE_REPLY_ACTION.parse().unwrap(), "Send".to_string()));
+        assert!(reply.conversation_key().is_some());
+    }
+
     #[test]
     fn test_grouping_category_display_names() {
         let notifications = vec![
@@ -868,6 +1371,22 @@ mod grouping_tests {
         assert_eq!(groups_cat.len(), 0);
     }
 
+    #[test]
+    fn test_redacted_keeps_only_the_app() {
+        let mut n = create_test_notification(1, "Signal", Some("im.received"));
+        n.actions = vec![(ActionId::Default, "Open".to_string())];
+        n.hints.push(Hint::Image(Image::Name("avatar".to_string())));
+        n.hints.push(Hint::Value(40));
+
+        let redacted = n.redacted("New notification from Signal".to_string());
+        assert_eq!(redacted.summary, "New notification from Signal");
+        assert!(redacted.body.is_empty());
+        assert!(redacted.actions.is_empty());
+        assert!(redacted.image().is_none());
+        assert_eq!(redacted.hints, vec![Hint::Category("im.received".to_string())]);
+        assert_eq!((redacted.id, redacted.app_name.as_str()), (1, "Signal"));
+    }
+
     #[test]
     fn test_single_notification() {
         let notifications = vec![create_test_notification(1, "Firefox", Some("email"))];
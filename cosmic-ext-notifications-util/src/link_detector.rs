@@ -1,5 +1,18 @@
 use linkify::{LinkFinder, LinkKind};
-use crate::NotificationLink;
+use crate::{extract_hrefs, NotificationLink};
+
+/// An anchor link checked for a mismatch between its text and destination
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CheckedLink {
+  /// The href the link actually opens
+  pub url: String,
+  /// The text shown to the user
+  pub text: String,
+  /// Host the link actually points at, if it has one
+  pub host: Option<String>,
+  /// True when the text names a different host than the href
+  pub mismatch: bool,
+}
 
 /// Detect URLs and emails in text
 pub fn detect_links(text: &str) -> Vec<NotificationLink> {
@@ -45,6 +58,73 @@ pub fn is_safe_url(url: &str) -> bool {
   }
 }
 
+/// Extract anchor links from HTML and flag deceptive ones
+///
+/// A link is flagged when its display text looks like a domain or URL but
+/// names a different host than its href, e.g.
+/// `<a href="https://evil.com">paypal.com</a>`. Links with ordinary text like
+/// "click here" are never flagged.
+pub fn check_links(html: &str) -> Vec<CheckedLink> {
+  extract_hrefs(html)
+    .into_iter()
+    .map(|(url, text)| {
+      let host = url_host(&url);
+      let mismatch = is_link_text_mismatch(&text, &url);
+      CheckedLink { url, text, host, mismatch }
+    })
+    .collect()
+}
+
+/// Check whether link text names a different host than the URL it opens
+///
+/// The URL host may be a subdomain of the host in the text (`paypal.com`
+/// linking to `https://www.paypal.com/signin` is fine), but not the other way
+/// around. Hosts are compared after IDNA normalization, so lookalike Unicode
+/// domains in the text do not match their ASCII counterparts.
+pub fn is_link_text_mismatch(text: &str, url: &str) -> bool {
+  let Some(text_host) = text_host(text) else {
+    return false;
+  };
+  let Some(url_host) = url_host(url) else {
+    return false;
+  };
+
+  let text_host = text_host.strip_prefix("www.").unwrap_or(&text_host);
+  let url_host = url_host.strip_prefix("www.").unwrap_or(&url_host);
+
+  !(url_host == text_host || url_host.ends_with(&format!(".{text_host}")))
+}
+
+/// Host of an http(s) URL, lowercased and IDNA-encoded
+fn url_host(url: &str) -> Option<String> {
+  let parsed = url::Url::parse(url).ok()?;
+  if !matches!(parsed.scheme(), "http" | "https") {
+    return None;
+  }
+  parsed.host_str().map(str::to_lowercase)
+}
+
+/// Host named by link text, if the text looks like a URL or bare domain
+fn text_host(text: &str) -> Option<String> {
+  let text = text.trim();
+  if text.is_empty() || text.chars().any(char::is_whitespace) {
+    return None;
+  }
+
+  let lower = text.to_lowercase();
+  if lower.starts_with("http://") || lower.starts_with("https://") {
+    return url_host(text);
+  }
+
+  // Bare domain: needs a dot and an alphabetic TLD, so "v1.2" is not a host
+  let candidate = text.split(['/', '?', '#']).next()?;
+  let tld = candidate.rsplit('.').next()?;
+  if !candidate.contains('.') || tld.chars().count() < 2 || !tld.chars().all(char::is_alphabetic) {
+    return None;
+  }
+  url_host(&format!("https://{candidate}"))
+}
+
 /// Open a URL in the default browser/handler
 pub fn open_link(url: &str) -> Result<(), std::io::Error> {
   if !is_safe_url(url) {
@@ -99,6 +179,44 @@ mod tests {
     assert!(!is_safe_url("mailto:"), "Bare mailto: should not be safe");
   }
 
+  #[test]
+  fn test_link_text_mismatch_detected() {
+    assert!(is_link_text_mismatch("paypal.com", "https://evil.com"));
+    assert!(is_link_text_mismatch("https://paypal.com", "https://evil.com/login"));
+    assert!(is_link_text_mismatch("paypal.com", "https://paypal.com.evil.com"));
+  }
+
+  #[test]
+  fn test_link_text_matches_host() {
+    assert!(!is_link_text_mismatch("example.com", "https://example.com/page"));
+    assert!(!is_link_text_mismatch("Example.COM", "https://example.com"));
+    assert!(!is_link_text_mismatch("paypal.com", "https://www.paypal.com/signin"));
+    assert!(!is_link_text_mismatch("www.example.com/docs", "https://example.com/docs"));
+  }
+
+  #[test]
+  fn test_plain_link_text_not_flagged() {
+    assert!(!is_link_text_mismatch("click here", "https://evil.com"));
+    assert!(!is_link_text_mismatch("v1.2", "https://evil.com"));
+    assert!(!is_link_text_mismatch("", "https://evil.com"));
+  }
+
+  #[test]
+  fn test_lookalike_domain_flagged() {
+    // Cyrillic "а" instead of Latin "a"
+    assert!(is_link_text_mismatch("p\u{430}ypal.com", "https://paypal.com"));
+  }
+
+  #[test]
+  fn test_check_links() {
+    let html = r#"<a href="https://evil.com">paypal.com</a> and <a href="https://example.com">docs</a>"#;
+    let links = check_links(html);
+    assert_eq!(links.len(), 2);
+    assert!(links[0].mismatch);
+    assert_eq!(links[0].host.as_deref(), Some("evil.com"));
+    assert!(!links[1].mismatch);
+  }
+
   #[test]
   fn test_no_links_in_plain_text() {
     let text = "Just plain text without any links";
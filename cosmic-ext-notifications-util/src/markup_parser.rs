@@ -12,6 +12,30 @@ pub struct TextStyle {
     pub bold: bool,
     pub italic: bool,
     pub underline: bool,
+    /// Heading level (1-4) when the text came from an `<h1>`-`<h4>` tag
+    pub heading: Option<u8>,
+    /// Blockquote nesting depth, 0 outside of any `<blockquote>`
+    pub quote_level: u8,
+}
+
+impl TextStyle {
+    /// Font size of this style relative to normal body text
+    ///
+    /// Headings are rendered progressively larger, plain text returns 1.0.
+    pub fn relative_size(&self) -> f32 {
+        match self.heading {
+            Some(1) => 1.5,
+            Some(2) => 1.35,
+            Some(3) => 1.2,
+            Some(4) => 1.1,
+            _ => 1.0,
+        }
+    }
+
+    /// Check if this text is inside a blockquote
+    pub fn is_quoted(&self) -> bool {
+        self.quote_level > 0
+    }
 }
 
 /// A segment of styled text
@@ -53,118 +77,199 @@ impl StyledSegment {
 
 /// Parse sanitized HTML into styled text segments
 ///
-/// Supports: <b>, <i>, <u>, <a href="...">
+/// Supports: <b>, <i>, <u>, <a href="...">, <h1>-<h4>, <blockquote>
 /// Nested tags are supported (e.g., <b><i>bold italic</i></b>)
 ///
+/// Headings are mapped to bold segments carrying their level in
+/// [`TextStyle::heading`] and are kept on their own line. Blockquotes are
+/// also kept on their own lines and raise [`TextStyle::quote_level`] by one
+/// per nesting level, so quoted replies can be indented.
+///
+/// Malformed markup is recovered from rather than rejected:
+/// - A closing tag closes its nearest matching open tag and every tag opened
+///   after it (close-through), so `<b><i>text</b></i>` ends both styles at
+///   `</b>` and the stray `</i>` is ignored
+/// - Closing tags with no matching open tag are ignored
+/// - A `<` that doesn't start a well-formed tag is kept as literal text
+///
+/// The concatenated segment text always equals the input with tags removed
+/// and entities decoded, apart from the line breaks inserted for `<br>`,
+/// `<p>`, headings and blockquotes.
+///
+/// Input beyond [`crate::sanitizer::MAX_MARKUP_BYTES`] is ignored.
+///
 /// SECURITY: Input must be pre-sanitized with ammonia to remove dangerous content.
 /// This parser validates URLs and uses case-insensitive tag matching.
 pub fn parse_markup(html: &str) -> Vec<StyledSegment> {
+    parse_uncapped(crate::sanitizer::cap_input(html, crate::sanitizer::MAX_MARKUP_BYTES))
+}
+
+/// [`parse_markup`] for input that was capped already
+///
+/// Sanitized markup can be longer than what went in, as ammonia escapes
+/// `&` and adds `rel` to links; capping it again could cut it off mid-tag.
+pub(crate) fn parse_uncapped(html: &str) -> Vec<StyledSegment> {
     let mut segments = Vec::new();
     let mut current_style = TextStyle::default();
     let mut current_link: Option<String> = None;
     let mut style_stack: Vec<(String, TextStyle, Option<String>)> = Vec::new();
 
-    let mut chars = html.chars().peekable();
-    let mut current_text = String::new();
-
-    while let Some(ch) = chars.next() {
-        if ch == '<' {
-            // Save any accumulated text
-            if !current_text.is_empty() {
-                let decoded = decode_entities(&current_text);
-                if !decoded.is_empty() {
-                    segments.push(StyledSegment {
-                        text: decoded,
-                        style: current_style.clone(),
-                        link: current_link.clone(),
-                    });
-                }
-                current_text.clear();
-            }
-
-            // Parse the tag
-            if let Some(tag) = parse_tag(&mut chars) {
-                match tag {
-                    Tag::Open(name, attrs) => {
-                        let tag_lower = name.to_lowercase();
-                        let prev_style = current_style.clone();
-                        let prev_link = current_link.clone();
+    // Start of the text not yet turned into a segment
+    let mut text_start = 0;
+    let mut cursor = 0;
+    // The next '>' is remembered so stray '<'s don't rescan the rest of the input
+    let mut tag_end = html.find('>');
 
-                        match tag_lower.as_str() {
-                            "b" | "strong" => {
-                                style_stack.push((tag_lower, prev_style, prev_link));
-                                current_style.bold = true;
-                            }
-                            "i" | "em" => {
-                                style_stack.push((tag_lower, prev_style, prev_link));
-                                current_style.italic = true;
-                            }
-                            "u" => {
+    while let Some(offset) = html[cursor..].find('<') {
+        let open = cursor + offset;
+        if tag_end.is_some_and(|end| end < open) {
+            tag_end = html[open..].find('>').map(|i| open + i);
+        }
+        // Without a closing '>' the rest of the input is text
+        let Some(end) = tag_end else {
+            break;
+        };
+        // A stray '<' is kept as text
+        let Some(tag) = parse_tag(&html[open + 1..end]) else {
+            cursor = open + 1;
+            continue;
+        };
+
+        // Save any accumulated text
+        push_text(&mut segments, &html[text_start..open], &current_style, &current_link);
+        cursor = end + 1;
+        text_start = cursor;
+
+        match tag {
+            Tag::Open(name, attrs) => {
+                let tag_lower = name.to_lowercase();
+                let prev_style = current_style.clone();
+                let prev_link = current_link.clone();
+
+                match tag_lower.as_str() {
+                    "b" | "strong" => {
+                        style_stack.push((tag_lower, prev_style, prev_link));
+                        current_style.bold = true;
+                    }
+                    "i" | "em" => {
+                        style_stack.push((tag_lower, prev_style, prev_link));
+                        current_style.italic = true;
+                    }
+                    "u" => {
+                        style_stack.push((tag_lower, prev_style, prev_link));
+                        current_style.underline = true;
+                    }
+                    "a" => {
+                        if let Some(href) = attrs.get("href") {
+                            // Validate URL is safe
+                            if is_safe_url(href) {
+                                let decoded_url = decode_entities(href);
                                 style_stack.push((tag_lower, prev_style, prev_link));
+                                current_link = Some(decoded_url);
                                 current_style.underline = true;
                             }
-                            "a" => {
-                                if let Some(href) = attrs.get("href") {
-                                    // Validate URL is safe
-                                    if is_safe_url(href) {
-                                        let decoded_url = decode_entities(href);
-                                        style_stack.push((tag_lower, prev_style, prev_link));
-                                        current_link = Some(decoded_url);
-                                        current_style.underline = true;
-                                    }
-                                }
-                            }
-                            "br" | "p" => {
-                                segments.push(StyledSegment::plain("\n"));
-                            }
-                            _ => {} // Ignore unknown tags
                         }
                     }
-                    Tag::Close(name) => {
-                        let tag_lower = name.to_lowercase();
-                        // Only pop from stack if the TOP matches (proper nesting)
-                        if let Some((tag, _, _)) = style_stack.last() {
-                            let matches = *tag == tag_lower
-                                || (*tag == "b" && tag_lower == "strong")
-                                || (*tag == "strong" && tag_lower == "b")
-                                || (*tag == "i" && tag_lower == "em")
-                                || (*tag == "em" && tag_lower == "i");
-
-                            if matches {
-                                if let Some((_, prev_style, prev_link)) = style_stack.pop() {
-                                    current_style = prev_style;
-                                    current_link = prev_link;
-                                }
-                            }
-                            // If no match, ignore the closing tag (malformed HTML)
+                    "br" | "p" => {
+                        push_text(&mut segments, "\n", &TextStyle::default(), &None);
+                    }
+                    "h1" | "h2" | "h3" | "h4" => {
+                        // Start the heading on its own line
+                        if segments.last().is_some_and(|s: &StyledSegment| !s.text.ends_with('\n')) {
+                            push_text(&mut segments, "\n", &TextStyle::default(), &None);
                         }
+                        current_style.heading = heading_level(&tag_lower);
+                        current_style.bold = true;
+                        style_stack.push((tag_lower, prev_style, prev_link));
+                    }
+                    "blockquote" => {
+                        if segments.last().is_some_and(|s: &StyledSegment| !s.text.ends_with('\n')) {
+                            push_text(&mut segments, "\n", &TextStyle::default(), &None);
+                        }
+                        current_style.quote_level = current_style.quote_level.saturating_add(1);
+                        style_stack.push((tag_lower, prev_style, prev_link));
+                    }
+                    _ => {} // Ignore unknown tags
+                }
+            }
+            Tag::Close(name) => {
+                let tag_lower = name.to_lowercase();
+                // Close-through: close the nearest matching tag along with
+                // everything opened after it. Unmatched closing tags are ignored.
+                if let Some(pos) = style_stack
+                    .iter()
+                    .rposition(|(tag, _, _)| tags_match(tag, &tag_lower))
+                {
+                    let closed = style_stack.split_off(pos);
+                    let ends_block = closed.iter().any(|(tag, _, _)| is_block_tag(tag));
+                    if let Some((_, prev_style, prev_link)) = closed.into_iter().next() {
+                        current_style = prev_style;
+                        current_link = prev_link;
+                    }
+                    // Body text continues on the line after a heading or quote
+                    if ends_block {
+                        push_text(&mut segments, "\n", &TextStyle::default(), &None);
                     }
                 }
             }
-        } else {
-            current_text.push(ch);
         }
     }
 
     // Add any remaining text
-    if !current_text.is_empty() {
-        let decoded = decode_entities(&current_text);
-        if !decoded.is_empty() {
-            segments.push(StyledSegment {
-                text: decoded,
-                style: current_style,
-                link: current_link,
-            });
+    push_text(&mut segments, &html[text_start..], &current_style, &current_link);
+
+    segments
+}
+
+/// Decode `text` and append it, extending the last segment if it has the
+/// same style and link
+fn push_text(
+    segments: &mut Vec<StyledSegment>,
+    text: &str,
+    style: &TextStyle,
+    link: &Option<String>,
+) {
+    if text.is_empty() {
+        return;
+    }
+    let decoded = decode_entities(text);
+    if decoded.is_empty() {
+        return;
+    }
+    if let Some(last) = segments.last_mut() {
+        if last.style == *style && last.link == *link {
+            last.text.push_str(&decoded);
+            return;
         }
     }
+    segments.push(StyledSegment {
+        text: decoded,
+        style: style.clone(),
+        link: link.clone(),
+    });
+}
 
-    // If no segments, return plain text
-    if segments.is_empty() && !html.is_empty() {
-        segments.push(StyledSegment::plain(decode_entities(html)));
+/// Map a lowercase heading tag name to its level
+fn heading_level(tag: &str) -> Option<u8> {
+    match tag {
+        "h1" => Some(1),
+        "h2" => Some(2),
+        "h3" => Some(3),
+        "h4" => Some(4),
+        _ => None,
     }
+}
 
-    // Merge adjacent segments with same style
-    merge_segments(segments)
+/// Check if a closing tag name closes an open tag, treating b/strong and
+/// i/em as equivalent
+fn tags_match(open: &str, close: &str) -> bool {
+    open == close
+        || matches!((open, close), ("b", "strong") | ("strong", "b") | ("i", "em") | ("em", "i"))
+}
+
+/// Tags that are rendered on their own lines
+fn is_block_tag(tag: &str) -> bool {
+    heading_level(tag).is_some() || tag == "blockquote"
 }
 
 /// Represents a parsed HTML tag
@@ -174,31 +279,23 @@ enum Tag {
     Close(String),
 }
 
-/// Parse a single HTML tag using character-by-character state machine
-fn parse_tag(chars: &mut std::iter::Peekable<std::str::Chars>) -> Option<Tag> {
-    let mut tag_content = String::new();
-
-    // Read until '>'
-    while let Some(&ch) = chars.peek() {
-        chars.next();
-        if ch == '>' {
-            break;
-        }
-        tag_content.push(ch);
-    }
-
-    if tag_content.is_empty() {
-        return None;
-    }
-
+/// Parse the text between `<` and `>` as an HTML tag
+///
+/// Returns None if the tag name doesn't start with a letter (e.g. `5 < 10`).
+fn parse_tag(tag_content: &str) -> Option<Tag> {
     // Check if closing tag
+    let tag_content = tag_content.trim_start();
     let is_closing = tag_content.starts_with('/');
     let tag_content = if is_closing {
-        &tag_content[1..]
+        tag_content[1..].trim_start()
     } else {
-        &tag_content
+        tag_content
     };
 
+    if !tag_content.starts_with(|c: char| c.is_ascii_alphabetic()) {
+        return None;
+    }
+
     // Split tag name from attributes
     let parts: Vec<&str> = tag_content.split_whitespace().collect();
     if parts.is_empty() {
@@ -331,21 +428,55 @@ fn decode_entities(text: &str) -> String {
         .replace("&nbsp;", " ")
 }
 
-/// Merge adjacent segments with the same style
-fn merge_segments(segments: Vec<StyledSegment>) -> Vec<StyledSegment> {
-    let mut merged: Vec<StyledSegment> = Vec::new();
+/// Turn bare URLs and email addresses in plain segments into link segments
+///
+/// Segments that are already links (from `<a href>`) are left untouched.
+/// Detected links keep the surrounding style, are underlined like anchor
+/// links, and point at the same URLs [`crate::detect_links`] would produce
+/// (`mailto:` for email addresses), so existing click handling applies.
+pub fn linkify_segments(segments: Vec<StyledSegment>) -> Vec<StyledSegment> {
+    let mut result = Vec::with_capacity(segments.len());
 
     for segment in segments {
-        if let Some(last) = merged.last_mut() {
-            if last.style == segment.style && last.link == segment.link {
-                last.text.push_str(&segment.text);
-                continue;
+        if segment.link.is_some() {
+            result.push(segment);
+            continue;
+        }
+
+        let links = crate::detect_links(&segment.text);
+        if links.is_empty() {
+            result.push(segment);
+            continue;
+        }
+
+        let mut pos = 0;
+        for link in links {
+            if link.start > pos {
+                result.push(StyledSegment::styled(
+                    &segment.text[pos..link.start],
+                    segment.style.clone(),
+                ));
             }
+            let end = link.start + link.length;
+            result.push(StyledSegment {
+                text: segment.text[link.start..end].to_string(),
+                style: TextStyle {
+                    underline: true,
+                    ..segment.style.clone()
+                },
+                link: Some(link.url),
+            });
+            pos = end;
+        }
+        if pos < segment.text.len() {
+            result.push(StyledSegment::styled(
+                &segment.text[pos..],
+                segment.style.clone(),
+            ));
         }
-        merged.push(segment);
     }
 
-    merged
+    result
 }
 
 /// Convert segments back to plain text (for fallback)
@@ -589,6 +720,117 @@ mod tests {
         assert!(segments[2].style.italic);
     }
 
+    #[test]
+    fn test_heading_tags() {
+        let segments = parse_markup("<h1>Title</h1>Body text");
+        assert_eq!(segments.len(), 2);
+        assert_eq!(segments[0].text, "Title");
+        assert!(segments[0].style.bold);
+        assert_eq!(segments[0].style.heading, Some(1));
+        assert_eq!(segments[1].text, "\nBody text");
+        assert_eq!(segments[1].style.heading, None);
+        assert!(!segments[1].style.bold);
+    }
+
+    #[test]
+    fn test_heading_starts_on_new_line() {
+        let segments = parse_markup("Intro<h2>Section</h2>");
+        let plain = segments_to_plain_text(&segments);
+        assert_eq!(plain, "Intro\nSection\n");
+        let heading = segments.iter().find(|s| s.style.heading.is_some()).unwrap();
+        assert_eq!(heading.style.heading, Some(2));
+    }
+
+    #[test]
+    fn test_heading_relative_sizes() {
+        let sizes: Vec<f32> = (1..=4)
+            .map(|level| TextStyle { heading: Some(level), ..Default::default() }.relative_size())
+            .collect();
+        assert!(sizes.windows(2).all(|w| w[0] > w[1]), "h1 should be largest");
+        assert_eq!(TextStyle::default().relative_size(), 1.0);
+    }
+
+    #[test]
+    fn test_unsupported_heading_levels_ignored() {
+        let segments = parse_markup("<h5>Small</h5>");
+        assert_eq!(segments.len(), 1);
+        assert_eq!(segments[0].style.heading, None);
+    }
+
+    #[test]
+    fn test_blockquote_sets_quote_level() {
+        let segments = parse_markup("Thanks!<blockquote>Original message</blockquote>");
+        let quoted = segments.iter().find(|s| s.text == "Original message").unwrap();
+        assert_eq!(quoted.style.quote_level, 1);
+        assert!(quoted.style.is_quoted());
+        assert!(!segments[0].style.is_quoted());
+        assert_eq!(segments_to_plain_text(&segments), "Thanks!\nOriginal message\n");
+    }
+
+    #[test]
+    fn test_nested_blockquotes() {
+        let segments = parse_markup("<blockquote>Reply<blockquote>Earlier</blockquote>Back</blockquote>After");
+        let level = |text: &str| segments.iter().find(|s| s.text.contains(text)).unwrap().style.quote_level;
+        assert_eq!(level("Reply"), 1);
+        assert_eq!(level("Earlier"), 2);
+        assert_eq!(level("Back"), 1);
+        assert_eq!(level("After"), 0);
+    }
+
+    #[test]
+    fn test_blockquote_keeps_inline_styles() {
+        let segments = parse_markup("<blockquote><b>Bold quote</b></blockquote>");
+        let quoted = segments.iter().find(|s| s.text == "Bold quote").unwrap();
+        assert!(quoted.style.bold);
+        assert_eq!(quoted.style.quote_level, 1);
+    }
+
+    #[test]
+    fn test_linkify_bare_url() {
+        let segments = linkify_segments(parse_markup("See https://example.com/page for details"));
+        assert_eq!(segments.len(), 3);
+        assert_eq!(segments[0].text, "See ");
+        assert_eq!(segments[1].text, "https://example.com/page");
+        assert_eq!(segments[1].link, Some("https://example.com/page".to_string()));
+        assert!(segments[1].style.underline);
+        assert_eq!(segments[2].text, " for details");
+        assert_eq!(segments[2].link, None);
+    }
+
+    #[test]
+    fn test_linkify_email() {
+        let segments = linkify_segments(parse_markup("Mail user@example.com"));
+        let link = segments.iter().find(|s| s.link.is_some()).unwrap();
+        assert_eq!(link.text, "user@example.com");
+        assert_eq!(link.link, Some("mailto:user@example.com".to_string()));
+    }
+
+    #[test]
+    fn test_linkify_keeps_style_and_existing_links() {
+        let segments = linkify_segments(parse_markup(
+            r#"<b>Build https://ci.example.com</b> <a href="https://example.com">docs</a>"#,
+        ));
+        let bare = segments.iter().find(|s| s.text == "https://ci.example.com").unwrap();
+        assert!(bare.style.bold);
+        assert_eq!(bare.link, Some("https://ci.example.com".to_string()));
+        let anchor = segments.iter().find(|s| s.text == "docs").unwrap();
+        assert_eq!(anchor.link, Some("https://example.com".to_string()));
+    }
+
+    #[test]
+    fn test_linkify_leaves_anchor_text_alone() {
+        // A URL in anchor text must keep pointing at the href
+        let segments = parse_markup(r#"<a href="https://example.com/real">https://example.com/shown</a>"#);
+        assert_eq!(linkify_segments(segments.clone()), segments);
+        assert_eq!(segments[0].link, Some("https://example.com/real".to_string()));
+    }
+
+    #[test]
+    fn test_linkify_plain_text_unchanged() {
+        let segments = parse_markup("No links here");
+        assert_eq!(linkify_segments(segments.clone()), segments);
+    }
+
     #[test]
     fn test_whitespace_in_tags() {
         let html = r#"<  b  >bold<  /  b  >"#;
@@ -596,4 +838,121 @@ mod tests {
         // Should handle whitespace gracefully
         assert!(segments.iter().any(|s| s.text.contains("bold")));
     }
+
+    #[test]
+    fn test_misnested_tags_close_through() {
+        let segments = parse_markup("<b><i>text</b></i> after");
+        assert_eq!(segments.len(), 2);
+        assert_eq!(segments[0].text, "text");
+        assert!(segments[0].style.bold);
+        assert!(segments[0].style.italic);
+        assert_eq!(segments[1].text, " after");
+        assert_eq!(segments[1].style, TextStyle::default());
+    }
+
+    #[test]
+    fn test_close_through_restores_outer_style() {
+        let segments = parse_markup("<u>a<b>b<i>c</b>d</u>e");
+        let style = |text: &str| segments.iter().find(|s| s.text == text).unwrap().style.clone();
+        assert!(style("c").bold && style("c").italic && style("c").underline);
+        assert!(style("d").underline && !style("d").bold && !style("d").italic);
+        assert_eq!(style("e"), TextStyle::default());
+    }
+
+    #[test]
+    fn test_stray_closing_tag_ignored() {
+        let segments = parse_markup("plain</b> <b>bold</b>");
+        assert_eq!(segments_to_plain_text(&segments), "plain bold");
+        assert!(!segments[0].style.bold);
+    }
+
+    #[test]
+    fn test_stray_angle_brackets_kept() {
+        assert_eq!(segments_to_plain_text(&parse_markup("5 < 10 and 3 <> 2")), "5 < 10 and 3 <> 2");
+        assert_eq!(segments_to_plain_text(&parse_markup("<b>x</b> <unterminated")), "x <unterminated");
+    }
+
+    #[test]
+    fn test_many_stray_angle_brackets() {
+        // Each stray '<' must not rescan the rest of the input
+        let input = format!("{}<b>x</b>", "< ".repeat(30_000));
+        let segments = parse_markup(&input);
+        assert_eq!(segments.len(), 2);
+        assert_eq!(segments[0].text, "< ".repeat(30_000));
+        assert_eq!(segments[1].text, "x");
+        assert!(segments[1].style.bold);
+    }
+
+    #[test]
+    fn test_tags_only_yield_no_text() {
+        assert_eq!(segments_to_plain_text(&parse_markup("<b></b><i></i>")), "");
+    }
+
+    /// Reference stripper: drop well-formed tags and decode entities
+    fn reference_strip(html: &str) -> String {
+        let mut out = String::new();
+        let mut rest = html;
+        while let Some(start) = rest.find('<') {
+            out.push_str(&rest[..start]);
+            let after = &rest[start + 1..];
+            let is_tag = after.find('>').is_some_and(|end| {
+                let inner = after[..end].trim_start();
+                let inner = inner.strip_prefix('/').map_or(inner, str::trim_start);
+                inner.starts_with(|c: char| c.is_ascii_alphabetic())
+            });
+            if is_tag {
+                rest = &after[after.find('>').unwrap() + 1..];
+            } else {
+                out.push('<');
+                rest = after;
+            }
+        }
+        out.push_str(rest);
+        decode_entities(&out)
+    }
+
+    /// Deterministic xorshift generator so failures are reproducible
+    struct Rng(u64);
+
+    impl Rng {
+        fn next(&mut self) -> u64 {
+            self.0 ^= self.0 << 13;
+            self.0 ^= self.0 >> 7;
+            self.0 ^= self.0 << 17;
+            self.0
+        }
+
+        fn pick<'a>(&mut self, items: &[&'a str]) -> &'a str {
+            items[(self.next() % items.len() as u64) as usize]
+        }
+    }
+
+    #[test]
+    fn test_fuzz_output_text_matches_stripped_input() {
+        const PIECES: &[&str] = &[
+            "<b>", "</b>", "<i>", "</i>", "<u>", "</u>", "<strong>", "</em>",
+            "<a href=\"https://example.com\">", "<a href=\"javascript:x\">", "</a>",
+            "<h1>", "</h2>", "<blockquote>", "</blockquote>", "<br>", "<p>", "<unknown>",
+            "<", ">", "</", "< b >", "<>", "text", " ", "é", "&amp;", "&lt;", "5 < 6",
+            "<b", "\"", "x>y",
+        ];
+
+        let mut rng = Rng(0x2545_f491_4f6c_dd1d);
+        for case in 0..2000 {
+            let len = (rng.next() % 12) as usize;
+            let input: String = (0..len).map(|_| rng.pick(PIECES)).collect();
+
+            let segments = parse_markup(&input);
+            let output = segments_to_plain_text(&segments).replace('\n', "");
+            let expected = reference_strip(&input).replace('\n', "");
+            assert_eq!(output, expected, "case {case}: input {input:?}");
+
+            // Merged output never has empty or mergeable neighbours
+            assert!(segments.iter().all(|s| !s.text.is_empty()), "case {case}: empty segment");
+            assert!(
+                segments.windows(2).all(|w| w[0].style != w[1].style || w[0].link != w[1].link),
+                "case {case}: unmerged segments"
+            );
+        }
+    }
 }
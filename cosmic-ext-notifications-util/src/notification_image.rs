@@ -5,8 +5,10 @@
 /// - File paths to image files
 ///
 /// All images are normalized to RGBA format and resized to fit within maximum dimensions
-/// while preserving aspect ratio.
+/// while preserving aspect ratio. An [`ImagePolicy`] can then crop or letterbox the
+/// image and mask its corners.
 
+use cosmic_ext_notifications_config::ImageFit;
 use fast_image_resize as fr;
 use image::ImageError;
 
@@ -16,6 +18,18 @@ pub const MAX_IMAGE_WIDTH: u32 = 128;
 /// Maximum height for notification images in pixels
 pub const MAX_IMAGE_HEIGHT: u32 = 128;
 
+/// Largest image file that is decoded at all, in bytes
+pub const MAX_IMAGE_FILE_BYTES: u64 = 64 * 1024 * 1024;
+
+/// Largest width or height an encoded image may declare
+pub const MAX_DECODE_DIMENSION: u32 = 16_384;
+
+/// Most memory a decoder may allocate for one image, in bytes
+///
+/// Leaves room for a 20 megapixel photo, the RGBA copy taken of it, and
+/// the decoder's own buffers.
+pub const MAX_DECODE_ALLOC: u64 = 512 * 1024 * 1024;
+
 /// Processed notification image ready for display
 #[derive(Debug, Clone)]
 pub struct ProcessedImage {
@@ -27,6 +41,36 @@ pub struct ProcessedImage {
   pub height: u32,
 }
 
+/// Presentation applied to an image after decoding and resizing
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct ImagePolicy {
+  /// Radius of the rounded corners in pixels (0 = square corners)
+  pub corner_radius: u32,
+  /// How non-square images fill the square image slot
+  pub fit: ImageFit,
+  /// Mask square images to a circle, for avatars from chat apps
+  pub circle_avatar: bool,
+}
+
+impl ImagePolicy {
+  /// Build the policy for a notification's image
+  ///
+  /// Circle masking only applies to message categories (chat, email), since
+  /// square images from other apps are usually icons or screenshots.
+  pub fn for_category(
+    corner_radius: u32,
+    fit: ImageFit,
+    circle_avatars: bool,
+    category: Option<&str>,
+  ) -> Self {
+    Self {
+      corner_radius,
+      fit,
+      circle_avatar: circle_avatars && category.is_some_and(crate::is_message_category),
+    }
+  }
+}
+
 /// Notification image processor
 pub struct NotificationImage;
 
@@ -67,8 +111,13 @@ impl NotificationImage {
     let height = height as u32;
     let channels = if has_alpha { 4 } else { 3 };
 
-    // Validate data length
-    if data.len() < (rowstride * height as i32) as usize {
+    // Validate row layout; the last row doesn't need to be padded to rowstride
+    let row_len = (width * channels) as usize;
+    let stride = usize::try_from(rowstride).unwrap_or(0);
+    let required = stride
+      .checked_mul(height as usize - 1)
+      .and_then(|n| n.checked_add(row_len));
+    if stride < row_len || required.is_none_or(|n| data.len() < n) {
       return Err(ImageError::Limits(
         image::error::LimitError::from_kind(
           image::error::LimitErrorKind::InsufficientMemory,
@@ -79,8 +128,8 @@ impl NotificationImage {
     // Extract pixel data handling rowstride
     let mut pixel_data = Vec::with_capacity((width * height * channels) as usize);
     for y in 0..height {
-      let row_start = (y as i32 * rowstride) as usize;
-      let row_data = &data[row_start..row_start + (width * channels) as usize];
+      let row_start = y as usize * stride;
+      let row_data = &data[row_start..row_start + row_len];
       pixel_data.extend_from_slice(row_data);
     }
 
@@ -121,9 +170,42 @@ impl NotificationImage {
   ///
   /// Returns `ImageError` if the file cannot be read or is not a valid image.
   pub fn from_path(path: &str) -> Result<ProcessedImage, ImageError> {
-    // Load image from file
-    let img = image::open(path)?;
+    // Refuse huge files before reading them
+    if std::fs::metadata(path)?.len() > MAX_IMAGE_FILE_BYTES {
+      return Err(Self::too_large());
+    }
+    let mut reader = image::ImageReader::open(path)?.with_guessed_format()?;
+    reader.limits(Self::decode_limits());
+    Self::from_dynamic(reader.decode()?)
+  }
+
+  /// Decode and process an encoded image (PNG, JPEG, ...) held in memory.
+  ///
+  /// # Errors
+  ///
+  /// Returns `ImageError` if the format is unknown or the data is corrupt.
+  pub fn from_bytes(bytes: &[u8]) -> Result<ProcessedImage, ImageError> {
+    let mut reader = image::ImageReader::new(std::io::Cursor::new(bytes)).with_guessed_format()?;
+    reader.limits(Self::decode_limits());
+    Self::from_dynamic(reader.decode()?)
+  }
+
+  /// Caps on what an encoded image may make the decoder allocate
+  pub(crate) fn decode_limits() -> image::Limits {
+    let mut limits = image::Limits::default();
+    limits.max_image_width = Some(MAX_DECODE_DIMENSION);
+    limits.max_image_height = Some(MAX_DECODE_DIMENSION);
+    limits.max_alloc = Some(MAX_DECODE_ALLOC);
+    limits
+  }
+
+  fn too_large() -> ImageError {
+    ImageError::Limits(image::error::LimitError::from_kind(
+      image::error::LimitErrorKind::InsufficientMemory,
+    ))
+  }
 
+  fn from_dynamic(img: image::DynamicImage) -> Result<ProcessedImage, ImageError> {
     // Convert to RGBA
     let rgba_img = img.to_rgba8();
     let width = rgba_img.width();
@@ -140,10 +222,112 @@ impl NotificationImage {
     })
   }
 
+  /// Apply crop/letterbox and corner masking to a processed image.
+  ///
+  /// Images that end up square (within a few pixels) are treated as avatars
+  /// when the policy asks for it and get a circular mask instead of rounded
+  /// corners.
+  pub fn apply_policy(image: ProcessedImage, policy: &ImagePolicy) -> ProcessedImage {
+    if image.width == 0 || image.height == 0 {
+      return image;
+    }
+
+    let mut image = match policy.fit {
+      ImageFit::Contain => image,
+      ImageFit::Crop => Self::crop_to_square(image),
+      ImageFit::Letterbox => Self::letterbox_to_square(image),
+    };
+
+    if policy.circle_avatar && Self::is_square(image.width, image.height) {
+      let radius = image.width.min(image.height) as f32 / 2.0;
+      Self::mask_corners(&mut image, radius);
+    } else if policy.corner_radius > 0 {
+      Self::mask_corners(&mut image, policy.corner_radius as f32);
+    }
+
+    image
+  }
+
+  /// Check if dimensions are square, allowing for rounding from resizing
+  fn is_square(width: u32, height: u32) -> bool {
+    width.abs_diff(height) <= width.max(height) / 32
+  }
+
+  /// Cut the centered square out of an image
+  fn crop_to_square(image: ProcessedImage) -> ProcessedImage {
+    let side = image.width.min(image.height);
+    if image.width == image.height {
+      return image;
+    }
+
+    let x0 = (image.width - side) / 2;
+    let y0 = (image.height - side) / 2;
+    let mut data = Vec::with_capacity((side * side * 4) as usize);
+    for y in y0..y0 + side {
+      let start = ((y * image.width + x0) * 4) as usize;
+      data.extend_from_slice(&image.data[start..start + (side * 4) as usize]);
+    }
+
+    ProcessedImage {
+      data,
+      width: side,
+      height: side,
+    }
+  }
+
+  /// Center an image on a transparent square
+  fn letterbox_to_square(image: ProcessedImage) -> ProcessedImage {
+    let side = image.width.max(image.height);
+    if image.width == image.height {
+      return image;
+    }
+
+    let x0 = (side - image.width) / 2;
+    let y0 = (side - image.height) / 2;
+    let mut data = vec![0u8; (side * side * 4) as usize];
+    for y in 0..image.height {
+      let src = ((y * image.width) * 4) as usize;
+      let dst = (((y + y0) * side + x0) * 4) as usize;
+      let len = (image.width * 4) as usize;
+      data[dst..dst + len].copy_from_slice(&image.data[src..src + len]);
+    }
+
+    ProcessedImage {
+      data,
+      width: side,
+      height: side,
+    }
+  }
+
+  /// Fade out the alpha channel outside rounded corners of `radius`
+  ///
+  /// Edge pixels get partial coverage so the curve is anti-aliased.
+  fn mask_corners(image: &mut ProcessedImage, radius: f32) {
+    let (width, height) = (image.width as f32, image.height as f32);
+    let radius = radius.min(width / 2.0).min(height / 2.0);
+
+    for y in 0..image.height {
+      for x in 0..image.width {
+        // Pixel center relative to the nearest corner circle center
+        let px = x as f32 + 0.5;
+        let py = y as f32 + 0.5;
+        let dx = (radius - px).max(px - (width - radius)).max(0.0);
+        let dy = (radius - py).max(py - (height - radius)).max(0.0);
+        if dx == 0.0 || dy == 0.0 {
+          continue;
+        }
+
+        let coverage = (radius - (dx * dx + dy * dy).sqrt() + 0.5).clamp(0.0, 1.0);
+        let alpha = &mut image.data[((y * image.width + x) * 4 + 3) as usize];
+        *alpha = (*alpha as f32 * coverage).round() as u8;
+      }
+    }
+  }
+
   /// Resize image if it exceeds maximum dimensions, preserving aspect ratio.
   ///
   /// Uses Lanczos3 algorithm for high-quality downscaling.
-  fn resize_if_needed(
+  pub(crate) fn resize_if_needed(
     width: u32,
     height: u32,
     data: Vec<u8>,
@@ -215,6 +399,27 @@ mod tests {
   use super::*;
   use image::RgbaImage;
 
+  /// Test that encoded images past the decode limits are refused
+  #[test]
+  fn test_oversized_encoded_image_is_refused() {
+    let mut png = Vec::new();
+    RgbaImage::new(MAX_DECODE_DIMENSION + 1, 1)
+      .write_to(&mut std::io::Cursor::new(&mut png), image::ImageFormat::Png)
+      .unwrap();
+    assert!(matches!(
+      NotificationImage::from_bytes(&png),
+      Err(ImageError::Limits(_))
+    ));
+
+    // A large photo still decodes and comes out thumbnail sized
+    let mut png = Vec::new();
+    RgbaImage::new(4000, 3000)
+      .write_to(&mut std::io::Cursor::new(&mut png), image::ImageFormat::Png)
+      .unwrap();
+    let processed = NotificationImage::from_bytes(&png).unwrap();
+    assert_eq!((processed.width, processed.height), (MAX_IMAGE_WIDTH, 96));
+  }
+
   /// Test that images larger than max dimensions are resized
   #[test]
   fn test_resize_large_image() {
@@ -340,6 +545,25 @@ mod tests {
     assert_eq!(processed.data[3], 255); // A (added)
   }
 
+  /// Test that the last row may omit its rowstride padding
+  #[test]
+  fn test_from_raw_data_unpadded_last_row() {
+    let (width, height, rowstride) = (3, 2, 16);
+    let mut data = vec![10u8; rowstride as usize];
+    data.extend_from_slice(&[20u8; 12]);
+
+    let processed = NotificationImage::from_raw_data(&data, width, height, rowstride, true).unwrap();
+    assert_eq!(processed.data.len(), 3 * 2 * 4);
+    assert_eq!(processed.data[0], 10);
+    assert_eq!(processed.data[12], 20);
+
+    // One byte short of the last row is rejected rather than read past the end
+    data.pop();
+    assert!(NotificationImage::from_raw_data(&data, width, height, rowstride, true).is_err());
+    // As is a rowstride that can't hold a row
+    assert!(NotificationImage::from_raw_data(&data, width, height, 8, true).is_err());
+  }
+
   /// Test loading an image from a file path
   #[test]
   fn test_from_path() {
@@ -412,6 +636,96 @@ mod tests {
     assert!(result.is_err(), "Should fail with insufficient data");
   }
 
+  fn solid_image(width: u32, height: u32) -> ProcessedImage {
+    ProcessedImage {
+      data: vec![255u8; (width * height * 4) as usize],
+      width,
+      height,
+    }
+  }
+
+  fn alpha_at(image: &ProcessedImage, x: u32, y: u32) -> u8 {
+    image.data[((y * image.width + x) * 4 + 3) as usize]
+  }
+
+  /// Test that the default policy leaves images untouched
+  #[test]
+  fn test_default_policy_is_noop() {
+    let image = solid_image(40, 20);
+    let result = NotificationImage::apply_policy(image.clone(), &ImagePolicy::default());
+    assert_eq!(result.data, image.data);
+    assert_eq!((result.width, result.height), (40, 20));
+  }
+
+  /// Test center cropping and letterboxing to a square
+  #[test]
+  fn test_crop_and_letterbox() {
+    let crop = ImagePolicy {
+      fit: ImageFit::Crop,
+      ..Default::default()
+    };
+    let cropped = NotificationImage::apply_policy(solid_image(40, 20), &crop);
+    assert_eq!((cropped.width, cropped.height), (20, 20));
+    assert_eq!(cropped.data.len(), 20 * 20 * 4);
+
+    let letterbox = ImagePolicy {
+      fit: ImageFit::Letterbox,
+      ..Default::default()
+    };
+    let boxed = NotificationImage::apply_policy(solid_image(40, 20), &letterbox);
+    assert_eq!((boxed.width, boxed.height), (40, 40));
+    // Bars are transparent, the image itself is centered
+    assert_eq!(alpha_at(&boxed, 20, 0), 0);
+    assert_eq!(alpha_at(&boxed, 20, 20), 255);
+  }
+
+  /// Test rounded corners clear the corner pixels but keep the middle
+  #[test]
+  fn test_rounded_corners() {
+    let policy = ImagePolicy {
+      corner_radius: 8,
+      ..Default::default()
+    };
+    let image = NotificationImage::apply_policy(solid_image(32, 32), &policy);
+    assert_eq!(alpha_at(&image, 0, 0), 0);
+    assert_eq!(alpha_at(&image, 31, 31), 0);
+    assert_eq!(alpha_at(&image, 16, 0), 255);
+    assert_eq!(alpha_at(&image, 16, 16), 255);
+  }
+
+  /// Test that square avatars get a circular mask
+  #[test]
+  fn test_circle_avatar() {
+    let policy = ImagePolicy::for_category(0, ImageFit::Contain, true, Some("im.received"));
+    assert!(policy.circle_avatar);
+    let image = NotificationImage::apply_policy(solid_image(32, 32), &policy);
+    assert_eq!(alpha_at(&image, 0, 0), 0);
+    // Edge midpoints lie on the circle and stay mostly opaque
+    assert!(alpha_at(&image, 16, 0) > 128);
+    assert!(alpha_at(&image, 4, 4) < 128);
+    assert_eq!(alpha_at(&image, 16, 16), 255);
+
+    // Non-message categories and non-square images are left alone
+    assert!(!ImagePolicy::for_category(0, ImageFit::Contain, true, Some("device")).circle_avatar);
+    let wide = NotificationImage::apply_policy(solid_image(40, 20), &policy);
+    assert_eq!(alpha_at(&wide, 0, 0), 255);
+  }
+
+  /// Test decoding an encoded image from memory
+  #[test]
+  fn test_from_bytes() {
+    let mut png = Vec::new();
+    RgbaImage::from_pixel(3, 2, image::Rgba([1, 2, 3, 255]))
+      .write_to(&mut std::io::Cursor::new(&mut png), image::ImageFormat::Png)
+      .unwrap();
+
+    let processed = NotificationImage::from_bytes(&png).unwrap();
+    assert_eq!((processed.width, processed.height), (3, 2));
+    assert_eq!(&processed.data[..4], &[1, 2, 3, 255]);
+
+    assert!(NotificationImage::from_bytes(b"not an image").is_err());
+  }
+
   /// Test loading non-existent file
   #[test]
   fn test_nonexistent_file() {
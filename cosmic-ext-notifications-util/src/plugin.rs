@@ -0,0 +1,365 @@
+//! Sandboxed WebAssembly plugins that transform notifications
+//!
+//! A plugin is a core WebAssembly module exporting `memory`,
+//! `alloc(len: i32) -> i32` and `transform(ptr: i32, len: i32) -> i64`.
+//! The host allocates room for a JSON description of the notification,
+//! writes it there and calls `transform`, which answers with a JSON object
+//! whose address and length are packed as `(ptr << 32) | len`:
+//!
+//! ```json
+//! {"action": "show", "summary": "…", "body": "…", "urgency": 1}
+//! ```
+//!
+//! `action` is `show` (the default), `history` to skip the popup or `drop`
+//! to discard the notification; `summary`, `body` and `urgency` replace the
+//! notification's when given. The input holds `app_name`, `desktop_entry`,
+//! `category`, `summary`, `body`, `urgency` and the plugin's `settings`.
+//!
+//! Plugins get no WASI and no access to files, network or clock. The only
+//! import offered is `host.log(ptr: i32, len: i32)`. Each notification runs
+//! in a fresh instance with bounded memory and fuel, so a plugin can't keep
+//! state between notifications or hang the daemon. A plugin that fails
+//! leaves the notification as it was.
+
+use crate::{Hint, Notification};
+use serde::{Deserialize, Serialize};
+use std::{collections::BTreeMap, fmt, path::Path};
+use wasmtime::{
+    Caller, Config, Engine, Extern, Linker, Module, Store, StoreLimits, StoreLimitsBuilder,
+};
+
+/// Instructions a plugin may run per notification, roughly
+const PLUGIN_FUEL: u64 = 50_000_000;
+
+/// Linear memory a plugin may grow to (bytes)
+const PLUGIN_MAX_MEMORY: usize = 16 << 20;
+
+/// Largest answer accepted from a plugin (bytes)
+const PLUGIN_MAX_OUTPUT: usize = 256 << 10;
+
+/// Longest message a plugin may log at once (bytes)
+const PLUGIN_MAX_LOG: usize = 1024;
+
+/// What becomes of a notification after the plugins had their say
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum PluginAction {
+    /// Carry on as usual
+    #[default]
+    Show,
+    /// Keep it in history without a popup
+    History,
+    /// Discard it
+    Drop,
+}
+
+#[derive(Serialize)]
+struct PluginInput<'a> {
+    app_name: &'a str,
+    desktop_entry: Option<&'a str>,
+    category: Option<&'a str>,
+    summary: &'a str,
+    body: &'a str,
+    urgency: u8,
+    settings: &'a BTreeMap<String, String>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+struct PluginOutput {
+    #[serde(default)]
+    action: PluginAction,
+    summary: Option<String>,
+    body: Option<String>,
+    urgency: Option<u8>,
+}
+
+struct HostState {
+    plugin: String,
+    limits: StoreLimits,
+}
+
+struct Plugin {
+    name: String,
+    module: Module,
+    /// Only run for this app name or desktop entry
+    app: Option<String>,
+    settings: BTreeMap<String, String>,
+}
+
+impl Plugin {
+    fn applies_to(&self, app_name: &str, desktop_entry: Option<&str>) -> bool {
+        self.app
+            .as_deref()
+            .is_none_or(|app| app == app_name || Some(app) == desktop_entry)
+    }
+
+    /// Run `transform` on a fresh instance
+    fn call(&self, input: &[u8]) -> wasmtime::Result<PluginOutput> {
+        let engine = self.module.engine();
+        let limits = StoreLimitsBuilder::new()
+            .memory_size(PLUGIN_MAX_MEMORY)
+            .instances(1)
+            .build();
+        let mut store = Store::new(
+            engine,
+            HostState {
+                plugin: self.name.clone(),
+                limits,
+            },
+        );
+        store.limiter(|state| &mut state.limits);
+        store.set_fuel(PLUGIN_FUEL)?;
+
+        let mut linker = Linker::new(engine);
+        linker.func_wrap("host", "log", host_log)?;
+        let instance = linker.instantiate(&mut store, &self.module)?;
+        let memory = instance
+            .get_memory(&mut store, "memory")
+            .ok_or_else(|| wasmtime::Error::msg("no exported memory"))?;
+        let alloc = instance.get_typed_func::<i32, i32>(&mut store, "alloc")?;
+        let transform = instance.get_typed_func::<(i32, i32), i64>(&mut store, "transform")?;
+
+        let len = i32::try_from(input.len())?;
+        let ptr = alloc.call(&mut store, len)?;
+        memory.write(&mut store, ptr as u32 as usize, input)?;
+        let packed = transform.call(&mut store, (ptr, len))? as u64;
+
+        let (out_ptr, out_len) = ((packed >> 32) as usize, (packed & 0xffff_ffff) as usize);
+        if out_len > PLUGIN_MAX_OUTPUT {
+            return Err(wasmtime::Error::msg(format!(
+                "answer of {out_len} bytes is too long"
+            )));
+        }
+        let mut output = vec![0; out_len];
+        memory.read(&store, out_ptr, &mut output)?;
+        Ok(serde_json::from_slice(&output)?)
+    }
+}
+
+/// `host.log`: a line from the plugin in the daemon's log
+fn host_log(mut caller: Caller<'_, HostState>, ptr: i32, len: i32) {
+    let Some(memory) = caller.get_export("memory").and_then(Extern::into_memory) else {
+        return;
+    };
+    let mut message = vec![0; (len.max(0) as usize).min(PLUGIN_MAX_LOG)];
+    if memory
+        .read(&caller, ptr as u32 as usize, &mut message)
+        .is_ok()
+    {
+        tracing::info!(
+            "Plugin {}: {}",
+            caller.data().plugin,
+            String::from_utf8_lossy(&message)
+        );
+    }
+}
+
+/// Loaded plugins, run in order on each notification
+#[derive(Default)]
+pub struct PluginHost {
+    plugins: Vec<Plugin>,
+}
+
+impl fmt::Debug for PluginHost {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_list()
+            .entries(self.plugins.iter().map(|plugin| &plugin.name))
+            .finish()
+    }
+}
+
+impl PluginHost {
+    /// Load `(path, app, settings)` plugins from `.wasm` or `.wat` files
+    ///
+    /// Plugins that don't load are logged and left out.
+    pub fn load<'a>(
+        plugins: impl IntoIterator<Item = (&'a Path, Option<&'a str>, &'a BTreeMap<String, String>)>,
+    ) -> Self {
+        let mut plugins = plugins.into_iter().peekable();
+        if plugins.peek().is_none() {
+            return Self::default();
+        }
+        let mut config = Config::new();
+        config.consume_fuel(true);
+        let engine = match Engine::new(&config) {
+            Ok(engine) => engine,
+            Err(err) => {
+                tracing::error!("Failed to start the plugin engine: {err}");
+                return Self::default();
+            }
+        };
+
+        let plugins = plugins
+            .filter_map(
+                |(path, app, settings)| match Module::from_file(&engine, path) {
+                    Ok(module) => Some(Plugin {
+                        name: path.file_stem().map_or_else(
+                            || path.display().to_string(),
+                            |stem| stem.to_string_lossy().into_owned(),
+                        ),
+                        module,
+                        app: app.map(str::to_string),
+                        settings: settings.clone(),
+                    }),
+                    Err(err) => {
+                        tracing::warn!("Ignoring plugin {}: {err:#}", path.display());
+                        None
+                    }
+                },
+            )
+            .collect();
+        Self { plugins }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.plugins.is_empty()
+    }
+
+    /// Let each plugin that applies change `n`, stopping at the first one
+    /// that keeps it from showing
+    pub fn run(&self, n: &mut Notification) -> PluginAction {
+        for plugin in &self.plugins {
+            if !plugin.applies_to(&n.app_name, n.desktop_entry()) {
+                continue;
+            }
+            let input = PluginInput {
+                app_name: &n.app_name,
+                desktop_entry: n.desktop_entry(),
+                category: n.category(),
+                summary: &n.summary,
+                body: &n.body,
+                urgency: n.urgency(),
+                settings: &plugin.settings,
+            };
+            let Ok(input) = serde_json::to_vec(&input) else {
+                continue;
+            };
+            let output = match plugin.call(&input) {
+                Ok(output) => output,
+                Err(err) => {
+                    tracing::warn!(
+                        "Plugin {} failed on notification {}: {err:#}",
+                        plugin.name,
+                        n.id
+                    );
+                    continue;
+                }
+            };
+
+            if let Some(summary) = output.summary {
+                n.summary = summary;
+            }
+            if let Some(body) = output.body {
+                n.body = body;
+            }
+            if let Some(urgency) = output.urgency {
+                n.hints.retain(|hint| !matches!(hint, Hint::Urgency(_)));
+                n.hints.push(Hint::Urgency(urgency.min(2)));
+            }
+            if output.action != PluginAction::Show {
+                return output.action;
+            }
+        }
+        PluginAction::Show
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::time::SystemTime;
+
+    static NEXT_FILE: AtomicUsize = AtomicUsize::new(0);
+
+    /// A plugin answering `answer` whatever it is given
+    fn constant(answer: &str) -> String {
+        let escaped = answer.replace('"', "\\\"");
+        format!(
+            r#"(module
+                (memory (export "memory") 1)
+                (data (i32.const 1024) "{escaped}")
+                (func (export "alloc") (param i32) (result i32) (i32.const 2048))
+                (func (export "transform") (param i32 i32) (result i64)
+                    (i64.or (i64.shl (i64.const 1024) (i64.const 32)) (i64.const {len}))))"#,
+            len = answer.len()
+        )
+    }
+
+    fn host(modules: &[(&str, Option<&str>)]) -> PluginHost {
+        let dir = std::env::temp_dir().join(format!("plugin-test-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let settings = BTreeMap::new();
+        let paths: Vec<_> = modules
+            .iter()
+            .map(|(source, app)| {
+                let path = dir.join(format!(
+                    "plugin-{}.wat",
+                    NEXT_FILE.fetch_add(1, Ordering::Relaxed)
+                ));
+                std::fs::write(&path, source).unwrap();
+                (path, *app)
+            })
+            .collect();
+        let host = PluginHost::load(
+            paths
+                .iter()
+                .map(|(path, app)| (path.as_path(), *app, &settings)),
+        );
+        for (path, _) in &paths {
+            _ = std::fs::remove_file(path);
+        }
+        host
+    }
+
+    fn notification(app_name: &str) -> Notification {
+        Notification {
+            id: 1,
+            app_name: app_name.to_string(),
+            app_icon: String::new(),
+            summary: "Build failed".to_string(),
+            body: "main is red".to_string(),
+            actions: vec![],
+            hints: vec![Hint::Urgency(1)],
+            expire_timeout: -1,
+            applied_timeout: None,
+            time: SystemTime::now(),
+        }
+    }
+
+    #[test]
+    fn test_plugin_rewrites_and_reroutes() {
+        let host = host(&[(
+            &constant(r#"{"action":"history","summary":"CI","urgency":7}"#),
+            None,
+        )]);
+        let mut n = notification("CI");
+        assert_eq!(host.run(&mut n), PluginAction::History);
+        assert_eq!(n.summary, "CI");
+        assert_eq!(n.body, "main is red");
+        assert_eq!(n.urgency(), 2);
+    }
+
+    #[test]
+    fn test_plugin_scoped_to_app() {
+        let host = host(&[(&constant(r#"{"action":"drop"}"#), Some("Spam"))]);
+        assert_eq!(host.run(&mut notification("Mail")), PluginAction::Show);
+        assert_eq!(host.run(&mut notification("Spam")), PluginAction::Drop);
+    }
+
+    #[test]
+    fn test_failing_plugins_leave_notification_alone() {
+        let endless = r#"(module
+            (memory (export "memory") 1)
+            (func (export "alloc") (param i32) (result i32) (i32.const 0))
+            (func (export "transform") (param i32 i32) (result i64) (loop (br 0)) (i64.const 0)))"#;
+        let wants_files = r#"(module (import "wasi_snapshot_preview1" "fd_write"
+            (func (param i32 i32 i32 i32) (result i32))))"#;
+        let host = host(&[(endless, None), (wants_files, None), ("not wasm", None)]);
+        assert_eq!(host.plugins.len(), 2);
+
+        let mut n = notification("CI");
+        assert_eq!(host.run(&mut n), PluginAction::Show);
+        assert_eq!(n.summary, "Build failed");
+    }
+}
@@ -0,0 +1,475 @@
+//! Versioned serialization format for notifications
+//!
+//! [`NotificationRecord`] is the one JSON shape that leaves the daemon:
+//! history queries, the control socket's event stream, webhooks and the
+//! saved snooze and restore state all use it. Hooks get environment
+//! variables instead. [`Notification`] itself mirrors the D-Bus call and may change
+//! freely; the record only changes together with [`SCHEMA_VERSION`].
+//!
+//! Compatibility rules: adding an optional field keeps the version, renaming
+//! or removing a field or changing its meaning bumps it, and [`from_json`]
+//! keeps reading every version ever written.
+//!
+//! Image data is not embedded. Records refer to it by size and a digest of
+//! the pixels so that consumers can deduplicate without megabytes of JSON.
+
+use crate::{ActionId, CloseReason, Hint, Image, Notification};
+use serde::{Deserialize, Serialize};
+use std::{
+    fmt,
+    path::PathBuf,
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+/// Version written into every record
+pub const SCHEMA_VERSION: u32 = 1;
+
+/// A notification as exchanged with other programs
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct NotificationRecord {
+    pub schema_version: u32,
+    pub id: u32,
+    pub app_name: String,
+    pub app_icon: String,
+    pub summary: String,
+    pub body: String,
+    #[serde(default)]
+    pub actions: Vec<RecordAction>,
+    #[serde(default)]
+    pub hints: RecordHints,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub image: Option<ImageRef>,
+    /// Timeout requested by the sender in milliseconds (-1 = server default)
+    pub expire_timeout: i32,
+    /// Timeout the daemon applied in milliseconds (0 = never)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub applied_timeout: Option<u32>,
+    /// Milliseconds since the Unix epoch
+    pub received_at: u64,
+    /// What happened to the notification, oldest first
+    #[serde(default)]
+    pub events: Vec<LifecycleEvent>,
+}
+
+/// An action button
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RecordAction {
+    pub id: String,
+    pub label: String,
+}
+
+/// Known hints, absent ones are left out of the JSON
+#[derive(Debug, Default, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RecordHints {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub urgency: Option<u8>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub category: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub desktop_entry: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub conversation: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub sender_pid: Option<u32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub sound_file: Option<PathBuf>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub sound_name: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub reply_placeholder: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub value: Option<i32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub x: Option<i32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub y: Option<i32>,
+    #[serde(default, skip_serializing_if = "is_false")]
+    pub action_icons: bool,
+    #[serde(default, skip_serializing_if = "is_false")]
+    pub resident: bool,
+    #[serde(default, skip_serializing_if = "is_false")]
+    pub suppress_sound: bool,
+    #[serde(default, skip_serializing_if = "is_false")]
+    pub transient: bool,
+}
+
+fn is_false(value: &bool) -> bool {
+    !value
+}
+
+/// Reference to a notification image instead of its pixels
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum ImageRef {
+    /// Icon theme name
+    Name { name: String },
+    /// Image file on disk
+    File { path: PathBuf },
+    /// Raw RGBA data sent over D-Bus, identified by [`pixel_digest`]
+    Data {
+        width: u32,
+        height: u32,
+        digest: String,
+    },
+}
+
+/// Something that happened to a notification
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct LifecycleEvent {
+    /// Milliseconds since the Unix epoch
+    pub at: u64,
+    #[serde(flatten)]
+    pub kind: LifecycleKind,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "event", rename_all = "snake_case")]
+pub enum LifecycleKind {
+    /// Received over D-Bus
+    Posted,
+    /// A popup was shown
+    Shown,
+    /// Updated in place through `replaces_id`
+    Replaced,
+    /// The user invoked an action
+    ActionInvoked { action: String },
+    /// Closed, with the reason sent in `NotificationClosed`
+    Closed { reason: RecordCloseReason },
+}
+
+/// Close reasons spelled out instead of the spec's numbers
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum RecordCloseReason {
+    Expired,
+    Dismissed,
+    ClosedByApp,
+    Undefined,
+}
+
+impl From<CloseReason> for RecordCloseReason {
+    fn from(reason: CloseReason) -> Self {
+        match reason {
+            CloseReason::Expired => RecordCloseReason::Expired,
+            CloseReason::Dismissed => RecordCloseReason::Dismissed,
+            CloseReason::CloseNotification => RecordCloseReason::ClosedByApp,
+            CloseReason::Undefined => RecordCloseReason::Undefined,
+        }
+    }
+}
+
+/// Errors reading a record
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum RecordError {
+    /// Not valid JSON or not a record
+    Invalid(String),
+    /// Written by a newer version of the daemon
+    UnsupportedVersion(u32),
+}
+
+impl fmt::Display for RecordError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            RecordError::Invalid(err) => write!(f, "Invalid notification record: {}", err),
+            RecordError::UnsupportedVersion(version) => write!(
+                f,
+                "Notification record version {} is newer than supported version {}",
+                version, SCHEMA_VERSION
+            ),
+        }
+    }
+}
+
+impl std::error::Error for RecordError {}
+
+impl NotificationRecord {
+    /// Record for a notification, with a `Posted` event at its arrival
+    pub fn from_notification(notification: &Notification) -> Self {
+        let received_at = unix_millis(notification.time);
+        let mut hints = RecordHints::default();
+        let mut image = None;
+        for hint in &notification.hints {
+            match hint {
+                Hint::ActionIcons(v) => hints.action_icons = *v,
+                Hint::Category(v) => hints.category = Some(v.clone()),
+                Hint::Conversation(v) => hints.conversation = Some(v.clone()),
+                Hint::DesktopEntry(v) => hints.desktop_entry = Some(v.clone()),
+                Hint::Image(v) => image = Some(ImageRef::from(v)),
+                // Legacy icon_data is only used when no image hint is present
+                Hint::IconData(data) => {
+                    image.get_or_insert_with(|| ImageRef::Data {
+                        width: 0,
+                        height: 0,
+                        digest: pixel_digest(data),
+                    });
+                }
+                Hint::ReplyPlaceholder(v) => hints.reply_placeholder = Some(v.clone()),
+                Hint::Resident(v) => hints.resident = *v,
+                Hint::SenderPid(v) => hints.sender_pid = Some(*v),
+                Hint::SoundFile(v) => hints.sound_file = Some(v.clone()),
+                Hint::SoundName(v) => hints.sound_name = Some(v.clone()),
+                Hint::SuppressSound(v) => hints.suppress_sound = *v,
+                Hint::Transient(v) => hints.transient = *v,
+                Hint::Urgency(v) => hints.urgency = Some(*v),
+                Hint::Value(v) => hints.value = Some(*v),
+                Hint::X(v) => hints.x = Some(*v),
+                Hint::Y(v) => hints.y = Some(*v),
+            }
+        }
+
+        Self {
+            schema_version: SCHEMA_VERSION,
+            id: notification.id,
+            app_name: notification.app_name.clone(),
+            app_icon: notification.app_icon.clone(),
+            summary: notification.summary.clone(),
+            body: notification.body.clone(),
+            actions: notification
+                .actions
+                .iter()
+                .map(|(id, label)| RecordAction {
+                    id: id.to_string(),
+                    label: label.clone(),
+                })
+                .collect(),
+            hints,
+            image,
+            expire_timeout: notification.expire_timeout,
+            applied_timeout: notification.applied_timeout,
+            received_at,
+            events: vec![LifecycleEvent {
+                at: received_at,
+                kind: LifecycleKind::Posted,
+            }],
+        }
+    }
+
+    /// Rebuild the notification
+    ///
+    /// Image data is only referenced in records and cannot be restored, so
+    /// such notifications come back without their image.
+    pub fn to_notification(&self) -> Notification {
+        let h = &self.hints;
+        let mut hints = Vec::new();
+        hints.extend(h.urgency.map(Hint::Urgency));
+        hints.extend(h.category.clone().map(Hint::Category));
+        hints.extend(h.desktop_entry.clone().map(Hint::DesktopEntry));
+        hints.extend(h.conversation.clone().map(Hint::Conversation));
+        hints.extend(h.sender_pid.map(Hint::SenderPid));
+        hints.extend(h.sound_file.clone().map(Hint::SoundFile));
+        hints.extend(h.sound_name.clone().map(Hint::SoundName));
+        hints.extend(h.reply_placeholder.clone().map(Hint::ReplyPlaceholder));
+        hints.extend(h.value.map(Hint::Value));
+        hints.extend(h.x.map(Hint::X));
+        hints.extend(h.y.map(Hint::Y));
+        hints.extend(h.action_icons.then_some(Hint::ActionIcons(true)));
+        hints.extend(h.resident.then_some(Hint::Resident(true)));
+        hints.extend(h.suppress_sound.then_some(Hint::SuppressSound(true)));
+        hints.extend(h.transient.then_some(Hint::Transient(true)));
+        match &self.image {
+            Some(ImageRef::Name { name }) => hints.push(Hint::Image(Image::Name(name.clone()))),
+            Some(ImageRef::File { path }) => hints.push(Hint::Image(Image::File(path.clone()))),
+            Some(ImageRef::Data { .. }) | None => {}
+        }
+
+        Notification {
+            id: self.id,
+            app_name: self.app_name.clone(),
+            app_icon: self.app_icon.clone(),
+            summary: self.summary.clone(),
+            body: self.body.clone(),
+            actions: self
+                .actions
+                .iter()
+                .map(|a| (a.id.parse().unwrap_or(ActionId::Default), a.label.clone()))
+                .collect(),
+            hints,
+            expire_timeout: self.expire_timeout,
+            applied_timeout: self.applied_timeout,
+            time: UNIX_EPOCH + Duration::from_millis(self.received_at),
+        }
+    }
+
+    /// Append a lifecycle event happening now
+    pub fn push_event(&mut self, kind: LifecycleKind) {
+        self.events.push(LifecycleEvent {
+            at: unix_millis(SystemTime::now()),
+            kind,
+        });
+    }
+
+    /// Serialize as a single line of JSON
+    pub fn to_json(&self) -> String {
+        // Records hold only strings, numbers and paths, so this cannot fail
+        serde_json::to_string(self).unwrap_or_default()
+    }
+
+    /// Read a record written by this or any earlier schema version
+    pub fn from_json(json: &str) -> Result<Self, RecordError> {
+        #[derive(Deserialize)]
+        struct Versioned {
+            schema_version: u32,
+        }
+
+        let versioned: Versioned =
+            serde_json::from_str(json).map_err(|e| RecordError::Invalid(e.to_string()))?;
+        match versioned.schema_version {
+            // Older versions are migrated here once there are any
+            1 => serde_json::from_str(json).map_err(|e| RecordError::Invalid(e.to_string())),
+            version => Err(RecordError::UnsupportedVersion(version)),
+        }
+    }
+}
+
+impl From<&Image> for ImageRef {
+    fn from(image: &Image) -> Self {
+        match image {
+            Image::Name(name) => ImageRef::Name { name: name.clone() },
+            Image::File(path) => ImageRef::File { path: path.clone() },
+            Image::Data {
+                width,
+                height,
+                data,
+            } => ImageRef::Data {
+                width: *width,
+                height: *height,
+                digest: pixel_digest(data),
+            },
+        }
+    }
+}
+
+/// Stable digest of image data, FNV-1a 64 as 16 hex digits
+///
+/// Not cryptographic, only used to tell images apart. Unlike std's hasher
+/// the result never changes between builds.
+pub fn pixel_digest(data: &[u8]) -> String {
+    let hash = data.iter().fold(0xcbf2_9ce4_8422_2325_u64, |hash, byte| {
+        (hash ^ u64::from(*byte)).wrapping_mul(0x0100_0000_01b3)
+    });
+    format!("{hash:016x}")
+}
+
+fn unix_millis(time: SystemTime) -> u64 {
+    time.duration_since(UNIX_EPOCH)
+        .map(|d| u64::try_from(d.as_millis()).unwrap_or(u64::MAX))
+        .unwrap_or(0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Arc;
+
+    /// Version 1 record as written by the daemon; must keep parsing
+    const V1_RECORD: &str = r#"{
+        "schema_version": 1,
+        "id": 7,
+        "app_name": "Chat",
+        "app_icon": "chat",
+        "summary": "Alice",
+        "body": "Lunch?",
+        "actions": [{"id": "default", "label": "Open"}, {"id": "reply", "label": "Reply"}],
+        "hints": {"urgency": 2, "category": "im.received", "transient": true},
+        "image": {"kind": "data", "width": 2, "height": 2, "digest": "00000000deadbeef"},
+        "expire_timeout": -1,
+        "applied_timeout": 0,
+        "received_at": 1700000000000,
+        "events": [
+            {"at": 1700000000000, "event": "posted"},
+            {"at": 1700000001000, "event": "action_invoked", "action": "reply"},
+            {"at": 1700000002000, "event": "closed", "reason": "closed_by_app"}
+        ]
+    }"#;
+
+    fn notification() -> Notification {
+        Notification {
+            id: 3,
+            app_name: "Mail".to_string(),
+            app_icon: "mail".to_string(),
+            summary: "New mail".to_string(),
+            body: "<b>Hi</b>".to_string(),
+            actions: vec![
+                (ActionId::Default, "Open".to_string()),
+                (ActionId::Custom("archive".to_string()), "Archive".to_string()),
+            ],
+            hints: vec![
+                Hint::Urgency(1),
+                Hint::Category("email.arrived".to_string()),
+                Hint::Image(Image::File(PathBuf::from("/tmp/avatar.png"))),
+                Hint::Resident(true),
+            ],
+            expire_timeout: 5000,
+            applied_timeout: Some(5000),
+            time: UNIX_EPOCH + Duration::from_millis(1_700_000_000_123),
+        }
+    }
+
+    #[test]
+    fn test_v1_record_parses() {
+        let record = NotificationRecord::from_json(V1_RECORD).unwrap();
+        assert_eq!(record.id, 7);
+        assert_eq!(record.hints.urgency, Some(2));
+        assert!(record.hints.transient);
+        assert_eq!(record.actions[1].id, "reply");
+        assert_eq!(record.events.len(), 3);
+        assert_eq!(
+            record.events[2].kind,
+            LifecycleKind::Closed {
+                reason: RecordCloseReason::ClosedByApp
+            }
+        );
+    }
+
+    #[test]
+    fn test_round_trip() {
+        let original = notification();
+        let record = NotificationRecord::from_notification(&original);
+        assert_eq!(record.schema_version, SCHEMA_VERSION);
+        assert_eq!(record.received_at, 1_700_000_000_123);
+        assert_eq!(record.events[0].kind, LifecycleKind::Posted);
+
+        let parsed = NotificationRecord::from_json(&record.to_json()).unwrap();
+        assert_eq!(parsed, record);
+
+        let restored = parsed.to_notification();
+        assert_eq!(restored.actions, original.actions);
+        assert_eq!(restored.time, original.time);
+        assert_eq!(restored.urgency(), original.urgency());
+        assert_eq!(restored.category(), original.category());
+        assert!(restored.hints.contains(&Hint::Resident(true)));
+    }
+
+    #[test]
+    fn test_image_data_is_referenced() {
+        let mut n = notification();
+        n.hints = vec![Hint::Image(Image::Data {
+            width: 1,
+            height: 1,
+            data: Arc::new(vec![255, 0, 0, 255]),
+        })];
+        let json = NotificationRecord::from_notification(&n).to_json();
+        assert!(json.contains(r#""kind":"data""#));
+        assert!(json.contains(&pixel_digest(&[255, 0, 0, 255])));
+        assert!(!json.contains("[255"));
+    }
+
+    #[test]
+    fn test_version_checks() {
+        let newer = V1_RECORD.replace(r#""schema_version": 1"#, r#""schema_version": 99"#);
+        assert_eq!(
+            NotificationRecord::from_json(&newer),
+            Err(RecordError::UnsupportedVersion(99))
+        );
+        assert!(matches!(
+            NotificationRecord::from_json(r#"{"id": 1}"#),
+            Err(RecordError::Invalid(_))
+        ));
+    }
+
+    #[test]
+    fn test_pixel_digest_is_stable() {
+        assert_eq!(pixel_digest(b""), "cbf29ce484222325");
+        assert_eq!(pixel_digest(b"a"), "af63dc4c8601ec8c");
+    }
+}
@@ -0,0 +1,108 @@
+//! User-defined find-and-replace for notification text
+//!
+//! Rewrite rules let users strip tracking boilerplate ("Sent from my…",
+//! unsubscribe footers) or redact account numbers before a notification is
+//! shown or kept in history. Patterns are regular expressions; since the
+//! `regex` crate matches in linear time, a badly written pattern can be slow
+//! but never hangs the daemon.
+
+use regex::{Regex, RegexBuilder};
+
+/// Largest compiled pattern accepted, so a typo can't eat memory
+const MAX_PATTERN_SIZE: usize = 1 << 20;
+
+#[derive(Debug, Clone)]
+struct Rule {
+    pattern: Regex,
+    replacement: String,
+    /// Only rewrite this app's notifications (app name or desktop entry)
+    app: Option<String>,
+}
+
+/// Compiled rewrite rules, applied in order
+#[derive(Debug, Clone, Default)]
+pub struct RewriteRules {
+    rules: Vec<Rule>,
+}
+
+impl RewriteRules {
+    /// Compile `(pattern, replacement, app)` rules
+    ///
+    /// Rules whose pattern doesn't compile are logged and left out.
+    /// Replacements may refer to capture groups as `$1` or `${name}`.
+    pub fn compile<'a>(rules: impl IntoIterator<Item = (&'a str, &'a str, Option<&'a str>)>) -> Self {
+        let rules = rules
+            .into_iter()
+            .filter_map(|(pattern, replacement, app)| {
+                match RegexBuilder::new(pattern).size_limit(MAX_PATTERN_SIZE).build() {
+                    Ok(pattern) => Some(Rule {
+                        pattern,
+                        replacement: replacement.to_string(),
+                        app: app.map(str::to_string),
+                    }),
+                    Err(err) => {
+                        tracing::warn!("Ignoring rewrite rule {pattern:?}: {err}");
+                        None
+                    }
+                }
+            })
+            .collect();
+        Self { rules }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.rules.is_empty()
+    }
+
+    /// `text` with every rule that applies to the app rewritten in
+    pub fn apply(&self, text: &str, app_name: &str, desktop_entry: Option<&str>) -> String {
+        let mut text = text.to_string();
+        for rule in &self.rules {
+            let applies = rule
+                .app
+                .as_deref()
+                .is_none_or(|app| app == app_name || Some(app) == desktop_entry);
+            if applies {
+                text = rule.pattern.replace_all(&text, rule.replacement.as_str()).into_owned();
+            }
+        }
+        text
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_redacts_account_numbers() {
+        let rules = RewriteRules::compile([(r"\b\d{4}(\d{4})\b", "••••$1", Some("Bank"))]);
+        assert_eq!(
+            rules.apply("Card 12345678 was charged", "Bank", None),
+            "Card ••••5678 was charged"
+        );
+        // Scoped to the bank app
+        assert_eq!(
+            rules.apply("Order 12345678 shipped", "Shop", None),
+            "Order 12345678 shipped"
+        );
+    }
+
+    #[test]
+    fn test_rules_apply_in_order() {
+        let rules = RewriteRules::compile([
+            (r"\s*Sent from my \w+$", "", None),
+            ("colour", "color", None),
+        ]);
+        assert_eq!(
+            rules.apply("Nice colour Sent from my phone", "Mail", Some("org.gnome.Evolution")),
+            "Nice color"
+        );
+    }
+
+    #[test]
+    fn test_invalid_pattern_is_skipped() {
+        let rules = RewriteRules::compile([("(unclosed", "", None), ("a", "b", None)]);
+        assert_eq!(rules.apply("aa", "App", None), "bb");
+    }
+}
@@ -1,21 +1,65 @@
 use ammonia::Builder;
-use once_cell::sync::Lazy;
 use regex::Regex;
 use std::collections::HashSet;
-use crate::is_safe_url;
+use std::sync::LazyLock;
+use crate::{is_safe_url, markup_parser::parse_uncapped, StyledSegment};
+
+/// Maximum markup size processed by the sanitizer and parser (64 KiB)
+///
+/// Larger input is truncated before any regex or HTML parsing runs, so a
+/// hostile client sending megabyte bodies can't burn CPU on every render.
+pub const MAX_MARKUP_BYTES: usize = 64 * 1024;
 
 // Static regex patterns compiled once at first use
-static TAG_PATTERN: Lazy<Regex> = Lazy::new(|| {
-  Regex::new(r"<\s*/?(?:b|i|u|a|p|br)(?:\s+[^>]*)?>").unwrap()
+static TAG_PATTERN: LazyLock<Regex> = LazyLock::new(|| {
+  Regex::new(r"<\s*/?(?:b|i|u|a|p|br|h[1-4]|blockquote)(?:\s+[^>]*)?>").unwrap()
 });
 
-static HREF_PATTERN: Lazy<Regex> = Lazy::new(|| {
+static HREF_PATTERN: LazyLock<Regex> = LazyLock::new(|| {
   Regex::new(r#"<a\s+[^>]*href\s*=\s*["']([^"']+)["'][^>]*>([^<]*)</a>"#).unwrap()
 });
 
+// Ammonia configurations are built once and shared
+static SANITIZER: LazyLock<Builder<'static>> = LazyLock::new(|| {
+  let allowed_tags: HashSet<&str> = [
+    "b", "i", "u", "a", "br", "p", "h1", "h2", "h3", "h4", "blockquote",
+  ]
+  .into_iter()
+  .collect();
+  let allowed_attrs: HashSet<&str> = ["href"].into_iter().collect();
+  let url_schemes: HashSet<&str> = ["http", "https", "mailto"].into_iter().collect();
+
+  let mut builder = Builder::default();
+  builder
+    .tags(allowed_tags)
+    .link_rel(Some("noopener noreferrer"))
+    .url_schemes(url_schemes)
+    .generic_attributes(HashSet::new()) // No global attributes allowed
+    .tag_attributes(std::iter::once(("a", allowed_attrs)).collect());
+  builder
+});
+
+static STRIPPER: LazyLock<Builder<'static>> = LazyLock::new(|| {
+  let mut builder = Builder::new();
+  builder.tags(HashSet::new()); // No tags allowed - strips everything
+  builder
+});
+
+/// Truncate text to at most `max_bytes`, respecting UTF-8 boundaries
+pub fn cap_input(text: &str, max_bytes: usize) -> &str {
+  if text.len() <= max_bytes {
+    return text;
+  }
+  let mut end = max_bytes;
+  while !text.is_char_boundary(end) {
+    end -= 1;
+  }
+  &text[..end]
+}
+
 /// Sanitize HTML for safe display in notifications.
 ///
-/// Allowed tags: b, i, u, a, br, p
+/// Allowed tags: b, i, u, a, br, p, h1-h4, blockquote
 /// Allowed attributes: href (on a tags only)
 /// Allowed URL schemes: http, https, mailto
 ///
@@ -25,31 +69,24 @@ static HREF_PATTERN: Lazy<Regex> = Lazy::new(|| {
 /// - dangerous URL schemes (javascript:, data:, vbscript:)
 ///
 /// Links automatically get rel="noopener noreferrer" for security.
+///
+/// Input beyond [`MAX_MARKUP_BYTES`] is dropped.
 pub fn sanitize_html(html: &str) -> String {
-  let mut allowed_tags = HashSet::new();
-  allowed_tags.insert("b");
-  allowed_tags.insert("i");
-  allowed_tags.insert("u");
-  allowed_tags.insert("a");
-  allowed_tags.insert("br");
-  allowed_tags.insert("p");
-
-  let mut allowed_attrs = HashSet::new();
-  allowed_attrs.insert("href");
-
-  let mut url_schemes = HashSet::new();
-  url_schemes.insert("http");
-  url_schemes.insert("https");
-  url_schemes.insert("mailto");
-
-  Builder::default()
-    .tags(allowed_tags)
-    .link_rel(Some("noopener noreferrer"))
-    .url_schemes(url_schemes)
-    .generic_attributes(HashSet::new()) // No global attributes allowed
-    .tag_attributes(std::iter::once(("a", allowed_attrs)).collect())
-    .clean(html)
-    .to_string()
+  SANITIZER.clean(cap_input(html, MAX_MARKUP_BYTES)).to_string()
+}
+
+/// Sanitize and parse notification markup in one step
+///
+/// Caps the input once, runs a single sanitizer pass and feeds the result
+/// straight into the markup parser. Prefer this over calling the two
+/// functions separately when rendering.
+///
+/// The two passes stay separate: ammonia only hands back a string, and
+/// doing its HTML5 parsing (nesting repair, dropping script and style
+/// content) inside the segment parser would mean a sanitizer of our own.
+/// The parser is a single linear scan, so the second pass costs little.
+pub fn sanitize_and_parse(html: &str) -> Vec<StyledSegment> {
+  parse_uncapped(&sanitize_html(html))
 }
 
 /// Check if text contains HTML markup that would be rendered.
@@ -81,9 +118,8 @@ pub fn has_rich_content(text: &str) -> bool {
 /// This approach ensures that even double-encoded XSS vectors are safely
 /// stripped, while still providing readable plain text output.
 pub fn strip_html(html: &str) -> String {
-  // Build ammonia config - no tags allowed
-  let mut stripper = Builder::new();
-  stripper.tags(HashSet::new()); // No tags allowed - strips everything
+  let stripper = &*STRIPPER;
+  let html = cap_input(html, MAX_MARKUP_BYTES);
 
   // First pass: strip actual HTML tags
   // Entity-encoded content like &lt;script&gt; passes through unchanged
@@ -138,6 +174,8 @@ pub fn clean_bare_schemes(text: &str) -> String {
 /// Note: This is a best-effort extraction using regex, not a full HTML parser.
 /// For security-critical applications, consider using a proper HTML parser.
 pub fn extract_hrefs(html: &str) -> Vec<(String, String)> {
+  let html = cap_input(html, MAX_MARKUP_BYTES);
+
   // SECURITY FIX: Sanitize FIRST to remove dangerous tags while still encoded,
   // then decode entities to find legitimate anchor tags.
   //
@@ -204,6 +242,43 @@ fn decode_entities(text: &str) -> String {
 mod tests {
   use super::*;
 
+  // Tests for input caps
+
+  #[test]
+  fn test_cap_input_respects_char_boundaries() {
+    assert_eq!(cap_input("hello", 10), "hello");
+    assert_eq!(cap_input("hello", 3), "hel");
+    // "é" is two bytes, cutting inside it must back off
+    assert_eq!(cap_input("aé", 2), "a");
+  }
+
+  #[test]
+  fn test_huge_input_is_capped() {
+    let huge = "<b>x</b>".repeat(MAX_MARKUP_BYTES);
+    assert!(sanitize_html(&huge).len() <= MAX_MARKUP_BYTES);
+    assert!(strip_html(&huge).len() <= MAX_MARKUP_BYTES);
+    let segments = sanitize_and_parse(&huge);
+    assert!(crate::segments_to_plain_text(&segments).len() <= MAX_MARKUP_BYTES);
+  }
+
+  #[test]
+  fn test_sanitize_and_parse() {
+    let segments = sanitize_and_parse(r#"<b>Hi</b><script>alert(1)</script>"#);
+    assert_eq!(crate::segments_to_plain_text(&segments), "Hi");
+    assert!(segments[0].style.bold);
+  }
+
+  #[test]
+  fn test_sanitize_and_parse_keeps_escaped_input_whole() {
+    // Ammonia escapes each `&` as `&amp;`, so the sanitized markup outgrows
+    // the cap; none of it may be cut off
+    let input = format!("{}<b>end</b>", "&".repeat(MAX_MARKUP_BYTES - 16));
+    let segments = sanitize_and_parse(&input);
+    let last = segments.last().unwrap();
+    assert_eq!(last.text, "end");
+    assert!(last.style.bold);
+  }
+
   // Tests for sanitize_html
 
   #[test]
@@ -417,6 +492,24 @@ mod tests {
     assert!(has_rich_content("line<br>break"), "Should detect <br> tag");
   }
 
+  #[test]
+  fn test_preserves_heading_tags() {
+    let output = sanitize_html("<h1>Title</h1><h4>Sub</h4><h5>Dropped</h5>");
+    assert!(output.contains("<h1>Title</h1>"), "Should preserve h1 tag");
+    assert!(output.contains("<h4>Sub</h4>"), "Should preserve h4 tag");
+    assert!(!output.contains("<h5>"), "Should strip h5 tag");
+    assert!(has_rich_content("<h2>Heading</h2>"), "Should detect heading tag");
+  }
+
+  #[test]
+  fn test_preserves_blockquote() {
+    let input = "<blockquote>quoted <b>reply</b></blockquote>";
+    let output = sanitize_html(input);
+    assert_eq!(output, input, "Should preserve blockquote tag");
+    assert!(has_rich_content(input), "Should detect blockquote tag");
+    assert_eq!(strip_html(input), "quoted reply");
+  }
+
   #[test]
   fn test_has_rich_content_plain_text() {
     assert!(
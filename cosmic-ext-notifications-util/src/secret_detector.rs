@@ -0,0 +1,175 @@
+//! One-time code and password-reset link detection
+//!
+//! Verification codes and reset links are as good as a password for a few
+//! minutes, so popups can hide them from anyone looking over the user's
+//! shoulder. Detection is heuristic: a number only counts as a code when
+//! the text talks about codes, logins or verification, and a link only
+//! counts when its address mentions resetting, verifying or a token.
+
+use linkify::{LinkFinder, LinkKind};
+use regex::Regex;
+use std::sync::LazyLock;
+
+/// Parts of a link address that mark it as a reset or sign-in link
+const LINK_KEYWORDS: &[&str] = &[
+    "reset", "password", "passwd", "verify", "confirm", "magic", "token", "otp", "login", "signin",
+];
+
+/// Character shown in place of each hidden character
+pub const MASK_CHAR: char = '•';
+
+/// Words that make a number in the same text read as a one-time code
+static KEYWORD_PATTERN: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(
+        r"(?i)\b(?:codes?|otp|passcode|password|pin|verif\w*|one[- ]time|2fa|two[- ]factor|log[- ]?in|sign[- ]?in|security|authenticat\w*|confirm\w*)\b",
+    )
+    .unwrap()
+});
+
+static CODE_PATTERN: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r"\b(?:[A-Z]{1,4}-)?\d{4,8}\b|\b\d{3}[- ]\d{3}\b").unwrap()
+});
+
+static YEAR_PATTERN: LazyLock<Regex> = LazyLock::new(|| Regex::new(r"^(?:19|20)\d{2}$").unwrap());
+
+/// Kind of secret found in a notification
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SecretKind {
+    /// A one-time or verification code
+    Code,
+    /// A password-reset, verification or magic sign-in link
+    Link,
+}
+
+/// A secret found in text
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DetectedSecret {
+    pub kind: SecretKind,
+    /// The secret as written in the text
+    pub value: String,
+    /// Byte offset of the secret in the text
+    pub start: usize,
+}
+
+impl DetectedSecret {
+    /// What to show instead of the secret
+    ///
+    /// Codes become a row of [`MASK_CHAR`]s of the same length, links keep
+    /// their scheme and host so the user can tell where they lead.
+    pub fn masked(&self) -> String {
+        match self.kind {
+            SecretKind::Code => self.value.chars().map(|_| MASK_CHAR).collect(),
+            SecretKind::Link => {
+                let host = url::Url::parse(&self.value)
+                    .ok()
+                    .and_then(|url| url.host_str().map(|host| format!("{}://{host}", url.scheme())));
+                let mask: String = std::iter::repeat_n(MASK_CHAR, 4).collect();
+                match host {
+                    Some(host) => format!("{host}/{mask}"),
+                    None => mask,
+                }
+            }
+        }
+    }
+}
+
+/// Detect one-time codes and reset links in plain text, sorted by position
+pub fn detect_secrets(text: &str) -> Vec<DetectedSecret> {
+    let mut finder = LinkFinder::new();
+    finder.kinds(&[LinkKind::Url]);
+    let links: Vec<_> = finder.links(text).collect();
+
+    let mut secrets: Vec<DetectedSecret> = links
+        .iter()
+        .filter(|link| {
+            let url = link.as_str().to_lowercase();
+            LINK_KEYWORDS.iter().any(|keyword| url.contains(keyword))
+        })
+        .map(|link| DetectedSecret {
+            kind: SecretKind::Link,
+            value: link.as_str().to_string(),
+            start: link.start(),
+        })
+        .collect();
+
+    if KEYWORD_PATTERN.is_match(text) {
+        for m in CODE_PATTERN.find_iter(text) {
+            let in_link = links
+                .iter()
+                .any(|link| m.start() < link.end() && link.start() < m.end());
+            if in_link || YEAR_PATTERN.is_match(m.as_str()) {
+                continue;
+            }
+            secrets.push(DetectedSecret {
+                kind: SecretKind::Code,
+                value: m.as_str().to_string(),
+                start: m.start(),
+            });
+        }
+    }
+
+    secrets.sort_by_key(|s| s.start);
+    secrets
+}
+
+/// `text` with every detected secret masked, or `None` if there is none
+pub fn mask_secrets(text: &str) -> Option<String> {
+    let secrets = detect_secrets(text);
+    if secrets.is_empty() {
+        return None;
+    }
+    let mut masked = String::with_capacity(text.len());
+    let mut end = 0;
+    for secret in &secrets {
+        masked.push_str(&text[end..secret.start]);
+        masked.push_str(&secret.masked());
+        end = secret.start + secret.value.len();
+    }
+    masked.push_str(&text[end..]);
+    Some(masked)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn codes(text: &str) -> Vec<String> {
+        detect_secrets(text)
+            .into_iter()
+            .filter(|s| s.kind == SecretKind::Code)
+            .map(|s| s.value)
+            .collect()
+    }
+
+    #[test]
+    fn test_detects_codes() {
+        assert_eq!(codes("Your verification code is 482913"), vec!["482913"]);
+        assert_eq!(codes("G-123456 is your Google verification code"), vec!["G-123456"]);
+        assert_eq!(codes("Use 123 456 to sign in"), vec!["123 456"]);
+    }
+
+    #[test]
+    fn test_ignores_numbers_without_context() {
+        assert!(codes("Order 482913 has shipped").is_empty());
+        // Years aren't codes, even in a login notice
+        assert!(codes("New login on 12 March 2024").is_empty());
+    }
+
+    #[test]
+    fn test_masks_reset_link_but_keeps_host() {
+        let text = "Reset your password: https://example.com/reset?token=abc123";
+        assert_eq!(
+            mask_secrets(text).as_deref(),
+            Some("Reset your password: https://example.com/••••")
+        );
+        assert!(mask_secrets("See https://example.com/blog").is_none());
+    }
+
+    #[test]
+    fn test_masks_code_in_place() {
+        assert_eq!(
+            mask_secrets("Your OTP is <b>482913</b>").as_deref(),
+            Some("Your OTP is <b>••••••</b>")
+        );
+    }
+}
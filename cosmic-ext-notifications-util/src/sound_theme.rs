@@ -0,0 +1,532 @@
+//! freedesktop.org sound theme lookup
+//!
+//! Implements the lookup from the Sound Theme Specification. Themes are
+//! directories under `sounds/` in the XDG data directories, described by an
+//! `index.theme` that lists their sound directories and parent themes.
+//!
+//! Lookup order for a sound name:
+//! 1. The configured theme, then its `Inherits` chain, then `freedesktop`
+//! 2. Within a theme directory, locale subdirectories before the plain one
+//! 3. Less specific names (`message-new-instant` → `message-new` → `message`)
+//! 4. Unthemed files directly in a `sounds/` directory
+//!
+//! A `<name>.disabled` file in a theme marks the sound as deliberately silent.
+
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// Theme every other theme implicitly inherits from
+pub const FALLBACK_THEME: &str = "freedesktop";
+
+/// File extensions tried for each sound, in order of preference
+const SOUND_EXTENSIONS: &[&str] = &["oga", "ogg", "wav", "mp3"];
+
+/// Output profile used when a theme offers several
+const OUTPUT_PROFILE: &str = "stereo";
+
+/// Sound names whose files are remembered before the cache starts over
+const MAX_CACHED_LOOKUPS: usize = 256;
+
+/// Result of resolving a sound name
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SoundLookup {
+    /// The sound file to play
+    Found(PathBuf),
+    /// The theme explicitly disables this sound
+    Disabled,
+}
+
+/// Parsed `index.theme` of a sound theme
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+struct ThemeIndex {
+    /// Parent themes, in lookup order
+    inherits: Vec<String>,
+    /// Sound directories inside the theme, in lookup order
+    directories: Vec<String>,
+}
+
+impl ThemeIndex {
+    /// Parse the `[Sound Theme]` group and the per-directory groups
+    ///
+    /// Directories whose `OutputProfile` is not stereo are skipped unless
+    /// the theme has nothing else.
+    fn parse(contents: &str) -> Self {
+        let mut inherits = Vec::new();
+        let mut directories = Vec::new();
+        let mut profiles: HashMap<String, String> = HashMap::new();
+        let mut section = String::new();
+
+        for line in contents.lines() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            if let Some(name) = line.strip_prefix('[').and_then(|l| l.strip_suffix(']')) {
+                section = name.to_string();
+                continue;
+            }
+            let Some((key, value)) = line.split_once('=') else {
+                continue;
+            };
+            let (key, value) = (key.trim(), value.trim());
+
+            match (section.as_str(), key) {
+                ("Sound Theme", "Inherits") => inherits = split_list(value),
+                ("Sound Theme", "Directories") => directories = split_list(value),
+                (dir, "OutputProfile") if !dir.is_empty() => {
+                    profiles.insert(dir.to_string(), value.to_string());
+                }
+                _ => {}
+            }
+        }
+
+        let preferred: Vec<String> = directories
+            .iter()
+            .filter(|dir| profiles.get(*dir).is_none_or(|p| p == OUTPUT_PROFILE))
+            .cloned()
+            .collect();
+        if !preferred.is_empty() {
+            directories = preferred;
+        }
+
+        Self {
+            inherits,
+            directories,
+        }
+    }
+}
+
+fn split_list(value: &str) -> Vec<String> {
+    value
+        .split(',')
+        .map(str::trim)
+        .filter(|s| !s.is_empty())
+        .map(String::from)
+        .collect()
+}
+
+/// Resolves sound names to files following the sound theme spec
+///
+/// Parsed theme indexes and the files found for up to
+/// [`MAX_CACHED_LOOKUPS`] sound names are cached; names no theme provides
+/// are looked up again each time. The cache is dropped when the theme
+/// changes; call [`SoundThemeResolver::clear_cache`] after installing new
+/// themes.
+#[derive(Debug, Clone)]
+pub struct SoundThemeResolver {
+    /// `sounds/` directories, most important first
+    base_dirs: Vec<PathBuf>,
+    /// Locale subdirectory names, most specific first
+    locales: Vec<String>,
+    /// Theme to start lookups from
+    theme: String,
+    indexes: HashMap<String, Option<ThemeIndex>>,
+    lookups: HashMap<String, SoundLookup>,
+}
+
+impl SoundThemeResolver {
+    /// Create a resolver over explicit `sounds/` directories and locales
+    pub fn new(base_dirs: Vec<PathBuf>, locales: Vec<String>, theme: &str) -> Self {
+        Self {
+            base_dirs,
+            locales,
+            theme: theme.to_string(),
+            indexes: HashMap::new(),
+            lookups: HashMap::new(),
+        }
+    }
+
+    /// Create a resolver from the XDG data directories and locale environment
+    pub fn from_env(theme: &str) -> Self {
+        Self::new(sound_base_dirs(), env_locales(), theme)
+    }
+
+    /// Theme lookups start from
+    pub fn theme(&self) -> &str {
+        &self.theme
+    }
+
+    /// Switch to another theme, dropping cached lookups if it changed
+    pub fn set_theme(&mut self, theme: &str) {
+        if self.theme != theme {
+            self.theme = theme.to_string();
+            self.lookups.clear();
+        }
+    }
+
+    /// Forget all cached theme indexes and lookups
+    pub fn clear_cache(&mut self) {
+        self.indexes.clear();
+        self.lookups.clear();
+    }
+
+    /// Resolve a sound name, or None if no theme provides it
+    pub fn lookup(&mut self, name: &str) -> Option<SoundLookup> {
+        if let Some(cached) = self.lookups.get(name) {
+            return Some(cached.clone());
+        }
+        let result = self.resolve(name)?;
+        if self.lookups.len() >= MAX_CACHED_LOOKUPS {
+            self.lookups.clear();
+        }
+        self.lookups.insert(name.to_string(), result.clone());
+        Some(result)
+    }
+
+    fn resolve(&mut self, name: &str) -> Option<SoundLookup> {
+        // Sound names are used as path components, never let them escape
+        if name.is_empty() || name.contains(['/', '\\']) || name.starts_with('.') {
+            return None;
+        }
+
+        let chain = self.theme_chain();
+        for variant in name_fallbacks(name) {
+            for theme in &chain {
+                if let Some(found) = self.lookup_in_theme(theme, variant) {
+                    return Some(found);
+                }
+            }
+        }
+
+        // Unthemed sounds directly in a sounds directory
+        for variant in name_fallbacks(name) {
+            for base in &self.base_dirs {
+                if let Some(path) = find_with_extension(base, variant) {
+                    return Some(SoundLookup::Found(path));
+                }
+            }
+        }
+
+        None
+    }
+
+    /// Configured theme, its ancestors depth-first, then the fallback theme
+    fn theme_chain(&mut self) -> Vec<String> {
+        let mut chain = Vec::new();
+        let mut seen = HashSet::new();
+        let mut stack = vec![self.theme.clone()];
+
+        while let Some(theme) = stack.pop() {
+            if !seen.insert(theme.clone()) {
+                continue;
+            }
+            if let Some(index) = self.index(&theme) {
+                // Push in reverse so the first parent is visited first
+                stack.extend(index.inherits.iter().rev().cloned());
+            }
+            chain.push(theme);
+        }
+
+        if !seen.contains(FALLBACK_THEME) {
+            chain.push(FALLBACK_THEME.to_string());
+        }
+        chain
+    }
+
+    /// Parsed index of a theme from the first base dir that has one
+    fn index(&mut self, theme: &str) -> Option<ThemeIndex> {
+        if let Some(cached) = self.indexes.get(theme) {
+            return cached.clone();
+        }
+        let index = self
+            .base_dirs
+            .iter()
+            .map(|base| base.join(theme).join("index.theme"))
+            .find_map(|path| fs::read_to_string(path).ok())
+            .map(|contents| ThemeIndex::parse(&contents));
+        self.indexes.insert(theme.to_string(), index.clone());
+        index
+    }
+
+    fn lookup_in_theme(&mut self, theme: &str, name: &str) -> Option<SoundLookup> {
+        // Themes without an index still get their stereo directory searched
+        let directories = self
+            .index(theme)
+            .map(|index| index.directories)
+            .filter(|dirs| !dirs.is_empty())
+            .unwrap_or_else(|| vec![OUTPUT_PROFILE.to_string()]);
+
+        for base in &self.base_dirs {
+            let theme_dir = base.join(theme);
+            if !theme_dir.is_dir() {
+                continue;
+            }
+            for dir in &directories {
+                let sound_dir = theme_dir.join(dir);
+                let candidates = self
+                    .locales
+                    .iter()
+                    .map(|locale| sound_dir.join(locale))
+                    .chain(std::iter::once(sound_dir.clone()));
+                for candidate in candidates {
+                    if candidate.join(format!("{name}.disabled")).exists() {
+                        return Some(SoundLookup::Disabled);
+                    }
+                    if let Some(path) = find_with_extension(&candidate, name) {
+                        return Some(SoundLookup::Found(path));
+                    }
+                }
+            }
+        }
+        None
+    }
+}
+
+/// `message-new-instant`, `message-new`, `message`
+fn name_fallbacks(name: &str) -> impl Iterator<Item = &str> {
+    std::iter::successors(Some(name), |n| n.rsplit_once('-').map(|(head, _)| head))
+}
+
+fn find_with_extension(dir: &Path, name: &str) -> Option<PathBuf> {
+    SOUND_EXTENSIONS
+        .iter()
+        .map(|ext| dir.join(format!("{name}.{ext}")))
+        .find(|path| path.is_file())
+}
+
+/// `sounds/` directories from `$XDG_DATA_HOME` and `$XDG_DATA_DIRS`
+pub fn sound_base_dirs() -> Vec<PathBuf> {
+    let mut dirs = Vec::new();
+
+    if let Some(data_home) = std::env::var_os("XDG_DATA_HOME").filter(|d| !d.is_empty()) {
+        dirs.push(PathBuf::from(data_home).join("sounds"));
+    } else if let Some(home) = std::env::var_os("HOME") {
+        dirs.push(PathBuf::from(home).join(".local/share/sounds"));
+    }
+
+    let data_dirs = std::env::var("XDG_DATA_DIRS")
+        .ok()
+        .filter(|d| !d.is_empty())
+        .unwrap_or_else(|| "/usr/local/share:/usr/share".to_string());
+    for dir in data_dirs.split(':').filter(|d| !d.is_empty()) {
+        let sounds = PathBuf::from(dir).join("sounds");
+        if !dirs.contains(&sounds) {
+            dirs.push(sounds);
+        }
+    }
+
+    dirs
+}
+
+/// Locale subdirectory names from the environment, most specific first
+fn env_locales() -> Vec<String> {
+    ["LC_ALL", "LC_MESSAGES", "LANG"]
+        .iter()
+        .filter_map(|var| std::env::var(var).ok())
+        .find(|value| !value.is_empty())
+        .map(|value| locale_variants(&value))
+        .unwrap_or_default()
+}
+
+/// `de_DE.UTF-8@euro` → `de_DE@euro`, `de_DE`, `de`
+fn locale_variants(locale: &str) -> Vec<String> {
+    let (base, modifier) = match locale.split_once('@') {
+        Some((base, modifier)) => (base, Some(modifier)),
+        None => (locale, None),
+    };
+    let base = base.split('.').next().unwrap_or_default();
+    if base.is_empty() || base == "C" || base == "POSIX" {
+        return Vec::new();
+    }
+
+    let mut variants = Vec::new();
+    if let Some(modifier) = modifier {
+        variants.push(format!("{base}@{modifier}"));
+    }
+    variants.push(base.to_string());
+    if let Some((lang, _)) = base.split_once('_') {
+        variants.push(lang.to_string());
+    }
+    variants
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    /// Temporary `sounds/` tree removed on drop
+    struct TempSounds(PathBuf);
+
+    impl TempSounds {
+        fn new() -> Self {
+            static COUNTER: AtomicUsize = AtomicUsize::new(0);
+            let dir = std::env::temp_dir().join(format!(
+                "sound-theme-test-{}-{}",
+                std::process::id(),
+                COUNTER.fetch_add(1, Ordering::SeqCst)
+            ));
+            fs::create_dir_all(&dir).unwrap();
+            Self(dir)
+        }
+
+        fn write(&self, rel: &str, contents: &str) -> PathBuf {
+            let path = self.0.join(rel);
+            fs::create_dir_all(path.parent().unwrap()).unwrap();
+            fs::write(&path, contents).unwrap();
+            path
+        }
+
+        fn resolver(&self, theme: &str, locales: &[&str]) -> SoundThemeResolver {
+            SoundThemeResolver::new(
+                vec![self.0.clone()],
+                locales.iter().map(|l| l.to_string()).collect(),
+                theme,
+            )
+        }
+    }
+
+    impl Drop for TempSounds {
+        fn drop(&mut self) {
+            let _ = fs::remove_dir_all(&self.0);
+        }
+    }
+
+    const FREEDESKTOP_INDEX: &str = "[Sound Theme]\nName=Default\nDirectories=stereo\n\n[stereo]\nOutputProfile=stereo\n";
+
+    #[test]
+    fn test_parse_index() {
+        let index = ThemeIndex::parse(
+            "# comment\n[Sound Theme]\nName=Ocean\nInherits=base, freedesktop\nDirectories=5.1,stereo\n\n[5.1]\nOutputProfile=5.1\n\n[stereo]\nOutputProfile=stereo\n",
+        );
+        assert_eq!(index.inherits, vec!["base", "freedesktop"]);
+        assert_eq!(index.directories, vec!["stereo"]);
+    }
+
+    #[test]
+    fn test_name_fallbacks() {
+        let names: Vec<_> = name_fallbacks("message-new-instant").collect();
+        assert_eq!(names, vec!["message-new-instant", "message-new", "message"]);
+    }
+
+    #[test]
+    fn test_locale_variants() {
+        assert_eq!(locale_variants("de_DE.UTF-8"), vec!["de_DE", "de"]);
+        assert_eq!(locale_variants("sr_RS.UTF-8@latin"), vec!["sr_RS@latin", "sr_RS", "sr"]);
+        assert!(locale_variants("C.UTF-8").is_empty());
+    }
+
+    #[test]
+    fn test_inherited_theme_and_fallback() {
+        let tmp = TempSounds::new();
+        tmp.write("ocean/index.theme", "[Sound Theme]\nInherits=base\nDirectories=stereo\n");
+        tmp.write("base/index.theme", "[Sound Theme]\nDirectories=stereo\n");
+        let bell = tmp.write("base/stereo/bell.oga", "");
+        tmp.write("freedesktop/index.theme", FREEDESKTOP_INDEX);
+        let message = tmp.write("freedesktop/stereo/message.oga", "");
+
+        let mut resolver = tmp.resolver("ocean", &[]);
+        assert_eq!(resolver.lookup("bell"), Some(SoundLookup::Found(bell)));
+        // Falls back to the less specific name in the freedesktop theme
+        assert_eq!(
+            resolver.lookup("message-new-instant"),
+            Some(SoundLookup::Found(message))
+        );
+        assert_eq!(resolver.lookup("missing"), None);
+    }
+
+    #[test]
+    fn test_configured_theme_wins() {
+        let tmp = TempSounds::new();
+        tmp.write("freedesktop/index.theme", FREEDESKTOP_INDEX);
+        let default = tmp.write("freedesktop/stereo/bell.oga", "");
+        tmp.write("ocean/index.theme", "[Sound Theme]\nDirectories=stereo\n");
+        let themed = tmp.write("ocean/stereo/bell.wav", "");
+
+        let mut resolver = tmp.resolver("freedesktop", &[]);
+        assert_eq!(resolver.lookup("bell"), Some(SoundLookup::Found(default)));
+
+        resolver.set_theme("ocean");
+        assert_eq!(resolver.lookup("bell"), Some(SoundLookup::Found(themed)));
+    }
+
+    #[test]
+    fn test_locale_subdirectory_preferred() {
+        let tmp = TempSounds::new();
+        tmp.write("freedesktop/index.theme", FREEDESKTOP_INDEX);
+        tmp.write("freedesktop/stereo/bell.oga", "");
+        let localized = tmp.write("freedesktop/stereo/de/bell.oga", "");
+
+        let mut resolver = tmp.resolver("freedesktop", &["de_DE", "de"]);
+        assert_eq!(resolver.lookup("bell"), Some(SoundLookup::Found(localized)));
+    }
+
+    #[test]
+    fn test_disabled_sound() {
+        let tmp = TempSounds::new();
+        tmp.write("quiet/index.theme", "[Sound Theme]\nInherits=freedesktop\nDirectories=stereo\n");
+        tmp.write("quiet/stereo/bell.disabled", "");
+        tmp.write("freedesktop/index.theme", FREEDESKTOP_INDEX);
+        tmp.write("freedesktop/stereo/bell.oga", "");
+
+        let mut resolver = tmp.resolver("quiet", &[]);
+        assert_eq!(resolver.lookup("bell"), Some(SoundLookup::Disabled));
+    }
+
+    #[test]
+    fn test_inherit_cycle_terminates() {
+        let tmp = TempSounds::new();
+        tmp.write("a/index.theme", "[Sound Theme]\nInherits=b\n");
+        tmp.write("b/index.theme", "[Sound Theme]\nInherits=a\n");
+
+        let mut resolver = tmp.resolver("a", &[]);
+        assert_eq!(resolver.theme_chain(), vec!["a", "b", FALLBACK_THEME]);
+        assert_eq!(resolver.lookup("bell"), None);
+    }
+
+    #[test]
+    fn test_rejects_path_like_names() {
+        let tmp = TempSounds::new();
+        tmp.write("secret.oga", "");
+        let mut resolver = tmp.resolver("freedesktop", &[]);
+        assert_eq!(resolver.lookup("../secret"), None);
+        assert_eq!(resolver.lookup("stereo/bell"), None);
+    }
+
+    #[test]
+    fn test_lookup_is_cached() {
+        let tmp = TempSounds::new();
+        tmp.write("freedesktop/index.theme", FREEDESKTOP_INDEX);
+        let bell = tmp.write("freedesktop/stereo/bell.oga", "");
+
+        let mut resolver = tmp.resolver("freedesktop", &[]);
+        assert_eq!(resolver.lookup("bell"), Some(SoundLookup::Found(bell.clone())));
+        fs::remove_file(&bell).unwrap();
+        assert_eq!(resolver.lookup("bell"), Some(SoundLookup::Found(bell)));
+
+        resolver.clear_cache();
+        assert_eq!(resolver.lookup("bell"), None);
+    }
+
+    #[test]
+    fn test_misses_are_not_cached() {
+        let tmp = TempSounds::new();
+        tmp.write("freedesktop/index.theme", FREEDESKTOP_INDEX);
+
+        let mut resolver = tmp.resolver("freedesktop", &[]);
+        for i in 0..MAX_CACHED_LOOKUPS * 2 {
+            assert_eq!(resolver.lookup(&format!("missing-{i}")), None);
+        }
+        assert!(resolver.lookups.is_empty());
+
+        let bell = tmp.write("freedesktop/stereo/bell.oga", "");
+        assert_eq!(resolver.lookup("missing-0"), None);
+        assert_eq!(resolver.lookup("bell"), Some(SoundLookup::Found(bell)));
+    }
+
+    #[test]
+    fn test_cache_is_bounded() {
+        let tmp = TempSounds::new();
+        tmp.write("freedesktop/index.theme", FREEDESKTOP_INDEX);
+        for i in 0..=MAX_CACHED_LOOKUPS {
+            tmp.write(&format!("freedesktop/stereo/sound-{i}.oga"), "");
+        }
+
+        let mut resolver = tmp.resolver("freedesktop", &[]);
+        for i in 0..=MAX_CACHED_LOOKUPS {
+            assert!(resolver.lookup(&format!("sound-{i}")).is_some());
+        }
+        assert!(resolver.lookups.len() <= MAX_CACHED_LOOKUPS);
+    }
+}
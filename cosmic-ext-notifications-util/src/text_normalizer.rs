@@ -0,0 +1,77 @@
+//! Unicode cleanup for notification text
+//!
+//! Notification text is normalized to NFC so that visually identical strings
+//! compare equal in rule matching and deduplication. Optionally, invisible
+//! characters used for spoofing are removed: bidi overrides such as U+202E
+//! can make `exe.txt` render as `txt.exe`, and zero-width spaces can split
+//! keywords so they slip past filters.
+
+use unicode_normalization::{is_nfc_quick, IsNormalized, UnicodeNormalization};
+
+/// Normalize text to Unicode NFC
+pub fn normalize_nfc(text: &str) -> String {
+    match is_nfc_quick(text.chars()) {
+        IsNormalized::Yes => text.to_string(),
+        _ => text.nfc().collect(),
+    }
+}
+
+/// Whether a character is invisible formatting that can disguise text
+///
+/// Zero-width joiners and non-joiners are kept since emoji sequences and
+/// scripts such as Persian need them, as are the LRM/RLM marks. Newlines and
+/// tabs are the only control characters kept.
+pub fn is_spoofing_char(c: char) -> bool {
+    matches!(
+        c,
+        // Bidi embeddings and overrides (LRE, RLE, PDF, LRO, RLO)
+        '\u{202A}'..='\u{202E}'
+        // Bidi isolates (LRI, RLI, FSI, PDI)
+        | '\u{2066}'..='\u{2069}'
+        // Zero-width space, word joiner, invisible operators
+        | '\u{200B}'
+        | '\u{2060}'..='\u{2064}'
+        // Byte order mark / zero-width no-break space
+        | '\u{FEFF}'
+        // Mongolian vowel separator, rendered zero-width
+        | '\u{180E}'
+    ) || (c.is_control() && c != '\n' && c != '\t')
+}
+
+/// Remove bidi overrides, zero-width characters and control characters
+pub fn strip_spoofing_chars(text: &str) -> String {
+    text.chars().filter(|c| !is_spoofing_char(*c)).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_normalize_nfc() {
+        // "e" + combining acute accent becomes a single precomposed "é"
+        assert_eq!(normalize_nfc("cafe\u{0301}"), "caf\u{00E9}");
+        assert_eq!(normalize_nfc("plain ascii"), "plain ascii");
+    }
+
+    #[test]
+    fn test_strips_bidi_override() {
+        assert_eq!(strip_spoofing_chars("invoice\u{202E}fdp.exe"), "invoicefdp.exe");
+        assert_eq!(strip_spoofing_chars("\u{2067}hidden\u{2069}"), "hidden");
+    }
+
+    #[test]
+    fn test_strips_zero_width_and_controls() {
+        assert_eq!(strip_spoofing_chars("pa\u{200B}ss\u{FEFF}word"), "password");
+        assert_eq!(strip_spoofing_chars("a\u{0007}b\u{009B}c"), "abc");
+        assert_eq!(strip_spoofing_chars("line one\nline\ttwo"), "line one\nline\ttwo");
+    }
+
+    #[test]
+    fn test_keeps_joiners() {
+        // Family emoji and Persian text rely on ZWJ / ZWNJ
+        let family = "\u{1F468}\u{200D}\u{1F469}\u{200D}\u{1F467}";
+        assert_eq!(strip_spoofing_chars(family), family);
+        assert_eq!(strip_spoofing_chars("می\u{200C}خواهم"), "می\u{200C}خواهم");
+    }
+}
@@ -382,6 +382,7 @@ fn test_estimated_size_basic() {
         actions: vec![],
         hints: vec![],
         expire_timeout: 5000,
+        applied_timeout: None,
         time: SystemTime::now(),
     };
 
@@ -410,6 +411,7 @@ fn test_estimated_size_with_actions() {
         ],
         hints: vec![],
         expire_timeout: 0,
+        applied_timeout: None,
         time: SystemTime::now(),
     };
 
@@ -436,6 +438,7 @@ fn test_estimated_size_with_hints() {
             Hint::Transient(true),                     // 8 bytes
         ],
         expire_timeout: 0,
+        applied_timeout: None,
         time: SystemTime::now(),
     };
 
@@ -466,6 +469,7 @@ fn test_estimated_size_with_image_data() {
             }),
         ],
         expire_timeout: 0,
+        applied_timeout: None,
         time: SystemTime::now(),
     };
 
@@ -490,6 +494,7 @@ fn test_estimated_size_with_large_body() {
         actions: vec![],
         hints: vec![],
         expire_timeout: 0,
+        applied_timeout: None,
         time: SystemTime::now(),
     };
 
@@ -562,6 +567,7 @@ fn test_notification_duration_since() {
         actions: vec![],
         hints: vec![],
         expire_timeout: 0,
+        applied_timeout: None,
         time: SystemTime::now() - Duration::from_secs(5),
     };
 
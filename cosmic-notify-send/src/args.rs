@@ -0,0 +1,331 @@
+//! Command line parsing, following libnotify's notify-send
+
+use std::collections::HashMap;
+use zbus::zvariant::Value;
+
+pub const USAGE: &str = "\
+Usage: cosmic-notify-send [OPTION...] SUMMARY [BODY]
+
+Options:
+  -u, --urgency=LEVEL         low, normal or critical
+  -t, --expire-time=TIME      timeout in milliseconds, 0 never expires
+  -a, --app-name=APP_NAME     name of the sending application
+  -i, --icon=ICON             icon name or file path
+  -c, --category=TYPE[,TYPE]  notification category
+  -e, --transient             don't keep the notification in history
+  -h, --hint=TYPE:NAME:VALUE  extra hint; TYPE is int, double, string,
+                              byte or boolean
+  -A, --action=[NAME=]TEXT    add an action; the name of the one clicked is
+                              printed and implies --wait
+  -w, --wait                  wait until the notification is closed
+  -p, --print-id              print the notification id
+  -r, --replace-id=ID         replace the notification with this id
+  -v, --version               print the version
+      --help                  show this help";
+
+/// Value of a `--hint`
+#[derive(Debug, Clone, PartialEq)]
+pub enum HintValue {
+    Int(i32),
+    Double(f64),
+    String(String),
+    Byte(u8),
+    Boolean(bool),
+}
+
+impl HintValue {
+    fn parse(kind: &str, value: &str) -> Result<Self, String> {
+        let invalid = || format!("Invalid {kind} hint value: {value}");
+        match kind {
+            "int" => value.parse().map(HintValue::Int).map_err(|_| invalid()),
+            "double" => value.parse().map(HintValue::Double).map_err(|_| invalid()),
+            "string" => Ok(HintValue::String(value.to_string())),
+            "byte" => value.parse().map(HintValue::Byte).map_err(|_| invalid()),
+            "boolean" => match value.to_ascii_lowercase().as_str() {
+                "true" | "1" => Ok(HintValue::Boolean(true)),
+                "false" | "0" => Ok(HintValue::Boolean(false)),
+                _ => Err(invalid()),
+            },
+            _ => Err(format!(
+                "Invalid hint type {kind}, expected int, double, string, byte or boolean"
+            )),
+        }
+    }
+
+    fn to_value(&self) -> Value<'_> {
+        match self {
+            HintValue::Int(v) => Value::I32(*v),
+            HintValue::Double(v) => Value::F64(*v),
+            HintValue::String(v) => Value::from(v.as_str()),
+            HintValue::Byte(v) => Value::U8(*v),
+            HintValue::Boolean(v) => Value::Bool(*v),
+        }
+    }
+}
+
+/// A notification to send
+#[derive(Debug, Clone, PartialEq)]
+pub struct Options {
+    pub app_name: String,
+    pub app_icon: String,
+    pub summary: String,
+    pub body: String,
+    pub urgency: Option<u8>,
+    pub category: Option<String>,
+    pub transient: bool,
+    /// `--hint`s in the order given
+    pub hints: Vec<(String, HintValue)>,
+    /// Action keys and labels
+    pub actions: Vec<(String, String)>,
+    /// -1 lets the daemon decide
+    pub expire_timeout: i32,
+    pub replace_id: u32,
+    pub print_id: bool,
+    pub wait: bool,
+}
+
+impl Options {
+    fn new(summary: String) -> Self {
+        Self {
+            app_name: "notify-send".to_string(),
+            app_icon: String::new(),
+            summary,
+            body: String::new(),
+            urgency: None,
+            category: None,
+            transient: false,
+            hints: Vec::new(),
+            actions: Vec::new(),
+            expire_timeout: -1,
+            replace_id: 0,
+            print_id: false,
+            wait: false,
+        }
+    }
+
+    /// Hints to send; `--hint` wins over the dedicated options
+    pub fn hints(&self) -> HashMap<&str, Value<'_>> {
+        let mut hints = HashMap::new();
+        if let Some(urgency) = self.urgency {
+            hints.insert("urgency", Value::U8(urgency));
+        }
+        if let Some(category) = &self.category {
+            hints.insert("category", Value::from(category.as_str()));
+        }
+        if self.transient {
+            hints.insert("transient", Value::Bool(true));
+        }
+        for (name, value) in &self.hints {
+            hints.insert(name.as_str(), value.to_value());
+        }
+        hints
+    }
+
+    /// Actions as the flat key, label list `Notify` takes
+    pub fn action_list(&self) -> Vec<&str> {
+        self.actions
+            .iter()
+            .flat_map(|(key, label)| [key.as_str(), label.as_str()])
+            .collect()
+    }
+
+    /// Whether to stay until the notification closes or an action runs
+    pub fn waits(&self) -> bool {
+        self.wait || !self.actions.is_empty()
+    }
+}
+
+/// What the command line asks for
+#[derive(Debug, Clone, PartialEq)]
+pub enum Command {
+    Send(Options),
+    Help,
+    Version,
+}
+
+fn parse_urgency(value: &str) -> Result<u8, String> {
+    match value.to_ascii_lowercase().as_str() {
+        "low" | "0" => Ok(0),
+        "normal" | "1" => Ok(1),
+        "critical" | "2" => Ok(2),
+        _ => Err(format!(
+            "Unknown urgency {value}, expected low, normal or critical"
+        )),
+    }
+}
+
+/// Parse the arguments, program name included
+pub fn parse(args: impl IntoIterator<Item = String>) -> Result<Command, String> {
+    let mut args = args.into_iter().skip(1);
+    let mut positional = Vec::new();
+    let mut options = Options::new(String::new());
+
+    while let Some(arg) = args.next() {
+        if arg == "--" {
+            positional.extend(args.by_ref());
+            break;
+        }
+        // Options take their value as `--name=value`, `--name value`,
+        // `-xvalue` or `-x value`
+        let (name, inline) = if let Some(long) = arg.strip_prefix("--") {
+            match long.split_once('=') {
+                Some((name, value)) => (name.to_string(), Some(value.to_string())),
+                None => (long.to_string(), None),
+            }
+        } else if arg.len() > 1 && arg.starts_with('-') {
+            let mut chars = arg[1..].chars();
+            let short = chars.next().map(String::from).unwrap_or_default();
+            let rest: String = chars.collect();
+            (short, (!rest.is_empty()).then_some(rest))
+        } else {
+            positional.push(arg);
+            continue;
+        };
+        let mut value = |option: &str| {
+            inline
+                .clone()
+                .or_else(|| args.next())
+                .ok_or_else(|| format!("Option --{option} needs a value"))
+        };
+
+        match name.as_str() {
+            "help" | "?" => return Ok(Command::Help),
+            "v" | "version" => return Ok(Command::Version),
+            "u" | "urgency" => options.urgency = Some(parse_urgency(&value("urgency")?)?),
+            "t" | "expire-time" => {
+                let timeout = value("expire-time")?;
+                options.expire_timeout = timeout
+                    .parse()
+                    .map_err(|_| format!("Invalid timeout {timeout}"))?;
+            }
+            "a" | "app-name" => options.app_name = value("app-name")?,
+            "i" | "icon" => options.app_icon = value("icon")?,
+            "c" | "category" => options.category = Some(value("category")?),
+            "e" | "transient" => options.transient = true,
+            "h" | "hint" => {
+                let hint = value("hint")?;
+                let mut parts = hint.splitn(3, ':');
+                let (Some(kind), Some(name), Some(raw)) =
+                    (parts.next(), parts.next(), parts.next())
+                else {
+                    return Err(format!("Invalid hint {hint}, expected TYPE:NAME:VALUE"));
+                };
+                options
+                    .hints
+                    .push((name.to_string(), HintValue::parse(kind, raw)?));
+            }
+            "A" | "action" => {
+                let action = value("action")?;
+                let (key, label) = match action.split_once('=') {
+                    Some((key, label)) => (key.to_string(), label.to_string()),
+                    // Unnamed actions are numbered like notify-send does
+                    None => (options.actions.len().to_string(), action),
+                };
+                options.actions.push((key, label));
+            }
+            "w" | "wait" => options.wait = true,
+            "p" | "print-id" => options.print_id = true,
+            "r" | "replace-id" => {
+                let id = value("replace-id")?;
+                options.replace_id = id.parse().map_err(|_| format!("Invalid id {id}"))?;
+            }
+            _ => return Err(format!("Unknown option {arg}")),
+        }
+    }
+
+    let mut positional = positional.into_iter();
+    let Some(summary) = positional.next() else {
+        return Err("No summary specified".to_string());
+    };
+    options.summary = summary;
+    options.body = positional.next().unwrap_or_default();
+    if positional.next().is_some() {
+        return Err("Too many arguments".to_string());
+    }
+    Ok(Command::Send(options))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn send(args: &[&str]) -> Options {
+        let args = std::iter::once("cosmic-notify-send")
+            .chain(args.iter().copied())
+            .map(String::from);
+        match parse(args) {
+            Ok(Command::Send(options)) => options,
+            other => panic!("expected a notification, got {other:?}"),
+        }
+    }
+
+    #[test]
+    fn test_summary_and_body() {
+        let options = send(&["Hello", "World"]);
+        assert_eq!(options.summary, "Hello");
+        assert_eq!(options.body, "World");
+        assert_eq!(options.app_name, "notify-send");
+        assert_eq!(options.expire_timeout, -1);
+        assert!(!options.waits());
+    }
+
+    #[test]
+    fn test_option_forms() {
+        let options = send(&[
+            "-ucritical",
+            "--expire-time=3000",
+            "-i",
+            "dialog-error",
+            "-p",
+            "Alert",
+        ]);
+        assert_eq!(options.urgency, Some(2));
+        assert_eq!(options.expire_timeout, 3000);
+        assert_eq!(options.app_icon, "dialog-error");
+        assert!(options.print_id);
+
+        let options = send(&["-r", "42", "--", "-not-an-option"]);
+        assert_eq!(options.replace_id, 42);
+        assert_eq!(options.summary, "-not-an-option");
+    }
+
+    #[test]
+    fn test_hints() {
+        let options = send(&[
+            "-h",
+            "int:value:75",
+            "--hint=string:desktop-entry:org.example.App",
+            "-h",
+            "string:x-note:a:b",
+            "-e",
+            "Progress",
+        ]);
+        let hints = options.hints();
+        assert_eq!(hints.get("value"), Some(&Value::I32(75)));
+        assert_eq!(
+            hints.get("desktop-entry"),
+            Some(&Value::from("org.example.App"))
+        );
+        assert_eq!(hints.get("x-note"), Some(&Value::from("a:b")));
+        assert_eq!(hints.get("transient"), Some(&Value::Bool(true)));
+
+        assert!(parse(["", "-h", "float:x:1", "S"].map(String::from)).is_err());
+        assert!(parse(["", "-h", "int:value", "S"].map(String::from)).is_err());
+    }
+
+    #[test]
+    fn test_actions() {
+        let options = send(&["-A", "open=Open", "--action", "Later", "Build done"]);
+        assert_eq!(options.action_list(), vec!["open", "Open", "1", "Later"]);
+        assert!(options.waits());
+    }
+
+    #[test]
+    fn test_errors() {
+        assert!(parse(["cosmic-notify-send"].map(String::from)).is_err());
+        assert!(parse(["", "-u", "urgent", "S"].map(String::from)).is_err());
+        assert!(parse(["", "--bogus", "S"].map(String::from)).is_err());
+        assert!(parse(["", "a", "b", "c"].map(String::from)).is_err());
+        assert_eq!(parse(["", "--help"].map(String::from)), Ok(Command::Help));
+    }
+}
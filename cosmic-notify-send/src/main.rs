@@ -0,0 +1,110 @@
+//! notify-send for the COSMIC notification daemon
+//!
+//! Speaks `org.freedesktop.Notifications` directly, so scripts get the same
+//! options as libnotify's notify-send without depending on libnotify.
+
+mod args;
+
+use args::{Command, Options};
+use cosmic_ext_notifications_client::NotificationsProxyBlocking;
+use std::process::ExitCode;
+use std::sync::mpsc;
+
+/// What ended the wait for a notification
+enum Event {
+    Action(u32, String),
+    Closed(u32),
+}
+
+fn main() -> ExitCode {
+    match args::parse(std::env::args()) {
+        Ok(Command::Send(options)) => match send(&options) {
+            Ok(()) => ExitCode::SUCCESS,
+            Err(err) => {
+                eprintln!("cosmic-notify-send: {err}");
+                ExitCode::FAILURE
+            }
+        },
+        Ok(Command::Help) => {
+            println!("{}", args::USAGE);
+            ExitCode::SUCCESS
+        }
+        Ok(Command::Version) => {
+            println!("cosmic-notify-send {}", env!("CARGO_PKG_VERSION"));
+            ExitCode::SUCCESS
+        }
+        Err(err) => {
+            eprintln!("cosmic-notify-send: {err}\n\n{}", args::USAGE);
+            ExitCode::from(2)
+        }
+    }
+}
+
+fn send(options: &Options) -> zbus::Result<()> {
+    let conn = zbus::blocking::Connection::session()?;
+    let proxy = NotificationsProxyBlocking::new(&conn)?;
+
+    // Subscribe before sending, or a quick click could be missed
+    let events = if options.waits() {
+        let (tx, rx) = mpsc::channel();
+        let actions = proxy.receive_action_invoked()?;
+        let closed = proxy.receive_notification_closed()?;
+        let action_tx = tx.clone();
+        std::thread::spawn(move || {
+            for signal in actions {
+                let Ok(args) = signal.args() else {
+                    continue;
+                };
+                let event = Event::Action(args.id, args.action_key.to_string());
+                if action_tx.send(event).is_err() {
+                    break;
+                }
+            }
+        });
+        std::thread::spawn(move || {
+            for signal in closed {
+                let Ok(args) = signal.args() else {
+                    continue;
+                };
+                if tx.send(Event::Closed(args.id)).is_err() {
+                    break;
+                }
+            }
+        });
+        Some(rx)
+    } else {
+        None
+    };
+
+    let id = proxy.notify(
+        &options.app_name,
+        options.replace_id,
+        &options.app_icon,
+        &options.summary,
+        &options.body,
+        &options.action_list(),
+        options.hints(),
+        options.expire_timeout,
+    )?;
+    if options.print_id {
+        println!("{id}");
+    }
+
+    let Some(events) = events else {
+        return Ok(());
+    };
+    // Both signal threads ending means the connection is gone
+    for event in events {
+        match event {
+            Event::Action(action_id, key) if action_id == id => {
+                println!("{key}");
+                // The daemon closes the notification after the action
+                // unless it's resident, so don't wait for that
+                return Ok(());
+            }
+            Event::Closed(closed_id) if closed_id == id => return Ok(()),
+            _ => {}
+        }
+    }
+    Ok(())
+}
@@ -1 +1,214 @@
 app-name = COSMIC Notifications
+
+## Focus sessions
+
+focus-session-ended = Focus session ended
+focus-session-nothing-suppressed = No notifications arrived while you were focused.
+focus-session-suppressed = { $count ->
+        [one] 1 notification
+       *[other] { $count } notifications
+    } from { $app_count ->
+        [one] 1 app
+       *[other] { $app_count } apps
+    } arrived while you were focused: { $apps }
+focus-session-suppressed-app = { $app } ({ $count })
+
+## Away summary
+
+away-summary-title = While you were away
+away-summary = { $count ->
+        [one] 1 notification
+       *[other] { $count } notifications
+    } from { $app_count ->
+        [one] 1 app
+       *[other] { $app_count } apps
+    }: { $apps }
+away-summary-app = { $app } ({ $count })
+away-summary-open = Open notification center
+
+## Rate limit summary
+
+rate-limit-title = Too many notifications
+rate-limit-summary = { $count ->
+        [one] 1 notification was
+       *[other] { $count } notifications were
+    } dropped: { $apps }
+
+## Notification storms
+
+storm-started-title = { $app } is sending too many notifications
+storm-started = { $minutes ->
+        [one] Its notifications go to the notification center for the next minute, then come as one summary
+       *[other] Its notifications go to the notification center for the next { $minutes } minutes, then come as one summary
+    }
+
+## Digests
+
+digest-title = { $app }: { $count ->
+        [one] 1 notification
+       *[other] { $count } notifications
+    }
+digest-more = { $count ->
+        [one] and 1 more
+       *[other] and { $count } more
+    }
+
+## Do-not-disturb summary
+
+dnd-summary-title = While do not disturb was on
+dnd-summary-open = Open notification center
+
+## Email digest
+
+email-digest-subject = { $count ->
+        [one] 1 notification
+       *[other] { $count } notifications
+    } while you were away
+email-digest-intro = Arrived since { $since } while you were away:
+email-digest-private = Private notification
+
+## Contact quick actions
+
+contact-call = Call
+contact-copy-number = Copy number
+contact-compose-email = Compose email
+
+## Masked one-time codes and reset links
+
+secret-show = Show
+secret-hide = Hide
+secret-copy-code = Copy code
+secret-copy-link = Copy link
+
+## History sections
+
+history-today = Today
+history-yesterday = Yesterday
+history-date = { $weekday }, { $month } { $day }, { $year }
+history-weekday = { $weekday ->
+        [1] Monday
+        [2] Tuesday
+        [3] Wednesday
+        [4] Thursday
+        [5] Friday
+        [6] Saturday
+       *[7] Sunday
+    }
+history-month = { $month ->
+        [1] January
+        [2] February
+        [3] March
+        [4] April
+        [5] May
+        [6] June
+        [7] July
+        [8] August
+        [9] September
+        [10] October
+        [11] November
+       *[12] December
+    }
+
+## Conversations
+
+mute-conversation = { $hours ->
+        [one] Mute for 1 hour
+       *[other] Mute for { $hours } hours
+    }
+
+## Dismissing
+
+dismiss-all = Dismiss all
+dismiss-app = Dismiss all from { $app }
+
+## Lock screen
+
+lock-redacted = New notification from { $app }
+
+## Critical banner
+
+banner-title = Critical notification
+banner-dismiss = Dismiss
+banner-more = { $count ->
+        [one] 1 more critical notification
+       *[other] { $count } more critical notifications
+    }
+
+## Popup menu
+
+menu-mute-app = Mute { $app }
+menu-edit-rule = Edit rule for { $app }
+menu-copy-text = Copy text
+menu-snooze = Snooze…
+
+## Snooze
+
+snooze = Snooze
+snooze-minutes = { $minutes ->
+        [one] 1 minute
+       *[other] { $minutes } minutes
+    }
+snooze-hours = { $hours ->
+        [one] 1 hour
+       *[other] { $hours } hours
+    }
+snooze-tomorrow = Tomorrow
+
+## Popup groups
+
+group-collapse = Show less
+group-clear = Clear group
+
+## Notification center
+
+center-title = Notifications
+center-empty = No notifications
+center-search = Search history
+center-no-results = No matching notifications
+center-dnd = Do not disturb
+center-priority-only = Only priority notifications
+center-clear-all = Clear all
+
+## Inline reply
+
+reply-placeholder = Write a reply…
+reply-open = Reply
+reply-send = Send
+reply-cancel = Cancel reply
+
+## Counts and numbers
+
+time-now = just now
+time-minutes = { $minutes ->
+        [one] 1 min ago
+       *[other] { $minutes } min ago
+    }
+time-hours = { $hours ->
+        [one] 1 hour ago
+       *[other] { $hours } hours ago
+    }
+time-days = { $days ->
+        [one] yesterday
+       *[other] { $days } days ago
+    }
+group-label = { $name } ({ $count })
+repeat-count = ×{ $count }
+more-notifications = { $count ->
+        [one] +1 more
+       *[other] +{ $count } more
+    }
+progress-percent = { $percent }%
+progress-label = Progress: { $percent }%
+progress-unknown = Progress: --
+
+## Screen reader announcements
+
+live-progress = { $summary }: { $percent }%
+live-arrived = { $app }: { $summary }
+live-arrived-urgent = Urgent, { $app }: { $summary }
+
+## Accessible names
+
+a11y-dismiss = Dismiss notification from { $app }
+a11y-remove = Remove from history
+a11y-close-center = Close notification center
@@ -29,41 +29,85 @@
 // - Card list animations are handled efficiently by cosmic_time::anim! macro
 
 use crate::rendering::build_element_row;
-use crate::subscriptions::notifications;
-use crate::widgets::{notification_progress, RichCardConfig};
+use crate::subscriptions::{control::ControlState, focused_window::focused_window, notifications, screen_share, session};
+#[cfg(feature = "audio")]
+use crate::subscriptions::sound;
+use crate::widgets::{live_region, notification_progress, AnimatedProgress, RichCardConfig};
 use cosmic::app::{Core, Settings};
-use cosmic::cosmic_config::{Config, CosmicConfigEntry};
+use cosmic::cosmic_config::{Config, ConfigGet, ConfigSet, CosmicConfigEntry};
 use cosmic::iced::platform_specific::runtime::wayland::layer_surface::{
     IcedMargin, IcedOutput, SctkLayerSurfaceSettings,
 };
 use cosmic::iced::platform_specific::shell::wayland::commands::{
     activation,
-    layer_surface::{Anchor, KeyboardInteractivity, destroy_layer_surface, get_layer_surface},
+    layer_surface::{
+        Anchor, KeyboardInteractivity, Layer, destroy_layer_surface, get_layer_surface,
+        set_keyboard_interactivity, set_margin,
+    },
 };
 use cosmic::iced::{self, Length, Limits, Subscription};
 use cosmic::iced_runtime::core::window::Id as SurfaceId;
-use cosmic::iced_widget::{column, row, vertical_space};
-use cosmic::widget::{autosize, button, container, icon, text};
+use cosmic::cctk::wayland_client::protocol::wl_output::WlOutput;
+use cosmic::iced_widget::{column, mouse_area, row, vertical_space};
+use cosmic::widget::{autosize, button, container, icon, scrollable, text, text_input, toggler, tooltip};
 use cosmic::{Application, Element, app::Task};
-use cosmic_ext_notifications_config::NotificationsConfig;
+use cosmic_ext_notifications_config::{
+    AnimationStyle, AnnounceVerbosity, AppRule, Density, DndMode, FullscreenPopups, GroupingMode, HistoryWipe, HookEvent, HoverTimeout, LockScreenVisibility,
+    MutedConversation, NotificationsConfig, PopupPlacement,
+    ReplaceTimeout, SnoozePreset,
+};
+#[cfg(feature = "audio")]
+use cosmic_ext_notifications_util::{PlaybackEvent, PlaybackHandle, SoundDebouncer};
+#[cfg(feature = "plugins")]
+use cosmic_ext_notifications_util::PluginHost;
 use cosmic_ext_notifications_util::{
-    ActionId, CloseReason, Notification, NotificationLink,
-    clean_bare_schemes, detect_links, extract_hrefs, sanitize_html, strip_html,
+    ActionId, CloseReason, Hint, Image, ImagePolicy, INLINE_REPLY_ACTION, Notification, NotificationGroup,
+    NotificationLink, StyledSegment, group_notifications,
+    action_icon_name, clean_bare_schemes, detect_contacts, extract_hrefs, is_message_category, linkify_segments,
+    sanitize_and_parse, sanitize_html, segments_to_plain_text, strip_html,
 };
 
-use crate::state::NotificationState;
-use crate::handlers::Message;
-use crate::rendering::{render_notification_image, render_markup_body, render_body_with_links, get_progress_from_hints};
+use crate::compositor::CompositorCapabilities;
+#[cfg(feature = "systemd")]
+use crate::journal::JournalSink;
+#[cfg(feature = "webhook")]
+use crate::webhook::WebhookSink;
+#[cfg(feature = "email")]
+use crate::email::EmailSink;
+use crate::state::{
+    history, restore, DateSection, DigestQueue, Exit, FocusSession, LiveAnnouncer, LiveChange, LiveUpdate, NotificationState, Politeness,
+    PriorityScorer,
+    HistoryStats, ImageCache, KeyFocus, KeyMove, SearchIndex, SectionCollapseState, SnoozeQueue, StormDetector, StormVerdict, Swipe, Wakeup, Wakeups,
+    Transitions, snooze_due, stats::{AuditOutcome, Outcome},
+};
+use crate::handlers::{KeyInput, MenuItem, Message, PointerInput};
+use crate::rendering::{decode_image, group_label, more_label, relative_time, repeat_label, render_notification_image, render_avatar_with_badge, render_markup_body, render_body_with_links, render_contact_actions, render_secret_actions, render_transition, get_progress_from_hints, snooze_label};
 use crate::constants::*;
-use cosmic_panel_config::{CosmicPanelConfig, CosmicPanelOuput, PanelAnchor};
+use cosmic_panel_config::{CosmicPanelConfig, CosmicPanelOuput, PanelAnchor, PanelSize};
+use chrono::{Datelike, Timelike};
 use cosmic_time::{Timeline, anim, id};
 use iced::Alignment;
 use std::borrow::Cow;
-use std::time::Duration;
+use std::collections::{BTreeSet, HashMap, HashSet};
+use std::sync::Arc;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
 use tokio::sync::mpsc;
 
 static NOTIFICATIONS_APPLET: &str = "com.system76.CosmicAppletNotifications";
 
+/// State key holding the snooze queue
+const SNOOZED_KEY: &str = "snoozed";
+
+/// State key holding the popups and held notifications of the last run
+const PENDING_KEY: &str = "pending";
+
+/// State key holding the per-app notification counts
+const STATS_KEY: &str = "stats";
+
+/// State key holding when the session was locked or ended, as Unix
+/// seconds, for the away summary at the next login; 0 once the user is back
+const AWAY_KEY: &str = "away_since";
+
 pub fn run() -> cosmic::iced::Result {
     cosmic::app::run::<CosmicNotifications>(
         Settings::default()
@@ -79,19 +123,407 @@ pub fn run() -> cosmic::iced::Result {
     Ok(())
 }
 
+/// Text for a card's live region
+fn live_announcement(update: &LiveUpdate) -> String {
+    match &update.change {
+        LiveChange::Content { summary, body } => [summary.as_deref(), body.as_deref()]
+            .into_iter()
+            .flatten()
+            .collect::<Vec<_>>()
+            .join(": "),
+        LiveChange::Arrived { app, summary, body } => {
+            let heading = if update.politeness == Politeness::Assertive {
+                crate::fl!("live-arrived-urgent", app = app.as_str(), summary = summary.as_str())
+            } else {
+                crate::fl!("live-arrived", app = app.as_str(), summary = summary.as_str())
+            };
+            match body {
+                Some(body) => format!("{heading}: {body}"),
+                None => heading,
+            }
+        }
+        LiveChange::Progress { summary, percent } => crate::fl!(
+            "live-progress",
+            summary = summary.as_str(),
+            percent = *percent
+        ),
+    }
+}
+
+/// `n` with its one-time codes and reset links masked, unless the user
+/// revealed them or the app's rule turns masking off
+fn masked(config: &NotificationsConfig, revealed: &HashSet<u32>, n: &Notification) -> Option<Notification> {
+    if revealed.contains(&n.id) || !config.mask_secrets_for_app(&n.app_name, n.desktop_entry()) {
+        return None;
+    }
+    n.masked()
+}
+
+/// The entries of `notifications` matching `query`, in order
+fn search(notifications: Vec<Notification>, query: &str) -> Vec<Notification> {
+    let mut index = SearchIndex::default();
+    index.sync(&notifications);
+    let ids = index.search(query);
+    notifications.into_iter().filter(|n| ids.contains(&n.id)).collect()
+}
+
+/// Whether the `wipe_history` setting removes a notification
+fn wiped(config: &NotificationsConfig, n: &Notification) -> bool {
+    match config.wipe_history {
+        HistoryWipe::Never => false,
+        HistoryWipe::Sensitive => config.is_sensitive_app(&n.app_name, n.desktop_entry()),
+        HistoryWipe::All => true,
+    }
+}
+
+/// Load the enabled `plugins` on a blocking thread, as compiling them
+/// takes a while
+#[cfg(feature = "plugins")]
+fn load_plugins(config: &NotificationsConfig) -> Task<Message> {
+    let plugins: Vec<_> = config.plugins.iter().filter(|p| p.enabled).cloned().collect();
+    Task::perform(
+        async move {
+            tokio::task::spawn_blocking(move || {
+                PluginHost::load(
+                    plugins
+                        .iter()
+                        .map(|p| (std::path::Path::new(&p.path), p.app_name.as_deref(), &p.settings)),
+                )
+            })
+            .await
+            .unwrap_or_default()
+        },
+        |host| cosmic::action::app(Message::PluginsLoaded(Arc::new(host))),
+    )
+}
+
+/// Connection to the journal for `journal_audit`, if it is on
+#[cfg(feature = "systemd")]
+fn journal_sink(config: &NotificationsConfig) -> Option<JournalSink> {
+    if !config.journal_audit {
+        return None;
+    }
+    match JournalSink::connect() {
+        Ok(journal) => Some(journal),
+        Err(err) => {
+            tracing::warn!("Journal audit unavailable: {}", err);
+            None
+        }
+    }
+}
+
+/// Worker posting to the `webhook`, if one is set
+#[cfg(feature = "webhook")]
+fn webhook_sink(config: &NotificationsConfig) -> Option<WebhookSink> {
+    config.webhook.as_ref().and_then(WebhookSink::start)
+}
+
+/// Worker sending the `email_digest`, if one is set
+#[cfg(feature = "email")]
+fn email_sink(config: &NotificationsConfig) -> Option<EmailSink> {
+    config.email_digest.as_ref().and_then(EmailSink::start)
+}
+
+/// Mouse and touch events that can drive a swipe
+fn pointer_input(
+    event: cosmic::iced::Event,
+    _status: cosmic::iced::event::Status,
+    surface: SurfaceId,
+) -> Option<Message> {
+    use cosmic::iced::{mouse, touch};
+
+    // Captured events count too, the whole card is a button
+    let input = match event {
+        cosmic::iced::Event::Mouse(mouse::Event::CursorMoved { position }) => {
+            PointerInput::Moved(position.x)
+        }
+        cosmic::iced::Event::Mouse(mouse::Event::ButtonPressed(mouse::Button::Left)) => {
+            PointerInput::Pressed(None)
+        }
+        cosmic::iced::Event::Mouse(mouse::Event::ButtonReleased(mouse::Button::Left)) => {
+            PointerInput::Released
+        }
+        cosmic::iced::Event::Touch(touch::Event::FingerPressed { position, .. }) => {
+            PointerInput::Pressed(Some(position.x))
+        }
+        cosmic::iced::Event::Touch(touch::Event::FingerMoved { position, .. }) => {
+            PointerInput::Moved(position.x)
+        }
+        cosmic::iced::Event::Touch(
+            touch::Event::FingerLifted { .. } | touch::Event::FingerLost { .. },
+        ) => PointerInput::Released,
+        _ => return None,
+    };
+    Some(Message::Pointer(surface, input))
+}
+
+/// Room a panel takes up from its screen edge, gap included
+///
+/// Panels don't publish their thickness, so it's worked out from the
+/// configured size.
+fn panel_thickness(panel: &CosmicPanelConfig) -> i32 {
+    let size = match panel.size {
+        PanelSize::XS => PANEL_THICKNESS_XS,
+        PanelSize::S => PANEL_THICKNESS_S,
+        PanelSize::M => PANEL_THICKNESS_M,
+        PanelSize::L => PANEL_THICKNESS_L,
+        PanelSize::XL => PANEL_THICKNESS_XL,
+        PanelSize::Custom(size) => i32::try_from(size).unwrap_or(i32::MAX),
+    };
+    size.saturating_add(i32::from(panel.margin))
+}
+
+/// Outputs appearing, getting their name, or going away
+fn output_event(
+    event: cosmic::iced::Event,
+    _status: cosmic::iced::event::Status,
+    _surface: SurfaceId,
+) -> Option<Message> {
+    use cosmic::iced::event::{PlatformSpecific, wayland};
+
+    let cosmic::iced::Event::PlatformSpecific(PlatformSpecific::Wayland(wayland::Event::Output(
+        event,
+        output,
+    ))) = event
+    else {
+        return None;
+    };
+    let name = match event {
+        wayland::OutputEvent::Created(Some(info)) | wayland::OutputEvent::InfoUpdate(info) => {
+            Some(info.name?)
+        }
+        wayland::OutputEvent::Removed => None,
+        _ => return None,
+    };
+    Some(Message::Output(output, name))
+}
+
+/// Key presses that navigate popups
+///
+/// Keys a focused reply field handles are left to it.
+fn key_input(
+    event: cosmic::iced::Event,
+    status: cosmic::iced::event::Status,
+    surface: SurfaceId,
+) -> Option<Message> {
+    use cosmic::iced::keyboard::{self, Key, key::Named};
+
+    if status == cosmic::iced::event::Status::Captured {
+        return None;
+    }
+    let cosmic::iced::Event::Keyboard(keyboard::Event::KeyPressed { key, modifiers, .. }) = event
+    else {
+        return None;
+    };
+    let input = match key {
+        Key::Named(Named::ArrowUp) => KeyInput::Move(KeyMove::Up),
+        Key::Named(Named::ArrowDown) => KeyInput::Move(KeyMove::Down),
+        Key::Named(Named::ArrowLeft) => KeyInput::Move(KeyMove::Left),
+        Key::Named(Named::ArrowRight) => KeyInput::Move(KeyMove::Right),
+        Key::Named(Named::Tab) if modifiers.shift() => KeyInput::Move(KeyMove::Previous),
+        Key::Named(Named::Tab) => KeyInput::Move(KeyMove::Next),
+        Key::Named(Named::Enter | Named::Space) => KeyInput::Activate,
+        Key::Named(Named::Delete | Named::Backspace) => KeyInput::Dismiss,
+        Key::Named(Named::Escape) => KeyInput::Leave,
+        _ => return None,
+    };
+    Some(Message::Key(surface, input))
+}
+
+/// Action buttons a popup shows, without the default action and the
+/// inline reply, which have their own controls
+fn visible_actions(n: &Notification) -> impl Iterator<Item = &(ActionId, String)> {
+    n.actions
+        .iter()
+        .filter(|(id, _)| {
+            !matches!(id, ActionId::Default)
+                && !matches!(id, ActionId::Custom(id) if id == INLINE_REPLY_ACTION)
+        })
+        .take(MAX_VISIBLE_ACTIONS)
+}
+
+/// Whether `n` shows on the critical banner instead of as a popup
+fn shows_in_banner(config: &NotificationsConfig, n: &Notification) -> bool {
+    config.critical_banner && n.urgency() == 2
+}
+
+/// Widget id of a popup's reply field, for focusing it
+fn reply_input_id(id: u32) -> cosmic::widget::Id {
+    cosmic::widget::Id::new(format!("reply-{id}"))
+}
+
+/// Current time as Unix seconds, for config entries that outlive the process
+fn unix_now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0)
+}
+
+/// Push the configured fade lengths to the audio engine
+#[cfg(feature = "audio")]
+fn apply_sound_fades(config: &NotificationsConfig) {
+    cosmic_ext_notifications_util::set_fade_durations(
+        Duration::from_millis(config.sound_fade_in_ms.into()),
+        Duration::from_millis(config.sound_fade_out_ms.into()),
+    );
+}
+
 struct CosmicNotifications {
     core: Core,
     active_surface: bool,
     autosize_id: iced::id::Id,
     window_id: SurfaceId,
+    /// Outputs by name, for placing popups on a particular one
+    outputs: HashMap<String, WlOutput>,
+    /// Output holding the focused window, when known
+    focused_output: Option<String>,
+    /// The focused window is fullscreen
+    fullscreen: bool,
+    /// App id of the focused window, if known
+    focused_app: Option<String>,
+    /// Output the popup surface was put on, `None` if the compositor chose
+    popup_output: Option<String>,
+    /// Surface of the critical notification banner, while `banner_open`
+    banner_id: SurfaceId,
+    banner_open: bool,
+    banner_autosize_id: iced::id::Id,
+    /// Surface of the notification center, while `center_open`
+    center_id: SurfaceId,
+    center_open: bool,
+    /// Search text in the notification center, and the ids it matched
+    center_query: String,
+    center_matches: BTreeSet<u32>,
     state: NotificationState,
     notifications_id: id::Cards,
     notifications_tx: Option<mpsc::Sender<notifications::Input>>,
     config: NotificationsConfig,
+    /// Handle for writing config changes made over D-Bus
+    config_helper: Option<Config>,
     dock_config: CosmicPanelConfig,
     panel_config: CosmicPanelConfig,
     anchor: Option<(Anchor, Option<String>)>,
     timeline: Timeline,
+    focus_session: Option<FocusSession>,
+    /// When the session was locked, for the away summary
+    locked_since: Option<SystemTime>,
+    /// Decides which card updates screen readers announce
+    live: LiveAnnouncer,
+    /// Latest announcement per card, exposed through its live region
+    live_updates: HashMap<u32, LiveUpdate>,
+    /// Latest new popup, exposed through the live region of the popup
+    /// list, which is already there when the card appears
+    arrival: Option<LiveUpdate>,
+    /// Inline reply text being typed, by notification id; a popup shows
+    /// its reply field while it has an entry here
+    reply_drafts: HashMap<u32, String>,
+    /// Popup images decoded off the UI thread
+    images: ImageCache,
+    /// Limits how many images decode at once
+    image_permits: Arc<tokio::sync::Semaphore>,
+    /// Keys of the popup groups opened in place
+    expanded_groups: HashSet<String>,
+    /// Popup showing its right-click menu
+    context_menu: Option<u32>,
+    /// Popup showing its snooze durations
+    snooze_picker: Option<u32>,
+    /// Keyboard focus the popup surface currently asks for
+    keyboard: KeyboardInteractivity,
+    /// Popup or button focused from the keyboard; keyboard navigation is
+    /// on while this is set
+    key_focus: Option<KeyFocus>,
+    /// Popup under the pointer, with what was left of its timeout when the
+    /// pointer entered (`None` if it had none running)
+    hovered: Option<(u32, Option<Duration>)>,
+    /// Last horizontal pointer position over the popups
+    pointer_x: Option<f32>,
+    /// Popup being dragged or sliding off
+    swipe: Option<Swipe>,
+    /// Popup whose drag just ended; the click that comes with letting go
+    /// must not activate it
+    swiped: Option<u32>,
+    /// Popups sliding, fading or scaling in and out per `animation_style`
+    transitions: Transitions,
+    /// When each popup expires; replacements push this back, so earlier
+    /// timeouts for the same id are ignored
+    expiry_deadlines: HashMap<u32, Instant>,
+    /// Progress bars easing towards the value of their latest replacement
+    progress_anims: HashMap<u32, AnimatedProgress>,
+    /// How many identical notifications each popup stands for, when more
+    /// than one
+    repeats: HashMap<u32, u32>,
+    /// Apps sending notification storms, quieted for a while
+    storms: StormDetector,
+    /// Notifications held for their app's digest
+    digests: DigestQueue,
+    /// Latest timers for snoozes, mutes, storms and digests
+    wakeups: Wakeups,
+    /// Audit entries go here while `journal_audit` is on
+    #[cfg(feature = "systemd")]
+    journal: Option<JournalSink>,
+    /// Matching notifications are posted here while a `webhook` is set
+    #[cfg(feature = "webhook")]
+    webhook: Option<WebhookSink>,
+    /// Loaded `plugins`
+    #[cfg(feature = "plugins")]
+    plugins: Arc<PluginHost>,
+    /// Popups whose one-time codes and reset links the user chose to see
+    revealed: HashSet<u32>,
+    /// When a timed do-not-disturb set over D-Bus turns off
+    dnd_until: Option<Instant>,
+    /// When do-not-disturb was turned on, for the summary once it ends
+    dnd_since: Option<SystemTime>,
+    /// The "while DND was on" summary, activating it opens the center
+    dnd_summary_id: Option<u32>,
+    /// The "while you were away" summary, activating it opens the center
+    away_summary_id: Option<u32>,
+    /// When the last session was locked or ended, for the away summary once
+    /// the interface is up
+    away_at_login: Option<SystemTime>,
+    /// The screen is being shared, with `screen_share_dnd` on
+    screen_shared: bool,
+    /// Digests are sent from here while an `email_digest` is set
+    #[cfg(feature = "email")]
+    email: Option<EmailSink>,
+    /// When the last email digest was made, to start the next one there
+    #[cfg(feature = "email")]
+    email_digest_sent: Option<SystemTime>,
+    /// Popups are held back until delivery resumes
+    paused: bool,
+    /// Notifications received while paused or queued behind a fullscreen
+    /// window, oldest first
+    held: Vec<Notification>,
+    /// Notifications waiting to be shown again
+    snoozed: SnoozeQueue,
+    /// Notifications saved by the last run, shown once the interface is up;
+    /// `None` after that, which is when saving starts
+    restore: Option<Vec<Notification>>,
+    /// Ids and times of the popups and held notifications last saved
+    pending_saved: Vec<(u32, SystemTime)>,
+    /// Notification counts per app and hour, for the statistics API
+    stats: HistoryStats,
+    /// The counts changed since they were last saved
+    stats_dirty: bool,
+    /// Popups whose action or reply was sent, so closing them afterwards
+    /// isn't counted as a dismissal
+    actioned: HashSet<u32>,
+    /// Handle for state that survives restarts, such as snoozes
+    state_helper: Option<Config>,
+    /// State last published on the control interface
+    control_state: Option<ControlState>,
+    capabilities: CompositorCapabilities,
+    history_collapse: SectionCollapseState,
+    /// Full-text index over the history, synced before each search
+    search_index: SearchIndex,
+    /// Sounds still playing, by notification id, so they can be cut off
+    #[cfg(feature = "audio")]
+    sounds: HashMap<u32, PlaybackHandle>,
+    /// Drops bursts of the same sound from one app
+    #[cfg(feature = "audio")]
+    sound_debouncer: SoundDebouncer,
+    /// Most recent sound that failed to play, for diagnostics
+    #[cfg(feature = "audio")]
+    last_sound_failure: Option<String>,
 }
 
 
@@ -105,6 +537,22 @@ impl CosmicNotifications {
     /// - Progress bar if present in hints
     /// - Action buttons if present
     fn render_rich_notification(&self, n: &Notification, config: &RichCardConfig) -> Element<'static, Message> {
+        let secrets = if !self.hides_content() && self.config.mask_secrets_for_app(&n.app_name, n.desktop_entry()) {
+            n.secrets()
+        } else {
+            Vec::new()
+        };
+        let redacted;
+        let n = if self.hides_content() {
+            redacted = self.redacted(n);
+            &redacted
+        } else if let Some(masked) = self.masked(n) {
+            redacted = masked;
+            &redacted
+        } else {
+            n
+        };
+
         // Header: App icon, app name, close button
         let app_name_text = text::caption(if n.app_name.len() > APP_NAME_MAX_LENGTH {
             Cow::from(format!("{:.26}...", n.app_name.lines().next().unwrap_or_default()))
@@ -128,26 +576,55 @@ impl CosmicNotifications {
                 .symbolic(true),
         )
         .on_press(Message::Dismissed(n.id))
-        .class(cosmic::theme::Button::Text);
+        .class(cosmic::theme::Button::Text)
+        .name(crate::fl!("a11y-dismiss", app = n.app_name.as_str()));
 
-        // Optional timestamp
+        // Optional timestamp, after how often the notification repeated
         let timestamp: Element<'static, Message> = if let Some(duration) = n.duration_since() {
-            let secs = duration.as_secs();
-            let time_text = if secs < 60 {
-                "now".to_string()
-            } else if secs < 3600 {
-                format!("{}m", secs / 60)
-            } else if secs < 86400 {
-                format!("{}h", secs / 3600)
-            } else {
-                format!("{}d", secs / 86400)
-            };
-            text::caption(time_text).into()
+            text::caption(relative_time(duration)).into()
+        } else {
+            cosmic::widget::Space::new(0, 0).into()
+        };
+        let repeats: Element<'static, Message> = match self.repeats.get(&n.id) {
+            Some(count) => text::caption_heading(repeat_label(*count)).into(),
+            None => cosmic::widget::Space::new(0, 0).into(),
+        };
+
+        // Apps flooding the screen can be cleared in one go
+        let from_app = self.state.visible().iter().filter(|v| v.app_name == n.app_name).count();
+        let dismiss_app: Element<'static, Message> = if from_app > 1 {
+            tooltip(
+                button::custom(
+                    icon::from_name("edit-clear-all-symbolic")
+                        .size(ICON_SIZE_SMALL)
+                        .symbolic(true),
+                )
+                .on_press(Message::DismissApp(n.app_name.clone()))
+                .class(cosmic::theme::Button::Text)
+                .name(crate::fl!("dismiss-app", app = n.app_name.as_str())),
+                text::caption(crate::fl!("dismiss-app", app = n.app_name.as_str())),
+                tooltip::Position::Top,
+            )
+            .into()
         } else {
             cosmic::widget::Space::new(0, 0).into()
         };
 
-        let header = row![app_icon_elem, app_name_text, timestamp, close_button]
+        let snooze_button: Element<'static, Message> = if self.config.snooze_button {
+            button::custom(
+                icon::from_name("alarm-symbolic")
+                    .size(ICON_SIZE_SMALL)
+                    .symbolic(true),
+            )
+            .on_press(Message::SnoozePicker(n.id))
+            .class(cosmic::theme::Button::Text)
+            .name(crate::fl!("snooze"))
+            .into()
+        } else {
+            cosmic::widget::Space::new(0, 0).into()
+        };
+
+        let header = row![app_icon_elem, app_name_text, repeats, timestamp, dismiss_app, snooze_button, close_button]
             .spacing(8)
             .align_y(Alignment::Center);
 
@@ -160,7 +637,17 @@ impl CosmicNotifications {
         if config.show_images {
             if let Some(image) = n.image() {
                 // Image from hints (image-data, image-path) - use Expanded size (128x128)
-                if let Some(img_elem) = render_notification_image(image) {
+                if let Some(img_elem) = render_notification_image(
+                    image,
+                    self.images.get(n.id),
+                    Instant::now(),
+                    config.enable_animations,
+                ) {
+                    // Chat apps send the sender's picture, keep the app recognizable with a badge
+                    let img_elem = match self.avatar_badge_icon(n, image) {
+                        Some(badge) => render_avatar_with_badge(img_elem, badge),
+                        None => img_elem,
+                    };
                     body_elements.push(img_elem);
                 }
             } else if !n.app_icon.is_empty() {
@@ -189,9 +676,10 @@ impl CosmicNotifications {
 
         let href_links: Vec<NotificationLink> = extracted
             .into_iter()
-            .map(|(url, _text)| NotificationLink {
+            .map(|(url, text)| NotificationLink {
                 url,
-                title: None,
+                // Keep the anchor text so deceptive links can be flagged
+                title: Some(text),
                 start: 0,
                 length: 0,
             })
@@ -204,8 +692,22 @@ impl CosmicNotifications {
         // Also clean bare URL schemes (e.g., "https://") that Chrome includes as truncated URLs
         let display_body_str = clean_bare_schemes(&strip_html(&sanitize_html(&body_text)));
 
-        // Detect plain text URLs in the stripped body
-        let plain_links = detect_links(&display_body_str);
+        // Auto-linkify bare URLs and emails in the stripped body
+        let plain_links: Vec<NotificationLink> = if config.enable_links {
+            linkify_segments(vec![StyledSegment::plain(display_body_str.clone())])
+                .into_iter()
+                .filter_map(|segment| {
+                    segment.link.map(|url| NotificationLink {
+                        url,
+                        title: None,
+                        start: 0,
+                        length: 0,
+                    })
+                })
+                .collect()
+        } else {
+            Vec::new()
+        };
 
         // Combine href-extracted links with plain text links, preferring href links
         let links: Vec<NotificationLink> = if !href_links.is_empty() {
@@ -215,7 +717,16 @@ impl CosmicNotifications {
         };
 
         // Create body text - use markup rendering if HTML is present, otherwise plain text
-        let body_element: Element<'static, Message> = if has_markup {
+        let body_element: Element<'static, Message> = if config.compact {
+            // Compact cards keep to one line, links and markup included
+            let line = display_body_str.lines().next().unwrap_or_default();
+            let body_display = if line.chars().count() > COMPACT_BODY_MAX_LENGTH {
+                format!("{}…", line.chars().take(COMPACT_BODY_MAX_LENGTH).collect::<String>())
+            } else {
+                line.to_string()
+            };
+            text::caption(body_display).width(Length::Fill).into()
+        } else if has_markup {
             // Render with HTML markup styling (body-markup capability)
             let markup_body = render_markup_body(&body_text);
             if config.enable_links && !links.is_empty() {
@@ -257,22 +768,31 @@ impl CosmicNotifications {
         };
 
         // Build card content
-        let mut card_content = column![header, body_section].spacing(8);
+        let mut card_content = column![header, body_section].spacing(if config.compact { 4 } else { 8 });
 
         // Optional progress bar
         if let Some(progress_value) = get_progress_from_hints(n) {
+            let progress_value = self
+                .progress_anims
+                .get(&n.id)
+                .map_or(progress_value, AnimatedProgress::current_value);
             let progress_bar = notification_progress(progress_value, true);
             card_content = card_content.push(progress_bar);
         }
 
+        // Buttons focused from the keyboard stand out
+        let key_focus = self.key_focus.filter(|focus| focus.id == n.id);
+        let button_class = |index: usize| {
+            if key_focus.is_some_and(|focus| focus.button == Some(index)) {
+                cosmic::theme::Button::Suggested
+            } else {
+                cosmic::theme::Button::Standard
+            }
+        };
+
         // Optional action buttons - inline creation for 'static lifetime
         if config.show_actions && !n.actions.is_empty() {
-            // Filter to non-default actions and take up to MAX_VISIBLE_ACTIONS
-            let visible_actions: Vec<_> = n.actions
-                .iter()
-                .filter(|(id, _)| !matches!(id, ActionId::Default))
-                .take(MAX_VISIBLE_ACTIONS)
-                .collect();
+            let visible_actions: Vec<_> = visible_actions(n).collect();
 
             if !visible_actions.is_empty() {
                 let notification_id = n.id;
@@ -281,22 +801,33 @@ impl CosmicNotifications {
                 let mut action_elements: Vec<Element<'static, Message>> = Vec::with_capacity(visible_actions.len());
 
                 let use_icons = n.action_icons();
-                for (action_id, label) in visible_actions {
+                for (index, (action_id, label)) in visible_actions.into_iter().enumerate() {
                     let action_id_str = action_id.to_string();
                     let label_str = label.clone();
 
-                    let btn: Element<'static, Message> = if use_icons {
-                        // When action-icons hint is true, interpret action ID as icon name
-                        // Common icon names: "media-playback-start", "media-playback-pause", etc.
-                        let icon_name = action_id_str.clone();
-                        button::icon(icon::from_name(icon_name).size(16).symbolic(true))
-                            .on_press(Message::ActionClicked(notification_id, action_id_str))
-                            .padding([6, 12])
-                            .into()
+                    // When action-icons hint is true, interpret action ID as icon name
+                    // Common icon names: "media-playback-start", "media-playback-pause", etc.
+                    let icon_name = use_icons
+                        .then(|| action_icon_name(&action_id_str).map(str::to_string))
+                        .flatten();
+                    let btn: Element<'static, Message> = if let Some(icon_name) = icon_name {
+                        // Icon-only button: the label shows on hover and is the
+                        // button's name for screen readers
+                        tooltip(
+                            button::icon(icon::from_name(icon_name).size(16).symbolic(true))
+                                .on_press(Message::ActionClicked(notification_id, action_id_str))
+                                .padding([6, 12])
+                                .class(button_class(index))
+                                .name(label_str.clone()),
+                            text::caption(label_str),
+                            tooltip::Position::Top,
+                        )
+                        .into()
                     } else {
                         button::text(label_str)
                             .on_press(Message::ActionClicked(notification_id, action_id_str))
                             .padding([6, 12])
+                            .class(button_class(index))
                             .into()
                     };
                     action_elements.push(btn);
@@ -318,114 +849,1168 @@ impl CosmicNotifications {
             }
         }
 
-        // Wrap in container with padding
-        container(card_content)
-            .padding(CARD_PADDING)
-            .width(Length::Fill)
+        // Chat apps that accept inline replies get a Reply button that
+        // reveals a text field
+        if config.show_actions {
+            if let Some(label) = n.inline_reply_label() {
+                let reply: Element<'static, Message> = if self.reply_drafts.contains_key(&n.id) {
+                    self.render_reply_field(n.id, n.reply_placeholder())
+                } else {
+                    let label = if label.is_empty() {
+                        crate::fl!("reply-open")
+                    } else {
+                        label.to_string()
+                    };
+                    // Focusable after the action buttons
+                    button::text(label)
+                        .on_press(Message::ReplyOpen(n.id))
+                        .padding([6, 12])
+                        .class(button_class(visible_actions(n).count()))
+                        .into()
+                };
+                card_content = card_content.push(reply);
+            }
+        }
+
+        // Conversation grouping offers muting a noisy chat for a while
+        if self.config.grouping_mode == GroupingMode::ByConversation {
+            if let Some(key) = n.conversation_key() {
+                card_content = card_content.push(
+                    button::text(crate::fl!(
+                        "mute-conversation",
+                        hours = self.config.mute_conversation_hours.max(1)
+                    ))
+                    .on_press(Message::MuteConversation(key))
+                    .padding([6, 12]),
+                );
+            }
+        }
+
+        // Optional quick actions for phone numbers and emails in the body
+        if config.detect_contacts {
+            let contacts = detect_contacts(&display_body_str);
+            if let Some(contact_row) = render_contact_actions(&contacts) {
+                card_content = card_content.push(contact_row);
+            }
+        }
+
+        // One-time codes and reset links stay masked until asked for
+        if let Some(secret_row) = render_secret_actions(n.id, &secrets, self.revealed.contains(&n.id)) {
+            card_content = card_content.push(secret_row);
+        }
+
+        // Tell screen readers what changed when the card was updated
+        if let Some(update) = self.live_updates.get(&n.id) {
+            card_content = card_content.push(live_region(
+                format!("live-region-{}", n.id),
+                live_announcement(update),
+                update.politeness,
+            ));
+        }
+
+        // Wrap in container with padding, outlined while keyboard focused
+        let card = container(card_content)
+            .padding(if config.compact { CARD_PADDING_COMPACT } else { CARD_PADDING })
+            .width(Length::Fill);
+        if key_focus.is_some() {
+            card.class(cosmic::theme::Container::custom(|theme| container::Style {
+                border: iced::Border {
+                    color: theme.cosmic().accent_color().into(),
+                    width: KEY_FOCUS_BORDER_WIDTH,
+                    radius: theme.cosmic().corner_radii.radius_s.into(),
+                },
+                ..Default::default()
+            }))
             .into()
+        } else {
+            card.into()
+        }
     }
 
 
-    fn expire(&mut self, i: u32) {
-        self.state.hide_notification(i);
-        self.sort_notifications();
-        self.group_notifications();
+    /// Card for a group of popups
+    ///
+    /// Shows the newest in full under the group name, with a link to
+    /// reveal the others in place as compact rows, each with its own
+    /// dismiss button.
+    fn render_group(&self, group: &NotificationGroup, config: &RichCardConfig) -> Element<'static, Message> {
+        let label = if self.config.show_group_count {
+            group_label(&group.display_name, group.count())
+        } else {
+            group.display_name.clone()
+        };
+        let toggle_label = if group.expanded {
+            crate::fl!("group-collapse")
+        } else {
+            more_label(group.count() - 1)
+        };
+        let header = row![
+            text::heading(label).width(Length::Fill),
+            button::text(toggle_label)
+                .on_press(Message::GroupToggle(group.key.clone()))
+                .class(cosmic::theme::Button::Link),
+            button::text(crate::fl!("group-clear"))
+                .on_press(Message::GroupClear(group.key.clone()))
+                .padding([6, 12]),
+        ]
+        .spacing(8)
+        .align_y(Alignment::Center);
+
+        let mut content = column![header].spacing(8);
+        if let Some(newest) = group.newest() {
+            content = content.push(self.render_rich_notification(newest, config));
+        }
+        if group.expanded {
+            for n in group.notifications.iter().skip(1) {
+                let redacted;
+                let n = if self.hides_content() {
+                    redacted = self.redacted(n);
+                    &redacted
+                } else if let Some(masked) = self.masked(n) {
+                    redacted = masked;
+                    &redacted
+                } else {
+                    n
+                };
+                let key_focused = self.key_focus.is_some_and(|focus| focus.id == n.id);
+                let body = strip_html(&n.body);
+                let entry = button::custom(
+                    column![
+                        text::body(n.summary.clone()),
+                        text::caption(body.lines().next().unwrap_or_default().to_string()),
+                    ]
+                    .width(Length::Fill),
+                )
+                .on_press(Message::ActivateNotification(n.id))
+                .class(if key_focused {
+                    cosmic::theme::Button::Standard
+                } else {
+                    cosmic::theme::Button::Text
+                });
+                let age: Element<'static, Message> = match n.duration_since() {
+                    Some(age) => text::caption(relative_time(age)).into(),
+                    None => cosmic::widget::Space::new(0, 0).into(),
+                };
+                let dismiss = button::custom(
+                    icon::from_name("window-close-symbolic")
+                        .size(ICON_SIZE_SMALL)
+                        .symbolic(true),
+                )
+                .on_press(Message::Dismissed(n.id))
+                .class(cosmic::theme::Button::Text)
+                .name(crate::fl!("a11y-dismiss", app = n.app_name.as_str()));
+                content = content.push(
+                    row![entry, age, dismiss]
+                        .spacing(8)
+                        .align_y(Alignment::Center)
+                        .padding([0, if config.compact { CARD_PADDING_COMPACT } else { CARD_PADDING }]),
+                );
+            }
+        }
+        content.into()
     }
 
-    fn close(&mut self, i: u32, reason: CloseReason) -> Option<Task<Message>> {
-        let notification = self.state.remove_notification(i)?;
+    /// Text field with send and cancel buttons for an inline reply
+    fn render_reply_field(&self, id: u32, placeholder: Option<&str>) -> Element<'static, Message> {
+        let placeholder = placeholder.map_or_else(|| crate::fl!("reply-placeholder"), str::to_string);
+        let draft = self.reply_drafts.get(&id).cloned().unwrap_or_default();
+        let can_send = !draft.trim().is_empty();
 
-        self.state.shrink_visible();
-        self.sort_notifications();
-        self.group_notifications();
+        let input = text_input(placeholder, draft)
+            .id(reply_input_id(id))
+            .on_input(move |text| Message::ReplyChanged(id, text))
+            .on_submit(move |_| Message::ReplySubmit(id))
+            .width(Length::Fill);
+        let send = button::text(crate::fl!("reply-send"))
+            .on_press_maybe(can_send.then_some(Message::ReplySubmit(id)))
+            .padding([6, 12]);
+        let cancel = tooltip(
+            button::icon(icon::from_name("window-close-symbolic").size(16).symbolic(true))
+                .on_press(Message::ReplyCancel(id))
+                .padding([6, 6])
+                .name(crate::fl!("reply-cancel")),
+            text::caption(crate::fl!("reply-cancel")),
+            tooltip::Position::Top,
+        );
+        row![input, send, cancel].spacing(8).align_y(Alignment::Center).into()
+    }
 
-        if let Some(sender) = &self.notifications_tx {
-            let id = notification.id;
-            let sender = sender.clone();
-            tokio::spawn(async move {
-                _ = sender.send(notifications::Input::Closed(id, reason));
-            });
+    /// Reveal the reply field of a popup and focus it
+    fn open_reply(&mut self, id: u32) -> Task<Message> {
+        if !self.state.visible().iter().any(|n| n.id == id) {
+            return Task::none();
         }
+        self.reply_drafts.entry(id).or_default();
+        Task::batch([
+            self.sync_keyboard(),
+            text_input::focus(reply_input_id(id)),
+        ])
+    }
 
-        if let Some(sender) = &self.notifications_tx {
-            let sender = sender.clone();
-            let id = notification.id;
-            tokio::spawn(async move { sender.send(notifications::Input::Dismissed(id)).await });
+    /// Right-click menu of a popup
+    fn render_context_menu(&self, n: &Notification) -> Element<'static, Message> {
+        let entry = |label: String, item: MenuItem| {
+            button::text(label)
+                .on_press(Message::Menu(n.id, item))
+                .class(cosmic::theme::Button::Text)
+                .width(Length::Fill)
+        };
+        container(
+            column![
+                entry(crate::fl!("menu-mute-app", app = n.app_name.clone()), MenuItem::MuteApp),
+                entry(crate::fl!("menu-edit-rule", app = n.app_name.clone()), MenuItem::EditRule),
+                entry(crate::fl!("menu-copy-text"), MenuItem::CopyText),
+                entry(crate::fl!("menu-snooze"), MenuItem::Snooze),
+            ]
+            .spacing(2),
+        )
+        .padding(4)
+        .class(cosmic::theme::Container::Background)
+        .into()
+    }
+
+    /// Durations a popup can be snoozed for, one button each
+    fn render_snooze_picker(&self, n: &Notification) -> Element<'static, Message> {
+        let presets = self.config.snooze_presets.iter().map(|preset| -> Element<'static, Message> {
+            button::text(snooze_label(*preset))
+                .on_press(Message::Snooze(n.id, *preset))
+                .class(cosmic::theme::Button::Text)
+                .into()
+        });
+        container(cosmic::iced_widget::Row::with_children(presets).spacing(4).wrap())
+            .padding(4)
+            .class(cosmic::theme::Container::Background)
+            .into()
+    }
+
+    /// Carry out an entry of a popup's right-click menu
+    fn menu_selected(&mut self, id: u32, item: MenuItem) -> Task<Message> {
+        self.context_menu = None;
+        let Some(n) = self.state.visible().iter().find(|n| n.id == id).cloned() else {
+            return Task::none();
+        };
+        match item {
+            MenuItem::MuteApp => {
+                tracing::info!("Muting {} from its popup", n.app_name);
+                self.update_app_rule(&n, |rule| rule.enabled = false);
+                self.dismiss_where(|other| other.app_name == n.app_name)
+            }
+            MenuItem::EditRule => {
+                // Saving the rule creates the file if the app had none
+                self.update_app_rule(&n, |_| {});
+                let path = crate::config::app_rules_path();
+                tokio::spawn(async move {
+                    if let Err(err) = crate::portal::open_file(&path).await {
+                        tracing::error!("Failed to open {}: {}", path.display(), err);
+                    }
+                });
+                Task::none()
+            }
+            MenuItem::CopyText => {
+                let body = segments_to_plain_text(&sanitize_and_parse(&n.body));
+                let text = if body.trim().is_empty() {
+                    n.summary.clone()
+                } else {
+                    format!("{}\n{}", n.summary, body.trim())
+                };
+                cosmic::iced::clipboard::write(text)
+            }
+            MenuItem::Snooze => {
+                self.snooze_picker = Some(id);
+                Task::none()
+            }
         }
+    }
 
-        if self.state.is_empty() && self.active_surface {
-            self.active_surface = false;
-            Some(destroy_layer_surface(self.window_id))
+    /// Change an app's rule, adding one if needed, and persist it
+    fn update_app_rule(&mut self, n: &Notification, change: impl FnOnce(&mut AppRule)) {
+        let mut updated = self.config.clone();
+        change(updated.app_rule_mut(&n.app_name, n.desktop_entry()));
+        match self.config_helper.as_ref() {
+            Some(helper) => {
+                if let Err(err) = self.config.set_app_rules(helper, updated.app_rules) {
+                    tracing::error!("Failed to save app rules: {:?}", err);
+                }
+            }
+            // Without a config backend the rule only lasts until restart
+            None => self.config.app_rules = updated.app_rules,
+        }
+    }
+
+    /// Hide the reply field without sending, and let the popup time out again
+    fn cancel_reply(&mut self, id: u32) -> Task<Message> {
+        if self.reply_drafts.remove(&id).is_none() {
+            return Task::none();
+        }
+        let mut tasks = vec![self.sync_keyboard()];
+        // The timeout was held back while the field was open
+        if !self.expiry_deadlines.contains_key(&id) {
+            if let Some(mut n) = self.state.visible().iter().find(|n| n.id == id).cloned() {
+                tasks.push(self.schedule_expiry(&mut n));
+            }
+        }
+        Task::batch(tasks)
+    }
+
+    /// Take keyboard focus on the popup surface while it needs the keyboard
+    ///
+    /// Keyboard navigation grabs focus exclusively, as it starts from a
+    /// shortcut pressed in another window. A reply field only asks for
+    /// on-demand focus, so the compositor still routes input method events
+    /// and focus goes back to the previous window once the field closes.
+    fn sync_keyboard(&mut self) -> Task<Message> {
+        let wanted = if !self.active_surface {
+            KeyboardInteractivity::None
+        } else if self.key_focus.is_some() {
+            KeyboardInteractivity::Exclusive
+        } else if !self.reply_drafts.is_empty() {
+            KeyboardInteractivity::OnDemand
         } else {
-            Some(Task::none())
+            KeyboardInteractivity::None
+        };
+        if wanted == self.keyboard {
+            return Task::none();
         }
+        self.keyboard = wanted;
+        set_keyboard_interactivity(self.window_id, wanted)
     }
 
-    fn anchor_for_notification_applet(&self) -> (Anchor, Option<String>) {
-        self.panel_config
-            .plugins_left()
+    /// What the focusable buttons of a popup send, left to right
+    fn key_targets(&self, n: &Notification) -> Vec<Message> {
+        if !self.config.show_actions {
+            return Vec::new();
+        }
+        let mut targets: Vec<Message> = visible_actions(n)
+            .map(|(action, _)| Message::ActionClicked(n.id, action.to_string()))
+            .collect();
+        if n.inline_reply_label().is_some() && !self.reply_drafts.contains_key(&n.id) {
+            targets.push(Message::ReplyOpen(n.id));
+        }
+        targets
+    }
+
+    /// Shown popups top to bottom, with how many focusable buttons each has
+    ///
+    /// A group counts its newest popup, and the rest once expanded.
+    fn key_cards(&self) -> Vec<(u32, usize)> {
+        let groups = self.popup_groups();
+        groups
             .iter()
-            .find_map(|p| {
-                if p.iter().any(|s| s == NOTIFICATIONS_APPLET) {
-                    return Some((
-                        match self.panel_config.anchor {
-                            PanelAnchor::Top => Anchor::TOP.union(Anchor::LEFT),
-                            PanelAnchor::Bottom => Anchor::BOTTOM.union(Anchor::LEFT),
-                            PanelAnchor::Left => Anchor::LEFT.union(Anchor::TOP),
-                            PanelAnchor::Right => Anchor::RIGHT.union(Anchor::TOP),
-                        },
-                        match self.panel_config.output {
-                            CosmicPanelOuput::Name(ref n) => Some(n.clone()),
-                            _ => None,
-                        },
-                    ));
-                }
-                None
-            })
-            .or_else(|| {
-                self.panel_config.plugins_right().iter().find_map(|p| {
-                    if p.iter().any(|s| s == NOTIFICATIONS_APPLET) {
-                        return Some((
-                            match self.panel_config.anchor {
-                                PanelAnchor::Top => Anchor::TOP.union(Anchor::RIGHT),
-                                PanelAnchor::Bottom => Anchor::BOTTOM.union(Anchor::RIGHT),
-                                PanelAnchor::Left => Anchor::LEFT.union(Anchor::BOTTOM),
-                                PanelAnchor::Right => Anchor::RIGHT.union(Anchor::BOTTOM),
-                            },
-                            match self.panel_config.output {
-                                CosmicPanelOuput::Name(ref n) => Some(n.clone()),
-                                _ => None,
-                            },
-                        ));
-                    }
-                    None
-                })
+            .flat_map(|group| {
+                let shown = if group.expanded { group.count() } else { 1 };
+                group.notifications.iter().take(shown).enumerate()
             })
-            .or_else(|| {
-                self.panel_config.plugins_center().iter().find_map(|p| {
-                    if p.iter().any(|s| s == NOTIFICATIONS_APPLET) {
-                        return Some((
-                            match self.panel_config.anchor {
-                                PanelAnchor::Top => Anchor::TOP,
-                                PanelAnchor::Bottom => Anchor::BOTTOM,
-                                PanelAnchor::Left => Anchor::LEFT,
-                                PanelAnchor::Right => Anchor::RIGHT,
-                            },
-                            match self.panel_config.output {
-                                CosmicPanelOuput::Name(ref n) => Some(n.clone()),
-                                _ => None,
-                            },
-                        ));
-                    }
-                    None
-                })
+            .map(|(index, n)| {
+                let buttons = if index == 0 { self.key_targets(n).len() } else { 0 };
+                (n.id, buttons)
             })
-            .or_else(|| {
-                self.dock_config.plugins_left().iter().find_map(|p| {
-                    if p.iter().any(|s| s == NOTIFICATIONS_APPLET) {
-                        return Some((
-                            match self.dock_config.anchor {
-                                PanelAnchor::Top => Anchor::TOP.union(Anchor::LEFT),
+            .collect()
+    }
+
+    /// Popups as shown, top to bottom, grouped by `grouping_mode`
+    ///
+    /// Each group lists its notifications newest first; without grouping
+    /// every popup is a group of its own.
+    fn popup_groups(&self) -> Vec<NotificationGroup> {
+        let visible: Vec<Notification> = self
+            .state
+            .visible()
+            .iter()
+            .filter(|n| !self.in_banner(n))
+            .cloned()
+            .collect();
+        let mut groups = group_notifications(&visible, self.config.grouping_mode);
+        // Groups come unordered, the top one has the top popup
+        let rank: HashMap<u32, usize> = visible
+            .iter()
+            .rev()
+            .enumerate()
+            .map(|(rank, n)| (n.id, rank))
+            .collect();
+        groups.sort_by_key(|group| {
+            group
+                .newest()
+                .and_then(|n| rank.get(&n.id).copied())
+                .unwrap_or(usize::MAX)
+        });
+        groups.truncate(self.effective_max_notifications());
+        for group in &mut groups {
+            group.expanded = self.expanded_groups.contains(&group.key);
+        }
+        groups
+    }
+
+    /// Open a group of popups in place, or fold it back to the newest
+    fn toggle_group(&mut self, key: String) {
+        if !self.expanded_groups.remove(&key) {
+            self.expanded_groups.insert(key);
+        }
+        // Forget groups that are gone
+        let current: HashSet<String> = self.popup_groups().into_iter().map(|group| group.key).collect();
+        self.expanded_groups.retain(|key| current.contains(key));
+    }
+
+    /// Dismiss every popup of a group
+    fn clear_group(&mut self, key: &str) -> Task<Message> {
+        let ids: HashSet<u32> = self
+            .popup_groups()
+            .into_iter()
+            .filter(|group| group.key == key)
+            .flat_map(|group| group.notifications)
+            .map(|n| n.id)
+            .collect();
+        self.expanded_groups.remove(key);
+        self.dismiss_where(|n| ids.contains(&n.id))
+    }
+
+    /// Start keyboard navigation on the newest popup
+    fn focus_popups(&mut self) -> Task<Message> {
+        if !self.active_surface {
+            return Task::none();
+        }
+        self.key_focus = KeyFocus::first(&self.key_cards());
+        self.sync_keyboard()
+    }
+
+    /// Stop keyboard navigation and let popups time out again
+    fn leave_popups(&mut self) -> Task<Message> {
+        self.key_focus = None;
+        let mut tasks = vec![self.sync_keyboard()];
+        // Timeouts that came due while navigating were held back
+        let held: Vec<Notification> = self
+            .state
+            .visible()
+            .iter()
+            .filter(|n| {
+                !self.expiry_deadlines.contains_key(&n.id)
+                    && !self.reply_drafts.contains_key(&n.id)
+                    && !self.hovered.is_some_and(|(h, _)| h == n.id)
+            })
+            .cloned()
+            .collect();
+        for mut n in held {
+            tasks.push(self.schedule_expiry(&mut n));
+        }
+        Task::batch(tasks)
+    }
+
+    /// Navigate popups with the keyboard
+    fn key_pressed(&mut self, input: KeyInput) -> Task<Message> {
+        let Some(focus) = self.key_focus else {
+            return Task::none();
+        };
+        match input {
+            KeyInput::Move(step) => {
+                self.key_focus = focus.moved(&self.key_cards(), step);
+                self.sync_keyboard()
+            }
+            KeyInput::Activate => {
+                let Some(n) = self.state.visible().iter().find(|n| n.id == focus.id) else {
+                    return Task::none();
+                };
+                let Some(button) = focus.button else {
+                    return self.request_activation(focus.id, None);
+                };
+                let Some(target) = self.key_targets(n).into_iter().nth(button) else {
+                    return Task::none();
+                };
+                // The Reply button makes way for the field
+                if matches!(target, Message::ReplyOpen(_)) {
+                    self.key_focus = Some(KeyFocus::card(focus.id));
+                }
+                self.update(target)
+            }
+            KeyInput::Dismiss => self
+                .close(focus.id, CloseReason::Dismissed)
+                .unwrap_or(Task::none()),
+            KeyInput::Leave => {
+                if self.reply_drafts.contains_key(&focus.id) {
+                    self.cancel_reply(focus.id)
+                } else {
+                    self.leave_popups()
+                }
+            }
+        }
+    }
+
+    /// Send the drafted inline reply, then close the notification
+    fn send_reply(&mut self, id: u32) -> Option<Task<Message>> {
+        // An empty draft keeps the field open
+        let text = self.reply_drafts.get(&id)?.trim().to_string();
+        if text.is_empty() {
+            return None;
+        }
+        let Some(tx) = self.notifications_tx.clone() else {
+            tracing::error!("Failed to send reply. No channel.");
+            return None;
+        };
+        self.reply_drafts.remove(&id);
+        self.actioned.insert(id);
+        Some(Task::future(async move {
+            _ = tx.send(notifications::Input::Replied { id, text }).await;
+            cosmic::Action::App(Message::Dismissed(id))
+        }))
+    }
+
+    /// Dismiss the popups of the app that just got focus, as
+    /// `dismiss_on_focus` asks
+    ///
+    /// Critical notifications are left for the user to dismiss.
+    fn dismiss_focused(&mut self, app_id: &str) -> Task<Message> {
+        if !self.config.dismiss_on_focus {
+            return Task::none();
+        }
+        let app_id = app_id.strip_suffix(".desktop").unwrap_or(app_id);
+        self.dismiss_where(|n| n.urgency() < 2 && n.is_from_app(app_id))
+    }
+
+    /// Dismiss every popup matching `filter`, as if each was closed by hand
+    fn dismiss_where(&mut self, filter: impl Fn(&Notification) -> bool) -> Task<Message> {
+        let ids: Vec<u32> = self
+            .state
+            .visible()
+            .iter()
+            .filter(|n| filter(n))
+            .map(|n| n.id)
+            .collect();
+        Task::batch(
+            ids.into_iter()
+                .filter_map(|id| self.close(id, CloseReason::Dismissed)),
+        )
+    }
+
+    fn expire(&mut self, i: u32) {
+        let Some((app, keep)) = self.state.visible().iter().find(|n| n.id == i).map(|n| {
+            let keep = self.config.store_in_history_for_app(&n.app_name, n.desktop_entry());
+            (n.app_name.clone(), keep)
+        }) else {
+            return;
+        };
+        self.record_stat(&app, Outcome::Expired);
+        if let Some(n) = self.state.visible().iter().find(|n| n.id == i) {
+            self.audit(n, Outcome::Expired);
+        }
+        self.images.forget(i);
+        self.transitions.forget(i);
+        if keep {
+            self.state.hide_notification(i);
+        } else {
+            self.state.remove_notification(i);
+        }
+        self.sort_notifications();
+        self.group_notifications();
+        self.sync_control_state();
+    }
+
+    /// Remove a notification along with everything kept for its popup
+    fn take_notification(&mut self, i: u32) -> Option<Notification> {
+        let notification = self.state.remove_notification(i)?;
+        self.stop_sound(i);
+        self.live.forget(i);
+        self.live_updates.remove(&i);
+        self.images.forget(i);
+        self.reply_drafts.remove(&i);
+        if self.context_menu == Some(i) {
+            self.context_menu = None;
+        }
+        if self.snooze_picker == Some(i) {
+            self.snooze_picker = None;
+        }
+        self.expiry_deadlines.remove(&i);
+        self.progress_anims.remove(&i);
+        self.repeats.remove(&i);
+        self.revealed.remove(&i);
+        self.actioned.remove(&i);
+        if self.hovered.is_some_and(|(id, _)| id == i) {
+            self.hovered = None;
+        }
+        if self.swipe.is_some_and(|swipe| swipe.id == i) {
+            self.swipe = None;
+        }
+        self.transitions.forget(i);
+
+        self.state.shrink_visible();
+        self.sort_notifications();
+        self.group_notifications();
+        self.sync_control_state();
+        Some(notification)
+    }
+
+    /// How long popups take to enter and leave, or `None` when they don't
+    /// animate
+    fn transition_duration(&self) -> Option<Duration> {
+        (self.config.enable_animations && self.config.animation_style != AnimationStyle::None)
+            .then(|| Duration::from_millis(self.config.animation_duration_ms.into()))
+    }
+
+    /// Start popup `id` on its way out; the close finishes once the exit
+    /// has played. Returns false if there is nothing to animate and the
+    /// caller should close it right away.
+    fn leave(&mut self, id: u32, exit: Exit) -> bool {
+        if self.transition_duration().is_none() || !self.active_surface {
+            return false;
+        }
+        if self.transitions.is_leaving(id) {
+            return true;
+        }
+        // Only popups with a card of their own animate; one in a group
+        // just drops out of it
+        let own_card = self
+            .popup_groups()
+            .iter()
+            .any(|group| group.count() == 1 && group.newest().is_some_and(|n| n.id == id));
+        own_card && self.transitions.leave(id, exit, Instant::now())
+    }
+
+    /// Whether popups are anchored to the left edge, so slide in from there
+    fn slides_from_left(&self) -> bool {
+        self.anchor
+            .as_ref()
+            .is_some_and(|(anchor, _)| anchor.contains(Anchor::LEFT) && !anchor.contains(Anchor::RIGHT))
+    }
+
+    /// Destroy the popup surface once the last popup is gone, and the
+    /// banner once the last critical notification on it is
+    fn close_surface_if_empty(&mut self) -> Task<Message> {
+        let mut tasks = Vec::new();
+        if !self.has_popups() && self.active_surface {
+            self.active_surface = false;
+            self.key_focus = None;
+            tasks.push(destroy_layer_surface(self.window_id));
+        }
+        if self.banner_open && !self.state.visible().iter().any(|n| self.in_banner(n)) {
+            self.banner_open = false;
+            tasks.push(destroy_layer_surface(self.banner_id));
+        }
+        Task::batch(tasks)
+    }
+
+    /// Whether a notification shows on the critical banner instead of as
+    /// a popup
+    fn in_banner(&self, n: &Notification) -> bool {
+        shows_in_banner(&self.config, n)
+    }
+
+    /// Whether anything is left for the popup surface
+    fn has_popups(&self) -> bool {
+        self.state.visible().iter().any(|n| !self.in_banner(n))
+    }
+
+    /// Put the critical banner up in the middle of the screen, above
+    /// everything including fullscreen windows
+    fn open_banner(&mut self) -> Task<Message> {
+        if self.banner_open {
+            return Task::none();
+        }
+        self.banner_open = true;
+        get_layer_surface(SctkLayerSurfaceSettings {
+            id: self.banner_id,
+            // Anchored to no edge, the compositor centers it
+            anchor: Anchor::empty(),
+            layer: Layer::Overlay,
+            exclusive_zone: 0,
+            keyboard_interactivity: KeyboardInteractivity::None,
+            namespace: "notification-banner".to_string(),
+            size: Some((Some(BANNER_WIDTH as u32), Some(1))),
+            output: self.placement_output().0,
+            size_limits: Limits::NONE
+                .min_width(BANNER_WIDTH)
+                .min_height(1.0)
+                .max_height(NOTIFICATION_MAX_HEIGHT)
+                .max_width(BANNER_WIDTH),
+            ..Default::default()
+        })
+    }
+
+    /// The newest critical notification, large and outlined, with a count
+    /// of the others waiting behind it
+    ///
+    /// It has no timeout; only dismissing it or one of its actions takes it
+    /// down.
+    fn view_banner(&self) -> Element<'_, Message> {
+        let banners: Vec<&Notification> = self
+            .state
+            .visible()
+            .iter()
+            .filter(|n| self.in_banner(n))
+            .collect();
+        let Some(n) = banners.last() else {
+            return container(vertical_space().height(Length::Fixed(1.0)))
+                .center_x(Length::Fixed(1.0))
+                .center_y(Length::Fixed(1.0))
+                .into();
+        };
+
+        let card_config = RichCardConfig::from_notifications_config(&self.config);
+        let mut footer = row![].spacing(8).align_y(Alignment::Center);
+        if banners.len() > 1 {
+            footer = footer.push(text::body(crate::fl!(
+                "banner-more",
+                count = banners.len() - 1
+            )));
+        }
+        footer = footer.push(cosmic::widget::horizontal_space()).push(
+            button::suggested(crate::fl!("banner-dismiss")).on_press(Message::Dismissed(n.id)),
+        );
+
+        let content = container(
+            column![
+                text::title4(crate::fl!("banner-title")),
+                self.render_rich_notification(n, &card_config),
+                footer,
+            ]
+            .spacing(12),
+        )
+        .padding(24)
+        .width(Length::Fixed(BANNER_WIDTH))
+        .class(cosmic::theme::Container::custom(|theme| container::Style {
+            background: Some(iced::Background::Color(
+                theme.cosmic().background.base.into(),
+            )),
+            border: iced::Border {
+                color: theme.cosmic().destructive_color().into(),
+                width: BANNER_BORDER_WIDTH,
+                radius: theme.cosmic().corner_radii.radius_m.into(),
+            },
+            ..Default::default()
+        }));
+
+        autosize::autosize(content, self.banner_autosize_id.clone())
+            .min_width(BANNER_WIDTH)
+            .min_height(AUTOSIZE_MIN_HEIGHT)
+            .max_width(BANNER_WIDTH)
+            .max_height(NOTIFICATION_MAX_HEIGHT)
+            .into()
+    }
+
+    fn close(&mut self, i: u32, reason: CloseReason) -> Option<Task<Message>> {
+        // Keyboard focus moves on to a neighbouring popup
+        if let Some(focus) = self.key_focus.filter(|focus| focus.id == i) {
+            self.key_focus = focus.neighbour(&self.key_cards());
+        }
+        let actioned = self.actioned.remove(&i);
+        let notification = self.take_notification(i)?;
+        if reason == CloseReason::Dismissed {
+            let outcome = if actioned {
+                Outcome::Actioned
+            } else {
+                Outcome::Dismissed
+            };
+            self.record_stat(&notification.app_name, outcome);
+            self.audit(&notification, outcome);
+            if !actioned {
+                self.run_hooks(HookEvent::Dismissed, &notification, None);
+            }
+            self.withdraw_from_phone(&notification);
+        }
+
+        if let Some(sender) = &self.notifications_tx {
+            let id = notification.id;
+            let sender = sender.clone();
+            tokio::spawn(async move {
+                _ = sender.send(notifications::Input::Closed(id, reason));
+            });
+        }
+
+        if let Some(sender) = &self.notifications_tx {
+            let sender = sender.clone();
+            let id = notification.id;
+            tokio::spawn(async move { sender.send(notifications::Input::Dismissed(id)).await });
+        }
+
+        Some(Task::batch([
+            self.sync_keyboard(),
+            self.close_surface_if_empty(),
+        ]))
+    }
+
+    /// Open the notification center, or close it if it is open
+    fn toggle_center(&mut self) -> Task<Message> {
+        if self.center_open {
+            self.center_open = false;
+            return destroy_layer_surface(self.center_id);
+        }
+        if !self.capabilities.layer_shell {
+            return Task::none();
+        }
+
+        self.center_open = true;
+        // History changed while it was closed
+        let query = std::mem::take(&mut self.center_query);
+        self.center_matches = self.search_ids(&query);
+        self.center_query = query;
+        let (anchor, _output) = self.anchor.clone().unwrap_or((Anchor::TOP, None));
+        get_layer_surface(SctkLayerSurfaceSettings {
+            id: self.center_id,
+            anchor,
+            exclusive_zone: 0,
+            keyboard_interactivity: KeyboardInteractivity::OnDemand,
+            namespace: "notification-center".to_string(),
+            margin: self.popup_margin(),
+            size: Some((Some(NOTIFICATION_WIDTH as u32), Some(CENTER_HEIGHT as u32))),
+            output: self.placement_output().0,
+            ..Default::default()
+        })
+    }
+
+    /// History grouped per day and app, with do-not-disturb and clear-all
+    /// controls
+    fn view_center(&self) -> Element<'_, Message> {
+        let close = button::custom(
+            icon::from_name("window-close-symbolic")
+                .size(ICON_SIZE_SMALL)
+                .symbolic(true),
+        )
+        .on_press(Message::ToggleCenter)
+        .class(cosmic::theme::Button::Text)
+        .name(crate::fl!("a11y-close-center"));
+        let header = row![
+            text::title4(crate::fl!("center-title")).width(Length::Fill),
+            button::text(crate::fl!("center-clear-all"))
+                .on_press_maybe((!self.state.hidden().is_empty()).then_some(Message::CenterClearAll)),
+            close,
+        ]
+        .spacing(8)
+        .align_y(Alignment::Center);
+
+        let dnd = toggler(self.config.do_not_disturb)
+            .label(crate::fl!("center-dnd"))
+            .on_toggle(Message::CenterDnd);
+        let priority_only = toggler(self.config.dnd_mode() == DndMode::PriorityOnly)
+            .label(crate::fl!("center-priority-only"))
+            .on_toggle(Message::CenterPriorityOnly);
+        let search = text_input::search_input(crate::fl!("center-search"), &self.center_query)
+            .on_input(Message::CenterSearch)
+            .on_clear(Message::CenterSearch(String::new()))
+            .width(Length::Fill);
+
+        let searching = !self.center_query.trim().is_empty();
+        let mut shown: Vec<&Notification> = self
+            .state
+            .hidden()
+            .iter()
+            .filter(|n| !searching || self.center_matches.contains(&n.id))
+            .collect();
+        if self.config.smart_ordering {
+            // Highest first, so the most important app heads its day
+            let scorer = self.priority_scorer();
+            shown.sort_by(|a, b| scorer.cmp(b, a));
+        }
+        let mut list = column![].spacing(12);
+        if shown.is_empty() {
+            let empty = if searching {
+                crate::fl!("center-no-results")
+            } else {
+                crate::fl!("center-empty")
+            };
+            list = list.push(text::body(empty));
+        }
+        for (section, entries) in history::group_by_date(shown, history::local_today()) {
+            // Search results show through collapsed sections
+            let collapsed = !searching && self.history_collapse.is_collapsed(section);
+            let expander = if collapsed {
+                "go-next-symbolic"
+            } else {
+                "go-down-symbolic"
+            };
+            let heading = button::custom(
+                row![
+                    text::heading(section.label()).width(Length::Fill),
+                    icon::from_name(expander).size(ICON_SIZE_SMALL).symbolic(true),
+                ]
+                .align_y(Alignment::Center),
+            )
+            .on_press(Message::CenterToggleSection(section))
+            .class(cosmic::theme::Button::Text);
+            let mut day = column![heading].spacing(8);
+            if !collapsed {
+                for (app, entries) in history::group_by_app(entries) {
+                    day = day.push(self.view_center_group(app, entries));
+                }
+            }
+            list = list.push(day);
+        }
+
+        container(
+            column![header, dnd, priority_only, search, scrollable(list).height(Length::Fill)]
+                .spacing(12)
+                .padding(CARD_PADDING),
+        )
+        .class(cosmic::theme::Container::Background)
+        .width(Length::Fixed(NOTIFICATION_WIDTH))
+        .height(Length::Fixed(CENTER_HEIGHT))
+        .into()
+    }
+
+    /// One app's entries of a notification center section
+    fn view_center_group<'a>(
+        &'a self,
+        app: &'a str,
+        entries: Vec<&'a Notification>,
+    ) -> Element<'a, Message> {
+        let mut group = column![text::caption_heading(app)].spacing(4);
+        for n in entries {
+            let age: Element<'_, Message> = match n.duration_since() {
+                Some(age) => text::caption(relative_time(age)).into(),
+                None => cosmic::widget::Space::new(0, 0).into(),
+            };
+            let (summary, body) = match self.masked(n) {
+                Some(masked) => (masked.summary, strip_html(&masked.body)),
+                None => (n.summary.clone(), strip_html(&n.body)),
+            };
+            let entry = button::custom(
+                column![text::body(summary), text::caption(body)].width(Length::Fill),
+            )
+            .on_press(Message::ActivateNotification(n.id))
+            .class(cosmic::theme::Button::Text);
+            let remove = button::custom(
+                icon::from_name("edit-delete-symbolic")
+                    .size(ICON_SIZE_SMALL)
+                    .symbolic(true),
+            )
+            .on_press(Message::CenterRemove(n.id))
+            .class(cosmic::theme::Button::Text)
+            .name(crate::fl!("a11y-remove"));
+            group = group.push(row![entry, age, remove].spacing(8).align_y(Alignment::Center));
+        }
+        group.into()
+    }
+
+    /// Take a popup off screen and show it again after `duration`
+    fn snooze(&mut self, id: u32, duration: Duration) -> Option<Task<Message>> {
+        self.snooze_until(id, unix_now() + duration.as_secs())
+    }
+
+    /// Take a popup off screen and show it again at `due` (unix seconds)
+    ///
+    /// The client isn't told the notification closed, since it comes back.
+    fn snooze_until(&mut self, id: u32, due: u64) -> Option<Task<Message>> {
+        if !self.state.visible().iter().any(|n| n.id == id) {
+            return None;
+        }
+        let notification = self.take_notification(id)?;
+        self.snoozed.snooze(notification, due);
+        self.save_snoozed();
+        Some(Task::batch([
+            self.schedule_snooze_wakeup(),
+            self.close_surface_if_empty(),
+        ]))
+    }
+
+    /// Show the snoozed notifications that are due and wait for the next one
+    fn wake_snoozed(&mut self) -> Task<Message> {
+        let due = self.snoozed.take_due(unix_now());
+        if !due.is_empty() {
+            self.save_snoozed();
+        }
+        let mut tasks = vec![self.schedule_snooze_wakeup()];
+        for mut n in due {
+            n.time = SystemTime::now();
+            n.applied_timeout = None;
+            if self.paused {
+                self.hold(n);
+            } else {
+                tasks.push(self.push_notification(n));
+            }
+        }
+        Task::batch(tasks)
+    }
+
+    fn schedule_snooze_wakeup(&mut self) -> Task<Message> {
+        let wait = self
+            .snoozed
+            .next_due()
+            .map(|next| Duration::from_secs(next.saturating_sub(unix_now())));
+        self.wake_after(Wakeup::Snooze, wait)
+    }
+
+    /// Wake up for `wakeup` after `wait`, or not at all for `None`; a
+    /// timer armed for it earlier no longer counts
+    fn wake_after(&mut self, wakeup: Wakeup, wait: Option<Duration>) -> Task<Message> {
+        let generation = self.wakeups.arm(wakeup);
+        let Some(wait) = wait else {
+            return Task::none();
+        };
+        Task::perform(tokio::time::sleep(wait), move |_| {
+            cosmic::action::app(Message::Wakeup(wakeup, generation))
+        })
+    }
+
+    fn save_snoozed(&self) {
+        let Some(state) = self.state_helper.as_ref() else {
+            // Without a state backend snoozes only last until restart
+            return;
+        };
+        // Apps kept out of history aren't written to disk either, so their
+        // snoozes only last until restart
+        let saved = self.snoozed.to_saved(|n| {
+            self.config.store_in_history_for_app(&n.app_name, n.desktop_entry())
+        });
+        if let Err(err) = state.set(SNOOZED_KEY, saved) {
+            tracing::error!("Failed to save snoozed notifications: {:?}", err);
+        }
+    }
+
+    /// Save the popups and held notifications, so a crash or restart
+    /// doesn't lose them
+    ///
+    /// Only writes when the set changed since the last save.
+    fn save_pending(&mut self) {
+        if self.restore.is_some() {
+            // Saving now would overwrite what is still waiting to be restored
+            return;
+        }
+        let Some(state) = self.state_helper.as_ref() else {
+            return;
+        };
+        // Apps kept out of history aren't written to disk either
+        let pending: Vec<&Notification> = self
+            .state
+            .visible()
+            .iter()
+            .chain(&self.held)
+            .filter(|n| self.config.store_in_history_for_app(&n.app_name, n.desktop_entry()))
+            .collect();
+        let signature: Vec<(u32, SystemTime)> = pending.iter().map(|n| (n.id, n.time)).collect();
+        if signature == self.pending_saved {
+            return;
+        }
+
+        let now = Instant::now();
+        let saved = restore::to_saved(pending.into_iter().map(|n| {
+            // Round up, so a popup about to expire isn't restored without a timeout
+            let expires = self.expiry_deadlines.get(&n.id).map(|deadline| {
+                unix_now() + deadline.saturating_duration_since(now).as_secs().max(1)
+            });
+            (n, expires)
+        }));
+        match state.set(PENDING_KEY, saved) {
+            Ok(()) => self.pending_saved = signature,
+            Err(err) => tracing::error!("Failed to save pending notifications: {:?}", err),
+        }
+    }
+
+    /// Count an outcome for an app in the statistics
+    ///
+    /// They are saved a while later, or when the session ends, rather than
+    /// for every notification.
+    fn record_stat(&mut self, app: &str, outcome: Outcome) {
+        let now = chrono::Local::now();
+        let today = now.date_naive().num_days_from_ce();
+        self.stats.record(app, outcome, today, now.hour());
+        self.stats.prune(today - STATS_RETENTION_DAYS + 1);
+        self.stats_dirty = true;
+    }
+
+    /// Save the statistics if they changed since the last save
+    fn save_stats(&mut self) {
+        if !std::mem::take(&mut self.stats_dirty) {
+            return;
+        }
+        let Some(state) = self.state_helper.as_ref() else {
+            return;
+        };
+        if let Err(err) = state.set(STATS_KEY, &self.stats) {
+            tracing::error!("Failed to save notification stats: {:?}", err);
+        }
+    }
+
+    /// Counts over the last `days` days including today, 0 for all kept
+    fn stats_report(&self, days: u32) -> notifications::HistoryStatsReport {
+        let since = if days == 0 {
+            i32::MIN
+        } else {
+            let today = chrono::Local::now().date_naive().num_days_from_ce();
+            today.saturating_sub(i32::try_from(days).unwrap_or(i32::MAX)) + 1
+        };
+        (self.stats.per_app(since), self.stats.per_hour(since))
+    }
+
+    /// Show what the last run left on screen or held
+    fn restore_pending(&mut self) -> Task<Message> {
+        let Some(restored) = self.restore.take() else {
+            return Task::none();
+        };
+        if !restored.is_empty() {
+            tracing::info!("Restoring {} notifications from the last run", restored.len());
+        }
+        let tasks: Vec<_> = restored
+            .into_iter()
+            .map(|mut n| {
+                // Client ids start over with the daemon
+                n.id = self.state.next_internal_id();
+                self.push_notification(n)
+            })
+            .collect();
+        self.save_pending();
+        Task::batch(tasks)
+    }
+
+    fn anchor_for_notification_applet(&self) -> (Anchor, Option<String>) {
+        self.panel_config
+            .plugins_left()
+            .iter()
+            .find_map(|p| {
+                if p.iter().any(|s| s == NOTIFICATIONS_APPLET) {
+                    return Some((
+                        match self.panel_config.anchor {
+                            PanelAnchor::Top => Anchor::TOP.union(Anchor::LEFT),
+                            PanelAnchor::Bottom => Anchor::BOTTOM.union(Anchor::LEFT),
+                            PanelAnchor::Left => Anchor::LEFT.union(Anchor::TOP),
+                            PanelAnchor::Right => Anchor::RIGHT.union(Anchor::TOP),
+                        },
+                        match self.panel_config.output {
+                            CosmicPanelOuput::Name(ref n) => Some(n.clone()),
+                            _ => None,
+                        },
+                    ));
+                }
+                None
+            })
+            .or_else(|| {
+                self.panel_config.plugins_right().iter().find_map(|p| {
+                    if p.iter().any(|s| s == NOTIFICATIONS_APPLET) {
+                        return Some((
+                            match self.panel_config.anchor {
+                                PanelAnchor::Top => Anchor::TOP.union(Anchor::RIGHT),
+                                PanelAnchor::Bottom => Anchor::BOTTOM.union(Anchor::RIGHT),
+                                PanelAnchor::Left => Anchor::LEFT.union(Anchor::BOTTOM),
+                                PanelAnchor::Right => Anchor::RIGHT.union(Anchor::BOTTOM),
+                            },
+                            match self.panel_config.output {
+                                CosmicPanelOuput::Name(ref n) => Some(n.clone()),
+                                _ => None,
+                            },
+                        ));
+                    }
+                    None
+                })
+            })
+            .or_else(|| {
+                self.panel_config.plugins_center().iter().find_map(|p| {
+                    if p.iter().any(|s| s == NOTIFICATIONS_APPLET) {
+                        return Some((
+                            match self.panel_config.anchor {
+                                PanelAnchor::Top => Anchor::TOP,
+                                PanelAnchor::Bottom => Anchor::BOTTOM,
+                                PanelAnchor::Left => Anchor::LEFT,
+                                PanelAnchor::Right => Anchor::RIGHT,
+                            },
+                            match self.panel_config.output {
+                                CosmicPanelOuput::Name(ref n) => Some(n.clone()),
+                                _ => None,
+                            },
+                        ));
+                    }
+                    None
+                })
+            })
+            .or_else(|| {
+                self.dock_config.plugins_left().iter().find_map(|p| {
+                    if p.iter().any(|s| s == NOTIFICATIONS_APPLET) {
+                        return Some((
+                            match self.dock_config.anchor {
+                                PanelAnchor::Top => Anchor::TOP.union(Anchor::LEFT),
                                 PanelAnchor::Bottom => Anchor::BOTTOM.union(Anchor::LEFT),
                                 PanelAnchor::Left => Anchor::LEFT.union(Anchor::TOP),
                                 PanelAnchor::Right => Anchor::RIGHT.union(Anchor::TOP),
@@ -480,109 +2065,1682 @@ impl CosmicNotifications {
             .unwrap_or((Anchor::TOP, None))
     }
 
-    fn push_notification(
-        &mut self,
-        notification: Notification,
-    ) -> Task<<CosmicNotifications as cosmic::app::Application>::Message> {
-        // Play notification sound if not in do-not-disturb mode
+    /// Whether a notification that skips its popup goes to history
+    ///
+    /// Transient notifications never do, nor do apps whose rule opts out.
+    fn keeps_in_history(&self, notification: &Notification) -> bool {
+        !notification.transient()
+            && self
+                .config
+                .store_in_history_for_app(&notification.app_name, notification.desktop_entry())
+    }
+
+    fn push_notification(
+        &mut self,
+        mut notification: Notification,
+    ) -> Task<<CosmicNotifications as cosmic::app::Application>::Message> {
+        // Muted apps go straight to history, without popup or sound
+        if !self
+            .config
+            .is_app_enabled(&notification.app_name, notification.desktop_entry())
+        {
+            if self.keeps_in_history(&notification) {
+                self.state.add_hidden(notification);
+            }
+            return Task::none();
+        }
+
+        // During a focus session, only allow-listed apps get a popup.
+        // Everything else goes straight to history for the end-of-session summary.
+        if let Some(session) = self.focus_session.as_mut() {
+            if !session.allows(&notification) {
+                session.record_suppressed(&notification);
+                if self.keeps_in_history(&notification) {
+                    self.state.add_hidden(notification);
+                }
+                return Task::none();
+            }
+        }
+
+        // Muted conversations go straight to history, without popup or sound
+        if notification
+            .conversation_key()
+            .is_some_and(|key| self.config.is_conversation_muted(&key, unix_now()))
+        {
+            if self.keeps_in_history(&notification) {
+                self.state.add_hidden(notification);
+            }
+            return Task::none();
+        }
+
+        // Priority-only do-not-disturb sends everything off the priority list
+        // straight to history.
+        let dnd_mode = self.effective_dnd();
+        if dnd_mode == DndMode::PriorityOnly && !self.is_priority(&notification) {
+            self.audit(&notification, AuditOutcome::SuppressedByDnd);
+            if self.keeps_in_history(&notification) {
+                self.state.add_hidden(notification);
+            }
+            return Task::none();
+        }
+        let show_popup = dnd_mode != DndMode::Full;
+        if !show_popup {
+            self.audit(&notification, AuditOutcome::SuppressedByDnd);
+        }
+
+        // Without layer-shell there is nowhere to show popups, so they go
+        // straight to history, where the applet still lists them, instead
+        // of waiting unseen among the popups
+        if !self.capabilities.layer_shell {
+            tracing::debug!("No layer-shell, notification {} goes to history", notification.id);
+            if self.keeps_in_history(&notification) {
+                self.state.add_hidden(notification);
+            }
+            return Task::none();
+        }
+
+        // Nothing pops up on a locked screen when all of it is hidden
+        if self.locked_since.is_some()
+            && self.config.lock_screen_visibility == LockScreenVisibility::HideAll
+        {
+            if self.keeps_in_history(&notification) {
+                self.state.add_hidden(notification);
+            }
+            return Task::none();
+        }
+
+        // Fullscreen games and videos are only interrupted by critical
+        // notifications
+        if self.fullscreen
+            && self.config.fullscreen_popups == FullscreenPopups::Suppress
+            && notification.urgency() < 2
+        {
+            if self.keeps_in_history(&notification) {
+                self.state.add_hidden(notification);
+            }
+            return Task::none();
+        }
+
+        // Apps on a digest are summed up every so often instead; critical
+        // notifications still pop up
+        if show_popup && notification.urgency() < 2 {
+            if let Some(interval) = self
+                .config
+                .digest_interval_for_app(&notification.app_name, notification.desktop_entry())
+            {
+                return self.add_to_digest(notification, interval);
+            }
+        }
+
+        // Apps flooding the screen are quieted for a while; critical
+        // notifications still get through
+        if show_popup && self.config.storm_detection && notification.urgency() < 2 {
+            let cooldown = Duration::from_secs(u64::from(self.config.storm_cooldown_minutes) * 60);
+            match self
+                .storms
+                .arrived(&notification.app_name, &notification.body, Instant::now(), cooldown)
+            {
+                StormVerdict::Calm => {}
+                StormVerdict::Started => return self.start_storm(notification),
+                // What a storm sends meanwhile comes as one card when it ends
+                StormVerdict::Quieted => {
+                    let now = Instant::now();
+                    let remaining = self
+                        .storms
+                        .quiet_until(&notification.app_name)
+                        .map_or(Duration::ZERO, |until| until.saturating_duration_since(now));
+                    return self.add_to_digest(notification, remaining);
+                }
+            }
+        }
+
+        let repeats = if show_popup { self.coalesce(&notification) } else { 1 };
+        if show_popup {
+            self.thread(&notification);
+        }
+
+        // Play the sound the client asked for, unless do-not-disturb is on
+        // or the user muted this app
+        #[cfg(feature = "audio")]
+        let mut duck_sound = None;
+        #[cfg(feature = "audio")]
+        if show_popup
+            && self
+                .config
+                .is_sound_enabled_for_app(&notification.app_name, notification.desktop_entry())
+            && notification.sound_key().is_some_and(|key| {
+                let window = self
+                    .config
+                    .sound_debounce_for_app(&notification.app_name, notification.desktop_entry());
+                self.sound_debouncer.should_play(&key, window)
+            })
+        {
+            let volume = self.config.sound_volume_for_app(
+                notification.urgency(),
+                &notification.app_name,
+                notification.desktop_entry(),
+            );
+            if let Some(handle) =
+                notification.play_sound(cosmic_ext_notifications_util::volume_from_percent(volume))
+            {
+                if self.config.duck_media && notification.urgency() == 2 {
+                    duck_sound = Some(handle.clone());
+                }
+                self.sounds.retain(|_, h| !h.is_finished());
+                self.sounds.insert(notification.id, handle);
+            }
+        }
+
+        // Read the summary aloud for apps that opted in, but not on the lock
+        // screen where anyone nearby could hear it
+        if show_popup
+            && self.locked_since.is_none()
+            && self
+                .config
+                .speak_for_app(&notification.app_name, notification.desktop_entry())
+        {
+            crate::tts::speak(&format!("{}: {}", notification.app_name, notification.summary));
+        }
+
+        let mut tasks = vec![self.schedule_expiry(&mut notification)];
+
+        #[cfg(feature = "audio")]
+        if let Some(sound) = duck_sound {
+            let level = self.config.duck_level;
+            tasks.push(Task::perform(
+                crate::ducking::duck_while_playing(sound, level),
+                |_| cosmic::action::app(Message::Ignore),
+            ));
+        }
+
+        if show_popup {
+            if self.in_banner(&notification) {
+                tasks.push(self.open_banner());
+            } else if !self.active_surface {
+                tasks.push(self.create_popup_surface());
+            }
+            if self.transition_duration().is_some() && !self.in_banner(&notification) {
+                self.transitions.enter(notification.id, Instant::now());
+            }
+        }
+
+        if show_popup {
+            // Screen readers may be heard on a call, so codes stay masked
+            let arrived = if self.hides_content() {
+                self.live.arrived(&self.redacted(&notification))
+            } else {
+                let masked = self.masked(&notification);
+                self.live.arrived(masked.as_ref().unwrap_or(&notification))
+            };
+            self.announce(arrived);
+            tasks.push(self.decode_image(&notification));
+        }
+
+        if repeats > 1 {
+            self.repeats.insert(notification.id, repeats);
+        }
+        self.state.sort_visible();
+        self.state.insert_sorted(notification);
+        self.sort_notifications();
+        self.group_notifications();
+        self.sync_control_state();
+
+        iced::Task::batch(tasks)
+    }
+
+    /// Quiet the app of `notification`, which tipped it into a storm
+    ///
+    /// Its popups go to history along with it, and the user is told why.
+    fn start_storm(&mut self, notification: Notification) -> Task<Message> {
+        let app = notification.app_name.clone();
+        tracing::warn!("Quieting {} after a notification storm", app);
+        let storm: Vec<u32> = self
+            .state
+            .visible()
+            .iter()
+            .filter(|n| n.app_name == app && !self.in_banner(n))
+            .map(|n| n.id)
+            .collect();
+        for id in storm {
+            self.expire(id);
+        }
+        self.audit(&notification, AuditOutcome::Quieted);
+        if self.keeps_in_history(&notification) {
+            self.state.add_hidden(notification);
+        }
+
+        let notice = Notification {
+            id: self.state.next_internal_id(),
+            app_name: crate::fl!("app-name"),
+            app_icon: STORM_ICON.to_string(),
+            summary: crate::fl!("storm-started-title", app = app.as_str()),
+            body: crate::fl!("storm-started", minutes = self.config.storm_cooldown_minutes),
+            actions: Vec::new(),
+            hints: vec![Hint::Urgency(1), Hint::Transient(true)],
+            expire_timeout: -1,
+            applied_timeout: None,
+            time: SystemTime::now(),
+        };
+        // The notice keeps the popup surface open if the storm filled it
+        let notice = self.push_notification(notice);
+        Task::batch([notice, self.close_surface_if_empty(), self.schedule_storm_end()])
+    }
+
+    /// Let quieted apps back whose time is up; what they sent meanwhile
+    /// comes as their digest
+    fn end_storms(&mut self) -> Task<Message> {
+        for (app, count) in self.storms.take_ended(Instant::now()) {
+            tracing::info!("{} is no longer quieted, {} notifications went to its digest", app, count);
+        }
+        self.schedule_storm_end()
+    }
+
+    fn schedule_storm_end(&mut self) -> Task<Message> {
+        let wait = self
+            .storms
+            .next_end()
+            .map(|end| end.saturating_duration_since(Instant::now()));
+        self.wake_after(Wakeup::Storms, wait)
+    }
+
+    /// Hold `notification` for its app's digest, which is due `interval`
+    /// after the first one held; it goes to history right away
+    fn add_to_digest(&mut self, notification: Notification, interval: Duration) -> Task<Message> {
+        let summary = self
+            .masked(&notification)
+            .map_or_else(|| notification.summary.clone(), |masked| masked.summary);
+        let started = self
+            .digests
+            .add(&notification.app_name, summary, Instant::now(), interval);
+        self.audit(&notification, AuditOutcome::Quieted);
+        if self.keeps_in_history(&notification) {
+            self.state.add_hidden(notification);
+        }
+        if started {
+            self.schedule_digest()
+        } else {
+            Task::none()
+        }
+    }
+
+    /// Show one card for each app whose digest is due
+    fn deliver_digests(&mut self) -> Task<Message> {
+        let mut tasks = Vec::new();
+        for (app, summaries) in self.digests.take_due(Instant::now()) {
+            let count = summaries.len();
+            tracing::debug!("Delivering a digest of {} notifications from {}", count, app);
+            let mut lines: Vec<String> = summaries
+                .iter()
+                .rev()
+                .take(DIGEST_MAX_SUMMARIES)
+                .map(|summary| summary.lines().next().unwrap_or_default().to_string())
+                .collect();
+            if count > DIGEST_MAX_SUMMARIES {
+                lines.push(crate::fl!("digest-more", count = count - DIGEST_MAX_SUMMARIES));
+            }
+            let digest = Notification {
+                id: self.state.next_internal_id(),
+                app_name: crate::fl!("app-name"),
+                app_icon: DIGEST_ICON.to_string(),
+                summary: crate::fl!("digest-title", app = app.as_str(), count = count),
+                // Plain bodies show one line, so the summaries share it
+                body: lines.join(" · "),
+                actions: Vec::new(),
+                hints: vec![Hint::Urgency(1), Hint::Transient(true)],
+                expire_timeout: -1,
+                applied_timeout: None,
+                time: SystemTime::now(),
+            };
+            tasks.push(self.push_notification(digest));
+        }
+        tasks.push(self.schedule_digest());
+        Task::batch(tasks)
+    }
+
+    fn schedule_digest(&mut self) -> Task<Message> {
+        let wait = self
+            .digests
+            .next_due()
+            .map(|due| due.saturating_duration_since(Instant::now()));
+        self.wake_after(Wakeup::Digests, wait)
+    }
+
+    /// Take away the app's latest popup if `notification` repeats it word
+    /// for word, returning how many times it has now arrived
+    ///
+    /// The client is told the earlier one closed, since its id is gone.
+    fn coalesce(&mut self, notification: &Notification) -> u32 {
+        if !self.config.coalesce_duplicates {
+            return 1;
+        }
+        let Some(previous) = self
+            .state
+            .visible()
+            .iter()
+            .filter(|n| n.app_name == notification.app_name && !self.in_banner(n))
+            .max_by_key(|n| n.time)
+            .filter(|n| n.summary == notification.summary && n.body == notification.body)
+            .map(|n| n.id)
+        else {
+            return 1;
+        };
+        let repeats = self.repeats.get(&previous).copied().unwrap_or(1) + 1;
+        if self.take_notification(previous).is_some() {
+            if let Some(sender) = self.notifications_tx.clone() {
+                tokio::spawn(async move {
+                    _ = sender
+                        .send(notifications::Input::Closed(previous, CloseReason::Undefined))
+                        .await;
+                });
+            }
+        }
+        repeats
+    }
+
+    /// Move the popups of `notification`'s conversation to history so it
+    /// takes their place, as if the app had replaced them
+    ///
+    /// The client is told the earlier ones closed, since their ids are gone.
+    fn thread(&mut self, notification: &Notification) {
+        if !self.config.thread_conversations {
+            return;
+        }
+        let Some(key) = notification.conversation_key() else {
+            return;
+        };
+        let previous: Vec<u32> = self
+            .state
+            .visible()
+            .iter()
+            .filter(|n| n.id != notification.id && !self.in_banner(n))
+            .filter(|n| n.conversation_key().as_deref() == Some(key.as_str()))
+            .map(|n| n.id)
+            .collect();
+        for previous in previous {
+            let Some(n) = self.take_notification(previous) else {
+                continue;
+            };
+            if self.keeps_in_history(&n) {
+                self.state.add_hidden(n);
+            }
+            if let Some(sender) = self.notifications_tx.clone() {
+                tokio::spawn(async move {
+                    _ = sender
+                        .send(notifications::Input::Closed(previous, CloseReason::Undefined))
+                        .await;
+                });
+            }
+        }
+    }
+
+    /// Whether notification content is hidden right now, as
+    /// `lock_screen_visibility` asks while the session is locked, or while
+    /// the screen is shared
+    fn hides_content(&self) -> bool {
+        (self.locked_since.is_some()
+            && self.config.lock_screen_visibility != LockScreenVisibility::ShowAll)
+            || self.screen_shared
+    }
+
+    /// A notification as shown while its content is hidden
+    fn redacted(&self, n: &Notification) -> Notification {
+        n.redacted(crate::fl!("lock-redacted", app = n.app_name.as_str()))
+    }
+
+    /// What listeners on the session bus and the IPC socket see of `n`
+    fn shared(&self, n: &Notification) -> Notification {
+        notifications::shared(&self.config, self.hides_content(), n)
+    }
+
+    /// `n` with its one-time codes and reset links masked, unless the user
+    /// revealed them or the app's rule turns masking off
+    fn masked(&self, n: &Notification) -> Option<Notification> {
+        masked(&self.config, &self.revealed, n)
+    }
+
+    /// Note what became of `n` in the journal, with `journal_audit` on
+    #[cfg_attr(not(feature = "systemd"), allow(unused_variables))]
+    fn audit(&self, n: &Notification, outcome: impl Into<AuditOutcome>) {
+        #[cfg(feature = "systemd")]
+        if let Some(journal) = &self.journal {
+            journal.record(n, outcome.into());
+        }
+    }
+
+    /// Note in the journal that the server rate-limited `dropped`, with
+    /// `journal_audit` on
+    #[cfg_attr(not(feature = "systemd"), allow(unused_variables))]
+    fn audit_rate_limited(&self, dropped: &[(String, u32)]) {
+        #[cfg(feature = "systemd")]
+        if let Some(journal) = &self.journal {
+            for (app, count) in dropped {
+                journal.record_rate_limited(app, *count);
+            }
+        }
+    }
+
+    /// Start the hooks configured for `event` on `n`
+    fn run_hooks(&self, event: HookEvent, n: &Notification, action: Option<&str>) {
+        let hooks = self.config.hooks_for(event, &n.app_name, n.desktop_entry());
+        crate::hooks::run(&hooks, event, n, action);
+    }
+
+    /// `n` with one-time codes and reset links masked before it leaves the
+    /// desktop, unless the app's rule turns masking off
+    fn masked_for_sharing(&self, n: &Notification) -> Option<Notification> {
+        if !self.config.mask_secrets_for_app(&n.app_name, n.desktop_entry()) {
+            return None;
+        }
+        n.masked()
+    }
+
+    /// Send `n` to the paired phone if its app's rule asks for it, with
+    /// one-time codes masked since the phone may show it on its lock screen
+    fn forward_to_phone(&self, n: &Notification) {
+        let Some(settings) = self.config.phone_forwarding_for(&n.app_name, n.desktop_entry()) else {
+            return;
+        };
+        let masked = self.masked_for_sharing(n);
+        let shown = masked.as_ref().unwrap_or(n);
+        let forwarded = crate::phone::Forwarded {
+            id: n.id,
+            app_name: n.app_name.clone(),
+            summary: shown.summary.clone(),
+            body: strip_html(&shown.body),
+        };
+        tokio::spawn(crate::phone::forward(settings.clone(), forwarded));
+    }
+
+    /// Post `n` to the `webhook` if it matches, one-time codes masked
+    #[cfg(feature = "webhook")]
+    fn post_to_webhook(&self, n: &Notification) {
+        let Some(webhook) = &self.webhook else {
+            return;
+        };
+        if self.config.webhook_matches(&n.app_name, n.desktop_entry(), n.urgency()) {
+            webhook.send(self.masked_for_sharing(n).as_ref().unwrap_or(n));
+        }
+    }
+
+    /// Take `n` off the phone again once it's dismissed here, with
+    /// `mirror_dismissals` on
+    fn withdraw_from_phone(&self, n: &Notification) {
+        let Some(settings) = self.config.phone_forwarding_for(&n.app_name, n.desktop_entry()) else {
+            return;
+        };
+        if settings.mirror_dismissals {
+            tokio::spawn(crate::phone::withdraw(settings.clone(), n.id));
+        }
+    }
+
+    /// Hand a card update to screen readers, as far as
+    /// `announce_verbosity` allows
+    ///
+    /// Bodies aren't read out on the lock screen.
+    fn announce(&mut self, update: LiveUpdate) {
+        let update = match self.config.announce_verbosity {
+            AnnounceVerbosity::Off => None,
+            AnnounceVerbosity::Summary => update.without_body(),
+            AnnounceVerbosity::Full if self.locked_since.is_some() => update.without_body(),
+            AnnounceVerbosity::Full => Some(update),
+        };
+        let Some(update) = update else {
+            return;
+        };
+        tracing::debug!(id = update.id, politeness = ?update.politeness, "live region update");
+        if matches!(update.change, LiveChange::Arrived { .. }) {
+            self.arrival = Some(update);
+        } else {
+            self.live_updates.insert(update.id, update);
+        }
+    }
+
+    /// App icon to badge the notification image with, when the image is a
+    /// sender avatar
+    ///
+    /// Per-app rules decide first, otherwise message categories (chat,
+    /// email) get the avatar layout. Themed icon images are never avatars.
+    fn avatar_badge_icon<'a>(&self, n: &'a Notification, image: &Image) -> Option<&'a str> {
+        if matches!(image, Image::Name(_)) {
+            return None;
+        }
+
+        let show_avatar = self
+            .config
+            .show_avatar_for_app(&n.app_name, n.desktop_entry())
+            .unwrap_or_else(|| n.category().is_some_and(is_message_category));
+        if !show_avatar {
+            return None;
+        }
+
+        // The badge needs a themed icon name, paths can't be shown at badge size reliably
+        if !n.app_icon.is_empty() && !n.app_icon.starts_with("file://") && !n.app_icon.starts_with('/') {
+            Some(n.app_icon.as_str())
+        } else {
+            n.desktop_entry()
+        }
+    }
+
+    /// Check if a notification breaks through priority-only do-not-disturb
+    fn is_priority(&self, notification: &Notification) -> bool {
+        (self.config.critical_bypass_dnd && notification.urgency() == 2)
+            || self.config.priority_list.matches(
+                &notification.app_name,
+                notification.desktop_entry(),
+                notification.category(),
+                &notification.summary,
+                &notification.body,
+            )
+    }
+
+    fn group_notifications(&mut self) {
+        self.state.group_by_app(
+            self.config.max_per_app as usize,
+            self.effective_max_notifications()
+        );
+    }
+
+    fn sort_notifications(&mut self) {
+        if !self.config.smart_ordering {
+            self.state.sort_visible();
+            return;
+        }
+        // Ascending like `sort_visible`, so the highest score ends up on top
+        let stats = self.stats.per_app(self.priority_since());
+        let scorer = PriorityScorer::new(&self.config, stats, SystemTime::now());
+        self.state.visible_mut().sort_by(|a, b| scorer.cmp(a, b));
+    }
+
+    /// First day of the statistics that feed the priority score
+    fn priority_since(&self) -> i32 {
+        let today = chrono::Local::now().date_naive().num_days_from_ce();
+        today - PRIORITY_HISTORY_DAYS + 1
+    }
+
+    /// Scorer for `smart_ordering` as of now
+    fn priority_scorer(&self) -> PriorityScorer<'_> {
+        PriorityScorer::new(&self.config, self.stats.per_app(self.priority_since()), SystemTime::now())
+    }
+
+    /// Calculate effective max notifications based on available screen space
+    fn effective_max_notifications(&self) -> usize {
+        // Estimated height per notification card (including spacing)
+        const ESTIMATED_CARD_HEIGHT: f32 = 120.0;
+        // Estimated panel/dock height reservation
+        const PANEL_RESERVATION: f32 = 48.0;
+        // Maximum screen height we design for
+        const MAX_SCREEN_HEIGHT: f32 = 1920.0;
+
+        let available_height = MAX_SCREEN_HEIGHT - PANEL_RESERVATION;
+        let calculated_max = (available_height / ESTIMATED_CARD_HEIGHT).floor() as usize;
+
+        // Use the lesser of calculated and configured max
+        let config_max = self.config.max_notifications as usize;
+        calculated_max.min(config_max).max(1) // Always show at least 1
+    }
+
+    /// How a notification's image is cropped and masked
+    fn image_policy(&self, n: &Notification) -> ImagePolicy {
+        ImagePolicy::for_category(
+            self.config.image_corner_radius,
+            self.config.image_fit,
+            self.config.circle_avatars,
+            n.category(),
+        )
+    }
+
+    /// Decode a popup's image on a worker thread, unless already done
+    ///
+    /// At most `IMAGE_DECODE_WORKERS` images decode at once, so a burst of
+    /// screenshots can't tie up every blocking thread.
+    fn decode_image(&mut self, n: &Notification) -> Task<Message> {
+        if !self.config.show_images || self.config.density == Density::Compact {
+            return Task::none();
+        }
+        let Some(image) = n.image().filter(|image| !matches!(image, Image::Name(_))).cloned() else {
+            return Task::none();
+        };
+        let policy = self.image_policy(n);
+        let animate = self.config.enable_animations;
+        let Some(ticket) = self.images.request(n.id, &image, policy, animate) else {
+            return Task::none();
+        };
+        let id = n.id;
+        let permits = self.image_permits.clone();
+        Task::perform(
+            async move {
+                let _permit = permits.acquire_owned().await.ok();
+                tokio::task::spawn_blocking(move || decode_image(&image, &policy, animate))
+                    .await
+                    .ok()
+                    .flatten()
+            },
+            move |decoded| cosmic::action::app(Message::ImageDecoded(id, ticket, decoded)),
+        )
+    }
+
+    fn decode_visible_images(&mut self) -> Task<Message> {
+        let visible = self.state.visible().to_vec();
+        Task::batch(visible.iter().map(|n| self.decode_image(n)))
+    }
+
+    /// Cut off the sound of a notification that was dismissed or replaced
+    fn stop_sound(&mut self, id: u32) {
         #[cfg(feature = "audio")]
-        if !self.config.do_not_disturb {
-            notification.play_sound();
+        if let Some(handle) = self.sounds.remove(&id) {
+            handle.stop();
+        }
+        #[cfg(not(feature = "audio"))]
+        let _ = id;
+    }
+
+    /// Resolve the popup timeout and schedule its expiry
+    ///
+    /// Records the applied timeout on the notification for history.
+    fn schedule_expiry(&mut self, notification: &mut Notification) -> Task<Message> {
+        let timeout = self.config.resolve_timeout(
+            notification.urgency(),
+            notification.expire_timeout,
+            &notification.app_name,
+            notification.desktop_entry(),
+        );
+        notification.applied_timeout = Some(timeout);
+        tracing::debug!(
+            id = notification.id,
+            requested = notification.expire_timeout,
+            applied = timeout,
+            "notification timeout"
+        );
+
+        let id = notification.id;
+        // The critical banner waits to be dismissed
+        if timeout == 0 || self.in_banner(notification) {
+            self.expiry_deadlines.remove(&id);
+            return Task::none();
+        }
+        let timeout = Duration::from_millis(u64::from(timeout));
+        self.expiry_deadlines.insert(id, Instant::now() + timeout);
+        Task::perform(tokio::time::sleep(timeout), move |_| {
+            cosmic::action::app(Message::Timeout(id))
+        })
+    }
+
+    /// Create the layer surface holding the popups
+    fn create_popup_surface(&mut self) -> Task<Message> {
+        let (anchor, _output) = self.anchor.clone().unwrap_or((Anchor::TOP, None));
+        let (output, name) = self.placement_output();
+        self.active_surface = true;
+        self.popup_output = name;
+        // A new surface starts without keyboard focus
+        self.keyboard = KeyboardInteractivity::None;
+        self.key_focus = None;
+        get_layer_surface(SctkLayerSurfaceSettings {
+            id: self.window_id,
+            anchor,
+            exclusive_zone: 0,
+            keyboard_interactivity: KeyboardInteractivity::None,
+            namespace: "notifications".to_string(),
+            margin: self.popup_margin(),
+            // Notification window size configuration
+            size: Some((Some(NOTIFICATION_WIDTH as u32), Some(1))),
+            output,
+            size_limits: Limits::NONE
+                .min_width(NOTIFICATION_MIN_WIDTH)
+                .min_height(1.0)
+                .max_height(NOTIFICATION_MAX_HEIGHT)
+                .max_width(NOTIFICATION_WIDTH),
+            ..Default::default()
+        })
+    }
+
+    /// Margins keeping popups clear of the panel and dock
+    ///
+    /// Panels reserving their space are kept clear by the compositor. Those
+    /// that don't, or that auto-hide and so reserve nothing while hidden,
+    /// get their thickness added to the margin at their edge, so popups
+    /// never end up underneath one as it slides in.
+    fn popup_margin(&self) -> IcedMargin {
+        let mut margin = IcedMargin {
+            top: NOTIFICATION_MARGIN,
+            right: NOTIFICATION_MARGIN,
+            bottom: NOTIFICATION_MARGIN,
+            left: NOTIFICATION_MARGIN,
+        };
+        for panel in [&self.panel_config, &self.dock_config] {
+            if panel.exclusive_zone && panel.autohide.is_none() {
+                continue;
+            }
+            let edge = match panel.anchor {
+                PanelAnchor::Top => &mut margin.top,
+                PanelAnchor::Bottom => &mut margin.bottom,
+                PanelAnchor::Left => &mut margin.left,
+                PanelAnchor::Right => &mut margin.right,
+            };
+            *edge = (*edge).max(NOTIFICATION_MARGIN + panel_thickness(panel));
+        }
+        margin
+    }
+
+    /// Move open surfaces clear of a panel or dock that changed
+    fn sync_margins(&self) -> Task<Message> {
+        let margin = self.popup_margin();
+        let open = [
+            (self.window_id, self.active_surface),
+            (self.center_id, self.center_open),
+        ];
+        Task::batch(open.into_iter().filter(|(_, open)| *open).map(|(id, _)| {
+            set_margin(id, margin.top, margin.right, margin.bottom, margin.left)
+        }))
+    }
+
+    /// Output for a new surface as `popup_placement` asks, with its name
+    ///
+    /// Falls back to the active output while the wanted one is unknown,
+    /// e.g. unplugged or not focused yet.
+    fn placement_output(&self) -> (IcedOutput, Option<String>) {
+        let wanted = match self.config.popup_placement {
+            PopupPlacement::Active => None,
+            PopupPlacement::FollowFocus => self.focused_output.as_ref(),
+            PopupPlacement::Fixed => self.config.popup_output.as_ref(),
+        };
+        match wanted.and_then(|name| Some((name, self.outputs.get(name)?))) {
+            Some((name, output)) => (IcedOutput::Output(output.clone()), Some(name.clone())),
+            None => (IcedOutput::Active, None),
+        }
+    }
+
+    /// Move the popups along to the monitor the user went on to
+    ///
+    /// Popups stay put while a reply is typed or they're navigated by
+    /// keyboard, since moving them drops keyboard focus.
+    fn follow_focus(&mut self, name: String) -> Task<Message> {
+        self.focused_output = Some(name);
+        if self.config.popup_placement != PopupPlacement::FollowFocus
+            || !self.active_surface
+            || !self.reply_drafts.is_empty()
+            || self.key_focus.is_some()
+        {
+            return Task::none();
+        }
+        let (_, wanted) = self.placement_output();
+        if wanted.is_none() || wanted == self.popup_output {
+            return Task::none();
+        }
+        let old = std::mem::replace(&mut self.window_id, SurfaceId::unique());
+        Task::batch([destroy_layer_surface(old), self.create_popup_surface()])
+    }
+
+    /// Pause a popup's timeout while the pointer is over it
+    fn hover_card(&mut self, id: u32, hovered: bool) -> Task<Message> {
+        if !hovered {
+            // Like any menu, the right-click menu and snooze durations go
+            // once left behind
+            if self.context_menu == Some(id) {
+                self.context_menu = None;
+            }
+            if self.snooze_picker == Some(id) {
+                self.snooze_picker = None;
+            }
+            return if self.hovered.is_some_and(|(h, _)| h == id) {
+                self.resume_expiry()
+            } else {
+                Task::none()
+            };
+        }
+        if self.hovered.is_some_and(|(h, _)| h == id) {
+            return Task::none();
+        }
+        // Moving straight to another card may not report leaving this one
+        let left = self.resume_expiry();
+        let now = Instant::now();
+        let remaining = self
+            .expiry_deadlines
+            .remove(&id)
+            .map(|deadline| deadline.saturating_duration_since(now));
+        self.hovered = Some((id, remaining));
+        left
+    }
+
+    /// Drag popups sideways to dismiss them
+    ///
+    /// The popup under the pointer follows it; let go past
+    /// `SWIPE_DISMISS_DISTANCE` and it slides off and closes as dismissed.
+    fn swipe_pointer(&mut self, input: PointerInput) {
+        match input {
+            PointerInput::Moved(x) => {
+                self.pointer_x = Some(x);
+                if let Some(swipe) = self.swipe.as_mut() {
+                    swipe.moved(x);
+                }
+            }
+            PointerInput::Pressed(x) => {
+                self.swiped = None;
+                if self.swipe.is_some_and(|swipe| swipe.is_leaving()) {
+                    return;
+                }
+                let x = x.or(self.pointer_x);
+                self.swipe = self
+                    .hovered
+                    .zip(x)
+                    .map(|((id, _), x)| Swipe::start(id, x));
+            }
+            PointerInput::Released => {
+                let Some(swipe) = self.swipe.as_mut() else {
+                    return;
+                };
+                if !swipe.is_dragging() {
+                    return;
+                }
+                if swipe.is_drag(SWIPE_SLOP) {
+                    self.swiped = Some(swipe.id);
+                }
+                if !swipe.release(SWIPE_DISMISS_DISTANCE, Instant::now()) {
+                    self.swipe = None;
+                }
+            }
+        }
+    }
+
+    /// Whether a click on popup `id` is really the end of a drag
+    fn is_swipe_click(&self, id: u32) -> bool {
+        self.swiped == Some(id)
+            || self
+                .swipe
+                .is_some_and(|swipe| swipe.id == id && swipe.is_drag(SWIPE_SLOP))
+    }
+
+    /// Restart the timeout of the popup the pointer just left
+    fn resume_expiry(&mut self) -> Task<Message> {
+        let Some((id, Some(remaining))) = self.hovered.take() else {
+            return Task::none();
+        };
+        let wait = match self.config.hover_timeout {
+            HoverTimeout::Resume => remaining,
+            HoverTimeout::Restart => self
+                .state
+                .visible()
+                .iter()
+                .find(|n| n.id == id)
+                .and_then(|n| n.applied_timeout)
+                .map_or(remaining, |ms| Duration::from_millis(u64::from(ms))),
+        };
+        // Don't let the popup vanish the moment the pointer leaves it
+        let wait = wait.max(Duration::from_millis(HOVER_RESUME_MIN_MS));
+        self.expiry_deadlines.insert(id, Instant::now() + wait);
+        Task::perform(tokio::time::sleep(wait), move |_| {
+            cosmic::action::app(Message::Timeout(id))
+        })
+    }
+
+    /// Update a visible notification in place, e.g. a progress update
+    ///
+    /// The card keeps its position and its content changes without a new
+    /// entry animation; a progress bar eases to its new value. Depending on
+    /// `replace_timeout` the timeout starts over, so a download reporting
+    /// progress stays on screen while it runs, or keeps its deadline.
+    fn replace_notification(&mut self, mut notification: Notification) -> Task<Message> {
+        self.stop_sound(notification.id);
+        let id = notification.id;
+        let Some((previous_progress, previous_timeout, was_banner)) = self
+            .state
+            .visible()
+            .iter()
+            .find(|n| n.id == id)
+            .map(|n| (get_progress_from_hints(n), n.applied_timeout, self.in_banner(n)))
+        else {
+            tracing::error!("Notification not found... pushing instead");
+            return self.push_notification(notification);
+        };
+
+        if self.config.enable_animations {
+            if let (Some(from), Some(to)) = (previous_progress, get_progress_from_hints(&notification)) {
+                self.progress_anims
+                    .entry(id)
+                    .or_insert_with(|| AnimatedProgress::new(from, PROGRESS_ANIMATION_MS))
+                    .set_target(to);
+            } else {
+                self.progress_anims.remove(&id);
+            }
         }
 
-        let mut timeout = u32::try_from(notification.expire_timeout).unwrap_or(3000);
-        let max_timeout = if notification.urgency() == 2 {
-            self.config.max_timeout_urgent
-        } else if notification.urgency() == 1 {
-            self.config.max_timeout_normal
+        // A change in urgency can move it between the popups and the banner
+        let in_banner = self.in_banner(&notification);
+        let moved = in_banner != was_banner;
+        // Moving starts its timeout over; the banner has none
+        let preserve = self.config.replace_timeout == ReplaceTimeout::Preserve
+            && self.expiry_deadlines.contains_key(&id)
+            && !moved;
+        let expiry = if preserve {
+            // The pending timer still fires at the original deadline
+            notification.applied_timeout = previous_timeout;
+            Task::none()
         } else {
-            self.config.max_timeout_low
+            self.schedule_expiry(&mut notification)
+        };
+
+        let image = self.decode_image(&notification);
+        if let Some(old) = self.state.visible().iter().find(|n| n.id == id).cloned() {
+            // Compared masked, so a changed code is neither read out nor
+            // given away by an announcement
+            let old = self.masked(&old).unwrap_or(old);
+            let masked = self.masked(&notification);
+            let update = self.live.replaced(&old, masked.as_ref().unwrap_or(&notification));
+            if let Some(notif) = self.state.visible_mut().iter_mut().find(|n| n.id == id) {
+                *notif = notification;
+            }
+            // Changes to hidden content would give it away
+            if let Some(update) = update.filter(|_| !self.hides_content()) {
+                self.announce(update);
+            }
+        }
+        self.save_pending();
+        let mut tasks = vec![expiry, image];
+        if moved && self.capabilities.layer_shell {
+            if in_banner {
+                self.transitions.forget(id);
+                tasks.push(self.open_banner());
+            } else if !self.active_surface {
+                tasks.push(self.create_popup_surface());
+            }
+            tasks.push(self.close_surface_if_empty());
         }
-        .unwrap_or(u32::try_from(notification.expire_timeout).unwrap_or(3000));
-        timeout = timeout.min(max_timeout);
+        Task::batch(tasks)
+    }
 
-        let mut tasks = vec![if timeout > 0 {
-            iced::Task::perform(
-                tokio::time::sleep(Duration::from_millis(timeout as u64)),
-                move |_| cosmic::action::app(Message::Timeout(notification.id)),
-            )
+    fn start_focus_session(&mut self, duration: Duration, allowed_apps: Vec<String>) -> Task<Message> {
+        tracing::info!("Starting focus session for {}s", duration.as_secs());
+        self.focus_session = Some(FocusSession::new(duration, allowed_apps));
+        self.broadcast_focus_session();
+
+        Task::perform(tokio::time::sleep(duration), |_| {
+            cosmic::action::app(Message::FocusSessionEnded)
+        })
+    }
+
+    /// End the focus session and show a summary of what was suppressed
+    fn end_focus_session(&mut self) -> Task<Message> {
+        let Some(session) = self.focus_session.take() else {
+            return Task::none();
+        };
+        tracing::info!(
+            "Focus session ended, {} notifications suppressed",
+            session.suppressed_count()
+        );
+        self.broadcast_focus_session();
+
+        let suppressed = session.suppressed_count();
+        let body = if suppressed == 0 {
+            crate::fl!("focus-session-nothing-suppressed")
         } else {
-            iced::Task::none()
-        }];
-
-        if self.state.is_empty() && !self.config.do_not_disturb {
-            let (anchor, _output) = self.anchor.clone().unwrap_or((Anchor::TOP, None));
-            self.active_surface = true;
-            tasks.push(get_layer_surface(SctkLayerSurfaceSettings {
-                id: self.window_id,
-                anchor,
-                exclusive_zone: 0,
-                keyboard_interactivity: KeyboardInteractivity::None,
-                namespace: "notifications".to_string(),
-                margin: IcedMargin {
-                    top: NOTIFICATION_MARGIN,
-                    right: NOTIFICATION_MARGIN,
-                    bottom: NOTIFICATION_MARGIN,
-                    left: NOTIFICATION_MARGIN,
-                },
-                // Notification window size configuration
-                size: Some((Some(NOTIFICATION_WIDTH as u32), Some(1))),
-                output: IcedOutput::Active, // TODO should we only create the notification on the output the applet is on?
-                size_limits: Limits::NONE
-                    .min_width(NOTIFICATION_MIN_WIDTH)
-                    .min_height(1.0)
-                    .max_height(NOTIFICATION_MAX_HEIGHT)
-                    .max_width(NOTIFICATION_WIDTH),
-                ..Default::default()
-            }));
+            // Keep everything on one line, plain bodies only show their first line
+            let apps = session
+                .suppressed_by_app()
+                .map(|(app, count)| crate::fl!("focus-session-suppressed-app", app = app, count = count))
+                .collect::<Vec<_>>()
+                .join(", ");
+            crate::fl!(
+                "focus-session-suppressed",
+                count = suppressed,
+                app_count = session.suppressed_app_count(),
+                apps = apps
+            )
+        };
+
+        let summary = Notification {
+            id: self.state.next_internal_id(),
+            app_name: crate::fl!("app-name"),
+            app_icon: FOCUS_SESSION_ICON.to_string(),
+            summary: crate::fl!("focus-session-ended"),
+            body,
+            actions: Vec::new(),
+            hints: vec![Hint::Urgency(1)],
+            expire_timeout: -1,
+            applied_timeout: None,
+            time: SystemTime::now(),
+        };
+        self.push_notification(summary)
+    }
+
+    /// Summarize what arrived while the session was locked
+    fn show_unlock_summary(&mut self) -> Task<Message> {
+        let Some(since) = self.locked_since.take() else {
+            return Task::none();
+        };
+        self.save_away_since(None);
+        let locked_for = since.elapsed().unwrap_or_default();
+        if locked_for.as_secs() < AWAY_SUMMARY_MIN_LOCKED {
+            return Task::none();
+        }
+        self.show_away_summary(since)
+    }
+
+    /// Summarize what the last session left from when it was locked or
+    /// ended, once the notifications it saved are restored
+    fn show_login_summary(&mut self) -> Task<Message> {
+        let Some(since) = self.away_at_login.take() else {
+            return Task::none();
+        };
+        self.save_away_since(None);
+        self.show_away_summary(since)
+    }
+
+    /// Remember when the session was locked or ended, for the away summary
+    /// at the next login; `None` once the user is back
+    fn save_away_since(&self, since: Option<SystemTime>) {
+        let Some(state) = self.state_helper.as_ref() else {
+            return;
+        };
+        let secs = since
+            .and_then(|since| since.duration_since(UNIX_EPOCH).ok())
+            .map_or(0, |since| since.as_secs());
+        if let Err(err) = state.set(AWAY_KEY, secs) {
+            tracing::error!("Failed to save when the session was left: {:?}", err);
+        }
+    }
+
+    /// Summarize what arrived since the user left at `since`
+    ///
+    /// Only app names and counts are shown, never notification content.
+    fn show_away_summary(&mut self, since: SystemTime) -> Task<Message> {
+        if !self.config.away_summary {
+            return Task::none();
+        }
+        let Some(body) = self.missed_since(since) else {
+            return Task::none();
         };
+        tracing::info!("Notifications arrived while away: {}", body);
+
+        let summary = Notification {
+            id: self.state.next_internal_id(),
+            app_name: crate::fl!("app-name"),
+            app_icon: AWAY_SUMMARY_ICON.to_string(),
+            summary: crate::fl!("away-summary-title"),
+            body,
+            actions: vec![(
+                ActionId::Custom(AWAY_SUMMARY_ACTION.to_string()),
+                crate::fl!("away-summary-open"),
+            )],
+            hints: vec![Hint::Urgency(1), Hint::Transient(true)],
+            expire_timeout: -1,
+            applied_timeout: None,
+            time: SystemTime::now(),
+        };
+        self.away_summary_id = Some(summary.id);
+        self.push_notification(summary)
+    }
+
+    /// Tell the user which apps sent too many notifications, and how many
+    /// of them were dropped
+    fn show_rate_limit_summary(&mut self, dropped: Vec<(String, u32)>) -> Task<Message> {
+        let count: u32 = dropped.iter().map(|(_, count)| count).sum();
+        if count == 0 {
+            return Task::none();
+        }
+        let apps = dropped
+            .iter()
+            .map(|(app, count)| crate::fl!("away-summary-app", app = app.as_str(), count = count))
+            .collect::<Vec<_>>()
+            .join(", ");
+        let body = crate::fl!("rate-limit-summary", count = count, apps = apps);
+        tracing::info!("Rate limited: {}", body);
+
+        let summary = Notification {
+            id: self.state.next_internal_id(),
+            app_name: crate::fl!("app-name"),
+            app_icon: RATE_LIMIT_SUMMARY_ICON.to_string(),
+            summary: crate::fl!("rate-limit-title"),
+            body,
+            actions: Vec::new(),
+            hints: vec![Hint::Urgency(1), Hint::Transient(true)],
+            expire_timeout: -1,
+            applied_timeout: None,
+            time: SystemTime::now(),
+        };
+        self.push_notification(summary)
+    }
+
+    /// One line saying how many notifications arrived from which apps
+    /// since `since`, or `None` if nothing did
+    fn missed_since(&self, since: SystemTime) -> Option<String> {
+        // Popups that are still up count too, they may have arrived meanwhile
+        let missed = history::count_by_app_since(
+            self.state.hidden().iter().chain(self.state.visible()),
+            since,
+        );
+        let count: u32 = missed.iter().map(|(_, count)| count).sum();
+        if count == 0 {
+            return None;
+        }
+
+        // Keep everything on one line, plain bodies only show their first line
+        let apps = missed
+            .iter()
+            .map(|(app, count)| crate::fl!("away-summary-app", app = app.as_str(), count = count))
+            .collect::<Vec<_>>()
+            .join(", ");
+        Some(crate::fl!(
+            "away-summary",
+            count = count,
+            app_count = missed.len(),
+            apps = apps
+        ))
+    }
+
+    /// Since when the user has been away: the session locked or
+    /// do-not-disturb on, whichever came first
+    #[cfg(feature = "email")]
+    fn away_since(&self) -> Option<SystemTime> {
+        match (self.locked_since, self.dnd_since) {
+            (Some(locked), Some(dnd)) => Some(locked.min(dnd)),
+            (locked, dnd) => locked.or(dnd),
+        }
+    }
+
+    /// Email what arrived since the last digest, once the user has been
+    /// away for `after_minutes`, and again every `after_minutes` after that
+    #[cfg(feature = "email")]
+    fn email_digest(&mut self) {
+        let (Some(settings), Some(email)) = (&self.config.email_digest, &self.email) else {
+            return;
+        };
+        let Some(away_since) = self.away_since() else {
+            return;
+        };
+        let now = SystemTime::now();
+        let every = Duration::from_secs(u64::from(settings.after_minutes.max(1)) * 60);
+        // A digest from an earlier time away doesn't count
+        let since = self
+            .email_digest_sent
+            .filter(|sent| *sent > away_since)
+            .unwrap_or(away_since);
+        if now.duration_since(since).unwrap_or_default() < every {
+            return;
+        }
+        self.email_digest_sent = Some(now);
+
+        let mut missed: Vec<&Notification> = self
+            .state
+            .hidden()
+            .iter()
+            .chain(self.state.visible())
+            .filter(|n| n.time >= since && n.time < now)
+            .collect();
+        if missed.is_empty() {
+            return;
+        }
+        missed.sort_by_key(|n| n.time);
+        let entries: Vec<crate::email::DigestEntry> = missed
+            .into_iter()
+            .map(|n| {
+                let sensitive = self.config.is_sensitive_app(&n.app_name, n.desktop_entry());
+                let masked = self.masked_for_sharing(n);
+                let shown = masked.as_ref().unwrap_or(n);
+                crate::email::DigestEntry {
+                    time: n.time,
+                    app_name: n.app_name.clone(),
+                    summary: (!sensitive).then(|| shown.summary.clone()),
+                    body: (!sensitive && settings.include_body).then(|| strip_html(&shown.body)),
+                }
+            })
+            .collect();
+        tracing::info!("Emailing a digest of {} missed notifications", entries.len());
+        let (subject, body) = crate::email::compose(&entries, since);
+        email.send(subject, body);
+    }
+
+    /// Turn do-not-disturb on while the screen is shared, and off again
+    /// once sharing ends unless the user had it on already or changed it
+    fn screen_share_changed(&mut self, sharing: bool) -> Task<Message> {
+        if sharing == self.screen_shared {
+            return Task::none();
+        }
+        self.screen_shared = sharing;
+        self.sync_content_rules();
+        Task::none()
+    }
+
+    /// Do-not-disturb mode in effect right now
+    ///
+    /// Screen sharing turns do-not-disturb on for as long as it lasts, without
+    /// touching the saved setting or counting as time away.
+    fn effective_dnd(&self) -> DndMode {
+        match self.config.dnd_mode() {
+            DndMode::Open if self.screen_shared && self.config.priority_only => DndMode::PriorityOnly,
+            DndMode::Open if self.screen_shared => DndMode::Full,
+            mode => mode,
+        }
+    }
+
+    /// Track do-not-disturb turning on, and summarize what it held back
+    /// once it turns off
+    fn dnd_changed(&mut self, enabled: bool) -> Task<Message> {
+        if enabled {
+            self.dnd_since.get_or_insert_with(SystemTime::now);
+            return Task::none();
+        }
+        let Some(since) = self.dnd_since.take() else {
+            return Task::none();
+        };
+        if !self.config.dnd_summary {
+            return Task::none();
+        }
+        let Some(body) = self.missed_since(since) else {
+            return Task::none();
+        };
+        tracing::info!("do-not-disturb ended: {}", body);
+
+        let summary = Notification {
+            id: self.state.next_internal_id(),
+            app_name: crate::fl!("app-name"),
+            app_icon: DND_SUMMARY_ICON.to_string(),
+            summary: crate::fl!("dnd-summary-title"),
+            body,
+            actions: vec![(
+                ActionId::Custom(DND_SUMMARY_ACTION.to_string()),
+                crate::fl!("dnd-summary-open"),
+            )],
+            hints: vec![Hint::Urgency(1), Hint::Transient(true)],
+            expire_timeout: -1,
+            applied_timeout: None,
+            time: SystemTime::now(),
+        };
+        self.dnd_summary_id = Some(summary.id);
+        self.push_notification(summary)
+    }
+
+    /// Tell connected applets how long the focus session has left
+    fn broadcast_focus_session(&self) {
+        let Some(tx) = self.notifications_tx.clone() else {
+            return;
+        };
+        let remaining = self.focus_session_remaining();
+        tokio::spawn(async move {
+            _ = tx
+                .send(notifications::Input::FocusSessionChanged(remaining))
+                .await;
+        });
+    }
+
+    /// Remove what `wipe_history` asks for from history
+    ///
+    /// When the session is ending, matching popups and held notifications
+    /// go too, so they aren't restored at the next login.
+    fn wipe_history(&mut self, session_ending: bool) {
+        if self.config.wipe_history == HistoryWipe::Never {
+            return;
+        }
+        let before = self.state.hidden().len();
+        self.state.hidden_mut().retain(|n| !wiped(&self.config, n));
+        tracing::info!("Wiped {} notifications from history", before - self.state.hidden().len());
+
+        // Snoozed notifications are kept on disk too
+        let snoozed = self.snoozed.len();
+        self.snoozed.retain(|n| !wiped(&self.config, n));
+        if self.snoozed.len() != snoozed {
+            self.save_snoozed();
+        }
+
+        if session_ending {
+            let ids: Vec<u32> = self
+                .state
+                .visible()
+                .iter()
+                .filter(|n| wiped(&self.config, n))
+                .map(|n| n.id)
+                .collect();
+            for id in ids {
+                self.take_notification(id);
+            }
+            self.held.retain(|n| !wiped(&self.config, n));
+            self.sync_control_state();
+        }
+    }
+
+    /// Ids of the history entries matching `query`
+    fn search_ids(&mut self, query: &str) -> BTreeSet<u32> {
+        self.search_index.sync(self.state.hidden());
+        self.search_index.search(query)
+    }
+
+    /// History entries matching `query`, newest first, for listeners
+    ///
+    /// Both the search and the results use what listeners see of history,
+    /// so a query can't probe for a masked code or withheld text.
+    fn search_history(&self, query: &str) -> Vec<Notification> {
+        search(self.state.hidden().iter().map(|n| self.shared(n)).collect(), query)
+    }
+
+    /// Hidden history grouped under date sections for the applet
+    fn history_sections(&mut self) -> Vec<notifications::HistorySectionInfo> {
+        let sections = history::group_by_date(self.state.hidden(), history::local_today());
+        let keys: Vec<_> = sections.iter().map(|(section, _)| *section).collect();
+        self.history_collapse.retain_sections(&keys);
+
+        sections
+            .into_iter()
+            .map(|(section, entries)| {
+                (
+                    section.key(),
+                    section.label(),
+                    self.history_collapse.is_collapsed(section),
+                    entries.iter().map(|n| n.id).collect(),
+                )
+            })
+            .collect()
+    }
+
+    /// Mute a conversation and take its popups off screen
+    fn mute_conversation(&mut self, key: String) -> Task<Message> {
+        let now = unix_now();
+        let hours = self.config.mute_conversation_hours.max(1);
+        tracing::info!("Muting conversation for {}h", hours);
+
+        let mut mutes = self.config.active_conversation_mutes(now);
+        mutes.retain(|m| m.key != key);
+        mutes.push(MutedConversation {
+            key: key.clone(),
+            until: now + u64::from(hours) * 3600,
+        });
+        self.save_muted_conversations(mutes);
+
+        let ids: Vec<u32> = self
+            .state
+            .visible()
+            .iter()
+            .filter(|n| n.conversation_key().as_deref() == Some(key.as_str()))
+            .map(|n| n.id)
+            .collect();
+        for id in ids {
+            self.stop_sound(id);
+            self.expire(id);
+        }
+
+        Task::batch([
+            self.schedule_conversation_mute_expiry(),
+            self.close_surface_if_empty(),
+        ])
+    }
+
+    /// Drop expired conversation mutes and wait for the next one to expire
+    fn expire_conversation_mutes(&mut self) -> Task<Message> {
+        let active = self.config.active_conversation_mutes(unix_now());
+        if active.len() != self.config.muted_conversations.len() {
+            self.save_muted_conversations(active);
+        }
+        self.schedule_conversation_mute_expiry()
+    }
+
+    fn schedule_conversation_mute_expiry(&mut self) -> Task<Message> {
+        let wait = self
+            .config
+            .muted_conversations
+            .iter()
+            .map(|m| m.until)
+            .min()
+            .map(|next| Duration::from_secs(next.saturating_sub(unix_now())));
+        self.wake_after(Wakeup::ConversationMutes, wait)
+    }
+
+    fn save_muted_conversations(&mut self, mutes: Vec<MutedConversation>) {
+        match self.config_helper.as_ref() {
+            Some(helper) => {
+                if let Err(err) = self.config.set_muted_conversations(helper, mutes) {
+                    tracing::error!("Failed to save muted conversations: {:?}", err);
+                }
+            }
+            // Without a config backend mutes only last until restart
+            None => self.config.muted_conversations = mutes,
+        }
+    }
+
+    /// Change the master sound volume and persist it
+    fn set_sound_volume(&mut self, volume: u8) {
+        let volume = volume.min(100);
+        match self.config_helper.as_ref() {
+            Some(helper) => {
+                if let Err(err) = self.config.set_sound_volume(helper, volume) {
+                    tracing::error!("Failed to save sound volume: {:?}", err);
+                }
+            }
+            // Without a config backend the change only lasts until restart
+            None => self.config.sound_volume = volume,
+        }
+        self.sync_capabilities();
+    }
+
+    /// Turn do-not-disturb on or off, optionally only for `duration`
+    ///
+    /// The setting is saved to the config like a change from the settings
+    /// page would be.
+    fn set_dnd(&mut self, enabled: bool, duration: Option<Duration>) -> Task<Message> {
+        match self.config_helper.as_ref() {
+            Some(helper) => {
+                if let Err(err) = self.config.set_do_not_disturb(helper, enabled) {
+                    tracing::error!("Failed to save do-not-disturb: {:?}", err);
+                }
+            }
+            // Without a config backend the change only lasts until restart
+            None => self.config.do_not_disturb = enabled,
+        }
+        self.sync_control_state();
+
+        self.dnd_until = None;
+        let expiry = match duration {
+            Some(duration) if enabled => {
+                self.dnd_until = Some(Instant::now() + duration);
+                Task::perform(tokio::time::sleep(duration), |_| {
+                    cosmic::action::app(Message::DndExpired)
+                })
+            }
+            _ => Task::none(),
+        };
+        Task::batch([expiry, self.dnd_changed(enabled)])
+    }
+
+    /// Switch to do-not-disturb `mode`, optionally only for `duration`,
+    /// saved to the config
+    ///
+    /// Turning it off keeps whether it comes back priority-only.
+    fn set_dnd_mode(&mut self, mode: DndMode, duration: Option<Duration>) -> Task<Message> {
+        if mode != DndMode::Open {
+            let priority_only = mode == DndMode::PriorityOnly;
+            match self.config_helper.as_ref() {
+                Some(helper) => {
+                    if let Err(err) = self.config.set_priority_only(helper, priority_only) {
+                        tracing::error!("Failed to save priority-only DND: {:?}", err);
+                    }
+                }
+                // Without a config backend the change only lasts until restart
+                None => self.config.priority_only = priority_only,
+            }
+        }
+        self.set_dnd(mode != DndMode::Open, duration)
+    }
+
+    /// Let critical notifications through priority-only DND or not, saved
+    /// to the config
+    fn set_critical_bypass(&mut self, enabled: bool) {
+        match self.config_helper.as_ref() {
+            Some(helper) => {
+                if let Err(err) = self.config.set_critical_bypass_dnd(helper, enabled) {
+                    tracing::error!("Failed to save critical bypass: {:?}", err);
+                }
+            }
+            // Without a config backend the change only lasts until restart
+            None => self.config.critical_bypass_dnd = enabled,
+        }
+        self.sync_control_state();
+    }
+
+    /// Hold popups back, or show everything held and deliver normally again
+    fn set_paused(&mut self, paused: bool) -> Task<Message> {
+        self.paused = paused;
+        self.release_held()
+    }
+
+    /// Whether a new notification waits in `held` instead of showing:
+    /// everything while paused, and non-critical ones over a fullscreen
+    /// window when `fullscreen_popups` is Queue
+    fn holds_back(&self, notification: &Notification) -> bool {
+        self.paused
+            || (self.fullscreen
+                && self.config.fullscreen_popups == FullscreenPopups::Queue
+                && notification.urgency() < 2)
+    }
+
+    /// Show the held notifications nothing holds back anymore
+    fn release_held(&mut self) -> Task<Message> {
+        let (held, released): (Vec<_>, Vec<_>) = std::mem::take(&mut self.held)
+            .into_iter()
+            .partition(|n| self.holds_back(n));
+        self.held = held;
+        let tasks: Vec<_> = released
+            .into_iter()
+            .map(|n| self.push_notification(n))
+            .collect();
+        self.sync_control_state();
+        Task::batch(tasks)
+    }
 
-        self.sort_notifications();
-        self.state.insert_sorted(notification);
-        self.group_notifications();
+    /// Keep a notification back while paused, updating it in place if one
+    /// with the same id is already waiting
+    fn hold(&mut self, notification: Notification) {
+        self.audit(&notification, AuditOutcome::Held);
+        match self.held.iter_mut().find(|n| n.id == notification.id) {
+            Some(held) => *held = notification,
+            None => self.held.push(notification),
+        }
+        self.sync_control_state();
+    }
 
-        iced::Task::batch(tasks)
+    /// Advertise capabilities matching the current config
+    fn sync_capabilities(&self) {
+        let Some(tx) = self.notifications_tx.as_ref() else {
+            return;
+        };
+        let capabilities = notifications::ServerCapabilities {
+            images: self.config.show_images,
+            actions: self.config.show_actions,
+            hyperlinks: self.config.enable_links,
+            sound: cfg!(feature = "audio") && self.config.sound_volume > 0,
+        };
+        if let Err(err) = tx.try_send(notifications::Input::CapabilitiesChanged(capabilities)) {
+            tracing::error!("Failed to update capabilities: {}", err);
+        }
     }
 
-    fn group_notifications(&mut self) {
-        self.state.group_by_app(
-            self.config.max_per_app as usize,
-            self.effective_max_notifications()
-        );
+    /// Hand the rate limits of the current config to the D-Bus interface
+    fn sync_rate_limits(&self) {
+        let Some(tx) = self.notifications_tx.as_ref() else {
+            return;
+        };
+        let limits = notifications::RateLimits::from_config(&self.config);
+        if let Err(err) = tx.try_send(notifications::Input::RateLimitsChanged(limits)) {
+            tracing::error!("Failed to update rate limits: {}", err);
+        }
     }
 
-    fn sort_notifications(&mut self) {
-        self.state.sort_visible();
+    /// Hand the text clean-up of the current config, and whether content
+    /// is hidden right now, to the D-Bus interface
+    fn sync_content_rules(&self) {
+        let Some(tx) = self.notifications_tx.as_ref() else {
+            return;
+        };
+        let rules = notifications::ContentRules::from_config(&self.config, self.hides_content());
+        #[cfg(feature = "plugins")]
+        let rules = rules.with_plugins(Arc::clone(&self.plugins));
+        if let Err(err) = tx.try_send(notifications::Input::ContentRulesChanged(rules)) {
+            tracing::error!("Failed to update content rules: {}", err);
+        }
     }
 
-    /// Calculate effective max notifications based on available screen space
-    fn effective_max_notifications(&self) -> usize {
-        // Estimated height per notification card (including spacing)
-        const ESTIMATED_CARD_HEIGHT: f32 = 120.0;
-        // Estimated panel/dock height reservation
-        const PANEL_RESERVATION: f32 = 48.0;
-        // Maximum screen height we design for
-        const MAX_SCREEN_HEIGHT: f32 = 1920.0;
+    /// Publish DND, pause and popup counts on the control interface if they
+    /// changed since last time, and save the popups for the next run
+    fn sync_control_state(&mut self) {
+        // Called whenever popups or held notifications change
+        self.save_pending();
 
-        let available_height = MAX_SCREEN_HEIGHT - PANEL_RESERVATION;
-        let calculated_max = (available_height / ESTIMATED_CARD_HEIGHT).floor() as usize;
+        let state = ControlState {
+            do_not_disturb: self.config.do_not_disturb,
+            critical_bypass_dnd: self.config.critical_bypass_dnd,
+            visible_count: u32::try_from(self.state.visible_count()).unwrap_or(u32::MAX),
+            paused: self.paused,
+            held_count: u32::try_from(self.held.len()).unwrap_or(u32::MAX),
+        };
+        if self.control_state == Some(state) {
+            return;
+        }
+        let Some(tx) = self.notifications_tx.as_ref() else {
+            return;
+        };
+        let input = notifications::Input::ControlStateChanged(state);
+        if tx.try_send(input).is_ok() {
+            self.control_state = Some(state);
+        }
+    }
 
-        // Use the lesser of calculated and configured max
-        let config_max = self.config.max_notifications as usize;
-        calculated_max.min(config_max).max(1) // Always show at least 1
+    /// Daemon state as pretty-printed JSON for bug reports
+    fn debug_dump(&self) -> String {
+        let dump = serde_json::json!({
+            "version": crate::config::VERSION,
+            "compositor_capabilities": self.capabilities.to_json(),
+            "active_surface": self.active_surface,
+            "dnd_mode": format!("{:?}", self.effective_dnd()),
+            "visible": self.state.visible_count(),
+            "hidden": self.state.hidden().len(),
+            "paused": self.paused,
+            "held": self.held.len(),
+            "snoozed": self.snoozed.len(),
+            "hidden_memory_bytes": self.state.hidden_memory_usage(),
+            "focus_session_remaining_secs": self.focus_session_remaining(),
+            "sound": self.sound_diagnostics(),
+        });
+        serde_json::to_string_pretty(&dump).unwrap_or_default()
     }
 
-    fn replace_notification(&mut self, notification: Notification) -> Task<Message> {
-        if let Some(notif) = self.state.visible_mut().iter_mut().find(|n| n.id == notification.id) {
-            *notif = notification;
-            Task::none()
-        } else {
-            tracing::error!("Notification not found... pushing instead");
-            self.push_notification(notification)
+    /// Playback counters and the last failure, null without audio support
+    fn sound_diagnostics(&self) -> serde_json::Value {
+        #[cfg(feature = "audio")]
+        {
+            let stats = cosmic_ext_notifications_util::playback_stats();
+            serde_json::json!({
+                "started": stats.started,
+                "dropped": stats.dropped,
+                "failed": stats.failed,
+                "last_failure": self.last_sound_failure,
+            })
         }
+        #[cfg(not(feature = "audio"))]
+        serde_json::Value::Null
+    }
+
+    /// Seconds left in the focus session, or 0 if none is active
+    fn focus_session_remaining(&self) -> u32 {
+        self.focus_session
+            .as_ref()
+            .map_or(0, |s| u32::try_from(s.remaining().as_secs()).unwrap_or(u32::MAX))
     }
 
     fn request_activation(&mut self, i: u32, action: Option<ActionId>) -> Task<Message> {
+        // The DND and away summaries are ours, they open the center
+        // instead of an app
+        if self.dnd_summary_id == Some(i) || self.away_summary_id == Some(i) {
+            self.dnd_summary_id.take_if(|id| *id == i);
+            self.away_summary_id.take_if(|id| *id == i);
+            let close = self.close(i, CloseReason::Dismissed).unwrap_or(Task::none());
+            let center = if self.center_open {
+                Task::none()
+            } else {
+                self.toggle_center()
+            };
+            return Task::batch([close, center]);
+        }
+        if !self.capabilities.activation {
+            // Invoke the action without a token, the app just won't be raised
+            return self
+                .activate_notification(String::new(), i, action)
+                .unwrap_or(Task::none());
+        }
         activation::request_token(Some(String::from(Self::APP_ID)), Some(self.window_id)).map(
             move |token| cosmic::Action::App(Message::ActivationToken(token, i, action.clone())),
         )
@@ -621,8 +3779,10 @@ impl CosmicNotifications {
             let Some(action) = maybe_action else {
                 return self.close(id, CloseReason::Dismissed);
             };
+            self.run_hooks(HookEvent::ActionInvoked, notification, Some(&action));
             let tx = tx.clone();
             tracing::info!("action for {id} {action}");
+            self.actioned.insert(id);
             return Some(Task::future(async move {
                 _ = tx
                     .send(notifications::Input::Activated { token, id, action })
@@ -663,22 +3823,142 @@ impl cosmic::Application for CosmicNotifications {
                 })
             })
             .unwrap_or_default();
+        let state_helper = Config::new_state(
+            cosmic_ext_notifications_config::ID,
+            NotificationsConfig::VERSION,
+        )
+        .ok();
+        let snoozed = state_helper
+            .as_ref()
+            .and_then(|state| state.get::<Vec<(u64, String)>>(SNOOZED_KEY).ok())
+            .map(SnoozeQueue::from_saved)
+            .unwrap_or_default();
+        let stats = state_helper
+            .as_ref()
+            .and_then(|state| state.get::<HistoryStats>(STATS_KEY).ok())
+            .unwrap_or_default();
+        let restore = state_helper
+            .as_ref()
+            .and_then(|state| state.get::<Vec<(u64, String)>>(PENDING_KEY).ok())
+            .map(|saved| restore::from_saved(saved, unix_now()))
+            .unwrap_or_default();
+        let away_at_login = state_helper
+            .as_ref()
+            .and_then(|state| state.get::<u64>(AWAY_KEY).ok())
+            .filter(|secs| *secs != 0)
+            .map(|secs| UNIX_EPOCH + Duration::from_secs(secs));
+        // Read before `config` moves into the app
+        #[cfg(feature = "systemd")]
+        let journal = journal_sink(&config);
+        #[cfg(feature = "webhook")]
+        let webhook = webhook_sink(&config);
+        #[cfg(feature = "email")]
+        let email = email_sink(&config);
+        #[cfg(feature = "plugins")]
+        let plugins_loaded = load_plugins(&config);
+        #[cfg(not(feature = "plugins"))]
+        let plugins_loaded = Task::none();
+        let dnd_since = config.do_not_disturb.then(SystemTime::now);
+
+        #[cfg(feature = "audio")]
+        {
+            cosmic_ext_notifications_util::set_extra_sound_dirs(&config.extra_sound_dirs);
+            cosmic_ext_notifications_util::set_sound_theme(&config.sound_theme);
+            apply_sound_fades(&config);
+        }
         (
             CosmicNotifications {
                 core,
                 active_surface: false,
                 autosize_id: iced::id::Id::new("autosize"),
                 window_id: SurfaceId::unique(),
+                outputs: HashMap::new(),
+                focused_output: None,
+                fullscreen: false,
+                focused_app: None,
+                popup_output: None,
+                banner_id: SurfaceId::unique(),
+                banner_open: false,
+                banner_autosize_id: iced::id::Id::new("banner-autosize"),
+                center_id: SurfaceId::unique(),
+                center_open: false,
+                center_query: String::new(),
+                center_matches: BTreeSet::new(),
                 anchor: None,
                 config,
+                config_helper: helper,
                 dock_config: CosmicPanelConfig::default(),
                 panel_config: CosmicPanelConfig::default(),
                 notifications_id: id::Cards::new("Notifications"),
                 notifications_tx: None,
                 timeline: Timeline::new(),
                 state: NotificationState::new(),
+                focus_session: None,
+                locked_since: None,
+                live: LiveAnnouncer::default(),
+                live_updates: HashMap::new(),
+                arrival: None,
+                reply_drafts: HashMap::new(),
+                images: ImageCache::default(),
+                image_permits: Arc::new(tokio::sync::Semaphore::new(IMAGE_DECODE_WORKERS)),
+                expanded_groups: HashSet::new(),
+                context_menu: None,
+                snooze_picker: None,
+                keyboard: KeyboardInteractivity::None,
+                key_focus: None,
+                hovered: None,
+                pointer_x: None,
+                swipe: None,
+                swiped: None,
+                transitions: Transitions::default(),
+                expiry_deadlines: HashMap::new(),
+                progress_anims: HashMap::new(),
+                repeats: HashMap::new(),
+                storms: StormDetector::default(),
+                digests: DigestQueue::default(),
+                wakeups: Wakeups::default(),
+                #[cfg(feature = "systemd")]
+                journal,
+                #[cfg(feature = "webhook")]
+                webhook,
+                #[cfg(feature = "plugins")]
+                plugins: Arc::default(),
+                revealed: HashSet::new(),
+                dnd_until: None,
+                dnd_since,
+                dnd_summary_id: None,
+                away_summary_id: None,
+                away_at_login,
+                screen_shared: false,
+                #[cfg(feature = "email")]
+                email,
+                #[cfg(feature = "email")]
+                email_digest_sent: None,
+                paused: false,
+                held: Vec::new(),
+                snoozed,
+                restore: Some(restore),
+                pending_saved: Vec::new(),
+                stats,
+                stats_dirty: false,
+                actioned: HashSet::new(),
+                state_helper,
+                control_state: None,
+                capabilities: CompositorCapabilities::detect(),
+                history_collapse: SectionCollapseState::default(),
+                search_index: SearchIndex::default(),
+                #[cfg(feature = "audio")]
+                sounds: HashMap::new(),
+                #[cfg(feature = "audio")]
+                sound_debouncer: SoundDebouncer::default(),
+                #[cfg(feature = "audio")]
+                last_sound_failure: None,
             },
-            Task::none(),
+            Task::batch([
+                cosmic::task::message(cosmic::action::app(Message::Wakeup(Wakeup::ConversationMutes, 0))),
+                cosmic::task::message(cosmic::action::app(Message::Wakeup(Wakeup::Snooze, 0))),
+                plugins_loaded,
+            ]),
         )
     }
 
@@ -698,71 +3978,512 @@ impl cosmic::Application for CosmicNotifications {
     fn update(&mut self, message: Message) -> Task<Self::Message> {
         match message {
             Message::ActivateNotification(id) => {
+                if self.is_swipe_click(id) {
+                    return Task::none();
+                }
                 tracing::trace!("requesting token for {id}");
                 return self.request_activation(id, None);
             }
             Message::ActivationToken(token, id, action) => {
                 tracing::trace!("token for {id}");
-                if let Some(token) = token {
-                    return self
-                        .activate_notification(token, id, action)
-                        .unwrap_or(Task::none());
-                } else {
-                    tracing::error!("Failed to get activation token for clicked notification.");
-                }
+                // Without a token the app isn't raised, but the action must
+                // still reach it
+                let token = token.unwrap_or_else(|| {
+                    tracing::warn!(
+                        "Failed to get activation token for notification {id}, invoking without one"
+                    );
+                    String::new()
+                });
+                return self
+                    .activate_notification(token, id, action)
+                    .unwrap_or(Task::none());
             }
             Message::Notification(e) => match e {
                 notifications::Event::Notification(n) => {
+                    self.record_stat(&n.app_name, Outcome::Received);
+                    self.audit(&n, Outcome::Received);
+                    self.run_hooks(HookEvent::Received, &n, None);
+                    self.forward_to_phone(&n);
+                    #[cfg(feature = "webhook")]
+                    self.post_to_webhook(&n);
+                    if self.holds_back(&n) {
+                        self.hold(n);
+                        return Task::none();
+                    }
                     return self.push_notification(n);
                 }
+                #[cfg(feature = "plugins")]
+                notifications::Event::ToHistory(n) => {
+                    self.record_stat(&n.app_name, Outcome::Received);
+                    self.audit(&n, Outcome::Received);
+                    self.run_hooks(HookEvent::Received, &n, None);
+                    if self.keeps_in_history(&n) {
+                        self.state.add_hidden(n);
+                    }
+                }
+                #[cfg(feature = "plugins")]
+                notifications::Event::DroppedByPlugin(n) => {
+                    self.audit(&n, AuditOutcome::DroppedByPlugin);
+                }
+                // Plugins only change the text of popups already on screen
+                #[cfg(feature = "plugins")]
+                notifications::Event::QuietReplace(n) => {
+                    if self.state.visible().iter().any(|v| v.id == n.id) {
+                        return self.update(Message::Notification(notifications::Event::Replace(n)));
+                    }
+                }
                 notifications::Event::Replace(n) => {
+                    // Snoozed notifications stay snoozed, with the new content
+                    if self.snoozed.replace(n.clone()) {
+                        self.save_snoozed();
+                        return Task::none();
+                    }
+                    // Popups already on screen keep updating while held back
+                    if self.holds_back(&n) && !self.state.visible().iter().any(|v| v.id == n.id) {
+                        self.hold(n);
+                        return Task::none();
+                    }
                     return self.replace_notification(n);
                 }
                 notifications::Event::CloseNotification(id) => {
+                    if let Some(pos) = self.held.iter().position(|n| n.id == id) {
+                        self.held.remove(pos);
+                        self.sync_control_state();
+                    }
+                    if self.snoozed.remove(id) {
+                        self.save_snoozed();
+                    }
                     if let Some(c) = self.close(id, CloseReason::CloseNotification) {
                         return c;
                     }
                 }
                 notifications::Event::Ready(tx) => {
                     self.notifications_tx = Some(tx);
+                    // A reconnected interface starts from scratch
+                    self.control_state = None;
+                    let restore = self.restore_pending();
+                    // After the restore, so what it brings back is counted
+                    let summary = self.show_login_summary();
+                    self.sync_control_state();
+                    self.sync_capabilities();
+                    self.sync_rate_limits();
+                    self.sync_content_rules();
+                    return Task::batch([restore, summary]);
                 }
                 notifications::Event::AppletActivated { id, action } => {
                     tracing::trace!("requesting token for {id}");
                     return self.request_activation(id, Some(action));
                 }
                 notifications::Event::GetHistory { tx } => {
-                    // Send the hidden notifications history
-                    let history: Vec<_> = self.state.hidden().iter().cloned().collect();
+                    // Listeners get history masked or withheld, like
+                    // notifications as they arrive
+                    let history: Vec<_> = self.state.hidden().iter().map(|n| self.shared(n)).collect();
                     if let Err(err) = tx.send(history) {
                         tracing::error!("Failed to send history response: {:?}", err);
                     }
                 }
+                notifications::Event::GetStats { days, tx } => {
+                    if let Err(err) = tx.send(self.stats_report(days)) {
+                        tracing::error!("Failed to send stats response: {:?}", err);
+                    }
+                }
+                notifications::Event::SearchHistory { query, tx } => {
+                    if let Err(err) = tx.send(self.search_history(&query)) {
+                        tracing::error!("Failed to send search results: {:?}", err);
+                    }
+                }
+                notifications::Event::StartFocusSession {
+                    duration,
+                    allowed_apps,
+                } => {
+                    return self.start_focus_session(duration, allowed_apps);
+                }
+                notifications::Event::StopFocusSession => {
+                    return self.end_focus_session();
+                }
+                notifications::Event::GetHistorySections { tx } => {
+                    if let Err(err) = tx.send(self.history_sections()) {
+                        tracing::error!("Failed to send history sections response: {:?}", err);
+                    }
+                }
+                notifications::Event::SetHistorySectionCollapsed { key, collapsed } => {
+                    if let Some(section) = DateSection::from_key(&key) {
+                        self.history_collapse.set_collapsed(section, collapsed);
+                    }
+                }
+                notifications::Event::SetSoundVolume(volume) => {
+                    self.set_sound_volume(volume);
+                }
+                notifications::Event::ClearHistory => {
+                    self.state.clear_hidden();
+                }
+                notifications::Event::ToggleDnd => {
+                    return self.set_dnd(!self.config.do_not_disturb, None);
+                }
+                notifications::Event::SetDnd { enabled, duration } => {
+                    return self.set_dnd(enabled, duration);
+                }
+                notifications::Event::SetDndMode { mode, duration } => {
+                    return self.set_dnd_mode(mode, duration);
+                }
+                notifications::Event::SetPaused(paused) => {
+                    return self.set_paused(paused);
+                }
+                notifications::Event::SetCriticalBypass(enabled) => {
+                    self.set_critical_bypass(enabled);
+                }
+                notifications::Event::Snooze { id, duration, tx } => {
+                    let task = self.snooze(id, duration);
+                    if let Err(err) = tx.send(task.is_some()) {
+                        tracing::error!("Failed to send snooze response: {:?}", err);
+                    }
+                    if let Some(task) = task {
+                        return task;
+                    }
+                }
+                notifications::Event::ToggleCenter => {
+                    return self.toggle_center();
+                }
+                notifications::Event::FocusPopups => {
+                    return self.focus_popups();
+                }
+                notifications::Event::CloseAll => {
+                    return self.dismiss_where(|_| true);
+                }
+                notifications::Event::CloseApp(app) => {
+                    return self.dismiss_where(|n| n.is_from_app(&app));
+                }
+                notifications::Event::InvokeAction { id, action, tx } => {
+                    let found = self.state.visible().iter().find(|n| n.id == id).is_some_and(|n| {
+                        action
+                            .as_ref()
+                            .is_none_or(|a| n.actions.iter().any(|(b, _)| b == a))
+                    });
+                    if let Err(err) = tx.send(found) {
+                        tracing::error!("Failed to send invoke action response: {:?}", err);
+                    }
+                    if found {
+                        return self.request_activation(id, action);
+                    }
+                }
+                notifications::Event::RateLimited(dropped) => {
+                    self.audit_rate_limited(&dropped);
+                    return self.show_rate_limit_summary(dropped);
+                }
+                notifications::Event::DebugDump { tx } => {
+                    if let Err(err) = tx.send(self.debug_dump()) {
+                        tracing::error!("Failed to send debug dump response: {:?}", err);
+                    }
+                }
+                notifications::Event::GetFocusSession { tx } => {
+                    if let Err(err) = tx.send(self.focus_session_remaining()) {
+                        tracing::error!("Failed to send focus session response: {:?}", err);
+                    }
+                }
             },
+            Message::Session(session::Event::Locked) => {
+                let since = *self.locked_since.get_or_insert_with(SystemTime::now);
+                self.save_away_since(Some(since));
+                self.sync_content_rules();
+                self.wipe_history(false);
+            }
+            Message::Session(session::Event::Ending) => {
+                self.save_stats();
+                self.save_away_since(Some(self.locked_since.unwrap_or_else(SystemTime::now)));
+                self.wipe_history(true);
+            }
+            Message::Session(session::Event::Unlocked) => {
+                let summary = self.show_unlock_summary();
+                self.sync_content_rules();
+                return summary;
+            }
+            Message::ScreenShare(sharing) => {
+                return self.screen_share_changed(sharing);
+            }
+            Message::DndExpired => {
+                // A later SetDnd may have moved or cleared the deadline
+                let slack = Duration::from_millis(EXPIRY_SLACK_MS);
+                if self.dnd_until.is_some_and(|until| until <= Instant::now() + slack) {
+                    return self.set_dnd(false, None);
+                }
+            }
+            Message::FocusSessionEnded => {
+                // A newer session may have replaced the one this timer belonged to
+                if self.focus_session.as_ref().is_some_and(FocusSession::is_expired) {
+                    return self.end_focus_session();
+                }
+            }
             Message::Dismissed(id) => {
+                if self.leave(id, Exit::Dismissed) {
+                    return Task::none();
+                }
                 if let Some(c) = self.close(id, CloseReason::Dismissed) {
                     return c;
                 }
             }
+            Message::DismissAll => {
+                return self.dismiss_where(|_| true);
+            }
+            Message::ToggleCenter => {
+                return self.toggle_center();
+            }
+            Message::CenterRemove(id) => {
+                self.state.remove_notification(id);
+            }
+            Message::CenterClearAll => {
+                self.state.clear_hidden();
+            }
+            Message::CenterDnd(enabled) => {
+                return self.set_dnd(enabled, None);
+            }
+            Message::CenterPriorityOnly(enabled) => {
+                let mode = match (enabled, self.config.do_not_disturb) {
+                    (true, _) => DndMode::PriorityOnly,
+                    (false, true) => DndMode::Full,
+                    (false, false) => DndMode::Open,
+                };
+                return self.set_dnd_mode(mode, None);
+            }
+            Message::CenterSearch(query) => {
+                self.center_matches = self.search_ids(&query);
+                self.center_query = query;
+            }
+            Message::CenterToggleSection(section) => {
+                let collapsed = self.history_collapse.is_collapsed(section);
+                self.history_collapse.set_collapsed(section, !collapsed);
+            }
+            Message::DismissApp(app) => {
+                return self.dismiss_where(|n| n.app_name == app);
+            }
+            Message::Output(output, name) => match name {
+                Some(name) => {
+                    self.outputs.insert(name, output);
+                }
+                None => self.outputs.retain(|_, known| *known != output),
+            },
+            Message::FocusedWindow(window) => {
+                self.fullscreen = window.fullscreen;
+                let mut tasks = vec![self.release_held()];
+                if window.app_id != self.focused_app {
+                    self.focused_app = window.app_id;
+                    if let Some(app_id) = self.focused_app.clone() {
+                        tasks.push(self.dismiss_focused(&app_id));
+                    }
+                }
+                if let Some(output) = window.output {
+                    tasks.push(self.follow_focus(output));
+                }
+                return Task::batch(tasks);
+            }
+            Message::ContextMenu(id) => {
+                // "Copy text" would give hidden content away
+                self.context_menu =
+                    (self.context_menu != Some(id) && !self.hides_content()).then_some(id);
+            }
+            Message::Menu(id, item) => {
+                return self.menu_selected(id, item);
+            }
+            Message::SnoozePicker(id) => {
+                self.context_menu = None;
+                self.snooze_picker = (self.snooze_picker != Some(id)).then_some(id);
+            }
+            Message::Snooze(id, preset) => {
+                self.snooze_picker = None;
+                let due = snooze_due(preset, &chrono::Local::now());
+                if let Some(task) = self.snooze_until(id, due) {
+                    return task;
+                }
+            }
+            Message::GroupToggle(key) => {
+                self.toggle_group(key);
+                return self.sync_keyboard();
+            }
+            Message::GroupClear(key) => {
+                return self.clear_group(&key);
+            }
+            Message::ImageDecoded(id, ticket, decoded) => {
+                self.images.finish(id, ticket, decoded);
+            }
+            Message::CardHovered(id, hovered) => {
+                return self.hover_card(id, hovered);
+            }
+            Message::Pointer(surface, input) => {
+                if surface == self.window_id {
+                    self.swipe_pointer(input);
+                }
+            }
+            Message::Key(surface, input) => {
+                if surface == self.window_id {
+                    return self.key_pressed(input);
+                }
+            }
+            Message::ReplyOpen(id) => {
+                return self.open_reply(id);
+            }
+            Message::ReplyChanged(id, text) => {
+                // Only while the field is open
+                if let Some(draft) = self.reply_drafts.get_mut(&id) {
+                    *draft = text;
+                }
+            }
+            Message::ReplyCancel(id) => {
+                return self.cancel_reply(id);
+            }
+            Message::ReplySubmit(id) => {
+                if let Some(task) = self.send_reply(id) {
+                    return task;
+                }
+            }
+            Message::MuteConversation(key) => {
+                return self.mute_conversation(key);
+            }
+            Message::Wakeup(wakeup, generation) => {
+                // A timer since replaced by a newer one
+                if !self.wakeups.is_current(wakeup, generation) {
+                    return Task::none();
+                }
+                return match wakeup {
+                    Wakeup::Snooze => self.wake_snoozed(),
+                    Wakeup::ConversationMutes => self.expire_conversation_mutes(),
+                    Wakeup::Storms => self.end_storms(),
+                    Wakeup::Digests => self.deliver_digests(),
+                };
+            }
+            #[cfg(feature = "email")]
+            Message::EmailDigestDue => {
+                self.email_digest();
+            }
+            Message::SaveStats => {
+                self.save_stats();
+            }
+            #[cfg(feature = "audio")]
+            Message::Playback(event) => match event {
+                PlaybackEvent::Started { path } => tracing::trace!("Playing sound {:?}", path),
+                PlaybackEvent::Dropped { path, reason } => {
+                    tracing::debug!("Sound {:?} skipped: {:?}", path, reason);
+                }
+                PlaybackEvent::Failed { path, error } => {
+                    self.last_sound_failure = Some(format!("{}: {}", path.display(), error));
+                }
+            },
             Message::Timeout(id) => {
-                self.expire(id);
-                if self.state.is_empty() && self.active_surface {
-                    self.active_surface = false;
-                    return destroy_layer_surface(self.window_id);
+                // A replacement rescheduled this popup, a later timeout handles it
+                if self.expiry_deadlines.get(&id).is_some_and(|deadline| {
+                    deadline.saturating_duration_since(Instant::now()) > Duration::from_millis(EXPIRY_SLACK_MS)
+                }) {
+                    return Task::none();
+                }
+                // Hovered, leaving the popup schedules a new timeout
+                if self.hovered.is_some_and(|(h, _)| h == id) {
+                    return Task::none();
+                }
+                self.expiry_deadlines.remove(&id);
+                // Don't pull the popup away while a reply is being typed or
+                // the popups are navigated by keyboard; cancelling the reply
+                // or leaving the popups starts the timeout over
+                if self.reply_drafts.contains_key(&id) || self.key_focus.is_some() {
+                    return Task::none();
+                }
+                if self.leave(id, Exit::Expired) {
+                    return Task::none();
                 }
+                self.expire(id);
+                return self.close_surface_if_empty();
             }
             Message::Config(config) => {
+                #[cfg(feature = "audio")]
+                if config.extra_sound_dirs != self.config.extra_sound_dirs {
+                    cosmic_ext_notifications_util::set_extra_sound_dirs(&config.extra_sound_dirs);
+                }
+                #[cfg(feature = "audio")]
+                if config.sound_theme != self.config.sound_theme {
+                    cosmic_ext_notifications_util::set_sound_theme(&config.sound_theme);
+                }
+                #[cfg(feature = "audio")]
+                apply_sound_fades(&config);
+                // Changed elsewhere, so a pending timed DND no longer applies
+                let dnd_changed = config.do_not_disturb != self.config.do_not_disturb;
+                if dnd_changed {
+                    self.dnd_until = None;
+                }
+                if !config.screen_share_dnd {
+                    self.screen_shared = false;
+                }
+                #[cfg(feature = "systemd")]
+                if config.journal_audit != self.config.journal_audit {
+                    self.journal = journal_sink(&config);
+                }
+                #[cfg(feature = "webhook")]
+                if config.webhook != self.config.webhook {
+                    self.webhook = webhook_sink(&config);
+                }
+                #[cfg(feature = "email")]
+                if config.email_digest != self.config.email_digest {
+                    self.email = email_sink(&config);
+                }
+                #[cfg(feature = "plugins")]
+                let plugins_loaded = if config.plugins != self.config.plugins {
+                    load_plugins(&config)
+                } else {
+                    Task::none()
+                };
+                #[cfg(not(feature = "plugins"))]
+                let plugins_loaded = Task::none();
                 self.config = config;
+                self.sync_control_state();
+                self.sync_capabilities();
+                self.sync_rate_limits();
+                self.sync_content_rules();
+                // Image settings may have changed how popup images look
+                let images = self.decode_visible_images();
+                if dnd_changed {
+                    return Task::batch([
+                        images,
+                        plugins_loaded,
+                        self.dnd_changed(self.config.do_not_disturb),
+                    ]);
+                }
+                return Task::batch([images, plugins_loaded]);
+            }
+            #[cfg(feature = "plugins")]
+            Message::PluginsLoaded(plugins) => {
+                self.plugins = plugins;
+                self.sync_content_rules();
             }
             Message::PanelConfig(c) => {
                 self.panel_config = c;
                 self.anchor = Some(self.anchor_for_notification_applet());
+                return self.sync_margins();
             }
             Message::DockConfig(c) => {
                 self.dock_config = c;
                 self.anchor = Some(self.anchor_for_notification_applet());
+                return self.sync_margins();
             }
             Message::Frame(now) => {
                 self.timeline.now(now);
+                self.progress_anims.retain(|_, anim| anim.is_animating());
+                let slide = Duration::from_millis(SWIPE_OUT_MS);
+                if let Some(swipe) = self.swipe.filter(|swipe| swipe.has_left(now, slide)) {
+                    self.swipe = None;
+                    if let Some(task) = self.close(swipe.id, CloseReason::Dismissed) {
+                        return task;
+                    }
+                }
+                // Popups that finished their exit go the way they were sent
+                let duration = self.transition_duration().unwrap_or_default();
+                let gone = self.transitions.finished(now, duration);
+                if !gone.is_empty() {
+                    let mut tasks = Vec::new();
+                    for (id, exit) in gone {
+                        match exit {
+                            Exit::Expired => self.expire(id),
+                            Exit::Dismissed => tasks.extend(self.close(id, CloseReason::Dismissed)),
+                        }
+                    }
+                    tasks.push(self.close_surface_if_empty());
+                    return Task::batch(tasks);
+                }
             }
             Message::Ignore => {}
             Message::Surface(a) => {
@@ -780,6 +4501,23 @@ impl cosmic::Application for CosmicNotifications {
                     tracing::warn!("Blocked unsafe URL: {}", url);
                 }
             }
+            Message::OpenUri(uri) => {
+                // tel: and mailto: go through the OpenURI portal so the
+                // user's preferred dialer or mail client is used
+                tokio::spawn(async move {
+                    if let Err(err) = crate::portal::open_uri(&uri).await {
+                        tracing::error!("Failed to open {}: {}", uri, err);
+                    }
+                });
+            }
+            Message::CopyToClipboard(contents) => {
+                return cosmic::iced::clipboard::write(contents);
+            }
+            Message::ToggleSecrets(id) => {
+                if !self.revealed.remove(&id) {
+                    self.revealed.insert(id);
+                }
+            }
             Message::ActionClicked(id, action_id) => {
                 // Handle action button click - request activation with the action
                 tracing::trace!("action clicked for {id}: {action_id}");
@@ -790,8 +4528,14 @@ impl cosmic::Application for CosmicNotifications {
     }
 
     #[allow(clippy::too_many_lines)]
-    fn view_window(&self, _: SurfaceId) -> Element<'_, Message> {
-        if self.state.is_empty() {
+    fn view_window(&self, id: SurfaceId) -> Element<'_, Message> {
+        if id == self.center_id {
+            return self.view_center();
+        }
+        if id == self.banner_id {
+            return self.view_banner();
+        }
+        if !self.has_popups() {
             return container(vertical_space().height(Length::Fixed(1.0)))
                 .center_x(Length::Fixed(1.0))
                 .center_y(Length::Fixed(1.0))
@@ -802,17 +4546,60 @@ impl cosmic::Application for CosmicNotifications {
         let card_config = RichCardConfig::from_notifications_config(&self.config);
 
         let (ids, notif_elems): (Vec<_>, Vec<_>) = self
-            .state
-            .visible()
+            .popup_groups()
             .iter()
-            .rev()
-            .map(|n| {
-                let e = self.render_rich_notification(n, &card_config);
+            .filter_map(|group| Some((group, group.newest()?)))
+            .map(|(group, n)| {
+                // Hovering holds the popup's timeout, dragging shifts it
+                let mut card = if group.count() > 1 {
+                    self.render_group(group, &card_config)
+                } else {
+                    self.render_rich_notification(n, &card_config)
+                };
+                if let Some(duration) = self.transition_duration() {
+                    card = render_transition(
+                        card,
+                        self.config.animation_style,
+                        self.transitions.shown(n.id, Instant::now(), duration),
+                        self.slides_from_left(),
+                    );
+                }
+                if let Some(swipe) = self.swipe.filter(|swipe| swipe.id == n.id) {
+                    let offset = swipe.offset(
+                        Instant::now(),
+                        NOTIFICATION_WIDTH,
+                        Duration::from_millis(SWIPE_OUT_MS),
+                    );
+                    card = container(card)
+                        .padding(iced::Padding {
+                            left: offset.max(0.0),
+                            right: (-offset).max(0.0),
+                            ..iced::Padding::ZERO
+                        })
+                        .into();
+                }
+                if self.context_menu == Some(n.id) {
+                    card = column![card, self.render_context_menu(n)].spacing(4).into();
+                }
+                if self.snooze_picker == Some(n.id) {
+                    card = column![card, self.render_snooze_picker(n)].spacing(4).into();
+                }
+                let e: Element<'static, Message> = mouse_area(card)
+                    .on_right_press(Message::ContextMenu(n.id))
+                    .on_enter(Message::CardHovered(n.id, true))
+                    .on_exit(Message::CardHovered(n.id, false))
+                    .into();
                 (n.id, e)
             })
-            .take(self.effective_max_notifications())
             .unzip();
 
+        let popup_count = self
+            .state
+            .visible()
+            .iter()
+            .filter(|n| !self.in_banner(n))
+            .count();
+
         // Card list with animations - width increased from 300px to 380px
         // for rich notifications with images and progress bars.
         // The anim! macro handles smooth entry/exit animations based on card
@@ -833,8 +4620,36 @@ impl cosmic::Application for CosmicNotifications {
         )
         .width(Length::Fixed(NOTIFICATION_WIDTH));
 
+        let content: Element<'_, Message> = if popup_count > 1 {
+            column![
+                card_list,
+                container(
+                    button::text(crate::fl!("dismiss-all"))
+                        .on_press(Message::DismissAll)
+                        .padding([6, 12]),
+                )
+                .align_right(Length::Fixed(NOTIFICATION_WIDTH)),
+            ]
+            .spacing(8)
+            .into()
+        } else {
+            card_list.into()
+        };
+
+        // New popups are announced through a region that stays in place,
+        // since screen readers only speak a live region when it changes
+        let (arrival, politeness) = match &self.arrival {
+            Some(update) => (live_announcement(update), update.politeness),
+            None => (String::new(), Politeness::Polite),
+        };
+        let content: Element<'_, Message> = column![
+            live_region("live-region-arrivals".to_string(), arrival, politeness),
+            content,
+        ]
+        .into();
+
         // Autosize container configuration
-        autosize::autosize(card_list, self.autosize_id.clone())
+        autosize::autosize(content, self.autosize_id.clone())
             .min_width(AUTOSIZE_MIN_WIDTH)
             .min_height(AUTOSIZE_MIN_HEIGHT)
             .max_width(NOTIFICATION_WIDTH)
@@ -843,7 +4658,56 @@ impl cosmic::Application for CosmicNotifications {
     }
 
     fn subscription(&self) -> Subscription<Message> {
+        // Progress bars, swiped popups and popups entering or leaving aren't
+        // driven by the timeline, so redraw while one eases
+        let sliding = self.swipe.is_some_and(|swipe| swipe.is_leaving())
+            || self.transitions.is_animating();
+        let progress_frames = if self.progress_anims.is_empty() && !sliding {
+            Subscription::none()
+        } else {
+            cosmic::iced::time::every(Duration::from_millis(PROGRESS_FRAME_MS)).map(Message::Frame)
+        };
+        // GIFs and APNGs play at a capped rate, and only for a while
+        let animation_frames = if self.config.enable_animations
+            && self.images.playing(Instant::now(), Duration::from_millis(ANIMATION_PLAY_MS))
+        {
+            cosmic::iced::time::every(Duration::from_millis(ANIMATION_FRAME_MS)).map(Message::Frame)
+        } else {
+            Subscription::none()
+        };
+
+        // "2 min ago" only needs to move on now and then, and only while
+        // something shows it
+        let timestamp_frames = if self.has_popups() || self.center_open {
+            cosmic::iced::time::every(Duration::from_millis(TIMESTAMP_REFRESH_MS)).map(Message::Frame)
+        } else {
+            Subscription::none()
+        };
+
+        // The email digest is only due while the user is away
+        #[cfg(feature = "email")]
+        let email_digest = if self.config.email_digest.is_some() && self.away_since().is_some() {
+            cosmic::iced::time::every(Duration::from_secs(EMAIL_DIGEST_CHECK_SECS))
+                .map(|_| Message::EmailDigestDue)
+        } else {
+            Subscription::none()
+        };
+        #[cfg(not(feature = "email"))]
+        let email_digest = Subscription::none();
+
+        // Statistics are saved a while after they change
+        let stats_save = if self.stats_dirty {
+            cosmic::iced::time::every(Duration::from_secs(STATS_SAVE_SECS)).map(|_| Message::SaveStats)
+        } else {
+            Subscription::none()
+        };
+
         Subscription::batch(vec![
+            progress_frames,
+            animation_frames,
+            timestamp_frames,
+            email_digest,
+            stats_save,
             self.core
                 .watch_config(cosmic_ext_notifications_config::ID)
                 .map(|u| {
@@ -884,6 +4748,112 @@ impl cosmic::Application for CosmicNotifications {
                 .as_subscription()
                 .map(|(_, now)| Message::Frame(now)),
             notifications::notifications().map(Message::Notification),
+            cosmic::iced::event::listen_with(output_event),
+            if self.capabilities.toplevel_info
+                && (self.config.popup_placement == PopupPlacement::FollowFocus
+                    || self.config.fullscreen_popups != FullscreenPopups::ShowAnyway
+                    || self.config.dismiss_on_focus)
+            {
+                focused_window().map(Message::FocusedWindow)
+            } else {
+                Subscription::none()
+            },
+            session::lock_state().map(Message::Session),
+            if self.config.screen_share_dnd {
+                screen_share::sharing().map(Message::ScreenShare)
+            } else {
+                Subscription::none()
+            },
+            if self.active_surface {
+                cosmic::iced::event::listen_with(pointer_input)
+            } else {
+                Subscription::none()
+            },
+            if self.key_focus.is_some() {
+                cosmic::iced::event::listen_with(key_input)
+            } else {
+                Subscription::none()
+            },
+            #[cfg(feature = "audio")]
+            sound::playback().map(Message::Playback),
         ])
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn notification(urgency: u8) -> Notification {
+        Notification {
+            id: 1,
+            app_name: "Alarm".to_string(),
+            app_icon: String::new(),
+            summary: "Wake up".to_string(),
+            body: String::new(),
+            actions: Vec::new(),
+            hints: vec![Hint::Urgency(urgency)],
+            expire_timeout: -1,
+            applied_timeout: None,
+            time: SystemTime::now(),
+        }
+    }
+
+    #[test]
+    fn test_replacement_moves_between_popups_and_banner() {
+        let mut config = NotificationsConfig {
+            critical_banner: true,
+            ..Default::default()
+        };
+        // Raised to critical, it moves to the banner, and back when lowered
+        assert!(!shows_in_banner(&config, &notification(1)));
+        assert!(shows_in_banner(&config, &notification(2)));
+
+        // Without the banner critical notifications stay popups
+        config.critical_banner = false;
+        assert!(!shows_in_banner(&config, &notification(2)));
+    }
+
+    #[test]
+    fn test_codes_are_not_announced() {
+        let config = NotificationsConfig::default();
+        let mut live = LiveAnnouncer::default();
+        let old = Notification {
+            body: "Your verification code is 482913".to_string(),
+            ..notification(1)
+        };
+        let shown = masked(&config, &HashSet::new(), &old).unwrap();
+        let arrived = live_announcement(&live.arrived(&shown));
+        assert!(arrived.contains("Wake up"));
+        assert!(!arrived.contains("482913"));
+
+        let new = Notification {
+            body: "Your verification code is 975310".to_string(),
+            ..notification(1)
+        };
+        let replaced = masked(&config, &HashSet::new(), &new).unwrap();
+        // Masked alike, the new code gives nothing to announce
+        assert!(live.replaced(&shown, &replaced).is_none());
+
+        // Revealed codes are read like the rest of the card
+        let revealed = HashSet::from([old.id]);
+        assert!(masked(&config, &revealed, &old).is_none());
+    }
+
+    #[test]
+    fn test_search_cannot_probe_masked_codes() {
+        let config = NotificationsConfig::default();
+        let n = Notification {
+            body: "Your verification code is 482913".to_string(),
+            ..notification(1)
+        };
+        let shared = || vec![notifications::shared(&config, false, &n)];
+        assert_eq!(search(shared(), "verification").len(), 1);
+        assert!(search(shared(), "4829").is_empty());
+        assert!(search(shared(), "482913").is_empty());
+
+        // Nothing of withheld text can be found either
+        let withheld = vec![notifications::shared(&config, true, &n)];
+        assert!(search(withheld, "verification").is_empty());
+    }
+}
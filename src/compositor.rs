@@ -0,0 +1,121 @@
+//! Detection of optional Wayland protocols offered by the compositor
+//!
+//! The daemon relies on a few protocols that not every compositor provides.
+//! They are probed once at startup so missing ones can be worked around
+//! instead of failing later when a surface or token is requested.
+
+use anyhow::Context;
+use sctk::reexports::client::{
+    Connection, Dispatch, QueueHandle,
+    globals::{GlobalListContents, registry_queue_init},
+    protocol::wl_registry,
+};
+
+/// Interface name of the layer-shell protocol used for notification popups
+const LAYER_SHELL: &str = "zwlr_layer_shell_v1";
+
+/// Interface name of the activation protocol used to focus apps on click
+const ACTIVATION: &str = "xdg_activation_v1";
+
+/// Interface names of toplevel info protocols used for focus detection
+const TOPLEVEL_INFO: &[&str] = &["ext_foreign_toplevel_list_v1", "zcosmic_toplevel_info_v1"];
+
+/// Protocols available from the running compositor
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CompositorCapabilities {
+    /// Popups can be shown as layer surfaces
+    pub layer_shell: bool,
+    /// Activation tokens can be requested when a notification is clicked
+    pub activation: bool,
+    /// Toplevel windows can be tracked for focus detection
+    pub toplevel_info: bool,
+}
+
+impl Default for CompositorCapabilities {
+    /// Assume everything is available when the compositor cannot be probed
+    fn default() -> Self {
+        Self {
+            layer_shell: true,
+            activation: true,
+            toplevel_info: true,
+        }
+    }
+}
+
+struct RegistryState;
+
+impl Dispatch<wl_registry::WlRegistry, GlobalListContents> for RegistryState {
+    fn event(
+        _state: &mut Self,
+        _registry: &wl_registry::WlRegistry,
+        _event: wl_registry::Event,
+        _data: &GlobalListContents,
+        _conn: &Connection,
+        _qh: &QueueHandle<Self>,
+    ) {
+    }
+}
+
+impl CompositorCapabilities {
+    /// Probe the compositor's globals for the protocols the daemon uses
+    ///
+    /// Uses a short-lived Wayland connection separate from the one owned by
+    /// the UI toolkit. Missing protocols are logged once here, and
+    /// everything is assumed available if the compositor can't be probed.
+    pub fn detect() -> Self {
+        match Self::probe() {
+            Ok(capabilities) => {
+                capabilities.log_missing();
+                capabilities
+            }
+            Err(err) => {
+                tracing::warn!("Failed to probe Wayland protocols: {:#}", err);
+                Self::default()
+            }
+        }
+    }
+
+    /// Probe the compositor's globals, failing if it can't be reached
+    pub fn probe() -> anyhow::Result<Self> {
+        let conn = Connection::connect_to_env().context("failed to connect to Wayland")?;
+        let (globals, _queue) = registry_queue_init::<RegistryState>(&conn)
+            .context("failed to read Wayland globals")?;
+
+        let has = |interface: &str| {
+            globals
+                .contents()
+                .with_list(|list| list.iter().any(|g| g.interface == interface))
+        };
+
+        Ok(Self {
+            layer_shell: has(LAYER_SHELL),
+            activation: has(ACTIVATION),
+            toplevel_info: TOPLEVEL_INFO.iter().any(|interface| has(interface)),
+        })
+    }
+
+    fn log_missing(&self) {
+        if !self.layer_shell {
+            tracing::error!(
+                "Compositor does not support {LAYER_SHELL}, popups are disabled and notifications only go to history"
+            );
+        }
+        if !self.activation {
+            tracing::warn!(
+                "Compositor does not support {ACTIVATION}, actions are invoked without activation tokens"
+            );
+        }
+        if !self.toplevel_info {
+            tracing::warn!("Compositor does not support toplevel info, focus detection is disabled");
+        }
+    }
+
+    /// Capability set as JSON for the debug dump
+    pub fn to_json(self) -> serde_json::Value {
+        serde_json::json!({
+            "layer_shell": self.layer_shell,
+            "activation": self.activation,
+            "toplevel_info": self.toplevel_info,
+        })
+    }
+}
@@ -1,6 +1,23 @@
+use cosmic::cosmic_config::CosmicConfigEntry;
+use cosmic_ext_notifications_config::NotificationsConfig;
+use std::path::PathBuf;
+
 pub const APP_ID: &str = "io.github.olafkfreund.CosmicExtNotifications";
 pub const VERSION: &str = env!("CARGO_PKG_VERSION");
 
+/// File cosmic-config keeps the per-app rules in
+pub fn app_rules_path() -> PathBuf {
+    let config_home = std::env::var_os("XDG_CONFIG_HOME")
+        .map(PathBuf::from)
+        .or_else(|| std::env::var_os("HOME").map(|home| PathBuf::from(home).join(".config")))
+        .unwrap_or_default();
+    config_home
+        .join("cosmic")
+        .join(cosmic_ext_notifications_config::ID)
+        .join(format!("v{}", NotificationsConfig::VERSION))
+        .join("app_rules")
+}
+
 pub fn profile() -> &'static str {
     std::env!("OUT_DIR")
         .split(std::path::MAIN_SEPARATOR)
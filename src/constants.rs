@@ -17,6 +17,14 @@ pub(crate) const NOTIFICATION_MAX_HEIGHT: f32 = 1920.0;
 /// Margin around notifications (pixels)
 pub(crate) const NOTIFICATION_MARGIN: i32 = 8;
 
+/// Thickness of a panel or dock at each configured size, icons and
+/// padding included (pixels)
+pub(crate) const PANEL_THICKNESS_XS: i32 = 32;
+pub(crate) const PANEL_THICKNESS_S: i32 = 40;
+pub(crate) const PANEL_THICKNESS_M: i32 = 48;
+pub(crate) const PANEL_THICKNESS_L: i32 = 56;
+pub(crate) const PANEL_THICKNESS_XL: i32 = 64;
+
 /// Minimum width for autosize mode
 pub(crate) const AUTOSIZE_MIN_WIDTH: f32 = 200.0;
 
@@ -26,6 +34,15 @@ pub(crate) const AUTOSIZE_MIN_HEIGHT: f32 = 100.0;
 /// Padding inside notification cards
 pub(crate) const CARD_PADDING: u16 = 12;
 
+/// Padding inside notification cards with compact density
+pub(crate) const CARD_PADDING_COMPACT: u16 = 6;
+
+/// Maximum characters of the body shown with compact density
+pub(crate) const COMPACT_BODY_MAX_LENGTH: usize = 60;
+
+/// Height of the notification center panel
+pub(crate) const CENTER_HEIGHT: f32 = 560.0;
+
 // ============================================================================
 // Icon Size Constants
 // ============================================================================
@@ -36,6 +53,9 @@ pub(crate) const ICON_SIZE_SMALL: u16 = 16;
 /// Large icon size (e.g., for fallback icons)
 pub(crate) const ICON_SIZE_LARGE: u16 = 96;
 
+/// App badge size drawn over a sender avatar
+pub(crate) const AVATAR_BADGE_SIZE: u16 = 32;
+
 // ============================================================================
 // Text Display Constants
 // ============================================================================
@@ -46,6 +66,12 @@ pub(crate) const APP_NAME_MAX_LENGTH: usize = 24;
 /// Maximum visible action buttons on a notification card
 pub(crate) const MAX_VISIBLE_ACTIONS: usize = 3;
 
+/// Maximum contacts (phone numbers/emails) offered as quick actions per card
+pub(crate) const MAX_CONTACT_ACTIONS: usize = 1;
+
+/// Prefix added to a quoted line once per blockquote level
+pub(crate) const QUOTE_MARKER: &str = "▎ ";
+
 // ============================================================================
 // Notification Queue Constants
 // ============================================================================
@@ -56,19 +82,109 @@ pub(crate) const MAX_HIDDEN_MEMORY: usize = 50 * 1024 * 1024;
 /// Initial capacity for notification cards vector
 pub(crate) const INITIAL_CARDS_CAPACITY: usize = 50;
 
+/// How long a replaced notification's progress bar takes to reach its new value (ms)
+pub(crate) const PROGRESS_ANIMATION_MS: u64 = 300;
+
+/// How often relative timestamps on popups and in the center are
+/// refreshed (ms)
+pub(crate) const TIMESTAMP_REFRESH_MS: u64 = 30_000;
+
+/// Redraw interval while a progress bar is animating (ms)
+pub(crate) const PROGRESS_FRAME_MS: u64 = 16;
+
+/// Redraw interval while an animated image is playing (ms)
+///
+/// Caps GIF and APNG playback at 25 frames per second whatever the file asks for.
+pub(crate) const ANIMATION_FRAME_MS: u64 = 40;
+
+/// How long an animated image plays before settling on its first frame (ms)
+pub(crate) const ANIMATION_PLAY_MS: u64 = 30_000;
+
+/// Timeouts firing this close to their deadline still expire the popup (ms)
+pub(crate) const EXPIRY_SLACK_MS: u64 = 50;
+
+/// Least time a popup stays up after the pointer leaves it (ms)
+pub(crate) const HOVER_RESUME_MIN_MS: u64 = 1000;
+
+/// How far a popup has to be dragged sideways to dismiss it (pixels)
+pub(crate) const SWIPE_DISMISS_DISTANCE: f32 = 120.0;
+
+/// Drags shorter than this still count as a click (pixels)
+pub(crate) const SWIPE_SLOP: f32 = 8.0;
+
+/// How long a swiped popup takes to slide off (ms)
+pub(crate) const SWIPE_OUT_MS: u64 = 150;
+
+/// Outline around a popup focused from the keyboard (pixels)
+pub(crate) const KEY_FOCUS_BORDER_WIDTH: f32 = 2.0;
+
+/// Width of the critical notification banner (pixels)
+pub(crate) const BANNER_WIDTH: f32 = 560.0;
+
+/// Outline around the critical notification banner (pixels)
+pub(crate) const BANNER_BORDER_WIDTH: f32 = 3.0;
+
+/// Popup images decoded at the same time, each on a blocking thread
+pub(crate) const IMAGE_DECODE_WORKERS: usize = 2;
+
 // ============================================================================
 // Rate Limiting Constants
 // ============================================================================
 
-/// Maximum notifications per minute per app
-pub(crate) const RATE_LIMIT_PER_MINUTE: u32 = 60;
-
 /// Maximum number of apps tracked by rate limiter
 pub(crate) const RATE_LIMIT_MAX_APPS: usize = 1000;
 
 /// Interval for rate limiter cleanup (in notification count)
 pub(crate) const RATE_LIMIT_CLEANUP_INTERVAL: u64 = 100;
 
+/// How many times an app's limit its critical notifications get, in a
+/// bucket of their own
+pub(crate) const CRITICAL_RATE_LIMIT_FACTOR: u32 = 4;
+
+// ============================================================================
+// Storm Detection Constants
+// ============================================================================
+
+/// Window in which an app's notifications count towards a storm (seconds)
+pub(crate) const STORM_WINDOW_SECS: u64 = 30;
+
+/// Notifications within the window that make a storm if they arrive like
+/// clockwork or carry random-looking bodies
+pub(crate) const STORM_MIN_BURST: usize = 6;
+
+/// Notifications within the window that make a storm regardless
+pub(crate) const STORM_HARD_BURST: usize = 15;
+
+/// How far gaps between notifications may stray from their average and
+/// still count as clockwork (fraction of the average)
+pub(crate) const STORM_INTERVAL_JITTER: f32 = 0.15;
+
+/// Character entropy from which a body reads as random data (bits per char)
+pub(crate) const STORM_ENTROPY_BITS: f32 = 4.5;
+
+/// Shortest body checked for random data (chars)
+pub(crate) const STORM_ENTROPY_MIN_CHARS: usize = 24;
+
+/// Icon of the notices about quieted apps
+pub(crate) const STORM_ICON: &str = "dialog-warning-symbolic";
+
+// ============================================================================
+// Sender Identity Constants
+// ============================================================================
+
+/// Maximum number of bus connections whose identity is cached
+pub(crate) const SENDER_CACHE_MAX: usize = 256;
+
+// ============================================================================
+// Portal Backend Constants
+// ============================================================================
+
+/// Maximum number of portal notifications remembered for routing actions
+pub(crate) const PORTAL_MAX_TRACKED: usize = 256;
+
+/// Largest portal notification icon read from a file descriptor (bytes)
+pub(crate) const PORTAL_ICON_MAX_BYTES: u64 = 4 * 1024 * 1024;
+
 // ============================================================================
 // Channel and Buffer Constants
 // ============================================================================
@@ -76,6 +192,60 @@ pub(crate) const RATE_LIMIT_CLEANUP_INTERVAL: u64 = 100;
 /// Buffer size for notification channel
 pub(crate) const CHANNEL_BUFFER_SIZE: usize = 100;
 
+/// Buffer size for the critical notification lane
+pub(crate) const URGENT_CHANNEL_BUFFER_SIZE: usize = 16;
+
+/// File name of the JSON IPC socket in `$XDG_RUNTIME_DIR`
+pub(crate) const IPC_SOCKET_NAME: &str = "cosmic-ext-notifications.sock";
+
+/// Events buffered for each IPC client before the slowest start missing some
+pub(crate) const IPC_EVENT_BUFFER_SIZE: usize = 64;
+
+/// Longest command line accepted on the IPC socket (bytes)
+pub(crate) const IPC_MAX_COMMAND_LEN: usize = 4096;
+
+/// Longest value passed to a hook in one environment variable (bytes)
+pub(crate) const HOOK_MAX_VALUE_LEN: usize = 4096;
+
+/// Texts waiting to be read aloud before new ones are dropped
+pub(crate) const TTS_QUEUE_SIZE: usize = 8;
+
+// ============================================================================
+// Webhook Constants
+// ============================================================================
+
+/// Notifications waiting for the webhook before new ones are dropped
+#[cfg(feature = "webhook")]
+pub(crate) const WEBHOOK_QUEUE_SIZE: usize = 64;
+
+/// Longest summary or body sent to the webhook (bytes)
+#[cfg(feature = "webhook")]
+pub(crate) const WEBHOOK_MAX_TEXT_LEN: usize = 4096;
+
+/// Largest JSON request body sent to the webhook (bytes)
+#[cfg(feature = "webhook")]
+pub(crate) const WEBHOOK_MAX_PAYLOAD: usize = 16 * 1024;
+
+/// Tries per notification, the first included
+#[cfg(feature = "webhook")]
+pub(crate) const WEBHOOK_MAX_ATTEMPTS: u32 = 4;
+
+/// Wait before the first retry, doubled for each one after (seconds)
+#[cfg(feature = "webhook")]
+pub(crate) const WEBHOOK_RETRY_DELAY: u64 = 2;
+
+/// Longest a single webhook request may take (seconds)
+#[cfg(feature = "webhook")]
+pub(crate) const WEBHOOK_TIMEOUT: u64 = 10;
+
+/// Digests waiting for the mail server before new ones are dropped
+#[cfg(feature = "email")]
+pub(crate) const EMAIL_QUEUE_SIZE: usize = 4;
+
+/// Longest an SMTP connection may wait on the server (seconds)
+#[cfg(feature = "email")]
+pub(crate) const EMAIL_TIMEOUT: u64 = 30;
+
 // ============================================================================
 // URL Display Constants
 // ============================================================================
@@ -85,3 +255,83 @@ pub(crate) const URL_DISPLAY_MAX_SINGLE: usize = 40;
 
 /// Maximum URL length for multiple URL display before truncation
 pub(crate) const URL_DISPLAY_MAX_MULTI: usize = 30;
+
+// ============================================================================
+// Focus Session Constants
+// ============================================================================
+
+/// Maximum duration of a focus session (seconds)
+pub(crate) const FOCUS_SESSION_MAX_DURATION: u32 = 24 * 60 * 60;
+
+/// Maximum duration of a timed do-not-disturb (seconds)
+pub(crate) const DND_MAX_DURATION: u32 = 7 * 24 * 60 * 60;
+
+/// Hour of the morning a notification snoozed until "Tomorrow" comes back
+/// (local time, 24-hour clock)
+pub(crate) const SNOOZE_TOMORROW_HOUR: u32 = 9;
+
+/// Maximum time a notification can be snoozed (seconds)
+pub(crate) const SNOOZE_MAX_DURATION: u32 = 7 * 24 * 60 * 60;
+
+/// Icon used for the focus session summary notification
+pub(crate) const FOCUS_SESSION_ICON: &str = "alarm-symbolic";
+
+// ============================================================================
+// History Statistics Constants
+// ============================================================================
+
+/// Days of per-app notification counts kept for the statistics API
+pub(crate) const STATS_RETENTION_DAYS: i32 = 90;
+
+/// How long changed statistics wait before they are saved (seconds)
+pub(crate) const STATS_SAVE_SECS: u64 = 30;
+
+// ============================================================================
+// Priority Scoring Constants
+// ============================================================================
+
+/// Age at which a notification's recency score has halved (seconds)
+pub(crate) const PRIORITY_RECENCY_SECS: f64 = 600.0;
+
+/// Days of statistics used to judge how often the user acts on an app
+pub(crate) const PRIORITY_HISTORY_DAYS: i32 = 30;
+
+/// Notifications an app needs in that time before its interaction counts,
+/// so one lucky click doesn't put a new app on top
+pub(crate) const PRIORITY_MIN_RECEIVED: u32 = 5;
+
+// ============================================================================
+// Away Summary Constants
+// ============================================================================
+
+/// Shortest lock that gets a "While you were away" summary (seconds)
+pub(crate) const AWAY_SUMMARY_MIN_LOCKED: u64 = 60;
+
+/// Icon used for the away summary notification
+pub(crate) const AWAY_SUMMARY_ICON: &str = "mail-unread-symbolic";
+
+/// Action key on the away summary that opens the notification center
+pub(crate) const AWAY_SUMMARY_ACTION: &str = "open-center";
+
+/// How often to check whether an email digest is due while away (seconds)
+#[cfg(feature = "email")]
+pub(crate) const EMAIL_DIGEST_CHECK_SECS: u64 = 60;
+
+/// Icon used for the summary of notifications dropped by the rate limiter
+pub(crate) const RATE_LIMIT_SUMMARY_ICON: &str = "dialog-warning-symbolic";
+
+// ============================================================================
+// DND Summary Constants
+// ============================================================================
+
+/// Icon used for digest cards
+pub(crate) const DIGEST_ICON: &str = "view-list-symbolic";
+
+/// Summaries listed on a digest card before "and N more"
+pub(crate) const DIGEST_MAX_SUMMARIES: usize = 3;
+
+/// Icon used for the summary shown when do-not-disturb ends
+pub(crate) const DND_SUMMARY_ICON: &str = "notification-symbolic";
+
+/// Action key on the DND summary that opens the notification center
+pub(crate) const DND_SUMMARY_ACTION: &str = "open-center";
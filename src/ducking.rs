@@ -0,0 +1,201 @@
+//! Lower media player volume while a critical notification sound plays
+//!
+//! Media players are found through MPRIS on the session bus. Only players
+//! that are currently playing are ducked, and each one is restored to the
+//! volume it had, unless the user changed it in the meantime. Overlapping
+//! sounds keep players ducked until the last of them ends.
+
+use cosmic_ext_notifications_util::PlaybackHandle;
+use std::sync::{Mutex, MutexGuard, PoisonError};
+use std::time::{Duration, Instant};
+use zbus::{Connection, Proxy};
+
+const MPRIS_PREFIX: &str = "org.mpris.MediaPlayer2.";
+const MPRIS_PATH: &str = "/org/mpris/MediaPlayer2";
+const MPRIS_PLAYER: &str = "org.mpris.MediaPlayer2.Player";
+
+/// How often to check whether the notification sound has ended
+const POLL_INTERVAL: Duration = Duration::from_millis(100);
+
+/// Restore volumes after this long even if the sound is still playing
+const MAX_DUCK_DURATION: Duration = Duration::from_secs(10);
+
+/// Sounds keeping players ducked, and the players to restore once the last
+/// of them ends
+static DUCKING: Mutex<Ducking> = Mutex::new(Ducking::new());
+
+/// A player whose volume was lowered
+#[derive(Debug)]
+struct Ducked {
+    name: String,
+    original: f64,
+    ducked: f64,
+}
+
+/// Critical sounds playing at once, and what the first of them ducked
+///
+/// Only the first sound ducks, so later ones don't save the already
+/// lowered volume as the one to restore.
+#[derive(Debug)]
+struct Ducking {
+    sounds: usize,
+    ducked: Vec<Ducked>,
+}
+
+impl Ducking {
+    const fn new() -> Self {
+        Self {
+            sounds: 0,
+            ducked: Vec::new(),
+        }
+    }
+
+    /// A sound starts; true if it is the only one, which has to duck
+    fn start(&mut self) -> bool {
+        self.sounds += 1;
+        self.sounds == 1
+    }
+
+    /// A sound ended; the players to restore if it was the last one
+    fn end(&mut self) -> Option<Vec<Ducked>> {
+        self.sounds = self.sounds.saturating_sub(1);
+        (self.sounds == 0).then(|| std::mem::take(&mut self.ducked))
+    }
+}
+
+fn ducking() -> MutexGuard<'static, Ducking> {
+    DUCKING.lock().unwrap_or_else(PoisonError::into_inner)
+}
+
+/// Duck playing media to `level` percent of its volume until `sound`, and
+/// any other critical sound playing meanwhile, ends
+pub async fn duck_while_playing(sound: PlaybackHandle, level: u8) {
+    let conn = match Connection::session().await {
+        Ok(conn) => conn,
+        Err(err) => {
+            tracing::warn!("Cannot duck media players, no session bus: {}", err);
+            return;
+        }
+    };
+
+    let first = ducking().start();
+    if first {
+        let ducked = duck(&conn, f64::from(level.min(100)) / 100.0).await;
+        ducking().ducked = ducked;
+    }
+
+    let start = Instant::now();
+    while !sound.is_finished() && start.elapsed() < MAX_DUCK_DURATION {
+        tokio::time::sleep(POLL_INTERVAL).await;
+    }
+
+    let ducked = ducking().end();
+    if let Some(ducked) = ducked {
+        restore(&conn, ducked).await;
+    }
+}
+
+async fn player(conn: &Connection, name: &str) -> zbus::Result<Proxy<'static>> {
+    Proxy::new(conn, name.to_string(), MPRIS_PATH, MPRIS_PLAYER).await
+}
+
+async fn duck(conn: &Connection, factor: f64) -> Vec<Ducked> {
+    let names = match zbus::fdo::DBusProxy::new(conn).await {
+        Ok(dbus) => dbus.list_names().await.unwrap_or_default(),
+        Err(err) => {
+            tracing::warn!("Failed to list media players: {}", err);
+            return Vec::new();
+        }
+    };
+
+    let mut ducked = Vec::new();
+    for name in names.iter().filter(|n| n.starts_with(MPRIS_PREFIX)) {
+        let Ok(proxy) = player(conn, name).await else {
+            continue;
+        };
+        let playing = proxy
+            .get_property::<String>("PlaybackStatus")
+            .await
+            .is_ok_and(|status| status == "Playing");
+        let Ok(original) = proxy.get_property::<f64>("Volume").await else {
+            continue;
+        };
+        if !playing || original <= 0.0 {
+            continue;
+        }
+
+        let lowered = original * factor;
+        match proxy.set_property("Volume", lowered).await {
+            Ok(()) => {
+                tracing::debug!("Ducked {} from {:.2} to {:.2}", name, original, lowered);
+                ducked.push(Ducked {
+                    name: name.to_string(),
+                    original,
+                    ducked: lowered,
+                });
+            }
+            Err(err) => tracing::debug!("Player {} does not allow setting volume: {}", name, err),
+        }
+    }
+    ducked
+}
+
+async fn restore(conn: &Connection, ducked: Vec<Ducked>) {
+    for player_state in ducked {
+        let Ok(proxy) = player(conn, &player_state.name).await else {
+            continue;
+        };
+        // Leave the volume alone if the user changed it while ducked
+        let unchanged = proxy
+            .get_property::<f64>("Volume")
+            .await
+            .is_ok_and(|v| (v - player_state.ducked).abs() < 0.01);
+        if !unchanged {
+            continue;
+        }
+        if let Err(err) = proxy.set_property("Volume", player_state.original).await {
+            tracing::warn!("Failed to restore volume of {}: {}", player_state.name, err);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn ducked(name: &str) -> Ducked {
+        Ducked {
+            name: name.to_string(),
+            original: 0.8,
+            ducked: 0.2,
+        }
+    }
+
+    #[test]
+    fn test_restores_after_last_sound() {
+        let mut ducking = Ducking::new();
+        assert!(ducking.start());
+        ducking.ducked = vec![ducked("org.mpris.MediaPlayer2.spotify")];
+        // A second sound doesn't duck again
+        assert!(!ducking.start());
+
+        // The first sound ending leaves players ducked for the second
+        assert!(ducking.end().is_none());
+        let restored = ducking.end().unwrap();
+        assert_eq!(restored.len(), 1);
+        assert_eq!(restored[0].name, "org.mpris.MediaPlayer2.spotify");
+    }
+
+    #[test]
+    fn test_ducks_again_after_restore() {
+        let mut ducking = Ducking::new();
+        assert!(ducking.start());
+        ducking.ducked = vec![ducked("org.mpris.MediaPlayer2.vlc")];
+        assert_eq!(ducking.end().unwrap().len(), 1);
+
+        assert!(ducking.start());
+        assert!(ducking.end().unwrap().is_empty());
+        // An unmatched end doesn't underflow
+        assert!(ducking.end().unwrap().is_empty());
+    }
+}
@@ -0,0 +1,172 @@
+//! Email a digest of notifications missed while the session was locked or
+//! do-not-disturb was on
+//!
+//! The digest is built from history when the app's timer asks for it, and
+//! sent in order from one worker thread so a slow mail server never holds
+//! up the daemon. SMTP connections time out; when the queue is full new
+//! digests are dropped. Failures are only logged; the next digest doesn't
+//! repeat what a failed one held.
+
+use crate::constants::{EMAIL_QUEUE_SIZE, EMAIL_TIMEOUT};
+use chrono::{DateTime, Local};
+use cosmic_ext_notifications_config::{EmailDigestConfig, EmailTransport};
+use lettre::{
+    Message, SendmailTransport, SmtpTransport, Transport, message::header::ContentType,
+    transport::smtp::authentication::Credentials,
+};
+use std::{
+    sync::mpsc::{Receiver, SyncSender, TrySendError, sync_channel},
+    time::{Duration, SystemTime},
+};
+
+/// A missed notification as it appears in the digest
+#[derive(Debug, Clone)]
+pub struct DigestEntry {
+    pub time: SystemTime,
+    pub app_name: String,
+    /// `None` for apps marked sensitive
+    pub summary: Option<String>,
+    pub body: Option<String>,
+}
+
+/// Subject and plain-text body of a digest of `entries`, oldest first
+pub fn compose(entries: &[DigestEntry], since: SystemTime) -> (String, String) {
+    let subject = crate::fl!("email-digest-subject", count = entries.len());
+    let since = DateTime::<Local>::from(since).format("%H:%M").to_string();
+    let mut body = crate::fl!("email-digest-intro", since = since.as_str());
+    body.push_str("\n\n");
+    for entry in entries {
+        let time = DateTime::<Local>::from(entry.time).format("%H:%M");
+        let summary = entry
+            .summary
+            .clone()
+            .unwrap_or_else(|| crate::fl!("email-digest-private"));
+        body.push_str(&format!("{time}  {}: {summary}\n", entry.app_name));
+        if let Some(text) = entry.body.as_deref().filter(|text| !text.is_empty()) {
+            for line in text.lines() {
+                body.push_str(&format!("       {line}\n"));
+            }
+        }
+    }
+    (subject, body)
+}
+
+fn deliver(settings: &EmailDigestConfig, subject: String, body: String) -> anyhow::Result<()> {
+    let from = settings.from.as_deref().unwrap_or(&settings.to);
+    let message = Message::builder()
+        .from(from.parse()?)
+        .to(settings.to.parse()?)
+        .subject(subject)
+        .header(ContentType::TEXT_PLAIN)
+        .body(body)?;
+
+    match &settings.transport {
+        EmailTransport::Sendmail { command } => {
+            let transport = match command {
+                Some(command) => SendmailTransport::new_with_command(command),
+                None => SendmailTransport::new(),
+            };
+            transport.send(&message)?;
+        }
+        EmailTransport::Smtp {
+            host,
+            port,
+            username,
+            password_file,
+        } => {
+            let mut builder = if *port == 465 {
+                SmtpTransport::relay(host)?
+            } else {
+                SmtpTransport::starttls_relay(host)?
+            }
+            .port(*port)
+            .timeout(Some(Duration::from_secs(EMAIL_TIMEOUT)));
+            if let Some(username) = username {
+                let password = match password_file {
+                    Some(path) => std::fs::read_to_string(path)?.trim_end().to_string(),
+                    None => String::new(),
+                };
+                builder = builder.credentials(Credentials::new(username.clone(), password));
+            }
+            builder.build().send(&message)?;
+        }
+    }
+    Ok(())
+}
+
+fn run(settings: EmailDigestConfig, queue: Receiver<(String, String)>) {
+    for (subject, body) in queue {
+        if let Err(err) = deliver(&settings, subject, body) {
+            tracing::warn!("Failed to email the notification digest: {err:#}");
+        }
+    }
+}
+
+/// Queue to the worker sending the `email_digest`
+///
+/// Dropping it lets the worker finish what's queued and stop.
+#[derive(Debug)]
+pub struct EmailSink {
+    queue: SyncSender<(String, String)>,
+}
+
+impl EmailSink {
+    /// Start a worker sending digests as `settings` says
+    pub fn start(settings: &EmailDigestConfig) -> Option<Self> {
+        let settings = settings.clone();
+        let (queue, pending) = sync_channel(EMAIL_QUEUE_SIZE);
+        let worker = std::thread::Builder::new()
+            .name("email-digest".to_string())
+            .spawn(move || run(settings, pending));
+        match worker {
+            Ok(_) => Some(Self { queue }),
+            Err(err) => {
+                tracing::warn!("Failed to start the email digest worker: {}", err);
+                None
+            }
+        }
+    }
+
+    /// Queue a digest for sending; failures are only logged
+    pub fn send(&self, subject: String, body: String) {
+        match self.queue.try_send((subject, body)) {
+            Ok(()) => {}
+            Err(TrySendError::Full(_)) => {
+                tracing::warn!("Email digest queue full, dropped a digest")
+            }
+            Err(TrySendError::Disconnected(_)) => {
+                tracing::warn!("Email digest worker stopped, dropped a digest")
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_compose_lists_entries() {
+        let since = SystemTime::now() - Duration::from_secs(3600);
+        let entries = [
+            DigestEntry {
+                time: since,
+                app_name: "Mail".to_string(),
+                summary: Some("Invoice due".to_string()),
+                body: Some("Pay by Friday\nThanks".to_string()),
+            },
+            DigestEntry {
+                time: since,
+                app_name: "Signal".to_string(),
+                summary: None,
+                body: None,
+            },
+        ];
+        let (subject, body) = compose(&entries, since);
+        assert!(subject.contains('2'));
+        assert!(body.contains("Mail: Invoice due\n"));
+        assert!(body.contains("       Pay by Friday\n       Thanks\n"));
+        assert!(body.contains("Signal: "));
+        assert!(!body.contains("Signal: \n"));
+    }
+}
@@ -1,6 +1,10 @@
-use crate::subscriptions::notifications;
+use crate::state::{DateSection, DecodedImage, KeyMove, Wakeup};
+use crate::subscriptions::{focused_window::FocusedWindow, notifications, session};
 use cosmic::surface;
+use cosmic_ext_notifications_config::SnoozePreset;
 use cosmic_ext_notifications_util::ActionId;
+use cosmic::cctk::wayland_client::protocol::wl_output::WlOutput;
+use cosmic::iced_runtime::core::window::Id as SurfaceId;
 use cosmic_time::Instant;
 
 /// Application message types
@@ -12,16 +16,81 @@ pub enum Message {
     ActivationToken(Option<String>, u32, Option<ActionId>),
     /// Notification dismissed by user
     Dismissed(u32),
+    /// Dismiss every popup
+    DismissAll,
+    /// Open or close the notification center
+    ToggleCenter,
+    /// Remove one entry from the notification center
+    CenterRemove(u32),
+    /// Empty the notification center
+    CenterClearAll,
+    /// Do-not-disturb switched from the notification center
+    CenterDnd(bool),
+    /// Priority-only do-not-disturb switched from the notification center
+    CenterPriorityOnly(bool),
+    /// Search text typed in the notification center
+    CenterSearch(String),
+    /// Collapse or expand a date section of the notification center
+    CenterToggleSection(DateSection),
+    /// Dismiss every popup from an app
+    DismissApp(String),
+    /// Open or close a popup's right-click menu
+    ContextMenu(u32),
+    /// Entry picked from a popup's right-click menu
+    Menu(u32, MenuItem),
+    /// Open or close the snooze durations of a popup
+    SnoozePicker(u32),
+    /// Snooze a popup for one of the configured durations
+    Snooze(u32, SnoozePreset),
+    /// Open a popup group in place, or fold it back
+    GroupToggle(String),
+    /// Dismiss every popup of a group
+    GroupClear(String),
+    /// A popup image finished decoding (notification_id, ticket, image)
+    ImageDecoded(u32, u64, Option<DecodedImage>),
     /// Notification event from subscription
     Notification(notifications::Event),
     /// Notification timeout expired
     Timeout(u32),
+    /// Pointer entered (true) or left (false) a popup
+    CardHovered(u32, bool),
+    /// Mouse or touch input on a surface, for swiping popups away
+    Pointer(SurfaceId, PointerInput),
+    /// Key pressed on the popups while they have keyboard focus
+    Key(SurfaceId, KeyInput),
+    /// Focus session timer elapsed
+    FocusSessionEnded,
+    /// Timed do-not-disturb elapsed
+    DndExpired,
+    /// The session was locked or unlocked
+    Session(session::Event),
+    /// Screen sharing started or ended
+    ScreenShare(bool),
+    /// Mute a conversation by its key for the configured number of hours
+    MuteConversation(String),
+    /// A timer armed as the given generation went off
+    Wakeup(Wakeup, u64),
+    /// An email digest of what arrived while away may be due
+    #[cfg(feature = "email")]
+    EmailDigestDue,
+    /// Statistics that changed may be saved
+    SaveStats,
+    /// Outcome of a sound playback from the audio engine
+    #[cfg(feature = "audio")]
+    Playback(cosmic_ext_notifications_util::PlaybackEvent),
+    /// The enabled plugins finished loading
+    #[cfg(feature = "plugins")]
+    PluginsLoaded(std::sync::Arc<cosmic_ext_notifications_util::PluginHost>),
     /// Configuration updated
     Config(cosmic_ext_notifications_config::NotificationsConfig),
     /// Panel configuration updated
     PanelConfig(cosmic_panel_config::CosmicPanelConfig),
     /// Dock configuration updated
     DockConfig(cosmic_panel_config::CosmicPanelConfig),
+    /// An output got its name, or went away (`None`)
+    Output(WlOutput, Option<String>),
+    /// The focused window moved to another output, or went fullscreen or back
+    FocusedWindow(FocusedWindow),
     /// Animation frame update
     Frame(Instant),
     /// No-op message
@@ -31,6 +100,55 @@ pub enum Message {
     Surface(surface::Action),
     /// Link clicked in notification body
     LinkClicked(String),
+    /// Contact quick action clicked (tel: or mailto: URI)
+    OpenUri(String),
+    /// Copy text to the clipboard
+    CopyToClipboard(String),
+    /// Show or mask again the one-time codes and reset links of a popup
+    ToggleSecrets(u32),
+    /// Reply button pressed, reveals the inline reply field
+    ReplyOpen(u32),
+    /// Inline reply text edited (notification_id, text)
+    ReplyChanged(u32, String),
+    /// Inline reply submitted
+    ReplySubmit(u32),
+    /// Inline reply field closed without sending
+    ReplyCancel(u32),
     /// Action button clicked (notification_id, action_id)
     ActionClicked(u32, String),
 }
+
+/// Pointer input used for swipe gestures
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum PointerInput {
+    /// Button or finger went down; touch reports where
+    Pressed(Option<f32>),
+    /// Moved to this horizontal position
+    Moved(f32),
+    Released,
+}
+
+/// Entries of a popup's right-click menu
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MenuItem {
+    /// Turn the app's notifications off in its app rule
+    MuteApp,
+    /// Open the file holding the app rules, with a rule for the app in it
+    EditRule,
+    /// Copy summary and body as plain text
+    CopyText,
+    /// Pick a duration to bring the popup back after
+    Snooze,
+}
+
+/// Key presses used for keyboard navigation of popups
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum KeyInput {
+    Move(KeyMove),
+    /// Enter: click the focused card or button
+    Activate,
+    /// Delete or Backspace: dismiss the focused popup
+    Dismiss,
+    /// Escape: give keyboard focus back
+    Leave,
+}
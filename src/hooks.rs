@@ -0,0 +1,143 @@
+//! Run the user's `hooks` when notifications arrive, are dismissed or have
+//! an action invoked
+//!
+//! Hooks are started without a shell and get the notification in
+//! environment variables, never as arguments, so nothing an app writes in
+//! a notification can become part of a command line. Values are stripped
+//! of markup and control characters and capped in length.
+
+use crate::constants::HOOK_MAX_VALUE_LEN;
+use cosmic_ext_notifications_config::{HookEvent, NotificationHook};
+use cosmic_ext_notifications_util::{Notification, strip_html};
+use std::process::{Command, Stdio};
+
+/// `text` without control characters other than newlines and tabs, cut
+/// to [`HOOK_MAX_VALUE_LEN`] bytes
+fn sanitize(text: &str) -> String {
+    let mut value = String::with_capacity(text.len().min(HOOK_MAX_VALUE_LEN));
+    for c in text.chars() {
+        if c.is_control() && c != '\n' && c != '\t' {
+            continue;
+        }
+        if value.len() + c.len_utf8() > HOOK_MAX_VALUE_LEN {
+            break;
+        }
+        value.push(c);
+    }
+    value
+}
+
+/// Environment describing a notification to a hook
+fn hook_env(
+    event: HookEvent,
+    n: &Notification,
+    action: Option<&str>,
+) -> Vec<(&'static str, String)> {
+    let event = match event {
+        HookEvent::Received => "received",
+        HookEvent::Dismissed => "dismissed",
+        HookEvent::ActionInvoked => "action-invoked",
+    };
+    let urgency = match n.urgency() {
+        0 => "low",
+        1 => "normal",
+        _ => "critical",
+    };
+    vec![
+        ("NOTIFICATION_EVENT", event.to_string()),
+        ("NOTIFICATION_ID", n.id.to_string()),
+        ("NOTIFICATION_APP_NAME", sanitize(&n.app_name)),
+        (
+            "NOTIFICATION_DESKTOP_ENTRY",
+            sanitize(n.desktop_entry().unwrap_or_default()),
+        ),
+        ("NOTIFICATION_SUMMARY", sanitize(&n.summary)),
+        ("NOTIFICATION_BODY", sanitize(&strip_html(&n.body))),
+        ("NOTIFICATION_URGENCY", urgency.to_string()),
+        (
+            "NOTIFICATION_CATEGORY",
+            sanitize(n.category().unwrap_or_default()),
+        ),
+        ("NOTIFICATION_ACTION", sanitize(action.unwrap_or_default())),
+    ]
+}
+
+/// Start `hooks` for `event` on `n`; `action` is the invoked action key
+///
+/// Returns immediately; failures are only logged.
+pub fn run(hooks: &[&NotificationHook], event: HookEvent, n: &Notification, action: Option<&str>) {
+    if hooks.is_empty() {
+        return;
+    }
+    let env = hook_env(event, n, action);
+
+    for hook in hooks {
+        let Some((program, args)) = hook.command.split_first() else {
+            continue;
+        };
+        let child = Command::new(program)
+            .args(args)
+            .envs(env.iter().map(|(key, value)| (*key, value)))
+            .stdin(Stdio::null())
+            .stdout(Stdio::null())
+            .stderr(Stdio::null())
+            .spawn();
+
+        match child {
+            Ok(mut child) => {
+                std::thread::spawn(move || child.wait());
+            }
+            Err(err) => tracing::warn!("Failed to run hook {}: {}", program, err),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use cosmic_ext_notifications_util::Hint;
+    use std::time::SystemTime;
+
+    fn notification(summary: &str, body: &str) -> Notification {
+        Notification {
+            id: 3,
+            app_name: "Mail".to_string(),
+            app_icon: String::new(),
+            summary: summary.to_string(),
+            body: body.to_string(),
+            actions: vec![],
+            hints: vec![Hint::Urgency(2)],
+            expire_timeout: -1,
+            applied_timeout: None,
+            time: SystemTime::now(),
+        }
+    }
+
+    fn value<'a>(env: &'a [(&str, String)], key: &str) -> &'a str {
+        env.iter()
+            .find(|(k, _)| *k == key)
+            .map(|(_, v)| v.as_str())
+            .unwrap()
+    }
+
+    #[test]
+    fn test_env_describes_notification() {
+        let n = notification("Invoice", "<b>Pay</b> today");
+        let env = hook_env(HookEvent::ActionInvoked, &n, Some("open"));
+        assert_eq!(value(&env, "NOTIFICATION_EVENT"), "action-invoked");
+        assert_eq!(value(&env, "NOTIFICATION_ID"), "3");
+        assert_eq!(value(&env, "NOTIFICATION_BODY"), "Pay today");
+        assert_eq!(value(&env, "NOTIFICATION_URGENCY"), "critical");
+        assert_eq!(value(&env, "NOTIFICATION_ACTION"), "open");
+        assert_eq!(value(&env, "NOTIFICATION_DESKTOP_ENTRY"), "");
+    }
+
+    #[test]
+    fn test_sanitize_drops_control_chars_and_caps_length() {
+        assert_eq!(sanitize("a\0b\x1b[31mc\nd"), "ab[31mc\nd");
+        let long = "é".repeat(HOOK_MAX_VALUE_LEN);
+        let value = sanitize(&long);
+        assert!(value.len() <= HOOK_MAX_VALUE_LEN);
+        assert!(value.chars().all(|c| c == 'é'));
+    }
+}
@@ -0,0 +1,239 @@
+//! dunst `dunstrc` importer
+//!
+//! Default timeouts come from the `[urgency_low]`, `[urgency_normal]` and
+//! `[urgency_critical]` sections. Any other section with an `appname` or
+//! `desktop_entry` matcher becomes an app rule, as long as that matcher
+//! names one app and the section matches on nothing else. App rules match
+//! one app exactly and on every notification from it, so sections with
+//! wildcards or with summary, body or similar matchers are skipped.
+
+use super::{literal_matcher, parse_ini, parse_urgency, Imported};
+
+/// Sections that configure dunst itself rather than match notifications
+const GLOBAL_SECTIONS: &[&str] = &["", "global", "experimental", "shortcuts", "frame"];
+
+/// Matchers that narrow a section to some of an app's notifications, which
+/// an app rule can't
+const UNSUPPORTED_MATCHERS: &[&str] = &["summary", "body", "category", "msg_urgency", "stack_tag"];
+
+pub(super) fn parse(contents: &str) -> Imported {
+    let mut imported = Imported::default();
+    let sections = parse_ini(contents);
+
+    // Matchers are shell globs unless the global section switches to regexes
+    let regex = sections
+        .iter()
+        .filter(|(section, _)| section == "global")
+        .flat_map(|(_, entries)| entries)
+        .any(|(key, value)| key == "enable_regex" && is_true(value));
+
+    for (section, entries) in sections {
+        if GLOBAL_SECTIONS.contains(&section.as_str()) {
+            continue;
+        }
+
+        let urgency_slot = match section.as_str() {
+            "urgency_low" => Some(0),
+            "urgency_normal" => Some(1),
+            "urgency_critical" => Some(2),
+            _ => None,
+        };
+        if let Some(slot) = urgency_slot {
+            for (key, value) in &entries {
+                if key == "timeout" {
+                    match parse_duration(value) {
+                        Some(ms) => imported.default_timeouts[slot] = Some(ms),
+                        None => imported
+                            .warnings
+                            .push(format!("[{section}] invalid timeout {value:?}")),
+                    }
+                }
+            }
+            continue;
+        }
+
+        let get = |name: &str| {
+            entries
+                .iter()
+                .find(|(key, _)| key == name)
+                .map(|(_, value)| value.as_str())
+        };
+        if get("appname").or(get("desktop_entry")).is_none() {
+            imported
+                .warnings
+                .push(format!("[{section}] does not match an app, skipped"));
+            continue;
+        }
+        if let Some(matcher) = UNSUPPORTED_MATCHERS.iter().find(|m| get(m).is_some()) {
+            imported.warnings.push(format!(
+                "[{section}] also matches on {matcher}, which app rules can't, skipped"
+            ));
+            continue;
+        }
+        let literal = |name: &str| get(name).map(|value| literal_matcher(value, regex));
+        let (app_name, desktop_entry) = match (literal("appname"), literal("desktop_entry")) {
+            (Some(None), _) | (_, Some(None)) => {
+                imported.warnings.push(format!(
+                    "[{section}] matches apps by pattern, which app rules can't, skipped"
+                ));
+                continue;
+            }
+            (app_name, desktop_entry) => (app_name.flatten(), desktop_entry.flatten()),
+        };
+        let Some(name) = app_name.as_ref().or(desktop_entry.as_ref()) else {
+            continue;
+        };
+
+        let mut warnings = Vec::new();
+        let rule = imported.rule_mut(name);
+        if let Some(entry) = desktop_entry {
+            rule.desktop_entry = Some(entry);
+        }
+        for (key, value) in &entries {
+            match key.as_str() {
+                "appname" | "desktop_entry" => {}
+                key if UNSUPPORTED_MATCHERS.contains(&key) => {}
+                "urgency" => match parse_urgency(value) {
+                    Some(urgency) => rule.urgency_override = Some(urgency),
+                    None => warnings.push(format!("[{section}] unknown urgency {value:?}")),
+                },
+                "timeout" => match parse_duration(value) {
+                    Some(ms) => rule.timeout_override = Some(ms),
+                    None => warnings.push(format!("[{section}] invalid timeout {value:?}")),
+                },
+                "skip_display" => rule.enabled = !is_true(value),
+                other => warnings.push(format!("[{section}] {other} is not supported")),
+            }
+        }
+        imported.warnings.extend(warnings);
+    }
+
+    imported
+}
+
+fn is_true(value: &str) -> bool {
+    matches!(value.to_ascii_lowercase().as_str(), "true" | "yes" | "1")
+}
+
+/// dunst times are seconds unless suffixed with ms, s, m, h or d
+fn parse_duration(value: &str) -> Option<u32> {
+    let value = value.trim();
+    let (number, factor) = if let Some(n) = value.strip_suffix("ms") {
+        (n, 1)
+    } else if let Some(n) = value.strip_suffix('s') {
+        (n, 1000)
+    } else if let Some(n) = value.strip_suffix('m') {
+        (n, 60_000)
+    } else if let Some(n) = value.strip_suffix('h') {
+        (n, 3_600_000)
+    } else if let Some(n) = value.strip_suffix('d') {
+        (n, 86_400_000)
+    } else {
+        (value, 1000)
+    };
+    number.trim().parse::<u32>().ok()?.checked_mul(factor)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const DUNSTRC: &str = r#"
+[global]
+    font = Monospace 8
+    # A comment
+[urgency_low]
+    timeout = 4
+[urgency_normal]
+    timeout = 8s
+[urgency_critical]
+    timeout = 0
+
+[spotify]
+    appname = Spotify
+    urgency = low
+    timeout = 1500ms
+
+[no_signal]
+    desktop_entry = "org.signal.Signal"
+    skip_display = true
+
+[by_summary]
+    summary = "*backup*"
+
+[backup_failures]
+    appname = Deja Dup
+    summary = "*failed*"
+    urgency = critical
+
+[all_chromium]
+    appname = "Chromium*"
+    skip_display = true
+"#;
+
+    #[test]
+    fn test_parse_dunstrc() {
+        let imported = parse(DUNSTRC);
+        assert_eq!(imported.default_timeouts, [Some(4000), Some(8000), Some(0)]);
+        assert_eq!(imported.app_rules.len(), 2);
+
+        let spotify = &imported.app_rules[0];
+        assert_eq!(spotify.app_name, "Spotify");
+        assert_eq!(spotify.urgency_override, Some(0));
+        assert_eq!(spotify.timeout_override, Some(1500));
+
+        let signal = &imported.app_rules[1];
+        assert_eq!(signal.desktop_entry.as_deref(), Some("org.signal.Signal"));
+        assert!(!signal.enabled);
+
+        assert!(imported.warnings.iter().any(|w| w.contains("by_summary")));
+
+        // Rules for some of an app's notifications, or for several apps,
+        // would apply more widely than in dunst
+        assert!(imported.app_rules.iter().all(|r| r.app_name != "Deja Dup"));
+        assert!(imported.warnings.iter().any(|w| w.contains("[backup_failures]")));
+        assert!(imported.warnings.iter().any(|w| w.contains("[all_chromium]")));
+    }
+
+    #[test]
+    fn test_parse_regex_matchers() {
+        let imported = parse(
+            r#"
+[global]
+    enable_regex = true
+[signal]
+    desktop_entry = "^org\.signal\.Signal$"
+    urgency = low
+[firefox]
+    appname = "^Firefox$"
+    timeout = 3
+[browsers]
+    appname = "Firefox|Chromium"
+    skip_display = true
+"#,
+        );
+        assert_eq!(imported.app_rules.len(), 2);
+        assert_eq!(imported.app_rules[0].desktop_entry.as_deref(), Some("org.signal.Signal"));
+        assert_eq!(imported.app_rules[0].urgency_override, Some(0));
+        assert_eq!(imported.app_rules[1].app_name, "Firefox");
+        assert!(imported.warnings.iter().any(|w| w.contains("[browsers]")));
+    }
+
+    #[test]
+    fn test_literal_matcher() {
+        assert_eq!(literal_matcher("Spotify", false).as_deref(), Some("Spotify"));
+        assert_eq!(literal_matcher("Spotify*", false), None);
+        assert_eq!(literal_matcher("What\\?", false).as_deref(), Some("What?"));
+        assert_eq!(literal_matcher("org.gnome.Shell", true).as_deref(), Some("org.gnome.Shell"));
+        assert_eq!(literal_matcher("^Slack$", true).as_deref(), Some("Slack"));
+        assert_eq!(literal_matcher("Sl(a|e)ck", true), None);
+    }
+
+    #[test]
+    fn test_parse_duration() {
+        assert_eq!(parse_duration("10"), Some(10_000));
+        assert_eq!(parse_duration("250ms"), Some(250));
+        assert_eq!(parse_duration("2m"), Some(120_000));
+        assert_eq!(parse_duration("soon"), None);
+    }
+}
@@ -0,0 +1,170 @@
+//! mako `config` importer
+//!
+//! mako sections are criteria such as `[app-name=Spotify]` or
+//! `[urgency=critical]`. Single app-name criteria become app rules and
+//! single urgency criteria set the default timeouts.
+
+use super::{parse_ini, parse_urgency, Imported};
+
+pub(super) fn parse(contents: &str) -> Imported {
+    let mut imported = Imported::default();
+    let mut global_timeout = None;
+
+    for (section, entries) in parse_ini(contents) {
+        let criteria = match parse_criteria(&section) {
+            Some(criteria) => criteria,
+            None => {
+                imported
+                    .warnings
+                    .push(format!("[{section}] criteria could not be parsed, skipped"));
+                continue;
+            }
+        };
+        let timeout = entries
+            .iter()
+            .find(|(key, _)| key == "default-timeout")
+            .map(|(_, value)| value.as_str());
+        let timeout = match timeout.map(|value| value.trim().parse::<u32>()) {
+            Some(Ok(ms)) => Some(ms),
+            Some(Err(_)) => {
+                imported
+                    .warnings
+                    .push(format!("[{section}] invalid default-timeout"));
+                None
+            }
+            None => None,
+        };
+
+        // Only timeouts are taken from the global and urgency sections
+        let unsupported = || {
+            entries
+                .iter()
+                .filter(|(key, _)| key != "default-timeout")
+                .map(|(key, _)| match section.as_str() {
+                    "" => format!("{key} is not supported"),
+                    section => format!("[{section}] {key} is not supported"),
+                })
+                .collect::<Vec<_>>()
+        };
+        match criteria.as_slice() {
+            [] => {
+                global_timeout = timeout.or(global_timeout);
+                imported.warnings.extend(unsupported());
+            }
+            [(key, value)] if key == "urgency" => {
+                match parse_urgency(value) {
+                    Some(urgency) => {
+                        if let Some(ms) = timeout {
+                            imported.default_timeouts[urgency as usize] = Some(ms);
+                        }
+                    }
+                    None => imported
+                        .warnings
+                        .push(format!("[{section}] unknown urgency {value:?}")),
+                }
+                imported.warnings.extend(unsupported());
+            }
+            [(key, value)] if key == "app-name" => {
+                let mut warnings = Vec::new();
+                let rule = imported.rule_mut(value);
+                rule.timeout_override = timeout.or(rule.timeout_override);
+                for (key, value) in &entries {
+                    match key.as_str() {
+                        "default-timeout" => {}
+                        "invisible" => rule.enabled = value.trim() != "1",
+                        other => warnings.push(format!("[{section}] {other} is not supported")),
+                    }
+                }
+                imported.warnings.extend(warnings);
+            }
+            _ => imported.warnings.push(format!(
+                "[{section}] only single app-name or urgency criteria can be imported, skipped"
+            )),
+        }
+    }
+
+    if let Some(ms) = global_timeout {
+        for slot in &mut imported.default_timeouts {
+            slot.get_or_insert(ms);
+        }
+    }
+
+    imported
+}
+
+/// Split `app-name="Foo Bar" urgency=low` into key/value pairs
+fn parse_criteria(section: &str) -> Option<Vec<(String, String)>> {
+    let mut criteria = Vec::new();
+    let mut rest = section.trim();
+    while !rest.is_empty() {
+        let (key, after) = rest.split_once('=')?;
+        let (value, after) = match after.strip_prefix('"') {
+            Some(quoted) => {
+                let end = quoted.find('"')?;
+                (&quoted[..end], &quoted[end + 1..])
+            }
+            None => after.split_once(' ').unwrap_or((after, "")),
+        };
+        criteria.push((key.trim().to_string(), value.to_string()));
+        rest = after.trim_start();
+    }
+    Some(criteria)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const CONFIG: &str = r#"
+default-timeout=6000
+font=monospace 10
+
+[urgency=critical]
+default-timeout=0
+
+[app-name=Spotify]
+default-timeout=2000
+
+[app-name="Some App"]
+invisible=1
+border-color=#ff0000
+
+[app-name=Firefox urgency=low]
+invisible=1
+"#;
+
+    #[test]
+    fn test_parse_config() {
+        let imported = parse(CONFIG);
+        assert_eq!(imported.default_timeouts, [Some(6000), Some(6000), Some(0)]);
+        assert_eq!(imported.app_rules.len(), 2);
+        assert_eq!(imported.app_rules[0].timeout_override, Some(2000));
+        assert_eq!(imported.app_rules[1].app_name, "Some App");
+        assert!(!imported.app_rules[1].enabled);
+        assert!(imported.warnings.iter().any(|w| w.contains("border-color")));
+        assert!(imported.warnings.iter().any(|w| w.contains("Firefox")));
+    }
+
+    #[test]
+    fn test_warns_about_unsupported_global_keys() {
+        let imported = parse("font=monospace 10\n\n[urgency=low]\nbackground-color=#000000\n");
+        assert!(imported.warnings.iter().any(|w| w == "font is not supported"));
+        assert!(imported
+            .warnings
+            .iter()
+            .any(|w| w == "[urgency=low] background-color is not supported"));
+    }
+
+    #[test]
+    fn test_parse_criteria() {
+        assert_eq!(parse_criteria(""), Some(vec![]));
+        assert_eq!(
+            parse_criteria("app-name=\"Foo Bar\" urgency=low"),
+            Some(vec![
+                ("app-name".to_string(), "Foo Bar".to_string()),
+                ("urgency".to_string(), "low".to_string()),
+            ])
+        );
+        assert_eq!(parse_criteria("grouped"), None);
+    }
+}
@@ -0,0 +1,320 @@
+//! `import`: bring notification rules over from other daemons
+//!
+//! Reads dunst, mako or swaync configuration and translates per-app rules,
+//! default timeouts and urgency overrides into this daemon's config. Settings
+//! without an equivalent are reported instead of silently dropped.
+
+mod dunst;
+mod mako;
+mod swaync;
+
+use cosmic::cosmic_config::{Config, CosmicConfigEntry};
+use cosmic_ext_notifications_config::{AppRule, NotificationsConfig};
+use std::path::{Path, PathBuf};
+
+/// Daemon whose configuration is imported
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Source {
+    Dunst,
+    Mako,
+    Swaync,
+}
+
+impl Source {
+    fn parse(name: &str) -> Option<Self> {
+        match name {
+            "dunst" => Some(Source::Dunst),
+            "mako" => Some(Source::Mako),
+            "swaync" => Some(Source::Swaync),
+            _ => None,
+        }
+    }
+
+    /// Where the daemon keeps its configuration by default
+    fn default_path(self) -> PathBuf {
+        let config_home = std::env::var_os("XDG_CONFIG_HOME")
+            .map(PathBuf::from)
+            .or_else(|| std::env::var_os("HOME").map(|home| PathBuf::from(home).join(".config")))
+            .unwrap_or_default();
+        match self {
+            Source::Dunst => config_home.join("dunst/dunstrc"),
+            Source::Mako => config_home.join("mako/config"),
+            Source::Swaync => config_home.join("swaync/config.json"),
+        }
+    }
+}
+
+/// Import options from the command line
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Options {
+    pub source: Source,
+    /// Config file to read instead of the daemon's default location
+    pub file: Option<PathBuf>,
+    /// Print what would be imported without saving it
+    pub dry_run: bool,
+}
+
+impl Options {
+    /// Parse `import --from <daemon> [--file PATH] [--dry-run]`
+    ///
+    /// None if the import command was not requested, an error if it was
+    /// requested with invalid arguments.
+    pub fn from_args(args: impl IntoIterator<Item = String>) -> Option<Result<Self, String>> {
+        let mut args = args.into_iter().skip(1);
+        if args.next().as_deref() != Some("import") {
+            return None;
+        }
+
+        let mut source = None;
+        let mut file = None;
+        let mut dry_run = false;
+        while let Some(arg) = args.next() {
+            match arg.as_str() {
+                "--from" => match args.next().as_deref().map(Source::parse) {
+                    Some(Some(s)) => source = Some(s),
+                    _ => return Some(Err("--from expects dunst, mako or swaync".to_string())),
+                },
+                "--file" => match args.next() {
+                    Some(path) => file = Some(PathBuf::from(path)),
+                    None => return Some(Err("--file expects a path".to_string())),
+                },
+                "--dry-run" => dry_run = true,
+                other => return Some(Err(format!("unknown argument {other}"))),
+            }
+        }
+
+        Some(
+            source
+                .map(|source| Self {
+                    source,
+                    file,
+                    dry_run,
+                })
+                .ok_or_else(|| "missing --from <dunst|mako|swaync>".to_string()),
+        )
+    }
+}
+
+/// Settings translated from another daemon
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct Imported {
+    pub app_rules: Vec<AppRule>,
+    /// Default timeouts in milliseconds per urgency (low, normal, critical)
+    pub default_timeouts: [Option<u32>; 3],
+    /// Settings that could not be translated
+    pub warnings: Vec<String>,
+}
+
+impl Imported {
+    /// Merge into a config, replacing existing rules for the same app
+    pub fn apply(self, config: &mut NotificationsConfig) {
+        for rule in self.app_rules {
+            config
+                .app_rules
+                .retain(|r| r.app_name != rule.app_name || r.desktop_entry != rule.desktop_entry);
+            config.app_rules.push(rule);
+        }
+        let [low, normal, critical] = self.default_timeouts;
+        if let Some(ms) = low {
+            config.default_timeout_low = ms;
+        }
+        if let Some(ms) = normal {
+            config.default_timeout_normal = ms;
+        }
+        if let Some(ms) = critical {
+            config.default_timeout_urgent = ms;
+        }
+    }
+
+    /// Rule for an app, created on first use
+    fn rule_mut(&mut self, app_name: &str) -> &mut AppRule {
+        let index = match self.app_rules.iter().position(|r| r.app_name == app_name) {
+            Some(index) => index,
+            None => {
+                self.app_rules.push(AppRule {
+                    app_name: app_name.to_string(),
+                    ..Default::default()
+                });
+                self.app_rules.len() - 1
+            }
+        };
+        &mut self.app_rules[index]
+    }
+}
+
+/// Read and translate a config file
+pub fn import(source: Source, path: &Path) -> Result<Imported, String> {
+    let contents =
+        std::fs::read_to_string(path).map_err(|e| format!("cannot read {}: {e}", path.display()))?;
+    match source {
+        Source::Dunst => Ok(dunst::parse(&contents)),
+        Source::Mako => Ok(mako::parse(&contents)),
+        Source::Swaync => swaync::parse(&contents),
+    }
+}
+
+/// Run the import command, returning whether it succeeded
+pub fn run(options: Options) -> bool {
+    let path = options
+        .file
+        .clone()
+        .unwrap_or_else(|| options.source.default_path());
+    let imported = match import(options.source, &path) {
+        Ok(imported) => imported,
+        Err(err) => {
+            eprintln!("Import failed: {err}");
+            return false;
+        }
+    };
+
+    for warning in &imported.warnings {
+        eprintln!("warning: {warning}");
+    }
+    println!(
+        "Imported {} app rules from {}",
+        imported.app_rules.len(),
+        path.display()
+    );
+
+    if options.dry_run {
+        for rule in &imported.app_rules {
+            println!("{rule:?}");
+        }
+        println!("Default timeouts (low, normal, critical): {:?}", imported.default_timeouts);
+        return true;
+    }
+
+    let helper = match Config::new(cosmic_ext_notifications_config::ID, NotificationsConfig::VERSION) {
+        Ok(helper) => helper,
+        Err(err) => {
+            eprintln!("Cannot open config: {err:?}");
+            return false;
+        }
+    };
+    let mut config = NotificationsConfig::get_entry(&helper).unwrap_or_else(|(_, config)| config);
+    imported.apply(&mut config);
+    match config.write_entry(&helper) {
+        Ok(()) => true,
+        Err(err) => {
+            eprintln!("Cannot save config: {err:?}");
+            false
+        }
+    }
+}
+
+/// Urgency name used by dunst, mako and swaync to the spec level
+fn parse_urgency(value: &str) -> Option<u8> {
+    match value.trim().to_ascii_lowercase().as_str() {
+        "low" => Some(0),
+        "normal" => Some(1),
+        "critical" => Some(2),
+        _ => None,
+    }
+}
+
+/// The one name a dunst or swaync matcher stands for, or `None` if it is
+/// a pattern matching more than one
+///
+/// Escaped characters stand for themselves. With regexes a `.` is taken
+/// literally too, since it mostly appears in desktop entries written
+/// without escaping, and anchors are dropped.
+fn literal_matcher(value: &str, regex: bool) -> Option<String> {
+    let value = if regex {
+        let value = value.strip_prefix('^').unwrap_or(value);
+        value.strip_suffix('$').filter(|v| !v.ends_with('\\')).unwrap_or(value)
+    } else {
+        value
+    };
+    let special: &[char] = if regex {
+        &['*', '+', '?', '(', ')', '[', ']', '{', '}', '|', '^', '$']
+    } else {
+        &['*', '?', '[', ']']
+    };
+    let mut literal = String::with_capacity(value.len());
+    let mut chars = value.chars();
+    while let Some(c) = chars.next() {
+        match c {
+            '\\' => literal.push(chars.next()?),
+            c if special.contains(&c) => return None,
+            c => literal.push(c),
+        }
+    }
+    (!literal.is_empty()).then_some(literal)
+}
+
+/// Sections of an INI-style file as (header, key/value pairs)
+///
+/// Keys before the first header go into a section with an empty name.
+/// Comments start with `#` or `;`, surrounding quotes are removed.
+fn parse_ini(contents: &str) -> Vec<(String, Vec<(String, String)>)> {
+    let mut sections = vec![(String::new(), Vec::new())];
+    for line in contents.lines() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') || line.starts_with(';') {
+            continue;
+        }
+        if let Some(header) = line.strip_prefix('[').and_then(|l| l.strip_suffix(']')) {
+            sections.push((header.trim().to_string(), Vec::new()));
+            continue;
+        }
+        if let Some((key, value)) = line.split_once('=') {
+            let value = value.trim();
+            let value = value
+                .strip_prefix('"')
+                .and_then(|v| v.strip_suffix('"'))
+                .unwrap_or(value);
+            if let Some((_, entries)) = sections.last_mut() {
+                entries.push((key.trim().to_string(), value.to_string()));
+            }
+        }
+    }
+    sections
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn args(list: &[&str]) -> Vec<String> {
+        list.iter().map(|s| s.to_string()).collect()
+    }
+
+    #[test]
+    fn test_options_from_args() {
+        assert!(Options::from_args(args(&["bin"])).is_none());
+        assert!(Options::from_args(args(&["bin", "--self-test"])).is_none());
+
+        let options = Options::from_args(args(&["bin", "import", "--from", "mako", "--dry-run"]))
+            .unwrap()
+            .unwrap();
+        assert_eq!(options.source, Source::Mako);
+        assert!(options.dry_run);
+        assert!(options.file.is_none());
+
+        assert!(Options::from_args(args(&["bin", "import"])).unwrap().is_err());
+        assert!(Options::from_args(args(&["bin", "import", "--from", "xfce"])).unwrap().is_err());
+    }
+
+    #[test]
+    fn test_apply_replaces_rules_for_same_app() {
+        let mut config = NotificationsConfig::default();
+        config.app_rules.push(AppRule {
+            app_name: "Firefox".to_string(),
+            sound_enabled: false,
+            ..Default::default()
+        });
+
+        let mut imported = Imported::default();
+        imported.rule_mut("Firefox").enabled = false;
+        imported.default_timeouts = [Some(1000), None, Some(0)];
+        imported.apply(&mut config);
+
+        assert_eq!(config.app_rules.len(), 1);
+        assert!(!config.app_rules[0].enabled);
+        assert!(!config.is_app_enabled("Firefox", None));
+        assert!(config.app_rules[0].sound_enabled);
+        assert_eq!(config.default_timeout_low, 1000);
+        assert_eq!(config.default_timeout_normal, 5000);
+        assert_eq!(config.default_timeout_urgent, 0);
+    }
+}
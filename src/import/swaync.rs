@@ -0,0 +1,134 @@
+//! swaync `config.json` importer
+//!
+//! Timeouts are in seconds. `notification-visibility` entries that match
+//! one app name, and nothing else, become app rules. `app-name` is a
+//! regex, so entries whose pattern could match more than one app are
+//! skipped.
+
+use super::{literal_matcher, parse_urgency, Imported};
+use serde_json::Value;
+
+/// Matchers that narrow an entry to some of an app's notifications, which
+/// an app rule can't
+const UNSUPPORTED_MATCHERS: &[&str] = &["summary", "body", "urgency", "category", "desktop-entry"];
+
+pub(super) fn parse(contents: &str) -> Result<Imported, String> {
+    let config: Value =
+        serde_json::from_str(contents).map_err(|e| format!("invalid swaync config: {e}"))?;
+    let mut imported = Imported::default();
+
+    for (slot, key) in ["timeout-low", "timeout", "timeout-critical"].into_iter().enumerate() {
+        if let Some(seconds) = config.get(key).and_then(Value::as_u64) {
+            imported.default_timeouts[slot] = u32::try_from(seconds.saturating_mul(1000)).ok();
+        }
+    }
+
+    let Some(visibility) = config.get("notification-visibility").and_then(Value::as_object) else {
+        return Ok(imported);
+    };
+    for (name, entry) in visibility {
+        let Some(pattern) = entry.get("app-name").and_then(Value::as_str) else {
+            imported
+                .warnings
+                .push(format!("{name}: does not match an app name, skipped"));
+            continue;
+        };
+        if let Some(matcher) = UNSUPPORTED_MATCHERS.iter().find(|m| entry.get(m).is_some()) {
+            imported.warnings.push(format!(
+                "{name}: also matches on {matcher}, which app rules can't, skipped"
+            ));
+            continue;
+        }
+        let Some(app_name) = literal_matcher(pattern, true) else {
+            imported.warnings.push(format!(
+                "{name}: matches apps by pattern, which app rules can't, skipped"
+            ));
+            continue;
+        };
+
+        let mut warnings = Vec::new();
+        let rule = imported.rule_mut(&app_name);
+        match entry.get("state").and_then(Value::as_str) {
+            Some("ignored") => rule.enabled = false,
+            Some("muted") => {
+                rule.enabled = false;
+                warnings.push(format!(
+                    "{name}: muted notifications are hidden entirely, not only their popup"
+                ));
+            }
+            Some("enabled") | None => {}
+            Some(state) => warnings.push(format!("{name}: state {state:?} is not supported")),
+        }
+        if let Some(urgency) = entry.get("override-urgency").and_then(Value::as_str) {
+            match parse_urgency(urgency) {
+                Some(urgency) => rule.urgency_override = Some(urgency),
+                None => warnings.push(format!("{name}: unknown urgency {urgency:?}")),
+            }
+        }
+        imported.warnings.extend(warnings);
+    }
+
+    Ok(imported)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const CONFIG: &str = r#"{
+        "timeout": 10,
+        "timeout-low": 5,
+        "timeout-critical": 0,
+        "notification-visibility": {
+            "spotify": {
+                "state": "ignored",
+                "app-name": "Spotify"
+            },
+            "slack": {
+                "app-name": "Slack",
+                "override-urgency": "critical"
+            },
+            "by-summary": {
+                "summary": "Backup.*",
+                "state": "muted"
+            }
+        }
+    }"#;
+
+    #[test]
+    fn test_parse_config() {
+        let imported = parse(CONFIG).unwrap();
+        assert_eq!(imported.default_timeouts, [Some(5000), Some(10_000), Some(0)]);
+        assert_eq!(imported.app_rules.len(), 2);
+
+        let spotify = imported.app_rules.iter().find(|r| r.app_name == "Spotify").unwrap();
+        assert!(!spotify.enabled);
+        let slack = imported.app_rules.iter().find(|r| r.app_name == "Slack").unwrap();
+        assert_eq!(slack.urgency_override, Some(2));
+
+        assert!(imported.warnings.iter().any(|w| w.starts_with("by-summary")));
+    }
+
+    #[test]
+    fn test_skips_patterns_and_narrower_matchers() {
+        let imported = parse(
+            r#"{
+                "notification-visibility": {
+                    "discord": { "app-name": "^discord$", "state": "ignored" },
+                    "browsers": { "app-name": "(firefox|chromium)", "state": "ignored" },
+                    "backups": { "app-name": "Deja Dup", "summary": "Backup.*", "state": "muted" }
+                }
+            }"#,
+        )
+        .unwrap();
+        assert_eq!(imported.app_rules.len(), 1);
+        assert_eq!(imported.app_rules[0].app_name, "discord");
+        assert!(imported.warnings.iter().any(|w| w.starts_with("browsers")));
+        assert!(imported.warnings.iter().any(|w| w.starts_with("backups") && w.contains("summary")));
+    }
+
+    #[test]
+    fn test_invalid_json() {
+        assert!(parse("{ not json").is_err());
+    }
+}
@@ -0,0 +1,213 @@
+//! Audit trail of notifications in the systemd journal
+//!
+//! With `journal_audit` on, every notification and what became of it is
+//! written to the journal as structured fields, so delivery of alerts on
+//! kiosks and servers can be checked with `journalctl`. The summary is
+//! only recorded as a SHA-256 hash; bodies are never recorded.
+//!
+//! Entries go straight to journald's native socket rather than through
+//! `tracing`, so they are kept whatever the log level. Only built with the
+//! `systemd` feature.
+
+use crate::state::stats::{AuditOutcome, Outcome};
+use cosmic_ext_notifications_util::Notification;
+use sha2::{Digest, Sha256};
+use std::{io, os::unix::net::UnixDatagram};
+
+const JOURNAL_SOCKET: &str = "/run/systemd/journal/socket";
+
+/// `MESSAGE_ID` of every audit entry, to find them with
+/// `journalctl MESSAGE_ID=…`
+pub const AUDIT_MESSAGE_ID: &str = "8d2c4f1e6a3b47c59e0f7b12d4a6c839";
+
+fn outcome_name(outcome: AuditOutcome) -> &'static str {
+    match outcome {
+        AuditOutcome::Counted(Outcome::Received) => "received",
+        AuditOutcome::Counted(Outcome::Dismissed) => "dismissed",
+        AuditOutcome::Counted(Outcome::Actioned) => "actioned",
+        AuditOutcome::Counted(Outcome::Expired) => "expired",
+        AuditOutcome::SuppressedByDnd => "suppressed_by_dnd",
+        AuditOutcome::RateLimited => "rate_limited",
+        AuditOutcome::DroppedByPlugin => "dropped_by_plugin",
+        AuditOutcome::Held => "held",
+        AuditOutcome::Quieted => "quieted",
+    }
+}
+
+/// Human-readable form of an outcome, for `MESSAGE`
+fn outcome_message(outcome: AuditOutcome) -> &'static str {
+    match outcome {
+        AuditOutcome::SuppressedByDnd => "suppressed by do-not-disturb",
+        AuditOutcome::RateLimited => "rate-limited",
+        AuditOutcome::DroppedByPlugin => "dropped by a plugin",
+        outcome => outcome_name(outcome),
+    }
+}
+
+/// Append a field in journald's native format
+///
+/// Values with a newline use the length-prefixed form.
+fn push_field(entry: &mut Vec<u8>, key: &str, value: &str) {
+    entry.extend_from_slice(key.as_bytes());
+    if value.contains('\n') {
+        entry.push(b'\n');
+        entry.extend_from_slice(&(value.len() as u64).to_le_bytes());
+    } else {
+        entry.push(b'=');
+    }
+    entry.extend_from_slice(value.as_bytes());
+    entry.push(b'\n');
+}
+
+/// Fields every audit entry starts with
+fn header(entry: &mut Vec<u8>, message: &str, priority: &str) {
+    push_field(entry, "MESSAGE", message);
+    push_field(entry, "MESSAGE_ID", AUDIT_MESSAGE_ID);
+    push_field(entry, "PRIORITY", priority);
+    push_field(entry, "SYSLOG_IDENTIFIER", "cosmic-ext-notifications");
+}
+
+fn entry(n: &Notification, outcome: AuditOutcome) -> Vec<u8> {
+    let summary_hash: String = Sha256::digest(n.summary.as_bytes())
+        .iter()
+        .map(|byte| format!("{byte:02x}"))
+        .collect();
+    // Critical notifications log at "warning", the rest at "info"
+    let priority = if n.urgency() >= 2 { "4" } else { "6" };
+
+    let mut entry = Vec::new();
+    header(
+        &mut entry,
+        &format!("Notification {} from {} {}", n.id, n.app_name, outcome_message(outcome)),
+        priority,
+    );
+    push_field(&mut entry, "NOTIFICATION_ID", &n.id.to_string());
+    push_field(&mut entry, "NOTIFICATION_APP", &n.app_name);
+    if let Some(desktop_entry) = n.desktop_entry() {
+        push_field(&mut entry, "NOTIFICATION_DESKTOP_ENTRY", desktop_entry);
+    }
+    if let Some(category) = n.category() {
+        push_field(&mut entry, "NOTIFICATION_CATEGORY", category);
+    }
+    push_field(&mut entry, "NOTIFICATION_URGENCY", &n.urgency().to_string());
+    push_field(&mut entry, "NOTIFICATION_SUMMARY_SHA256", &summary_hash);
+    push_field(&mut entry, "NOTIFICATION_OUTCOME", outcome_name(outcome));
+    entry
+}
+
+/// Entry for `count` notifications from `app_name` the server dropped for
+/// arriving too fast; it never passes them on, so there is nothing more to
+/// record about them
+fn rate_limited_entry(app_name: &str, count: u32) -> Vec<u8> {
+    let mut entry = Vec::new();
+    header(
+        &mut entry,
+        &format!("{count} notifications from {app_name} rate-limited"),
+        "4",
+    );
+    push_field(&mut entry, "NOTIFICATION_APP", app_name);
+    push_field(&mut entry, "NOTIFICATION_COUNT", &count.to_string());
+    push_field(
+        &mut entry,
+        "NOTIFICATION_OUTCOME",
+        outcome_name(AuditOutcome::RateLimited),
+    );
+    entry
+}
+
+/// Connection to journald for audit entries
+#[derive(Debug)]
+pub struct JournalSink {
+    socket: UnixDatagram,
+}
+
+impl JournalSink {
+    pub fn connect() -> io::Result<Self> {
+        let socket = UnixDatagram::unbound()?;
+        socket.connect(JOURNAL_SOCKET)?;
+        Ok(Self { socket })
+    }
+
+    /// Record what became of `n`; failures are only logged
+    pub fn record(&self, n: &Notification, outcome: AuditOutcome) {
+        if let Err(err) = self.socket.send(&entry(n, outcome)) {
+            tracing::warn!("Failed to write notification {} to the journal: {}", n.id, err);
+        }
+    }
+
+    /// Record that `count` notifications from `app_name` were rate-limited;
+    /// failures are only logged
+    pub fn record_rate_limited(&self, app_name: &str, count: u32) {
+        if let Err(err) = self.socket.send(&rate_limited_entry(app_name, count)) {
+            tracing::warn!("Failed to write rate-limited notifications to the journal: {}", err);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use cosmic_ext_notifications_util::Hint;
+    use std::time::SystemTime;
+
+    fn notification(summary: &str) -> Notification {
+        Notification {
+            id: 12,
+            app_name: "Backup".to_string(),
+            app_icon: String::new(),
+            summary: summary.to_string(),
+            body: "secret body".to_string(),
+            actions: vec![],
+            hints: vec![Hint::Urgency(2), Hint::DesktopEntry("org.example.Backup".to_string())],
+            expire_timeout: -1,
+            applied_timeout: None,
+            time: SystemTime::now(),
+        }
+    }
+
+    #[test]
+    fn test_entry_fields() {
+        let entry = entry(&notification("abc"), Outcome::Expired.into());
+        let text = String::from_utf8(entry).unwrap();
+        let fields: Vec<&str> = text.lines().collect();
+        assert!(fields.contains(&"MESSAGE=Notification 12 from Backup expired"));
+        assert!(fields.contains(&"PRIORITY=4"));
+        assert!(fields.contains(&"NOTIFICATION_DESKTOP_ENTRY=org.example.Backup"));
+        assert!(fields.contains(&"NOTIFICATION_OUTCOME=expired"));
+        assert!(fields.contains(
+            &"NOTIFICATION_SUMMARY_SHA256=ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad"
+        ));
+        assert!(!text.contains("secret body"));
+        assert!(!fields.iter().any(|f| f.starts_with("NOTIFICATION_CATEGORY")));
+    }
+
+    #[test]
+    fn test_suppressed_entry() {
+        let entry = entry(&notification("abc"), AuditOutcome::SuppressedByDnd);
+        let text = String::from_utf8(entry).unwrap();
+        let fields: Vec<&str> = text.lines().collect();
+        assert!(fields.contains(&"MESSAGE=Notification 12 from Backup suppressed by do-not-disturb"));
+        assert!(fields.contains(&"NOTIFICATION_OUTCOME=suppressed_by_dnd"));
+    }
+
+    #[test]
+    fn test_rate_limited_entry() {
+        let text = String::from_utf8(rate_limited_entry("Chat", 7)).unwrap();
+        let fields: Vec<&str> = text.lines().collect();
+        assert!(fields.contains(&"MESSAGE=7 notifications from Chat rate-limited"));
+        assert!(fields.contains(&format!("MESSAGE_ID={AUDIT_MESSAGE_ID}").as_str()));
+        assert!(fields.contains(&"NOTIFICATION_APP=Chat"));
+        assert!(fields.contains(&"NOTIFICATION_COUNT=7"));
+        assert!(fields.contains(&"NOTIFICATION_OUTCOME=rate_limited"));
+    }
+
+    #[test]
+    fn test_multiline_values_are_length_prefixed() {
+        let mut entry = Vec::new();
+        push_field(&mut entry, "MESSAGE", "a\nb");
+        let mut expected = b"MESSAGE\n".to_vec();
+        expected.extend_from_slice(&3u64.to_le_bytes());
+        expected.extend_from_slice(b"a\nb\n");
+        assert_eq!(entry, expected);
+    }
+}
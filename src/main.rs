@@ -1,12 +1,28 @@
 mod app;
+mod compositor;
 mod config;
 mod constants;
+#[cfg(feature = "audio")]
+mod ducking;
+#[cfg(feature = "email")]
+mod email;
+mod import;
 mod localize;
 mod subscriptions;
 mod widgets;
 mod state;
 mod handlers;
+mod hooks;
+#[cfg(feature = "systemd")]
+mod journal;
+mod phone;
+mod portal;
 mod rendering;
+mod self_test;
+mod sender;
+mod tts;
+#[cfg(feature = "webhook")]
+mod webhook;
 
 use config::APP_ID;
 use tracing::{info, metadata::LevelFilter};
@@ -44,6 +60,23 @@ fn main() -> anyhow::Result<()> {
     // Prepare i18n
     localize();
 
+    if let Some(options) = import::Options::from_args(std::env::args()) {
+        let passed = match options {
+            Ok(options) => import::run(options),
+            Err(err) => {
+                eprintln!("{err}");
+                eprintln!("Usage: cosmic-ext-notifications import --from <dunst|mako|swaync> [--file PATH] [--dry-run]");
+                false
+            }
+        };
+        std::process::exit(if passed { 0 } else { 1 });
+    }
+
+    if let Some(options) = self_test::Options::from_args(std::env::args()) {
+        let passed = self_test::run(options);
+        std::process::exit(if passed { 0 } else { 1 });
+    }
+
     app::run()?;
     Ok(())
 }
@@ -0,0 +1,178 @@
+//! Forward notifications to a paired phone through KDE Connect or Valent
+//!
+//! KDE Connect can only show a message through its ping plugin, so
+//! notifications it forwards can't be dismissed from the desktop. Valent
+//! takes real notifications through its device's `notification.send`
+//! action, and drops them again through `notification.cancel`.
+
+use cosmic_ext_notifications_config::{PhoneBridge, PhoneForwarding};
+use std::collections::HashMap;
+use tokio::sync::OnceCell;
+use zbus::{Connection, zvariant::Value};
+
+#[zbus::proxy(
+    interface = "org.kde.kdeconnect.device.ping",
+    default_service = "org.kde.kdeconnect"
+)]
+trait KdeConnectPing {
+    #[zbus(name = "sendPing")]
+    fn send_ping(&self, message: &str) -> zbus::Result<()>;
+}
+
+#[zbus::proxy(
+    interface = "org.gtk.Actions",
+    default_service = "ca.andyholmes.Valent"
+)]
+trait ValentDevice {
+    fn activate(
+        &self,
+        action_name: &str,
+        parameter: Vec<Value<'_>>,
+        platform_data: HashMap<&str, Value<'_>>,
+    ) -> zbus::Result<()>;
+}
+
+/// A notification on its way to the phone, in plain text
+#[derive(Debug, Clone)]
+pub struct Forwarded {
+    pub id: u32,
+    pub app_name: String,
+    pub summary: String,
+    pub body: String,
+}
+
+impl Forwarded {
+    /// The single message KDE Connect's ping shows
+    fn ping_message(&self) -> String {
+        let mut message = format!("{}: {}", self.app_name, self.summary);
+        if !self.body.is_empty() {
+            message.push('\n');
+            message.push_str(&self.body);
+        }
+        message
+    }
+}
+
+static SESSION: OnceCell<Connection> = OnceCell::const_new();
+
+async fn session() -> zbus::Result<&'static Connection> {
+    SESSION.get_or_try_init(Connection::session).await
+}
+
+/// Object path of a Valent device, escaped like `g_dbus_escape_object_path`
+fn valent_device_path(device_id: &str) -> String {
+    let mut path = String::from("/ca/andyholmes/Valent/Device/");
+    for byte in device_id.bytes() {
+        if byte.is_ascii_alphanumeric() {
+            path.push(char::from(byte));
+        } else {
+            path.push_str(&format!("_{byte:02x}"));
+        }
+    }
+    path
+}
+
+/// Id of a forwarded notification on the phone
+fn phone_id(id: u32) -> String {
+    format!("cosmic-ext-notifications-{id}")
+}
+
+async fn valent_device(device_id: &str) -> zbus::Result<ValentDeviceProxy<'static>> {
+    ValentDeviceProxy::builder(session().await?)
+        .path(valent_device_path(device_id))?
+        .build()
+        .await
+}
+
+async fn send(settings: &PhoneForwarding, notification: &Forwarded) -> zbus::Result<()> {
+    match settings.bridge {
+        PhoneBridge::KdeConnect => {
+            let path = format!("/modules/kdeconnect/devices/{}/ping", settings.device_id);
+            KdeConnectPingProxy::builder(session().await?)
+                .path(path)?
+                .build()
+                .await?
+                .send_ping(&notification.ping_message())
+                .await
+        }
+        PhoneBridge::Valent => {
+            let parameter = HashMap::from([
+                ("id", Value::from(phone_id(notification.id))),
+                ("application", Value::from(notification.app_name.as_str())),
+                ("title", Value::from(notification.summary.as_str())),
+                ("body", Value::from(notification.body.as_str())),
+            ]);
+            valent_device(&settings.device_id)
+                .await?
+                .activate(
+                    "notification.send",
+                    vec![Value::from(parameter)],
+                    HashMap::new(),
+                )
+                .await
+        }
+    }
+}
+
+/// Show `notification` on the phone; failures are only logged
+pub async fn forward(settings: PhoneForwarding, notification: Forwarded) {
+    if let Err(err) = send(&settings, &notification).await {
+        tracing::warn!(
+            "Failed to forward notification {} to the phone: {}",
+            notification.id,
+            err
+        );
+    }
+}
+
+/// Take a forwarded notification off the phone again, where the bridge
+/// allows it; failures are only logged
+pub async fn withdraw(settings: PhoneForwarding, id: u32) {
+    if settings.bridge != PhoneBridge::Valent {
+        return;
+    }
+    let result = async {
+        valent_device(&settings.device_id)
+            .await?
+            .activate(
+                "notification.cancel",
+                vec![Value::from(phone_id(id))],
+                HashMap::new(),
+            )
+            .await
+    }
+    .await;
+    if let Err(err) = result {
+        tracing::warn!("Failed to dismiss notification {id} on the phone: {}", err);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_valent_device_path() {
+        assert_eq!(
+            valent_device_path("a1b2c3"),
+            "/ca/andyholmes/Valent/Device/a1b2c3"
+        );
+        assert_eq!(
+            valent_device_path("my-phone_1"),
+            "/ca/andyholmes/Valent/Device/my_2dphone_5f1"
+        );
+    }
+
+    #[test]
+    fn test_ping_message() {
+        let mut forwarded = Forwarded {
+            id: 1,
+            app_name: "Mail".to_string(),
+            summary: "Invoice due".to_string(),
+            body: "Pay by Friday".to_string(),
+        };
+        assert_eq!(forwarded.ping_message(), "Mail: Invoice due\nPay by Friday");
+        forwarded.body.clear();
+        assert_eq!(forwarded.ping_message(), "Mail: Invoice due");
+    }
+}
@@ -0,0 +1,51 @@
+//! Minimal client for XDG desktop portals
+//!
+//! Only the calls the daemon needs are implemented, using the existing zbus
+//! dependency rather than a full portal crate.
+
+use std::collections::HashMap;
+use tokio::sync::OnceCell;
+use zbus::{Connection, zvariant::Value};
+
+const PORTAL_DESTINATION: &str = "org.freedesktop.portal.Desktop";
+const PORTAL_PATH: &str = "/org/freedesktop/portal/desktop";
+
+static SESSION: OnceCell<Connection> = OnceCell::const_new();
+
+async fn session() -> zbus::Result<&'static Connection> {
+    SESSION.get_or_try_init(Connection::session).await
+}
+
+/// Open a URI with the user's preferred handler via the OpenURI portal
+///
+/// Works for schemes like `tel:` and `mailto:` that are not opened as links.
+pub async fn open_uri(uri: &str) -> zbus::Result<()> {
+    let conn = session().await?;
+    let options: HashMap<&str, Value<'_>> = HashMap::new();
+    conn.call_method(
+        Some(PORTAL_DESTINATION),
+        PORTAL_PATH,
+        Some("org.freedesktop.portal.OpenURI"),
+        "OpenURI",
+        &("", uri, options),
+    )
+    .await?;
+    Ok(())
+}
+
+/// Open a local file with the user's preferred application via the
+/// OpenURI portal, allowing it to be edited
+pub async fn open_file(path: &std::path::Path) -> zbus::Result<()> {
+    let file = std::fs::OpenOptions::new().read(true).write(true).open(path)?;
+    let conn = session().await?;
+    let options: HashMap<&str, Value<'_>> = HashMap::from([("writable", Value::from(true))]);
+    conn.call_method(
+        Some(PORTAL_DESTINATION),
+        PORTAL_PATH,
+        Some("org.freedesktop.portal.OpenURI"),
+        "OpenFile",
+        &("", zbus::zvariant::Fd::from(&file), options),
+    )
+    .await?;
+    Ok(())
+}
@@ -1,49 +1,172 @@
 use crate::handlers::Message;
-use crate::widgets::{notification_image, ImageSize};
+use crate::state::{AnimatedHandles, DecodedImage, ImageSlot};
+use crate::widgets::{notification_frame, notification_image, placeholder_image, ImageSize};
 use crate::constants::*;
-use cosmic::iced::Length;
-use cosmic::iced_widget::{column, container};
+use cosmic::iced::{Alignment, Background, Color, Length, Padding};
+use cosmic::iced_widget::{column, container, stack, Space};
 use cosmic::widget::{icon, text};
 use cosmic::Element;
+use cosmic_ext_notifications_config::AnimationStyle;
 use cosmic_ext_notifications_util::{
-    parse_markup, sanitize_html, Image, Notification, NotificationImage,
-    NotificationLink, ProcessedImage,
+    is_link_text_mismatch, sanitize_and_parse, AnimatedImage, ContactKind, DetectedContact, DetectedSecret,
+    Image, ImagePolicy, Notification, NotificationImage, NotificationLink, SecretKind,
 };
+use std::sync::Arc;
+use std::time::{Duration, Instant};
 
-/// Render notification image from Image hint
+/// Decode a notification image and apply `policy` to it
+///
+/// Slow for large files and pixel buffers, so it runs on a worker thread;
+/// either way the result is scaled down to thumbnail size. `None` for icon
+/// names, which need no decoding, and for images that can't be read.
 ///
-/// Uses Expanded size (128x128) for better visibility with text content
-pub fn render_notification_image(image: &Image) -> Option<Element<'static, Message>> {
-    match image {
+/// With `animate` set, GIF and APNG files keep all their frames; anything
+/// else, or an animation that fails to decode, ends up as a still image.
+pub fn decode_image(image: &Image, policy: &ImagePolicy, animate: bool) -> Option<DecodedImage> {
+    if animate {
+        if let Image::File(path) = image {
+            if let Some(animation) = AnimatedImage::from_path(path) {
+                let animation = animation.map_frames(|frame| NotificationImage::apply_policy(frame, policy));
+                return Some(DecodedImage::Animated(Arc::new(AnimatedHandles::new(animation))));
+            }
+        }
+    }
+
+    let processed = match image {
         Image::Data { width, height, data } => {
-            // Create ProcessedImage from raw data
-            // Clone the inner Vec from Arc - only happens during rendering
-            let processed = ProcessedImage {
-                data: (**data).clone(),
-                width: *width,
-                height: *height,
+            // Tightly packed RGBA, so each row is four bytes per pixel
+            let (Ok(w), Ok(h), Ok(rowstride)) = (
+                i32::try_from(*width),
+                i32::try_from(*height),
+                i32::try_from(u64::from(*width) * 4),
+            ) else {
+                tracing::warn!("Notification image data too large: {}x{}", width, height);
+                return None;
             };
-            Some(notification_image(&processed, ImageSize::Expanded))
-        }
-        Image::File(path) => {
-            // Try to load image from file
-            match NotificationImage::from_path(path.to_str().unwrap_or_default()) {
-                Ok(processed) => Some(notification_image(&processed, ImageSize::Expanded)),
+            match NotificationImage::from_raw_data(data, w, h, rowstride, true) {
+                Ok(processed) => processed,
                 Err(e) => {
-                    tracing::warn!("Failed to load notification image from {}: {}", path.display(), e);
-                    None
+                    tracing::warn!("Failed to load notification image data: {}", e);
+                    return None;
                 }
             }
         }
-        Image::Name(name) => {
-            // Use icon from name - 96x96 to match text height
-            Some(
-                container(icon::from_name(name.as_str()).size(96).icon())
-                    .width(Length::Fixed(96.0))
-                    .height(Length::Fixed(96.0))
-                    .into()
-            )
+        Image::File(path) => match NotificationImage::from_path(path.to_str().unwrap_or_default()) {
+            Ok(processed) => processed,
+            Err(e) => {
+                tracing::warn!("Failed to load notification image from {}: {}", path.display(), e);
+                return None;
+            }
+        },
+        Image::Name(_) => return None,
+    };
+    Some(DecodedImage::Still(Arc::new(NotificationImage::apply_policy(processed, policy))))
+}
+
+/// Render notification image from Image hint
+///
+/// Uses Expanded size (128x128) for better visibility with text content.
+/// Decoded images come from the image cache; while one is still being
+/// decoded an empty slot of the same size holds its place. Animations show
+/// the frame due at `now` while `play` is set and they're within
+/// [`ANIMATION_PLAY_MS`], and their first frame otherwise.
+pub fn render_notification_image(
+    image: &Image,
+    slot: Option<&ImageSlot>,
+    now: Instant,
+    play: bool,
+) -> Option<Element<'static, Message>> {
+    if let Image::Name(name) = image {
+        // Use icon from name - 96x96 to match text height
+        return Some(
+            container(icon::from_name(name.as_str()).size(96).icon())
+                .width(Length::Fixed(96.0))
+                .height(Length::Fixed(96.0))
+                .into()
+        );
+    }
+    match slot? {
+        ImageSlot::Ready(processed) => Some(notification_image(processed, ImageSize::Expanded)),
+        ImageSlot::Animated { animation, started } => {
+            let elapsed = now.saturating_duration_since(*started);
+            let frame = if play && elapsed < Duration::from_millis(ANIMATION_PLAY_MS) {
+                animation.frame_at(elapsed.as_millis() as u32)
+            } else {
+                animation.first_frame()
+            }?;
+            Some(notification_frame(frame, ImageSize::Expanded))
+        }
+        ImageSlot::Pending => {
+            let (width, height) = ImageSize::Expanded.dimensions();
+            Some(placeholder_image(width, height))
+        }
+        ImageSlot::Failed => None,
+    }
+}
+
+/// Overlay a small app badge on the bottom-right corner of a sender avatar
+///
+/// Used for chat notifications that carry both a sender picture and an app
+/// icon, so the person is the primary image and the app stays recognizable.
+pub fn render_avatar_with_badge(
+    avatar: Element<'static, Message>,
+    badge_icon: &str,
+) -> Element<'static, Message> {
+    let badge = container(icon::from_name(badge_icon).size(AVATAR_BADGE_SIZE).icon())
+        .width(Length::Fill)
+        .height(Length::Fill)
+        .align_x(Alignment::End)
+        .align_y(Alignment::End);
+
+    stack![avatar, badge].into()
+}
+
+/// Present a popup card that is `shown` of the way onto the screen
+///
+/// Sliding shifts the card toward the screen edge it came from, fading
+/// veils it in the popup background and scaling squeezes it towards the
+/// middle. Fully shown cards are returned as they are.
+pub fn render_transition(
+    card: Element<'static, Message>,
+    style: AnimationStyle,
+    shown: f32,
+    from_left: bool,
+) -> Element<'static, Message> {
+    if shown >= 1.0 {
+        return card;
+    }
+    let hidden = 1.0 - shown.max(0.0);
+    match style {
+        AnimationStyle::SlideFromEdge => {
+            let offset = hidden * NOTIFICATION_WIDTH;
+            let padding = if from_left {
+                Padding { right: offset, ..Padding::ZERO }
+            } else {
+                Padding { left: offset, ..Padding::ZERO }
+            };
+            container(card).padding(padding).into()
         }
+        AnimationStyle::Fade => {
+            let veil = container(Space::new(Length::Fill, Length::Fill))
+                .width(Length::Fill)
+                .height(Length::Fill)
+                .class(cosmic::theme::Container::custom(move |theme| {
+                    let mut color: Color = theme.cosmic().background.base.into();
+                    color.a = hidden;
+                    container::Style {
+                        background: Some(Background::Color(color)),
+                        ..Default::default()
+                    }
+                }));
+            stack![card, veil].into()
+        }
+        AnimationStyle::Scale => {
+            let inset = hidden * NOTIFICATION_WIDTH / 2.0;
+            container(card)
+                .padding(Padding { left: inset, right: inset, ..Padding::ZERO })
+                .into()
+        }
+        AnimationStyle::None => card,
     }
 }
 
@@ -53,13 +176,25 @@ pub fn render_notification_image(image: &Image) -> Option<Element<'static, Messa
 /// The markup is processed and validated even though current cosmic widgets
 /// don't support styled text rendering.
 pub fn render_markup_body(body_html: &str) -> Element<'static, Message> {
-    let sanitized = sanitize_html(body_html);
-    let segments = parse_markup(&sanitized);
+    let segments = sanitize_and_parse(body_html);
 
     // Convert segments to plain text
     // Note: Rich text styling (bold/italic) would require cosmic widget support
     // that currently isn't available. The markup is still processed and validated.
-    let plain_text: String = segments.iter().map(|s| s.text.as_str()).collect();
+    // Quoted lines get a bar per quote level so replies stand out.
+    let mut plain_text = String::new();
+    for segment in &segments {
+        for (i, line) in segment.text.split('\n').enumerate() {
+            if i > 0 {
+                plain_text.push('\n');
+            }
+            let at_line_start = plain_text.is_empty() || plain_text.ends_with('\n');
+            if segment.style.is_quoted() && at_line_start && !line.is_empty() {
+                plain_text.push_str(&QUOTE_MARKER.repeat(segment.style.quote_level as usize));
+            }
+            plain_text.push_str(line);
+        }
+    }
 
     if plain_text.is_empty() {
         return text::caption("").width(Length::Fill).into();
@@ -95,7 +230,7 @@ pub fn render_body_with_links(
             url.clone()
         };
 
-        let link_button: Element<'static, Message> = button::text(format!("🔗 {}", display_url))
+        let link_button: Element<'static, Message> = button::text(format!("{} {}", link_marker(link), display_url))
             .on_press(Message::LinkClicked(url))
             .class(cosmic::theme::Button::Link)
             .padding([2, 4])
@@ -118,7 +253,7 @@ pub fn render_body_with_links(
             url.clone()
         };
 
-        let link_button: Element<'static, Message> = button::text(format!("🔗 {}", display_url))
+        let link_button: Element<'static, Message> = button::text(format!("{} {}", link_marker(link), display_url))
             .on_press(Message::LinkClicked(url))
             .class(cosmic::theme::Button::Link)
             .padding([2, 4])
@@ -146,6 +281,106 @@ pub fn render_body_with_links(
         .into()
 }
 
+/// Render quick action buttons for detected phone numbers and emails
+///
+/// Phone numbers get "Call" and "Copy number", emails get "Compose email".
+/// Only the first [`MAX_CONTACT_ACTIONS`] contacts are shown.
+pub fn render_contact_actions(contacts: &[DetectedContact]) -> Option<Element<'static, Message>> {
+    use cosmic::widget::button;
+
+    let mut buttons: Vec<Element<'static, Message>> = Vec::new();
+    for contact in contacts.iter().take(MAX_CONTACT_ACTIONS) {
+        match contact.kind {
+            ContactKind::Phone => {
+                buttons.push(
+                    button::text(crate::fl!("contact-call"))
+                        .on_press(Message::OpenUri(contact.uri.clone()))
+                        .padding([6, 12])
+                        .into(),
+                );
+                buttons.push(
+                    button::text(crate::fl!("contact-copy-number"))
+                        .on_press(Message::CopyToClipboard(contact.value.clone()))
+                        .padding([6, 12])
+                        .into(),
+                );
+            }
+            ContactKind::Email => {
+                buttons.push(
+                    button::text(crate::fl!("contact-compose-email"))
+                        .on_press(Message::OpenUri(contact.uri.clone()))
+                        .padding([6, 12])
+                        .into(),
+                );
+            }
+        }
+    }
+
+    if buttons.is_empty() {
+        return None;
+    }
+
+    Some(
+        buttons
+            .into_iter()
+            .fold(
+                cosmic::iced_widget::Row::new()
+                    .spacing(8)
+                    .align_y(cosmic::iced::Alignment::Center),
+                |row, elem| row.push(elem),
+            )
+            .into(),
+    )
+}
+
+/// Render the buttons for a notification with masked secrets
+///
+/// "Show" reveals the codes and links on the card, "Hide" masks them
+/// again; the copy button copies the first secret whether it's shown or not.
+pub fn render_secret_actions(id: u32, secrets: &[DetectedSecret], revealed: bool) -> Option<Element<'static, Message>> {
+    use cosmic::widget::button;
+
+    let first = secrets.first()?;
+    let toggle = button::text(if revealed {
+        crate::fl!("secret-hide")
+    } else {
+        crate::fl!("secret-show")
+    })
+    .on_press(Message::ToggleSecrets(id))
+    .padding([6, 12]);
+    let copy = button::text(match first.kind {
+        SecretKind::Code => crate::fl!("secret-copy-code"),
+        SecretKind::Link => crate::fl!("secret-copy-link"),
+    })
+    .on_press(Message::CopyToClipboard(first.value.clone()))
+    .padding([6, 12]);
+
+    Some(
+        cosmic::iced_widget::Row::new()
+            .push(toggle)
+            .push(copy)
+            .spacing(8)
+            .align_y(cosmic::iced::Alignment::Center)
+            .into(),
+    )
+}
+
+/// Marker shown before a link button
+///
+/// Links whose text names a different host than their URL get a warning sign
+/// so the real destination shown on the button stands out.
+fn link_marker(link: &NotificationLink) -> &'static str {
+    if link
+        .title
+        .as_deref()
+        .is_some_and(|text| is_link_text_mismatch(text, &link.url))
+    {
+        "⚠️"
+    } else {
+        "🔗"
+    }
+}
+
 /// Extract progress value from notification hints
 pub fn get_progress_from_hints(n: &Notification) -> Option<f32> {
     use cosmic_ext_notifications_util::Hint;
@@ -201,3 +436,32 @@ where
         _ => combiner(elements),
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use cosmic_ext_notifications_util::{MAX_IMAGE_HEIGHT, MAX_IMAGE_WIDTH};
+
+    #[test]
+    fn test_oversized_image_data_is_downscaled() {
+        let image = Image::Data {
+            width: 1024,
+            height: 512,
+            data: Arc::new(vec![200u8; 1024 * 512 * 4]),
+        };
+        let Some(DecodedImage::Still(processed)) = decode_image(&image, &ImagePolicy::default(), false)
+        else {
+            panic!("image data should decode to a still image");
+        };
+        assert_eq!((processed.width, processed.height), (MAX_IMAGE_WIDTH, MAX_IMAGE_HEIGHT / 2));
+        assert_eq!(processed.data.len(), (MAX_IMAGE_WIDTH * MAX_IMAGE_HEIGHT / 2 * 4) as usize);
+
+        // A buffer shorter than its dimensions claim is refused
+        let short = Image::Data {
+            width: 1024,
+            height: 512,
+            data: Arc::new(vec![0u8; 16]),
+        };
+        assert!(decode_image(&short, &ImagePolicy::default(), false).is_none());
+    }
+}
@@ -0,0 +1,82 @@
+//! Localized labels with counts and numbers
+//!
+//! Every user-visible string that embeds a number goes through fluent here,
+//! so plural rules and number formatting follow the user's language instead
+//! of English-only `format!` strings.
+
+use cosmic_ext_notifications_config::SnoozePreset;
+use std::time::Duration;
+
+/// Relative age of a notification ("just now", "2 min ago", "3 days ago")
+pub fn relative_time(age: Duration) -> String {
+    let secs = age.as_secs();
+    if secs < 60 {
+        crate::fl!("time-now")
+    } else if secs < 3600 {
+        crate::fl!("time-minutes", minutes = secs / 60)
+    } else if secs < 86400 {
+        crate::fl!("time-hours", hours = secs / 3600)
+    } else {
+        crate::fl!("time-days", days = secs / 86400)
+    }
+}
+
+/// Group header with its size, e.g. "Firefox (3)"
+///
+/// Single notifications show just the name.
+pub fn group_label(name: &str, count: usize) -> String {
+    if count > 1 {
+        crate::fl!("group-label", name = name, count = count)
+    } else {
+        name.to_string()
+    }
+}
+
+/// How often the same notification arrived, e.g. "×3"
+pub fn repeat_label(count: u32) -> String {
+    crate::fl!("repeat-count", count = count)
+}
+
+/// Collapsed overflow indicator, e.g. "+12 more"
+pub fn more_label(count: usize) -> String {
+    crate::fl!("more-notifications", count = count)
+}
+
+/// Percentage text for progress bars, e.g. "42%"
+pub fn percent_label(percent: u32) -> String {
+    crate::fl!("progress-percent", percent = percent.min(100))
+}
+
+/// Snooze duration on its button, e.g. "5 minutes", "1 hour", "Tomorrow"
+pub fn snooze_label(preset: SnoozePreset) -> String {
+    match preset {
+        SnoozePreset::Minutes(minutes) if minutes >= 60 && minutes % 60 == 0 => {
+            crate::fl!("snooze-hours", hours = minutes / 60)
+        }
+        SnoozePreset::Minutes(minutes) => crate::fl!("snooze-minutes", minutes = minutes),
+        SnoozePreset::Tomorrow => crate::fl!("snooze-tomorrow"),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_labels_include_counts() {
+        assert!(relative_time(Duration::from_secs(5 * 60)).contains('5'));
+        assert!(relative_time(Duration::from_secs(2 * 86400)).contains('2'));
+        assert!(group_label("Firefox", 3).contains("Firefox"));
+        assert!(group_label("Firefox", 3).contains('3'));
+        assert!(more_label(12).contains("12"));
+        assert!(repeat_label(3).contains('3'));
+        assert!(percent_label(150).contains("100"));
+        assert!(snooze_label(SnoozePreset::Minutes(5)).contains('5'));
+        assert!(snooze_label(SnoozePreset::Minutes(120)).contains('2'));
+    }
+
+    #[test]
+    fn test_single_notification_group_has_no_count() {
+        assert_eq!(group_label("Firefox", 1), "Firefox");
+    }
+}
@@ -1,3 +1,5 @@
 pub mod cards;
+pub mod labels;
 
 pub use cards::*;
+pub use labels::*;
@@ -0,0 +1,302 @@
+//! `--self-test`: exercise the notification pipeline without showing anything
+//!
+//! Runs each stage a notification goes through (sanitizing, markup parsing,
+//! image decoding, sound lookup, D-Bus, layer surfaces) against built-in
+//! inputs and prints a pass/fail summary for bug reports and packaging QA.
+
+use crate::compositor::CompositorCapabilities;
+use cosmic_ext_notifications_util::{
+    sanitize_and_parse, sanitize_html, segments_to_plain_text, ImagePolicy, NotificationImage,
+};
+
+/// 2x2 RGBA PNG used to exercise the image decoder
+const TEST_PNG: &[u8] = &[
+    0x89, 0x50, 0x4e, 0x47, 0x0d, 0x0a, 0x1a, 0x0a, 0x00, 0x00, 0x00, 0x0d, 0x49, 0x48, 0x44,
+    0x52, 0x00, 0x00, 0x00, 0x02, 0x00, 0x00, 0x00, 0x02, 0x08, 0x06, 0x00, 0x00, 0x00, 0x72,
+    0xb6, 0x0d, 0x24, 0x00, 0x00, 0x00, 0x13, 0x49, 0x44, 0x41, 0x54, 0x78, 0x9c, 0x63, 0xf8,
+    0xcf, 0xc0, 0xf0, 0x1f, 0x0c, 0x81, 0x34, 0x08, 0x34, 0x00, 0x00, 0x49, 0x49, 0x09, 0x78,
+    0x28, 0xa0, 0xdb, 0x77, 0x00, 0x00, 0x00, 0x00, 0x49, 0x45, 0x4e, 0x44, 0xae, 0x42, 0x60,
+    0x82,
+];
+
+/// Theme sound every freedesktop sound theme provides
+#[cfg(feature = "audio")]
+const TEST_SOUND: &str = "message-new-instant";
+
+/// Self-test options from the command line
+#[derive(Debug, Default, Clone, Copy)]
+pub struct Options {
+    /// Print the summary as JSON
+    pub json: bool,
+    /// Play the test sound instead of only decoding it
+    pub play_sound: bool,
+}
+
+impl Options {
+    /// Parse `--self-test` and its modifiers, None if not requested
+    pub fn from_args(args: impl IntoIterator<Item = String>) -> Option<Self> {
+        let mut requested = false;
+        let mut options = Self::default();
+        for arg in args {
+            match arg.as_str() {
+                "--self-test" => requested = true,
+                "--json" => options.json = true,
+                "--play-sound" => options.play_sound = true,
+                _ => {}
+            }
+        }
+        requested.then_some(options)
+    }
+}
+
+/// Outcome of one check
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Status {
+    Pass(String),
+    Fail(String),
+    /// The check could not run in this environment
+    Skip(String),
+}
+
+impl Status {
+    fn label(&self) -> &'static str {
+        match self {
+            Status::Pass(_) => "PASS",
+            Status::Fail(_) => "FAIL",
+            Status::Skip(_) => "SKIP",
+        }
+    }
+
+    fn detail(&self) -> &str {
+        match self {
+            Status::Pass(d) | Status::Fail(d) | Status::Skip(d) => d,
+        }
+    }
+}
+
+/// Run every check, print the summary and return whether all passed
+///
+/// Skipped checks don't count as failures.
+pub fn run(options: Options) -> bool {
+    let checks = [
+        ("sanitizer", check_sanitizer()),
+        ("markup-parser", check_parser()),
+        ("image-decode", check_image()),
+        ("sound", check_sound(options.play_sound)),
+        ("sound-formats", check_sound_formats()),
+        ("dbus", check_dbus()),
+        ("layer-surface", check_surface()),
+    ];
+
+    if options.json {
+        let results: Vec<_> = checks
+            .iter()
+            .map(|(name, status)| {
+                serde_json::json!({
+                    "check": name,
+                    "status": status.label(),
+                    "detail": status.detail(),
+                })
+            })
+            .collect();
+        let summary = serde_json::json!({
+            "version": crate::config::VERSION,
+            "checks": results,
+        });
+        println!("{}", serde_json::to_string_pretty(&summary).unwrap_or_default());
+    } else {
+        println!("cosmic-ext-notifications {} self-test", crate::config::VERSION);
+        for (name, status) in &checks {
+            println!("{:<4} {:<14} {}", status.label(), name, status.detail());
+        }
+    }
+
+    checks
+        .iter()
+        .all(|(_, status)| !matches!(status, Status::Fail(_)))
+}
+
+fn check_sanitizer() -> Status {
+    let html = sanitize_html(
+        r#"<b>ok</b><script>alert(1)</script><a href="javascript:alert(2)">link</a><img src=x onerror=alert(3)>"#,
+    );
+    let lower = html.to_lowercase();
+    if ["<script", "javascript:", "onerror", "<img"]
+        .iter()
+        .any(|bad| lower.contains(bad))
+    {
+        Status::Fail(format!("dangerous markup survived: {html}"))
+    } else if !html.contains("<b>ok</b>") {
+        Status::Fail(format!("allowed markup was removed: {html}"))
+    } else {
+        Status::Pass("scripts, javascript: links and event handlers removed".to_string())
+    }
+}
+
+fn check_parser() -> Status {
+    let segments = sanitize_and_parse("<b>bold</b> <i>italic</i> <a href=\"https://example.com\">link</a>");
+    let text = segments_to_plain_text(&segments);
+    let bold = segments.first().is_some_and(|s| s.style.bold);
+    let link = segments.iter().any(|s| s.link.as_deref() == Some("https://example.com"));
+
+    if text != "bold italic link" {
+        Status::Fail(format!("unexpected text {text:?}"))
+    } else if !bold || !link {
+        Status::Fail("styles or links were lost".to_string())
+    } else {
+        Status::Pass(format!("{} styled segments", segments.len()))
+    }
+}
+
+fn check_image() -> Status {
+    match NotificationImage::from_bytes(TEST_PNG) {
+        Ok(image) if (image.width, image.height) == (2, 2) => {
+            let policy = ImagePolicy {
+                corner_radius: 1,
+                ..Default::default()
+            };
+            let image = NotificationImage::apply_policy(image, &policy);
+            if image.data.len() == 2 * 2 * 4 {
+                Status::Pass("PNG decoded and masked".to_string())
+            } else {
+                Status::Fail(format!("unexpected buffer size {}", image.data.len()))
+            }
+        }
+        Ok(image) => Status::Fail(format!("decoded as {}x{}", image.width, image.height)),
+        Err(err) => Status::Fail(format!("decode failed: {err}")),
+    }
+}
+
+#[cfg(feature = "audio")]
+fn check_sound(play: bool) -> Status {
+    use cosmic_ext_notifications_util::{check_sound_name, play_sound_name, AudioError};
+
+    match check_sound_name(TEST_SOUND) {
+        Ok(path) => {
+            if play {
+                if let Err(err) = play_sound_name(TEST_SOUND) {
+                    return Status::Fail(format!("playback failed: {err}"));
+                }
+                // Give the audio engine time to play before the process exits
+                std::thread::sleep(std::time::Duration::from_secs(2));
+                Status::Pass(format!("played {}", path.display()))
+            } else {
+                Status::Pass(format!("decoded {} (mock output)", path.display()))
+            }
+        }
+        Err(AudioError::SoundNotFound(_)) => {
+            Status::Skip(format!("'{TEST_SOUND}' not found, is a sound theme installed?"))
+        }
+        Err(err) => Status::Fail(err.to_string()),
+    }
+}
+
+#[cfg(not(feature = "audio"))]
+fn check_sound(_play: bool) -> Status {
+    Status::Skip("built without audio support".to_string())
+}
+
+/// Which of the common sound formats installed sounds use and whether they decode
+#[cfg(feature = "audio")]
+fn check_sound_formats() -> Status {
+    use cosmic_ext_notifications_util::{format_support, FormatSupport};
+
+    let mut working = Vec::new();
+    let mut broken = Vec::new();
+    let mut untested = Vec::new();
+    for (format, support) in format_support() {
+        match support {
+            FormatSupport::Supported(_) => working.push(format.extension()),
+            FormatSupport::Unsupported { path, error } => {
+                broken.push(format!("{} ({}: {error})", format.extension(), path.display()))
+            }
+            FormatSupport::Untested => untested.push(format.extension()),
+        }
+    }
+
+    let summary = format!(
+        "decodes: {}; untested: {}",
+        working.join(" "),
+        if untested.is_empty() { "none".to_string() } else { untested.join(" ") }
+    );
+    if broken.is_empty() {
+        Status::Pass(summary)
+    } else {
+        Status::Fail(format!("cannot decode {}; {summary}", broken.join(", ")))
+    }
+}
+
+#[cfg(not(feature = "audio"))]
+fn check_sound_formats() -> Status {
+    Status::Skip("built without audio support".to_string())
+}
+
+/// Round trip through the session bus to whichever notification server runs
+fn check_dbus() -> Status {
+    let conn = match zbus::blocking::Connection::session() {
+        Ok(conn) => conn,
+        Err(err) => return Status::Skip(format!("no session bus: {err}")),
+    };
+
+    let reply = conn.call_method(
+        Some("org.freedesktop.Notifications"),
+        "/org/freedesktop/Notifications",
+        Some("org.freedesktop.Notifications"),
+        "GetServerInformation",
+        &(),
+    );
+    match reply {
+        Ok(reply) => match reply.body().deserialize::<(String, String, String, String)>() {
+            Ok((name, vendor, version, spec)) => Status::Pass(format!(
+                "server {name} {version} by {vendor}, spec {spec}"
+            )),
+            Err(err) => Status::Fail(format!("malformed GetServerInformation reply: {err}")),
+        },
+        Err(zbus::Error::MethodError(name, _, _))
+            if name.as_str() == "org.freedesktop.DBus.Error.ServiceUnknown" =>
+        {
+            Status::Skip("no notification server is running".to_string())
+        }
+        Err(err) => Status::Fail(err.to_string()),
+    }
+}
+
+/// Popups need layer-shell, probe for it the same way the daemon does
+fn check_surface() -> Status {
+    if std::env::var_os("WAYLAND_DISPLAY").is_none() {
+        return Status::Skip("not running under Wayland".to_string());
+    }
+
+    match CompositorCapabilities::probe() {
+        Ok(capabilities) if capabilities.layer_shell => {
+            Status::Pass("compositor supports layer-shell surfaces".to_string())
+        }
+        Ok(_) => Status::Fail("compositor lacks layer-shell, popups cannot be shown".to_string()),
+        Err(err) => Status::Skip(format!("{err:#}")),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn args(list: &[&str]) -> Vec<String> {
+        list.iter().map(|s| s.to_string()).collect()
+    }
+
+    #[test]
+    fn test_options_from_args() {
+        assert!(Options::from_args(args(&["cosmic-ext-notifications"])).is_none());
+
+        let options = Options::from_args(args(&["bin", "--self-test", "--json"])).unwrap();
+        assert!(options.json);
+        assert!(!options.play_sound);
+    }
+
+    #[test]
+    fn test_offline_checks_pass() {
+        assert!(matches!(check_sanitizer(), Status::Pass(_)));
+        assert!(matches!(check_parser(), Status::Pass(_)));
+        assert!(matches!(check_image(), Status::Pass(_)));
+    }
+}
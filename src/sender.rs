@@ -0,0 +1,180 @@
+//! Identification of the process behind a D-Bus sender
+//!
+//! `app_name` and the `desktop-entry` hint are whatever the client chooses to
+//! send. The bus daemon knows the sender's PID, and the PID's cgroup (or the
+//! Flatpak sandbox metadata) names the application that was launched, so
+//! per-app rules can be keyed on that instead.
+
+use crate::constants::SENDER_CACHE_MAX;
+use std::collections::HashMap;
+use zbus::{Connection, fdo::DBusProxy, names::UniqueName};
+
+/// What the daemon could verify about a notification's sender
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SenderIdentity {
+    pub pid: u32,
+    /// Application ID, matching its desktop entry name
+    pub app_id: Option<String>,
+    /// The ID comes from a sandbox the app can't leave, rather than a scope
+    /// it merely inherited from whatever launched it
+    pub sandboxed: bool,
+}
+
+/// Per-connection cache of resolved identities
+///
+/// Unique bus names are never reused, so entries only go stale when the
+/// client disconnects; the cache is simply cleared when it fills up.
+#[derive(Debug, Default)]
+pub struct SenderCache {
+    entries: HashMap<String, Option<SenderIdentity>>,
+}
+
+impl SenderCache {
+    pub async fn resolve(
+        &mut self,
+        conn: &Connection,
+        sender: &UniqueName<'_>,
+    ) -> Option<SenderIdentity> {
+        if let Some(identity) = self.entries.get(sender.as_str()) {
+            return identity.clone();
+        }
+
+        let identity = resolve(conn, sender).await;
+        if self.entries.len() >= SENDER_CACHE_MAX {
+            self.entries.clear();
+        }
+        self.entries.insert(sender.to_string(), identity.clone());
+        identity
+    }
+}
+
+/// Look up the sender's PID on the bus and derive its application ID
+pub async fn resolve(conn: &Connection, sender: &UniqueName<'_>) -> Option<SenderIdentity> {
+    let proxy = DBusProxy::new(conn).await.ok()?;
+    let pid = match proxy.get_connection_unix_process_id(sender.to_owned().into()).await {
+        Ok(pid) => pid,
+        Err(err) => {
+            tracing::debug!("Failed to get PID of {sender}: {err}");
+            return None;
+        }
+    };
+
+    let flatpak = std::fs::read_to_string(format!("/proc/{pid}/root/.flatpak-info"))
+        .ok()
+        .and_then(|info| flatpak_app_id(&info));
+    let identity = match flatpak {
+        Some(app_id) => SenderIdentity {
+            pid,
+            app_id: Some(app_id),
+            sandboxed: true,
+        },
+        None => {
+            let unit = std::fs::read_to_string(format!("/proc/{pid}/cgroup"))
+                .ok()
+                .and_then(|cgroup| app_id_from_cgroup(&cgroup));
+            SenderIdentity {
+                pid,
+                sandboxed: unit.as_ref().is_some_and(|(_, sandboxed)| *sandboxed),
+                app_id: unit.map(|(app_id, _)| app_id),
+            }
+        }
+    };
+    tracing::trace!("Sender {sender} resolved to {identity:?}");
+    Some(identity)
+}
+
+/// Read the app ID from a sandbox's `.flatpak-info` keyfile
+fn flatpak_app_id(info: &str) -> Option<String> {
+    let mut in_application = false;
+    for line in info.lines().map(str::trim) {
+        if line.starts_with('[') {
+            in_application = line == "[Application]";
+        } else if in_application {
+            if let Some(name) = line.strip_prefix("name=") {
+                let name = name.trim();
+                return (!name.is_empty()).then(|| name.to_string());
+            }
+        }
+    }
+    None
+}
+
+/// Find the application unit in a `/proc/<pid>/cgroup` listing
+///
+/// Returns the app ID and whether it came from a Flatpak scope.
+fn app_id_from_cgroup(cgroup: &str) -> Option<(String, bool)> {
+    cgroup
+        .lines()
+        .filter_map(|line| line.splitn(3, ':').nth(2))
+        .flat_map(|path| path.rsplit('/'))
+        .find_map(app_id_from_unit)
+}
+
+/// Parse a unit named per the systemd XDG application conventions:
+/// `app[-<launcher>]-<ApplicationID>-<RANDOM>.scope` or
+/// `app[-<launcher>]-<ApplicationID>[@<RANDOM>].service`
+fn app_id_from_unit(unit: &str) -> Option<(String, bool)> {
+    let rest = unit.strip_prefix("app-")?;
+    let name = if let Some(scope) = rest.strip_suffix(".scope") {
+        scope.rsplit_once('-')?.0
+    } else if let Some(service) = rest.strip_suffix(".service") {
+        service.split_once('@').map_or(service, |(name, _)| name)
+    } else {
+        return None;
+    };
+
+    // Dashes inside the ID are escaped, so a bare one ends the launcher
+    let (launcher, app_id) = name.split_once('-').unwrap_or(("", name));
+    let app_id = app_id.replace("\\x2d", "-");
+    if app_id.is_empty() {
+        return None;
+    }
+    Some((app_id, launcher == "flatpak"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_flatpak_info() {
+        let info = "[Application]\nname=org.mozilla.firefox\nruntime=runtime/org.freedesktop.Platform\n\n[Instance]\ninstance-id=123\n";
+        assert_eq!(flatpak_app_id(info).as_deref(), Some("org.mozilla.firefox"));
+        assert_eq!(flatpak_app_id("[Instance]\nname=nope\n"), None);
+    }
+
+    #[test]
+    fn test_app_units() {
+        assert_eq!(
+            app_id_from_unit("app-flatpak-org.telegram.desktop-4321.scope"),
+            Some(("org.telegram.desktop".to_string(), true))
+        );
+        assert_eq!(
+            app_id_from_unit("app-cosmic-com.system76.CosmicTerm-98765.scope"),
+            Some(("com.system76.CosmicTerm".to_string(), false))
+        );
+        assert_eq!(
+            app_id_from_unit("app-gnome-org.gnome.Evolution\\x2dalarm\\x2dnotify@autostart.service"),
+            Some(("org.gnome.Evolution-alarm-notify".to_string(), false))
+        );
+        assert_eq!(
+            app_id_from_unit("app-firefox-1234.scope"),
+            Some(("firefox".to_string(), false))
+        );
+        assert_eq!(app_id_from_unit("session-2.scope"), None);
+        assert_eq!(app_id_from_unit("app.slice"), None);
+    }
+
+    #[test]
+    fn test_cgroup_listing() {
+        let cgroup = "0::/user.slice/user-1000.slice/user@1000.service/app.slice/app-flatpak-com.slack.Slack-5555.scope\n";
+        assert_eq!(
+            app_id_from_cgroup(cgroup),
+            Some(("com.slack.Slack".to_string(), true))
+        );
+
+        // Started from a login shell, nothing to go on
+        let cgroup = "0::/user.slice/user-1000.slice/session-2.scope\n";
+        assert_eq!(app_id_from_cgroup(cgroup), None);
+    }
+}
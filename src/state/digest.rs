@@ -0,0 +1,95 @@
+use std::collections::BTreeMap;
+use std::time::{Duration, Instant};
+
+/// Notifications of one app waiting for its digest
+#[derive(Debug)]
+struct Batch {
+    /// When the digest is delivered
+    due: Instant,
+    /// Summaries of the notifications, oldest first
+    summaries: Vec<String>,
+}
+
+/// Apps whose notifications are delivered as one digest card every few
+/// minutes instead of one popup each
+///
+/// The first notification of a batch starts the clock, so an app that
+/// stays quiet never gets an empty digest.
+#[derive(Debug, Default)]
+pub struct DigestQueue {
+    batches: BTreeMap<String, Batch>,
+}
+
+impl DigestQueue {
+    /// Hold a notification of `app` with `summary`, for a digest `interval`
+    /// after the first one held; returns true if it started a new batch
+    pub fn add(&mut self, app: &str, summary: String, now: Instant, interval: Duration) -> bool {
+        let mut started = false;
+        let batch = self.batches.entry(app.to_string()).or_insert_with(|| {
+            started = true;
+            Batch {
+                due: now + interval,
+                summaries: Vec::new(),
+            }
+        });
+        batch.summaries.push(summary);
+        started
+    }
+
+    /// Batches due at `now`, with their summaries oldest first
+    pub fn take_due(&mut self, now: Instant) -> Vec<(String, Vec<String>)> {
+        let due: Vec<String> = self
+            .batches
+            .iter()
+            .filter(|(_, batch)| batch.due <= now)
+            .map(|(app, _)| app.clone())
+            .collect();
+        due.into_iter()
+            .filter_map(|app| {
+                let batch = self.batches.remove(&app)?;
+                Some((app, batch.summaries))
+            })
+            .collect()
+    }
+
+    /// When the next digest is due
+    pub fn next_due(&self) -> Option<Instant> {
+        self.batches.values().map(|batch| batch.due).min()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const INTERVAL: Duration = Duration::from_secs(900);
+
+    #[test]
+    fn test_batch_is_delivered_once_due() {
+        let mut digest = DigestQueue::default();
+        let now = Instant::now();
+        assert!(digest.add("RSS", "First".to_string(), now, INTERVAL));
+        assert!(!digest.add("RSS", "Second".to_string(), now + INTERVAL / 2, INTERVAL));
+        assert_eq!(digest.next_due(), Some(now + INTERVAL));
+
+        assert!(digest.take_due(now + INTERVAL / 2).is_empty());
+        assert_eq!(
+            digest.take_due(now + INTERVAL),
+            vec![("RSS".to_string(), vec!["First".to_string(), "Second".to_string()])]
+        );
+        assert_eq!(digest.next_due(), None);
+    }
+
+    #[test]
+    fn test_apps_are_batched_apart() {
+        let mut digest = DigestQueue::default();
+        let now = Instant::now();
+        digest.add("RSS", "News".to_string(), now, INTERVAL);
+        digest.add("CI", "Build passed".to_string(), now + INTERVAL / 2, INTERVAL);
+
+        assert_eq!(digest.take_due(now + INTERVAL).len(), 1);
+        assert_eq!(digest.next_due(), Some(now + INTERVAL / 2 + INTERVAL));
+        // A new notification after the digest starts a new batch
+        assert!(digest.add("RSS", "More news".to_string(), now + INTERVAL, INTERVAL));
+    }
+}
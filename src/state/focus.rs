@@ -0,0 +1,149 @@
+use cosmic_ext_notifications_util::Notification;
+use std::collections::BTreeMap;
+use std::time::{Duration, Instant};
+
+/// A timed focus session (Pomodoro style)
+///
+/// While a session is running, popups behave as if do-not-disturb were
+/// enabled except for apps on the allow-list. Suppressed notifications are
+/// still stored in history and counted so a summary can be shown when the
+/// session ends.
+#[derive(Debug, Clone)]
+pub struct FocusSession {
+    /// When the session is scheduled to end
+    ends_at: Instant,
+    /// App names or desktop entries that may still show popups
+    allowed_apps: Vec<String>,
+    /// Number of suppressed notifications per app name
+    suppressed: BTreeMap<String, u32>,
+}
+
+impl FocusSession {
+    /// Start a new focus session lasting `duration`
+    pub fn new(duration: Duration, allowed_apps: Vec<String>) -> Self {
+        Self {
+            ends_at: Instant::now() + duration,
+            allowed_apps,
+            suppressed: BTreeMap::new(),
+        }
+    }
+
+    /// Check whether a notification may be shown during the session
+    ///
+    /// Critical notifications are always allowed through. Other notifications
+    /// are matched case-insensitively against the allow-list by app name or
+    /// desktop entry.
+    pub fn allows(&self, notification: &Notification) -> bool {
+        if notification.urgency() == 2 {
+            return true;
+        }
+
+        let desktop_entry = notification.desktop_entry();
+        self.allowed_apps.iter().any(|allowed| {
+            allowed.eq_ignore_ascii_case(&notification.app_name)
+                || desktop_entry.is_some_and(|entry| allowed.eq_ignore_ascii_case(entry))
+        })
+    }
+
+    /// Record a notification that was suppressed by this session
+    pub fn record_suppressed(&mut self, notification: &Notification) {
+        *self
+            .suppressed
+            .entry(notification.app_name.clone())
+            .or_insert(0) += 1;
+    }
+
+    /// Time left until the session ends
+    pub fn remaining(&self) -> Duration {
+        self.ends_at.saturating_duration_since(Instant::now())
+    }
+
+    /// Check if the session has run its full duration
+    pub fn is_expired(&self) -> bool {
+        self.remaining().is_zero()
+    }
+
+    /// Total number of suppressed notifications
+    pub fn suppressed_count(&self) -> u32 {
+        self.suppressed.values().sum()
+    }
+
+    /// Number of distinct apps that had notifications suppressed
+    pub fn suppressed_app_count(&self) -> usize {
+        self.suppressed.len()
+    }
+
+    /// Suppressed notification counts per app name, sorted by app name
+    pub fn suppressed_by_app(&self) -> impl Iterator<Item = (&str, u32)> {
+        self.suppressed.iter().map(|(app, count)| (app.as_str(), *count))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use cosmic_ext_notifications_util::Hint;
+    use std::time::SystemTime;
+
+    fn notification(app_name: &str, hints: Vec<Hint>) -> Notification {
+        Notification {
+            id: 1,
+            app_name: app_name.to_string(),
+            app_icon: String::new(),
+            summary: "Summary".to_string(),
+            body: String::new(),
+            actions: vec![],
+            hints,
+            expire_timeout: -1,
+            applied_timeout: None,
+            time: SystemTime::now(),
+        }
+    }
+
+    #[test]
+    fn test_allow_list_matches_app_name_case_insensitive() {
+        let session = FocusSession::new(Duration::from_secs(60), vec!["Slack".to_string()]);
+        assert!(session.allows(&notification("slack", vec![])));
+        assert!(!session.allows(&notification("Firefox", vec![])));
+    }
+
+    #[test]
+    fn test_allow_list_matches_desktop_entry() {
+        let session = FocusSession::new(Duration::from_secs(60), vec!["org.gnome.Calendar".to_string()]);
+        let n = notification(
+            "Calendar",
+            vec![Hint::DesktopEntry("org.gnome.Calendar".to_string())],
+        );
+        assert!(session.allows(&n));
+    }
+
+    #[test]
+    fn test_critical_always_allowed() {
+        let session = FocusSession::new(Duration::from_secs(60), vec![]);
+        assert!(session.allows(&notification("Battery", vec![Hint::Urgency(2)])));
+        assert!(!session.allows(&notification("Battery", vec![Hint::Urgency(1)])));
+    }
+
+    #[test]
+    fn test_suppressed_counts() {
+        let mut session = FocusSession::new(Duration::from_secs(60), vec![]);
+        session.record_suppressed(&notification("Firefox", vec![]));
+        session.record_suppressed(&notification("Firefox", vec![]));
+        session.record_suppressed(&notification("Thunderbird", vec![]));
+
+        assert_eq!(session.suppressed_count(), 3);
+        assert_eq!(session.suppressed_app_count(), 2);
+        let by_app: Vec<_> = session.suppressed_by_app().collect();
+        assert_eq!(by_app, vec![("Firefox", 2), ("Thunderbird", 1)]);
+    }
+
+    #[test]
+    fn test_expiry() {
+        let session = FocusSession::new(Duration::ZERO, vec![]);
+        assert!(session.is_expired());
+
+        let session = FocusSession::new(Duration::from_secs(60), vec![]);
+        assert!(!session.is_expired());
+        assert!(session.remaining() <= Duration::from_secs(60));
+    }
+}
@@ -0,0 +1,342 @@
+use chrono::{DateTime, Datelike, Local, NaiveDate};
+use cosmic_ext_notifications_util::Notification;
+use std::cmp::Reverse;
+use std::collections::HashSet;
+use std::time::SystemTime;
+
+/// Calendar section a history entry is listed under
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum DateSection {
+    Today,
+    Yesterday,
+    /// Any earlier local date
+    Day(NaiveDate),
+}
+
+impl DateSection {
+    /// Section for a notification time, relative to the local date `today`
+    pub fn for_time(time: SystemTime, today: NaiveDate) -> Self {
+        let date = DateTime::<Local>::from(time).date_naive();
+        if date >= today {
+            // Clock skew can put notifications slightly in the future
+            DateSection::Today
+        } else if today.pred_opt() == Some(date) {
+            DateSection::Yesterday
+        } else {
+            DateSection::Day(date)
+        }
+    }
+
+    /// Stable identifier used for collapse state over D-Bus
+    ///
+    /// "today", "yesterday" or an ISO date like "2026-01-31".
+    pub fn key(&self) -> String {
+        match self {
+            DateSection::Today => "today".to_string(),
+            DateSection::Yesterday => "yesterday".to_string(),
+            DateSection::Day(date) => date.format("%Y-%m-%d").to_string(),
+        }
+    }
+
+    /// Parse a key produced by [`DateSection::key`]
+    pub fn from_key(key: &str) -> Option<Self> {
+        match key {
+            "today" => Some(DateSection::Today),
+            "yesterday" => Some(DateSection::Yesterday),
+            _ => NaiveDate::parse_from_str(key, "%Y-%m-%d")
+                .ok()
+                .map(DateSection::Day),
+        }
+    }
+
+    /// Localized section header
+    ///
+    /// Weekday and month names, and the order of the date's parts, come
+    /// from the translation rather than the C locale chrono formats in.
+    pub fn label(&self) -> String {
+        match self {
+            DateSection::Today => crate::fl!("history-today"),
+            DateSection::Yesterday => crate::fl!("history-yesterday"),
+            DateSection::Day(date) => crate::fl!(
+                "history-date",
+                weekday = crate::fl!("history-weekday", weekday = date.weekday().number_from_monday()),
+                month = crate::fl!("history-month", month = date.month()),
+                day = date.day(),
+                year = date.year()
+            ),
+        }
+    }
+
+    /// Local date the section stands for, relative to `today`
+    fn date(&self, today: NaiveDate) -> NaiveDate {
+        match self {
+            DateSection::Today => today,
+            DateSection::Yesterday => today.pred_opt().unwrap_or(today),
+            DateSection::Day(date) => *date,
+        }
+    }
+}
+
+/// Group history entries under date sections
+///
+/// Sections are listed newest first, Today, Yesterday, then older days,
+/// whatever order the entries come in. Order within a section is preserved,
+/// so entries sorted by priority stay that way.
+pub fn group_by_date<'a>(
+    notifications: impl IntoIterator<Item = &'a Notification>,
+    today: NaiveDate,
+) -> Vec<(DateSection, Vec<&'a Notification>)> {
+    let mut sections: Vec<(DateSection, Vec<&'a Notification>)> = Vec::new();
+
+    for n in notifications {
+        let section = DateSection::for_time(n.time, today);
+        match sections.iter_mut().find(|(s, _)| *s == section) {
+            Some((_, entries)) => entries.push(n),
+            None => sections.push((section, vec![n])),
+        }
+    }
+
+    sections.sort_by_key(|(section, _)| Reverse(section.date(today)));
+    sections
+}
+
+/// Group history entries by app name
+///
+/// Apps appear in the order their first entry is seen, so newest-first
+/// history lists the most recently active app first. Order within an app
+/// is preserved.
+pub fn group_by_app<'a>(
+    notifications: impl IntoIterator<Item = &'a Notification>,
+) -> Vec<(&'a str, Vec<&'a Notification>)> {
+    let mut apps: Vec<(&'a str, Vec<&'a Notification>)> = Vec::new();
+
+    for n in notifications {
+        match apps.iter_mut().find(|(app, _)| *app == n.app_name) {
+            Some((_, entries)) => entries.push(n),
+            None => apps.push((n.app_name.as_str(), vec![n])),
+        }
+    }
+
+    apps
+}
+
+/// Notifications received at or after `since`, counted per app
+///
+/// Apps with the most notifications come first.
+pub fn count_by_app_since<'a>(
+    notifications: impl IntoIterator<Item = &'a Notification>,
+    since: SystemTime,
+) -> Vec<(String, u32)> {
+    let mut counts: Vec<(String, u32)> = Vec::new();
+    for n in notifications.into_iter().filter(|n| n.time >= since) {
+        match counts.iter_mut().find(|(app, _)| *app == n.app_name) {
+            Some((_, count)) => *count += 1,
+            None => counts.push((n.app_name.clone(), 1)),
+        }
+    }
+    counts.sort_by(|a, b| b.1.cmp(&a.1));
+    counts
+}
+
+/// Local date used as "today" for grouping
+pub fn local_today() -> NaiveDate {
+    Local::now().date_naive()
+}
+
+/// Which history sections the user has collapsed
+#[derive(Debug, Default, Clone)]
+pub struct SectionCollapseState {
+    collapsed: HashSet<DateSection>,
+}
+
+impl SectionCollapseState {
+    /// Check if a section is collapsed
+    pub fn is_collapsed(&self, section: DateSection) -> bool {
+        self.collapsed.contains(&section)
+    }
+
+    /// Collapse or expand a section
+    pub fn set_collapsed(&mut self, section: DateSection, collapsed: bool) {
+        if collapsed {
+            self.collapsed.insert(section);
+        } else {
+            self.collapsed.remove(&section);
+        }
+    }
+
+    /// Forget collapse state for sections that no longer exist
+    ///
+    /// "Today" and "Yesterday" keep their state across midnight since they
+    /// are relative sections.
+    pub fn retain_sections(&mut self, sections: &[DateSection]) {
+        self.collapsed.retain(|s| {
+            matches!(s, DateSection::Today | DateSection::Yesterday) || sections.contains(s)
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::{Duration, TimeZone};
+
+    fn notification_at(id: u32, time: SystemTime) -> Notification {
+        Notification {
+            id,
+            app_name: "test_app".to_string(),
+            app_icon: String::new(),
+            summary: "Summary".to_string(),
+            body: String::new(),
+            actions: vec![],
+            hints: vec![],
+            expire_timeout: -1,
+            applied_timeout: None,
+            time,
+        }
+    }
+
+    fn local_noon(date: NaiveDate) -> SystemTime {
+        Local
+            .from_local_datetime(&date.and_hms_opt(12, 0, 0).unwrap())
+            .unwrap()
+            .into()
+    }
+
+    fn today() -> NaiveDate {
+        NaiveDate::from_ymd_opt(2026, 3, 10).unwrap()
+    }
+
+    #[test]
+    fn test_section_for_time() {
+        let today = today();
+        assert_eq!(DateSection::for_time(local_noon(today), today), DateSection::Today);
+        assert_eq!(
+            DateSection::for_time(local_noon(today - Duration::days(1)), today),
+            DateSection::Yesterday
+        );
+        let older = today - Duration::days(5);
+        assert_eq!(DateSection::for_time(local_noon(older), today), DateSection::Day(older));
+        // Future timestamps count as today
+        assert_eq!(
+            DateSection::for_time(local_noon(today + Duration::days(1)), today),
+            DateSection::Today
+        );
+    }
+
+    #[test]
+    fn test_group_by_app_most_recent_first() {
+        let now = SystemTime::now();
+        let mut history = vec![
+            notification_at(4, now),
+            notification_at(3, now),
+            notification_at(2, now),
+            notification_at(1, now),
+        ];
+        history[0].app_name = "Mail".to_string();
+        history[2].app_name = "Mail".to_string();
+
+        let apps: Vec<_> = group_by_app(&history)
+            .into_iter()
+            .map(|(app, entries)| (app, entries.iter().map(|n| n.id).collect::<Vec<_>>()))
+            .collect();
+        assert_eq!(apps, vec![("Mail", vec![4, 2]), ("test_app", vec![3, 1])]);
+    }
+
+    #[test]
+    fn test_group_by_date_preserves_order() {
+        let today = today();
+        let history = vec![
+            notification_at(4, local_noon(today)),
+            notification_at(3, local_noon(today)),
+            notification_at(2, local_noon(today - Duration::days(1))),
+            notification_at(1, local_noon(today - Duration::days(3))),
+        ];
+
+        let sections = group_by_date(&history, today);
+        assert_eq!(sections.len(), 3);
+        assert_eq!(sections[0].0, DateSection::Today);
+        assert_eq!(sections[0].1.iter().map(|n| n.id).collect::<Vec<_>>(), vec![4, 3]);
+        assert_eq!(sections[1].0, DateSection::Yesterday);
+        assert_eq!(sections[2].0, DateSection::Day(today - Duration::days(3)));
+    }
+
+    #[test]
+    fn test_group_by_date_newest_first() {
+        let today = today();
+        // Ordered by priority rather than time
+        let history = vec![
+            notification_at(3, local_noon(today - Duration::days(3))),
+            notification_at(2, local_noon(today)),
+            notification_at(1, local_noon(today - Duration::days(1))),
+        ];
+
+        let sections: Vec<_> = group_by_date(&history, today)
+            .into_iter()
+            .map(|(section, _)| section)
+            .collect();
+        assert_eq!(
+            sections,
+            vec![
+                DateSection::Today,
+                DateSection::Yesterday,
+                DateSection::Day(today - Duration::days(3))
+            ]
+        );
+    }
+
+    #[test]
+    fn test_day_label_is_translated() {
+        let label = DateSection::Day(NaiveDate::from_ymd_opt(2026, 3, 5).unwrap()).label();
+        assert!(label.contains("Thursday"));
+        assert!(label.contains("March"));
+        assert!(label.contains('5'));
+    }
+
+    #[test]
+    fn test_count_by_app_since() {
+        let since = local_noon(today());
+        let mut history = vec![
+            notification_at(1, since - std::time::Duration::from_secs(60)),
+            notification_at(2, since),
+            notification_at(3, since + std::time::Duration::from_secs(60)),
+            notification_at(4, since + std::time::Duration::from_secs(120)),
+        ];
+        history[3].app_name = "other_app".to_string();
+
+        let counts = count_by_app_since(&history, since);
+        assert_eq!(
+            counts,
+            vec![("test_app".to_string(), 2), ("other_app".to_string(), 1)]
+        );
+        assert!(count_by_app_since(&history, since + std::time::Duration::from_secs(600)).is_empty());
+    }
+
+    #[test]
+    fn test_key_round_trip() {
+        for section in [
+            DateSection::Today,
+            DateSection::Yesterday,
+            DateSection::Day(NaiveDate::from_ymd_opt(2026, 1, 31).unwrap()),
+        ] {
+            assert_eq!(DateSection::from_key(&section.key()), Some(section));
+        }
+        assert_eq!(DateSection::from_key("not-a-date"), None);
+    }
+
+    #[test]
+    fn test_collapse_state() {
+        let old = DateSection::Day(NaiveDate::from_ymd_opt(2026, 1, 31).unwrap());
+        let mut state = SectionCollapseState::default();
+        state.set_collapsed(DateSection::Today, true);
+        state.set_collapsed(old, true);
+        assert!(state.is_collapsed(DateSection::Today));
+        assert!(state.is_collapsed(old));
+
+        state.retain_sections(&[DateSection::Yesterday]);
+        assert!(state.is_collapsed(DateSection::Today));
+        assert!(!state.is_collapsed(old));
+
+        state.set_collapsed(DateSection::Today, false);
+        assert!(!state.is_collapsed(DateSection::Today));
+    }
+}
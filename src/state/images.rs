@@ -0,0 +1,251 @@
+use cosmic::widget::icon;
+use cosmic_ext_notifications_util::{
+    AnimatedImage, AnimationFrame, Image, ImagePolicy, ProcessedImage,
+};
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+/// What a worker made of a popup's image
+#[derive(Debug, Clone)]
+pub enum DecodedImage {
+    Still(Arc<ProcessedImage>),
+    Animated(Arc<AnimatedHandles>),
+}
+
+/// An animation ready to draw, with an image handle per frame
+///
+/// The handles are built once when the animation is decoded, so drawing a
+/// frame only picks one out instead of copying its pixels again. `timing`
+/// keeps each frame's delay; its pixels have moved into the handles.
+#[derive(Debug, Clone)]
+pub struct AnimatedHandles {
+    timing: AnimatedImage,
+    frames: Vec<icon::Handle>,
+}
+
+impl AnimatedHandles {
+    pub fn new(animation: AnimatedImage) -> Self {
+        let (frames, timing) = animation
+            .into_frames()
+            .into_iter()
+            .map(|frame| {
+                let handle = icon::from_raster_pixels(frame.width, frame.height, frame.data);
+                let timing = AnimationFrame {
+                    data: Vec::new(),
+                    ..frame
+                };
+                (handle, timing)
+            })
+            .unzip();
+        Self {
+            timing: AnimatedImage::new(timing),
+            frames,
+        }
+    }
+
+    /// The frame due `elapsed_ms` into the animation (loops)
+    pub fn frame_at(&self, elapsed_ms: u32) -> Option<&icon::Handle> {
+        self.frames.get(self.timing.frame_index_at(elapsed_ms)?)
+    }
+
+    pub fn first_frame(&self) -> Option<&icon::Handle> {
+        self.frames.first()
+    }
+}
+
+/// Where a popup's image is in decoding
+#[derive(Debug, Clone)]
+pub enum ImageSlot {
+    /// Being decoded off the UI thread
+    Pending,
+    Ready(Arc<ProcessedImage>),
+    /// A GIF or APNG, playing since `started`
+    Animated {
+        animation: Arc<AnimatedHandles>,
+        started: Instant,
+    },
+    /// Unreadable or too large; the popup goes without
+    Failed,
+}
+
+/// Popup images decoded ahead of rendering, by notification id
+///
+/// Decoding and downscaling happen on worker threads, so the view only
+/// ever looks images up here. Each request gets a ticket; results for a
+/// notification whose image or policy changed in the meantime carry an old
+/// ticket and are dropped.
+#[derive(Debug, Default)]
+pub struct ImageCache {
+    entries: HashMap<u32, Entry>,
+    next_ticket: u64,
+}
+
+#[derive(Debug)]
+struct Entry {
+    image: Image,
+    policy: ImagePolicy,
+    animate: bool,
+    ticket: u64,
+    slot: ImageSlot,
+}
+
+impl ImageCache {
+    /// Ask for `image` of notification `id` with `policy` applied, keeping
+    /// all its frames if `animate` is set
+    ///
+    /// Returns the ticket to decode under, or `None` if that exact image is
+    /// already decoded or on its way.
+    pub fn request(
+        &mut self,
+        id: u32,
+        image: &Image,
+        policy: ImagePolicy,
+        animate: bool,
+    ) -> Option<u64> {
+        if self.entries.get(&id).is_some_and(|entry| {
+            entry.policy == policy && entry.animate == animate && entry.image == *image
+        }) {
+            return None;
+        }
+        self.next_ticket += 1;
+        self.entries.insert(
+            id,
+            Entry {
+                image: image.clone(),
+                policy,
+                animate,
+                ticket: self.next_ticket,
+                slot: ImageSlot::Pending,
+            },
+        );
+        Some(self.next_ticket)
+    }
+
+    /// Store the outcome of decoding under `ticket`
+    pub fn finish(&mut self, id: u32, ticket: u64, decoded: Option<DecodedImage>) {
+        if let Some(entry) = self.entries.get_mut(&id).filter(|entry| entry.ticket == ticket) {
+            entry.slot = match decoded {
+                Some(DecodedImage::Still(image)) => ImageSlot::Ready(image),
+                Some(DecodedImage::Animated(animation)) => ImageSlot::Animated {
+                    animation,
+                    started: Instant::now(),
+                },
+                None => ImageSlot::Failed,
+            };
+        }
+    }
+
+    /// Whether any animation started less than `limit` before `now`, and
+    /// so still needs frames drawn
+    pub fn playing(&self, now: Instant, limit: Duration) -> bool {
+        self.entries.values().any(|entry| {
+            matches!(entry.slot, ImageSlot::Animated { started, .. }
+                if now.saturating_duration_since(started) < limit)
+        })
+    }
+
+    pub fn get(&self, id: u32) -> Option<&ImageSlot> {
+        self.entries.get(&id).map(|entry| &entry.slot)
+    }
+
+    pub fn forget(&mut self, id: u32) {
+        self.entries.remove(&id);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::PathBuf;
+
+    fn decoded() -> Option<DecodedImage> {
+        Some(DecodedImage::Still(Arc::new(ProcessedImage {
+            data: vec![0; 4],
+            width: 1,
+            height: 1,
+        })))
+    }
+
+    #[test]
+    fn test_same_image_decodes_once() {
+        let mut cache = ImageCache::default();
+        let image = Image::File(PathBuf::from("/tmp/shot.png"));
+        let policy = ImagePolicy::default();
+
+        let ticket = cache.request(1, &image, policy, false).unwrap();
+        assert!(matches!(cache.get(1), Some(ImageSlot::Pending)));
+        assert_eq!(cache.request(1, &image, policy, false), None);
+
+        cache.finish(1, ticket, decoded());
+        assert!(matches!(cache.get(1), Some(ImageSlot::Ready(_))));
+
+        // A new policy decodes again
+        let rounded = ImagePolicy {
+            corner_radius: 8,
+            ..policy
+        };
+        assert!(cache.request(1, &image, rounded, false).is_some());
+        // So does turning animations on
+        assert!(cache.request(1, &image, rounded, true).is_some());
+    }
+
+    #[test]
+    fn test_animations_stop_playing() {
+        let mut cache = ImageCache::default();
+        let image = Image::File(PathBuf::from("/tmp/cat.gif"));
+        let ticket = cache
+            .request(1, &image, ImagePolicy::default(), true)
+            .unwrap();
+        let limit = Duration::from_secs(10);
+        assert!(!cache.playing(Instant::now(), limit));
+
+        let frame = AnimationFrame {
+            data: vec![0; 4],
+            width: 1,
+            height: 1,
+            delay_ms: 100,
+        };
+        let animation = AnimatedHandles::new(AnimatedImage::new(vec![frame.clone(), frame]));
+        cache.finish(1, ticket, Some(DecodedImage::Animated(Arc::new(animation))));
+        assert!(cache.playing(Instant::now(), limit));
+        assert!(!cache.playing(Instant::now() + limit, limit));
+    }
+
+    #[test]
+    fn test_animation_frames_are_prebuilt() {
+        let frame = |delay_ms| AnimationFrame {
+            data: vec![0; 4],
+            width: 1,
+            height: 1,
+            delay_ms,
+        };
+        let animation = AnimatedHandles::new(AnimatedImage::new(vec![frame(100), frame(50)]));
+        assert_eq!(animation.frames.len(), 2);
+        assert!(animation.timing.first_frame().unwrap().data.is_empty());
+        assert_eq!(animation.frame_at(120), animation.frames.get(1));
+        assert_eq!(animation.frame_at(160), animation.first_frame());
+    }
+
+    #[test]
+    fn test_stale_results_are_dropped() {
+        let mut cache = ImageCache::default();
+        let policy = ImagePolicy::default();
+        let old = cache
+            .request(1, &Image::File(PathBuf::from("/tmp/a.png")), policy, false)
+            .unwrap();
+        let new = cache
+            .request(1, &Image::File(PathBuf::from("/tmp/b.png")), policy, false)
+            .unwrap();
+
+        cache.finish(1, old, decoded());
+        assert!(matches!(cache.get(1), Some(ImageSlot::Pending)));
+        cache.finish(1, new, None);
+        assert!(matches!(cache.get(1), Some(ImageSlot::Failed)));
+
+        cache.forget(1);
+        assert!(cache.get(1).is_none());
+        cache.finish(1, new, decoded());
+        assert!(cache.get(1).is_none());
+    }
+}
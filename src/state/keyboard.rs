@@ -0,0 +1,163 @@
+/// A step of keyboard navigation between popups
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum KeyMove {
+    /// The card above
+    Up,
+    /// The card below
+    Down,
+    /// The previous button of the focused card, or the card itself
+    Left,
+    /// The next button of the focused card
+    Right,
+    /// The next card or button, wrapping around
+    Next,
+    /// The previous card or button, wrapping around
+    Previous,
+}
+
+/// Which popup, and which of its buttons, has keyboard focus
+///
+/// Cards are given top to bottom as `(id, buttons)` pairs, where `buttons`
+/// is the number of action buttons the card shows.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct KeyFocus {
+    pub id: u32,
+    /// Index of the focused button, `None` for the card itself
+    pub button: Option<usize>,
+}
+
+impl KeyFocus {
+    pub fn card(id: u32) -> Self {
+        Self { id, button: None }
+    }
+
+    /// Focus on the top card, if there is one
+    pub fn first(cards: &[(u32, usize)]) -> Option<Self> {
+        cards.first().map(|(id, _)| Self::card(*id))
+    }
+
+    /// Focus after `step`
+    ///
+    /// Arrows stop at the edges, Tab wraps around. Focus on a card that's
+    /// gone moves to the top card.
+    pub fn moved(self, cards: &[(u32, usize)], step: KeyMove) -> Option<Self> {
+        let Some(index) = cards.iter().position(|(id, _)| *id == self.id) else {
+            return Self::first(cards);
+        };
+        let buttons = cards[index].1;
+        let focus = match step {
+            KeyMove::Up => index
+                .checked_sub(1)
+                .map_or(self, |above| Self::card(cards[above].0)),
+            KeyMove::Down => cards.get(index + 1).map_or(self, |(id, _)| Self::card(*id)),
+            KeyMove::Left => Self {
+                button: self.button.and_then(|button| button.checked_sub(1)),
+                ..self
+            },
+            KeyMove::Right => {
+                let next = self.button.map_or(0, |button| button + 1);
+                if next < buttons {
+                    Self {
+                        button: Some(next),
+                        ..self
+                    }
+                } else {
+                    self
+                }
+            }
+            KeyMove::Next | KeyMove::Previous => {
+                let stops: Vec<Self> = cards
+                    .iter()
+                    .flat_map(|(id, buttons)| {
+                        std::iter::once(Self::card(*id)).chain((0..*buttons).map(|button| Self {
+                            id: *id,
+                            button: Some(button),
+                        }))
+                    })
+                    .collect();
+                let at = stops.iter().position(|stop| *stop == self).unwrap_or(0);
+                let to = if step == KeyMove::Next {
+                    (at + 1) % stops.len()
+                } else {
+                    (at + stops.len() - 1) % stops.len()
+                };
+                stops[to]
+            }
+        };
+        Some(focus)
+    }
+
+    /// Where focus goes once the focused card closes: the card below it,
+    /// or above if it was the last
+    pub fn neighbour(self, cards: &[(u32, usize)]) -> Option<Self> {
+        let index = cards.iter().position(|(id, _)| *id == self.id)?;
+        cards
+            .get(index + 1)
+            .or_else(|| index.checked_sub(1).and_then(|above| cards.get(above)))
+            .map(|(id, _)| Self::card(*id))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const CARDS: [(u32, usize); 3] = [(7, 2), (5, 0), (3, 1)];
+
+    #[test]
+    fn test_arrows_stop_at_edges() {
+        let top = KeyFocus::first(&CARDS).unwrap();
+        assert_eq!(top, KeyFocus::card(7));
+        assert_eq!(top.moved(&CARDS, KeyMove::Up), Some(top));
+        assert_eq!(top.moved(&CARDS, KeyMove::Left), Some(top));
+
+        let button = top.moved(&CARDS, KeyMove::Right).unwrap();
+        assert_eq!(button.button, Some(0));
+        let last = button.moved(&CARDS, KeyMove::Right).unwrap();
+        assert_eq!(last.moved(&CARDS, KeyMove::Right), Some(last));
+
+        // Changing cards starts on the card itself
+        assert_eq!(last.moved(&CARDS, KeyMove::Down), Some(KeyFocus::card(5)));
+        let bottom = KeyFocus::card(3);
+        assert_eq!(bottom.moved(&CARDS, KeyMove::Down), Some(bottom));
+    }
+
+    #[test]
+    fn test_tab_visits_cards_and_buttons() {
+        let mut focus = KeyFocus::card(7);
+        let mut visited = vec![];
+        for _ in 0..6 {
+            focus = focus.moved(&CARDS, KeyMove::Next).unwrap();
+            visited.push((focus.id, focus.button));
+        }
+        assert_eq!(
+            visited,
+            vec![
+                (7, Some(0)),
+                (7, Some(1)),
+                (5, None),
+                (3, None),
+                (3, Some(0)),
+                (7, None),
+            ]
+        );
+        assert_eq!(
+            focus.moved(&CARDS, KeyMove::Previous),
+            Some(KeyFocus { id: 3, button: Some(0) })
+        );
+    }
+
+    #[test]
+    fn test_focus_follows_closed_cards() {
+        assert_eq!(KeyFocus::card(7).neighbour(&CARDS), Some(KeyFocus::card(5)));
+        assert_eq!(KeyFocus::card(3).neighbour(&CARDS), Some(KeyFocus::card(5)));
+        assert_eq!(KeyFocus::card(7).neighbour(&[(7, 0)]), None);
+
+        // A card that went away by itself
+        assert_eq!(
+            KeyFocus::card(9).moved(&CARDS, KeyMove::Down),
+            Some(KeyFocus::card(7))
+        );
+        assert_eq!(KeyFocus::card(9).moved(&[], KeyMove::Down), None);
+    }
+}
@@ -0,0 +1,251 @@
+use cosmic_ext_notifications_util::{Hint, Notification, strip_html};
+use std::collections::HashMap;
+use std::time::{Duration, Instant};
+
+/// Progress is announced when it crosses a step of this many percent
+const PROGRESS_STEP: i32 = 10;
+
+/// Minimum time between two progress announcements for one notification
+const PROGRESS_INTERVAL: Duration = Duration::from_secs(5);
+
+/// How urgently assistive technology should speak an update
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Politeness {
+    /// Wait until the screen reader is idle
+    Polite,
+    /// Interrupt what is being read (critical notifications)
+    Assertive,
+}
+
+/// What changed in a notification that is worth announcing
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum LiveChange {
+    /// A new popup appeared
+    Arrived {
+        app: String,
+        summary: String,
+        body: Option<String>,
+    },
+    /// New summary or body text, only the parts that changed are set
+    Content {
+        summary: Option<String>,
+        body: Option<String>,
+    },
+    /// Progress moved on to a new step, or finished
+    Progress { summary: String, percent: u32 },
+}
+
+/// A live region update for one notification card
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LiveUpdate {
+    pub id: u32,
+    pub change: LiveChange,
+    pub politeness: Politeness,
+}
+
+impl LiveUpdate {
+    /// The update without body text, `None` if nothing else is left
+    pub fn without_body(mut self) -> Option<Self> {
+        match &mut self.change {
+            LiveChange::Arrived { body, .. } => *body = None,
+            LiveChange::Content { summary, body } => {
+                *body = None;
+                summary.as_ref()?;
+            }
+            LiveChange::Progress { .. } => {}
+        }
+        Some(self)
+    }
+}
+
+/// Decides which notification updates screen reader users hear
+///
+/// A replaced notification is announced as a change, not re-read in full.
+/// Progress updates are announced at most every [`PROGRESS_INTERVAL`] and
+/// only when they reach the next [`PROGRESS_STEP`], apart from completion
+/// which is always announced. Updates that change nothing readable, such
+/// as a new image, stay silent.
+#[derive(Debug, Default)]
+pub struct LiveAnnouncer {
+    /// Last announced progress step and when, per notification
+    progress: HashMap<u32, (i32, Instant)>,
+}
+
+impl LiveAnnouncer {
+    /// The update to announce when `n` pops up
+    pub fn arrived(&self, n: &Notification) -> LiveUpdate {
+        LiveUpdate {
+            id: n.id,
+            change: LiveChange::Arrived {
+                app: n.app_name.clone(),
+                summary: n.summary.clone(),
+                body: Some(strip_html(&n.body)).filter(|b| !b.trim().is_empty()),
+            },
+            politeness: politeness(n),
+        }
+    }
+
+    /// The update to announce when `old` is replaced by `new`
+    pub fn replaced(&mut self, old: &Notification, new: &Notification) -> Option<LiveUpdate> {
+        self.replaced_at(old, new, Instant::now())
+    }
+
+    fn replaced_at(
+        &mut self,
+        old: &Notification,
+        new: &Notification,
+        now: Instant,
+    ) -> Option<LiveUpdate> {
+        let politeness = politeness(new);
+
+        let summary = (new.summary != old.summary).then(|| new.summary.clone());
+        let body = (new.body != old.body)
+            .then(|| strip_html(&new.body))
+            .filter(|b| !b.trim().is_empty());
+        if summary.is_some() || body.is_some() {
+            self.progress.remove(&new.id);
+            return Some(LiveUpdate {
+                id: new.id,
+                change: LiveChange::Content { summary, body },
+                politeness,
+            });
+        }
+
+        let percent = progress(new)?;
+        if progress(old) == Some(percent) {
+            return None;
+        }
+        let step = percent / PROGRESS_STEP;
+        let done = percent >= 100;
+        if let Some((last_step, at)) = self.progress.get(&new.id) {
+            let too_soon = now.duration_since(*at) < PROGRESS_INTERVAL;
+            if !done && (step == *last_step || too_soon) {
+                return None;
+            }
+        }
+        self.progress.insert(new.id, (step, now));
+
+        Some(LiveUpdate {
+            id: new.id,
+            change: LiveChange::Progress {
+                summary: new.summary.clone(),
+                percent: percent as u32,
+            },
+            politeness,
+        })
+    }
+
+    /// Drop state for a notification that was closed
+    pub fn forget(&mut self, id: u32) {
+        self.progress.remove(&id);
+    }
+}
+
+/// Critical notifications interrupt, others wait their turn
+fn politeness(n: &Notification) -> Politeness {
+    if n.urgency() == 2 {
+        Politeness::Assertive
+    } else {
+        Politeness::Polite
+    }
+}
+
+fn progress(n: &Notification) -> Option<i32> {
+    n.hints.iter().find_map(|h| match h {
+        Hint::Value(v) => Some((*v).clamp(0, 100)),
+        _ => None,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::SystemTime;
+
+    fn notification(summary: &str, body: &str, value: Option<i32>) -> Notification {
+        Notification {
+            id: 7,
+            app_name: "test_app".to_string(),
+            app_icon: String::new(),
+            summary: summary.to_string(),
+            body: body.to_string(),
+            actions: vec![],
+            hints: value.map(Hint::Value).into_iter().collect(),
+            expire_timeout: -1,
+            applied_timeout: None,
+            time: SystemTime::now(),
+        }
+    }
+
+    #[test]
+    fn test_only_changed_text_is_announced() {
+        let mut announcer = LiveAnnouncer::default();
+        let old = notification("Alice", "Hi", None);
+        let new = notification("Alice", "<b>How are you?</b>", None);
+
+        let update = announcer.replaced(&old, &new).unwrap();
+        assert_eq!(
+            update.change,
+            LiveChange::Content {
+                summary: None,
+                body: Some("How are you?".to_string())
+            }
+        );
+        assert_eq!(update.politeness, Politeness::Polite);
+
+        // Identical replacement (e.g. a new icon) is silent
+        assert!(announcer.replaced(&new, &new.clone()).is_none());
+    }
+
+    #[test]
+    fn test_arrival_without_body() {
+        let announcer = LiveAnnouncer::default();
+        let update = announcer.arrived(&notification("Alice", "<i>Lunch?</i>", None));
+        assert_eq!(
+            update.change,
+            LiveChange::Arrived {
+                app: "test_app".to_string(),
+                summary: "Alice".to_string(),
+                body: Some("Lunch?".to_string())
+            }
+        );
+
+        let brief = update.without_body().unwrap();
+        assert!(matches!(brief.change, LiveChange::Arrived { body: None, .. }));
+
+        // A body-only change has nothing left to say
+        let mut announcer = LiveAnnouncer::default();
+        let old = notification("Alice", "Hi", None);
+        let new = notification("Alice", "Bye", None);
+        assert!(announcer.replaced(&old, &new).unwrap().without_body().is_none());
+    }
+
+    #[test]
+    fn test_progress_is_throttled() {
+        let mut announcer = LiveAnnouncer::default();
+        let start = Instant::now();
+        let at = |secs| start + Duration::from_secs(secs);
+        let step = |from, to| (notification("Copying", "", Some(from)), notification("Copying", "", Some(to)));
+
+        let (old, new) = step(0, 12);
+        assert!(announcer.replaced_at(&old, &new, at(0)).is_some());
+        // Same 10% step
+        let (old, new) = step(12, 18);
+        assert!(announcer.replaced_at(&old, &new, at(10)).is_none());
+        // Next step, but too soon
+        let (old, new) = step(18, 25);
+        assert!(announcer.replaced_at(&old, &new, at(11)).is_none());
+        let (old, new) = step(25, 31);
+        assert!(announcer.replaced_at(&old, &new, at(20)).is_some());
+        // Completion is always announced
+        let (old, new) = step(31, 100);
+        let update = announcer.replaced_at(&old, &new, at(21)).unwrap();
+        assert_eq!(
+            update.change,
+            LiveChange::Progress {
+                summary: "Copying".to_string(),
+                percent: 100
+            }
+        );
+    }
+}
@@ -1,3 +1,32 @@
+pub mod focus;
+pub mod digest;
+pub mod history;
+pub mod images;
+pub mod keyboard;
+pub mod live_region;
 pub mod notifications;
+pub mod priority;
+pub mod restore;
+pub mod search;
+pub mod snooze;
+pub mod stats;
+pub mod storm;
+pub mod swipe;
+pub mod transition;
+pub mod wakeup;
 
+pub use digest::DigestQueue;
+pub use focus::FocusSession;
+pub use history::{DateSection, SectionCollapseState};
+pub use images::{AnimatedHandles, DecodedImage, ImageCache, ImageSlot};
+pub use keyboard::{KeyFocus, KeyMove};
+pub use live_region::{LiveAnnouncer, LiveChange, LiveUpdate, Politeness};
 pub use notifications::NotificationState;
+pub use priority::PriorityScorer;
+pub use search::SearchIndex;
+pub use snooze::{snooze_due, SnoozeQueue};
+pub use stats::HistoryStats;
+pub use storm::{StormDetector, StormVerdict};
+pub use swipe::Swipe;
+pub use transition::{Exit, Transitions};
+pub use wakeup::{Wakeup, Wakeups};
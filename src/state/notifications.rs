@@ -14,6 +14,11 @@ pub struct NotificationState {
     cards: Vec<Notification>,
     /// Hidden notifications (dismissed or expired)
     hidden: VecDeque<Notification>,
+    /// Next ID for notifications generated by the daemon itself
+    ///
+    /// Counts down from `u32::MAX` so it stays clear of client IDs, which
+    /// count up from 1.
+    next_internal_id: u32,
 }
 
 impl NotificationState {
@@ -22,6 +27,7 @@ impl NotificationState {
         Self {
             cards: Vec::with_capacity(INITIAL_CARDS_CAPACITY),
             hidden: VecDeque::new(),
+            next_internal_id: u32::MAX,
         }
     }
 
@@ -50,6 +56,21 @@ impl NotificationState {
         self.cards.push(notification);
     }
 
+    /// Add a notification directly to hidden history without showing it
+    ///
+    /// Applies memory budget management to hidden queue
+    pub fn add_hidden(&mut self, notification: Notification) {
+        self.hidden.push_front(notification);
+        self.apply_memory_budget(MAX_HIDDEN_MEMORY);
+    }
+
+    /// Allocate an ID for a notification generated by the daemon itself
+    pub fn next_internal_id(&mut self) -> u32 {
+        let id = self.next_internal_id;
+        self.next_internal_id = self.next_internal_id.checked_sub(1).unwrap_or(u32::MAX);
+        id
+    }
+
     /// Remove a notification by ID from both visible and hidden queues
     ///
     /// Returns the removed notification if found
@@ -98,6 +119,11 @@ impl NotificationState {
         self.hidden.truncate(keep_count);
     }
 
+    /// Drop every hidden notification
+    pub fn clear_hidden(&mut self) {
+        self.hidden = VecDeque::new();
+    }
+
     /// Get total memory usage of hidden notifications
     pub fn hidden_memory_usage(&self) -> usize {
         self.hidden.iter().map(|n| n.estimated_size()).sum()
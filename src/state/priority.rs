@@ -0,0 +1,162 @@
+use cosmic_ext_notifications_config::{NotificationsConfig, PriorityWeights};
+use cosmic_ext_notifications_util::Notification;
+use std::collections::HashMap;
+use std::time::SystemTime;
+
+use super::stats::AppCounts;
+use crate::constants::{PRIORITY_MIN_RECEIVED, PRIORITY_RECENCY_SECS};
+
+/// Ranks notifications for `smart_ordering`
+///
+/// The score is a weighted sum of four signals, each between 0 and 1
+/// except the app rule, which goes from -1 to 1: the urgency, the
+/// `priority` of the app's rule, how new the notification is and how
+/// often the user acted on the app's notifications lately.
+#[derive(Debug)]
+pub struct PriorityScorer<'a> {
+    config: &'a NotificationsConfig,
+    weights: PriorityWeights,
+    /// Share of each app's notifications the user acted on
+    engagement: HashMap<String, f64>,
+    now: SystemTime,
+}
+
+impl<'a> PriorityScorer<'a> {
+    /// Scorer for `now`, with `per_app` counts from [`super::HistoryStats::per_app`]
+    pub fn new(config: &'a NotificationsConfig, per_app: Vec<(String, AppCounts)>, now: SystemTime) -> Self {
+        let engagement = per_app
+            .into_iter()
+            .filter(|(_, counts)| counts.received >= PRIORITY_MIN_RECEIVED)
+            .map(|(app, counts)| {
+                let rate = counts.rate(counts.actioned).min(1.0);
+                (app, rate)
+            })
+            .collect();
+        Self {
+            config,
+            weights: config.priority_weights,
+            engagement,
+            now,
+        }
+    }
+
+    /// Higher scores matter more
+    pub fn score(&self, notification: &Notification) -> f64 {
+        let urgency = f64::from(notification.urgency().min(2)) / 2.0;
+        let app = f64::from(
+            self.config
+                .priority_for_app(&notification.app_name, notification.desktop_entry()),
+        ) / 100.0;
+        let age = self
+            .now
+            .duration_since(notification.time)
+            .unwrap_or_default()
+            .as_secs_f64();
+        let recency = 1.0 / (1.0 + age / PRIORITY_RECENCY_SECS);
+        let interaction = self
+            .engagement
+            .get(&notification.app_name)
+            .copied()
+            .unwrap_or(0.0);
+
+        f64::from(self.weights.urgency) * urgency
+            + f64::from(self.weights.app) * app
+            + f64::from(self.weights.recency) * recency
+            + f64::from(self.weights.interaction) * interaction
+    }
+
+    /// Order of `a` and `b` by score, older first on a tie
+    pub fn cmp(&self, a: &Notification, b: &Notification) -> std::cmp::Ordering {
+        self.score(a)
+            .total_cmp(&self.score(b))
+            .then_with(|| a.time.cmp(&b.time))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use cosmic_ext_notifications_config::AppRule;
+    use cosmic_ext_notifications_util::Hint;
+    use std::time::Duration;
+
+    fn notification(app_name: &str, urgency: u8, time: SystemTime) -> Notification {
+        Notification {
+            id: 1,
+            app_name: app_name.to_string(),
+            app_icon: String::new(),
+            summary: "Summary".to_string(),
+            body: String::new(),
+            actions: vec![],
+            hints: vec![Hint::Urgency(urgency)],
+            expire_timeout: -1,
+            applied_timeout: None,
+            time,
+        }
+    }
+
+    fn counts(received: u32, actioned: u32) -> AppCounts {
+        AppCounts {
+            received,
+            actioned,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_urgency_outweighs_recency_by_default() {
+        let config = NotificationsConfig::default();
+        let now = SystemTime::now();
+        let scorer = PriorityScorer::new(&config, Vec::new(), now);
+
+        let old_critical = notification("Pager", 2, now - Duration::from_secs(3600));
+        let new_normal = notification("Chat", 1, now);
+        assert!(scorer.score(&old_critical) > scorer.score(&new_normal));
+
+        let old_normal = notification("Chat", 1, now - Duration::from_secs(3600));
+        assert!(scorer.score(&new_normal) > scorer.score(&old_normal));
+    }
+
+    #[test]
+    fn test_app_rule_and_interaction() {
+        let mut config = NotificationsConfig::default();
+        config.app_rules.push(AppRule {
+            app_name: "Newsletter".to_string(),
+            priority: Some(-100),
+            ..Default::default()
+        });
+        let now = SystemTime::now();
+        let per_app = vec![
+            ("Mail".to_string(), counts(20, 15)),
+            ("Shop".to_string(), counts(20, 0)),
+            // Too few to tell
+            ("New".to_string(), counts(1, 1)),
+        ];
+        let scorer = PriorityScorer::new(&config, per_app, now);
+
+        let mail = scorer.score(&notification("Mail", 1, now));
+        let shop = scorer.score(&notification("Shop", 1, now));
+        let new = scorer.score(&notification("New", 1, now));
+        let newsletter = scorer.score(&notification("Newsletter", 1, now));
+        assert!(mail > shop);
+        assert_eq!(shop, new);
+        assert!(newsletter < shop);
+    }
+
+    #[test]
+    fn test_zero_weights_tie_on_time() {
+        let mut config = NotificationsConfig::default();
+        config.priority_weights = PriorityWeights {
+            urgency: 0,
+            app: 0,
+            recency: 0,
+            interaction: 0,
+        };
+        let now = SystemTime::now();
+        let scorer = PriorityScorer::new(&config, Vec::new(), now);
+
+        let older = notification("Pager", 2, now - Duration::from_secs(60));
+        let newer = notification("Chat", 0, now);
+        assert_eq!(scorer.cmp(&older, &newer), std::cmp::Ordering::Less);
+    }
+}
@@ -0,0 +1,91 @@
+use cosmic_ext_notifications_util::{Notification, NotificationRecord};
+
+/// Popups and held notifications saved for the next run
+///
+/// Saved as `(expires, record JSON)` pairs, where `expires` is the Unix
+/// second the popup was due to expire at, or 0 if it had no deadline.
+/// Records don't carry image data, so restored notifications come back
+/// without their image.
+pub fn to_saved<'a>(
+    pending: impl IntoIterator<Item = (&'a Notification, Option<u64>)>,
+) -> Vec<(u64, String)> {
+    pending
+        .into_iter()
+        .map(|(n, expires)| {
+            (
+                expires.unwrap_or(0),
+                NotificationRecord::from_notification(n).to_json(),
+            )
+        })
+        .collect()
+}
+
+/// Notifications saved by a previous run that haven't expired by `now`
+///
+/// Each gets a timeout covering what was left of its popup time. Ids are
+/// kept as saved; the daemon restarted its numbering, so the caller has to
+/// assign new ones.
+pub fn from_saved(saved: Vec<(u64, String)>, now: u64) -> Vec<Notification> {
+    saved
+        .into_iter()
+        .filter(|(expires, _)| *expires == 0 || *expires > now)
+        .filter_map(|(expires, json)| match NotificationRecord::from_json(&json) {
+            Ok(record) => Some((expires, record.to_notification())),
+            Err(err) => {
+                tracing::warn!("Dropping unreadable saved notification: {}", err);
+                None
+            }
+        })
+        .map(|(expires, mut n)| {
+            if expires != 0 {
+                let remaining_ms = (expires - now).saturating_mul(1000);
+                n.expire_timeout = i32::try_from(remaining_ms).unwrap_or(i32::MAX);
+            }
+            n.applied_timeout = None;
+            n
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::{Duration, UNIX_EPOCH};
+
+    fn notification(id: u32, summary: &str, expire_timeout: i32) -> Notification {
+        Notification {
+            id,
+            app_name: "Mail".to_string(),
+            app_icon: String::new(),
+            summary: summary.to_string(),
+            body: String::new(),
+            actions: vec![],
+            hints: vec![],
+            expire_timeout,
+            applied_timeout: Some(5000),
+            time: UNIX_EPOCH + Duration::from_secs(1_000),
+        }
+    }
+
+    #[test]
+    fn test_restore_drops_expired() {
+        let expired = notification(1, "expired", 5000);
+        let running = notification(2, "running", 5000);
+        let sticky = notification(3, "sticky", 0);
+        let mut saved = to_saved([
+            (&expired, Some(100)),
+            (&running, Some(104)),
+            (&sticky, None),
+        ]);
+        saved.push((0, "not json".to_string()));
+
+        let restored = from_saved(saved, 100);
+        let summaries: Vec<_> = restored.iter().map(|n| n.summary.as_str()).collect();
+        assert_eq!(summaries, vec!["running", "sticky"]);
+
+        // Only the time that was left
+        assert_eq!(restored[0].expire_timeout, 4000);
+        assert_eq!(restored[0].applied_timeout, None);
+        assert_eq!(restored[1].expire_timeout, 0);
+    }
+}
@@ -0,0 +1,170 @@
+use cosmic_ext_notifications_util::{Notification, strip_html};
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use std::time::SystemTime;
+
+/// Full-text index over the notification history
+///
+/// Maps each word of the app name, summary and plain-text body to the
+/// notifications containing it. The history can change in many places, so
+/// instead of hooking every one the index is brought up to date with
+/// [`SearchIndex::sync`] right before searching; only notifications it
+/// hasn't seen are tokenized.
+#[derive(Debug, Default)]
+pub struct SearchIndex {
+    /// Indexed notifications by id, with the time of the indexed version
+    /// and its words
+    docs: HashMap<u32, (SystemTime, Vec<String>)>,
+    /// Notification ids by word
+    terms: BTreeMap<String, BTreeSet<u32>>,
+}
+
+impl SearchIndex {
+    /// Index what's new in `notifications` and forget what's gone
+    ///
+    /// A notification replaced under the same id has a new time, so it is
+    /// indexed again.
+    pub fn sync<'a>(&mut self, notifications: impl IntoIterator<Item = &'a Notification>) {
+        let mut current = HashMap::new();
+        for n in notifications {
+            current.insert(n.id, n);
+        }
+
+        let stale: Vec<u32> = self
+            .docs
+            .iter()
+            .filter(|(id, (time, _))| current.get(id).is_none_or(|n| n.time != *time))
+            .map(|(id, _)| *id)
+            .collect();
+        for id in stale {
+            self.remove(id);
+        }
+
+        for (id, n) in current {
+            if self.docs.contains_key(&id) {
+                continue;
+            }
+            let words = document_words(n);
+            for word in &words {
+                self.terms.entry(word.clone()).or_default().insert(id);
+            }
+            self.docs.insert(id, (n.time, words));
+        }
+    }
+
+    fn remove(&mut self, id: u32) {
+        let Some((_, words)) = self.docs.remove(&id) else {
+            return;
+        };
+        for word in words {
+            if let Some(ids) = self.terms.get_mut(&word) {
+                ids.remove(&id);
+                if ids.is_empty() {
+                    self.terms.remove(&word);
+                }
+            }
+        }
+    }
+
+    /// Ids of the notifications matching every word of `query`
+    ///
+    /// Each query word matches indexed words it is a prefix of, so "otp"
+    /// finds "OTP" and "invo" finds "invoice". An empty query matches
+    /// nothing.
+    pub fn search(&self, query: &str) -> BTreeSet<u32> {
+        let mut matches: Option<BTreeSet<u32>> = None;
+        for term in tokenize(query) {
+            let ids: BTreeSet<u32> = self
+                .terms
+                .range(term.clone()..)
+                .take_while(|(word, _)| word.starts_with(&term))
+                .flat_map(|(_, ids)| ids.iter().copied())
+                .collect();
+            let narrowed = match matches {
+                Some(prev) => prev.intersection(&ids).copied().collect(),
+                None => ids,
+            };
+            if narrowed.is_empty() {
+                return narrowed;
+            }
+            matches = Some(narrowed);
+        }
+        matches.unwrap_or_default()
+    }
+
+    pub fn len(&self) -> usize {
+        self.docs.len()
+    }
+}
+
+/// Distinct words of a notification's app name, summary and body
+fn document_words(n: &Notification) -> Vec<String> {
+    let text = format!("{} {} {}", n.app_name, n.summary, strip_html(&n.body));
+    let words: BTreeSet<String> = tokenize(&text).collect();
+    words.into_iter().collect()
+}
+
+/// Lowercased alphanumeric runs, so codes like "482913" are words too
+fn tokenize(text: &str) -> impl Iterator<Item = String> + '_ {
+    text.split(|c: char| !c.is_alphanumeric())
+        .filter(|word| !word.is_empty())
+        .map(str::to_lowercase)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::{Duration, UNIX_EPOCH};
+
+    fn notification(id: u32, app: &str, summary: &str, body: &str) -> Notification {
+        Notification {
+            id,
+            app_name: app.to_string(),
+            app_icon: String::new(),
+            summary: summary.to_string(),
+            body: body.to_string(),
+            actions: vec![],
+            hints: vec![],
+            expire_timeout: -1,
+            applied_timeout: None,
+            time: UNIX_EPOCH + Duration::from_secs(1_000),
+        }
+    }
+
+    #[test]
+    fn test_search_prefix_and_all_terms() {
+        let history = [
+            notification(1, "Messages", "Bank", "Your OTP is <b>482913</b>"),
+            notification(2, "Mail", "Invoice", "Invoice 2024-17 is due"),
+            notification(3, "Messages", "Alice", "See you at the OTP meeting?"),
+        ];
+        let mut index = SearchIndex::default();
+        index.sync(&history);
+
+        assert_eq!(index.search("otp"), BTreeSet::from([1, 3]));
+        assert_eq!(index.search("OTP 4829"), BTreeSet::from([1]));
+        assert_eq!(index.search("invo mail"), BTreeSet::from([2]));
+        assert!(index.search("otp invoice").is_empty());
+        assert!(index.search("  ").is_empty());
+        // Markup isn't indexed
+        assert!(index.search("b").is_empty());
+    }
+
+    #[test]
+    fn test_sync_follows_history() {
+        let mut index = SearchIndex::default();
+        index.sync(&[
+            notification(1, "Mail", "Invoice", ""),
+            notification(2, "Mail", "Receipt", ""),
+        ]);
+        assert_eq!(index.len(), 2);
+
+        // 2 is gone, 1 was replaced with new content
+        let mut replaced = notification(1, "Mail", "Reminder", "");
+        replaced.time += Duration::from_secs(60);
+        index.sync(&[replaced]);
+        assert_eq!(index.len(), 1);
+        assert!(index.search("invoice").is_empty());
+        assert!(index.search("receipt").is_empty());
+        assert_eq!(index.search("remind"), BTreeSet::from([1]));
+    }
+}
@@ -0,0 +1,216 @@
+use chrono::{DateTime, Days, TimeZone};
+use cosmic_ext_notifications_config::SnoozePreset;
+use cosmic_ext_notifications_util::{Notification, NotificationRecord};
+
+use crate::constants::SNOOZE_TOMORROW_HOUR;
+
+/// Unix seconds when a notification snoozed at `now` with `preset` is due
+///
+/// "Tomorrow" means `SNOOZE_TOMORROW_HOUR` on the next day in `now`'s time
+/// zone, or a day from now if that hour doesn't exist there.
+pub fn snooze_due<Tz: TimeZone>(preset: SnoozePreset, now: &DateTime<Tz>) -> u64 {
+    let now_secs = u64::try_from(now.timestamp()).unwrap_or_default();
+    match preset {
+        SnoozePreset::Minutes(minutes) => now_secs + u64::from(minutes) * 60,
+        SnoozePreset::Tomorrow => now
+            .date_naive()
+            .checked_add_days(Days::new(1))
+            .and_then(|day| day.and_hms_opt(SNOOZE_TOMORROW_HOUR, 0, 0))
+            .and_then(|morning| morning.and_local_timezone(now.timezone()).earliest())
+            .and_then(|due| u64::try_from(due.timestamp()).ok())
+            .unwrap_or(now_secs + 24 * 60 * 60),
+    }
+}
+
+/// Notifications put away until a later time
+///
+/// The queue is saved as `(due, record JSON)` pairs so it survives a
+/// restart. Records don't carry image data, so a notification restored
+/// from disk comes back without its image.
+#[derive(Debug, Default)]
+pub struct SnoozeQueue {
+    /// Unix seconds when each notification is due again, with the notification
+    entries: Vec<(u64, Notification)>,
+}
+
+impl SnoozeQueue {
+    /// Rebuild the queue saved by a previous run
+    ///
+    /// Notification ids restart at 1 with the daemon, so restored entries
+    /// get ids from the top of the range where new ones won't collide.
+    pub fn from_saved(saved: Vec<(u64, String)>) -> Self {
+        let entries = saved
+            .into_iter()
+            .filter_map(|(due, json)| match NotificationRecord::from_json(&json) {
+                Ok(record) => Some((due, record.to_notification())),
+                Err(err) => {
+                    tracing::warn!("Dropping unreadable snoozed notification: {}", err);
+                    None
+                }
+            })
+            .zip((0..=u32::MAX).rev())
+            .map(|((due, mut n), id)| {
+                n.id = id;
+                (due, n)
+            })
+            .collect();
+        Self { entries }
+    }
+
+    /// The queue in its saved form, leaving out notifications `keep`
+    /// rejects
+    pub fn to_saved(&self, keep: impl Fn(&Notification) -> bool) -> Vec<(u64, String)> {
+        self.entries
+            .iter()
+            .filter(|(_, n)| keep(n))
+            .map(|(due, n)| (*due, NotificationRecord::from_notification(n).to_json()))
+            .collect()
+    }
+
+    /// Put a notification away until `due`, replacing an earlier snooze of it
+    pub fn snooze(&mut self, notification: Notification, due: u64) {
+        self.entries.retain(|(_, n)| n.id != notification.id);
+        self.entries.push((due, notification));
+    }
+
+    /// Update a snoozed notification its app replaced, keeping it snoozed
+    ///
+    /// Returns `false` if no notification with that id is snoozed.
+    pub fn replace(&mut self, notification: Notification) -> bool {
+        let Some((_, n)) = self.entries.iter_mut().find(|(_, n)| n.id == notification.id) else {
+            return false;
+        };
+        *n = notification;
+        true
+    }
+
+    /// Drop a snoozed notification its app closed
+    ///
+    /// Returns `false` if no notification with that id is snoozed.
+    pub fn remove(&mut self, id: u32) -> bool {
+        let len = self.entries.len();
+        self.entries.retain(|(_, n)| n.id != id);
+        self.entries.len() != len
+    }
+
+    /// Keep only the snoozed notifications `keep` accepts
+    pub fn retain(&mut self, mut keep: impl FnMut(&Notification) -> bool) {
+        self.entries.retain(|(_, n)| keep(n));
+    }
+
+    /// Remove and return the notifications due at `now`, earliest first
+    pub fn take_due(&mut self, now: u64) -> Vec<Notification> {
+        let (mut due, pending): (Vec<_>, Vec<_>) = std::mem::take(&mut self.entries)
+            .into_iter()
+            .partition(|(at, _)| *at <= now);
+        self.entries = pending;
+        due.sort_by_key(|(at, _)| *at);
+        due.into_iter().map(|(_, n)| n).collect()
+    }
+
+    /// When the next notification is due
+    pub fn next_due(&self) -> Option<u64> {
+        self.entries.iter().map(|(due, _)| *due).min()
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::{Duration, UNIX_EPOCH};
+
+    fn notification(id: u32, summary: &str) -> Notification {
+        Notification {
+            id,
+            app_name: "Mail".to_string(),
+            app_icon: String::new(),
+            summary: summary.to_string(),
+            body: String::new(),
+            actions: vec![],
+            hints: vec![],
+            expire_timeout: -1,
+            applied_timeout: None,
+            time: UNIX_EPOCH + Duration::from_secs(1_000),
+        }
+    }
+
+    #[test]
+    fn test_take_due_in_order() {
+        let mut queue = SnoozeQueue::default();
+        queue.snooze(notification(1, "later"), 300);
+        queue.snooze(notification(2, "sooner"), 200);
+        queue.snooze(notification(3, "pending"), 900);
+        assert_eq!(queue.next_due(), Some(200));
+
+        let due: Vec<_> = queue.take_due(500).into_iter().map(|n| n.summary).collect();
+        assert_eq!(due, vec!["sooner", "later"]);
+        assert_eq!(queue.len(), 1);
+        assert_eq!(queue.next_due(), Some(900));
+    }
+
+    #[test]
+    fn test_snoozing_again_replaces() {
+        let mut queue = SnoozeQueue::default();
+        queue.snooze(notification(1, "first"), 100);
+        queue.snooze(notification(1, "again"), 200);
+        assert_eq!(queue.len(), 1);
+        assert_eq!(queue.next_due(), Some(200));
+    }
+
+    #[test]
+    fn test_replace_and_remove() {
+        let mut queue = SnoozeQueue::default();
+        queue.snooze(notification(1, "Build running"), 300);
+        queue.snooze(notification(2, "Invoice due"), 200);
+
+        assert!(queue.replace(notification(1, "Build finished")));
+        assert!(!queue.replace(notification(3, "Unknown")));
+        assert_eq!(queue.len(), 2);
+
+        assert!(queue.remove(2));
+        assert!(!queue.remove(2));
+        queue.snooze(notification(3, "Reminder"), 100);
+        queue.retain(|n| n.id != 3);
+        assert_eq!(queue.len(), 1);
+        let due = queue.take_due(300);
+        assert_eq!(due.len(), 1);
+        assert_eq!(due[0].summary, "Build finished");
+    }
+
+    #[test]
+    fn test_snooze_presets() {
+        use chrono::{FixedOffset, TimeZone};
+
+        let zone = FixedOffset::east_opt(2 * 3600).unwrap();
+        let evening = zone.with_ymd_and_hms(2024, 3, 9, 22, 30, 0).unwrap();
+        let start = evening.timestamp() as u64;
+
+        assert_eq!(snooze_due(SnoozePreset::Minutes(5), &evening), start + 300);
+        assert_eq!(snooze_due(SnoozePreset::Minutes(60), &evening), start + 3600);
+        let morning = zone.with_ymd_and_hms(2024, 3, 10, 9, 0, 0).unwrap();
+        assert_eq!(snooze_due(SnoozePreset::Tomorrow, &evening), morning.timestamp() as u64);
+    }
+
+    #[test]
+    fn test_saved_round_trip() {
+        let mut queue = SnoozeQueue::default();
+        queue.snooze(notification(7, "Invoice due"), 100);
+        let mut private = notification(8, "Your code is 123456");
+        private.app_name = "Bank".to_string();
+        queue.snooze(private, 100);
+
+        let mut saved = queue.to_saved(|n| n.app_name != "Bank");
+        assert_eq!(saved.len(), 1);
+        saved.push((50, "not json".to_string()));
+        let mut restored = SnoozeQueue::from_saved(saved);
+        assert_eq!(restored.len(), 1);
+
+        let due = restored.take_due(100);
+        assert_eq!(due[0].summary, "Invoice due");
+        assert_eq!(due[0].id, u32::MAX);
+    }
+}
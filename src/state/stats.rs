@@ -0,0 +1,205 @@
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, VecDeque};
+
+/// What happened to a notification, as counted by [`HistoryStats`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Outcome {
+    Received,
+    /// Closed by the user
+    Dismissed,
+    /// Clicked, or one of its actions was
+    Actioned,
+    /// The popup timed out
+    Expired,
+}
+
+/// What became of a notification, as written to the journal audit
+///
+/// Besides the outcomes counted in [`HistoryStats`], these are the ways a
+/// notification can be kept from popping up.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AuditOutcome {
+    Counted(Outcome),
+    /// Sent to history, or left without a popup, by do-not-disturb
+    SuppressedByDnd,
+    /// Dropped by the server for arriving too fast
+    RateLimited,
+    /// Dropped by a plugin
+    DroppedByPlugin,
+    /// Kept back while notifications are paused
+    Held,
+    /// Sent to history by a notification storm or the app's digest
+    Quieted,
+}
+
+impl From<Outcome> for AuditOutcome {
+    fn from(outcome: Outcome) -> Self {
+        Self::Counted(outcome)
+    }
+}
+
+/// Outcome counts for one app
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct AppCounts {
+    pub received: u32,
+    pub dismissed: u32,
+    pub actioned: u32,
+    pub expired: u32,
+}
+
+impl AppCounts {
+    fn add(&mut self, outcome: Outcome) {
+        let count = match outcome {
+            Outcome::Received => &mut self.received,
+            Outcome::Dismissed => &mut self.dismissed,
+            Outcome::Actioned => &mut self.actioned,
+            Outcome::Expired => &mut self.expired,
+        };
+        *count = count.saturating_add(1);
+    }
+
+    fn merge(&mut self, other: &AppCounts) {
+        self.received = self.received.saturating_add(other.received);
+        self.dismissed = self.dismissed.saturating_add(other.dismissed);
+        self.actioned = self.actioned.saturating_add(other.actioned);
+        self.expired = self.expired.saturating_add(other.expired);
+    }
+
+    /// Share of received notifications that ended with `count`, 0 without any
+    pub fn rate(&self, count: u32) -> f64 {
+        if self.received == 0 {
+            0.0
+        } else {
+            f64::from(count) / f64::from(self.received)
+        }
+    }
+}
+
+/// Counts for one local day
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+struct Day {
+    /// Days since the common era, see `NaiveDate::num_days_from_ce`
+    day: i32,
+    apps: BTreeMap<String, AppCounts>,
+    /// Notifications received per local hour
+    hours: [u32; 24],
+}
+
+/// Notification counts per app and per hour, kept per day
+///
+/// Only aggregates are stored, never notification content, so the stats
+/// can be kept for weeks without holding on to anything private.
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct HistoryStats {
+    /// Oldest first
+    days: VecDeque<Day>,
+}
+
+impl HistoryStats {
+    /// Count an outcome for `app` on `day` at local `hour`
+    ///
+    /// The hour only matters for [`Outcome::Received`].
+    pub fn record(&mut self, app: &str, outcome: Outcome, day: i32, hour: u32) {
+        if self.days.back().is_none_or(|d| d.day < day) {
+            self.days.push_back(Day {
+                day,
+                ..Default::default()
+            });
+        }
+        // A clock set back lands in the newest day rather than reordering
+        let Some(entry) = self.days.back_mut() else {
+            return;
+        };
+        entry.apps.entry(app.to_string()).or_default().add(outcome);
+        if outcome == Outcome::Received {
+            if let Some(count) = entry.hours.get_mut(hour as usize) {
+                *count = count.saturating_add(1);
+            }
+        }
+    }
+
+    /// Forget days before `oldest`
+    pub fn prune(&mut self, oldest: i32) {
+        while self.days.front().is_some_and(|d| d.day < oldest) {
+            self.days.pop_front();
+        }
+    }
+
+    /// Counts per app from `since` on, most notifications first
+    pub fn per_app(&self, since: i32) -> Vec<(String, AppCounts)> {
+        let mut apps: BTreeMap<&str, AppCounts> = BTreeMap::new();
+        for day in self.days.iter().filter(|d| d.day >= since) {
+            for (app, counts) in &day.apps {
+                apps.entry(app.as_str()).or_default().merge(counts);
+            }
+        }
+        let mut apps: Vec<_> = apps
+            .into_iter()
+            .map(|(app, counts)| (app.to_string(), counts))
+            .collect();
+        apps.sort_by(|a, b| b.1.received.cmp(&a.1.received));
+        apps
+    }
+
+    /// Notifications received in each local hour of the day from `since` on
+    pub fn per_hour(&self, since: i32) -> [u32; 24] {
+        let mut hours = [0u32; 24];
+        for day in self.days.iter().filter(|d| d.day >= since) {
+            for (total, count) in hours.iter_mut().zip(day.hours) {
+                *total = total.saturating_add(count);
+            }
+        }
+        hours
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_counts_per_app_and_hour() {
+        let mut stats = HistoryStats::default();
+        stats.record("Firefox", Outcome::Received, 10, 9);
+        stats.record("Firefox", Outcome::Dismissed, 10, 9);
+        stats.record("Mail", Outcome::Received, 10, 14);
+        stats.record("Firefox", Outcome::Received, 11, 9);
+        stats.record("Firefox", Outcome::Actioned, 11, 9);
+
+        let apps = stats.per_app(0);
+        assert_eq!(apps[0].0, "Firefox");
+        assert_eq!(
+            apps[0].1,
+            AppCounts {
+                received: 2,
+                dismissed: 1,
+                actioned: 1,
+                expired: 0,
+            }
+        );
+        assert_eq!(apps[0].1.rate(apps[0].1.dismissed), 0.5);
+        assert_eq!(apps[1].0, "Mail");
+
+        let hours = stats.per_hour(0);
+        assert_eq!(hours[9], 2);
+        assert_eq!(hours[14], 1);
+
+        // Only the second day
+        assert_eq!(stats.per_app(11).len(), 1);
+        assert_eq!(stats.per_hour(11)[9], 1);
+    }
+
+    #[test]
+    fn test_prune_drops_old_days() {
+        let mut stats = HistoryStats::default();
+        stats.record("Mail", Outcome::Received, 1, 8);
+        stats.record("Mail", Outcome::Received, 5, 8);
+        stats.prune(3);
+        assert_eq!(stats.per_app(0)[0].1.received, 1);
+
+        // Set back clocks count towards the newest day
+        stats.record("Mail", Outcome::Expired, 4, 8);
+        assert_eq!(stats.per_app(5)[0].1.expired, 1);
+        assert_eq!(AppCounts::default().rate(0), 0.0);
+    }
+}
@@ -0,0 +1,212 @@
+use std::collections::{HashMap, VecDeque};
+use std::time::{Duration, Instant};
+
+use crate::constants::{
+    STORM_ENTROPY_BITS, STORM_ENTROPY_MIN_CHARS, STORM_HARD_BURST, STORM_INTERVAL_JITTER, STORM_MIN_BURST,
+    STORM_WINDOW_SECS,
+};
+
+/// What to do with a notification, as far as storms go
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StormVerdict {
+    /// Nothing unusual, show it
+    Calm,
+    /// This notification tipped its app into a storm; it is quieted from now on
+    Started,
+    /// Its app is quieted, it waits for the digest due when that ends
+    Quieted,
+}
+
+/// Recent notifications of one app
+#[derive(Debug, Default)]
+struct Activity {
+    /// When each arrived, and whether its body looked random
+    arrivals: VecDeque<(Instant, bool)>,
+    /// While set, the app is quieted until then
+    quiet_until: Option<Instant>,
+    /// Notifications quieted meanwhile
+    quieted: u32,
+}
+
+/// Spots apps sending notification storms and quiets them for a while
+///
+/// A storm is a burst of notifications that either arrive like clockwork,
+/// as a stuck loop or script sends them, or carry random-looking bodies
+/// such as hashes and ids. A much larger burst is a storm either way.
+#[derive(Debug, Default)]
+pub struct StormDetector {
+    apps: HashMap<String, Activity>,
+}
+
+impl StormDetector {
+    /// A notification from `app` with `body` arrived at `now`; storms quiet
+    /// the app for `cooldown`
+    pub fn arrived(&mut self, app: &str, body: &str, now: Instant, cooldown: Duration) -> StormVerdict {
+        let window = Duration::from_secs(STORM_WINDOW_SECS);
+        // Apps that went quiet are forgotten, so the map stays small
+        self.apps.retain(|_, activity| {
+            activity.quiet_until.is_some()
+                || activity
+                    .arrivals
+                    .back()
+                    .is_some_and(|(at, _)| now.saturating_duration_since(*at) < window)
+        });
+
+        let activity = self.apps.entry(app.to_string()).or_default();
+        if activity.quiet_until.is_some() {
+            activity.quieted += 1;
+            return StormVerdict::Quieted;
+        }
+
+        while activity
+            .arrivals
+            .front()
+            .is_some_and(|(at, _)| now.saturating_duration_since(*at) >= window)
+        {
+            activity.arrivals.pop_front();
+        }
+        activity.arrivals.push_back((now, looks_random(body)));
+
+        if is_storm(&activity.arrivals) {
+            activity.arrivals.clear();
+            activity.quiet_until = Some(now + cooldown);
+            StormVerdict::Started
+        } else {
+            StormVerdict::Calm
+        }
+    }
+
+    /// Apps whose quiet time is over at `now`, with how many notifications
+    /// each had quieted meanwhile
+    pub fn take_ended(&mut self, now: Instant) -> Vec<(String, u32)> {
+        let mut ended = Vec::new();
+        for (app, activity) in &mut self.apps {
+            if activity.quiet_until.is_some_and(|until| until <= now) {
+                activity.quiet_until = None;
+                ended.push((app.clone(), std::mem::take(&mut activity.quieted)));
+            }
+        }
+        ended.sort();
+        ended
+    }
+
+    /// When the quiet time of `app` ends, if it is quieted
+    pub fn quiet_until(&self, app: &str) -> Option<Instant> {
+        self.apps.get(app).and_then(|activity| activity.quiet_until)
+    }
+
+    /// When the next quiet time ends
+    pub fn next_end(&self) -> Option<Instant> {
+        self.apps.values().filter_map(|activity| activity.quiet_until).min()
+    }
+
+    pub fn is_quieted(&self, app: &str) -> bool {
+        self.apps.get(app).is_some_and(|activity| activity.quiet_until.is_some())
+    }
+}
+
+/// Whether recent arrivals of one app make a storm
+fn is_storm(arrivals: &VecDeque<(Instant, bool)>) -> bool {
+    if arrivals.len() >= STORM_HARD_BURST {
+        return true;
+    }
+    if arrivals.len() < STORM_MIN_BURST {
+        return false;
+    }
+    let random = arrivals.iter().filter(|(_, random)| *random).count();
+    random * 2 > arrivals.len() || is_regular(arrivals)
+}
+
+/// Whether notifications arrive at a steady pace, within
+/// `STORM_INTERVAL_JITTER` of the average gap
+fn is_regular(arrivals: &VecDeque<(Instant, bool)>) -> bool {
+    let gaps: Vec<f32> = arrivals
+        .iter()
+        .zip(arrivals.iter().skip(1))
+        .map(|((a, _), (b, _))| b.saturating_duration_since(*a).as_secs_f32())
+        .collect();
+    if gaps.is_empty() {
+        return false;
+    }
+    let mean = gaps.iter().sum::<f32>() / gaps.len() as f32;
+    // Gaps too short to time apart are as regular as it gets
+    let tolerance = (mean * STORM_INTERVAL_JITTER).max(0.05);
+    gaps.iter().all(|gap| (gap - mean).abs() <= tolerance)
+}
+
+/// Whether a body reads like random data (tokens, hashes, ids) rather
+/// than words, by the Shannon entropy of its characters
+fn looks_random(body: &str) -> bool {
+    let len = body.chars().count();
+    if len < STORM_ENTROPY_MIN_CHARS {
+        return false;
+    }
+    let mut counts: HashMap<char, usize> = HashMap::new();
+    for c in body.chars() {
+        *counts.entry(c).or_default() += 1;
+    }
+    let entropy: f32 = counts
+        .values()
+        .map(|&count| {
+            let p = count as f32 / len as f32;
+            -p * p.log2()
+        })
+        .sum();
+    entropy >= STORM_ENTROPY_BITS
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const COOLDOWN: Duration = Duration::from_secs(600);
+
+    #[test]
+    fn test_random_bodies() {
+        assert!(looks_random("Zk3q9XvB2mT7pLw8Rf4Hs1Nd6Yc0Ge5Ju"));
+        assert!(!looks_random("Your build finished successfully"));
+        assert!(!looks_random("short"));
+    }
+
+    #[test]
+    fn test_clockwork_burst_is_a_storm() {
+        let mut storms = StormDetector::default();
+        let start = Instant::now();
+        let at = |i: u64| start + Duration::from_secs(2 * i);
+        for i in 0..STORM_MIN_BURST as u64 - 1 {
+            assert_eq!(storms.arrived("cron", "Job ran", at(i), COOLDOWN), StormVerdict::Calm);
+        }
+        let last = at(STORM_MIN_BURST as u64 - 1);
+        assert_eq!(storms.arrived("cron", "Job ran", last, COOLDOWN), StormVerdict::Started);
+        assert!(storms.is_quieted("cron"));
+        assert_eq!(storms.arrived("cron", "Job ran", last, COOLDOWN), StormVerdict::Quieted);
+        assert_eq!(storms.arrived("mail", "New message", last, COOLDOWN), StormVerdict::Calm);
+
+        assert_eq!(storms.next_end(), Some(last + COOLDOWN));
+        assert!(storms.take_ended(last).is_empty());
+        assert_eq!(storms.take_ended(last + COOLDOWN), vec![("cron".to_string(), 1)]);
+        assert!(!storms.is_quieted("cron"));
+    }
+
+    #[test]
+    fn test_irregular_chat_is_calm() {
+        let mut storms = StormDetector::default();
+        let start = Instant::now();
+        let gaps = [0, 1, 5, 6, 14, 15, 22, 29];
+        for secs in gaps {
+            let verdict = storms.arrived("chat", "See you at lunch", start + Duration::from_secs(secs), COOLDOWN);
+            assert_eq!(verdict, StormVerdict::Calm);
+        }
+    }
+
+    #[test]
+    fn test_old_arrivals_drop_out() {
+        let mut storms = StormDetector::default();
+        let start = Instant::now();
+        let window = Duration::from_secs(STORM_WINDOW_SECS);
+        for i in 0..STORM_MIN_BURST as u32 - 1 {
+            storms.arrived("cron", "Job ran", start + window * i, COOLDOWN);
+        }
+        assert!(!storms.is_quieted("cron"));
+    }
+}
@@ -0,0 +1,144 @@
+use std::time::{Duration, Instant};
+
+/// How a popup being swiped moves
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Phase {
+    /// Following the pointer, which went down at `start_x`
+    Dragging { start_x: f32 },
+    /// Let go past the threshold, sliding off from `from`
+    Leaving { from: f32, started: Instant },
+    /// Let go short of the threshold; nothing left to do
+    Released,
+}
+
+/// Horizontal drag of a popup, dismissing it once dragged far enough
+///
+/// Pointer positions are in surface coordinates; only the horizontal
+/// distance from where the pointer went down matters.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Swipe {
+    pub id: u32,
+    phase: Phase,
+    offset: f32,
+}
+
+impl Swipe {
+    /// Pointer went down on popup `id` at `x`
+    pub fn start(id: u32, x: f32) -> Self {
+        Self {
+            id,
+            phase: Phase::Dragging { start_x: x },
+            offset: 0.0,
+        }
+    }
+
+    /// Pointer moved to `x`
+    pub fn moved(&mut self, x: f32) {
+        if let Phase::Dragging { start_x } = self.phase {
+            self.offset = x - start_x;
+        }
+    }
+
+    /// Whether the pointer moved far enough for this to count as a drag
+    /// rather than a click
+    pub fn is_drag(&self, slop: f32) -> bool {
+        self.offset.abs() > slop
+    }
+
+    /// Pointer went up at `now`; returns whether the popup is leaving
+    ///
+    /// Short of `threshold` the popup snaps back.
+    pub fn release(&mut self, threshold: f32, now: Instant) -> bool {
+        if !matches!(self.phase, Phase::Dragging { .. }) {
+            return false;
+        }
+        if self.offset.abs() >= threshold {
+            self.phase = Phase::Leaving {
+                from: self.offset,
+                started: now,
+            };
+            true
+        } else {
+            self.phase = Phase::Released;
+            self.offset = 0.0;
+            false
+        }
+    }
+
+    /// Horizontal shift of the popup at `now`
+    ///
+    /// A leaving popup slides the rest of the way to `distance` in the
+    /// direction it was swiped, easing out over `duration`.
+    pub fn offset(&self, now: Instant, distance: f32, duration: Duration) -> f32 {
+        match self.phase {
+            Phase::Leaving { from, started } => {
+                let t = progress(now, started, duration);
+                let eased = 1.0 - (1.0 - t).powi(3);
+                let to = distance.copysign(from);
+                from + (to - from) * eased
+            }
+            _ => self.offset,
+        }
+    }
+
+    /// Whether the popup is done sliding off and can be closed
+    pub fn has_left(&self, now: Instant, duration: Duration) -> bool {
+        matches!(self.phase, Phase::Leaving { started, .. } if progress(now, started, duration) >= 1.0)
+    }
+
+    pub fn is_leaving(&self) -> bool {
+        matches!(self.phase, Phase::Leaving { .. })
+    }
+
+    pub fn is_dragging(&self) -> bool {
+        matches!(self.phase, Phase::Dragging { .. })
+    }
+}
+
+fn progress(now: Instant, started: Instant, duration: Duration) -> f32 {
+    if duration.is_zero() {
+        return 1.0;
+    }
+    (now.saturating_duration_since(started).as_secs_f32() / duration.as_secs_f32()).min(1.0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SLIDE: Duration = Duration::from_millis(100);
+
+    #[test]
+    fn test_short_drag_snaps_back() {
+        let mut swipe = Swipe::start(1, 50.0);
+        swipe.moved(54.0);
+        assert!(!swipe.is_drag(8.0));
+        swipe.moved(90.0);
+        assert!(swipe.is_drag(8.0));
+
+        let now = Instant::now();
+        assert!(!swipe.release(120.0, now));
+        assert_eq!(swipe.offset(now, 380.0, SLIDE), 0.0);
+        assert!(!swipe.is_dragging());
+        assert!(!swipe.has_left(now + SLIDE, SLIDE));
+    }
+
+    #[test]
+    fn test_long_drag_slides_off() {
+        let mut swipe = Swipe::start(1, 200.0);
+        swipe.moved(50.0);
+        let now = Instant::now();
+        assert!(swipe.release(120.0, now));
+        assert!(swipe.is_leaving());
+
+        // Keeps going left, to the full distance
+        assert_eq!(swipe.offset(now, 380.0, SLIDE), -150.0);
+        assert_eq!(swipe.offset(now + SLIDE, 380.0, SLIDE), -380.0);
+        assert!(!swipe.has_left(now, SLIDE));
+        assert!(swipe.has_left(now + SLIDE, SLIDE));
+
+        // Moves after letting go don't count
+        swipe.moved(400.0);
+        assert_eq!(swipe.offset(now + SLIDE, 380.0, SLIDE), -380.0);
+    }
+}
@@ -0,0 +1,140 @@
+use std::collections::HashMap;
+use std::time::{Duration, Instant};
+
+/// Why a popup is leaving, and so what happens to it once it's gone
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Exit {
+    /// Timed out; kept in history if the app's rules say so
+    Expired,
+    /// Closed by the user
+    Dismissed,
+}
+
+/// Popups easing onto or off the screen
+///
+/// A popup that's leaving stays in place until its exit has played out,
+/// then the caller finishes the close with [`Transitions::finished`].
+#[derive(Debug, Default)]
+pub struct Transitions {
+    entering: HashMap<u32, Instant>,
+    leaving: HashMap<u32, (Instant, Exit)>,
+}
+
+impl Transitions {
+    /// Popup `id` appeared at `now`
+    pub fn enter(&mut self, id: u32, now: Instant) {
+        self.leaving.remove(&id);
+        self.entering.insert(id, now);
+    }
+
+    /// Popup `id` starts leaving at `now`; returns false if it already was
+    pub fn leave(&mut self, id: u32, exit: Exit, now: Instant) -> bool {
+        if self.leaving.contains_key(&id) {
+            return false;
+        }
+        self.entering.remove(&id);
+        self.leaving.insert(id, (now, exit));
+        true
+    }
+
+    pub fn is_leaving(&self, id: u32) -> bool {
+        self.leaving.contains_key(&id)
+    }
+
+    /// How far popup `id` is on screen at `now`, from 0.0 (gone) to 1.0
+    ///
+    /// Entering eases out, leaving eases in, so both are quick near the
+    /// screen edge and gentle where the popup rests.
+    pub fn shown(&self, id: u32, now: Instant, duration: Duration) -> f32 {
+        if let Some((started, _)) = self.leaving.get(&id) {
+            let t = progress(now, *started, duration);
+            return 1.0 - t.powi(3);
+        }
+        if let Some(started) = self.entering.get(&id) {
+            let t = progress(now, *started, duration);
+            return 1.0 - (1.0 - t).powi(3);
+        }
+        1.0
+    }
+
+    /// Drop transitions that have played out by `now`, returning the popups
+    /// that finished leaving
+    pub fn finished(&mut self, now: Instant, duration: Duration) -> Vec<(u32, Exit)> {
+        self.entering
+            .retain(|_, started| progress(now, *started, duration) < 1.0);
+        let mut gone = Vec::new();
+        self.leaving.retain(|id, (started, exit)| {
+            let done = progress(now, *started, duration) >= 1.0;
+            if done {
+                gone.push((*id, *exit));
+            }
+            !done
+        });
+        gone
+    }
+
+    pub fn forget(&mut self, id: u32) {
+        self.entering.remove(&id);
+        self.leaving.remove(&id);
+    }
+
+    pub fn is_animating(&self) -> bool {
+        !self.entering.is_empty() || !self.leaving.is_empty()
+    }
+}
+
+fn progress(now: Instant, started: Instant, duration: Duration) -> f32 {
+    if duration.is_zero() {
+        return 1.0;
+    }
+    (now.saturating_duration_since(started).as_secs_f32() / duration.as_secs_f32()).min(1.0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const DURATION: Duration = Duration::from_millis(200);
+
+    #[test]
+    fn test_enter_then_rest() {
+        let mut transitions = Transitions::default();
+        let now = Instant::now();
+        transitions.enter(1, now);
+
+        assert_eq!(transitions.shown(1, now, DURATION), 0.0);
+        let halfway = transitions.shown(1, now + DURATION / 2, DURATION);
+        assert!(halfway > 0.5 && halfway < 1.0);
+        assert_eq!(transitions.shown(1, now + DURATION, DURATION), 1.0);
+        assert_eq!(transitions.shown(2, now, DURATION), 1.0);
+
+        assert!(transitions.finished(now + DURATION, DURATION).is_empty());
+        assert!(!transitions.is_animating());
+    }
+
+    #[test]
+    fn test_leave_finishes_once() {
+        let mut transitions = Transitions::default();
+        let now = Instant::now();
+        transitions.enter(1, now);
+        assert!(transitions.leave(1, Exit::Expired, now));
+        // A second close while leaving doesn't restart the exit
+        assert!(!transitions.leave(1, Exit::Dismissed, now + DURATION / 2));
+        assert!(transitions.is_leaving(1));
+
+        assert_eq!(transitions.shown(1, now, DURATION), 1.0);
+        assert!(transitions.finished(now + DURATION / 2, DURATION).is_empty());
+        assert_eq!(transitions.finished(now + DURATION, DURATION), vec![(1, Exit::Expired)]);
+        assert!(!transitions.is_leaving(1));
+        assert!(transitions.finished(now + DURATION * 2, DURATION).is_empty());
+    }
+
+    #[test]
+    fn test_zero_duration_is_instant() {
+        let mut transitions = Transitions::default();
+        let now = Instant::now();
+        transitions.leave(1, Exit::Dismissed, now);
+        assert_eq!(transitions.shown(1, now, Duration::ZERO), 0.0);
+        assert_eq!(transitions.finished(now, Duration::ZERO), vec![(1, Exit::Dismissed)]);
+    }
+}
@@ -0,0 +1,62 @@
+use std::collections::HashMap;
+
+/// Something the app wakes up for at a time it keeps moving
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Wakeup {
+    /// A snoozed notification is due
+    Snooze,
+    /// A conversation mute expires
+    ConversationMutes,
+    /// An app quieted for a notification storm may be let back
+    Storms,
+    /// An app's digest is due
+    Digests,
+}
+
+/// Timers of which only the latest one per [`Wakeup`] counts
+///
+/// Timers can't be cancelled once started, so each one carries the
+/// generation it was armed with and is ignored when it fires after a newer
+/// one was armed. Rescheduling on every snooze or wakeup then keeps a
+/// single timer going instead of one more each time.
+#[derive(Debug, Default)]
+pub struct Wakeups {
+    generations: HashMap<Wakeup, u64>,
+}
+
+impl Wakeups {
+    /// Arm a new timer for `wakeup`, superseding the one before; answers
+    /// its generation
+    pub fn arm(&mut self, wakeup: Wakeup) -> u64 {
+        let generation = self.generations.entry(wakeup).or_default();
+        *generation += 1;
+        *generation
+    }
+
+    /// Whether the timer armed as `generation` is still the latest for
+    /// `wakeup`
+    pub fn is_current(&self, wakeup: Wakeup, generation: u64) -> bool {
+        self.generations.get(&wakeup).copied().unwrap_or_default() == generation
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_only_latest_timer_counts() {
+        let mut wakeups = Wakeups::default();
+        assert!(wakeups.is_current(Wakeup::Snooze, 0));
+
+        let first = wakeups.arm(Wakeup::Snooze);
+        let second = wakeups.arm(Wakeup::Snooze);
+        assert!(!wakeups.is_current(Wakeup::Snooze, first));
+        assert!(wakeups.is_current(Wakeup::Snooze, second));
+
+        // Other wakeups keep their own timers
+        let digest = wakeups.arm(Wakeup::Digests);
+        assert!(wakeups.is_current(Wakeup::Digests, digest));
+        assert!(wakeups.is_current(Wakeup::Snooze, second));
+    }
+}
@@ -10,9 +10,10 @@ use zbus::{
 };
 
 use super::notifications::Input;
+use crate::constants::FOCUS_SESSION_MAX_DURATION;
 
 use anyhow::{Result, bail};
-use cosmic_ext_notifications_util::DAEMON_NOTIFICATIONS_FD;
+use cosmic_ext_notifications_util::{DAEMON_NOTIFICATIONS_FD, NotificationRecord};
 use std::os::unix::io::FromRawFd;
 
 use serde_json;
@@ -144,6 +145,16 @@ impl NotificationsApplet {
         expire_timeout: i32,
     ) -> zbus::Result<()>;
 
+    /// Emitted when a focus session starts, stops or ends
+    ///
+    /// `remaining_secs` is 0 when no session is active. Applets use this to
+    /// start or clear their countdown.
+    #[zbus(signal)]
+    pub async fn focus_session_changed(
+        signal_ctxt: &SignalEmitter<'_>,
+        remaining_secs: u32,
+    ) -> zbus::Result<()>;
+
     pub async fn invoke_action(&self, id: u32, action: &str) -> zbus::fdo::Result<()> {
         tracing::trace!("Received action from applet {id} {action}");
         let res = self
@@ -241,4 +252,193 @@ impl NotificationsApplet {
 
         result
     }
+
+    /// History as versioned notification records, one JSON object each
+    ///
+    /// Unlike `get_history_full` the format is stable across releases, see
+    /// `NotificationRecord`.
+    pub async fn get_history_records(&self) -> zbus::fdo::Result<Vec<String>> {
+        tracing::trace!("Received get_history_records request from applet");
+
+        let (tx, rx) = tokio::sync::oneshot::channel();
+        if let Err(err) = self.tx.send(Input::GetHistory { tx }).await {
+            tracing::error!("Failed to send get_history_records message to channel");
+            return Err(zbus::fdo::Error::Failed(err.to_string()));
+        }
+
+        let notifications = match tokio::time::timeout(
+            tokio::time::Duration::from_secs(2),
+            rx
+        ).await {
+            Ok(Ok(notifs)) => notifs,
+            Ok(Err(err)) => {
+                tracing::error!("Failed to receive history records: {}", err);
+                return Err(zbus::fdo::Error::Failed("Channel closed".to_string()));
+            }
+            Err(_) => {
+                tracing::error!("Timeout waiting for history records");
+                return Err(zbus::fdo::Error::Failed("Timeout".to_string()));
+            }
+        };
+
+        Ok(notifications
+            .iter()
+            .map(|n| NotificationRecord::from_notification(n).to_json())
+            .collect())
+    }
+
+    /// Start a focus session lasting `duration_secs`
+    ///
+    /// Popups are suppressed as with do-not-disturb, except for apps in
+    /// `allowed_apps` (matched by app name or desktop entry) and critical
+    /// notifications. Starting a session replaces any running one.
+    pub async fn start_focus_session(
+        &self,
+        duration_secs: u32,
+        allowed_apps: Vec<String>,
+    ) -> zbus::fdo::Result<()> {
+        tracing::trace!("Received start_focus_session request for {duration_secs}s");
+
+        if duration_secs == 0 || duration_secs > FOCUS_SESSION_MAX_DURATION {
+            return Err(zbus::fdo::Error::InvalidArgs(format!(
+                "Duration must be between 1 and {FOCUS_SESSION_MAX_DURATION} seconds"
+            )));
+        }
+
+        let res = self
+            .tx
+            .send(Input::StartFocusSession {
+                duration: std::time::Duration::from_secs(duration_secs.into()),
+                allowed_apps,
+            })
+            .await;
+        if let Err(err) = res {
+            tracing::error!("Failed to send start_focus_session message to channel");
+            return Err(zbus::fdo::Error::Failed(err.to_string()));
+        }
+        Ok(())
+    }
+
+    /// Stop the running focus session early, if any
+    pub async fn stop_focus_session(&self) -> zbus::fdo::Result<()> {
+        tracing::trace!("Received stop_focus_session request");
+
+        if let Err(err) = self.tx.send(Input::StopFocusSession).await {
+            tracing::error!("Failed to send stop_focus_session message to channel");
+            return Err(zbus::fdo::Error::Failed(err.to_string()));
+        }
+        Ok(())
+    }
+
+    /// Seconds remaining in the current focus session, or 0 if none is active
+    pub async fn focus_session_remaining(&self) -> zbus::fdo::Result<u32> {
+        let (tx, rx) = tokio::sync::oneshot::channel();
+
+        if let Err(err) = self.tx.send(Input::GetFocusSession { tx }).await {
+            tracing::error!("Failed to send focus_session_remaining message to channel");
+            return Err(zbus::fdo::Error::Failed(err.to_string()));
+        }
+
+        match tokio::time::timeout(tokio::time::Duration::from_secs(2), rx).await {
+            Ok(Ok(remaining)) => Ok(remaining),
+            Ok(Err(err)) => {
+                tracing::error!("Failed to receive focus session state: {}", err);
+                Err(zbus::fdo::Error::Failed("Channel closed".to_string()))
+            }
+            Err(_) => {
+                tracing::error!("Timeout waiting for focus session state");
+                Err(zbus::fdo::Error::Failed("Timeout".to_string()))
+            }
+        }
+    }
+
+    /// Daemon state as JSON, including the detected compositor capabilities
+    pub async fn debug_dump(&self) -> zbus::fdo::Result<String> {
+        let (tx, rx) = tokio::sync::oneshot::channel();
+
+        if let Err(err) = self.tx.send(Input::DebugDump { tx }).await {
+            tracing::error!("Failed to send debug_dump message to channel");
+            return Err(zbus::fdo::Error::Failed(err.to_string()));
+        }
+
+        match tokio::time::timeout(tokio::time::Duration::from_secs(2), rx).await {
+            Ok(Ok(dump)) => Ok(dump),
+            Ok(Err(err)) => {
+                tracing::error!("Failed to receive debug dump: {}", err);
+                Err(zbus::fdo::Error::Failed("Channel closed".to_string()))
+            }
+            Err(_) => {
+                tracing::error!("Timeout waiting for debug dump");
+                Err(zbus::fdo::Error::Failed("Timeout".to_string()))
+            }
+        }
+    }
+
+    /// History grouped under date sections, newest first
+    ///
+    /// Returns (key, label, collapsed, notification ids) per section, where
+    /// key is "today", "yesterday" or an ISO date and label is localized.
+    /// Use the ids to look up entries from `get_history`.
+    pub async fn get_history_sections(
+        &self,
+    ) -> zbus::fdo::Result<Vec<(String, String, bool, Vec<u32>)>> {
+        let (tx, rx) = tokio::sync::oneshot::channel();
+
+        if let Err(err) = self.tx.send(Input::GetHistorySections { tx }).await {
+            tracing::error!("Failed to send get_history_sections message to channel");
+            return Err(zbus::fdo::Error::Failed(err.to_string()));
+        }
+
+        match tokio::time::timeout(tokio::time::Duration::from_secs(2), rx).await {
+            Ok(Ok(sections)) => Ok(sections),
+            Ok(Err(err)) => {
+                tracing::error!("Failed to receive history sections: {}", err);
+                Err(zbus::fdo::Error::Failed("Channel closed".to_string()))
+            }
+            Err(_) => {
+                tracing::error!("Timeout waiting for history sections");
+                Err(zbus::fdo::Error::Failed("Timeout".to_string()))
+            }
+        }
+    }
+
+    /// Collapse or expand a history section by key
+    pub async fn set_history_section_collapsed(
+        &self,
+        key: String,
+        collapsed: bool,
+    ) -> zbus::fdo::Result<()> {
+        if crate::state::DateSection::from_key(&key).is_none() {
+            return Err(zbus::fdo::Error::InvalidArgs(format!("Unknown section '{key}'")));
+        }
+
+        if let Err(err) = self
+            .tx
+            .send(Input::SetHistorySectionCollapsed { key, collapsed })
+            .await
+        {
+            tracing::error!("Failed to send set_history_section_collapsed message to channel");
+            return Err(zbus::fdo::Error::Failed(err.to_string()));
+        }
+        Ok(())
+    }
+
+    /// Set the master notification sound volume in percent (0-100)
+    ///
+    /// The new level is saved to the config so it survives restarts.
+    pub async fn set_sound_volume(&self, volume: u8) -> zbus::fdo::Result<()> {
+        tracing::trace!("Received set_sound_volume request: {volume}");
+
+        if volume > 100 {
+            return Err(zbus::fdo::Error::InvalidArgs(
+                "Volume must be between 0 and 100".to_string(),
+            ));
+        }
+
+        if let Err(err) = self.tx.send(Input::SetSoundVolume(volume)).await {
+            tracing::error!("Failed to send set_sound_volume message to channel");
+            return Err(zbus::fdo::Error::Failed(err.to_string()));
+        }
+        Ok(())
+    }
 }
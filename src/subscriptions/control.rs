@@ -0,0 +1,676 @@
+//! `com.system76.CosmicNotifications` control interface
+//!
+//! Lets scripts and panel applets query and change daemon state over the
+//! session bus instead of editing cosmic-config behind the daemon's back.
+//! Property values are pushed by the app whenever they change, so reads
+//! never wait on the UI thread. Every accepted notification is also
+//! broadcast as `NotificationPosted`, letting status bars and loggers follow
+//! along without owning the notification bus name.
+
+use super::notifications::Input;
+use crate::constants::{DND_MAX_DURATION, SNOOZE_MAX_DURATION};
+use crate::state::stats::AppCounts;
+use cosmic_ext_notifications_config::DndMode;
+use cosmic_ext_notifications_util::{ActionId, Notification, sanitize_html};
+use std::{
+    collections::HashMap,
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+use tokio::sync::mpsc::Sender;
+use zbus::{
+    Connection, interface,
+    object_server::SignalEmitter,
+    zvariant::{OwnedValue, Value},
+};
+
+/// Well-known name requested for the control interface
+pub const CONTROL_BUS_NAME: &str = "com.system76.CosmicNotifications";
+pub const CONTROL_PATH: &str = "/com/system76/CosmicNotifications";
+
+/// Daemon state exposed as properties
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct ControlState {
+    pub do_not_disturb: bool,
+    /// Critical notifications break through priority-only DND
+    pub critical_bypass_dnd: bool,
+    /// Number of notification popups on screen
+    pub visible_count: u32,
+    /// Popups are held back until delivery resumes
+    pub paused: bool,
+    /// Notifications waiting for delivery to resume
+    pub held_count: u32,
+}
+
+pub struct NotificationsControl {
+    tx: Sender<Input>,
+    state: ControlState,
+}
+
+impl NotificationsControl {
+    pub fn new(tx: Sender<Input>) -> Self {
+        Self {
+            tx,
+            state: ControlState::default(),
+        }
+    }
+
+    /// Send a request to the app and wait for its answer
+    async fn request<T>(
+        &self,
+        what: &str,
+        input: impl FnOnce(tokio::sync::oneshot::Sender<T>) -> Input,
+    ) -> zbus::fdo::Result<T> {
+        let (tx, rx) = tokio::sync::oneshot::channel();
+        if let Err(err) = self.tx.send(input(tx)).await {
+            tracing::error!("Failed to send {what} request to channel");
+            return Err(zbus::fdo::Error::Failed(err.to_string()));
+        }
+
+        match tokio::time::timeout(tokio::time::Duration::from_secs(2), rx).await {
+            Ok(Ok(response)) => Ok(response),
+            Ok(Err(err)) => {
+                tracing::error!("Failed to receive {what}: {}", err);
+                Err(zbus::fdo::Error::Failed("Channel closed".to_string()))
+            }
+            Err(_) => {
+                tracing::error!("Timeout waiting for {what}");
+                Err(zbus::fdo::Error::Failed("Timeout".to_string()))
+            }
+        }
+    }
+
+    /// Fetch the notification history from the app, newest first
+    async fn history(&self) -> zbus::fdo::Result<Vec<Notification>> {
+        self.request("history", |tx| Input::GetHistory { tx }).await
+    }
+}
+
+/// Which history entries `ListHistory` returns
+#[derive(Debug, Default, PartialEq, Eq)]
+struct HistoryFilter {
+    /// Matches the app name or desktop entry, ignoring case
+    app: Option<String>,
+    /// Substring of the summary or body, ignoring case
+    text: Option<String>,
+    /// Only entries received at or after this time
+    since: Option<SystemTime>,
+    /// Only entries at least this urgent
+    min_urgency: Option<u8>,
+}
+
+impl HistoryFilter {
+    /// Parse the `a{sv}` filter argument; unknown keys are rejected so typos
+    /// don't silently return everything
+    fn from_dict(filter: &HashMap<String, OwnedValue>) -> Result<Self, String> {
+        let mut parsed = Self::default();
+        for (key, value) in filter {
+            match key.as_str() {
+                "app" => parsed.app = Some(string_arg(key, value)?.to_lowercase()),
+                "text" => parsed.text = Some(string_arg(key, value)?.to_lowercase()),
+                "since" => {
+                    let secs = i64::try_from(&**value)
+                        .map_err(|_| format!("'{key}' must be an int64 of Unix seconds"))?;
+                    parsed.since =
+                        Some(UNIX_EPOCH + Duration::from_secs(u64::try_from(secs).unwrap_or(0)));
+                }
+                "urgency" => {
+                    parsed.min_urgency = Some(
+                        u8::try_from(&**value).map_err(|_| format!("'{key}' must be a byte"))?,
+                    );
+                }
+                _ => return Err(format!("Unknown filter '{key}'")),
+            }
+        }
+        Ok(parsed)
+    }
+
+    fn matches(&self, n: &Notification) -> bool {
+        let contains = |haystack: &str, needle: &str| haystack.to_lowercase().contains(needle);
+
+        self.app.as_deref().is_none_or(|app| n.is_from_app(app))
+            && self
+            .text
+            .as_deref()
+            .is_none_or(|text| contains(&n.summary, text) || contains(&n.body, text))
+            && self.since.is_none_or(|since| n.time >= since)
+            && self.min_urgency.is_none_or(|urgency| n.urgency() >= urgency)
+    }
+}
+
+fn string_arg<'a>(key: &str, value: &'a OwnedValue) -> Result<&'a str, String> {
+    <&str>::try_from(&**value).map_err(|_| format!("'{key}' must be a string"))
+}
+
+/// A notification as the `a{sv}` sent over D-Bus, with its body sanitized
+fn notification_dict(n: &Notification) -> HashMap<&'static str, Value<'static>> {
+    let time = n
+        .time
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |d| i64::try_from(d.as_secs()).unwrap_or(i64::MAX));
+
+    let mut entry = HashMap::from([
+        ("id", Value::from(n.id)),
+        ("app-name", Value::from(n.app_name.clone())),
+        ("app-icon", Value::from(n.app_icon.clone())),
+        ("summary", Value::from(n.summary.clone())),
+        ("body", Value::from(sanitize_html(&n.body))),
+        ("urgency", Value::from(n.urgency())),
+        ("time", Value::from(time)),
+        (
+            "actions",
+            Value::from(
+                n.actions
+                    .iter()
+                    .map(|(id, label)| (id.to_string(), label.clone()))
+                    .collect::<Vec<_>>(),
+            ),
+        ),
+    ]);
+    if let Some(entry_name) = n.desktop_entry() {
+        entry.insert("desktop-entry", Value::from(entry_name.to_string()));
+    }
+    if let Some(category) = n.category() {
+        entry.insert("category", Value::from(category.to_string()));
+    }
+    entry
+}
+
+/// One app's line of `HistoryStats`
+fn stats_dict(app: &str, counts: &AppCounts) -> HashMap<&'static str, Value<'static>> {
+    HashMap::from([
+        ("app", Value::from(app.to_string())),
+        ("received", Value::from(counts.received)),
+        ("dismissed", Value::from(counts.dismissed)),
+        ("actioned", Value::from(counts.actioned)),
+        ("expired", Value::from(counts.expired)),
+        ("dismiss-rate", Value::from(counts.rate(counts.dismissed))),
+        ("action-rate", Value::from(counts.rate(counts.actioned))),
+    ])
+}
+
+/// Update the published state and emit `PropertiesChanged` for what changed
+pub async fn publish(conn: &Connection, state: ControlState) {
+    let Ok(iface_ref) = conn
+        .object_server()
+        .interface::<_, NotificationsControl>(CONTROL_PATH)
+        .await
+    else {
+        return;
+    };
+    let mut iface = iface_ref.get_mut().await;
+    let emitter = iface_ref.signal_emitter();
+
+    let previous = std::mem::replace(&mut iface.state, state);
+
+    if previous.do_not_disturb != state.do_not_disturb {
+        if let Err(err) = iface.do_not_disturb_changed(emitter).await {
+            tracing::error!("Failed to signal DoNotDisturb change: {}", err);
+        }
+    }
+    if previous.critical_bypass_dnd != state.critical_bypass_dnd {
+        if let Err(err) = iface.critical_bypasses_dnd_changed(emitter).await {
+            tracing::error!("Failed to signal CriticalBypassesDnd change: {}", err);
+        }
+    }
+    if previous.visible_count != state.visible_count {
+        if let Err(err) = iface.visible_count_changed(emitter).await {
+            tracing::error!("Failed to signal VisibleCount change: {}", err);
+        }
+    }
+    if previous.paused != state.paused {
+        if let Err(err) = iface.paused_changed(emitter).await {
+            tracing::error!("Failed to signal Paused change: {}", err);
+        }
+    }
+    if previous.held_count != state.held_count {
+        if let Err(err) = iface.held_count_changed(emitter).await {
+            tracing::error!("Failed to signal HeldCount change: {}", err);
+        }
+    }
+}
+
+/// Broadcast a notification the daemon has just accepted
+pub async fn posted(conn: &Connection, notification: &Notification, replaced: bool) {
+    let Ok(iface_ref) = conn
+        .object_server()
+        .interface::<_, NotificationsControl>(CONTROL_PATH)
+        .await
+    else {
+        return;
+    };
+    if let Err(err) = NotificationsControl::notification_posted(
+        iface_ref.signal_emitter(),
+        notification_dict(notification),
+        replaced,
+    )
+    .await
+    {
+        tracing::error!("Failed to signal posted notification: {}", err);
+    }
+}
+
+#[interface(name = "com.system76.CosmicNotifications")]
+impl NotificationsControl {
+    /// Whether do-not-disturb is on
+    #[zbus(property)]
+    async fn do_not_disturb(&self) -> bool {
+        self.state.do_not_disturb
+    }
+
+    /// Whether critical notifications break through priority-only DND
+    ///
+    /// Writable, and saved to the config like a change in settings.
+    #[zbus(property)]
+    async fn critical_bypasses_dnd(&self) -> bool {
+        self.state.critical_bypass_dnd
+    }
+
+    #[zbus(property)]
+    async fn set_critical_bypasses_dnd(&mut self, enabled: bool) -> zbus::fdo::Result<()> {
+        tracing::trace!("Received critical bypass change: {enabled}");
+
+        if let Err(err) = self.tx.send(Input::SetCriticalBypass(enabled)).await {
+            tracing::error!("Failed to send critical bypass message to channel");
+            return Err(zbus::fdo::Error::Failed(err.to_string()));
+        }
+        // Reported right away; the app publishes the saved value if it differs
+        self.state.critical_bypass_dnd = enabled;
+        Ok(())
+    }
+
+    /// Number of notification popups on screen
+    #[zbus(property)]
+    async fn visible_count(&self) -> u32 {
+        self.state.visible_count
+    }
+
+    /// Whether popups are held back by `Pause`
+    #[zbus(property)]
+    async fn paused(&self) -> bool {
+        self.state.paused
+    }
+
+    /// Number of notifications waiting for `Resume`
+    #[zbus(property)]
+    async fn held_count(&self) -> u32 {
+        self.state.held_count
+    }
+
+    /// A notification was accepted, in the `ListHistory` format
+    ///
+    /// `replaced` is set when it updates one posted earlier with the same id.
+    #[zbus(signal)]
+    async fn notification_posted(
+        emitter: &SignalEmitter<'_>,
+        notification: HashMap<&str, Value<'_>>,
+        replaced: bool,
+    ) -> zbus::Result<()>;
+
+    /// Past notifications, newest first
+    ///
+    /// `filter` may hold "app" (s, app name or desktop entry), "text" (s,
+    /// found in summary or body), "since" (x, Unix seconds) and "urgency"
+    /// (y, minimum). A `limit` of 0 returns every match. Each entry has id,
+    /// app-name, app-icon, summary, body, urgency, time, actions and, when
+    /// known, desktop-entry and category.
+    async fn list_history(
+        &self,
+        filter: HashMap<String, OwnedValue>,
+        limit: u32,
+    ) -> zbus::fdo::Result<Vec<HashMap<&'static str, Value<'static>>>> {
+        let filter = HistoryFilter::from_dict(&filter).map_err(zbus::fdo::Error::InvalidArgs)?;
+        let limit = if limit == 0 { usize::MAX } else { limit as usize };
+
+        Ok(self
+            .history()
+            .await?
+            .iter()
+            .filter(|n| filter.matches(n))
+            .take(limit)
+            .map(notification_dict)
+            .collect())
+    }
+
+    /// History entries matching every word of `query`, newest first, in
+    /// the `ListHistory` format
+    ///
+    /// Words match the start of words in the app name, summary or body,
+    /// ignoring case, so "otp" finds "Your OTP is 482913". Only the masked
+    /// or withheld form listeners get is searched. A `limit` of 0
+    /// returns every match.
+    async fn search_history(
+        &self,
+        query: String,
+        limit: u32,
+    ) -> zbus::fdo::Result<Vec<HashMap<&'static str, Value<'static>>>> {
+        let limit = if limit == 0 { usize::MAX } else { limit as usize };
+
+        Ok(self
+            .request("search results", |tx| Input::SearchHistory { query, tx })
+            .await?
+            .iter()
+            .take(limit)
+            .map(notification_dict)
+            .collect())
+    }
+
+    /// Notification counts over the last `days` days, today included
+    /// (0 = everything kept, up to 90 days)
+    ///
+    /// `apps` has one entry per app, most notifications first, with app,
+    /// received, dismissed, actioned and expired (u) and dismiss-rate and
+    /// action-rate (d, share of received). `hours` has 24 entries counting
+    /// the notifications received in each local hour.
+    #[zbus(out_args("apps", "hours"))]
+    async fn history_stats(
+        &self,
+        days: u32,
+    ) -> zbus::fdo::Result<(Vec<HashMap<&'static str, Value<'static>>>, Vec<u32>)> {
+        let (apps, hours) = self
+            .request("stats", |tx| Input::GetStats { days, tx })
+            .await?;
+        Ok((
+            apps.iter().map(|(app, counts)| stats_dict(app, counts)).collect(),
+            hours.to_vec(),
+        ))
+    }
+
+    /// A single history entry by notification id, in the `ListHistory` format
+    async fn get_notification(
+        &self,
+        id: u32,
+    ) -> zbus::fdo::Result<HashMap<&'static str, Value<'static>>> {
+        self.history()
+            .await?
+            .iter()
+            .find(|n| n.id == id)
+            .map(notification_dict)
+            .ok_or_else(|| {
+                zbus::fdo::Error::InvalidArgs(format!("No notification {id} in history"))
+            })
+    }
+
+    /// Forget every past notification
+    async fn clear_history(&self) -> zbus::fdo::Result<()> {
+        tracing::trace!("Received clear_history request");
+
+        if let Err(err) = self.tx.send(Input::ClearHistory).await {
+            tracing::error!("Failed to send clear_history message to channel");
+            return Err(zbus::fdo::Error::Failed(err.to_string()));
+        }
+        Ok(())
+    }
+
+    /// Run an action of a notification on screen, as if it was clicked
+    ///
+    /// An empty `action_key` runs the default action, or the first one the
+    /// notification offers.
+    async fn invoke_action(&self, id: u32, action_key: &str) -> zbus::fdo::Result<()> {
+        tracing::trace!("Received invoke_action request: {id} {action_key}");
+
+        let action = (!action_key.is_empty()).then(|| ActionId::from(action_key));
+        let invoked = self
+            .request("invoke action", |tx| Input::InvokeAction { id, action, tx })
+            .await?;
+        if !invoked {
+            return Err(zbus::fdo::Error::InvalidArgs(format!(
+                "No visible notification {id} with action '{action_key}'"
+            )));
+        }
+        Ok(())
+    }
+
+    /// Hide a notification popup and show it again after `seconds`
+    ///
+    /// Snoozed notifications are saved, so they still come back after a
+    /// restart.
+    async fn snooze(&self, id: u32, seconds: u32) -> zbus::fdo::Result<()> {
+        tracing::trace!("Received snooze request: {id} for {seconds}s");
+
+        if seconds == 0 || seconds > SNOOZE_MAX_DURATION {
+            return Err(zbus::fdo::Error::InvalidArgs(format!(
+                "Snooze must last between 1 and {SNOOZE_MAX_DURATION} seconds"
+            )));
+        }
+        let duration = Duration::from_secs(seconds.into());
+        let snoozed = self
+            .request("snooze", |tx| Input::Snooze { id, duration, tx })
+            .await?;
+        if !snoozed {
+            return Err(zbus::fdo::Error::InvalidArgs(format!("No visible notification {id}")));
+        }
+        Ok(())
+    }
+
+    /// Open the notification center, or close it if it is open
+    async fn toggle_center(&self) -> zbus::fdo::Result<()> {
+        tracing::trace!("Received toggle_center request");
+
+        if let Err(err) = self.tx.send(Input::ToggleCenter).await {
+            tracing::error!("Failed to send toggle_center message to channel");
+            return Err(zbus::fdo::Error::Failed(err.to_string()));
+        }
+        Ok(())
+    }
+
+    /// Give the popups keyboard focus, starting on the newest
+    ///
+    /// Meant to be bound to a shortcut; arrows and Tab move between popups
+    /// and their buttons, Enter clicks, Delete dismisses and Escape gives
+    /// focus back.
+    async fn focus_popups(&self) -> zbus::fdo::Result<()> {
+        tracing::trace!("Received focus_popups request");
+
+        if let Err(err) = self.tx.send(Input::FocusPopups).await {
+            tracing::error!("Failed to send focus_popups message to channel");
+            return Err(zbus::fdo::Error::Failed(err.to_string()));
+        }
+        Ok(())
+    }
+
+    /// Dismiss every notification popup
+    ///
+    /// Each emits `NotificationClosed` with reason 2, as if the user closed it.
+    async fn close_all(&self) -> zbus::fdo::Result<()> {
+        tracing::trace!("Received close_all request");
+
+        if let Err(err) = self.tx.send(Input::CloseAll).await {
+            tracing::error!("Failed to send close_all message to channel");
+            return Err(zbus::fdo::Error::Failed(err.to_string()));
+        }
+        Ok(())
+    }
+
+    /// Dismiss the popups of one app, named by app name or desktop entry
+    async fn close_app(&self, app_name: &str) -> zbus::fdo::Result<()> {
+        tracing::trace!("Received close_app request: {app_name}");
+
+        if app_name.is_empty() {
+            return Err(zbus::fdo::Error::InvalidArgs(
+                "App name must not be empty".to_string(),
+            ));
+        }
+        if let Err(err) = self.tx.send(Input::CloseApp(app_name.to_string())).await {
+            tracing::error!("Failed to send close_app message to channel");
+            return Err(zbus::fdo::Error::Failed(err.to_string()));
+        }
+        Ok(())
+    }
+
+    /// Hold back new popups until `Resume`
+    ///
+    /// Unlike do-not-disturb nothing goes straight to history; held
+    /// notifications are all shown at once when delivery resumes.
+    async fn pause(&self) -> zbus::fdo::Result<()> {
+        tracing::trace!("Received pause request");
+
+        if let Err(err) = self.tx.send(Input::SetPaused(true)).await {
+            tracing::error!("Failed to send pause message to channel");
+            return Err(zbus::fdo::Error::Failed(err.to_string()));
+        }
+        Ok(())
+    }
+
+    /// Show everything held back since `Pause` and deliver normally again
+    async fn resume(&self) -> zbus::fdo::Result<()> {
+        tracing::trace!("Received resume request");
+
+        if let Err(err) = self.tx.send(Input::SetPaused(false)).await {
+            tracing::error!("Failed to send resume message to channel");
+            return Err(zbus::fdo::Error::Failed(err.to_string()));
+        }
+        Ok(())
+    }
+
+    /// Turn do-not-disturb on if it is off, and off if it is on
+    async fn toggle_dnd(&self) -> zbus::fdo::Result<()> {
+        tracing::trace!("Received toggle_dnd request");
+
+        if let Err(err) = self.tx.send(Input::ToggleDnd).await {
+            tracing::error!("Failed to send toggle_dnd message to channel");
+            return Err(zbus::fdo::Error::Failed(err.to_string()));
+        }
+        Ok(())
+    }
+
+    /// Turn do-not-disturb on or off
+    ///
+    /// When enabling, a non-zero `duration_secs` turns it back off after
+    /// that long; 0 keeps it on until changed again.
+    async fn set_dnd(&self, enabled: bool, duration_secs: u32) -> zbus::fdo::Result<()> {
+        tracing::trace!("Received set_dnd request: {enabled} for {duration_secs}s");
+
+        let duration = dnd_duration(duration_secs)?;
+        if let Err(err) = self.tx.send(Input::SetDnd { enabled, duration }).await {
+            tracing::error!("Failed to send set_dnd message to channel");
+            return Err(zbus::fdo::Error::Failed(err.to_string()));
+        }
+        Ok(())
+    }
+
+    /// Switch do-not-disturb to `mode`: "off", "priority" for only the
+    /// priority list (and critical notifications, unless
+    /// `CriticalBypassesDnd` is off), or "on" for nothing at all
+    ///
+    /// A non-zero `duration_secs` turns it back off after that long, like
+    /// `SetDnd`.
+    async fn set_dnd_mode(&self, mode: &str, duration_secs: u32) -> zbus::fdo::Result<()> {
+        tracing::trace!("Received set_dnd_mode request: {mode} for {duration_secs}s");
+
+        let mode = match mode {
+            "off" => DndMode::Open,
+            "priority" => DndMode::PriorityOnly,
+            "on" => DndMode::Full,
+            _ => {
+                return Err(zbus::fdo::Error::InvalidArgs(format!(
+                    "Mode must be \"off\", \"priority\" or \"on\", not {mode:?}"
+                )));
+            }
+        };
+        let duration = dnd_duration(duration_secs)?;
+        if let Err(err) = self.tx.send(Input::SetDndMode { mode, duration }).await {
+            tracing::error!("Failed to send set_dnd_mode message to channel");
+            return Err(zbus::fdo::Error::Failed(err.to_string()));
+        }
+        Ok(())
+    }
+}
+
+/// How long do-not-disturb stays on, `None` for until turned off
+fn dnd_duration(secs: u32) -> zbus::fdo::Result<Option<Duration>> {
+    if secs > DND_MAX_DURATION {
+        return Err(zbus::fdo::Error::InvalidArgs(format!(
+            "Duration must be at most {DND_MAX_DURATION} seconds"
+        )));
+    }
+    Ok((secs > 0).then(|| Duration::from_secs(secs.into())))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use cosmic_ext_notifications_util::Hint;
+
+    fn notification(app_name: &str, summary: &str, urgency: u8, secs: u64) -> Notification {
+        Notification {
+            id: 1,
+            app_name: app_name.to_string(),
+            app_icon: String::new(),
+            summary: summary.to_string(),
+            body: String::new(),
+            actions: vec![],
+            hints: vec![
+                Hint::Urgency(urgency),
+                Hint::DesktopEntry(format!("org.example.{app_name}")),
+            ],
+            expire_timeout: -1,
+            applied_timeout: None,
+            time: UNIX_EPOCH + Duration::from_secs(secs),
+        }
+    }
+
+    fn filter(entries: Vec<(&str, Value<'static>)>) -> Result<HistoryFilter, String> {
+        let dict = entries
+            .into_iter()
+            .map(|(k, v)| (k.to_string(), OwnedValue::try_from(v).unwrap()))
+            .collect();
+        HistoryFilter::from_dict(&dict)
+    }
+
+    #[test]
+    fn test_history_filter() {
+        let mail = notification("Mail", "Invoice due", 1, 1_000);
+        let chat = notification("Chat", "Lunch?", 2, 2_000);
+
+        let by_app = filter(vec![("app", Value::from("org.example.chat"))]).unwrap();
+        assert!(!by_app.matches(&mail));
+        assert!(by_app.matches(&chat));
+
+        let by_text = filter(vec![("text", Value::from("INVOICE"))]).unwrap();
+        assert!(by_text.matches(&mail));
+        assert!(!by_text.matches(&chat));
+
+        let recent_urgent = filter(vec![
+            ("since", Value::from(1_500i64)),
+            ("urgency", Value::from(2u8)),
+        ])
+        .unwrap();
+        assert!(!recent_urgent.matches(&mail));
+        assert!(recent_urgent.matches(&chat));
+
+        assert!(HistoryFilter::default().matches(&mail));
+    }
+
+    #[test]
+    fn test_history_filter_rejects_bad_input() {
+        assert!(filter(vec![("apps", Value::from("Mail"))]).is_err());
+        assert!(filter(vec![("since", Value::from("yesterday"))]).is_err());
+    }
+
+    #[test]
+    fn test_notification_dict() {
+        let mut n = notification("Mail", "Invoice due", 1, 1_000);
+        n.body = "<b>Pay</b> <img src=x onerror=alert(1)>".to_string();
+        let entry = notification_dict(&n);
+        assert_eq!(entry.get("body"), Some(&Value::from("<b>Pay</b> ")));
+        assert_eq!(entry.get("app-name"), Some(&Value::from("Mail")));
+        assert_eq!(entry.get("time"), Some(&Value::from(1_000i64)));
+        assert_eq!(entry.get("desktop-entry"), Some(&Value::from("org.example.Mail")));
+        assert!(!entry.contains_key("category"));
+    }
+
+    #[test]
+    fn test_stats_dict() {
+        let counts = AppCounts {
+            received: 4,
+            dismissed: 3,
+            actioned: 1,
+            expired: 0,
+        };
+        let entry = stats_dict("Firefox", &counts);
+        assert_eq!(entry.get("app"), Some(&Value::from("Firefox")));
+        assert_eq!(entry.get("received"), Some(&Value::from(4u32)));
+        assert_eq!(entry.get("dismiss-rate"), Some(&Value::from(0.75)));
+        assert_eq!(entry.get("action-rate"), Some(&Value::from(0.25)));
+    }
+}
@@ -0,0 +1,176 @@
+//! Tracking of the window the user is working in
+//!
+//! Follows the activated window through the COSMIC toplevel info protocol
+//! and reports its app, which output it's on and whether it's fullscreen
+//! whenever any of them changes. The protocol is read on a Wayland connection of its own,
+//! on a thread of its own, so the UI toolkit's connection is left alone.
+
+use cosmic::cctk::{
+    self,
+    cosmic_protocols::toplevel_info::v1::client::zcosmic_toplevel_handle_v1,
+    sctk::{
+        self,
+        output::{OutputHandler, OutputState},
+        registry::{ProvidesRegistryState, RegistryState},
+    },
+    toplevel_info::{ToplevelInfoHandler, ToplevelInfoState},
+    wayland_client::{Connection, QueueHandle, globals::registry_queue_init, protocol::wl_output},
+    wayland_protocols::ext::foreign_toplevel_list::v1::client::ext_foreign_toplevel_handle_v1,
+};
+use cosmic::{
+    iced::{
+        futures::{self, SinkExt, StreamExt},
+        stream,
+    },
+    iced_futures::Subscription,
+};
+use futures::channel::mpsc;
+
+/// What is known about the focused window
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct FocusedWindow {
+    /// App id of the window, usually its desktop entry
+    pub app_id: Option<String>,
+    /// Name of the output it's on
+    pub output: Option<String>,
+    /// Covers its whole output, as games and videos do
+    pub fullscreen: bool,
+}
+
+/// The focused window, each time focus moves to another app, the window
+/// moves or goes fullscreen or back
+pub fn focused_window() -> Subscription<FocusedWindow> {
+    struct FocusedWindowWorker;
+
+    Subscription::run_with_id(
+        std::any::TypeId::of::<FocusedWindowWorker>(),
+        stream::channel(4, |mut output| async move {
+            let (tx, mut rx) = mpsc::unbounded();
+            std::thread::spawn(move || {
+                if let Err(err) = watch(tx) {
+                    tracing::warn!("Focused window tracking unavailable: {}", err);
+                }
+            });
+            while let Some(window) = rx.next().await {
+                _ = output.send(window).await;
+            }
+            futures::future::pending::<()>().await;
+        }),
+    )
+}
+
+fn watch(tx: mpsc::UnboundedSender<FocusedWindow>) -> anyhow::Result<()> {
+    let conn = Connection::connect_to_env()?;
+    let (globals, mut queue) = registry_queue_init::<Tracker>(&conn)?;
+    let qh = queue.handle();
+    let registry = RegistryState::new(&globals);
+    let mut tracker = Tracker {
+        outputs: OutputState::new(&globals, &qh),
+        toplevels: ToplevelInfoState::new(&registry, &qh),
+        registry,
+        focused: FocusedWindow::default(),
+        tx,
+    };
+    loop {
+        queue.blocking_dispatch(&mut tracker)?;
+        if tracker.tx.is_closed() {
+            return Ok(());
+        }
+    }
+}
+
+struct Tracker {
+    registry: RegistryState,
+    outputs: OutputState,
+    toplevels: ToplevelInfoState,
+    /// Last state reported
+    focused: FocusedWindow,
+    tx: mpsc::UnboundedSender<FocusedWindow>,
+}
+
+impl Tracker {
+    /// Report the activated window if it changed
+    ///
+    /// With no window activated, e.g. on an empty workspace, nothing is
+    /// fullscreen and the last output stays in place.
+    fn refresh(&mut self) {
+        use zcosmic_toplevel_handle_v1::State;
+
+        let activated = self
+            .toplevels
+            .toplevels()
+            .find(|info| info.state.contains(&State::Activated));
+        let window = FocusedWindow {
+            app_id: activated
+                .map(|info| info.app_id.clone())
+                .filter(|app_id| !app_id.is_empty()),
+            output: activated
+                .and_then(|info| info.output.iter().next())
+                .and_then(|output| self.outputs.info(output))
+                .and_then(|info| info.name)
+                .or_else(|| self.focused.output.clone()),
+            fullscreen: activated.is_some_and(|info| info.state.contains(&State::Fullscreen)),
+        };
+        if window != self.focused {
+            self.focused = window.clone();
+            _ = self.tx.unbounded_send(window);
+        }
+    }
+}
+
+impl ProvidesRegistryState for Tracker {
+    fn registry(&mut self) -> &mut RegistryState {
+        &mut self.registry
+    }
+
+    sctk::registry_handlers!(OutputState);
+}
+
+impl OutputHandler for Tracker {
+    fn output_state(&mut self) -> &mut OutputState {
+        &mut self.outputs
+    }
+
+    fn new_output(&mut self, _conn: &Connection, _qh: &QueueHandle<Self>, _output: wl_output::WlOutput) {}
+
+    fn update_output(&mut self, _conn: &Connection, _qh: &QueueHandle<Self>, _output: wl_output::WlOutput) {}
+
+    fn output_destroyed(&mut self, _conn: &Connection, _qh: &QueueHandle<Self>, _output: wl_output::WlOutput) {}
+}
+
+impl ToplevelInfoHandler for Tracker {
+    fn toplevel_info_state(&mut self) -> &mut ToplevelInfoState {
+        &mut self.toplevels
+    }
+
+    fn new_toplevel(
+        &mut self,
+        _conn: &Connection,
+        _qh: &QueueHandle<Self>,
+        _toplevel: &ext_foreign_toplevel_handle_v1::ExtForeignToplevelHandleV1,
+    ) {
+        self.refresh();
+    }
+
+    fn update_toplevel(
+        &mut self,
+        _conn: &Connection,
+        _qh: &QueueHandle<Self>,
+        _toplevel: &ext_foreign_toplevel_handle_v1::ExtForeignToplevelHandleV1,
+    ) {
+        self.refresh();
+    }
+
+    fn toplevel_closed(
+        &mut self,
+        _conn: &Connection,
+        _qh: &QueueHandle<Self>,
+        _toplevel: &ext_foreign_toplevel_handle_v1::ExtForeignToplevelHandleV1,
+    ) {
+        self.refresh();
+    }
+}
+
+sctk::delegate_output!(Tracker);
+sctk::delegate_registry!(Tracker);
+cctk::delegate_toplevel_info!(Tracker);
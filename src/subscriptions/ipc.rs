@@ -0,0 +1,434 @@
+//! JSON lines over a Unix socket, for status bars that don't speak D-Bus
+//!
+//! The socket lives at `$XDG_RUNTIME_DIR/cosmic-ext-notifications.sock`.
+//! Each client first gets the current state, then one JSON object per line
+//! for every state change, accepted notification and closed notification.
+//! Clients may write one command per line, answered with a `reply` line:
+//!
+//! ```text
+//! state | dnd on [SECONDS] | dnd priority [SECONDS] | dnd off | dnd toggle
+//! pause | resume | close-all | close-app APP | toggle-center | clear-history
+//! ```
+
+use super::{control::ControlState, notifications::Input};
+use crate::constants::{
+    DND_MAX_DURATION, IPC_EVENT_BUFFER_SIZE, IPC_MAX_COMMAND_LEN, IPC_SOCKET_NAME,
+};
+use cosmic_ext_notifications_config::DndMode;
+use cosmic_ext_notifications_util::{CloseReason, Notification, NotificationRecord, strip_html};
+use std::{
+    io,
+    os::unix::fs::PermissionsExt,
+    path::PathBuf,
+    time::Duration,
+};
+use tokio::{
+    io::{AsyncBufReadExt, AsyncReadExt, AsyncWriteExt, BufReader},
+    net::{UnixListener, UnixStream, unix::OwnedWriteHalf},
+    sync::{broadcast, mpsc::Sender, watch},
+    task::JoinHandle,
+};
+
+/// A command written to the socket
+#[derive(Debug, PartialEq, Eq)]
+enum Command {
+    State,
+    ToggleDnd,
+    SetDnd {
+        enabled: bool,
+        duration: Option<Duration>,
+    },
+    SetDndMode {
+        mode: DndMode,
+        duration: Option<Duration>,
+    },
+    SetPaused(bool),
+    CloseAll,
+    CloseApp(String),
+    ToggleCenter,
+    ClearHistory,
+}
+
+impl Command {
+    fn parse(line: &str) -> Result<Self, String> {
+        let line = line.trim();
+        let (name, rest) = line
+            .split_once(char::is_whitespace)
+            .map_or((line, ""), |(name, rest)| (name, rest.trim()));
+        let no_args = |command: Self| {
+            if rest.is_empty() {
+                Ok(command)
+            } else {
+                Err(format!("'{name}' takes no arguments"))
+            }
+        };
+
+        match name {
+            "state" => no_args(Command::State),
+            "pause" => no_args(Command::SetPaused(true)),
+            "resume" => no_args(Command::SetPaused(false)),
+            "close-all" => no_args(Command::CloseAll),
+            "toggle-center" => no_args(Command::ToggleCenter),
+            "clear-history" => no_args(Command::ClearHistory),
+            "close-app" if rest.is_empty() => Err("'close-app' needs an app name".to_string()),
+            "close-app" => Ok(Command::CloseApp(rest.to_string())),
+            "dnd" => {
+                let mut args = rest.split_whitespace();
+                let command = match (args.next(), args.next()) {
+                    (Some("toggle"), None) => Command::ToggleDnd,
+                    (Some("off"), None) => Command::SetDnd {
+                        enabled: false,
+                        duration: None,
+                    },
+                    (Some(mode @ ("on" | "priority")), secs) => {
+                        let secs = secs.map_or(Ok(0), str::parse::<u32>).map_err(|_| {
+                            format!("Duration must be a number of seconds, not '{rest}'")
+                        })?;
+                        if secs > DND_MAX_DURATION {
+                            return Err(format!(
+                                "Duration must be at most {DND_MAX_DURATION} seconds"
+                            ));
+                        }
+                        let duration = (secs > 0).then(|| Duration::from_secs(secs.into()));
+                        if mode == "on" {
+                            Command::SetDnd {
+                                enabled: true,
+                                duration,
+                            }
+                        } else {
+                            Command::SetDndMode {
+                                mode: DndMode::PriorityOnly,
+                                duration,
+                            }
+                        }
+                    }
+                    _ => {
+                        return Err("Expected 'dnd on [SECONDS]', 'dnd priority [SECONDS]', \
+                             'dnd off' or 'dnd toggle'"
+                            .to_string());
+                    }
+                };
+                if args.next().is_some() {
+                    return Err("Too many arguments to 'dnd'".to_string());
+                }
+                Ok(command)
+            }
+            _ => Err(format!("Unknown command '{name}'")),
+        }
+    }
+
+    /// What to ask the app for; `None` for queries answered by the socket
+    fn into_input(self) -> Option<Input> {
+        match self {
+            Command::State => None,
+            Command::ToggleDnd => Some(Input::ToggleDnd),
+            Command::SetDnd { enabled, duration } => Some(Input::SetDnd { enabled, duration }),
+            Command::SetDndMode { mode, duration } => Some(Input::SetDndMode { mode, duration }),
+            Command::SetPaused(paused) => Some(Input::SetPaused(paused)),
+            Command::CloseAll => Some(Input::CloseAll),
+            Command::CloseApp(app) => Some(Input::CloseApp(app)),
+            Command::ToggleCenter => Some(Input::ToggleCenter),
+            Command::ClearHistory => Some(Input::ClearHistory),
+        }
+    }
+}
+
+fn state_event(state: ControlState) -> String {
+    serde_json::json!({
+        "event": "state",
+        "dnd": state.do_not_disturb,
+        "critical-bypasses-dnd": state.critical_bypass_dnd,
+        "visible": state.visible_count,
+        "paused": state.paused,
+        "held": state.held_count,
+    })
+    .to_string()
+}
+
+/// A notification as its record, with the body in plain text as a status
+/// bar shows it
+fn notification_event(n: &Notification, replaced: bool) -> String {
+    let mut record = NotificationRecord::from_notification(n);
+    record.body = strip_html(&n.body);
+    serde_json::json!({
+        "event": "notification",
+        "replaced": replaced,
+        "notification": record,
+    })
+    .to_string()
+}
+
+fn closed_event(id: u32, reason: CloseReason) -> String {
+    let reason = match reason {
+        CloseReason::Expired => "expired",
+        CloseReason::Dismissed => "dismissed",
+        CloseReason::CloseNotification => "closed",
+        CloseReason::Undefined => "undefined",
+    };
+    serde_json::json!({ "event": "closed", "id": id, "reason": reason }).to_string()
+}
+
+fn reply(result: Result<(), String>) -> String {
+    match result {
+        Ok(()) => serde_json::json!({ "event": "reply", "ok": true }),
+        Err(err) => serde_json::json!({ "event": "reply", "ok": false, "error": err }),
+    }
+    .to_string()
+}
+
+/// The listening socket and the streams fed to its clients
+///
+/// Dropping it stops accepting clients and removes the socket; connected
+/// clients see end of file once their last events are written.
+#[derive(Debug)]
+pub struct IpcServer {
+    path: PathBuf,
+    state: watch::Sender<ControlState>,
+    events: broadcast::Sender<String>,
+    accept: JoinHandle<()>,
+}
+
+impl IpcServer {
+    /// Listen in `$XDG_RUNTIME_DIR`, forwarding commands to `tx`
+    ///
+    /// Fails if another daemon is already answering on the socket.
+    pub fn bind(tx: Sender<Input>) -> io::Result<Self> {
+        let dir = std::env::var_os("XDG_RUNTIME_DIR")
+            .ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, "XDG_RUNTIME_DIR is not set"))?;
+        let path = PathBuf::from(dir).join(IPC_SOCKET_NAME);
+
+        if path.exists() {
+            if std::os::unix::net::UnixStream::connect(&path).is_ok() {
+                return Err(io::Error::new(
+                    io::ErrorKind::AddrInUse,
+                    format!("{} is in use", path.display()),
+                ));
+            }
+            // Left behind by a daemon that didn't exit cleanly
+            std::fs::remove_file(&path)?;
+        }
+        let listener = UnixListener::bind(&path)?;
+        std::fs::set_permissions(&path, std::fs::Permissions::from_mode(0o600))?;
+
+        let (state, _) = watch::channel(ControlState::default());
+        let (events, _) = broadcast::channel(IPC_EVENT_BUFFER_SIZE);
+        let accept = tokio::spawn({
+            let state = state.clone();
+            let events = events.clone();
+            async move {
+                loop {
+                    match listener.accept().await {
+                        Ok((stream, _)) => {
+                            let client = serve_client(
+                                stream,
+                                tx.clone(),
+                                state.subscribe(),
+                                events.subscribe(),
+                            );
+                            tokio::spawn(async move {
+                                if let Err(err) = client.await {
+                                    tracing::debug!("IPC client disconnected: {}", err);
+                                }
+                            });
+                        }
+                        Err(err) => {
+                            tracing::error!("Failed to accept IPC client: {}", err);
+                            return;
+                        }
+                    }
+                }
+            }
+        });
+
+        tracing::info!("Listening for IPC clients on {}", path.display());
+        Ok(Self {
+            path,
+            state,
+            events,
+            accept,
+        })
+    }
+
+    /// Send the new state to clients if it changed
+    pub fn publish(&self, state: ControlState) {
+        self.state
+            .send_if_modified(|current| std::mem::replace(current, state) != state);
+    }
+
+    pub fn posted(&self, notification: &Notification, replaced: bool) {
+        // Fails only when nobody is connected
+        _ = self.events.send(notification_event(notification, replaced));
+    }
+
+    pub fn closed(&self, id: u32, reason: CloseReason) {
+        _ = self.events.send(closed_event(id, reason));
+    }
+}
+
+impl Drop for IpcServer {
+    fn drop(&mut self) {
+        self.accept.abort();
+        _ = std::fs::remove_file(&self.path);
+    }
+}
+
+async fn write_line(write: &mut OwnedWriteHalf, line: &str) -> io::Result<()> {
+    write.write_all(line.as_bytes()).await?;
+    write.write_all(b"\n").await
+}
+
+async fn serve_client(
+    stream: UnixStream,
+    tx: Sender<Input>,
+    mut state: watch::Receiver<ControlState>,
+    mut events: broadcast::Receiver<String>,
+) -> io::Result<()> {
+    let (read, mut write) = stream.into_split();
+    let mut reader = BufReader::new(read);
+    let mut line = Vec::new();
+
+    let current = *state.borrow_and_update();
+    write_line(&mut write, &state_event(current)).await?;
+
+    loop {
+        // Reading into `line` is resumed after another branch wins, since
+        // `read_until` keeps what it has read so far
+        let remaining = (IPC_MAX_COMMAND_LEN + 1).saturating_sub(line.len()) as u64;
+        let mut limited = (&mut reader).take(remaining);
+        let out = tokio::select! {
+            read = limited.read_until(b'\n', &mut line) => {
+                let read = read?;
+                if line.last() != Some(&b'\n') {
+                    if line.len() > IPC_MAX_COMMAND_LEN {
+                        let err = format!("Commands are limited to {IPC_MAX_COMMAND_LEN} bytes");
+                        write_line(&mut write, &reply(Err(err))).await?;
+                        return Ok(());
+                    }
+                    if read == 0 {
+                        return Ok(());
+                    }
+                    continue;
+                }
+                let command = String::from_utf8_lossy(&line).trim().to_string();
+                line.clear();
+                if command.is_empty() {
+                    continue;
+                }
+                match Command::parse(&command).map(Command::into_input) {
+                    Ok(Some(input)) => reply(tx.send(input).await.map_err(|err| err.to_string())),
+                    Ok(None) => state_event(*state.borrow()),
+                    Err(err) => reply(Err(err)),
+                }
+            }
+            changed = state.changed() => {
+                if changed.is_err() {
+                    return Ok(());
+                }
+                state_event(*state.borrow_and_update())
+            }
+            event = events.recv() => match event {
+                Ok(event) => event,
+                Err(broadcast::error::RecvError::Lagged(missed)) => {
+                    tracing::debug!("IPC client missed {missed} events");
+                    continue;
+                }
+                Err(broadcast::error::RecvError::Closed) => return Ok(()),
+            },
+        };
+        write_line(&mut write, &out).await?;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use cosmic_ext_notifications_util::Hint;
+    use std::time::UNIX_EPOCH;
+
+    #[test]
+    fn test_parse_commands() {
+        assert_eq!(Command::parse("state"), Ok(Command::State));
+        assert_eq!(Command::parse(" pause \n"), Ok(Command::SetPaused(true)));
+        assert_eq!(
+            Command::parse("close-app Slack Desktop"),
+            Ok(Command::CloseApp("Slack Desktop".to_string()))
+        );
+        assert_eq!(Command::parse("dnd toggle"), Ok(Command::ToggleDnd));
+        assert_eq!(
+            Command::parse("dnd on 3600"),
+            Ok(Command::SetDnd {
+                enabled: true,
+                duration: Some(Duration::from_secs(3600)),
+            })
+        );
+        assert_eq!(
+            Command::parse("dnd on"),
+            Ok(Command::SetDnd {
+                enabled: true,
+                duration: None,
+            })
+        );
+    }
+
+    #[test]
+    fn test_parse_priority_dnd() {
+        assert_eq!(
+            Command::parse("dnd priority 600"),
+            Ok(Command::SetDndMode {
+                mode: DndMode::PriorityOnly,
+                duration: Some(Duration::from_secs(600)),
+            })
+        );
+        assert!(Command::parse("dnd priority later").is_err());
+    }
+
+    #[test]
+    fn test_parse_rejects_bad_commands() {
+        assert!(Command::parse("reboot").is_err());
+        assert!(Command::parse("pause now").is_err());
+        assert!(Command::parse("close-app").is_err());
+        assert!(Command::parse("dnd on soon").is_err());
+        assert!(Command::parse("dnd off 60").is_err());
+        assert!(Command::parse(&format!("dnd on {}", DND_MAX_DURATION + 1)).is_err());
+    }
+
+    #[test]
+    fn test_notification_event_is_plain_text() {
+        let n = Notification {
+            id: 7,
+            app_name: "Mail".to_string(),
+            app_icon: String::new(),
+            summary: "Invoice due".to_string(),
+            body: "<b>Pay</b> today".to_string(),
+            actions: vec![],
+            hints: vec![Hint::Urgency(2)],
+            expire_timeout: -1,
+            applied_timeout: None,
+            time: UNIX_EPOCH + Duration::from_secs(1_000),
+        };
+        let event: serde_json::Value =
+            serde_json::from_str(&notification_event(&n, false)).unwrap();
+        assert_eq!(event["event"], "notification");
+        let record = NotificationRecord::from_json(&event["notification"].to_string()).unwrap();
+        assert_eq!(record.body, "Pay today");
+        assert_eq!(record.hints.urgency, Some(2));
+        assert_eq!(record.received_at, 1_000_000);
+        assert_eq!(record.hints.desktop_entry, None);
+    }
+
+    #[test]
+    fn test_state_and_closed_events() {
+        let state = ControlState {
+            do_not_disturb: true,
+            visible_count: 3,
+            ..ControlState::default()
+        };
+        let event: serde_json::Value = serde_json::from_str(&state_event(state)).unwrap();
+        assert_eq!(event["dnd"], true);
+        assert_eq!(event["visible"], 3);
+
+        let event: serde_json::Value =
+            serde_json::from_str(&closed_event(7, CloseReason::Dismissed)).unwrap();
+        assert_eq!(event["reason"], "dismissed");
+    }
+}
@@ -1,2 +1,10 @@
 pub mod applet;
+pub mod control;
+pub mod focused_window;
+pub mod ipc;
 pub mod notifications;
+pub mod portal_backend;
+pub mod screen_share;
+pub mod session;
+#[cfg(feature = "audio")]
+pub mod sound;
@@ -7,7 +7,12 @@ use cosmic::{
     },
     iced_futures::Subscription,
 };
-use cosmic_ext_notifications_util::{ActionId, CloseReason, Notification};
+use cosmic_ext_notifications_config::{DndMode, NotificationsConfig, RateLimit};
+use cosmic_ext_notifications_util::{ActionId, CloseReason, Notification, RewriteRules};
+#[cfg(feature = "plugins")]
+use cosmic_ext_notifications_util::{PluginAction, PluginHost};
+#[cfg(feature = "plugins")]
+use std::sync::Arc;
 use futures::channel::mpsc;
 use std::{collections::HashMap, fmt::Debug, num::NonZeroU64, time::{Duration, Instant}};
 use tokio::sync::mpsc::{Receiver, Sender, channel};
@@ -17,19 +22,31 @@ use zbus::{
     Connection, connection::Builder as ConnectionBuilder, interface, object_server::SignalEmitter,
 };
 
-use super::applet::NotificationsApplet;
+use super::{
+    applet::NotificationsApplet,
+    control::{self, CONTROL_BUS_NAME, CONTROL_PATH, ControlState, NotificationsControl},
+    ipc::IpcServer,
+    portal_backend,
+};
+use crate::sender::{SenderCache, SenderIdentity};
+use crate::state::stats::AppCounts;
 
 #[derive(Debug)]
 pub struct Conns {
     notifications: Connection,
     pub tx: Sender<Input>,
     rx: Receiver<Input>,
+    /// Priority lane for critical notifications, drained before `rx`
+    urgent_rx: Receiver<Input>,
     _panel: Option<Connection>,
+    /// JSON socket for status bars, `None` if it couldn't be bound
+    ipc: Option<IpcServer>,
 }
 
 impl Conns {
     pub async fn new() -> zbus::Result<Self> {
         let (tx, rx) = channel(CHANNEL_BUFFER_SIZE);
+        let (urgent_tx, urgent_rx) = channel(URGENT_CHANNEL_BUFFER_SIZE);
         let panel = match applet::setup_panel_conn(tx.clone()).await {
             Ok(conn) => Some(conn),
             Err(err) => {
@@ -47,6 +64,10 @@ impl Conns {
                     NonZeroU64::new(1).unwrap(),
                     Vec::new(),
                     RateLimiter::new(),
+                    urgent_tx,
+                    SenderCache::default(),
+                    ServerCapabilities::default(),
+                    ContentRules::default(),
                 ),
             )?
             // Also serve the applet interface on session bus for history API access
@@ -54,14 +75,35 @@ impl Conns {
                 "/com/system76/NotificationsApplet",
                 NotificationsApplet { tx: tx.clone() },
             )?
+            .serve_at(CONTROL_PATH, NotificationsControl::new(tx.clone()))?
             .build()
             .await?;
 
+        // Also reachable through org.freedesktop.Notifications, so a name
+        // clash only costs the shorter address
+        if let Err(err) = conn.request_name(CONTROL_BUS_NAME).await {
+            tracing::warn!("Failed to own {}: {}", CONTROL_BUS_NAME, err);
+        }
+
+        if let Err(err) = portal_backend::serve(&conn).await {
+            tracing::warn!("Notification portal backend unavailable: {}", err);
+        }
+
+        let ipc = match IpcServer::bind(tx.clone()) {
+            Ok(ipc) => Some(ipc),
+            Err(err) => {
+                tracing::warn!("IPC socket unavailable: {}", err);
+                None
+            }
+        };
+
         Ok(Self {
             tx,
             notifications: conn,
             rx,
+            urgent_rx,
             _panel: panel,
+            ipc,
         })
     }
 }
@@ -80,10 +122,36 @@ enum ConnectionState {
     },
 }
 
+/// Receive the next input, always draining the urgent lane first
+///
+/// Critical notifications are sent on their own channel so they never wait
+/// behind a burst of normal notifications queued on `rx`.
+async fn next_input(urgent_rx: &mut Receiver<Input>, rx: &mut Receiver<Input>) -> Option<Input> {
+    tokio::select! {
+        biased;
+        Some(input) = urgent_rx.recv() => Some(input),
+        input = rx.recv() => input,
+    }
+}
+
+/// Whether a notification goes on the urgent lane
+///
+/// Only new critical notifications do: a replacement has to stay behind
+/// the notification it replaces, which may still be queued on the
+/// regular lane.
+fn takes_urgent_lane(replaces_id: u32, critical: bool) -> bool {
+    critical && replaces_id == 0
+}
+
 /// Process a single input message from the D-Bus interface
 async fn process_input(output: &mut mpsc::Sender<Event>, conns: &Conns, input: Input) {
     match input {
         Input::Activated { token, id, action } => {
+            if portal_backend::action_invoked(&conns.notifications, id, &action, &token).await {
+                tracing::trace!("Activated portal notification");
+                return;
+            }
+
             let object_server = conns.notifications.object_server();
             let Ok(iface_ref) = object_server
                 .interface::<_, Notifications>("/org/freedesktop/Notifications")
@@ -92,11 +160,14 @@ async fn process_input(output: &mut mpsc::Sender<Event>, conns: &Conns, input: I
                 return;
             };
 
-            if let Err(err) =
-                Notifications::activation_token(iface_ref.signal_emitter(), id, &token)
-                    .await
-            {
-                error!("Failed to signal notification with token {}", err);
+            // Empty when the compositor lacks xdg-activation
+            if !token.is_empty() {
+                if let Err(err) =
+                    Notifications::activation_token(iface_ref.signal_emitter(), id, &token)
+                        .await
+                {
+                    error!("Failed to signal notification with token {}", err);
+                }
             }
 
             if let Err(err) =
@@ -107,7 +178,25 @@ async fn process_input(output: &mut mpsc::Sender<Event>, conns: &Conns, input: I
             }
             tracing::trace!("Activated application");
         }
+        Input::Replied { id, text } => {
+            let object_server = conns.notifications.object_server();
+            if let Ok(iface_ref) = object_server
+                .interface::<_, Notifications>("/org/freedesktop/Notifications")
+                .await
+            {
+                if let Err(err) =
+                    Notifications::notification_replied(iface_ref.signal_emitter(), id, &text)
+                        .await
+                {
+                    error!("Failed to signal reply to notification {}", err);
+                }
+            }
+        }
         Input::Closed(id, reason) => {
+            if let Some(ipc) = &conns.ipc {
+                ipc.closed(id, reason);
+            }
+            portal_backend::forget(&conns.notifications, id).await;
             let object_server = conns.notifications.object_server();
             if let Ok(iface_ref) = object_server
                 .interface::<_, Notifications>("/org/freedesktop/Notifications")
@@ -121,14 +210,47 @@ async fn process_input(output: &mut mpsc::Sender<Event>, conns: &Conns, input: I
                 .await;
             }
         }
-        Input::Notification(notification) => {
+        Input::Notification(notification, posted) => {
             _ = output.send(Event::Notification(notification)).await;
+            if let Some(ipc) = &conns.ipc {
+                ipc.posted(&posted, false);
+            }
+            control::posted(&conns.notifications, &posted, false).await;
         }
-        Input::Replace(notification) => {
+        Input::Replace(notification, posted) => {
             _ = output.send(Event::Replace(notification)).await;
+            if let Some(ipc) = &conns.ipc {
+                ipc.posted(&posted, true);
+            }
+            control::posted(&conns.notifications, &posted, true).await;
+        }
+        #[cfg(feature = "plugins")]
+        Input::Quieted {
+            notification,
+            shared,
+            replace,
+        } => {
+            let event = if replace {
+                Event::QuietReplace(notification)
+            } else {
+                Event::ToHistory(notification)
+            };
+            _ = output.send(event).await;
+            if let Some(ipc) = &conns.ipc {
+                ipc.posted(&shared, replace);
+            }
+            control::posted(&conns.notifications, &shared, replace).await;
+        }
+        #[cfg(feature = "plugins")]
+        Input::DroppedByPlugin(notification) => {
+            _ = output.send(Event::DroppedByPlugin(notification)).await;
         }
         Input::CloseNotification(id) => {
             _ = output.send(Event::CloseNotification(id)).await;
+            if let Some(ipc) = &conns.ipc {
+                ipc.closed(id, CloseReason::CloseNotification);
+            }
+            portal_backend::forget(&conns.notifications, id).await;
 
             let object_server = conns.notifications.object_server();
             let Ok(iface_ref) = object_server
@@ -145,6 +267,10 @@ async fn process_input(output: &mut mpsc::Sender<Event>, conns: &Conns, input: I
             }
         }
         Input::Dismissed(id) => {
+            if let Some(ipc) = &conns.ipc {
+                ipc.closed(id, CloseReason::Dismissed);
+            }
+            portal_backend::forget(&conns.notifications, id).await;
             let object_server = conns.notifications.object_server();
             let Ok(iface_ref) = object_server
                 .interface::<_, Notifications>("/org/freedesktop/Notifications")
@@ -188,6 +314,159 @@ async fn process_input(output: &mut mpsc::Sender<Event>, conns: &Conns, input: I
                 );
             }
         }
+        Input::SearchHistory { query, tx } => {
+            if let Err(err) = output.send(Event::SearchHistory { query, tx }).await {
+                tracing::error!(
+                    "Failed to send SearchHistory event to subscription channel: {err}"
+                );
+            }
+        }
+        Input::GetStats { days, tx } => {
+            if let Err(err) = output.send(Event::GetStats { days, tx }).await {
+                tracing::error!("Failed to send GetStats event to subscription channel: {err}");
+            }
+        }
+        Input::StartFocusSession {
+            duration,
+            allowed_apps,
+        } => {
+            _ = output
+                .send(Event::StartFocusSession {
+                    duration,
+                    allowed_apps,
+                })
+                .await;
+        }
+        Input::StopFocusSession => {
+            _ = output.send(Event::StopFocusSession).await;
+        }
+        Input::GetHistorySections { tx } => {
+            if let Err(err) = output.send(Event::GetHistorySections { tx }).await {
+                tracing::error!(
+                    "Failed to send GetHistorySections event to subscription channel: {err}"
+                );
+            }
+        }
+        Input::SetHistorySectionCollapsed { key, collapsed } => {
+            _ = output
+                .send(Event::SetHistorySectionCollapsed { key, collapsed })
+                .await;
+        }
+        Input::SetSoundVolume(volume) => {
+            _ = output.send(Event::SetSoundVolume(volume)).await;
+        }
+        Input::ClearHistory => {
+            _ = output.send(Event::ClearHistory).await;
+        }
+        Input::ToggleDnd => {
+            _ = output.send(Event::ToggleDnd).await;
+        }
+        Input::SetDnd { enabled, duration } => {
+            _ = output.send(Event::SetDnd { enabled, duration }).await;
+        }
+        Input::SetDndMode { mode, duration } => {
+            _ = output.send(Event::SetDndMode { mode, duration }).await;
+        }
+        Input::ControlStateChanged(state) => {
+            if let Some(ipc) = &conns.ipc {
+                ipc.publish(state);
+            }
+            control::publish(&conns.notifications, state).await;
+        }
+        Input::CapabilitiesChanged(capabilities) => {
+            let object_server = conns.notifications.object_server();
+            if let Ok(iface_ref) = object_server
+                .interface::<_, Notifications>("/org/freedesktop/Notifications")
+                .await
+            {
+                iface_ref.get_mut().await.6 = capabilities;
+            }
+        }
+        Input::RateLimitsChanged(limits) => {
+            let object_server = conns.notifications.object_server();
+            if let Ok(iface_ref) = object_server
+                .interface::<_, Notifications>("/org/freedesktop/Notifications")
+                .await
+            {
+                iface_ref.get_mut().await.3.limits = limits;
+            }
+        }
+        Input::ContentRulesChanged(rules) => {
+            let object_server = conns.notifications.object_server();
+            if let Ok(iface_ref) = object_server
+                .interface::<_, Notifications>("/org/freedesktop/Notifications")
+                .await
+            {
+                iface_ref.get_mut().await.7 = rules;
+            }
+        }
+        Input::SetPaused(paused) => {
+            _ = output.send(Event::SetPaused(paused)).await;
+        }
+        Input::SetCriticalBypass(enabled) => {
+            _ = output.send(Event::SetCriticalBypass(enabled)).await;
+        }
+        Input::Snooze { id, duration, tx } => {
+            if let Err(err) = output.send(Event::Snooze { id, duration, tx }).await {
+                tracing::error!("Failed to send Snooze event to subscription channel: {err}");
+            }
+        }
+        Input::CloseAll => {
+            _ = output.send(Event::CloseAll).await;
+        }
+        Input::ToggleCenter => {
+            _ = output.send(Event::ToggleCenter).await;
+        }
+        Input::FocusPopups => {
+            _ = output.send(Event::FocusPopups).await;
+        }
+        Input::CloseApp(app) => {
+            _ = output.send(Event::CloseApp(app)).await;
+        }
+        Input::InvokeAction { id, action, tx } => {
+            if let Err(err) = output.send(Event::InvokeAction { id, action, tx }).await {
+                tracing::error!("Failed to send InvokeAction event to subscription channel: {err}");
+            }
+        }
+        Input::DebugDump { tx } => {
+            if let Err(err) = output.send(Event::DebugDump { tx }).await {
+                tracing::error!("Failed to send DebugDump event to subscription channel: {err}");
+            }
+        }
+        Input::GetFocusSession { tx } => {
+            if let Err(err) = output.send(Event::GetFocusSession { tx }).await {
+                tracing::error!(
+                    "Failed to send GetFocusSession event to subscription channel: {err}"
+                );
+            }
+        }
+        Input::FocusSessionChanged(remaining_secs) => {
+            // Let every connected applet update its countdown
+            let mut conns_to_signal = vec![conns.notifications.clone()];
+            let object_server = conns.notifications.object_server();
+            if let Ok(iface_ref) = object_server
+                .interface::<_, Notifications>("/org/freedesktop/Notifications")
+                .await
+            {
+                conns_to_signal.extend(iface_ref.get().await.2.iter().cloned());
+            }
+
+            for c in conns_to_signal {
+                let Ok(iface_ref) = c
+                    .object_server()
+                    .interface::<_, NotificationsApplet>("/com/system76/NotificationsApplet")
+                    .await
+                else {
+                    continue;
+                };
+                if let Err(err) =
+                    NotificationsApplet::focus_session_changed(iface_ref.signal_emitter(), remaining_secs)
+                        .await
+                {
+                    error!("Failed to signal focus session change {}", err);
+                }
+            }
+        }
         Input::CleanupRateLimiter => {
             let object_server = conns.notifications.object_server();
             if let Ok(iface_ref) = object_server
@@ -198,8 +477,13 @@ async fn process_input(output: &mut mpsc::Sender<Event>, conns: &Conns, input: I
                 iface.3.cleanup();
                 tracing::debug!(
                     "Rate limiter cleanup: {} apps tracked",
-                    iface.3.limits.len()
+                    iface.3.buckets.len()
                 );
+                let dropped = iface.3.take_dropped();
+                drop(iface);
+                if !dropped.is_empty() {
+                    _ = output.send(Event::RateLimited(dropped)).await;
+                }
             }
         }
     }
@@ -216,15 +500,111 @@ pub enum Input {
         id: u32,
         action: ActionId,
     },
-    Notification(Notification),
-    Replace(Notification),
+    /// A new notification, and what listeners other than the app see of it
+    Notification(Notification, Notification),
+    /// An updated notification, and what listeners other than the app see
+    /// of it
+    Replace(Notification, Notification),
+    /// A notification a plugin kept from popping up, what listeners other
+    /// than the app see of it, and whether it is an update
+    #[cfg(feature = "plugins")]
+    Quieted {
+        notification: Notification,
+        shared: Notification,
+        replace: bool,
+    },
+    /// A new notification a plugin dropped, for the app to audit
+    #[cfg(feature = "plugins")]
+    DroppedByPlugin(Notification),
     CloseNotification(u32),
+    /// The user sent an inline reply
+    Replied {
+        id: u32,
+        text: String,
+    },
     Closed(u32, CloseReason),
     Dismissed(u32),
     AppletConn(Connection),
     GetHistory {
         tx: tokio::sync::oneshot::Sender<Vec<Notification>>,
     },
+    /// History entries matching a full-text query, newest first
+    SearchHistory {
+        query: String,
+        tx: tokio::sync::oneshot::Sender<Vec<Notification>>,
+    },
+    /// Notification counts over the last `days` days (0 = all kept)
+    GetStats {
+        days: u32,
+        tx: tokio::sync::oneshot::Sender<HistoryStatsReport>,
+    },
+    StartFocusSession {
+        duration: Duration,
+        allowed_apps: Vec<String>,
+    },
+    StopFocusSession,
+    GetFocusSession {
+        tx: tokio::sync::oneshot::Sender<u32>,
+    },
+    DebugDump {
+        tx: tokio::sync::oneshot::Sender<String>,
+    },
+    GetHistorySections {
+        tx: tokio::sync::oneshot::Sender<Vec<HistorySectionInfo>>,
+    },
+    SetHistorySectionCollapsed {
+        key: String,
+        collapsed: bool,
+    },
+    SetSoundVolume(u8),
+    ClearHistory,
+    ToggleDnd,
+    SetDnd {
+        enabled: bool,
+        /// Turn DND back off after this long
+        duration: Option<Duration>,
+    },
+    /// Switch to a do-not-disturb mode, such as priority-only
+    SetDndMode {
+        mode: DndMode,
+        /// Turn DND back off after this long
+        duration: Option<Duration>,
+    },
+    /// Hold popups back, or show the held ones and stop holding
+    SetPaused(bool),
+    /// Let critical notifications through priority-only DND or not
+    SetCriticalBypass(bool),
+    /// Hide a popup until `duration` has passed; answers whether it was shown
+    Snooze {
+        id: u32,
+        duration: Duration,
+        tx: tokio::sync::oneshot::Sender<bool>,
+    },
+    /// Dismiss every popup
+    CloseAll,
+    /// Open or close the notification center
+    ToggleCenter,
+    /// Move keyboard focus to the newest popup
+    FocusPopups,
+    /// Dismiss the popups of one app
+    CloseApp(String),
+    /// Activate a visible notification; answers whether it had the action
+    InvokeAction {
+        id: u32,
+        /// `None` picks the default action
+        action: Option<ActionId>,
+        tx: tokio::sync::oneshot::Sender<bool>,
+    },
+    /// Publish state on the control interface
+    ControlStateChanged(ControlState),
+    /// Advertise a new set of capabilities after a config change
+    CapabilitiesChanged(ServerCapabilities),
+    /// Apply new rate limits after a config change
+    RateLimitsChanged(RateLimits),
+    /// Clean up notification text differently after a config change
+    ContentRulesChanged(ContentRules),
+    /// Broadcast the remaining focus session time (0 when inactive) to applets
+    FocusSessionChanged(u32),
     CleanupRateLimiter,
 }
 
@@ -233,19 +613,99 @@ pub enum Event {
     Ready(Sender<Input>),
     Notification(Notification),
     Replace(Notification),
+    /// A new notification a plugin sent straight to history
+    #[cfg(feature = "plugins")]
+    ToHistory(Notification),
+    /// An update a plugin kept from popping up; it only changes a popup
+    /// already on screen
+    #[cfg(feature = "plugins")]
+    QuietReplace(Notification),
+    /// A new notification a plugin dropped
+    #[cfg(feature = "plugins")]
+    DroppedByPlugin(Notification),
     CloseNotification(u32),
     AppletActivated { id: u32, action: ActionId },
     GetHistory {
         tx: tokio::sync::oneshot::Sender<Vec<Notification>>,
     },
+    /// History entries matching a full-text query, newest first
+    SearchHistory {
+        query: String,
+        tx: tokio::sync::oneshot::Sender<Vec<Notification>>,
+    },
+    /// Notification counts over the last `days` days (0 = all kept)
+    GetStats {
+        days: u32,
+        tx: tokio::sync::oneshot::Sender<HistoryStatsReport>,
+    },
+    StartFocusSession {
+        duration: Duration,
+        allowed_apps: Vec<String>,
+    },
+    StopFocusSession,
+    GetFocusSession {
+        tx: tokio::sync::oneshot::Sender<u32>,
+    },
+    DebugDump {
+        tx: tokio::sync::oneshot::Sender<String>,
+    },
+    GetHistorySections {
+        tx: tokio::sync::oneshot::Sender<Vec<HistorySectionInfo>>,
+    },
+    SetHistorySectionCollapsed {
+        key: String,
+        collapsed: bool,
+    },
+    SetSoundVolume(u8),
+    ClearHistory,
+    ToggleDnd,
+    SetDnd {
+        enabled: bool,
+        duration: Option<Duration>,
+    },
+    SetDndMode {
+        mode: DndMode,
+        duration: Option<Duration>,
+    },
+    SetPaused(bool),
+    SetCriticalBypass(bool),
+    Snooze {
+        id: u32,
+        duration: Duration,
+        tx: tokio::sync::oneshot::Sender<bool>,
+    },
+    CloseAll,
+    CloseApp(String),
+    ToggleCenter,
+    FocusPopups,
+    InvokeAction {
+        id: u32,
+        action: Option<ActionId>,
+        tx: tokio::sync::oneshot::Sender<bool>,
+    },
+    /// Notifications dropped by the rate limiter, per app
+    RateLimited(Vec<(String, u32)>),
 }
 
+/// A date section of the history list: (key, label, collapsed, notification ids)
+pub type HistorySectionInfo = (String, String, bool, Vec<u32>);
+
+/// Counts per app, most notifications first, and notifications received
+/// in each local hour of the day
+pub type HistoryStatsReport = (Vec<(String, AppCounts)>, [u32; 24]);
+
 impl Clone for Event {
     fn clone(&self) -> Self {
         match self {
             Event::Ready(tx) => Event::Ready(tx.clone()),
             Event::Notification(n) => Event::Notification(n.clone()),
             Event::Replace(n) => Event::Replace(n.clone()),
+            #[cfg(feature = "plugins")]
+            Event::ToHistory(n) => Event::ToHistory(n.clone()),
+            #[cfg(feature = "plugins")]
+            Event::QuietReplace(n) => Event::QuietReplace(n.clone()),
+            #[cfg(feature = "plugins")]
+            Event::DroppedByPlugin(n) => Event::DroppedByPlugin(n.clone()),
             Event::CloseNotification(id) => Event::CloseNotification(*id),
             Event::AppletActivated { id, action } => Event::AppletActivated {
                 id: *id,
@@ -254,6 +714,59 @@ impl Clone for Event {
             Event::GetHistory { .. } => {
                 panic!("GetHistory event cannot be cloned - it contains a oneshot sender")
             }
+            Event::SearchHistory { .. } => {
+                panic!("SearchHistory event cannot be cloned - it contains a oneshot sender")
+            }
+            Event::GetStats { .. } => {
+                panic!("GetStats event cannot be cloned - it contains a oneshot sender")
+            }
+            Event::StartFocusSession {
+                duration,
+                allowed_apps,
+            } => Event::StartFocusSession {
+                duration: *duration,
+                allowed_apps: allowed_apps.clone(),
+            },
+            Event::StopFocusSession => Event::StopFocusSession,
+            Event::GetFocusSession { .. } => {
+                panic!("GetFocusSession event cannot be cloned - it contains a oneshot sender")
+            }
+            Event::DebugDump { .. } => {
+                panic!("DebugDump event cannot be cloned - it contains a oneshot sender")
+            }
+            Event::GetHistorySections { .. } => {
+                panic!("GetHistorySections event cannot be cloned - it contains a oneshot sender")
+            }
+            Event::SetHistorySectionCollapsed { key, collapsed } => {
+                Event::SetHistorySectionCollapsed {
+                    key: key.clone(),
+                    collapsed: *collapsed,
+                }
+            }
+            Event::SetSoundVolume(volume) => Event::SetSoundVolume(*volume),
+            Event::ClearHistory => Event::ClearHistory,
+            Event::ToggleDnd => Event::ToggleDnd,
+            Event::SetDnd { enabled, duration } => Event::SetDnd {
+                enabled: *enabled,
+                duration: *duration,
+            },
+            Event::SetDndMode { mode, duration } => Event::SetDndMode {
+                mode: *mode,
+                duration: *duration,
+            },
+            Event::SetPaused(paused) => Event::SetPaused(*paused),
+            Event::SetCriticalBypass(enabled) => Event::SetCriticalBypass(*enabled),
+            Event::Snooze { .. } => {
+                panic!("Snooze event cannot be cloned - it contains a oneshot sender")
+            }
+            Event::CloseAll => Event::CloseAll,
+            Event::ToggleCenter => Event::ToggleCenter,
+            Event::FocusPopups => Event::FocusPopups,
+            Event::CloseApp(app) => Event::CloseApp(app.clone()),
+            Event::InvokeAction { .. } => {
+                panic!("InvokeAction event cannot be cloned - it contains a oneshot sender")
+            }
+            Event::RateLimited(dropped) => Event::RateLimited(dropped.clone()),
         }
     }
 }
@@ -317,7 +830,7 @@ pub fn notifications() -> Subscription<Event> {
                     ConnectionState::Connected { mut output, mut conns } => {
                         // Process messages until channel closes
                         loop {
-                            match conns.rx.recv().await {
+                            match next_input(&mut conns.urgent_rx, &mut conns.rx).await {
                                 Some(input) => {
                                     process_input(&mut output, &conns, input).await;
                                 }
@@ -338,76 +851,377 @@ pub fn notifications() -> Subscription<Event> {
     )
 }
 
-/// Rate limiter to prevent notification spam attacks
+/// Rate limits of each app, which follow the config
+///
+/// Limits are looked up through the app rules each time, so they match
+/// the same way every other per-app setting does.
+#[derive(Debug, Clone, Default)]
+pub struct RateLimits {
+    config: NotificationsConfig,
+}
+
+impl RateLimits {
+    pub fn from_config(config: &NotificationsConfig) -> Self {
+        Self {
+            config: config.clone(),
+        }
+    }
+
+    fn for_app(&self, app_name: &str, desktop_entry: Option<&str>) -> RateLimit {
+        self.config.rate_limit_for_app(app_name, desktop_entry)
+    }
+
+    /// Limit of an app's critical notifications, counted separately
+    fn for_critical(&self, app_name: &str, desktop_entry: Option<&str>) -> RateLimit {
+        let limit = self.for_app(app_name, desktop_entry);
+        RateLimit {
+            per_minute: limit.per_minute.saturating_mul(CRITICAL_RATE_LIMIT_FACTOR),
+            burst: limit.burst.saturating_mul(CRITICAL_RATE_LIMIT_FACTOR),
+        }
+    }
+}
+
+/// How notification text is cleaned up as it arrives, and how much of it
+/// listeners get to see, which follows the config and the session
+///
+/// Applied before a notification is handed to the app, applets or any
+/// other listener, so none of them sees the text as it was sent.
+#[derive(Debug, Clone, Default)]
+pub struct ContentRules {
+    config: NotificationsConfig,
+    /// Compiled `rewrite_rules`
+    rewrites: RewriteRules,
+    /// Content is hidden right now, on the lock screen or while the
+    /// screen is shared
+    hide_content: bool,
+    /// Loaded `plugins`, run after the rewrite rules
+    #[cfg(feature = "plugins")]
+    plugins: Arc<PluginHost>,
+}
+
+impl ContentRules {
+    pub fn from_config(config: &NotificationsConfig, hide_content: bool) -> Self {
+        Self {
+            config: config.clone(),
+            rewrites: RewriteRules::compile(
+                config
+                    .rewrite_rules
+                    .iter()
+                    .map(|r| (r.pattern.as_str(), r.replacement.as_str(), r.app_name.as_deref())),
+            ),
+            hide_content,
+            #[cfg(feature = "plugins")]
+            plugins: Arc::default(),
+        }
+    }
+
+    /// Run `plugins` on arriving notifications as well
+    #[cfg(feature = "plugins")]
+    pub fn with_plugins(self, plugins: Arc<PluginHost>) -> Self {
+        Self { plugins, ..self }
+    }
+
+    /// Clean up the text of `n` as it arrives
+    fn apply(&self, n: &mut Notification) {
+        if self.config.strip_invisible_chars {
+            n.strip_spoofing_chars();
+        }
+        n.rewrite(&self.rewrites);
+    }
+
+    /// Let the plugins change `n`, on a blocking thread as a plugin may
+    /// run for a while; a plugin that panics leaves `n` as it was
+    #[cfg(feature = "plugins")]
+    async fn run_plugins(&self, n: Notification) -> (Notification, PluginAction) {
+        if self.plugins.is_empty() {
+            return (n, PluginAction::Show);
+        }
+        let plugins = Arc::clone(&self.plugins);
+        let mut plugged = n.clone();
+        let run = tokio::task::spawn_blocking(move || {
+            let action = plugins.run(&mut plugged);
+            (plugged, action)
+        });
+        match run.await {
+            Ok(done) => done,
+            Err(err) => {
+                tracing::warn!("Plugins failed on notification {}: {}", n.id, err);
+                (n, PluginAction::Show)
+            }
+        }
+    }
+
+    /// Whether listeners only learn which app `n` is from, see [`withholds`]
+    fn withholds(&self, n: &Notification) -> bool {
+        withholds(&self.config, self.hide_content, n)
+    }
+
+    /// What listeners see of `n`, see [`shared`]
+    fn shared(&self, n: &Notification) -> Notification {
+        shared(&self.config, self.hide_content, n)
+    }
+}
+
+/// Whether listeners only learn which app `n` is from
+///
+/// So while content is hidden, and always for apps marked sensitive or
+/// kept out of history.
+pub fn withholds(config: &NotificationsConfig, hide_content: bool, n: &Notification) -> bool {
+    hide_content
+        || config.is_sensitive_app(&n.app_name, n.desktop_entry())
+        || !config.store_in_history_for_app(&n.app_name, n.desktop_entry())
+}
+
+/// What applets, the control interface and the IPC socket see of `n`,
+/// as it arrives or from history
+///
+/// Withheld notifications are redacted like on the lock screen; others
+/// have their one-time codes and reset links masked unless the app's
+/// rule turns masking off.
+pub fn shared(config: &NotificationsConfig, hide_content: bool, n: &Notification) -> Notification {
+    if withholds(config, hide_content, n) {
+        return n.redacted(crate::fl!("lock-redacted", app = n.app_name.as_str()));
+    }
+    if config.mask_secrets_for_app(&n.app_name, n.desktop_entry()) {
+        if let Some(masked) = n.masked() {
+            return masked;
+        }
+    }
+    n.clone()
+}
+
+/// Hints applets get of a withheld notification, which leave out
+/// everything [`Notification::redacted`] does
+fn withheld_hint(key: &str) -> bool {
+    !matches!(
+        key,
+        "image-data"
+            | "image_data"
+            | "image-path"
+            | "image_path"
+            | "icon_data"
+            | "x-kde-reply-placeholder-text"
+            | "value"
+    )
+}
+
+/// Notifications an app may still send right now
+#[derive(Debug, Clone, Copy)]
+struct Bucket {
+    tokens: f64,
+    refilled: Instant,
+    /// Limit the app's rule set when it last sent a notification
+    limit: RateLimit,
+}
+
+impl Bucket {
+    /// Top the bucket up for the time passed since the last refill
+    fn refill(&mut self, now: Instant) {
+        let elapsed = now.saturating_duration_since(self.refilled).as_secs_f64();
+        self.tokens = (self.tokens + elapsed * f64::from(self.limit.per_minute) / 60.0)
+            .min(f64::from(self.limit.burst));
+        self.refilled = now;
+    }
+}
+
+/// Token-bucket rate limiter so a runaway sender can't flood the screen
+///
+/// Every app gets a bucket of `burst` notifications, refilled at its
+/// per-minute rate. Notifications finding the bucket empty are dropped
+/// and counted, so the user can be told about them. Critical
+/// notifications draw from a second, larger bucket, so a flood of normal
+/// ones never holds back an alarm but a flood of alarms is still stopped.
 struct RateLimiter {
-    // app_name -> (window_start, count_in_window)
-    pub(super) limits: HashMap<String, (Instant, u32)>,
+    buckets: HashMap<String, Bucket>,
+    critical_buckets: HashMap<String, Bucket>,
+    limits: RateLimits,
+    /// Notifications dropped per app since the last summary
+    dropped: HashMap<String, u32>,
 }
 
 impl RateLimiter {
     fn new() -> Self {
         Self {
-            limits: HashMap::new(),
+            buckets: HashMap::new(),
+            critical_buckets: HashMap::new(),
+            limits: RateLimits::default(),
+            dropped: HashMap::new(),
         }
     }
 
     /// Check if a notification from the given app should be accepted.
     /// Returns true if under rate limit, false if rate limited.
-    fn check_and_update(&mut self, app_name: &str) -> bool {
-        const WINDOW: Duration = Duration::from_secs(60);
+    ///
+    /// The limit follows the rule matching `app_name` and `desktop_entry`,
+    /// while the bucket is kept under `key`, the verified app where known.
+    fn check_and_update(&mut self, key: &str, app_name: &str, desktop_entry: Option<&str>) -> bool {
+        let limit = self.limits.for_app(app_name, desktop_entry);
+        self.take(key, limit, false)
+    }
+
+    /// [`Self::check_and_update`] for a critical notification
+    fn check_critical(&mut self, key: &str, app_name: &str, desktop_entry: Option<&str>) -> bool {
+        let limit = self.limits.for_critical(app_name, desktop_entry);
+        self.take(key, limit, true)
+    }
+
+    fn take(&mut self, app_name: &str, limit: RateLimit, critical: bool) -> bool {
+        if limit.per_minute == 0 {
+            return true;
+        }
 
         // If too many apps tracked, force cleanup first
-        if self.limits.len() >= RATE_LIMIT_MAX_APPS {
+        if self.buckets.len().max(self.critical_buckets.len()) >= RATE_LIMIT_MAX_APPS {
             self.cleanup();
         }
 
+        let buckets = if critical {
+            &mut self.critical_buckets
+        } else {
+            &mut self.buckets
+        };
+
         // If still too many after cleanup, reject (likely attack)
-        if self.limits.len() >= RATE_LIMIT_MAX_APPS {
+        if buckets.len() >= RATE_LIMIT_MAX_APPS && !buckets.contains_key(app_name) {
             tracing::warn!(
                 "Rate limiter tracking too many apps ({}), rejecting notification from '{}'",
-                self.limits.len(),
+                buckets.len(),
                 app_name
             );
             return false;
         }
 
         let now = Instant::now();
-
-        let entry = self
-            .limits
-            .entry(app_name.to_string())
-            .or_insert((now, 0));
-
-        // Reset window if expired
-        if now.duration_since(entry.0) > WINDOW {
-            *entry = (now, 1);
-            return true;
-        }
-
-        // Check rate limit
-        if entry.1 >= RATE_LIMIT_PER_MINUTE {
-            tracing::warn!(
-                "Rate limiting notifications from '{}' - exceeded {} notifications per minute",
-                app_name,
-                RATE_LIMIT_PER_MINUTE
-            );
+        let bucket = buckets.entry(app_name.to_string()).or_insert(Bucket {
+            tokens: f64::from(limit.burst),
+            refilled: now,
+            limit,
+        });
+        bucket.refill(now);
+        bucket.limit = limit;
+
+        if bucket.tokens < 1.0 {
+            let dropped = self.dropped.entry(app_name.to_string()).or_default();
+            if *dropped == 0 {
+                tracing::warn!(
+                    "Rate limiting notifications from '{}' - exceeded {} at once or {} per minute",
+                    app_name,
+                    limit.burst,
+                    limit.per_minute
+                );
+            }
+            *dropped += 1;
             return false;
         }
 
-        entry.1 += 1;
+        bucket.tokens -= 1.0;
         true
     }
 
-    /// Clean up old entries periodically to prevent memory growth
+    /// Forget buckets that have filled up again, to prevent memory growth
     fn cleanup(&mut self) {
-        const WINDOW: Duration = Duration::from_secs(60);
         let now = Instant::now();
-        self.limits
-            .retain(|_, (start, _)| now.duration_since(*start) <= WINDOW);
+        let keep = |bucket: &mut Bucket| {
+            bucket.refill(now);
+            bucket.tokens < f64::from(bucket.limit.burst)
+        };
+        self.buckets.retain(|_, bucket| keep(bucket));
+        self.critical_buckets.retain(|_, bucket| keep(bucket));
+    }
+
+    /// Dropped notifications per app since the last call, most first
+    fn take_dropped(&mut self) -> Vec<(String, u32)> {
+        let mut dropped: Vec<_> = self.dropped.drain().collect();
+        dropped.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+        dropped
     }
 }
 
-pub struct Notifications(Sender<Input>, NonZeroU64, Vec<Connection>, RateLimiter);
+/// Optional capabilities, which follow the config so clients don't send
+/// what would be hidden anyway
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ServerCapabilities {
+    /// Notification images and icons are shown
+    pub images: bool,
+    /// Action buttons and the inline reply field are shown
+    pub actions: bool,
+    /// Links in the body are clickable
+    pub hyperlinks: bool,
+    /// Sound hints are played
+    pub sound: bool,
+}
+
+impl Default for ServerCapabilities {
+    fn default() -> Self {
+        Self {
+            images: true,
+            actions: true,
+            hyperlinks: true,
+            sound: cfg!(feature = "audio"),
+        }
+    }
+}
+
+impl ServerCapabilities {
+    fn list(&self) -> Vec<&'static str> {
+        let mut caps = vec![
+            "body",        // Supports body text
+            "persistence", // Notifications retained until acknowledged
+            "body-markup", // Renders bold/italic styling in body
+        ];
+        if self.images {
+            caps.push("icon-static"); // Displays single-frame notification icons
+        }
+        if self.actions {
+            caps.push("actions"); // Supports action buttons
+            caps.push("action-icons"); // Uses icons for action buttons when hint is set
+            caps.push("inline-reply"); // Reply text field for the inline-reply action
+        }
+        if self.hyperlinks {
+            caps.push("body-hyperlinks"); // Supports clickable links in body
+        }
+        if self.sound {
+            caps.push("sound"); // Plays sound-file and sound-name hints
+        }
+        caps
+    }
+}
+
+/// Urgency level of a notification straight from its D-Bus hints
+fn hints_urgency(hints: &HashMap<&str, zbus::zvariant::Value<'_>>) -> Option<u8> {
+    hints.get("urgency").and_then(|v| u8::try_from(v).ok())
+}
+
+/// Desktop entry the notification will carry once the sender's identity
+/// is applied, as in [`Notification::set_sender_identity`]
+fn rule_desktop_entry<'a>(
+    hints: &'a HashMap<&str, zbus::zvariant::Value<'_>>,
+    identity: Option<&'a SenderIdentity>,
+) -> Option<&'a str> {
+    let app_id = identity.and_then(|i| i.app_id.as_deref());
+    if identity.is_some_and(|i| i.sandboxed) && app_id.is_some() {
+        return app_id;
+    }
+    hints
+        .get("desktop-entry")
+        .and_then(|v| <&str>::try_from(v).ok())
+        .or(app_id)
+}
+
+pub struct Notifications(
+    Sender<Input>,
+    NonZeroU64,
+    Vec<Connection>,
+    RateLimiter,
+    /// Urgent lane for critical notifications
+    Sender<Input>,
+    /// Verified identity of each client connection
+    SenderCache,
+    /// What `GetCapabilities` currently advertises
+    ServerCapabilities,
+    /// Clean-up applied to arriving notifications
+    ContentRules,
+);
 
 #[interface(name = "org.freedesktop.Notifications")]
 impl Notifications {
@@ -427,17 +1241,11 @@ impl Notifications {
     /// "icon-static"	Supports display of exactly 1 frame of any given image array. This value is mutually exclusive with "icon-multi", it is a protocol error for the server to specify both.
     /// "persistence"	The server supports persistence of notifications. Notifications will be retained until they are acknowledged or removed by the user or recalled by the sender. The presence of this capability allows clients to depend on the server to ensure a notification is seen and eliminate the need for the client to display a reminding function (such as a status icon) of its own.
     /// "sound"	The server supports sounds on notifications. If returned, the server must support the "sound-file" and "suppress-sound" hints.
+    /// "inline-reply"	KDE extension: an "inline-reply" action gets a text field, the text is sent back with the NotificationReplied signal.
+    ///
+    /// Optional capabilities are dropped while the config hides them.
     async fn get_capabilities(&self) -> Vec<&'static str> {
-        vec![
-            "body",           // Supports body text
-            "icon-static",    // Displays single-frame notification icons
-            "persistence",    // Notifications retained until acknowledged
-            "actions",        // Supports action buttons
-            "action-icons",   // Uses icons for action buttons when hint is set
-            "body-markup",    // Renders bold/italic styling in body
-            "body-hyperlinks",// Supports clickable links in body
-            "sound",          // Plays sound-file and sound-name hints
-        ]
+        self.6.list()
     }
 
     #[zbus(out_args("name", "vendor", "version", "spec_version"))]
@@ -476,24 +1284,129 @@ impl Notifications {
         actions: Vec<&str>,
         hints: HashMap<&str, zbus::zvariant::Value<'_>>,
         expire_timeout: i32,
+        #[zbus(header)] header: zbus::message::Header<'_>,
+        #[zbus(connection)] conn: &Connection,
     ) -> u32 {
+        let identity = match header.sender() {
+            Some(sender) => self.5.resolve(conn, sender).await,
+            None => None,
+        };
+        // Rate limited - return a non-zero dummy ID without processing.
+        // Use 1 as a safe fallback that won't conflict with active notifications
+        // and doesn't indicate an error (0 in D-Bus spec can trigger retries)
+        self.deliver(
+            app_name,
+            replaces_id,
+            app_icon,
+            summary,
+            body,
+            actions,
+            hints,
+            expire_timeout,
+            identity,
+        )
+        .await
+        .unwrap_or(1)
+    }
+
+    #[zbus(signal)]
+    async fn action_invoked(
+        signal_ctxt: &SignalEmitter<'_>,
+        id: u32,
+        action_key: &str,
+    ) -> zbus::Result<()>;
+
+    #[zbus(signal)]
+    async fn activation_token(
+        signal_ctxt: &SignalEmitter<'_>,
+        id: u32,
+        activation_token: &str,
+    ) -> zbus::Result<()>;
+
+    /// KDE extension sent when the user submits an inline reply
+    ///
+    /// id	UINT32	The ID of the notification replied to.
+    /// text	STRING	The reply the user typed.
+    #[zbus(signal)]
+    async fn notification_replied(
+        signal_ctxt: &SignalEmitter<'_>,
+        id: u32,
+        text: &str,
+    ) -> zbus::Result<()>;
+
+    /// id	UINT32	The ID of the notification that was closed.
+    /// reason	UINT32
+    ///
+    /// The reason the notification was closed.
+    ///
+    /// 1 - The notification expired.
+    ///
+    /// 2 - The notification was dismissed by the user.
+    ///
+    /// 3 - The notification was closed by a call to CloseNotification.
+    ///
+    /// 4 - Undefined/reserved reasons.
+    #[zbus(signal)]
+    async fn notification_closed(
+        signal_ctxt: &SignalEmitter<'_>,
+        id: u32,
+        reason: u32,
+    ) -> zbus::Result<()>;
+}
+
+impl Notifications {
+    /// Close a notification on behalf of the portal backend
+    pub(crate) async fn close(&self, id: u32) {
+        if let Err(err) = self.0.send(Input::CloseNotification(id)).await {
+            tracing::error!("Failed to send close notification: {}", err);
+        }
+    }
+
+    /// Assign an id to a notification and hand it to the app and applets
+    ///
+    /// Shared by `Notify` and the portal backend. Returns `None` when the
+    /// sender is rate limited.
+    #[allow(clippy::too_many_arguments)]
+    pub(crate) async fn deliver(
+        &mut self,
+        app_name: &str,
+        replaces_id: u32,
+        app_icon: &str,
+        summary: &str,
+        body: &str,
+        actions: Vec<&str>,
+        hints: HashMap<&str, zbus::zvariant::Value<'_>>,
+        expire_timeout: i32,
+        identity: Option<SenderIdentity>,
+    ) -> Option<u32> {
+        // Key rate limiting on the verified app so renaming doesn't reset it
+        let rate_key = identity
+            .as_ref()
+            .and_then(|i| i.app_id.as_deref())
+            .unwrap_or(app_name)
+            .to_string();
+
         // Periodic cleanup of rate limiter to prevent memory growth
         // Only cleanup periodically to avoid overhead
         if self.1.get() % RATE_LIMIT_CLEANUP_INTERVAL == 0 {
             self.3.cleanup();
         }
 
+        // Critical notifications have a rate limit of their own
+        let critical = hints_urgency(&hints) == Some(2);
+
         // Check rate limit for new notifications (not replacements)
-        if replaces_id == 0 && !self.3.check_and_update(app_name) {
-            // Rate limited - return a non-zero dummy ID without processing.
-            // Use 1 as a safe fallback that won't conflict with active notifications
-            // and doesn't indicate an error (0 in D-Bus spec can trigger retries)
-            let dummy_id = 1u32;
-            tracing::debug!(
-                "Notification from '{}' rejected due to rate limiting, returning dummy ID {}",
-                app_name, dummy_id
-            );
-            return dummy_id;
+        if replaces_id == 0 {
+            let desktop_entry = rule_desktop_entry(&hints, identity.as_ref());
+            let allowed = if critical {
+                self.3.check_critical(&rate_key, app_name, desktop_entry)
+            } else {
+                self.3.check_and_update(&rate_key, app_name, desktop_entry)
+            };
+            if !allowed {
+                tracing::debug!("Notification from '{}' rejected due to rate limiting", app_name);
+                return None;
+            }
         }
 
         let id = if replaces_id == 0 {
@@ -518,7 +1431,7 @@ impl Notifications {
             .iter()
             .filter_map(|(k, v)| Some((*k, v.try_clone().ok()?)))
             .collect();
-        let n = Notification::new(
+        let mut n = Notification::new(
             app_name,
             id,
             app_icon,
@@ -528,8 +1441,58 @@ impl Notifications {
             hints_clone,
             expire_timeout,
         );
+        if let Some(identity) = identity {
+            n.set_sender_identity(identity.pid, identity.app_id.as_deref(), identity.sandboxed);
+        }
+        self.7.apply(&mut n);
+        #[cfg(feature = "plugins")]
+        let (n, action) = self.7.run_plugins(n).await;
+        // Only the app hears of a new notification a plugin dropped, to
+        // audit it; an update is handled like one sent to history
+        #[cfg(feature = "plugins")]
+        if action == PluginAction::Drop && replaces_id == 0 {
+            for input in [
+                Input::DroppedByPlugin(n),
+                Input::Closed(id, CloseReason::Undefined),
+            ] {
+                if let Err(err) = self.0.send(input).await {
+                    tracing::error!("Failed to send dropped notification: {}", err);
+                }
+            }
+            return Some(id);
+        }
+        // Applets get the text as cleaned up, and masked or withheld, like
+        // every other listener
+        let withheld = self.7.withholds(&n);
+        let shared = self.7.shared(&n);
+        let (app_name, summary, body) = (
+            shared.app_name.clone(),
+            shared.summary.clone(),
+            shared.body.clone(),
+        );
+        let actions = if withheld { Vec::new() } else { actions };
+        let transient = n.transient();
+        let mut input = Some(match replaces_id {
+            #[cfg(feature = "plugins")]
+            _ if action != PluginAction::Show => Input::Quieted {
+                notification: n,
+                shared,
+                replace: replaces_id != 0,
+            },
+            0 => Input::Notification(n, shared),
+            _ => Input::Replace(n, shared),
+        });
+
+        // Show critical notifications before spending time on applet forwarding
+        if takes_urgent_lane(replaces_id, critical) {
+            if let Some(input) = input.take() {
+                if let Err(err) = self.4.send(input).await {
+                    tracing::error!("Failed to send critical notification: {}", err);
+                }
+            }
+        }
 
-        if !n.transient() {
+        if !transient {
             let mut new_conns = Vec::with_capacity(self.2.len());
             for c in self.2.drain(..) {
                 let object_server = c.object_server();
@@ -544,17 +1507,18 @@ impl Notifications {
                 };
                 let hints_clone = hints
                     .iter()
+                    .filter(|(k, _)| !withheld || withheld_hint(k))
                     .filter_map(|(k, v)| Some((*k, v.try_clone().ok()?)))
                     .collect();
                 match tokio::time::timeout(
                     tokio::time::Duration::from_millis(500),
                     NotificationsApplet::notify(
                         iface_ref.signal_emitter(),
-                        app_name,
+                        &app_name,
                         id,
                         app_icon,
-                        summary,
-                        body,
+                        &summary,
+                        &body,
                         actions.clone(),
                         hints_clone,
                         expire_timeout,
@@ -571,67 +1535,164 @@ impl Notifications {
             self.2 = new_conns;
         }
 
-        if let Err(err) = self
-            .0
-            .send(if replaces_id == 0 {
-                Input::Notification(n)
-            } else {
-                Input::Replace(n)
-            })
-            .await
-        {
-            tracing::error!("Failed to send notification: {}", err);
+        if let Some(input) = input {
+            if let Err(err) = self.0.send(input).await {
+                tracing::error!("Failed to send notification: {}", err);
+            }
         }
 
-        id
+        Some(id)
     }
-
-    #[zbus(signal)]
-    async fn action_invoked(
-        signal_ctxt: &SignalEmitter<'_>,
-        id: u32,
-        action_key: &str,
-    ) -> zbus::Result<()>;
-
-    #[zbus(signal)]
-    async fn activation_token(
-        signal_ctxt: &SignalEmitter<'_>,
-        id: u32,
-        activation_token: &str,
-    ) -> zbus::Result<()>;
-
-    /// id	UINT32	The ID of the notification that was closed.
-    /// reason	UINT32
-    ///
-    /// The reason the notification was closed.
-    ///
-    /// 1 - The notification expired.
-    ///
-    /// 2 - The notification was dismissed by the user.
-    ///
-    /// 3 - The notification was closed by a call to CloseNotification.
-    ///
-    /// 4 - Undefined/reserved reasons.
-    #[zbus(signal)]
-    async fn notification_closed(
-        signal_ctxt: &SignalEmitter<'_>,
-        id: u32,
-        reason: u32,
-    ) -> zbus::Result<()>;
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use cosmic_ext_notifications_util::Hint;
+    use std::time::SystemTime;
+    use tokio::sync::mpsc::error::TrySendError;
+
+    #[test]
+    fn test_capabilities_follow_config() {
+        let all = ServerCapabilities::default().list();
+        assert!(all.contains(&"actions"));
+        assert!(all.contains(&"icon-static"));
+        assert_eq!(all.contains(&"sound"), cfg!(feature = "audio"));
+
+        let plain = ServerCapabilities {
+            images: false,
+            actions: false,
+            hyperlinks: false,
+            sound: false,
+        }
+        .list();
+        assert_eq!(plain, vec!["body", "persistence", "body-markup"]);
+    }
+
+    fn test_notification(id: u32, urgency: u8) -> Notification {
+        Notification {
+            id,
+            app_name: "test_app".to_string(),
+            app_icon: String::new(),
+            summary: "Summary".to_string(),
+            body: String::new(),
+            actions: vec![],
+            hints: vec![Hint::Urgency(urgency)],
+            expire_timeout: -1,
+            applied_timeout: None,
+            time: SystemTime::now(),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_urgent_lane_skips_backlog() {
+        let (tx, mut rx) = channel(CHANNEL_BUFFER_SIZE);
+        let (urgent_tx, mut urgent_rx) = channel(URGENT_CHANNEL_BUFFER_SIZE);
+
+        // Burst of normal notifications fills the regular queue
+        for id in 1..=CHANNEL_BUFFER_SIZE as u32 {
+            let n = test_notification(id, 1);
+            tx.send(Input::Notification(n.clone(), n))
+                .await
+                .unwrap();
+        }
+
+        // The regular queue is full, yet a critical notification is accepted
+        let n = test_notification(1000, 1);
+        assert!(matches!(
+            tx.try_send(Input::Notification(n.clone(), n)),
+            Err(TrySendError::Full(_))
+        ));
+        let n = test_notification(999, 2);
+        assert!(urgent_tx.try_send(Input::Notification(n.clone(), n)).is_ok());
+        drop(tx);
+        drop(urgent_tx);
+
+        // and is the very next input, ahead of the whole backlog
+        let next = next_input(&mut urgent_rx, &mut rx).await;
+        assert!(matches!(next, Some(Input::Notification(ref n, _)) if n.id == 999));
+
+        let mut order = vec![999];
+        while let Some(input) = next_input(&mut urgent_rx, &mut rx).await {
+            if let Input::Notification(n, _) = input {
+                order.push(n.id);
+            }
+        }
+
+        // Then the regular queue in order
+        let mut expected = vec![999];
+        expected.extend(1..=CHANNEL_BUFFER_SIZE as u32);
+        assert_eq!(order, expected);
+    }
+
+    #[test]
+    fn test_replacements_stay_on_regular_lane() {
+        assert!(takes_urgent_lane(0, true));
+        assert!(!takes_urgent_lane(0, false));
+        // A critical update must not overtake the notification it replaces
+        assert!(!takes_urgent_lane(7, true));
+    }
+
+    #[tokio::test]
+    async fn test_regular_lane_still_served() {
+        let (tx, mut rx) = channel(CHANNEL_BUFFER_SIZE);
+        let (_urgent_tx, mut urgent_rx) = channel::<Input>(URGENT_CHANNEL_BUFFER_SIZE);
+
+        let n = test_notification(1, 1);
+        tx.send(Input::Notification(n.clone(), n)).await.unwrap();
+        let next = next_input(&mut urgent_rx, &mut rx).await;
+        assert!(matches!(next, Some(Input::Notification(ref n, _)) if n.id == 1));
+
+        // Closing the regular lane ends the stream
+        drop(tx);
+        assert!(next_input(&mut urgent_rx, &mut rx).await.is_none());
+    }
+
+    #[test]
+    fn test_listeners_see_masked_or_withheld_content() {
+        let mut config = NotificationsConfig::default();
+        config.app_rules.push(cosmic_ext_notifications_config::AppRule {
+            app_name: "Signal".to_string(),
+            sensitive: true,
+            ..Default::default()
+        });
+        let mut n = test_notification(1, 1);
+        n.body = "Your verification code is 482913".to_string();
+
+        let rules = ContentRules::from_config(&config, false);
+        let shared = rules.shared(&n);
+        assert!(!shared.body.contains("482913"));
+        assert_eq!(shared.summary, "Summary");
+
+        // Hidden content only says which app it's from
+        let locked = ContentRules::from_config(&config, true);
+        let shared = locked.shared(&n);
+        assert_ne!(shared.summary, "Summary");
+        assert!(shared.body.is_empty());
+
+        n.app_name = "Signal".to_string();
+        assert!(rules.withholds(&n));
+        assert!(rules.shared(&n).body.is_empty());
+    }
+
+    #[test]
+    fn test_hints_urgency() {
+        let mut hints = HashMap::new();
+        assert_eq!(hints_urgency(&hints), None);
+        hints.insert("urgency", zbus::zvariant::Value::U8(2));
+        assert_eq!(hints_urgency(&hints), Some(2));
+    }
+
+    const BURST: u32 = 20;
 
     #[test]
     fn test_rate_limiter_allows_under_limit() {
         let mut limiter = RateLimiter::new();
 
-        // Should allow first 60 notifications
-        for i in 1..=60 {
+        // Should allow a full burst
+        for i in 1..=BURST {
             assert!(
-                limiter.check_and_update("test_app"),
+                limiter.check_and_update("test_app", "test_app", None),
                 "Notification {} should be allowed",
                 i
             );
@@ -642,84 +1703,160 @@ mod tests {
     fn test_rate_limiter_blocks_over_limit() {
         let mut limiter = RateLimiter::new();
 
-        // Fill up to the limit
-        for _ in 1..=60 {
-            limiter.check_and_update("test_app");
+        // Empty the bucket
+        for _ in 1..=BURST {
+            limiter.check_and_update("test_app", "test_app", None);
         }
 
-        // 61st should be blocked
+        // The next one should be blocked, and counted
         assert!(
-            !limiter.check_and_update("test_app"),
+            !limiter.check_and_update("test_app", "test_app", None),
             "Notification over limit should be blocked"
         );
+        assert!(!limiter.check_and_update("test_app", "test_app", None));
+        assert_eq!(limiter.take_dropped(), vec![("test_app".to_string(), 2)]);
+        assert!(limiter.take_dropped().is_empty());
     }
 
     #[test]
-    fn test_rate_limiter_resets_after_window() {
+    fn test_rate_limiter_refills_over_time() {
         let mut limiter = RateLimiter::new();
 
-        // Fill up to the limit
-        for _ in 1..=60 {
-            limiter.check_and_update("test_app");
+        // Empty the bucket
+        for _ in 1..=BURST {
+            limiter.check_and_update("test_app", "test_app", None);
         }
+        assert!(!limiter.check_and_update("test_app", "test_app", None));
 
-        // Manually advance time by modifying the entry
-        if let Some(entry) = limiter.limits.get_mut("test_app") {
-            entry.0 = Instant::now() - Duration::from_secs(61);
+        // At 60 a minute, two seconds buy two more notifications
+        if let Some(bucket) = limiter.buckets.get_mut("test_app") {
+            bucket.refilled -= Duration::from_millis(2_100);
         }
-
-        // Should allow again after window expires
-        assert!(
-            limiter.check_and_update("test_app"),
-            "Should allow after time window expires"
-        );
+        assert!(limiter.check_and_update("test_app", "test_app", None), "Should allow after refilling");
+        assert!(limiter.check_and_update("test_app", "test_app", None));
+        assert!(!limiter.check_and_update("test_app", "test_app", None));
     }
 
     #[test]
     fn test_rate_limiter_per_app_isolation() {
         let mut limiter = RateLimiter::new();
 
-        // Fill up limit for app1
-        for _ in 1..=60 {
-            limiter.check_and_update("app1");
+        // Empty the bucket of app1
+        for _ in 1..=BURST {
+            limiter.check_and_update("app1", "app1", None);
         }
 
         // app1 should be blocked
         assert!(
-            !limiter.check_and_update("app1"),
+            !limiter.check_and_update("app1", "app1", None),
             "app1 should be rate limited"
         );
 
         // app2 should still be allowed
         assert!(
-            limiter.check_and_update("app2"),
+            limiter.check_and_update("app2", "app2", None),
             "app2 should not be affected by app1's rate limit"
         );
     }
 
+    #[test]
+    fn test_rate_limiter_critical_bucket() {
+        let mut limiter = RateLimiter::new();
+
+        // Normal notifications use up their own bucket only
+        for _ in 0..BURST {
+            assert!(limiter.check_and_update("test_app", "test_app", None));
+        }
+        assert!(!limiter.check_and_update("test_app", "test_app", None));
+        assert!(limiter.check_critical("test_app", "test_app", None));
+
+        // Critical ones get a larger bucket, but are still limited
+        for _ in 1..BURST * CRITICAL_RATE_LIMIT_FACTOR {
+            assert!(limiter.check_critical("test_app", "test_app", None));
+        }
+        assert!(!limiter.check_critical("test_app", "test_app", None));
+    }
+
+    #[test]
+    fn test_rate_limiter_app_rule_overrides() {
+        let mut config = NotificationsConfig::default();
+        config.app_rules.push(cosmic_ext_notifications_config::AppRule {
+            app_name: "noisy".to_string(),
+            rate_limit_burst: Some(2),
+            ..Default::default()
+        });
+        config.app_rules.push(cosmic_ext_notifications_config::AppRule {
+            app_name: "trusted".to_string(),
+            rate_limit_per_minute: Some(0),
+            ..Default::default()
+        });
+        let mut limiter = RateLimiter::new();
+        limiter.limits = RateLimits::from_config(&config);
+
+        assert!(limiter.check_and_update("noisy", "noisy", None));
+        assert!(limiter.check_and_update("noisy", "noisy", None));
+        assert!(!limiter.check_and_update("noisy", "noisy", None));
+
+        // Unlimited apps are never tracked
+        for _ in 0..=BURST {
+            assert!(limiter.check_and_update("trusted", "trusted", None));
+        }
+        assert!(!limiter.buckets.contains_key("trusted"));
+    }
+
+    #[test]
+    fn test_rate_limiter_rule_matches_verified_sender() {
+        let mut config = NotificationsConfig::default();
+        config.app_rules.push(cosmic_ext_notifications_config::AppRule {
+            app_name: "Chat".to_string(),
+            desktop_entry: Some("org.example.Chat".to_string()),
+            rate_limit_burst: Some(2),
+            ..Default::default()
+        });
+        let mut limiter = RateLimiter::new();
+        limiter.limits = RateLimits::from_config(&config);
+
+        // The rule matches by desktop entry, whatever name the app sends
+        assert!(limiter.check_and_update("org.example.Chat", "Chat 2.0", Some("org.example.Chat")));
+        assert!(limiter.check_and_update("org.example.Chat", "Chat", Some("org.example.Chat")));
+        assert!(!limiter.check_and_update("org.example.Chat", "Chat", Some("org.example.Chat")));
+        assert!(limiter.buckets.contains_key("org.example.Chat"));
+
+        // A rule matching by name only still applies to a verified sender
+        let mut config = NotificationsConfig::default();
+        config.app_rules.push(cosmic_ext_notifications_config::AppRule {
+            app_name: "Mail".to_string(),
+            rate_limit_burst: Some(1),
+            ..Default::default()
+        });
+        limiter.limits = RateLimits::from_config(&config);
+        assert!(limiter.check_and_update("org.example.Mail", "Mail", Some("org.example.Mail")));
+        assert!(!limiter.check_and_update("org.example.Mail", "Mail", Some("org.example.Mail")));
+    }
+
     #[test]
     fn test_rate_limiter_cleanup() {
         let mut limiter = RateLimiter::new();
 
         // Add entries for multiple apps
-        limiter.check_and_update("app1");
-        limiter.check_and_update("app2");
-        limiter.check_and_update("app3");
+        limiter.check_and_update("app1", "app1", None);
+        limiter.check_and_update("app2", "app2", None);
+        limiter.check_and_update("app3", "app3", None);
 
-        assert_eq!(limiter.limits.len(), 3, "Should have 3 apps tracked");
+        assert_eq!(limiter.buckets.len(), 3, "Should have 3 apps tracked");
 
         // Manually age the entries
-        for (_, entry) in limiter.limits.iter_mut() {
-            entry.0 = Instant::now() - Duration::from_secs(61);
+        for (_, bucket) in limiter.buckets.iter_mut() {
+            bucket.refilled -= Duration::from_secs(61);
         }
 
-        // Cleanup should remove old entries
+        // Cleanup should remove full buckets
         limiter.cleanup();
 
         assert_eq!(
-            limiter.limits.len(),
+            limiter.buckets.len(),
             0,
-            "Cleanup should remove expired entries"
+            "Cleanup should remove refilled buckets"
         );
     }
 
@@ -728,17 +1865,17 @@ mod tests {
         let mut limiter = RateLimiter::new();
 
         // Empty app names should still be rate limited
-        for i in 1..=60 {
+        for i in 1..=BURST {
             assert!(
-                limiter.check_and_update(""),
+                limiter.check_and_update("", "", None),
                 "Empty app name notification {} should be allowed",
                 i
             );
         }
 
         assert!(
-            !limiter.check_and_update(""),
-            "Empty app name should be rate limited after 60"
+            !limiter.check_and_update("", "", None),
+            "Empty app name should be rate limited after a burst"
         );
     }
 }
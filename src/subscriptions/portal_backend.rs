@@ -0,0 +1,569 @@
+//! Backend for the `org.freedesktop.impl.portal.Notification` portal
+//!
+//! Sandboxed apps notify through xdg-desktop-portal, which forwards each
+//! notification to the backend named in the desktop's `portals.conf`. Serving
+//! it here gives Flatpak apps the same cards, actions and icons as apps that
+//! talk to `org.freedesktop.Notifications` directly: portal notifications are
+//! translated to the classic shape and go through the same delivery path.
+
+use super::notifications::Notifications;
+use crate::constants::{PORTAL_ICON_MAX_BYTES, PORTAL_MAX_TRACKED};
+use cosmic_ext_notifications_util::NotificationImage;
+use std::{
+    collections::HashMap,
+    io::Read,
+    os::fd::AsFd,
+    sync::{Mutex, MutexGuard, PoisonError},
+};
+use zbus::{
+    Connection, interface,
+    object_server::{ObjectServer, SignalEmitter},
+    zvariant::{OwnedValue, Structure, Value},
+};
+
+/// Well-known name the backend is served under, see the `.portal` file
+pub const PORTAL_BUS_NAME: &str = "org.freedesktop.impl.portal.desktop.cosmic_ext_notifications";
+const PORTAL_PATH: &str = "/org/freedesktop/portal/desktop";
+const NOTIFICATIONS_PATH: &str = "/org/freedesktop/Notifications";
+
+/// Serve the portal backend on the daemon's connection
+///
+/// The portal frontend only uses it when `portals.conf` points here, so
+/// failing to get the name is not fatal.
+pub async fn serve(conn: &Connection) -> zbus::Result<()> {
+    conn.object_server()
+        .at(PORTAL_PATH, PortalNotifications::default())
+        .await?;
+    conn.request_name(PORTAL_BUS_NAME).await
+}
+
+/// Send `ActionInvoked` for a portal notification
+///
+/// Returns false if `id` did not come from the portal, so the classic
+/// signals should be sent instead.
+pub async fn action_invoked(conn: &Connection, id: u32, action: &str, token: &str) -> bool {
+    let Ok(iface_ref) = conn
+        .object_server()
+        .interface::<_, PortalNotifications>(PORTAL_PATH)
+        .await
+    else {
+        return false;
+    };
+    // Copied out, so the lock isn't held while the signal goes out
+    let (app_id, portal_id, action, target) = {
+        let iface = iface_ref.get().await;
+        let tracking = iface.tracking();
+        let Some(tracked) = tracking.tracked.get(&id) else {
+            return false;
+        };
+        let (action, target) = tracked.resolve(action);
+        (
+            tracked.app_id.clone(),
+            tracked.id.clone(),
+            action.to_string(),
+            target.and_then(|t| t.try_clone().ok()),
+        )
+    };
+
+    let mut parameter = Vec::with_capacity(2);
+    if let Some(target) = target {
+        parameter.push(Value::from(target));
+    }
+    // Platform data, so the app can raise itself under Wayland
+    let mut platform_data = HashMap::new();
+    if !token.is_empty() {
+        platform_data.insert("activation-token", Value::from(token));
+    }
+    parameter.push(Value::from(platform_data));
+
+    if let Err(err) = PortalNotifications::action_invoked(
+        iface_ref.signal_emitter(),
+        &app_id,
+        &portal_id,
+        &action,
+        parameter,
+    )
+    .await
+    {
+        tracing::error!("Failed to signal portal action for {id}: {err}");
+    }
+    true
+}
+
+/// Stop tracking a portal notification once the daemon closed it
+pub async fn forget(conn: &Connection, id: u32) {
+    if let Ok(iface_ref) = conn
+        .object_server()
+        .interface::<_, PortalNotifications>(PORTAL_PATH)
+        .await
+    {
+        iface_ref.get().await.tracking().untrack(id);
+    }
+}
+
+/// A portal notification currently known to the daemon
+#[derive(Debug)]
+struct Tracked {
+    app_id: String,
+    id: String,
+    /// Portal action behind the classic "default" action
+    default_action: Option<String>,
+    default_target: Option<OwnedValue>,
+    /// Targets of the buttons, by action name
+    targets: HashMap<String, OwnedValue>,
+}
+
+impl Tracked {
+    /// Map a classic action key back to the portal action and its target
+    fn resolve<'a>(&'a self, action: &'a str) -> (&'a str, Option<&'a OwnedValue>) {
+        if action == "default" {
+            if let Some(default) = self.default_action.as_deref() {
+                return (default, self.default_target.as_ref());
+            }
+        }
+        (action, self.targets.get(action))
+    }
+}
+
+/// The portal notifications the daemon shows
+#[derive(Debug, Default)]
+struct Tracking {
+    /// Daemon id by (app id, portal id)
+    ids: HashMap<(String, String), u32>,
+    tracked: HashMap<u32, Tracked>,
+}
+
+impl Tracking {
+    fn track(&mut self, daemon_id: u32, tracked: Tracked) {
+        self.ids
+            .insert((tracked.app_id.clone(), tracked.id.clone()), daemon_id);
+        self.tracked.insert(daemon_id, tracked);
+
+        // Ids only grow, so the smallest belongs to the oldest notification
+        if self.tracked.len() > PORTAL_MAX_TRACKED {
+            if let Some(&oldest) = self.tracked.keys().min() {
+                self.untrack(oldest);
+            }
+        }
+    }
+
+    fn untrack(&mut self, daemon_id: u32) {
+        if let Some(tracked) = self.tracked.remove(&daemon_id) {
+            self.ids.remove(&(tracked.app_id, tracked.id));
+        }
+    }
+}
+
+/// The portal backend
+///
+/// Its methods take `&self` and keep their state behind a mutex that is
+/// never held across an await: delivering can wait on the daemon's input
+/// queue, whose consumer untracks closed notifications through [`forget`].
+#[derive(Debug, Default)]
+pub struct PortalNotifications {
+    tracking: Mutex<Tracking>,
+}
+
+impl PortalNotifications {
+    fn tracking(&self) -> MutexGuard<'_, Tracking> {
+        self.tracking.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+}
+
+#[interface(name = "org.freedesktop.impl.portal.Notification")]
+impl PortalNotifications {
+    /// Show a notification, replacing one with the same app id and id
+    async fn add_notification(
+        &self,
+        app_id: &str,
+        id: &str,
+        notification: HashMap<&str, OwnedValue>,
+        #[zbus(object_server)] server: &ObjectServer,
+    ) -> zbus::fdo::Result<()> {
+        let parsed = PortalNotification::parse(&notification);
+        let replaces_id = self
+            .tracking()
+            .ids
+            .get(&(app_id.to_string(), id.to_string()))
+            .copied()
+            .unwrap_or(0);
+
+        let mut actions = Vec::with_capacity(parsed.buttons.len() * 2 + 2);
+        if parsed.default_action.is_some() {
+            actions.extend(["default", ""]);
+        }
+        for button in &parsed.buttons {
+            actions.extend([button.action.as_str(), button.label.as_str()]);
+        }
+
+        let iface_ref = server
+            .interface::<_, Notifications>(NOTIFICATIONS_PATH)
+            .await?;
+        let daemon_id = iface_ref
+            .get_mut()
+            .await
+            .deliver(
+                app_id,
+                replaces_id,
+                "",
+                &parsed.title,
+                &parsed.body,
+                actions,
+                parsed.hints(app_id),
+                -1,
+                None,
+            )
+            .await;
+        let Some(daemon_id) = daemon_id else {
+            tracing::debug!("Portal notification {id} from {app_id} was rate limited");
+            return Ok(());
+        };
+
+        let targets = parsed
+            .buttons
+            .into_iter()
+            .filter_map(|b| Some((b.action, b.target?)))
+            .collect();
+        self.tracking().track(
+            daemon_id,
+            Tracked {
+                app_id: app_id.to_string(),
+                id: id.to_string(),
+                default_action: parsed.default_action,
+                default_target: parsed.default_target,
+                targets,
+            },
+        );
+        Ok(())
+    }
+
+    /// Withdraw a notification the app no longer wants shown
+    async fn remove_notification(
+        &self,
+        app_id: &str,
+        id: &str,
+        #[zbus(object_server)] server: &ObjectServer,
+    ) -> zbus::fdo::Result<()> {
+        let daemon_id = {
+            let mut tracking = self.tracking();
+            let Some(daemon_id) = tracking.ids.get(&(app_id.to_string(), id.to_string())).copied() else {
+                return Ok(());
+            };
+            tracking.untrack(daemon_id);
+            daemon_id
+        };
+
+        let iface_ref = server
+            .interface::<_, Notifications>(NOTIFICATIONS_PATH)
+            .await?;
+        iface_ref.get().await.close(daemon_id).await;
+        Ok(())
+    }
+
+    #[zbus(property)]
+    async fn supported_options(&self) -> HashMap<String, OwnedValue> {
+        HashMap::new()
+    }
+
+    #[zbus(property, name = "version")]
+    async fn version(&self) -> u32 {
+        2
+    }
+
+    /// app_id	STRING	The app that sent the notification.
+    /// id	STRING	The app's id for the notification.
+    /// action	STRING	The action that was invoked.
+    /// parameter	av	The action's target if it has one, then a dictionary of
+    /// platform data holding the activation token.
+    #[zbus(signal)]
+    async fn action_invoked(
+        signal_ctxt: &SignalEmitter<'_>,
+        app_id: &str,
+        id: &str,
+        action: &str,
+        parameter: Vec<Value<'_>>,
+    ) -> zbus::Result<()>;
+}
+
+/// A button on a portal notification
+#[derive(Debug, PartialEq)]
+struct Button {
+    label: String,
+    action: String,
+    target: Option<OwnedValue>,
+}
+
+/// The icon of a portal notification, a serialized `GIcon`
+#[derive(Debug, PartialEq)]
+enum PortalIcon {
+    /// Icon names, most specific first
+    Themed(String),
+    /// A file:// URI
+    File(String),
+    /// Encoded image data (PNG, JPEG, ...)
+    Bytes(Vec<u8>),
+}
+
+/// The fields of a portal notification the daemon uses
+#[derive(Debug, Default, PartialEq)]
+struct PortalNotification {
+    title: String,
+    body: String,
+    icon: Option<PortalIcon>,
+    urgency: u8,
+    category: Option<String>,
+    default_action: Option<String>,
+    default_target: Option<OwnedValue>,
+    buttons: Vec<Button>,
+    silent: bool,
+    transient: bool,
+}
+
+impl PortalNotification {
+    fn parse(notification: &HashMap<&str, OwnedValue>) -> Self {
+        let string = |key: &str| {
+            notification
+                .get(key)
+                .and_then(|v| <&str>::try_from(&**v).ok())
+                .map(str::to_string)
+        };
+
+        let urgency = match string("priority").as_deref() {
+            Some("low") => 0,
+            Some("urgent") => 2,
+            _ => 1,
+        };
+        let transient = match notification.get("display-hint").map(|v| &**v) {
+            Some(Value::Array(hints)) => hints
+                .iter()
+                .any(|h| <&str>::try_from(inner(h)) == Ok("transient")),
+            _ => false,
+        };
+
+        Self {
+            title: string("title").unwrap_or_default(),
+            // The markup subset the portal allows is a subset of ours
+            body: string("markup-body")
+                .or_else(|| string("body"))
+                .unwrap_or_default(),
+            icon: notification.get("icon").and_then(|v| parse_icon(v)),
+            urgency,
+            category: string("category"),
+            default_action: string("default-action"),
+            default_target: notification
+                .get("default-action-target")
+                .and_then(|v| v.try_clone().ok()),
+            buttons: notification
+                .get("buttons")
+                .map(|v| parse_buttons(v))
+                .unwrap_or_default(),
+            silent: string("sound").as_deref() == Some("silent"),
+            transient,
+        }
+    }
+
+    /// Classic hints carrying what the portal notification asked for
+    fn hints(&self, app_id: &str) -> HashMap<&'static str, Value<'static>> {
+        let mut hints = HashMap::new();
+        // The frontend verified the app id, so it is the sender's identity
+        hints.insert("desktop-entry", Value::from(app_id.to_string()));
+        hints.insert("urgency", Value::U8(self.urgency));
+        if let Some(category) = &self.category {
+            hints.insert("category", Value::from(category.clone()));
+        }
+        if self.silent {
+            hints.insert("suppress-sound", Value::from(true));
+        }
+        if self.transient {
+            hints.insert("transient", Value::from(true));
+        }
+        match &self.icon {
+            Some(PortalIcon::Themed(name)) => {
+                hints.insert("image-path", Value::from(name.clone()));
+            }
+            Some(PortalIcon::File(uri)) => {
+                hints.insert("image-path", Value::from(uri.clone()));
+            }
+            Some(PortalIcon::Bytes(bytes)) => match NotificationImage::from_bytes(bytes) {
+                Ok(image) => {
+                    let rowstride = image.width as i32 * 4;
+                    hints.insert(
+                        "image-data",
+                        Value::from(Structure::from((
+                            image.width as i32,
+                            image.height as i32,
+                            rowstride,
+                            true,
+                            8i32,
+                            4i32,
+                            image.data,
+                        ))),
+                    );
+                }
+                Err(err) => tracing::warn!("Failed to decode portal notification icon: {err}"),
+            },
+            None => {}
+        }
+        hints
+    }
+}
+
+/// Look through the variant wrapping values in nested `a{sv}` and `(sv)`
+fn inner<'a>(value: &'a Value<'a>) -> &'a Value<'a> {
+    match value {
+        Value::Value(value) => value,
+        value => value,
+    }
+}
+
+fn parse_icon(value: &Value<'_>) -> Option<PortalIcon> {
+    let Value::Structure(icon) = value else {
+        return None;
+    };
+    let [Value::Str(kind), data] = icon.fields() else {
+        return None;
+    };
+
+    match (kind.as_str(), inner(data)) {
+        ("themed", Value::Array(names)) => names.iter().find_map(|name| match inner(name) {
+            Value::Str(name) => Some(PortalIcon::Themed(name.to_string())),
+            _ => None,
+        }),
+        ("file", Value::Str(uri)) => Some(PortalIcon::File(uri.to_string())),
+        ("bytes", Value::Array(bytes)) => bytes
+            .iter()
+            .map(|b| u8::try_from(inner(b)).ok())
+            .collect::<Option<Vec<u8>>>()
+            .map(PortalIcon::Bytes),
+        // Newer frontends hand icon bytes over as a sealed memfd
+        ("file-descriptor", Value::Fd(fd)) => {
+            let file = std::fs::File::from(fd.as_fd().try_clone_to_owned().ok()?);
+            let mut bytes = Vec::new();
+            file.take(PORTAL_ICON_MAX_BYTES)
+                .read_to_end(&mut bytes)
+                .ok()?;
+            Some(PortalIcon::Bytes(bytes))
+        }
+        _ => None,
+    }
+}
+
+fn parse_buttons(value: &Value<'_>) -> Vec<Button> {
+    let Value::Array(buttons) = value else {
+        return Vec::new();
+    };
+
+    buttons
+        .iter()
+        .filter_map(|button| {
+            let Value::Dict(button) = button else {
+                return None;
+            };
+            let fields: HashMap<&str, &Value<'_>> = button
+                .iter()
+                .filter_map(|(k, v)| Some((<&str>::try_from(k).ok()?, inner(v))))
+                .collect();
+            let string = |key: &str| match fields.get(key) {
+                Some(Value::Str(s)) => Some(s.to_string()),
+                _ => None,
+            };
+            Some(Button {
+                label: string("label")?,
+                action: string("action")?,
+                target: fields
+                    .get("target")
+                    .and_then(|v| OwnedValue::try_from(v.try_clone().ok()?).ok()),
+            })
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn owned(value: Value<'_>) -> OwnedValue {
+        OwnedValue::try_from(value).unwrap()
+    }
+
+    #[test]
+    fn test_parse_portal_notification() {
+        let button = HashMap::from([
+            ("label", Value::from("Reply")),
+            ("action", Value::from("app.reply")),
+            ("target", Value::from(7u32)),
+        ]);
+        let icon = Structure::from(("themed", Value::from(vec!["mail-unread", "mail"])));
+        let notification = HashMap::from([
+            ("title", owned(Value::from("New mail"))),
+            ("body", owned(Value::from("plain"))),
+            ("markup-body", owned(Value::from("<b>bold</b>"))),
+            ("priority", owned(Value::from("urgent"))),
+            ("category", owned(Value::from("email.arrived"))),
+            ("default-action", owned(Value::from("app.open"))),
+            ("icon", owned(Value::from(icon))),
+            ("buttons", owned(Value::from(vec![button]))),
+            ("display-hint", owned(Value::from(vec!["transient"]))),
+        ]);
+
+        let parsed = PortalNotification::parse(&notification);
+        assert_eq!(parsed.title, "New mail");
+        assert_eq!(parsed.body, "<b>bold</b>");
+        assert_eq!(parsed.urgency, 2);
+        assert_eq!(parsed.category.as_deref(), Some("email.arrived"));
+        assert_eq!(parsed.default_action.as_deref(), Some("app.open"));
+        assert_eq!(parsed.icon, Some(PortalIcon::Themed("mail-unread".to_string())));
+        assert!(parsed.transient);
+        assert_eq!(parsed.buttons.len(), 1);
+        assert_eq!(parsed.buttons[0].label, "Reply");
+        assert_eq!(parsed.buttons[0].action, "app.reply");
+        assert_eq!(parsed.buttons[0].target, Some(owned(Value::from(7u32))));
+
+        let hints = parsed.hints("org.gnome.Evolution");
+        assert_eq!(
+            hints.get("desktop-entry").and_then(|v| <&str>::try_from(v).ok()),
+            Some("org.gnome.Evolution")
+        );
+        assert_eq!(hints.get("urgency"), Some(&Value::U8(2)));
+        assert_eq!(
+            hints.get("image-path").and_then(|v| <&str>::try_from(v).ok()),
+            Some("mail-unread")
+        );
+    }
+
+    #[test]
+    fn test_resolve_actions() {
+        let tracked = Tracked {
+            app_id: "org.example.App".to_string(),
+            id: "1".to_string(),
+            default_action: Some("app.open".to_string()),
+            default_target: None,
+            targets: HashMap::from([("app.reply".to_string(), owned(Value::from(7u32)))]),
+        };
+
+        assert_eq!(tracked.resolve("default"), ("app.open", None));
+        let (action, target) = tracked.resolve("app.reply");
+        assert_eq!(action, "app.reply");
+        assert_eq!(target, Some(&owned(Value::from(7u32))));
+    }
+
+    #[test]
+    fn test_tracking_is_bounded() {
+        let mut portal = Tracking::default();
+        for id in 0..=PORTAL_MAX_TRACKED as u32 {
+            portal.track(
+                id,
+                Tracked {
+                    app_id: "org.example.App".to_string(),
+                    id: id.to_string(),
+                    default_action: None,
+                    default_target: None,
+                    targets: HashMap::new(),
+                },
+            );
+        }
+        assert_eq!(portal.tracked.len(), PORTAL_MAX_TRACKED);
+        assert_eq!(portal.ids.len(), PORTAL_MAX_TRACKED);
+        assert!(!portal.tracked.contains_key(&0));
+    }
+}
@@ -0,0 +1,318 @@
+//! Screen sharing tracking, for `screen_share_dnd`
+//!
+//! Screencasts go through the ScreenCast and RemoteDesktop portals, so a
+//! separate session bus connection monitors the portal backend: a `Start`
+//! call the backend answers with success begins a share, and the session
+//! closing ends it. A `Start` the user cancels never counts. With the
+//! `pipewire` feature, the video streams the compositor's portal backend
+//! publishes in PipeWire count as well, for casts set up outside the
+//! portal. The subscription yields whether anything is being shared.
+
+use cosmic::{
+    iced::{
+        futures::{self, SinkExt, StreamExt},
+        stream,
+    },
+    iced_futures::Subscription,
+};
+use futures::channel::mpsc;
+use std::{
+    collections::{HashMap, HashSet},
+    num::NonZeroU32,
+};
+use zbus::{
+    Connection, MatchRule, MessageStream,
+    message::Type,
+    zvariant::{OwnedObjectPath, OwnedValue},
+};
+
+const SCREEN_CAST: &str = "org.freedesktop.impl.portal.ScreenCast";
+const REMOTE_DESKTOP: &str = "org.freedesktop.impl.portal.RemoteDesktop";
+const IMPL_SESSION: &str = "org.freedesktop.impl.portal.Session";
+const SESSION: &str = "org.freedesktop.portal.Session";
+
+/// A source saying whether it sees the screen shared
+#[derive(Debug, Clone, Copy)]
+enum Change {
+    Portal(bool),
+    #[cfg(feature = "pipewire")]
+    PipeWire(bool),
+}
+
+/// Portal response code of a request that succeeded; 1 is cancelled by
+/// the user and 2 ended some other way
+const RESPONSE_SUCCESS: u32 = 0;
+
+/// Portal sessions that started sharing and haven't closed yet
+#[derive(Debug, Default)]
+struct PortalSessions {
+    active: HashSet<String>,
+    /// Sessions of `Start` calls waiting for their answer, by caller and
+    /// call serial
+    pending: HashMap<(String, NonZeroU32), String>,
+}
+
+impl PortalSessions {
+    /// A `Start` call for `session` was sent by `caller` as `serial`
+    fn requested(&mut self, caller: &str, serial: NonZeroU32, session: &str) {
+        self.pending
+            .insert((caller.to_string(), serial), session.to_string());
+    }
+
+    /// The backend answered call `serial` of `caller` with `response`, or
+    /// with an error for `None`; returns true if this is the first share
+    fn answered(&mut self, caller: &str, serial: NonZeroU32, response: Option<u32>) -> bool {
+        let Some(session) = self.pending.remove(&(caller.to_string(), serial)) else {
+            return false;
+        };
+        response == Some(RESPONSE_SUCCESS) && self.started(&session)
+    }
+
+    /// Returns true if this is the first share
+    fn started(&mut self, session: &str) -> bool {
+        self.active.insert(session.to_string()) && self.active.len() == 1
+    }
+
+    /// Returns true if this was the last share
+    fn closed(&mut self, session: &str) -> bool {
+        self.pending.retain(|_, pending| pending != session);
+        self.active.remove(session) && self.active.is_empty()
+    }
+}
+
+pub fn sharing() -> Subscription<bool> {
+    struct ScreenShareWorker;
+
+    Subscription::run_with_id(
+        std::any::TypeId::of::<ScreenShareWorker>(),
+        stream::channel(4, |mut output| async move {
+            let (tx, mut changes) = mpsc::unbounded();
+            #[cfg(feature = "pipewire")]
+            pipewire::watch(tx.clone());
+            let portal = async move {
+                if let Err(err) = watch_portal(&tx).await {
+                    tracing::warn!("Screen sharing tracking unavailable: {}", err);
+                }
+                futures::future::pending::<()>().await;
+            };
+            let forward = async move {
+                // What the portal and PipeWire each last said
+                let mut sources = [false; 2];
+                while let Some(change) = changes.next().await {
+                    let before = sources.contains(&true);
+                    match change {
+                        Change::Portal(sharing) => sources[0] = sharing,
+                        #[cfg(feature = "pipewire")]
+                        Change::PipeWire(sharing) => sources[1] = sharing,
+                    }
+                    let sharing = sources.contains(&true);
+                    if sharing != before {
+                        _ = output.send(sharing).await;
+                    }
+                }
+            };
+            futures::join!(portal, forward);
+        }),
+    )
+}
+
+async fn watch_portal(tx: &mpsc::UnboundedSender<Change>) -> zbus::Result<()> {
+    // A monitoring connection can't be used for anything else
+    let conn = Connection::session().await?;
+    let rules = [
+        MatchRule::builder()
+            .msg_type(Type::MethodCall)
+            .interface(SCREEN_CAST)?
+            .member("Start")?
+            .build(),
+        MatchRule::builder()
+            .msg_type(Type::MethodCall)
+            .interface(REMOTE_DESKTOP)?
+            .member("Start")?
+            .build(),
+        MatchRule::builder()
+            .msg_type(Type::MethodCall)
+            .interface(IMPL_SESSION)?
+            .member("Close")?
+            .build(),
+        MatchRule::builder()
+            .msg_type(Type::Signal)
+            .interface(IMPL_SESSION)?
+            .member("Closed")?
+            .build(),
+        MatchRule::builder()
+            .msg_type(Type::Signal)
+            .interface(SESSION)?
+            .member("Closed")?
+            .build(),
+        // Answers carry no interface, so `Start` is matched by serial
+        MatchRule::builder().msg_type(Type::MethodReturn).build(),
+        MatchRule::builder().msg_type(Type::Error).build(),
+    ];
+    zbus::fdo::MonitoringProxy::new(&conn)
+        .await?
+        .become_monitor(&rules, 0)
+        .await?;
+
+    let mut sessions = PortalSessions::default();
+    let mut messages = MessageStream::from(&conn);
+    while let Some(message) = messages.next().await {
+        let Ok(message) = message else {
+            continue;
+        };
+        let header = message.header();
+        let change = match (header.message_type(), header.member().map(|m| m.as_str())) {
+            (Type::MethodCall, Some("Start")) => {
+                // (handle, session_handle, app_id, parent_window, options)
+                let Ok((_, session, ..)) = message.body().deserialize::<(
+                    OwnedObjectPath,
+                    OwnedObjectPath,
+                    String,
+                    String,
+                    HashMap<String, OwnedValue>,
+                )>() else {
+                    continue;
+                };
+                if let Some(caller) = header.sender() {
+                    sessions.requested(
+                        caller.as_str(),
+                        header.primary().serial_num(),
+                        session.as_str(),
+                    );
+                }
+                None
+            }
+            (Type::MethodReturn | Type::Error, _) => {
+                let (Some(caller), Some(serial)) = (header.destination(), header.reply_serial())
+                else {
+                    continue;
+                };
+                // (response, results)
+                let response = match header.message_type() {
+                    Type::MethodReturn => message
+                        .body()
+                        .deserialize::<(u32, HashMap<String, OwnedValue>)>()
+                        .ok()
+                        .map(|(response, _)| response),
+                    _ => None,
+                };
+                sessions
+                    .answered(caller.as_str(), serial, response)
+                    .then_some(true)
+            }
+            (_, Some("Close" | "Closed")) => header
+                .path()
+                .filter(|path| sessions.closed(path.as_str()))
+                .map(|_| false),
+            _ => None,
+        };
+        if let Some(sharing) = change {
+            tracing::info!(
+                "Screen sharing {}",
+                if sharing { "started" } else { "ended" }
+            );
+            _ = tx.unbounded_send(Change::Portal(sharing));
+        }
+    }
+    Ok(())
+}
+
+#[cfg(feature = "pipewire")]
+mod pipewire {
+    use super::Change;
+    use ::pipewire as pw;
+    use futures::channel::mpsc::UnboundedSender;
+    use std::{cell::RefCell, collections::HashSet, rc::Rc};
+
+    /// Whether a PipeWire node is a screencast: a video source that isn't
+    /// a camera or other device
+    fn is_cast(props: &pw::spa::utils::dict::DictRef) -> bool {
+        props.get(*pw::keys::MEDIA_CLASS) == Some("Video/Source")
+            && props.get(*pw::keys::DEVICE_API).is_none()
+    }
+
+    pub(super) fn watch(tx: UnboundedSender<Change>) {
+        let spawned = std::thread::Builder::new()
+            .name("screen-share-pipewire".into())
+            .spawn(move || {
+                if let Err(err) = run(tx) {
+                    tracing::debug!("PipeWire screencast tracking unavailable: {}", err);
+                }
+            });
+        if let Err(err) = spawned {
+            tracing::warn!("Failed to start PipeWire screencast tracking: {}", err);
+        }
+    }
+
+    fn run(tx: UnboundedSender<Change>) -> Result<(), pw::Error> {
+        pw::init();
+        let mainloop = pw::main_loop::MainLoop::new(None)?;
+        let context = pw::context::Context::new(&mainloop)?;
+        let core = context.connect(None)?;
+        let registry = core.get_registry()?;
+
+        let casts = Rc::new(RefCell::new(HashSet::new()));
+        let (added, added_tx) = (casts.clone(), tx.clone());
+        let _listener = registry
+            .add_listener_local()
+            .global(move |global| {
+                if global.type_ != pw::types::ObjectType::Node
+                    || !global.props.as_ref().is_some_and(|props| is_cast(props))
+                {
+                    return;
+                }
+                let mut casts = added.borrow_mut();
+                if casts.insert(global.id) && casts.len() == 1 {
+                    _ = added_tx.unbounded_send(Change::PipeWire(true));
+                }
+            })
+            .global_remove(move |id| {
+                let mut casts = casts.borrow_mut();
+                if casts.remove(&id) && casts.is_empty() {
+                    _ = tx.unbounded_send(Change::PipeWire(false));
+                }
+            })
+            .register();
+        mainloop.run();
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_sharing_lasts_until_last_session_closes() {
+        let mut sessions = PortalSessions::default();
+        assert!(sessions.started("/org/freedesktop/portal/desktop/session/1_42/a"));
+        assert!(!sessions.started("/org/freedesktop/portal/desktop/session/1_43/b"));
+        assert!(!sessions.closed("/org/freedesktop/portal/desktop/session/1_42/a"));
+        // Sessions that never shared the screen close too
+        assert!(!sessions.closed("/org/freedesktop/portal/desktop/session/1_44/c"));
+        assert!(sessions.closed("/org/freedesktop/portal/desktop/session/1_43/b"));
+    }
+
+    #[test]
+    fn test_only_successful_start_shares() {
+        let session = "/org/freedesktop/portal/desktop/session/1_42/a";
+        let serial = |n| NonZeroU32::new(n).unwrap();
+        let mut sessions = PortalSessions::default();
+
+        // The user cancelled the share dialog
+        sessions.requested(":1.42", serial(7), session);
+        assert!(!sessions.answered(":1.42", serial(7), Some(1)));
+        assert!(sessions.active.is_empty());
+        // The backend failed
+        sessions.requested(":1.42", serial(8), session);
+        assert!(!sessions.answered(":1.42", serial(8), None));
+        // Another caller's answer with the same serial
+        sessions.requested(":1.42", serial(9), session);
+        assert!(!sessions.answered(":1.43", serial(9), Some(0)));
+        assert!(sessions.active.is_empty());
+
+        assert!(sessions.answered(":1.42", serial(9), Some(0)));
+        assert!(sessions.closed(session));
+        assert!(sessions.pending.is_empty());
+    }
+}
@@ -0,0 +1,77 @@
+//! Screen lock tracking through logind
+//!
+//! Follows the `LockedHint` of the current login session, which COSMIC's
+//! lock screen sets while the session is locked, and its `State`, which
+//! turns to "closing" on logout.
+
+use cosmic::{
+    iced::{
+        futures::{self, SinkExt, StreamExt},
+        stream,
+    },
+    iced_futures::Subscription,
+};
+use futures::channel::mpsc;
+use zbus::Connection;
+
+#[zbus::proxy(
+    interface = "org.freedesktop.login1.Session",
+    default_service = "org.freedesktop.login1",
+    default_path = "/org/freedesktop/login1/session/auto"
+)]
+trait Session {
+    #[zbus(property)]
+    fn locked_hint(&self) -> zbus::Result<bool>;
+
+    #[zbus(property)]
+    fn state(&self) -> zbus::Result<String>;
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Event {
+    Locked,
+    Unlocked,
+    /// The user is logging out
+    Ending,
+}
+
+pub fn lock_state() -> Subscription<Event> {
+    struct LockWorker;
+
+    Subscription::run_with_id(
+        std::any::TypeId::of::<LockWorker>(),
+        stream::channel(4, |mut output| async move {
+            if let Err(err) = watch(&mut output).await {
+                tracing::warn!("Screen lock tracking unavailable: {}", err);
+            }
+            futures::future::pending::<()>().await;
+        }),
+    )
+}
+
+async fn watch(output: &mut mpsc::Sender<Event>) -> zbus::Result<()> {
+    let conn = Connection::system().await?;
+    let session = SessionProxy::new(&conn).await?;
+    let mut locks = session.receive_locked_hint_changed().await;
+    let mut states = session.receive_state_changed().await;
+    loop {
+        let event = tokio::select! {
+            Some(change) = locks.next() => {
+                if change.get().await? {
+                    Event::Locked
+                } else {
+                    Event::Unlocked
+                }
+            }
+            Some(change) = states.next() => {
+                if change.get().await? != "closing" {
+                    continue;
+                }
+                Event::Ending
+            }
+            else => break,
+        };
+        _ = output.send(event).await;
+    }
+    Ok(())
+}
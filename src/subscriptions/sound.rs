@@ -0,0 +1,37 @@
+//! Sound playback outcomes from the audio engine
+//!
+//! The engine reports on a blocking channel; a helper thread forwards the
+//! events into the iced runtime.
+
+use cosmic::{
+    iced::{futures, stream},
+    iced_futures::Subscription,
+};
+use cosmic_ext_notifications_util::{playback_events, PlaybackEvent};
+
+pub fn playback() -> Subscription<PlaybackEvent> {
+    struct PlaybackWorker;
+
+    Subscription::run_with_id(
+        std::any::TypeId::of::<PlaybackWorker>(),
+        stream::channel(16, |mut output| async move {
+            let events = playback_events();
+            let forward = std::thread::Builder::new()
+                .name("sound-events".into())
+                .spawn(move || {
+                    for event in events {
+                        // Drop events the UI can't keep up with, counters stay exact
+                        if let Err(err) = output.try_send(event) {
+                            if err.is_disconnected() {
+                                break;
+                            }
+                        }
+                    }
+                });
+            if let Err(err) = forward {
+                tracing::warn!("Sound playback events unavailable: {}", err);
+            }
+            futures::future::pending::<()>().await;
+        }),
+    )
+}
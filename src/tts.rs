@@ -0,0 +1,78 @@
+//! Read notification summaries aloud through speech-dispatcher
+//!
+//! Uses the `spd-say` client that ships with speech-dispatcher, so the
+//! user's configured voice, rate and output module apply. Texts are handed
+//! over in order from one worker thread; when the queue is full new ones
+//! are dropped rather than buffered without bound.
+
+use crate::constants::TTS_QUEUE_SIZE;
+use std::{
+    process::{Command, Stdio},
+    sync::{
+        OnceLock,
+        mpsc::{Receiver, SyncSender, TrySendError, sync_channel},
+    },
+};
+
+const SPD_SAY: &str = "spd-say";
+
+/// Queue to the worker running `spd-say`, started on first use; `None` if
+/// it couldn't be
+static QUEUE: OnceLock<Option<SyncSender<String>>> = OnceLock::new();
+
+/// Hand one text to speech-dispatcher, waiting until the client is done
+fn say(text: &str) {
+    let status = Command::new(SPD_SAY)
+        .args(["--application-name", "cosmic-ext-notifications"])
+        .args(["--priority", "notification"])
+        // Text starting with '-' must not be parsed as an option
+        .arg("--")
+        .arg(text)
+        .stdin(Stdio::null())
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .status();
+    if let Err(err) = status {
+        tracing::warn!("Failed to run {} for text-to-speech: {}", SPD_SAY, err);
+    }
+}
+
+fn run(queue: Receiver<String>) {
+    for text in queue {
+        say(&text);
+    }
+}
+
+fn start() -> Option<SyncSender<String>> {
+    let (queue, pending) = sync_channel(TTS_QUEUE_SIZE);
+    let worker = std::thread::Builder::new()
+        .name("text-to-speech".to_string())
+        .spawn(move || run(pending));
+    match worker {
+        Ok(_) => Some(queue),
+        Err(err) => {
+            tracing::warn!("Failed to start the text-to-speech worker: {}", err);
+            None
+        }
+    }
+}
+
+/// Queue `text` with speech-dispatcher at notification priority
+///
+/// Returns immediately; failures are only logged.
+pub fn speak(text: &str) {
+    let text = text.trim();
+    if text.is_empty() {
+        return;
+    }
+    let Some(queue) = QUEUE.get_or_init(start) else {
+        return;
+    };
+    match queue.try_send(text.to_string()) {
+        Ok(()) => {}
+        Err(TrySendError::Full(_)) => tracing::warn!("Text-to-speech queue full, dropped a text"),
+        Err(TrySendError::Disconnected(_)) => {
+            tracing::warn!("Text-to-speech worker stopped, dropped a text")
+        }
+    }
+}
@@ -0,0 +1,82 @@
+use cosmic::iced_accessibility::{
+    A11yTree,
+    accesskit::{Live, NodeBuilder, Rect, Role},
+};
+use cosmic::iced_core::{
+    Layout, Length, Rectangle, Size, Widget, layout, mouse, renderer,
+    widget::{Id, Tree},
+};
+use cosmic::{Element, Renderer, Theme};
+
+use crate::state::Politeness;
+
+/// Text that assistive technology reads when it changes, without showing
+/// it on screen
+///
+/// The text only exists in the accessibility tree, as a status node marked
+/// live, or an alert for assertive updates. Screen readers speak the node
+/// when its text changes, so a card can tell them what changed without
+/// having them re-read it. `key` keeps the node the same from one frame to
+/// the next; a node that is new every frame would never count as changed.
+pub struct LiveRegion {
+    id: Id,
+    announcement: String,
+    politeness: Politeness,
+}
+
+impl<Message> Widget<Message, Theme, Renderer> for LiveRegion {
+    fn size(&self) -> Size<Length> {
+        Size::new(Length::Shrink, Length::Shrink)
+    }
+
+    fn layout(&self, _tree: &mut Tree, _renderer: &Renderer, _limits: &layout::Limits) -> layout::Node {
+        layout::Node::new(Size::ZERO)
+    }
+
+    fn draw(
+        &self,
+        _tree: &Tree,
+        _renderer: &mut Renderer,
+        _theme: &Theme,
+        _style: &renderer::Style,
+        _layout: Layout<'_>,
+        _cursor: mouse::Cursor,
+        _viewport: &Rectangle,
+    ) {
+    }
+
+    fn a11y_nodes(&self, layout: Layout<'_>, _state: &Tree, _cursor: mouse::Cursor) -> A11yTree {
+        let (role, live) = match self.politeness {
+            Politeness::Polite => (Role::Status, Live::Polite),
+            Politeness::Assertive => (Role::Alert, Live::Assertive),
+        };
+        let bounds = layout.bounds();
+        let mut node = NodeBuilder::new(role);
+        node.set_name(self.announcement.clone());
+        node.set_live(live);
+        node.set_bounds(Rect::new(
+            f64::from(bounds.x),
+            f64::from(bounds.y),
+            f64::from(bounds.x + bounds.width),
+            f64::from(bounds.y + bounds.height),
+        ));
+        A11yTree::leaf(node, self.id.clone())
+    }
+
+    fn id(&self) -> Option<Id> {
+        Some(self.id.clone())
+    }
+}
+
+/// A live region announcing `announcement`, kept under `key`
+pub fn live_region<'a, Message: 'static>(
+    key: String,
+    announcement: String,
+    politeness: Politeness,
+) -> Element<'a, Message> {
+    Element::new(LiveRegion {
+        id: Id::new(key),
+        announcement,
+        politeness,
+    })
+}
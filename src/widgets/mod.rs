@@ -1,11 +1,13 @@
 pub mod action_buttons;
 pub mod image_animator;
 pub mod linkified_text;
+pub mod live_region;
 pub mod notification_image;
 pub mod progress_bar;
 pub mod rich_card;
 
 // Re-export items used by app.rs and rendering/cards.rs
-pub use notification_image::{notification_image, ImageSize};
-pub use progress_bar::{notification_progress, should_show_progress};
+pub use live_region::live_region;
+pub use notification_image::{notification_frame, notification_image, placeholder_image, ImageSize};
+pub use progress_bar::{notification_progress, should_show_progress, AnimatedProgress};
 pub use rich_card::RichCardConfig;
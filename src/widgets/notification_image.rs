@@ -49,6 +49,22 @@ pub fn notification_image<'a, Message: 'a>(
     .into()
 }
 
+/// Create an image element from an already built icon handle, such as a
+/// frame of an animation
+pub fn notification_frame<'a, Message: 'a>(
+  handle: &icon::Handle,
+  size: ImageSize,
+) -> Element<'a, Message> {
+  let (width, height) = size.dimensions();
+
+  container(handle.clone().icon().size(width))
+    .width(Length::Fixed(width as f32))
+    .height(Length::Fixed(height as f32))
+    .center_x(Length::Fixed(width as f32))
+    .center_y(Length::Fixed(height as f32))
+    .into()
+}
+
 /// Create a placeholder when image is not available
 pub fn placeholder_image<'a, Message: 'a>(width: u16, height: u16) -> Element<'a, Message> {
   container(cosmic::widget::Space::new(width, height))
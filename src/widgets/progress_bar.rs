@@ -28,7 +28,7 @@ pub fn notification_progress<'a, Message: 'static>(
         .height(Length::Fixed(4.0));
 
     if show_percentage {
-        let percentage = format!("{}%", (clamped_value * 100.0).round() as u32);
+        let percentage = crate::rendering::percent_label((clamped_value * 100.0).round() as u32);
 
         row![
             bar,
@@ -150,10 +150,10 @@ impl AnimatedProgress {
     value.clamp(0.0, 1.0)
   }
 
-  /// Check if animation is complete
+  /// Check if animation is still running
   pub fn is_animating(&self) -> bool {
     let elapsed = self.start_time.elapsed().as_millis() as u64;
-    elapsed < self.duration_ms && (self.current - self.target).abs() > f32::EPSILON
+    elapsed < self.duration_ms && (self.start_value - self.target).abs() > f32::EPSILON
   }
 
   /// Instantly set value without animation
@@ -4,7 +4,7 @@ use cosmic::iced::{Alignment, Length};
 use cosmic::iced_widget::{column, row};
 use cosmic::widget::{button, container, icon, text};
 use cosmic::Element;
-use cosmic_ext_notifications_config;
+use cosmic_ext_notifications_config::{self, ImageFit};
 
 /// Configuration for the rich notification card
 #[derive(Debug, Clone)]
@@ -23,6 +23,16 @@ pub struct RichCardConfig {
     pub enable_links: bool,
     /// Whether animated images and card animations are enabled
     pub enable_animations: bool,
+    /// Whether to offer quick actions for phone numbers and emails in the body
+    pub detect_contacts: bool,
+    /// Corner radius in pixels for notification images
+    pub image_corner_radius: u32,
+    /// How non-square notification images are fitted
+    pub image_fit: ImageFit,
+    /// Whether square chat/email images are masked to a circle
+    pub circle_avatars: bool,
+    /// Tighter paddings and the body on a single line
+    pub compact: bool,
 }
 
 impl Default for RichCardConfig {
@@ -35,6 +45,11 @@ impl Default for RichCardConfig {
             max_image_size: 128,
             enable_links: true,
             enable_animations: true,
+            detect_contacts: false,
+            image_corner_radius: 0,
+            image_fit: ImageFit::default(),
+            circle_avatars: true,
+            compact: false,
         }
     }
 }
@@ -42,15 +57,22 @@ impl Default for RichCardConfig {
 impl RichCardConfig {
     /// Create a RichCardConfig from NotificationsConfig
     pub fn from_notifications_config(config: &cosmic_ext_notifications_config::NotificationsConfig) -> Self {
+        let compact = config.density == cosmic_ext_notifications_config::Density::Compact;
         Self {
             width: 380.0,
             show_progress: false,
             show_actions: config.show_actions,
-            show_images: config.show_images,
+            // Compact cards leave images out to save room
+            show_images: config.show_images && !compact,
             // Clamp max_image_size to valid range (32-256)
             max_image_size: config.max_image_size.clamp(32, 256),
             enable_links: config.enable_links,
             enable_animations: config.enable_animations,
+            detect_contacts: config.detect_contacts,
+            image_corner_radius: config.image_corner_radius,
+            image_fit: config.image_fit,
+            circle_avatars: config.circle_avatars,
+            compact,
         }
     }
 }
@@ -185,9 +207,9 @@ fn create_body<'a, Message: 'static>(summary: &'a str, body: &'a str) -> Element
 /// Creates a placeholder for the progress bar section
 fn create_progress_placeholder<'a, Message: 'static>(progress: Option<u8>) -> Element<'a, Message> {
     let progress_text = if let Some(pct) = progress {
-        format!("Progress: {}%", pct.min(100))
+        crate::fl!("progress-label", percent = pct.min(100))
     } else {
-        "Progress: --".to_string()
+        crate::fl!("progress-unknown")
     };
 
     // Simple text placeholder for now - actual progress bar will be implemented in another task